@@ -0,0 +1,49 @@
+/*
+ * C API for embedding hsab. Build the library with
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * Values cross the boundary as JSON, and every string hsab returns must be
+ * freed with hsab_string_free. A handle must only be used by one thread at
+ * a time. See src/ffi.rs for details.
+ */
+
+#ifndef HSAB_H
+#define HSAB_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An evaluator: its stack, definitions, and working directory */
+typedef struct hsab hsab;
+
+/* A builtin implemented by the host. Pop arguments and push results with
+ * hsab_pop and hsab_push; returning nonzero fails the eval. */
+typedef int (*hsab_builtin_fn)(hsab *h, void *userdata);
+
+/* Create an evaluator; free it with hsab_free */
+hsab *hsab_new(void);
+void hsab_free(hsab *h);
+
+/* Evaluate source, returning {"stack": [...], "output": "...",
+ * "exit_code": n} or {"error": "..."} as JSON */
+char *hsab_eval(hsab *h, const char *source);
+
+/* Make name a word that calls f with userdata. Returns 0, or -1 on bad
+ * arguments. */
+int hsab_register_builtin(hsab *h, const char *name, hsab_builtin_fn f, void *userdata);
+
+/* Pop the top of the stack as JSON, or NULL if it is empty */
+char *hsab_pop(hsab *h);
+
+/* Push a value written as JSON. Returns 0, or -1 if it isn't valid JSON. */
+int hsab_push(hsab *h, const char *json);
+
+void hsab_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HSAB_H */
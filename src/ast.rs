@@ -8,7 +8,8 @@
 
 use crate::util::lock_or_recover;
 use indexmap::IndexMap;
-use num_bigint::BigUint;
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
 /// Convert a Value to a JSON value for serialization
@@ -81,6 +82,12 @@ pub fn value_to_json(v: &Value) -> JsonValue {
             obj.insert("hex".into(), JsonValue::String(hex::encode(data)));
             JsonValue::Object(obj)
         }
+        Value::Status(code) => {
+            let mut obj = serde_json::Map::new();
+            obj.insert("type".into(), JsonValue::String("status".into()));
+            obj.insert("code".into(), JsonValue::Number((*code).into()));
+            JsonValue::Object(obj)
+        }
         Value::Link { url, text } => {
             let mut obj = serde_json::Map::new();
             obj.insert("type".into(), JsonValue::String("link".into()));
@@ -160,13 +167,18 @@ pub fn json_to_value(json: JsonValue) -> Value {
 }
 
 /// A value that can be on the stack
-#[derive(Debug, Clone)]
+///
+/// Serializes faithfully via serde (see [`crate::value_serde`]): each value
+/// is tagged with its lowercase variant name, e.g. `{"int": 3}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Value {
     /// A literal string (argument, path, flag, etc.)
     Literal(String),
     /// Output from a command execution
     Output(String),
     /// A deferred block of expressions
+    #[serde(with = "crate::value_serde::block_source")]
     Block(Vec<Expr>),
     /// Nil - represents empty/no output
     Nil,
@@ -175,8 +187,10 @@ pub enum Value {
     /// A list of values (for structured data)
     List(Vec<Value>),
     /// A map/object of key-value pairs (for structured data)
+    #[serde(rename = "record")]
     Map(IndexMap<String, Value>),
     /// A floating-point numeric value
+    #[serde(with = "crate::value_serde::float")]
     Number(f64),
     /// A first-class integer value (exact within i64 range)
     Int(i64),
@@ -195,11 +209,14 @@ pub enum Value {
         source: Option<String>,
         command: Option<String>,
     },
+    /// Exit status of a block run by `status` (0 is success)
+    Status(i32),
     /// Media content (images, graphics) for terminal display
     Media {
         /// MIME type (e.g., "image/png", "image/jpeg", "image/gif")
         mime_type: String,
         /// Raw binary data
+        #[serde(with = "crate::value_serde::base64_bytes")]
         data: Vec<u8>,
         /// Optional width in pixels
         width: Option<u32>,
@@ -218,14 +235,18 @@ pub enum Value {
         text: Option<String>,
     },
     /// Raw bytes (for hashes, binary data, encoding operations)
+    #[serde(with = "crate::value_serde::base64_bytes")]
     Bytes(Vec<u8>),
-    /// Arbitrary precision unsigned integer (for cryptographic operations)
-    BigInt(BigUint),
+    /// Arbitrary precision integer (for cryptographic operations and
+    /// arithmetic past the i64 range)
+    #[serde(with = "crate::value_serde::bigint_decimal")]
+    BigInt(BigInt),
     /// A Future representing a background computation
     Future {
         /// Unique identifier for this future
         id: String,
         /// Shared state containing result when complete
+        #[serde(with = "crate::value_serde::future_state")]
         state: std::sync::Arc<std::sync::Mutex<FutureState>>,
     },
 }
@@ -305,6 +326,7 @@ impl PartialEq for Value {
             (Value::Link { url: u1, text: t1 }, Value::Link { url: u2, text: t2 }) => {
                 u1 == u2 && t1 == t2
             }
+            (Value::Status(a), Value::Status(b)) => a == b,
             (Value::Bytes(a), Value::Bytes(b)) => a == b,
             (Value::BigInt(a), Value::BigInt(b)) => a == b,
             (Value::Future { id: id1, .. }, Value::Future { id: id2, .. }) => {
@@ -357,6 +379,7 @@ impl Value {
             Value::Nil => "nil",
             Value::Marker => "marker",
             Value::Error { .. } => "error",
+            Value::Status(_) => "status",
             Value::Media { .. } => "media",
             Value::Link { .. } => "link",
             Value::Bytes(_) => "bytes",
@@ -476,6 +499,7 @@ impl Value {
                 ))
             }
             Value::Link { url, .. } => Some(url.clone()),
+            Value::Status(code) => Some(code.to_string()),
             Value::Bytes(data) => {
                 // For shell compatibility, return hex representation
                 Some(hex::encode(data))
@@ -527,6 +551,9 @@ pub enum Expr {
     /// An array literal [...] - immediate evaluation to List
     ArrayLiteral(Vec<Expr>),
 
+    /// A record literal { key value ... } - immediate evaluation to Record
+    RecordLiteral(Vec<Expr>),
+
     /// Execute/apply: pops a block from stack and executes it
     Apply,
 
@@ -543,6 +570,7 @@ pub enum Expr {
     RedirectOut, // >
     RedirectAppend,    // >>
     RedirectIn,        // <
+    HereString,        // <<<
     RedirectErr,       // 2>
     RedirectErrAppend, // 2>>
     RedirectBoth,      // &>
@@ -627,6 +655,108 @@ pub enum Expr {
     LimboRef(String),
 }
 
+impl Expr {
+    /// Source text that lexes and parses back to this expression
+    pub fn to_source(&self) -> String {
+        let word = match self {
+            Expr::Literal(s) | Expr::Variable(s) => return s.clone(),
+            Expr::Quoted {
+                content,
+                double: false,
+            } if !content.contains('\'') => return format!("'{}'", content),
+            Expr::Quoted { content, .. } => {
+                let mut out = String::from("\"");
+                for c in content.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        '\t' => out.push_str("\\t"),
+                        '\r' => out.push_str("\\r"),
+                        '\x1b' => out.push_str("\\e"),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+                return out;
+            }
+            Expr::Block(inner) => return format!("#[{}]", exprs_to_source(inner)),
+            Expr::ArrayLiteral(inner) => return format!("[{}]", exprs_to_source(inner)),
+            Expr::RecordLiteral(inner) => return format!("{{ {} }}", exprs_to_source(inner)),
+            Expr::Define(name) => return format!(":{}", name),
+            Expr::ScopedBlock { assignments, body } => {
+                let assigns: Vec<String> = assignments
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                return format!("{}; {}", assigns.join(" "), exprs_to_source(body));
+            }
+            Expr::LimboRef(id) => return format!("`&{}`", id),
+            Expr::Apply => "apply",
+            Expr::Peek => "peek",
+            Expr::PeekAll => "peek-all",
+            Expr::Pipe => "|",
+            Expr::RedirectOut => ">",
+            Expr::RedirectAppend => ">>",
+            Expr::RedirectIn => "<",
+            Expr::HereString => "<<<",
+            Expr::RedirectErr => "2>",
+            Expr::RedirectErrAppend => "2>>",
+            Expr::RedirectBoth => "&>",
+            Expr::RedirectErrToOut => "2>&1",
+            Expr::Background => "&",
+            Expr::And => "&&",
+            Expr::Or => "||",
+            Expr::Dup => "dup",
+            Expr::Swap => "swap",
+            Expr::Drop => "drop",
+            Expr::Over => "over",
+            Expr::Rot => "rot",
+            Expr::Depth => "depth",
+            Expr::Join => "path-join",
+            Expr::Suffix => "suffix",
+            Expr::Dirname => "dirname",
+            Expr::Basename => "basename",
+            Expr::Realpath => "path-resolve",
+            Expr::Split1 => "split1",
+            Expr::Rsplit1 => "rsplit1",
+            Expr::Marker => "marker",
+            Expr::Spread => "spread",
+            Expr::Each => "each",
+            Expr::Collect => "collect",
+            Expr::Keep => "keep",
+            Expr::Map => "map",
+            Expr::Filter => "filter",
+            Expr::If => "if",
+            Expr::ElseIf => "elseif",
+            Expr::Else => "else",
+            Expr::Times => "times",
+            Expr::While => "while",
+            Expr::Until => "until",
+            Expr::Break => "break",
+            Expr::Parallel => "parallel",
+            Expr::Fork => "fork",
+            Expr::Subst => "subst",
+            Expr::Fifo => "fifo",
+            Expr::Json => "json",
+            Expr::Unjson => "unjson",
+            Expr::Timeout => "timeout",
+            Expr::Pipestatus => "pipestatus",
+            Expr::Import => ".import",
+        };
+        word.to_string()
+    }
+}
+
+/// Source text for a sequence of expressions (the inverse of lex + parse)
+pub fn exprs_to_source(exprs: &[Expr]) -> String {
+    exprs
+        .iter()
+        .map(Expr::to_source)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// A parsed hsab program is a sequence of expressions
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
@@ -1,5 +1,8 @@
-use crate::rcfile::{dirs_home, load_hsab_profile, load_hsabrc, load_stdlib, STDLIB_CONTENT};
-use crate::terminal::execute_line;
+use crate::rcfile::{
+    dirs_home, load_hsab_profile, load_hsabrc, load_stdlib, load_workspace, split_stdlib_header,
+    stamped_stdlib, STDLIB_CONTENT,
+};
+use crate::terminal::{execute_line, execute_line_after, run_exit_trap, set_output_format};
 use hsab::Evaluator;
 use std::fs;
 use std::process::ExitCode;
@@ -11,10 +14,32 @@ pub(crate) struct CliArgs {
     pub(crate) login: bool,
     pub(crate) command: Option<String>,
     pub(crate) script: Option<String>,
+    /// Arguments after the script path, for the script itself
+    pub(crate) script_args: Vec<String>,
     pub(crate) help: bool,
     pub(crate) version: bool,
     pub(crate) init: bool,
+    /// `hsab init --upgrade`: merge a newer stdlib into the installed one
+    pub(crate) upgrade: bool,
     pub(crate) trace: bool,
+    /// Raw `--color=WHEN` value, validated in main
+    pub(crate) color: Option<String>,
+    /// Serve the Debug Adapter Protocol on stdin/stdout
+    pub(crate) dap: bool,
+    /// Record each evaluation step of a script or -c command to this file
+    pub(crate) record: Option<String>,
+    /// Checkpoint to resume the script from
+    pub(crate) resume: Option<String>,
+    /// `hsab run [NAME]`: run a script from hsab.toml
+    pub(crate) run: Option<Vec<String>>,
+    /// `hsab config [get|set|unset ...]`: read or change ~/.hsab/config.toml
+    pub(crate) config: Option<Vec<String>>,
+    /// Describe the -c command or script instead of running it
+    pub(crate) explain: bool,
+    /// Restricted shell (`--restricted`, or run as `rhsab`)
+    pub(crate) restricted: bool,
+    /// Start the REPL in calculator mode
+    pub(crate) rpn: bool,
 }
 
 /// Parse command-line arguments
@@ -23,10 +48,26 @@ pub(crate) fn parse_args(args: &[String]) -> CliArgs {
         login: false,
         command: None,
         script: None,
+        script_args: Vec::new(),
         help: false,
         version: false,
         init: false,
+        upgrade: false,
         trace: false,
+        color: None,
+        dap: false,
+        record: None,
+        resume: None,
+        run: None,
+        config: None,
+        explain: false,
+        // Like rbash, the name alone restricts (`-rhsab` for a login shell)
+        restricted: args.first().is_some_and(|argv0| {
+            std::path::Path::new(argv0)
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().trim_start_matches('-') == "rhsab")
+        }),
+        rpn: false,
     };
 
     let mut i = 1; // Skip program name
@@ -35,12 +76,51 @@ pub(crate) fn parse_args(args: &[String]) -> CliArgs {
             "init" => {
                 cli.init = true;
             }
+            "--upgrade" => {
+                cli.upgrade = true;
+            }
+            "run" if cli.script.is_none() => {
+                // Everything after `run` belongs to it
+                cli.run = Some(args[i + 1..].to_vec());
+                break;
+            }
+            "config" if cli.script.is_none() => {
+                cli.config = Some(args[i + 1..].to_vec());
+                break;
+            }
             "-l" | "--login" => {
                 cli.login = true;
             }
             "--trace" => {
                 cli.trace = true;
             }
+            "--dap" => {
+                cli.dap = true;
+            }
+            "--explain" => {
+                cli.explain = true;
+            }
+            "-r" | "--restricted" => {
+                cli.restricted = true;
+            }
+            "--rpn" => {
+                cli.rpn = true;
+            }
+            "--record" if i + 1 < args.len() => {
+                i += 1;
+                cli.record = Some(args[i].clone());
+            }
+            "--resume" if i + 1 < args.len() => {
+                i += 1;
+                cli.resume = Some(args[i].clone());
+            }
+            "--color" if i + 1 < args.len() => {
+                i += 1;
+                cli.color = Some(args[i].clone());
+            }
+            flag if flag.starts_with("--color=") => {
+                cli.color = Some(flag["--color=".len()..].to_string());
+            }
             "-c" => {
                 // Everything after -c is the command
                 if i + 1 < args.len() {
@@ -58,6 +138,9 @@ pub(crate) fn parse_args(args: &[String]) -> CliArgs {
                 // Assume it's a script file if not a flag
                 if !path.starts_with('-') {
                     cli.script = Some(path.to_string());
+                    // Everything after the script is its own argv
+                    cli.script_args = args[i + 1..].to_vec();
+                    break;
                 }
             }
         }
@@ -74,17 +157,37 @@ pub(crate) fn print_help() {
 USAGE:
     hsab                    Start interactive REPL
     hsab init               Install stdlib to ~/.hsab/lib/
+    hsab init --upgrade     Update the installed stdlib, keeping your edits
+    hsab run [-j N] <task>… Run hsab.toml tasks after their dependencies
+    hsab run --list         List the tasks in hsab.toml
+    hsab config             List settings, with where each value comes from
+    hsab config get <key>   Print a setting
+    hsab config set <key> <value>
+                            Save a setting to ~/.hsab/config.toml
+    hsab config unset <key> Remove a setting from ~/.hsab/config.toml
     hsab -l, --login        Start as login shell (sources profile)
+    hsab -r, --restricted   Restricted shell, like rbash (also when run as rhsab)
+    hsab --rpn              Start the REPL as an RPN calculator (see .calc)
     hsab -c <command>       Execute a single command
-    hsab <script.hsab>      Execute a script file
+    hsab <script.hsab> [args…]
+                            Execute a script file (see `args`, `parse-args`)
     hsab --help             Show this help message
     hsab --version          Show version
+    hsab --color=WHEN       Color output: auto (default), always, never
+    hsab --dap              Serve the Debug Adapter Protocol on stdin/stdout
+    hsab --record <file> …  Record each step of a script or -c command (see .replay)
+    hsab --explain …        Show what a script or -c command does, as bash, without running it
+    hsab --resume <ckpt> <script.hsab>
+                            Restore a checkpoint and continue the script after it
 
 STARTUP:
     ~/.hsabrc               Executed on REPL startup (if exists)
     ~/.hsab/lib/stdlib.hsabrc  Auto-loaded if present (run 'hsab init')
+    hsab.toml               Project config, found in the working directory or above
+    ~/.hsab/config.toml     Settings (see 'hsab config'); environment variables override
     ~/.hsab_profile         Executed on login shell startup (-l flag)
     HSAB_BANNER=1           Show startup banner (quiet by default)
+    NO_COLOR=1              Disable colored output (CLICOLOR_FORCE=1 forces it)
 
 CORE CONCEPT:
     Values push to stack, executables pop args and push output.
@@ -104,6 +207,7 @@ SYNTAX:
     apply                   Apply: execute top block
     |                       Pipe: producer #[consumer] |
     > >> <                  Redirect stdout: #[cmd] #[file] >
+    <<<                     Here-string: "text" #[cmd] <<< (heredoc dedents)
     2> 2>>                  Redirect stderr: #[cmd] #[file] 2>
     &>                      Redirect both: #[cmd] #[file] &>
     && ||                   Logic: #[left] #[right] &&
@@ -150,6 +254,7 @@ PREDICATES:
 
 ARITHMETIC:
     plus minus mul div mod  Math ops: 3 5 plus -> 8, 10 3 mod -> 1
+    inc! dec!               Step a variable in place: "i" inc!, "i" 5 dec!
 
 LIST OPS:
     spread                  Split value by lines onto stack (with marker)
@@ -171,7 +276,7 @@ PARALLEL:
     fork                    #[cmd1] #[cmd2] 2 fork - background N blocks
 
 PROCESS SUBST:
-    subst                   #[cmd] subst - create temp file with output
+    subst                   #[cmd] subst - /dev/fd path streaming cmd output
     fifo                    #[cmd] fifo - create named pipe with output
 
 JSON / STRUCTURED DATA:
@@ -188,6 +293,8 @@ STRUCTURED DATA OPS:
     Record Operations:
       record                Create: "name" "Alice" "age" 30 record
       get                   Get field: record "name" get (supports "a.b.c" paths)
+      get-or                Get with default: record "name" "anon" get-or
+      get?                  Get field or nil: record "name" get? (exit 0/1)
       set                   Set field: record "a.b" "val" set (deep set)
       del                   Delete field: record "name" del
       has?                  Check field: record "name" has? (exit 0/1)
@@ -218,6 +325,9 @@ STRUCTURED DATA OPS:
       try                   Catch errors: #[cmd] try
       error?                Check if error value (exit 0/1)
       throw                 Raise error: "message" throw
+      status                Exit status as a value: #[cmd] status
+      ok? / failed?         Whether a status succeeded: #[cmd] status ok?
+      code                  Exit code of a status: #[cmd] status code
 
     Serialization (text -> structured, from-X = parse):
       from-csv              "csv text" from-csv -> table
@@ -272,9 +382,13 @@ STRUCTURED DATA OPS:
                             Retry block up to N times until success
       compose               #[op1] #[op2] #[op3] compose -> #[op1 op2 op3]
                             Combine blocks into a single pipeline
+      and-then / or-else    #[a] #[b] and-then -> b on a's value, if a succeeded
+                            #[a] #[b] or-else -> b on a's value, if a failed
 
-RESOURCE LIMITS:
+RESOURCE LIMITS / PIPELINE STATUS:
     timeout                 N #[cmd] timeout - kill after N seconds
+    pipestatus              List of exit codes from the last pipeline
+    pipefail                "on" pipefail - a pipeline fails with its first failing stage
 
 MODULE SYSTEM:
     .import                 Import module: "path.hsab" .import
@@ -302,6 +416,7 @@ META COMMANDS (dot-prefixed, affect shell state):
     .bg                     Resume job in background: %1 .bg
     .exit                   Exit the shell: .exit, 0 .exit
     .tty                    Run interactive command: file.txt vim .tty
+    live / capture          Run the next command on the terminal / capture it
     .source / .             Execute file in current context: file.hsab .source
     .hash                   Show/manage command hash table: ls .hash, -r .hash
     .type                   Show how a word resolves: ls .type
@@ -347,6 +462,10 @@ REPL COMMANDS:
     .types, .t              Toggle type annotations in hint
     .hint                   Toggle hint visibility
     .highlight, .hl         Toggle syntax highlighting
+    .calc                   Toggle calculator mode (ans, numbers like 1h30m and 1.5G)
+    .sync                   Share definitions with other REPLs and sync now
+    .edit [code]            Edit code (or the last command) in $EDITOR, then run it
+    "name" .edit-def        Edit a definition in $EDITOR and redefine it
     exit, quit              Exit the REPL
 
 DEBUGGER:
@@ -355,7 +474,12 @@ DEBUGGER:
     .delbreak <pat>, .db    Remove a breakpoint
     .breakpoints, .bl       List all breakpoints
     .clearbreaks, .cb       Clear all breakpoints
+    .break-if <cond>        Pause when a condition holds (e.g. depth 5 gt?)
+    .break-def <name>       Pause on a definition's entry and exit
+    .watch <expr>, .unwatch Print an expression after each step / clear
     .step                   Enable single-step mode
+    .record <file>, .record Record each step to a file / stop recording
+    .replay <file>          Step through a recording (n/p/g N/e/q)
     When paused:
       n/next/Enter          Step to next expression
       c/continue            Continue until next breakpoint
@@ -409,7 +533,63 @@ pub(crate) fn print_version() {
 }
 
 /// Execute a single command with optional login shell mode
-pub(crate) fn execute_command_with_login(cmd: &str, is_login: bool, trace: bool) -> ExitCode {
+/// `--explain`: describe a -c command or a script's lines without running
+/// anything
+pub(crate) fn explain_program(command: Option<&str>, script: Option<&str>) -> ExitCode {
+    let mut eval = Evaluator::new();
+    load_stdlib(&mut eval);
+    load_hsabrc(&mut eval);
+    if !apply_workspace(&mut eval) {
+        return ExitCode::FAILURE;
+    }
+
+    let lines: Vec<(Option<usize>, String)> = match (command, script) {
+        (Some(cmd), _) => vec![(None, cmd.to_string())],
+        (None, Some(path)) => match fs::read_to_string(path) {
+            // Each script line starts with an empty stack, so explain
+            // them one at a time
+            Ok(content) => content
+                .lines()
+                .enumerate()
+                .map(|(n, line)| (Some(n + 1), line.trim().to_string()))
+                .filter(|(_, line)| {
+                    !line.is_empty() && (!line.starts_with('#') || line.starts_with("#["))
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!("Error reading {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, None) => {
+            eprintln!("hsab: --explain needs -c <command> or a script");
+            return ExitCode::from(2);
+        }
+    };
+
+    for (line_num, line) in lines {
+        if let Some(n) = line_num {
+            println!("line {}: {}", n, line);
+        }
+        match eval.explain(&line) {
+            Ok(text) if line_num.is_some() => println!("{}", text),
+            Ok(text) => print!("{}", text),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub(crate) fn execute_command_with_login(
+    cmd: &str,
+    is_login: bool,
+    trace: bool,
+    restricted: bool,
+    record: Option<&str>,
+) -> ExitCode {
     let mut eval = Evaluator::new();
     eval.set_trace_mode(trace);
 
@@ -424,23 +604,42 @@ pub(crate) fn execute_command_with_login(cmd: &str, is_login: bool, trace: bool)
     // Load ~/.hsabrc (user customizations override stdlib)
     load_hsabrc(&mut eval);
 
-    match execute_line(&mut eval, cmd, true) {
-        Ok(exit_code) => {
-            if exit_code == 0 {
-                ExitCode::SUCCESS
-            } else {
-                ExitCode::from(exit_code as u8)
-            }
-        }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            ExitCode::FAILURE
-        }
+    if !apply_workspace(&mut eval) {
+        return ExitCode::FAILURE;
+    }
+
+    // Restrictions start once the startup files have run
+    if restricted {
+        eval.set_restricted();
+    }
+
+    if !begin_recording(&mut eval, record) {
+        return ExitCode::FAILURE;
     }
+
+    // A startup file that calls `exit` ends the shell before the command
+    let exit_code = match eval.exit_requested() {
+        Some(code) => code,
+        None => match execute_line(&mut eval, cmd, true) {
+            Ok(exit_code) => exit_code,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                1
+            }
+        },
+    };
+    ExitCode::from(run_exit_trap(&mut eval, exit_code) as u8)
 }
 
 /// Execute a script file
-pub(crate) fn execute_script(path: &str, trace: bool) -> ExitCode {
+pub(crate) fn execute_script(
+    path: &str,
+    script_args: &[String],
+    trace: bool,
+    restricted: bool,
+    record: Option<&str>,
+    resume: Option<&str>,
+) -> ExitCode {
     let content = match fs::read_to_string(path) {
         Ok(c) => c,
         Err(e) => {
@@ -451,10 +650,33 @@ pub(crate) fn execute_script(path: &str, trace: bool) -> ExitCode {
 
     let mut eval = Evaluator::new();
     eval.set_trace_mode(trace);
+    eval.set_script_args(script_args.to_vec());
 
     // Load stdlib if installed
     load_stdlib(&mut eval);
 
+    if !apply_workspace(&mut eval) {
+        return ExitCode::FAILURE;
+    }
+
+    if restricted {
+        eval.set_restricted();
+    }
+
+    let resume_point = match resume.map(|name| resume_checkpoint(&mut eval, name, &content)) {
+        Some(Ok(point)) => Some(point),
+        Some(Err(e)) => {
+            eprintln!("hsab: {}", e);
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    if !begin_recording(&mut eval, record) {
+        return ExitCode::FAILURE;
+    }
+
+    let mut exit_code = 0;
     for (line_num, line) in content.lines().enumerate() {
         let trimmed = line.trim();
 
@@ -464,33 +686,393 @@ pub(crate) fn execute_script(path: &str, trace: bool) -> ExitCode {
             continue;
         }
 
-        match execute_line(&mut eval, trimmed, true) {
-            Ok(exit_code) => {
+        eval.set_script_location(path, line_num + 1, trimmed);
+        let result = match &resume_point {
+            // Lines before the checkpoint already ran
+            Some(point) if line_num + 1 < point.line => continue,
+            Some(point) if line_num + 1 == point.line => {
+                execute_line_after(&mut eval, trimmed, point.col)
+            }
+            _ => execute_line(&mut eval, trimmed, true),
+        };
+
+        match result {
+            // `exit` ends the script with its code, failing or not
+            Ok(code) if eval.exit_requested().is_some() => {
+                exit_code = code;
+                break;
+            }
+            Ok(code) => {
                 // Clear the stack after each line (like .hsabrc loading)
                 // Output was already printed by execute_line
                 eval.clear_stack();
 
-                if exit_code != 0 {
+                if code != 0 {
                     eprintln!(
                         "Error at line {}: command failed with exit code {}",
                         line_num + 1,
-                        exit_code
+                        code
                     );
-                    return ExitCode::FAILURE;
+                    exit_code = 1;
+                    break;
                 }
             }
             Err(e) => {
                 eprintln!("Error at line {}: {}", line_num + 1, e);
+                exit_code = 1;
+                break;
+            }
+        }
+    }
+
+    ExitCode::from(run_exit_trap(&mut eval, exit_code) as u8)
+}
+
+/// Restore `--resume` state, refusing if the checkpointed line has changed
+fn resume_checkpoint(
+    eval: &mut Evaluator,
+    name: &str,
+    content: &str,
+) -> Result<hsab::eval::ResumePoint, String> {
+    let point = eval.restore_checkpoint(name)?;
+    let current = content
+        .lines()
+        .nth(point.line.saturating_sub(1))
+        .map(str::trim);
+    if current != Some(point.text.as_str()) {
+        return Err(format!(
+            "script changed since checkpoint '{}' (line {} differs); not resuming",
+            name, point.line
+        ));
+    }
+    Ok(point)
+}
+
+/// Apply the project's hsab.toml, including its output format. Returns
+/// false (after reporting) if the file is invalid.
+fn apply_workspace(eval: &mut Evaluator) -> bool {
+    match load_workspace(eval) {
+        Ok(config) => {
+            set_output_format(config.map(|c| c.output).unwrap_or_default());
+            true
+        }
+        Err(e) => {
+            eprintln!("hsab: {}", e);
+            false
+        }
+    }
+}
+
+/// `hsab config [get KEY | set KEY VALUE | unset KEY]`: list, read, or
+/// change the settings in ~/.hsab/config.toml. Reads show the value hsab
+/// would use, so an environment variable beats the file.
+pub(crate) fn run_config(args: &[String]) -> ExitCode {
+    use hsab::config::{setting, Source, UserConfig, SETTINGS};
+
+    let mut config = match UserConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("hsab config: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] | ["list"] => {
+            for s in SETTINGS {
+                let (value, source) = match config.effective(s) {
+                    Some((value, Source::Env)) => (value, format!("env {}", s.var)),
+                    Some((value, Source::File)) => (value, "file".to_string()),
+                    Some((value, Source::Default)) => (value, "default".to_string()),
+                    None => ("-".to_string(), "unset".to_string()),
+                };
+                println!("{:<15} {:<12} {}", s.key, value, source);
+            }
+            ExitCode::SUCCESS
+        }
+        ["get", key] => match setting(key) {
+            Ok(s) => match config.effective(s) {
+                Some((value, _)) => {
+                    println!("{}", value);
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            },
+            Err(e) => {
+                eprintln!("hsab config: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        ["set", key, value] => {
+            if let Err(e) = config.set(key, value) {
+                eprintln!("hsab config: {}", e);
                 return ExitCode::FAILURE;
             }
+            match config.save() {
+                Ok(path) => {
+                    println!("{} = {} (in {})", key, value, path.display());
+                    let var = setting(key).map(|s| s.var).unwrap_or_default();
+                    if std::env::var_os(var).is_some() {
+                        eprintln!(
+                            "hsab config: {} is set in the environment, and overrides this",
+                            var
+                        );
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("hsab config: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        ["unset", key] => match config.unset(key) {
+            Ok(true) => match config.save() {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("hsab config: {}", e);
+                    ExitCode::FAILURE
+                }
+            },
+            Ok(false) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("hsab config: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("usage: hsab config [get KEY | set KEY VALUE | unset KEY]");
+            ExitCode::from(2)
         }
     }
+}
 
-    ExitCode::SUCCESS
+/// `hsab run [-j N] [--list] TASK...`: run tasks (and scripts) from the
+/// nearest hsab.toml in the project root. Each task runs after its
+/// dependencies; independent tasks run in parallel, up to `-j` at a time.
+/// Without a task name the available tasks are listed.
+pub(crate) fn run_tasks(args: &[String]) -> ExitCode {
+    let mut targets = Vec::new();
+    let mut list = false;
+    let mut jobs = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--list" | "-l" => list = true,
+            "-j" | "--jobs" => {
+                i += 1;
+                match args.get(i).and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n) if n > 0 => jobs = n,
+                    _ => {
+                        eprintln!("hsab run: -j needs a positive number");
+                        return ExitCode::from(2);
+                    }
+                }
+            }
+            name => targets.push(name.to_string()),
+        }
+        i += 1;
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_else(|_| ".".into());
+    let config = match hsab::workspace::WorkspaceConfig::discover(&cwd) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            eprintln!("hsab run: no {} found", hsab::workspace::WORKSPACE_FILE);
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            eprintln!("hsab: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if list || targets.is_empty() {
+        print_tasks(&config);
+        return ExitCode::SUCCESS;
+    }
+    let plan = match config.plan(&targets) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("hsab run: {}", e);
+            if !config.task_names().is_empty() {
+                print_tasks(&config);
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Tasks run from the project root, like npm scripts
+    if let Err(e) = std::env::set_current_dir(&config.root) {
+        eprintln!("hsab run: {}: {}", config.root.display(), e);
+        return ExitCode::FAILURE;
+    }
+    set_output_format(config.output);
+
+    if plan.len() == 1 {
+        return match run_task(&config, &plan[0]) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(code) => code,
+        };
+    }
+    run_task_graph(std::sync::Arc::new(config), plan, jobs)
+}
+
+/// Print each task with its dependencies and description
+fn print_tasks(config: &hsab::workspace::WorkspaceConfig) {
+    let names = config.task_names();
+    if names.is_empty() {
+        println!(
+            "No tasks in {}",
+            config.root.join(hsab::workspace::WORKSPACE_FILE).display()
+        );
+        return;
+    }
+    println!("Tasks:");
+    for name in names {
+        let Some(task) = config.task(name) else {
+            continue;
+        };
+        let summary = task
+            .description
+            .clone()
+            .unwrap_or_else(|| task.run.lines().next().unwrap_or("").trim().to_string());
+        if task.deps.is_empty() {
+            println!("  {:<16} {}", name, summary);
+        } else {
+            println!(
+                "  {:<16} {}  (after {})",
+                name,
+                summary,
+                task.deps.join(", ")
+            );
+        }
+    }
+}
+
+/// Run the tasks in `plan` (already in dependency order), starting each as
+/// soon as its dependencies succeed. After a failure no new tasks start.
+fn run_task_graph(
+    config: std::sync::Arc<hsab::workspace::WorkspaceConfig>,
+    plan: Vec<String>,
+    jobs: usize,
+) -> ExitCode {
+    use std::collections::HashSet;
+    use std::sync::mpsc::channel;
+
+    let (tx, rx) = channel();
+    let mut pending = plan;
+    let mut done: HashSet<String> = HashSet::new();
+    let mut running = 0;
+    let mut failed: Option<ExitCode> = None;
+    loop {
+        if failed.is_none() {
+            while running < jobs {
+                let ready = pending.iter().position(|name| {
+                    config
+                        .task(name)
+                        .is_some_and(|t| t.deps.iter().all(|d| done.contains(d)))
+                });
+                let Some(pos) = ready else {
+                    break;
+                };
+                let name = pending.remove(pos);
+                let (config, tx) = (config.clone(), tx.clone());
+                std::thread::spawn(move || {
+                    let result = run_task(&config, &name);
+                    let _ = tx.send((name, result));
+                });
+                running += 1;
+            }
+        }
+        if running == 0 {
+            break;
+        }
+        let Ok((name, result)) = rx.recv() else {
+            break;
+        };
+        running -= 1;
+        match result {
+            Ok(()) => {
+                done.insert(name);
+            }
+            Err(code) => failed = failed.or(Some(code)),
+        }
+    }
+    failed.unwrap_or(ExitCode::SUCCESS)
+}
+
+/// Run one task's code in a fresh evaluator, line by line like a script
+fn run_task(config: &hsab::workspace::WorkspaceConfig, name: &str) -> Result<(), ExitCode> {
+    let Some(task) = config.task(name) else {
+        return Err(ExitCode::FAILURE);
+    };
+    if task.run.trim().is_empty() {
+        return Ok(());
+    }
+    hsab::display::eprintln_styled(&format!("\x1b[36m▸ {}\x1b[0m", name));
+    let mut eval = Evaluator::new();
+    load_stdlib(&mut eval);
+    load_hsabrc(&mut eval);
+    config.apply(&mut eval);
+
+    let mut exit_code = 0;
+    for line in task.run.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (trimmed.starts_with('#') && !trimmed.starts_with("#[")) {
+            continue;
+        }
+        match execute_line(&mut eval, trimmed, true) {
+            Ok(0) if eval.exit_requested().is_some() => break,
+            Ok(0) => eval.clear_stack(),
+            Ok(code) => {
+                eprintln!("hsab run: task '{}' failed with exit code {}", name, code);
+                exit_code = code;
+                break;
+            }
+            Err(e) => {
+                eprintln!("hsab run: task '{}' failed: {}", name, e);
+                exit_code = 1;
+                break;
+            }
+        }
+    }
+    match run_exit_trap(&mut eval, exit_code) {
+        0 => Ok(()),
+        code => Err(ExitCode::from(code.clamp(1, 255) as u8)),
+    }
+}
+
+/// Start `--record` after startup files load, so only the user's program
+/// is recorded. Returns false (after reporting) if the file can't be made.
+fn begin_recording(eval: &mut Evaluator, record: Option<&str>) -> bool {
+    match record.map(|path| eval.start_recording(path)) {
+        Some(Err(e)) => {
+            eprintln!("hsab: cannot record: {}", e);
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Serve a Debug Adapter Protocol session for an editor
+#[cfg(feature = "dap")]
+pub(crate) fn run_dap() -> ExitCode {
+    let mut eval = Evaluator::new();
+    load_stdlib(&mut eval);
+    let code = hsab::dap::serve_stdio(&mut eval);
+    ExitCode::from(code.clamp(0, 255) as u8)
+}
+
+#[cfg(not(feature = "dap"))]
+pub(crate) fn run_dap() -> ExitCode {
+    eprintln!("hsab: built without DAP support (rebuild with --features dap)");
+    ExitCode::from(2)
 }
 
 /// Initialize hsab stdlib: create ~/.hsab/lib/ and install stdlib.hsabrc
-pub(crate) fn run_init() -> ExitCode {
+/// (or, with `--upgrade`, merge this version's stdlib into it)
+pub(crate) fn run_init(upgrade: bool) -> ExitCode {
     let home = match dirs_home() {
         Some(h) => h,
         None => {
@@ -501,6 +1083,9 @@ pub(crate) fn run_init() -> ExitCode {
 
     let lib_dir = home.join(".hsab").join("lib");
     let stdlib_file = lib_dir.join("stdlib.hsabrc");
+    // The stdlib as last installed, before any edits: the base that
+    // `--upgrade` merges from
+    let base_file = lib_dir.join(".stdlib-base.hsabrc");
 
     // Create directory if it doesn't exist
     if let Err(e) = fs::create_dir_all(&lib_dir) {
@@ -510,14 +1095,19 @@ pub(crate) fn run_init() -> ExitCode {
 
     // Check if stdlib already exists
     if stdlib_file.exists() {
+        if upgrade {
+            return upgrade_stdlib(&stdlib_file, &base_file);
+        }
         println!("Stdlib already installed at {}", stdlib_file.display());
-        println!("To reinstall, remove the file first:");
-        println!("  rm {}", stdlib_file.display());
+        println!("To update it to this version, keeping your edits:");
+        println!("  hsab init --upgrade");
         return ExitCode::SUCCESS;
     }
 
     // Write stdlib content
-    if let Err(e) = fs::write(&stdlib_file, STDLIB_CONTENT) {
+    if let Err(e) = fs::write(&stdlib_file, stamped_stdlib(STDLIB_CONTENT))
+        .and_then(|()| fs::write(&base_file, STDLIB_CONTENT))
+    {
         eprintln!("Error writing {}: {}", stdlib_file.display(), e);
         return ExitCode::FAILURE;
     }
@@ -535,3 +1125,68 @@ pub(crate) fn run_init() -> ExitCode {
 
     ExitCode::SUCCESS
 }
+
+/// `hsab init --upgrade`: three-way merge the installed stdlib with this
+/// version's, from the stdlib it was installed as. Edits are kept, and
+/// where an edit and the new stdlib change the same lines, both are left
+/// between conflict markers (the stdlib isn't loaded until they're gone).
+/// The installed file is kept in `stdlib.hsabrc.orig`.
+fn upgrade_stdlib(stdlib_file: &std::path::Path, base_file: &std::path::Path) -> ExitCode {
+    let installed = match fs::read_to_string(stdlib_file) {
+        Ok(installed) => installed,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", stdlib_file.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let (version, ours) = split_stdlib_header(&installed);
+    // Installs from before the base was kept merge without one
+    let base = fs::read_to_string(base_file).ok();
+    if base.is_none() {
+        println!(
+            "No copy of {} as first installed; lines it shares with this version are taken as unedited",
+            stdlib_file.display()
+        );
+    }
+
+    let merge = hsab::eval::merge3(
+        base.as_deref(),
+        ours,
+        STDLIB_CONTENT,
+        ("your stdlib", concat!("hsab ", env!("CARGO_PKG_VERSION"))),
+    );
+    if merge.text == ours && version == Some(VERSION) {
+        println!("Stdlib at {} is up to date", stdlib_file.display());
+        return ExitCode::SUCCESS;
+    }
+
+    let backup = stdlib_file.with_extension("hsabrc.orig");
+    let written = fs::write(&backup, &installed)
+        .and_then(|()| fs::write(stdlib_file, stamped_stdlib(&merge.text)))
+        .and_then(|()| fs::write(base_file, STDLIB_CONTENT));
+    if let Err(e) = written {
+        eprintln!("Error writing {}: {}", stdlib_file.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    if merge.conflicts > 0 {
+        println!(
+            "Upgraded stdlib at {} to hsab {}, with {} conflict{}",
+            stdlib_file.display(),
+            VERSION,
+            merge.conflicts,
+            if merge.conflicts == 1 { "" } else { "s" }
+        );
+        println!("Your lines and the new ones are both kept between <<<<<<< and >>>>>>> markers;");
+        println!("the stdlib isn't loaded until you resolve them.");
+        println!("The previous version is in {}", backup.display());
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "\u{2713} Upgraded stdlib at {} to hsab {}, keeping your edits",
+        stdlib_file.display(),
+        VERSION
+    );
+    println!("The previous version is in {}", backup.display());
+    ExitCode::SUCCESS
+}
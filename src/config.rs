@@ -0,0 +1,336 @@
+//! User settings: `~/.hsab/config.toml`
+//!
+//! hsab's knobs are environment variables (`HSAB_MAX_RECURSION`,
+//! `HSAB_BANNER`, ...), which are awkward to keep set. The config file
+//! holds them in one place, one key per variable:
+//!
+//! ```toml
+//! max_recursion = 50000
+//! preview_len = 20
+//! banner = true
+//! strict = true
+//! ```
+//!
+//! At startup each setting the environment doesn't already have is set
+//! from the file, so a variable set in the environment still wins, and
+//! everything that reads a variable sees the file's value. `hsab config`
+//! lists, reads, and writes the settings; the `config` builtin pushes them
+//! as a record.
+
+use std::path::PathBuf;
+
+/// What a setting holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Int,
+    Bool,
+    Text,
+}
+
+/// A setting: its key in the file, the variable it sets, and its default
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub key: &'static str,
+    pub var: &'static str,
+    pub kind: Kind,
+    /// What hsab does when it's unset (None: nothing, or it depends)
+    pub default: Option<&'static str>,
+    pub about: &'static str,
+}
+
+/// Every setting, in the order `hsab config` lists them
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "max_recursion",
+        var: "HSAB_MAX_RECURSION",
+        kind: Kind::Int,
+        default: Some("10000"),
+        about: "Deepest nesting of definition calls",
+    },
+    Setting {
+        key: "preview_len",
+        var: "HSAB_PREVIEW_LEN",
+        kind: Kind::Int,
+        default: Some("8"),
+        about: "Characters of a string shown in limbo references",
+    },
+    Setting {
+        key: "strict",
+        var: "HSAB_STRICT",
+        kind: Kind::Bool,
+        default: Some("false"),
+        about: "Expanding an unset $VAR is an error",
+    },
+    Setting {
+        key: "pipefail",
+        var: "HSAB_PIPEFAIL",
+        kind: Kind::Bool,
+        default: Some("false"),
+        about: "A pipeline fails with its first failing stage",
+    },
+    Setting {
+        key: "banner",
+        var: "HSAB_BANNER",
+        kind: Kind::Bool,
+        default: Some("false"),
+        about: "Show the banner when the REPL starts",
+    },
+    Setting {
+        key: "highlight",
+        var: "HSAB_HIGHLIGHT",
+        kind: Kind::Bool,
+        default: Some("true"),
+        about: "Syntax highlighting in the REPL",
+    },
+    Setting {
+        key: "persist_stack",
+        var: "HSAB_PERSIST_STACK",
+        kind: Kind::Bool,
+        default: Some("false"),
+        about: "Keep the REPL's stack between sessions",
+    },
+    Setting {
+        key: "share_defs",
+        var: "HSAB_SHARE_DEFS",
+        kind: Kind::Bool,
+        default: Some("false"),
+        about: "Share definitions between running REPLs",
+    },
+    Setting {
+        key: "path",
+        var: "HSAB_PATH",
+        kind: Kind::Text,
+        default: None,
+        about: "Directories .import searches, separated by ':'",
+    },
+    Setting {
+        key: "encoding",
+        var: "HSAB_ENCODING",
+        kind: Kind::Text,
+        default: Some("utf-8"),
+        about: "Encoding of command output",
+    },
+    Setting {
+        key: "log",
+        var: "HSAB_LOG",
+        kind: Kind::Text,
+        default: Some("info"),
+        about: "Lowest level the log words write",
+    },
+    Setting {
+        key: "log_format",
+        var: "HSAB_LOG_FORMAT",
+        kind: Kind::Text,
+        default: Some("text"),
+        about: "How log messages are written: text or json",
+    },
+    Setting {
+        key: "parallel_view",
+        var: "HSAB_PARALLEL_VIEW",
+        kind: Kind::Text,
+        default: None,
+        about: "How parallel shows progress: panes, lines, or off",
+    },
+    Setting {
+        key: "checkpoint_dir",
+        var: "HSAB_CHECKPOINT_DIR",
+        kind: Kind::Text,
+        default: None,
+        about: "Where named checkpoints are saved",
+    },
+    Setting {
+        key: "dns_server",
+        var: "DNS_SERVER",
+        kind: Kind::Text,
+        default: None,
+        about: "Name server the dns- words ask",
+    },
+];
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Env,
+    File,
+    Default,
+}
+
+/// The setting named `key`
+pub fn setting(key: &str) -> Result<&'static Setting, String> {
+    SETTINGS.iter().find(|s| s.key == key).ok_or_else(|| {
+        let keys: Vec<&str> = SETTINGS.iter().map(|s| s.key).collect();
+        format!("no setting '{}' (settings: {})", key, keys.join(", "))
+    })
+}
+
+/// Whether a variable's value turns a switch on: `1`, `true`, `on`, `yes`
+pub fn is_on(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "on" | "yes"
+    )
+}
+
+/// The config file: `~/.hsab/config.toml`
+pub fn path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".hsab").join("config.toml"))
+}
+
+/// The settings in a config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserConfig {
+    values: toml::Table,
+}
+
+impl UserConfig {
+    /// Read the config file; a missing one has no settings
+    pub fn load() -> Result<UserConfig, String> {
+        let Some(path) = path() else {
+            return Ok(UserConfig::default());
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(UserConfig::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parse a config file's text
+    pub fn parse(text: &str) -> Result<UserConfig, String> {
+        let values: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        for (key, value) in &values {
+            let setting = setting(key)?;
+            check_type(setting, value)?;
+        }
+        Ok(UserConfig { values })
+    }
+
+    /// Write the settings back to the config file
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let path = path().ok_or("Could not determine home directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        let text = toml::to_string(&self.values).map_err(|e| e.to_string())?;
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// The file's value for a setting, as its variable would hold it
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Boolean(b) => if *b { "1" } else { "0" }.to_string(),
+            other => other.to_string(),
+        })
+    }
+
+    /// Set a setting from text, checked against its kind
+    pub fn set(&mut self, key: &str, text: &str) -> Result<(), String> {
+        let setting = setting(key)?;
+        let value = match setting.kind {
+            Kind::Int => toml::Value::Integer(
+                text.trim()
+                    .parse()
+                    .map_err(|_| format!("{} is a number, not '{}'", key, text))?,
+            ),
+            Kind::Bool => match text.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "on" | "yes" => toml::Value::Boolean(true),
+                "0" | "false" | "off" | "no" => toml::Value::Boolean(false),
+                _ => return Err(format!("{} is true or false, not '{}'", key, text)),
+            },
+            Kind::Text => toml::Value::String(text.to_string()),
+        };
+        self.values.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Remove a setting; whether it was there
+    pub fn unset(&mut self, key: &str) -> Result<bool, String> {
+        setting(key)?;
+        Ok(self.values.remove(key).is_some())
+    }
+
+    /// A setting's value as hsab sees it, and where it came from: the
+    /// environment, then the file, then the default
+    pub fn effective(&self, setting: &Setting) -> Option<(String, Source)> {
+        if let Ok(value) = std::env::var(setting.var) {
+            return Some((value, Source::Env));
+        }
+        if let Some(value) = self.get(setting.key) {
+            return Some((value, Source::File));
+        }
+        setting.default.map(|d| (d.to_string(), Source::Default))
+    }
+
+    /// Set the variable of each setting in the file that the environment
+    /// doesn't have, for everything that reads it
+    pub fn apply_env(&self) {
+        for setting in SETTINGS {
+            if std::env::var_os(setting.var).is_some() {
+                continue;
+            }
+            if let Some(value) = self.get(setting.key) {
+                std::env::set_var(setting.var, value);
+            }
+        }
+    }
+}
+
+fn check_type(setting: &Setting, value: &toml::Value) -> Result<(), String> {
+    let fits = matches!(
+        (setting.kind, value),
+        (Kind::Int, toml::Value::Integer(_))
+            | (Kind::Bool, toml::Value::Boolean(_))
+            | (Kind::Text, toml::Value::String(_))
+    );
+    if fits {
+        return Ok(());
+    }
+    let expected = match setting.kind {
+        Kind::Int => "an integer",
+        Kind::Bool => "true or false",
+        Kind::Text => "a string",
+    };
+    Err(format!("{} should be {}", setting.key, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checks_keys_and_types() {
+        let config =
+            UserConfig::parse("max_recursion = 50000\nbanner = true\nlog = \"debug\"\n").unwrap();
+        assert_eq!(config.get("max_recursion").as_deref(), Some("50000"));
+        assert_eq!(config.get("banner").as_deref(), Some("1"));
+        assert_eq!(config.get("log").as_deref(), Some("debug"));
+        assert_eq!(config.get("preview_len"), None);
+
+        let err = UserConfig::parse("max_recursoin = 5\n").unwrap_err();
+        assert!(err.contains("no setting 'max_recursoin'"), "{}", err);
+        let err = UserConfig::parse("banner = \"yes\"\n").unwrap_err();
+        assert_eq!(err, "banner should be true or false");
+    }
+
+    #[test]
+    fn test_set_parses_by_kind() {
+        let mut config = UserConfig::default();
+        config.set("preview_len", "20").unwrap();
+        config.set("strict", "on").unwrap();
+        config.set("dns_server", "1.1.1.1").unwrap();
+        assert!(config.set("preview_len", "lots").is_err());
+        assert!(config.set("strict", "maybe").is_err());
+        assert!(config.set("nope", "1").is_err());
+        let text = toml::to_string(&config.values).unwrap();
+        assert_eq!(
+            UserConfig::parse(&text).unwrap(),
+            config,
+            "a saved config reads back the same"
+        );
+        assert!(config.unset("strict").unwrap());
+        assert!(!config.unset("strict").unwrap());
+    }
+}
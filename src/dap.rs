@@ -0,0 +1,543 @@
+//! Debug Adapter Protocol server (feature `dap`)
+//!
+//! `hsab --dap` speaks DAP over stdin/stdout so editors such as VS Code can
+//! debug `.hsab` scripts: line and definition breakpoints, stepping, the
+//! stack shown as variables, and `evaluate` in the debug console. The
+//! server drives the evaluator's existing debug hooks through a
+//! [`DebugFrontend`] instead of the interactive stdin prompt.
+//!
+//! A script runs line by line, like `hsab script.hsab`. Stepping is per
+//! expression; a `pause` request takes effect before the next line.
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value as Json};
+
+use crate::eval::{DebugAction, DebugFrontend};
+use crate::{display, lex_spanned, parse_with_spans, EvalError, Evaluator, Value};
+
+/// The only thread reported to the client
+const THREAD_ID: i64 = 1;
+/// Variables reference of the stack scope; nested values count up from here
+const STACK_REF: i64 = 1;
+
+/// Read one `Content-Length` framed message. None on EOF or a bad frame.
+fn read_message<R: BufRead>(reader: &mut R) -> Option<Json> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+/// Write one message with `Content-Length` framing
+fn write_message<W: Write + ?Sized>(writer: &mut W, msg: &Json) -> std::io::Result<()> {
+    let body = msg.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Session state shared by the script runner and the pause frontend
+struct Client {
+    requests: Receiver<Json>,
+    out: Box<dyn Write + Send>,
+    seq: i64,
+    program: String,
+    stop_on_entry: bool,
+    /// 1-based script lines with a breakpoint
+    line_breakpoints: BTreeSet<usize>,
+    /// Definition names from `setFunctionBreakpoints`
+    function_breakpoints: HashSet<String>,
+    /// Line of the script currently running (1-based)
+    line: usize,
+    /// Stop before the next line (set by `pause` and `stepOut`)
+    stop_next_line: Option<&'static str>,
+    /// Values expanded in the variables view; reference = index + 2
+    var_refs: Vec<Value>,
+    configured: bool,
+    disconnected: bool,
+}
+
+impl Client {
+    fn send(&mut self, mut msg: Json) {
+        self.seq += 1;
+        msg["seq"] = json!(self.seq);
+        // A vanished client surfaces as EOF on the request side
+        let _ = write_message(&mut *self.out, &msg);
+    }
+
+    fn respond(&mut self, req: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": req["seq"],
+            "command": req["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn respond_error(&mut self, req: &Json, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": req["seq"],
+            "command": req["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn output(&mut self, category: &str, text: &str) {
+        self.event("output", json!({ "category": category, "output": text }));
+    }
+
+    /// Handle a request that means the same thing whether or not the
+    /// program is paused. Returns false for commands left to the caller.
+    fn handle_common(&mut self, eval: &mut Evaluator, req: &Json) -> bool {
+        let args = &req["arguments"];
+        match req["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                self.respond(
+                    req,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsFunctionBreakpoints": true,
+                        "supportsEvaluateForHovers": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                );
+                self.event("initialized", json!({}));
+            }
+            "launch" => {
+                self.program = args["program"].as_str().unwrap_or("").to_string();
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                self.respond(req, json!({}));
+            }
+            "setBreakpoints" => {
+                let lines: Vec<usize> = args["breakpoints"]
+                    .as_array()
+                    .map(|bps| {
+                        bps.iter()
+                            .filter_map(|bp| bp["line"].as_u64())
+                            .map(|l| l as usize)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                self.line_breakpoints = lines.iter().copied().collect();
+                let verified: Vec<Json> = lines
+                    .iter()
+                    .map(|l| json!({ "verified": true, "line": l }))
+                    .collect();
+                self.respond(req, json!({ "breakpoints": verified }));
+            }
+            "setFunctionBreakpoints" => {
+                for name in self.function_breakpoints.drain() {
+                    eval.def_breakpoints.remove(&name);
+                }
+                let names: Vec<String> = args["breakpoints"]
+                    .as_array()
+                    .map(|bps| {
+                        bps.iter()
+                            .filter_map(|bp| bp["name"].as_str())
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                for name in &names {
+                    eval.add_definition_breakpoint(name.clone());
+                    self.function_breakpoints.insert(name.clone());
+                }
+                let verified: Vec<Json> =
+                    names.iter().map(|_| json!({ "verified": true })).collect();
+                self.respond(req, json!({ "breakpoints": verified }));
+            }
+            "setExceptionBreakpoints" => self.respond(req, json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(req, json!({}));
+            }
+            "threads" => self.respond(
+                req,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            ),
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                self.respond(req, json!({}));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Variables view entry for a value, registering a reference for
+    /// values that can be expanded
+    fn variable(&mut self, name: String, val: &Value) -> Json {
+        let expandable = match val {
+            Value::List(items) => !items.is_empty(),
+            Value::Map(map) => !map.is_empty(),
+            Value::Table { rows, .. } => !rows.is_empty(),
+            _ => false,
+        };
+        let reference = if expandable {
+            self.var_refs.push(val.clone());
+            self.var_refs.len() as i64 + STACK_REF
+        } else {
+            0
+        };
+        json!({
+            "name": name,
+            "value": display::format_value_hint(val),
+            "type": val.type_name(),
+            "variablesReference": reference,
+        })
+    }
+
+    fn variables(&mut self, eval: &Evaluator, reference: i64) -> Vec<Json> {
+        if reference == STACK_REF {
+            // Index 0 is the top of the stack, as in `${.0}`
+            let stack: Vec<Value> = eval.stack().iter().rev().cloned().collect();
+            return stack
+                .iter()
+                .enumerate()
+                .map(|(i, v)| self.variable(i.to_string(), v))
+                .collect();
+        }
+        let Some(val) = usize::try_from(reference - STACK_REF - 1)
+            .ok()
+            .and_then(|i| self.var_refs.get(i).cloned())
+        else {
+            return Vec::new();
+        };
+        match val {
+            Value::List(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| self.variable(i.to_string(), v))
+                .collect(),
+            Value::Map(map) => map
+                .iter()
+                .map(|(k, v)| self.variable(k.clone(), v))
+                .collect(),
+            Value::Table { columns, rows } => rows
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let record = columns.iter().cloned().zip(row.iter().cloned()).collect();
+                    self.variable(i.to_string(), &Value::Map(record))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Frontend installed in the evaluator while a script runs under DAP
+struct DapFrontend {
+    client: Arc<Mutex<Client>>,
+}
+
+impl DebugFrontend for DapFrontend {
+    fn on_pause(&mut self, eval: &mut Evaluator, reason: &str, expr: &str) -> DebugAction {
+        let mut client = self.client.lock().unwrap_or_else(|e| e.into_inner());
+        let reason = client.stop_next_line.take().unwrap_or(reason);
+        client.var_refs.clear();
+        client.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": expr,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+
+        loop {
+            let Ok(req) = client.requests.recv() else {
+                client.disconnected = true;
+                return DebugAction::Terminate;
+            };
+            if client.handle_common(eval, &req) {
+                if client.disconnected {
+                    return DebugAction::Terminate;
+                }
+                continue;
+            }
+            let args = &req["arguments"];
+            match req["command"].as_str().unwrap_or("") {
+                "stackTrace" => {
+                    let column = eval.current_span.map(|(_, col)| col).unwrap_or(1);
+                    let frame = json!({
+                        "id": 1,
+                        "name": expr,
+                        "line": client.line,
+                        "column": column,
+                        "source": { "path": client.program },
+                    });
+                    client.respond(&req, json!({ "stackFrames": [frame], "totalFrames": 1 }));
+                }
+                "scopes" => client.respond(
+                    &req,
+                    json!({ "scopes": [{
+                        "name": "Stack",
+                        "variablesReference": STACK_REF,
+                        "expensive": false,
+                    }] }),
+                ),
+                "variables" => {
+                    let reference = args["variablesReference"].as_i64().unwrap_or(0);
+                    let vars = client.variables(eval, reference);
+                    client.respond(&req, json!({ "variables": vars }));
+                }
+                "evaluate" => {
+                    let code = args["expression"].as_str().unwrap_or("");
+                    match eval.debug_evaluate(code) {
+                        Ok(top) => {
+                            let shown = top
+                                .as_ref()
+                                .map(display::format_value_hint)
+                                .unwrap_or_else(|| "(nothing)".to_string());
+                            client
+                                .respond(&req, json!({ "result": shown, "variablesReference": 0 }));
+                        }
+                        Err(e) => client.respond_error(&req, &e),
+                    }
+                }
+                "continue" => {
+                    client.respond(&req, json!({ "allThreadsContinued": true }));
+                    return DebugAction::Continue;
+                }
+                "next" | "stepIn" => {
+                    client.respond(&req, json!({}));
+                    return DebugAction::Step;
+                }
+                "stepOut" => {
+                    // Finish the current line and stop at the next one
+                    client.respond(&req, json!({}));
+                    client.stop_next_line = Some("step");
+                    return DebugAction::Continue;
+                }
+                "pause" => client.respond(&req, json!({})),
+                _ => client.respond_error(&req, "unsupported request"),
+            }
+        }
+    }
+}
+
+/// Serve one debug session: configure, run the launched script, report its
+/// exit, and wait for `disconnect`. Returns the script's exit code.
+pub fn serve<R, W>(eval: &mut Evaluator, reader: R, writer: W) -> i32
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        while let Some(msg) = read_message(&mut reader) {
+            if tx.send(msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    let client = Arc::new(Mutex::new(Client {
+        requests: rx,
+        out: Box::new(writer),
+        seq: 0,
+        program: String::new(),
+        stop_on_entry: false,
+        line_breakpoints: BTreeSet::new(),
+        function_breakpoints: HashSet::new(),
+        line: 0,
+        stop_next_line: None,
+        var_refs: Vec::new(),
+        configured: false,
+        disconnected: false,
+    }));
+    // Configuration phase: everything up to configurationDone
+    loop {
+        let mut c = client.lock().unwrap_or_else(|e| e.into_inner());
+        let Ok(req) = c.requests.recv() else {
+            return 0;
+        };
+        if !c.handle_common(eval, &req) {
+            c.respond_error(&req, "program is not running");
+        }
+        if c.disconnected {
+            return 0;
+        }
+        if c.configured {
+            break;
+        }
+    }
+
+    eval.set_debug_mode(true);
+    eval.set_debug_frontend(Box::new(DapFrontend {
+        client: Arc::clone(&client),
+    }));
+    let exit_code = run_program(eval, &client);
+
+    let mut c = client.lock().unwrap_or_else(|e| e.into_inner());
+    c.event("exited", json!({ "exitCode": exit_code }));
+    c.event("terminated", json!({}));
+    while !c.disconnected {
+        let Ok(req) = c.requests.recv() else {
+            break;
+        };
+        if !c.handle_common(eval, &req) {
+            c.respond_error(&req, "program has exited");
+        }
+    }
+    exit_code
+}
+
+/// Run the launched script line by line. Stops at breakpoint lines by
+/// entering step mode before the line's first expression.
+fn run_program(eval: &mut Evaluator, client: &Arc<Mutex<Client>>) -> i32 {
+    let lock = || client.lock().unwrap_or_else(|e| e.into_inner());
+    let program = lock().program.clone();
+    let content = match std::fs::read_to_string(&program) {
+        Ok(c) => c,
+        Err(e) => {
+            lock().output("stderr", &format!("Error reading {}: {}\n", program, e));
+            return 1;
+        }
+    };
+    if lock().stop_on_entry {
+        lock().stop_next_line = Some("entry");
+    }
+
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || (trimmed.starts_with('#') && !trimmed.starts_with("#[")) {
+            continue;
+        }
+
+        {
+            let mut c = lock();
+            // Requests that arrived while the script was running
+            loop {
+                match c.requests.try_recv() {
+                    Ok(req) if req["command"] == "pause" => {
+                        c.respond(&req, json!({}));
+                        c.stop_next_line = Some("pause");
+                    }
+                    Ok(req) => {
+                        if !c.handle_common(eval, &req) {
+                            c.respond_error(&req, "program is running");
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        c.disconnected = true;
+                        break;
+                    }
+                }
+            }
+            if c.disconnected {
+                return 1;
+            }
+            c.line = idx + 1;
+            if c.line_breakpoints.contains(&c.line) && c.stop_next_line.is_none() {
+                c.stop_next_line = Some("breakpoint");
+            }
+            if c.stop_next_line.is_some() {
+                eval.set_step_mode(true);
+            }
+        }
+
+        match eval_line(eval, trimmed) {
+            Ok((stack, exit_code)) => {
+                eval.clear_stack();
+                let mut c = lock();
+                for s in stack {
+                    c.output("stdout", &format!("{}\n", s));
+                }
+                if eval.exit_requested().is_some() {
+                    return exit_code;
+                }
+                if exit_code != 0 {
+                    c.output(
+                        "stderr",
+                        &format!(
+                            "Error at line {}: command failed with exit code {}\n",
+                            idx + 1,
+                            exit_code
+                        ),
+                    );
+                    return exit_code;
+                }
+            }
+            Err(e) => {
+                let mut c = lock();
+                if !c.disconnected && !eval.debug_terminate {
+                    c.output("stderr", &format!("Error at line {}: {}\n", idx + 1, e));
+                }
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+/// Evaluate one script line, returning the printable stack and exit code
+fn eval_line(eval: &mut Evaluator, input: &str) -> Result<(Vec<String>, i32), String> {
+    let tokens = lex_spanned(input).map_err(|e| e.to_string())?;
+    if tokens.is_empty() {
+        return Ok((Vec::new(), 0));
+    }
+    let (program, spans) = parse_with_spans(tokens).map_err(|e| e.to_string())?;
+    let exit_code = match eval.eval_with_spans(&program, &spans) {
+        Ok(result) => result.exit_code,
+        Err(EvalError::Exit(code)) => code,
+        Err(e) => return Err(e.to_string()),
+    };
+    let stack = eval
+        .stack()
+        .iter()
+        .filter_map(|v| v.as_arg())
+        .collect::<Vec<_>>();
+    Ok((stack, exit_code))
+}
+
+/// Serve a session on stdin/stdout. Program output written directly to
+/// stdout (external commands) is redirected to stderr so it cannot
+/// corrupt the protocol stream.
+pub fn serve_stdio(eval: &mut Evaluator) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::FromRawFd;
+        // SAFETY: fd 1 is valid for the life of the process; the duplicate
+        // is owned by the File, and fd 1 is then pointed at stderr
+        let protocol = unsafe {
+            let fd = libc::dup(1);
+            if fd >= 0 && libc::dup2(2, 1) >= 0 {
+                Some(std::fs::File::from_raw_fd(fd))
+            } else {
+                None
+            }
+        };
+        if let Some(out) = protocol {
+            return serve(eval, std::io::stdin(), out);
+        }
+    }
+    serve(eval, std::io::stdin(), std::io::stdout())
+}
@@ -11,11 +11,20 @@
 //! - **Sixel**: Wide terminal support, DEC-style bitmap graphics
 //!
 //! Protocol detection is automatic based on TERM_PROGRAM and capability queries.
+//!
+//! ## Color Policy
+//!
+//! All ANSI styling goes through [`color_enabled`] / [`stderr_color_enabled`]:
+//! `--color=always|never` wins, then `NO_COLOR`, then `CLICOLOR_FORCE`, then
+//! TTY detection.
 
 use crate::ast::Value;
 use crate::util::lock_or_recover;
 use indexmap::IndexMap;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::OnceLock;
+use unicode_width::UnicodeWidthChar;
 
 /// Terminal graphics protocol supported by the current terminal
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -288,11 +297,11 @@ fn format_table(columns: &[String], rows: &[Vec<Value>], max_width: usize) -> St
     }
 
     // Calculate column widths
-    let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+    let mut widths: Vec<usize> = columns.iter().map(|c| display_width(c)).collect();
     for row in rows {
         for (i, val) in row.iter().enumerate() {
             if let Some(w) = widths.get_mut(i) {
-                let val_width = val.as_arg().unwrap_or_default().len();
+                let val_width = display_width(&val.as_arg().unwrap_or_default());
                 *w = (*w).max(val_width);
             }
         }
@@ -329,12 +338,8 @@ fn format_table(columns: &[String], rows: &[Vec<Value>], max_width: usize) -> St
     out.push_str("\x1b[90m│\x1b[0m");
     for (i, col) in columns.iter().enumerate() {
         let w = widths.get(i).copied().unwrap_or(10);
-        let truncated = truncate_str(col, w);
-        out.push_str(&format!(
-            " \x1b[1m{:width$}\x1b[0m \x1b[90m│\x1b[0m",
-            truncated,
-            width = w
-        ));
+        let truncated = pad_str(&truncate_str(col, w), w);
+        out.push_str(&format!(" \x1b[1m{}\x1b[0m \x1b[90m│\x1b[0m", truncated));
     }
     out.push('\n');
 
@@ -354,12 +359,8 @@ fn format_table(columns: &[String], rows: &[Vec<Value>], max_width: usize) -> St
         for (i, val) in row.iter().enumerate() {
             let w = widths.get(i).copied().unwrap_or(10);
             let s = val.as_arg().unwrap_or_default();
-            let truncated = truncate_str(&s, w);
-            out.push_str(&format!(
-                " {:width$} \x1b[90m│\x1b[0m",
-                truncated,
-                width = w
-            ));
+            let truncated = pad_str(&truncate_str(&s, w), w);
+            out.push_str(&format!(" {} \x1b[90m│\x1b[0m", truncated));
         }
         out.push('\n');
     }
@@ -386,7 +387,7 @@ fn format_record(map: &IndexMap<String, Value>, _max_width: usize) -> String {
         return "{}".to_string();
     }
 
-    let max_key_len = map.keys().map(|k| k.len()).max().unwrap_or(0);
+    let max_key_len = map.keys().map(|k| display_width(k)).max().unwrap_or(0);
     let mut out = String::from("\x1b[90m{\x1b[0m\n");
 
     let mut keys: Vec<_> = map.keys().collect();
@@ -395,10 +396,9 @@ fn format_record(map: &IndexMap<String, Value>, _max_width: usize) -> String {
     for key in keys {
         let val = map.get(key).unwrap();
         out.push_str(&format!(
-            "  \x1b[36m{:width$}\x1b[0m: {}\n",
-            key,
-            format_value_inline(val),
-            width = max_key_len
+            "  \x1b[36m{}\x1b[0m: {}\n",
+            pad_str(key, max_key_len),
+            format_value_inline(val)
         ));
     }
     out.push_str("\x1b[90m}\x1b[0m");
@@ -464,11 +464,12 @@ fn format_number_compact(n: f64, mode: CompactMode) -> String {
 /// Truncate a string to max_len, appending "..." if truncated.
 /// If `quote` is true, wraps result in double quotes.
 fn truncate_compact(s: &str, max_len: usize, quote: bool) -> String {
-    if s.len() > max_len {
+    if display_width(s) > max_len {
+        let head = take_width(s, max_len - 3);
         if quote {
-            format!("\"{}...\"", &s[..max_len - 3])
+            format!("\"{}...\"", head)
         } else {
-            format!("{}...", &s[..max_len - 3])
+            format!("{}...", head)
         }
     } else if quote {
         format!("\"{}\"", s)
@@ -522,6 +523,13 @@ fn format_value_compact(val: &Value, mode: CompactMode) -> String {
             CompactMode::Inline => color(mode, "31", &format!("Error: {}", message)),
             CompactMode::Hint => "err".to_string(),
         },
+        Value::Status(code) => {
+            let code_color = if *code == 0 { "32" } else { "31" };
+            match mode {
+                CompactMode::Inline => color(mode, code_color, &format!("status:{}", code)),
+                CompactMode::Hint => format!("?{}", code),
+            }
+        }
         Value::Media {
             mime_type,
             data,
@@ -553,8 +561,12 @@ fn format_value_compact(val: &Value, mode: CompactMode) -> String {
         Value::Link { url, text } => match mode {
             CompactMode::Inline => {
                 let display = text.as_deref().unwrap_or(url);
-                if display.len() > 30 {
-                    color(mode, "34", &format!("<link:{}...>", &display[..27]))
+                if display_width(display) > 30 {
+                    color(
+                        mode,
+                        "34",
+                        &format!("<link:{}...>", take_width(display, 27)),
+                    )
                 } else {
                     color(mode, "34", &format!("<link:{}>", display))
                 }
@@ -613,17 +625,412 @@ fn format_value_compact(val: &Value, mode: CompactMode) -> String {
     }
 }
 
+/// Compact diff between two structured values of the same kind, for
+/// trace/debug output: Records by key (`+added`, `-removed`, `~changed`),
+/// Lists by position, Tables by columns and row count. Returns None for
+/// other types or when nothing changed.
+pub fn format_structured_diff(old: &Value, new: &Value) -> Option<String> {
+    let added = |text: String| format!("\x1b[32m+{}\x1b[0m", text);
+    let removed = |text: String| format!("\x1b[31m-{}\x1b[0m", text);
+    let changed = |text: String| format!("\x1b[33m~{}\x1b[0m", text);
+
+    let parts: Vec<String> = match (old, new) {
+        (Value::Map(a), Value::Map(b)) => {
+            let mut parts = Vec::new();
+            for (k, v) in b {
+                match a.get(k) {
+                    None => parts.push(added(format!("{}: {}", k, format_value_hint(v)))),
+                    Some(old_v) if old_v != v => parts.push(changed(format!(
+                        "{}: {} → {}",
+                        k,
+                        format_value_hint(old_v),
+                        format_value_hint(v)
+                    ))),
+                    Some(_) => {}
+                }
+            }
+            for k in a.keys().filter(|k| !b.contains_key(*k)) {
+                parts.push(removed(k.clone()));
+            }
+            parts
+        }
+        (Value::List(a), Value::List(b)) => {
+            let mut parts: Vec<String> = a
+                .iter()
+                .zip(b.iter())
+                .enumerate()
+                .filter(|(_, (x, y))| x != y)
+                .map(|(i, (x, y))| {
+                    changed(format!(
+                        "[{}]: {} → {}",
+                        i,
+                        format_value_hint(x),
+                        format_value_hint(y)
+                    ))
+                })
+                .collect();
+            let items = |n: usize| format!("{} item{}", n, if n == 1 { "" } else { "s" });
+            if b.len() > a.len() {
+                parts.push(added(items(b.len() - a.len())));
+            } else if a.len() > b.len() {
+                parts.push(removed(items(a.len() - b.len())));
+            }
+            parts
+        }
+        (
+            Value::Table {
+                columns: ca,
+                rows: ra,
+            },
+            Value::Table {
+                columns: cb,
+                rows: rb,
+            },
+        ) => {
+            let mut parts: Vec<String> = cb
+                .iter()
+                .filter(|c| !ca.contains(c))
+                .map(|c| added(format!("col {}", c)))
+                .collect();
+            parts.extend(
+                ca.iter()
+                    .filter(|c| !cb.contains(c))
+                    .map(|c| removed(format!("col {}", c))),
+            );
+            if ra.len() != rb.len() {
+                parts.push(changed(format!("rows {} → {}", ra.len(), rb.len())));
+            } else if parts.is_empty() && ra != rb {
+                parts.push(changed("rows".to_string()));
+            }
+            parts
+        }
+        _ => return None,
+    };
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
 /// Truncate a string to max width, adding ellipsis if needed
-fn truncate_str(s: &str, max_width: usize) -> String {
-    if s.len() <= max_width {
+pub(crate) fn truncate_str(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
         s.to_string()
     } else if max_width <= 1 {
         ".".to_string()
     } else {
-        format!("{}…", &s[..max_width - 1])
+        format!("{}…", take_width(&strip_ansi(s), max_width - 1))
+    }
+}
+
+/// Pad a string with spaces to `width` terminal columns
+fn pad_str(s: &str, width: usize) -> String {
+    let w = display_width(s);
+    if w >= width {
+        s.to_string()
+    } else {
+        format!("{}{}", s, " ".repeat(width - w))
+    }
+}
+
+/// Longest prefix of `s` that fits in `max_width` terminal columns
+fn take_width(s: &str, max_width: usize) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > max_width {
+            break;
+        }
+        used += w;
+        out.push(c);
+    }
+    out
+}
+
+/// Remove ANSI escape sequences: CSI (`ESC [ ... final`), OSC
+/// (`ESC ] ... BEL` or `ESC ] ... ESC \\`), and two-byte escapes
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                // Parameter/intermediate bytes, then a final byte in @..~
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(ch) = chars.next() {
+                    if ch == '\x07' {
+                        break;
+                    }
+                    if ch == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Terminal column width of a string, ignoring ANSI escapes
+/// (wide CJK/emoji characters count as two columns)
+pub fn display_width(s: &str) -> usize {
+    strip_ansi(s).chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// When to emit ANSI color, set by the `--color` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Decide from NO_COLOR, CLICOLOR_FORCE, and TTY detection
+    Auto,
+    /// Always emit color
+    Always,
+    /// Never emit color
+    Never,
+}
+
+impl ColorChoice {
+    /// Parse a `--color` value: `auto`, `always`, or `never`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(ColorChoice::Auto),
+            "always" | "force" => Some(ColorChoice::Always),
+            "never" | "none" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Process-wide color choice (0 = auto, 1 = always, 2 = never)
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(0);
+
+/// Set the process-wide color choice (from `--color`)
+pub fn set_color_choice(choice: ColorChoice) {
+    let raw = match choice {
+        ColorChoice::Auto => 0,
+        ColorChoice::Always => 1,
+        ColorChoice::Never => 2,
+    };
+    COLOR_CHOICE.store(raw, Ordering::Relaxed);
+}
+
+/// The current process-wide color choice
+pub fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+/// Central color policy. An explicit `--color` wins; otherwise a non-empty
+/// NO_COLOR disables color, a CLICOLOR_FORCE other than "0" forces it, and
+/// failing both, color follows whether the stream is a terminal.
+fn color_policy(is_tty: bool) -> bool {
+    match color_choice() {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            let set = |name| std::env::var_os(name).filter(|v| !v.is_empty());
+            if set("NO_COLOR").is_some() {
+                false
+            } else if set("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+                true
+            } else {
+                is_tty
+            }
+        }
+    }
+}
+
+/// Whether colored output should be written to stdout
+pub fn color_enabled() -> bool {
+    color_policy(std::io::stdout().is_terminal())
+}
+
+/// Whether colored output should be written to stderr (trace, debugger)
+pub fn stderr_color_enabled() -> bool {
+    color_policy(std::io::stderr().is_terminal())
+}
+
+/// Remove SGR (color/style) sequences only, keeping hyperlinks and
+/// graphics escapes intact
+pub fn strip_sgr(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find("\x1b[") {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 2..];
+        let end = after
+            .find(|c: char| !(c.is_ascii_digit() || c == ';'))
+            .unwrap_or(after.len());
+        if after[end..].starts_with('m') {
+            rest = &after[end + 1..];
+        } else {
+            out.push_str(&rest[pos..pos + 2]);
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Apply the color policy to text bound for stdout
+pub fn styled(s: String) -> String {
+    if color_enabled() {
+        s
+    } else {
+        strip_sgr(&s)
     }
 }
 
+/// Print a line to stderr, dropping its styling when color is disabled
+pub fn eprintln_styled(s: &str) {
+    if stderr_color_enabled() {
+        eprintln!("{}", s);
+    } else {
+        eprintln!("{}", strip_sgr(s));
+    }
+}
+
+/// SGR code for a color/style name (`red`, `bright-blue`, `bold`, ...)
+pub fn sgr_code(name: &str) -> Option<&'static str> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "reset" => "0",
+        "bold" => "1",
+        "dim" => "2",
+        "italic" => "3",
+        "underline" => "4",
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        "gray" | "grey" | "bright-black" => "90",
+        "bright-red" => "91",
+        "bright-green" => "92",
+        "bright-yellow" => "93",
+        "bright-blue" => "94",
+        "bright-magenta" => "95",
+        "bright-cyan" => "96",
+        "bright-white" => "97",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Sparkline bars, lowest first
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One bar per number, scaled from the lowest to the highest; past
+/// `max_width` numbers only the latest are drawn
+pub fn sparkline(numbers: &[f64], max_width: usize) -> String {
+    let numbers = &numbers[numbers.len().saturating_sub(max_width)..];
+    let low = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let top = (SPARK_BARS.len() - 1) as f64;
+    numbers
+        .iter()
+        .map(|n| {
+            // A flat line sits in the middle
+            let level = if high > low {
+                ((n - low) / (high - low) * top).round()
+            } else {
+                top / 2.0
+            };
+            SPARK_BARS[level as usize]
+        })
+        .collect()
+}
+
+/// Bytes as `hexdump -C` shows them: an offset, the bytes in hex, and
+/// the printable ones as text, then the length. Lines hold 16 bytes, or 8
+/// when `max_width` is too narrow for 16.
+pub fn hexdump(data: &[u8], max_width: usize) -> String {
+    let per_line = if max_width >= 78 { 16 } else { 8 };
+    let mut out = String::new();
+    for (n, chunk) in data.chunks(per_line).enumerate() {
+        out.push_str(&format!("{:08x}  ", n * per_line));
+        for i in 0..per_line {
+            match chunk.get(i) {
+                Some(b) => out.push_str(&format!("{:02x} ", b)),
+                None => out.push_str("   "),
+            }
+            if i == 7 && per_line == 16 {
+                out.push(' ');
+            }
+        }
+        let text: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!(" |{}|\n", text));
+    }
+    if !data.is_empty() {
+        out.push_str(&format!("{:08x}", data.len()));
+    }
+    out
+}
+
+/// Cells in a gauge's bar when the terminal has room
+const GAUGE_CELLS: usize = 10;
+
+/// The left-aligned blocks for one to seven eighths of a cell
+const GAUGE_EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// A bar filled to `value` out of `max`, and the percentage:
+/// `███████▍░░ 74%`. With color it's green, yellow from 70%, and red from
+/// 90%; the bar shrinks to fit `max_width`
+pub fn gauge(value: f64, max: f64, max_width: usize) -> String {
+    let fraction = (value / max).clamp(0.0, 1.0);
+    let percent = format!(" {:.0}%", fraction * 100.0);
+    let cells = GAUGE_CELLS
+        .min(max_width.saturating_sub(percent.len()))
+        .max(1);
+
+    // Eighths of a cell, so a small change still shows
+    let eighths = (fraction * (cells * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if let Some(partial) = (eighths % 8).checked_sub(1) {
+        bar.push(GAUGE_EIGHTHS[partial]);
+    }
+    let empty = cells - bar.chars().count();
+    let bar = if color_enabled() {
+        let code = match fraction {
+            f if f >= 0.9 => "31",
+            f if f >= 0.7 => "33",
+            _ => "32",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, bar)
+    } else {
+        bar
+    };
+    format!("{}{}{}", bar, "░".repeat(empty), percent)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1117,4 +1524,98 @@ mod tests {
     fn test_truncate_str_empty() {
         assert_eq!(truncate_str("", 5), "");
     }
+
+    #[test]
+    fn test_truncate_str_multibyte() {
+        // Truncation counts columns, never splits a character
+        assert_eq!(truncate_str("日本語テキスト", 5), "日本…");
+        assert_eq!(truncate_str("héllo wörld", 6), "héllo…");
+    }
+
+    #[test]
+    fn test_strip_ansi_csi_and_osc() {
+        assert_eq!(strip_ansi("\x1b[1;31mred\x1b[0m"), "red");
+        assert_eq!(strip_ansi("\x1b]8;;http://x\x1b\\link\x1b]8;;\x07"), "link");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_display_width_ignores_escapes() {
+        assert_eq!(display_width("\x1b[32mok\x1b[0m"), 2);
+        assert_eq!(display_width("日本"), 4);
+    }
+
+    #[test]
+    fn test_format_table_aligns_colored_cells() {
+        let table = Value::Table {
+            columns: vec!["name".to_string()],
+            rows: vec![
+                vec![Value::Literal("\x1b[31mab\x1b[0m".to_string())],
+                vec![Value::Literal("abcd".to_string())],
+            ],
+        };
+        let result = strip_ansi(&format_value(&table, 80));
+        let widths: Vec<usize> = result
+            .lines()
+            .filter(|l| l.starts_with('│'))
+            .map(display_width)
+            .collect();
+        assert_eq!(widths.len(), 3);
+        assert!(widths.iter().all(|w| *w == widths[0]), "{:?}", widths);
+    }
+
+    #[test]
+    fn test_strip_sgr_keeps_hyperlinks() {
+        let s = "\x1b[1;36mkey\x1b[0m \x1b]8;;http://x\x1b\\a\x1b]8;;\x1b\\";
+        assert_eq!(strip_sgr(s), "key \x1b]8;;http://x\x1b\\a\x1b]8;;\x1b\\");
+    }
+
+    #[test]
+    fn test_color_choice_parse() {
+        assert_eq!(ColorChoice::parse("never"), Some(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("auto"), Some(ColorChoice::Auto));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn test_structured_diff_records() {
+        let mut a = IndexMap::new();
+        a.insert("name".to_string(), Value::Literal("a".into()));
+        a.insert("tmp".to_string(), Value::Int(1));
+        let mut b = IndexMap::new();
+        b.insert("name".to_string(), Value::Literal("b".into()));
+        b.insert("age".to_string(), Value::Int(3));
+        let diff = strip_ansi(&format_structured_diff(&Value::Map(a), &Value::Map(b)).unwrap());
+        assert_eq!(diff, "~name: \"a\" → \"b\", +age: 3, -tmp");
+    }
+
+    #[test]
+    fn test_structured_diff_lists_and_unchanged() {
+        let a = Value::List(vec![Value::Int(1), Value::Int(2)]);
+        let b = Value::List(vec![Value::Int(1), Value::Int(5), Value::Int(6)]);
+        let diff = strip_ansi(&format_structured_diff(&a, &b).unwrap());
+        assert_eq!(diff, "~[1]: 2 → 5, +1 item");
+        assert!(format_structured_diff(&a, &a).is_none());
+        assert!(format_structured_diff(&Value::Int(1), &Value::Int(2)).is_none());
+    }
+
+    #[test]
+    fn test_sparkline_keeps_the_latest_that_fit() {
+        assert_eq!(
+            sparkline(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], 80),
+            "▁▂▃▄▅▆▇█"
+        );
+        assert_eq!(sparkline(&[9.0, 1.0, 5.0, 3.0], 2), "█▁");
+        assert_eq!(sparkline(&[2.0, 2.0], 80), "▄▄");
+        assert_eq!(sparkline(&[], 80), "");
+    }
+
+    #[test]
+    fn test_gauge_fills_in_eighths_and_fits_the_width() {
+        assert_eq!(strip_ansi(&gauge(74.0, 100.0, 80)), "███████▍░░ 74%");
+        assert_eq!(strip_ansi(&gauge(-3.0, 10.0, 80)), "░░░░░░░░░░ 0%");
+        assert_eq!(strip_ansi(&gauge(15.0, 10.0, 80)), "██████████ 100%");
+        assert_eq!(strip_ansi(&gauge(1.0, 2.0, 8)), "██░░ 50%");
+    }
 }
@@ -1,3 +1,5 @@
+use super::helpers::{deep_key, Num};
+use super::math::{num_add, num_div, num_max, num_min};
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
 
@@ -8,16 +10,9 @@ impl Evaluator {
             .pop()
             .ok_or_else(|| EvalError::StackUnderflow("sum requires a list".into()))?;
 
-        let total: f64 = match val {
-            Value::List(items) => items
-                .iter()
-                .filter_map(|v| match v {
-                    Value::Number(n) => Some(*n),
-                    Value::Int(i) => Some(*i as f64),
-                    Value::Literal(s) | Value::Output(s) => s.trim().parse().ok(),
-                    _ => None,
-                })
-                .sum(),
+        // Exact over Ints and BigInts, so big IDs and checksums add up
+        let total = match val {
+            Value::List(items) => items.iter().filter_map(Num::of).fold(Num::Int(0), num_add),
             _ => {
                 return Err(EvalError::TypeError {
                     expected: "List".into(),
@@ -26,7 +21,7 @@ impl Evaluator {
             }
         };
 
-        self.stack.push(Value::Number(total));
+        self.stack.push(total.into_value());
         self.last_exit_code = 0;
         Ok(())
     }
@@ -39,16 +34,9 @@ impl Evaluator {
 
         let (total, count) = match val {
             Value::List(items) => {
-                let nums: Vec<f64> = items
-                    .iter()
-                    .filter_map(|v| match v {
-                        Value::Number(n) => Some(*n),
-                        Value::Int(i) => Some(*i as f64),
-                        Value::Literal(s) | Value::Output(s) => s.trim().parse().ok(),
-                        _ => None,
-                    })
-                    .collect();
-                (nums.iter().sum::<f64>(), nums.len())
+                let nums: Vec<Num> = items.iter().filter_map(Num::of).collect();
+                let count = nums.len();
+                (nums.into_iter().fold(Num::Int(0), num_add), count)
             }
             _ => {
                 return Err(EvalError::TypeError {
@@ -58,8 +46,12 @@ impl Evaluator {
             }
         };
 
-        let avg = if count > 0 { total / count as f64 } else { 0.0 };
-        self.stack.push(Value::Number(avg));
+        let avg = if count > 0 {
+            num_div(total, Num::Int(count as i64)).into_value()
+        } else {
+            Value::Number(0.0)
+        };
+        self.stack.push(avg);
         self.last_exit_code = 0;
         Ok(())
     }
@@ -70,16 +62,9 @@ impl Evaluator {
             .pop()
             .ok_or_else(|| EvalError::StackUnderflow("min requires a list".into()))?;
 
+        // Exact over Ints and BigInts, like sum
         let result = match val {
-            Value::List(items) => items
-                .iter()
-                .filter_map(|v| match v {
-                    Value::Number(n) => Some(*n),
-                    Value::Int(i) => Some(*i as f64),
-                    Value::Literal(s) | Value::Output(s) => s.trim().parse().ok(),
-                    _ => None,
-                })
-                .fold(f64::INFINITY, f64::min),
+            Value::List(items) => items.iter().filter_map(Num::of).reduce(num_min),
             _ => {
                 return Err(EvalError::TypeError {
                     expected: "List".into(),
@@ -88,11 +73,7 @@ impl Evaluator {
             }
         };
 
-        if result.is_infinite() {
-            self.stack.push(Value::Nil);
-        } else {
-            self.stack.push(Value::Number(result));
-        }
+        self.stack.push(result.map_or(Value::Nil, Num::into_value));
         self.last_exit_code = 0;
         Ok(())
     }
@@ -103,16 +84,9 @@ impl Evaluator {
             .pop()
             .ok_or_else(|| EvalError::StackUnderflow("max requires a list".into()))?;
 
+        // Exact over Ints and BigInts, like sum
         let result = match val {
-            Value::List(items) => items
-                .iter()
-                .filter_map(|v| match v {
-                    Value::Number(n) => Some(*n),
-                    Value::Int(i) => Some(*i as f64),
-                    Value::Literal(s) | Value::Output(s) => s.trim().parse().ok(),
-                    _ => None,
-                })
-                .fold(f64::NEG_INFINITY, f64::max),
+            Value::List(items) => items.iter().filter_map(Num::of).reduce(num_max),
             _ => {
                 return Err(EvalError::TypeError {
                     expected: "List".into(),
@@ -121,11 +95,7 @@ impl Evaluator {
             }
         };
 
-        if result.is_infinite() {
-            self.stack.push(Value::Nil);
-        } else {
-            self.stack.push(Value::Number(result));
-        }
+        self.stack.push(result.map_or(Value::Nil, Num::into_value));
         self.last_exit_code = 0;
         Ok(())
     }
@@ -332,10 +302,7 @@ impl Evaluator {
                 let mut seen = HashSet::new();
                 let unique: Vec<Value> = items
                     .into_iter()
-                    .filter(|v| {
-                        let key = v.as_arg().unwrap_or_default();
-                        seen.insert(key)
-                    })
+                    .filter(|v| seen.insert(deep_key(v)))
                     .collect();
                 self.stack.push(Value::List(unique));
             }
@@ -343,14 +310,7 @@ impl Evaluator {
                 let mut seen = HashSet::new();
                 let unique: Vec<Vec<Value>> = rows
                     .into_iter()
-                    .filter(|row| {
-                        let key: String = row
-                            .iter()
-                            .filter_map(|v| v.as_arg())
-                            .collect::<Vec<_>>()
-                            .join("\t");
-                        seen.insert(key)
-                    })
+                    .filter(|row| seen.insert(deep_key(&Value::List(row.clone()))))
                     .collect();
                 self.stack.push(Value::Table {
                     columns,
@@ -527,7 +487,7 @@ impl Evaluator {
         // Count occurrences
         let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
         for item in &items {
-            let key = item.as_arg().unwrap_or_default();
+            let key = deep_key(item);
             *counts.entry(key).or_insert(0) += 1;
         }
 
@@ -536,7 +496,7 @@ impl Evaluator {
         let duplicates: Vec<Value> = items
             .into_iter()
             .filter(|item| {
-                let key = item.as_arg().unwrap_or_default();
+                let key = deep_key(item);
                 if counts.get(&key).copied().unwrap_or(0) > 1 && !seen.contains(&key) {
                     seen.insert(key);
                     true
@@ -550,4 +510,64 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    /// freq: list freq -> Table (value, count), most frequent first
+    /// Takes a List, spread items, or lines of text - `sort | uniq -c | sort -rn`
+    /// in one word. Values that tie keep the order they first appeared in.
+    pub(crate) fn builtin_freq(&mut self) -> Result<(), EvalError> {
+        let items = match self.stack.pop() {
+            Some(Value::List(items)) => items,
+            Some(Value::Literal(s)) | Some(Value::Output(s))
+                if !self.stack.iter().any(Value::is_marker) =>
+            {
+                s.lines()
+                    .map(|line| Value::Literal(line.to_string()))
+                    .collect()
+            }
+            Some(top) => {
+                // Spread items, down to the marker
+                self.stack.push(top);
+                let start = match self.stack.iter().rposition(Value::is_marker) {
+                    Some(i) => i,
+                    None => {
+                        let top = self.stack.last().map(Value::type_name).unwrap_or("nothing");
+                        return Err(EvalError::TypeError {
+                            expected: "List or spread items (freq)".into(),
+                            got: top.to_string(),
+                        });
+                    }
+                };
+                let items = self.stack.split_off(start + 1);
+                self.stack.pop();
+                items
+            }
+            None => return Err(EvalError::StackUnderflow("freq requires a list".into())),
+        };
+
+        let mut index: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        let mut rows: Vec<(Value, i64)> = Vec::new();
+        for item in items {
+            if item.is_nil() {
+                continue;
+            }
+            match index.entry(deep_key(&item)) {
+                std::collections::hash_map::Entry::Occupied(e) => rows[*e.get()].1 += 1,
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    e.insert(rows.len());
+                    rows.push((item, 1));
+                }
+            }
+        }
+        rows.sort_by_key(|row| std::cmp::Reverse(row.1));
+
+        self.stack.push(Value::Table {
+            columns: vec!["value".to_string(), "count".to_string()],
+            rows: rows
+                .into_iter()
+                .map(|(value, count)| vec![value, Value::Int(count)])
+                .collect(),
+        });
+        self.last_exit_code = 0;
+        Ok(())
+    }
 }
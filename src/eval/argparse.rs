@@ -0,0 +1,480 @@
+//! Command-line parsing for scripts: `args` and `parse-args`
+//!
+//! `hsab script.hsab a b` passes everything after the script path to the
+//! script; `args` pushes it as a list. `parse-args` turns it into a record
+//! according to a spec:
+//!
+//! ```hsab
+//! { name greet about "Greet someone" positional "NAME..."
+//!   flags { loud false
+//!           count { default 1 short c help "How many times" }
+//!           lang { required true help "Language code" } } } parse-args
+//! ```
+//!
+//! Each flag's type comes from its default: booleans are switches (with a
+//! `--no-` form), ints and floats are checked, lists collect repeated uses,
+//! and anything else is a string. The result has one entry per flag plus
+//! `args`, the positional arguments. `--help` prints generated usage.
+//!
+//! In a script, `--help` and bad usage end the script (exit 0 and 2), as
+//! they would for any command-line tool. Elsewhere the help text, or a
+//! `usage` error with code 2, is left on the stack.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Exit code for bad usage
+const USAGE_EXIT: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Switch,
+    Int,
+    Float,
+    Text,
+    Multi,
+}
+
+#[derive(Debug, Clone)]
+struct Flag {
+    name: String,
+    kind: Kind,
+    default: Value,
+    short: Option<char>,
+    help: Option<String>,
+    required: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ArgSpec {
+    name: String,
+    about: Option<String>,
+    positional: Option<String>,
+    flags: Vec<Flag>,
+}
+
+enum Parsed {
+    Options(IndexMap<String, Value>),
+    Help,
+}
+
+fn spec_error(msg: impl Into<String>) -> EvalError {
+    EvalError::ExecError(format!("parse-args: {}", msg.into()))
+}
+
+fn spec_text(map: &IndexMap<String, Value>, key: &str) -> Option<String> {
+    map.get(key).and_then(Value::as_arg)
+}
+
+impl Flag {
+    fn from_spec(name: &str, spec: &Value) -> Result<Flag, EvalError> {
+        let mut flag = Flag {
+            name: name.to_string(),
+            kind: Kind::Text,
+            default: Value::Nil,
+            short: None,
+            help: None,
+            required: false,
+        };
+        let explicit_type = match spec {
+            Value::Map(opts) => {
+                for key in opts.keys() {
+                    if !["default", "short", "help", "required", "type"].contains(&key.as_str()) {
+                        return Err(spec_error(format!(
+                            "flag '{}': unknown key '{}'",
+                            name, key
+                        )));
+                    }
+                }
+                flag.default = opts.get("default").cloned().unwrap_or(Value::Nil);
+                flag.help = spec_text(opts, "help");
+                flag.required = matches!(opts.get("required"), Some(Value::Bool(true)));
+                if let Some(short) = spec_text(opts, "short") {
+                    let mut chars = short.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if c != '-' => flag.short = Some(c),
+                        _ => {
+                            return Err(spec_error(format!(
+                                "flag '{}': short must be one character",
+                                name
+                            )))
+                        }
+                    }
+                }
+                spec_text(opts, "type")
+            }
+            other => {
+                flag.default = other.clone();
+                None
+            }
+        };
+        flag.kind = match explicit_type.as_deref() {
+            Some("bool") => Kind::Switch,
+            Some("int") => Kind::Int,
+            Some("float") => Kind::Float,
+            Some("string") => Kind::Text,
+            Some("list") => Kind::Multi,
+            Some(other) => {
+                return Err(spec_error(format!(
+                    "flag '{}': unknown type '{}'",
+                    name, other
+                )))
+            }
+            None => match flag.default {
+                Value::Bool(_) => Kind::Switch,
+                Value::Int(_) => Kind::Int,
+                Value::Number(_) => Kind::Float,
+                Value::List(_) => Kind::Multi,
+                _ => Kind::Text,
+            },
+        };
+        Ok(flag)
+    }
+
+    /// Value before any arguments are seen
+    fn initial(&self) -> Value {
+        match (&self.kind, &self.default) {
+            (Kind::Switch, Value::Nil) => Value::Bool(false),
+            (Kind::Multi, Value::Nil) => Value::List(Vec::new()),
+            (_, default) => default.clone(),
+        }
+    }
+
+    /// Convert one argument for this flag
+    fn convert(&self, text: &str) -> Result<Value, String> {
+        match self.kind {
+            Kind::Switch => match text {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => Err(format!(
+                    "--{} expects true or false, got '{}'",
+                    self.name, text
+                )),
+            },
+            Kind::Int => text
+                .parse()
+                .map(Value::Int)
+                .map_err(|_| format!("--{} expects an integer, got '{}'", self.name, text)),
+            Kind::Float => text
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("--{} expects a number, got '{}'", self.name, text)),
+            Kind::Text | Kind::Multi => Ok(Value::Literal(text.to_string())),
+        }
+    }
+
+    fn placeholder(&self) -> &'static str {
+        match self.kind {
+            Kind::Switch => "",
+            Kind::Int => " <INT>",
+            Kind::Float => " <NUM>",
+            Kind::Text => " <VALUE>",
+            Kind::Multi => " <VALUE>...",
+        }
+    }
+}
+
+impl ArgSpec {
+    fn from_value(spec: Value, default_name: String) -> Result<ArgSpec, EvalError> {
+        let Value::Map(map) = spec else {
+            return Err(spec_error(format!(
+                "spec must be a record, got {}",
+                spec.type_name()
+            )));
+        };
+        let mut flags = Vec::new();
+        match map.get("flags") {
+            Some(Value::Map(defs)) => {
+                for (name, def) in defs {
+                    if name == "args" || name == "help" {
+                        return Err(spec_error(format!("'{}' is reserved", name)));
+                    }
+                    flags.push(Flag::from_spec(name, def)?);
+                }
+            }
+            Some(other) => {
+                return Err(spec_error(format!(
+                    "flags must be a record, got {}",
+                    other.type_name()
+                )))
+            }
+            None => {}
+        }
+        let mut shorts = HashSet::new();
+        for short in flags.iter().filter_map(|f| f.short) {
+            if !shorts.insert(short) {
+                return Err(spec_error(format!("short flag -{} used twice", short)));
+            }
+        }
+        Ok(ArgSpec {
+            name: spec_text(&map, "name").unwrap_or(default_name),
+            about: spec_text(&map, "about").or_else(|| spec_text(&map, "description")),
+            positional: spec_text(&map, "positional"),
+            flags,
+        })
+    }
+
+    fn usage_line(&self) -> String {
+        let mut line = format!("Usage: {}", self.name);
+        if !self.flags.is_empty() {
+            line.push_str(" [OPTIONS]");
+        }
+        line.push(' ');
+        line.push_str(self.positional.as_deref().unwrap_or("[ARGS...]"));
+        line
+    }
+
+    fn help(&self) -> String {
+        let mut out = self.usage_line();
+        if let Some(about) = &self.about {
+            out.push_str("\n\n");
+            out.push_str(about);
+        }
+        let help_short = if self.takes_short_help() {
+            "-h, "
+        } else {
+            "    "
+        };
+        let mut rows: Vec<(String, String)> = Vec::new();
+        for flag in &self.flags {
+            let left = match flag.short {
+                Some(c) => format!("-{}, --{}{}", c, flag.name, flag.placeholder()),
+                None => format!("    --{}{}", flag.name, flag.placeholder()),
+            };
+            let mut right = flag.help.clone().unwrap_or_default();
+            let note = if flag.required {
+                Some("required".to_string())
+            } else {
+                match (&flag.kind, flag.initial()) {
+                    (Kind::Switch, Value::Bool(false)) | (_, Value::Nil) => None,
+                    (Kind::Multi, Value::List(items)) if items.is_empty() => None,
+                    (_, default) => default.as_arg().map(|d| format!("default: {}", d)),
+                }
+            };
+            if let Some(note) = note {
+                if !right.is_empty() {
+                    right.push(' ');
+                }
+                right.push_str(&format!("({})", note));
+            }
+            rows.push((left, right));
+        }
+        rows.push((
+            format!("{}--help", help_short),
+            "Show this help".to_string(),
+        ));
+        let width = rows
+            .iter()
+            .map(|(l, _)| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        out.push_str("\n\nOptions:");
+        for (left, right) in rows {
+            let line = format!("  {:<width$}  {}", left, right, width = width);
+            out.push('\n');
+            out.push_str(line.trim_end());
+        }
+        out
+    }
+
+    /// `-h` means help unless a flag claimed it
+    fn takes_short_help(&self) -> bool {
+        self.flags.iter().all(|f| f.short != Some('h'))
+    }
+
+    fn long(&self, name: &str) -> Option<&Flag> {
+        self.flags.iter().find(|f| f.name == name)
+    }
+
+    fn parse(&self, argv: &[String]) -> Result<Parsed, String> {
+        let mut values: IndexMap<String, Value> = self
+            .flags
+            .iter()
+            .map(|f| (f.name.clone(), f.initial()))
+            .collect();
+        let mut seen = HashSet::new();
+        let mut positional = Vec::new();
+        let mut set = |flag: &Flag, value: Value, seen: &mut HashSet<String>| {
+            if flag.kind == Kind::Multi {
+                // Repeated uses accumulate, replacing the default
+                if seen.insert(flag.name.clone()) {
+                    values.insert(flag.name.clone(), Value::List(Vec::new()));
+                }
+                if let Some(Value::List(items)) = values.get_mut(&flag.name) {
+                    items.push(value);
+                }
+            } else {
+                seen.insert(flag.name.clone());
+                values.insert(flag.name.clone(), value);
+            }
+        };
+
+        let mut iter = argv.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                positional.extend(iter.by_ref().cloned());
+                break;
+            }
+            if arg == "--help" || (arg == "-h" && self.takes_short_help()) {
+                return Ok(Parsed::Help);
+            }
+            if let Some(body) = arg.strip_prefix("--") {
+                let (name, inline) = match body.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (body, None),
+                };
+                if let Some(flag) = self.long(name) {
+                    let value = match (flag.kind, inline) {
+                        (Kind::Switch, None) => Value::Bool(true),
+                        (_, Some(text)) => flag.convert(text)?,
+                        (_, None) => match iter.next() {
+                            Some(text) => flag.convert(text)?,
+                            None => return Err(format!("--{} requires a value", name)),
+                        },
+                    };
+                    set(flag, value, &mut seen);
+                    continue;
+                }
+                match (name.strip_prefix("no-").and_then(|n| self.long(n)), inline) {
+                    (Some(flag), None) if flag.kind == Kind::Switch => {
+                        set(flag, Value::Bool(false), &mut seen);
+                        continue;
+                    }
+                    _ => return Err(format!("unknown option '--{}'", name)),
+                }
+            }
+            // A lone "-" and negative numbers are positional
+            let is_short = arg.len() > 1 && arg.starts_with('-') && arg.parse::<f64>().is_err();
+            if !is_short {
+                positional.push(arg.clone());
+                continue;
+            }
+            let cluster = &arg[1..];
+            for (i, c) in cluster.char_indices() {
+                let Some(flag) = self.flags.iter().find(|f| f.short == Some(c)) else {
+                    return Err(format!("unknown option '-{}'", c));
+                };
+                if flag.kind == Kind::Switch {
+                    set(flag, Value::Bool(true), &mut seen);
+                    continue;
+                }
+                // The rest of the cluster, or the next argument, is the value
+                let rest = &cluster[i + c.len_utf8()..];
+                let text = if !rest.is_empty() {
+                    rest
+                } else {
+                    match iter.next() {
+                        Some(text) => text.as_str(),
+                        None => return Err(format!("-{} requires a value", c)),
+                    }
+                };
+                let value = flag.convert(text)?;
+                set(flag, value, &mut seen);
+                break;
+            }
+        }
+
+        if let Some(flag) = self
+            .flags
+            .iter()
+            .find(|f| f.required && !seen.contains(&f.name))
+        {
+            return Err(format!("missing required option --{}", flag.name));
+        }
+        values.insert(
+            "args".to_string(),
+            Value::List(positional.into_iter().map(Value::Literal).collect()),
+        );
+        Ok(Parsed::Options(values))
+    }
+}
+
+impl Evaluator {
+    /// Set the arguments the running script was given
+    pub fn set_script_args(&mut self, args: Vec<String>) {
+        self.script_args = args;
+    }
+
+    /// args: Push the script's arguments as a list
+    pub(crate) fn builtin_args(&mut self) -> Result<(), EvalError> {
+        let args = self
+            .script_args
+            .iter()
+            .cloned()
+            .map(Value::Literal)
+            .collect();
+        self.stack.push(Value::List(args));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// parse-args: SPEC [ARGV] -> record of options
+    ///
+    /// Without an ARGV list, the script's own arguments are parsed.
+    pub(crate) fn builtin_parse_args(&mut self) -> Result<(), EvalError> {
+        let argv = match self.stack.last() {
+            Some(Value::List(_)) => self
+                .pop_list("parse-args")?
+                .iter()
+                .filter_map(Value::as_arg)
+                .collect(),
+            _ => self.script_args.clone(),
+        };
+        let spec = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow("parse-args requires a spec record".into()))?;
+        let script_name = self.script_location.as_ref().map(|loc| {
+            std::path::Path::new(&loc.path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| loc.path.clone())
+        });
+        let in_script = script_name.is_some();
+        let spec = ArgSpec::from_value(spec, script_name.unwrap_or_else(|| "hsab".into()))?;
+
+        match spec.parse(&argv) {
+            Ok(Parsed::Options(values)) => {
+                self.stack.push(Value::Map(values));
+                self.last_exit_code = 0;
+            }
+            Ok(Parsed::Help) => {
+                if in_script {
+                    println!("{}", spec.help());
+                    let _ = std::io::stdout().flush();
+                    self.last_exit_code = 0;
+                    return Err(EvalError::Exit(0));
+                }
+                self.stack.push(Value::Output(format!("{}\n", spec.help())));
+                self.last_exit_code = 0;
+            }
+            Err(message) => {
+                eprintln!(
+                    "{}: {}\n{}\nTry '{} --help' for more information.",
+                    spec.name,
+                    message,
+                    spec.usage_line(),
+                    spec.name
+                );
+                if in_script {
+                    self.last_exit_code = USAGE_EXIT;
+                    return Err(EvalError::Exit(USAGE_EXIT));
+                }
+                self.stack.push(Value::Error {
+                    kind: "usage".to_string(),
+                    message,
+                    code: Some(USAGE_EXIT),
+                    source: None,
+                    command: Some(spec.name),
+                });
+                self.last_exit_code = USAGE_EXIT;
+            }
+        }
+        Ok(())
+    }
+}
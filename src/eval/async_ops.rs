@@ -3,12 +3,12 @@
 //! Provides futures, parallel execution with limits, and delays.
 //! Note: `timeout` is in process.rs, `retry` is in combinators.rs
 
-use super::{EvalError, Evaluator};
+use super::{units, EvalError, Evaluator};
 use crate::ast::{Expr, FutureState, Value};
 use crate::util::lock_or_recover;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 impl Evaluator {
     // === Core Async Operations ===
@@ -243,24 +243,51 @@ impl Evaluator {
     // === Delay Operations ===
 
     /// delay: ms delay -> ()
-    /// Sleep for specified milliseconds (blocking)
+    /// Sleep for specified milliseconds (blocking), or a duration like "2s"
     pub(crate) fn builtin_delay(&mut self, args: &[String]) -> Result<(), EvalError> {
         if args.is_empty() {
             return Err(EvalError::ExecError("delay requires milliseconds".into()));
         }
 
-        let ms: u64 = args[0].parse().map_err(|_| EvalError::TypeError {
-            expected: "integer milliseconds".into(),
-            got: args[0].clone(),
-        })?;
+        let delay = millis_arg(&args[0])?;
+        self.pause(delay)?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
 
-        thread::sleep(Duration::from_millis(ms));
+    /// sleep: duration sleep -> ()
+    /// Sleep without spawning a process. The duration is in seconds and may
+    /// be fractional, with an optional ms, s, m, h, or d suffix.
+    pub(crate) fn builtin_sleep(&mut self) -> Result<(), EvalError> {
+        let seconds = self.pop_seconds("sleep")?;
+        let duration = Duration::try_from_secs_f64(seconds).map_err(|_| EvalError::TypeError {
+            expected: "duration (sleep)".into(),
+            got: seconds.to_string(),
+        })?;
+        self.pause(duration)?;
         self.last_exit_code = 0;
         Ok(())
     }
 
+    /// Sleep for `duration`, stopping early with an error if the eval is
+    /// cancelled or runs out of time
+    pub(crate) fn pause(&self, duration: Duration) -> Result<(), EvalError> {
+        const SLICE: Duration = Duration::from_millis(20);
+        // Too far off to represent means forever
+        let deadline = Instant::now().checked_add(duration);
+        loop {
+            self.check_cancelled()?;
+            self.check_deadline()?;
+            let left = deadline.map_or(SLICE, |d| d.saturating_duration_since(Instant::now()));
+            if left.is_zero() {
+                return Ok(());
+            }
+            thread::sleep(left.min(SLICE));
+        }
+    }
+
     /// delay-async: ms delay-async -> Future
-    /// Return a Future that resolves after the delay
+    /// Return a Future that resolves after the delay (ms, or a duration)
     pub(crate) fn builtin_delay_async(&mut self, args: &[String]) -> Result<(), EvalError> {
         if args.is_empty() {
             return Err(EvalError::ExecError(
@@ -268,10 +295,7 @@ impl Evaluator {
             ));
         }
 
-        let ms: u64 = args[0].parse().map_err(|_| EvalError::TypeError {
-            expected: "integer milliseconds".into(),
-            got: args[0].clone(),
-        })?;
+        let delay = millis_arg(&args[0])?;
 
         // Generate unique ID
         self.future_counter += 1;
@@ -283,7 +307,7 @@ impl Evaluator {
 
         // Spawn thread that sleeps then completes
         let handle = thread::spawn(move || {
-            thread::sleep(Duration::from_millis(ms));
+            thread::sleep(delay);
             let mut guard = lock_or_recover(&state_clone);
             if matches!(*guard, FutureState::Pending) {
                 *guard = FutureState::Completed(Box::new(Value::Nil));
@@ -427,7 +451,7 @@ impl Evaluator {
                 // Evaluate the block to produce a list of values
                 let saved_stack = std::mem::take(&mut self.stack);
                 self.eval_block(&exprs)?;
-                std::mem::replace(&mut self.stack, saved_stack)
+                std::mem::replace(&mut self.stack, saved_stack).into_vec()
             }
             _ => {
                 return Err(EvalError::TypeError {
@@ -995,3 +1019,13 @@ impl Evaluator {
         Ok(())
     }
 }
+
+/// Read `delay`'s argument: milliseconds, or a duration like "2s"
+fn millis_arg(text: &str) -> Result<Duration, EvalError> {
+    units::parse_millis(text)
+        .and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok())
+        .ok_or_else(|| EvalError::TypeError {
+            expected: "milliseconds".into(),
+            got: text.to_string(),
+        })
+}
@@ -0,0 +1,204 @@
+//! Audit log of spawned processes, usually configured from `hsab.toml`
+//!
+//! When enabled, every external process hsab starts is recorded once it
+//! finishes: plain commands, pipes, redirects, background jobs, `parallel`,
+//! `fork`, `timeout`, `subst`, `fifo`, and the helpers builtins run (the
+//! editor, pagers, `man`, `git`, `ps`, cloud CLIs). Each goes through
+//! `start_process`, which also checks it against the exec policy. Each
+//! record is one line of JSON:
+//!
+//! ```json
+//! {"time":"2026-01-02T03:04:05.678+00:00","via":"command","argv":["git","status"],
+//!  "cwd":"/src/app","env":{"GIT_PAGER":"cat"},"duration_ms":12,"exit":0}
+//! ```
+//!
+//! `env` holds the variables hsab set, changed (`"NAME":"value"`) or removed
+//! (`"NAME":null`) since the log was enabled, which is what the process saw
+//! beyond the environment hsab was started with. `exit` is null when the
+//! process could not be started. Records are appended to `file` and, on Unix,
+//! sent to syslog when `syslog` is set.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use super::{EvalError, Evaluator};
+
+/// Where audit records go
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Append one JSON line per process to this file
+    pub file: Option<PathBuf>,
+    /// Also send each record to syslog (Unix only)
+    pub syslog: bool,
+}
+
+impl AuditConfig {
+    /// Check if records go anywhere
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some() || self.syslog
+    }
+}
+
+/// An enabled audit log and the environment it compares against
+#[derive(Debug)]
+pub(crate) struct AuditLog {
+    config: AuditConfig,
+    base_env: HashMap<OsString, OsString>,
+}
+
+impl AuditLog {
+    /// Variables set, changed, or removed since the log was enabled
+    fn env_delta(&self) -> BTreeMap<String, Option<String>> {
+        let mut delta = BTreeMap::new();
+        let current: HashMap<OsString, OsString> = std::env::vars_os().collect();
+        for (name, value) in &current {
+            if self.base_env.get(name) != Some(value) {
+                delta.insert(
+                    name.to_string_lossy().into_owned(),
+                    Some(value.to_string_lossy().into_owned()),
+                );
+            }
+        }
+        for name in self.base_env.keys() {
+            if !current.contains_key(name) {
+                delta.insert(name.to_string_lossy().into_owned(), None);
+            }
+        }
+        delta
+    }
+
+    fn write(&self, line: &str) {
+        if let Some(path) = &self.config.file {
+            let appended = std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut f| f.write_all(format!("{}\n", line).as_bytes()));
+            if let Err(e) = appended {
+                eprintln!("hsab: audit: {}: {}", path.display(), e);
+            }
+        }
+        #[cfg(unix)]
+        if self.config.syslog {
+            if let Ok(message) = std::ffi::CString::new(line) {
+                unsafe {
+                    libc::syslog(
+                        libc::LOG_USER | libc::LOG_INFO,
+                        c"%s".as_ptr(),
+                        message.as_ptr(),
+                    )
+                };
+            }
+        }
+    }
+}
+
+/// One process being audited. The record is written when the entry is
+/// finished, or dropped (the process never started).
+#[derive(Debug)]
+pub(crate) struct AuditEntry {
+    log: Option<Arc<AuditLog>>,
+    time: String,
+    started: Instant,
+    via: &'static str,
+    argv: Vec<String>,
+    cwd: PathBuf,
+    env: BTreeMap<String, Option<String>>,
+    exit: Option<i32>,
+}
+
+impl AuditEntry {
+    /// An entry that records nothing
+    fn disabled() -> AuditEntry {
+        AuditEntry {
+            log: None,
+            time: String::new(),
+            started: Instant::now(),
+            via: "",
+            argv: Vec::new(),
+            cwd: PathBuf::new(),
+            env: BTreeMap::new(),
+            exit: None,
+        }
+    }
+
+    /// Record the process's exit code and write the record
+    pub(crate) fn finish(mut self, exit_code: i32) {
+        self.exit = Some(exit_code);
+    }
+}
+
+impl Drop for AuditEntry {
+    fn drop(&mut self) {
+        let Some(log) = &self.log else {
+            return;
+        };
+        let record = serde_json::json!({
+            "time": self.time,
+            "via": self.via,
+            "argv": self.argv,
+            "cwd": self.cwd,
+            "env": self.env,
+            "duration_ms": self.started.elapsed().as_millis() as u64,
+            "exit": self.exit,
+        });
+        log.write(&record.to_string());
+    }
+}
+
+impl Evaluator {
+    /// Enable or disable the audit log. The environment at this point is
+    /// the baseline for each record's `env`.
+    pub fn set_audit(&mut self, config: AuditConfig) {
+        if !config.is_enabled() {
+            self.audit = None;
+            return;
+        }
+        #[cfg(unix)]
+        if config.syslog {
+            unsafe { libc::openlog(c"hsab".as_ptr(), libc::LOG_PID, libc::LOG_USER) };
+        }
+        self.audit = Some(Arc::new(AuditLog {
+            config,
+            base_env: std::env::vars_os().collect(),
+        }));
+    }
+
+    /// Clear a process about to be spawned by `via` with the exec policy
+    /// and start auditing it. Every spawn goes through here.
+    pub(crate) fn start_process(
+        &self,
+        via: &'static str,
+        cmd: &str,
+        args: &[String],
+    ) -> Result<AuditEntry, EvalError> {
+        self.check_exec(cmd)?;
+        Ok(self.audit_start(via, cmd, args))
+    }
+
+    fn audit_start(&self, via: &'static str, cmd: &str, args: &[String]) -> AuditEntry {
+        let Some(log) = &self.audit else {
+            return AuditEntry::disabled();
+        };
+        let mut argv = Vec::with_capacity(args.len() + 1);
+        argv.push(cmd.to_string());
+        argv.extend(args.iter().cloned());
+        AuditEntry {
+            log: Some(Arc::clone(log)),
+            time: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            started: Instant::now(),
+            via,
+            argv,
+            cwd: self.cwd.clone(),
+            env: log.env_delta(),
+            exit: None,
+        }
+    }
+}
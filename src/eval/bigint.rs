@@ -1,6 +1,6 @@
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint, Sign};
 
 impl Evaluator {
     /// Convert value to BigInt: "123" to-bigint -> BigInt
@@ -13,7 +13,7 @@ impl Evaluator {
         let bigint = match &value {
             Value::Bytes(data) => {
                 // Convert bytes to BigInt (big-endian)
-                BigUint::from_bytes_be(data)
+                BigInt::from_bytes_be(Sign::Plus, data)
             }
             Value::Number(n) => {
                 if *n < 0.0 {
@@ -22,7 +22,7 @@ impl Evaluator {
                         "to-bigint: negative numbers not supported".to_string(),
                     ));
                 }
-                BigUint::from(*n as u64)
+                BigInt::from(*n as u64)
             }
             Value::Int(i) => {
                 if *i < 0 {
@@ -31,7 +31,7 @@ impl Evaluator {
                         "to-bigint: negative numbers not supported".to_string(),
                     ));
                 }
-                BigUint::from(*i as u64)
+                BigInt::from(*i as u64)
             }
             Value::BigInt(n) => {
                 // Already BigInt
@@ -42,10 +42,12 @@ impl Evaluator {
                 if s.starts_with("0x") || s.starts_with("0X") {
                     // Parse as hex
                     BigUint::parse_bytes(&s.as_bytes()[2..], 16)
+                        .map(BigInt::from)
                         .ok_or_else(|| EvalError::ExecError(format!("Invalid hex: {}", s)))?
                 } else {
                     // Parse as decimal
                     BigUint::parse_bytes(s.as_bytes(), 10)
+                        .map(BigInt::from)
                         .ok_or_else(|| EvalError::ExecError(format!("Invalid decimal: {}", s)))?
                 }
             }
@@ -97,7 +99,7 @@ impl Evaluator {
     /// BigInt division: a b big-div -> a/b
     pub(crate) fn builtin_big_div(&mut self) -> Result<(), EvalError> {
         let b = self.pop_bigint("big-div")?;
-        if b == BigUint::ZERO {
+        if b.sign() == Sign::NoSign {
             return Err(EvalError::ExecError(
                 "big-div: division by zero".to_string(),
             ));
@@ -111,7 +113,7 @@ impl Evaluator {
     /// BigInt modulo: a b big-mod -> a%b
     pub(crate) fn builtin_big_mod(&mut self) -> Result<(), EvalError> {
         let b = self.pop_bigint("big-mod")?;
-        if b == BigUint::ZERO {
+        if b.sign() == Sign::NoSign {
             return Err(EvalError::ExecError(
                 "big-mod: division by zero".to_string(),
             ));
@@ -0,0 +1,147 @@
+//! `cached`: reuse a block's results for a while
+//!
+//! `#["ec2 describe-instances" aws] "instances" 300 cached` runs the block
+//! once and keeps what it pushed in `~/.hsab/cache` under the key
+//! `"instances"`. For the next 300 seconds the same line pushes the kept
+//! values instead of running the block again, in this session and in any
+//! other. A block that fails isn't kept. `refresh` before `cached` runs the
+//! block even when the values are fresh (keeping the new ones), and
+//! `cache-clear` empties the cache.
+//!
+//! The block runs on a stack of its own with its output captured, so it
+//! can't take values from below; what it leaves there is its result.
+//!
+//! The cache is hsab's key-value store: `oauth2-token` keeps its tokens in
+//! it too, so entries are readable only by the user.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Seconds since the Unix epoch
+pub(crate) fn now_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// The directory cached results are kept in
+fn cache_dir() -> Option<PathBuf> {
+    let home = super::env::var("HOME").ok().filter(|h| !h.is_empty())?;
+    Some(PathBuf::from(home).join(".hsab").join("cache"))
+}
+
+/// The file for `key`, named by its hash so any key makes a file name
+fn cache_file(key: &str) -> Option<PathBuf> {
+    let hash = hex::encode(Sha256::digest(key.as_bytes()));
+    Some(cache_dir()?.join(format!("{}.json", hash)))
+}
+
+/// The values kept for `key`, if stored less than `ttl` seconds ago
+pub(crate) fn lookup(key: &str, ttl: f64) -> Option<Vec<Value>> {
+    let text = std::fs::read_to_string(cache_file(key)?).ok()?;
+    let doc: JsonValue = serde_json::from_str(&text).ok()?;
+    if doc["key"].as_str() != Some(key) || now_secs() - doc["stored"].as_f64()? >= ttl {
+        return None;
+    }
+    crate::value_serde::from_json_value(&doc["values"]).ok()
+}
+
+/// Keep `values` for `key`
+pub(crate) fn store(key: &str, values: &[Value]) -> std::io::Result<()> {
+    let Some(path) = cache_file(key) else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+    }
+    let doc = json!({
+        "key": key,
+        "stored": now_secs(),
+        "values": crate::value_serde::to_json_value(values),
+    });
+    // Write then rename so a reader never sees half an entry
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, doc.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp, path)
+}
+
+impl Evaluator {
+    /// cached: #[block] "key" seconds cached → the block's results
+    /// (seconds may be a duration like "5m")
+    /// Push the results kept for key if fresh, else run the block and keep them
+    pub(crate) fn builtin_cached(&mut self) -> Result<(), EvalError> {
+        let ttl = self.pop_seconds("cached")?;
+        let key = self.pop_string()?;
+        let block = self.pop_block()?;
+        let refresh = std::mem::take(&mut self.refresh_next);
+
+        if let Some(values) = lookup(&key, ttl).filter(|_| !refresh) {
+            self.stack.extend(values);
+            self.last_exit_code = 0;
+            return Ok(());
+        }
+
+        // Everything the block runs is captured, to be kept
+        let saved = std::mem::take(&mut self.stack);
+        let old_capture = self.capture_mode;
+        let mut outcome = Ok(());
+        for expr in &block {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
+            }
+        }
+        self.capture_mode = old_capture;
+        let produced = std::mem::replace(&mut self.stack, saved);
+        outcome?;
+        if self.last_exit_code == 0 {
+            if let Err(e) = store(&key, &produced) {
+                eprintln!("cached: couldn't keep {}: {}", key, e);
+            }
+        }
+        self.stack.extend(produced);
+        Ok(())
+    }
+
+    /// refresh: refresh #[block] "key" seconds cached
+    /// Make the next `cached` run its block even if its results are fresh
+    pub(crate) fn builtin_refresh(&mut self) -> Result<(), EvalError> {
+        self.refresh_next = true;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// cache-clear: cache-clear → count
+    /// Remove everything `cached` kept, pushing how many entries went
+    pub(crate) fn builtin_cache_clear(&mut self) -> Result<(), EvalError> {
+        let mut removed = 0;
+        if let Some(Ok(entries)) = cache_dir().map(std::fs::read_dir) {
+            for entry in entries.flatten() {
+                if std::fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        self.stack.push(Value::Int(removed));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
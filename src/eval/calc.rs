@@ -0,0 +1,66 @@
+//! Calculator mode: the REPL as an RPN calculator
+//!
+//! `.calc` in the REPL toggles it, and `hsab --rpn` starts with it on.
+//! Numbers and `+ - * /` work on the stack as always; calculator mode adds
+//! `ans`, the value on top when the last line finished, and numbers
+//! written with units, read as seconds or bytes like `duration` and
+//! `size-bytes` read them:
+//!
+//! ```text
+//! 3 4 +                → 7
+//! ans 2 *              → 14
+//! 1.5G 512M /          → 3
+//! 1h30m m to-unit      → 90
+//! ```
+
+use super::units;
+use super::Evaluator;
+use crate::ast::Value;
+
+impl Evaluator {
+    /// Turn calculator mode on or off
+    pub fn set_calc_mode(&mut self, enabled: bool) {
+        self.calc_mode = enabled;
+    }
+
+    /// Whether calculator mode is on
+    pub fn is_calc_mode(&self) -> bool {
+        self.calc_mode
+    }
+
+    /// Push what a word stands for in calculator mode, if it is `ans` or a
+    /// number with a unit (`90s`, `1.5G`)
+    pub(crate) fn push_calc_word(&mut self, word: &str) -> bool {
+        let value = match calc_value(word) {
+            _ if word == "ans" => self.calc_ans.clone(),
+            Some(n) => Value::Number(n),
+            None => return false,
+        };
+        self.stack.push(value);
+        true
+    }
+
+    /// Keep the top of the stack as `ans` once a line has run
+    pub(crate) fn remember_ans(&mut self) {
+        if let Some(top) = self.stack.last() {
+            self.calc_ans = top.clone();
+        }
+    }
+}
+
+/// The seconds or bytes in a number written with a unit
+fn calc_value(word: &str) -> Option<f64> {
+    let (sign, digits) = match word.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, word),
+    };
+    // Plain numbers stay as they are, exact
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        || digits.parse::<f64>().is_ok()
+    {
+        return None;
+    }
+    units::parse_duration(digits)
+        .or_else(|| units::parse_size(digits))
+        .map(|n| sign * n)
+}
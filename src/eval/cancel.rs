@@ -0,0 +1,127 @@
+//! Cancelling a running eval from another thread
+//!
+//! An embedder that hands hsab an untrusted or runaway script can stop it
+//! without killing its own process: take a [`CancelToken`] from the
+//! evaluator before starting the eval, and call `cancel()` from any thread.
+//! The eval stops before its next expression with `EvalError::Cancelled`,
+//! and the foreground processes it is waiting for are killed. Background
+//! jobs keep running.
+//!
+//! ```rust
+//! use hsab::{lex, parse, EvalError, Evaluator};
+//!
+//! let mut eval = Evaluator::new();
+//! let token = eval.cancel_token();
+//! std::thread::spawn(move || {
+//!     std::thread::sleep(std::time::Duration::from_millis(50));
+//!     token.cancel();
+//! });
+//! let program = parse(lex("#[1 drop] 1000000000 times").unwrap()).unwrap();
+//! assert!(matches!(eval.eval(&program), Err(EvalError::Cancelled)));
+//! ```
+
+use std::io;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::{EvalError, Evaluator};
+use crate::util::lock_or_recover;
+
+/// Cancels an evaluator's running eval. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    /// Pids of the foreground processes being waited for
+    children: Mutex<Vec<u32>>,
+}
+
+impl CancelToken {
+    /// Stop the running eval at its next expression and kill the processes
+    /// it is waiting for. If no eval is running, the next one is cancelled
+    /// as soon as it starts.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.kill_foreground();
+    }
+
+    /// Check if the token has been cancelled and no eval has stopped for it
+    /// yet
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Treat `pid` as a foreground process until the guard is dropped. It
+    /// is killed at once if the token is already cancelled.
+    pub(crate) fn track(&self, pid: u32) -> Foreground {
+        let mut children = lock_or_recover(&self.inner.children);
+        children.push(pid);
+        if self.is_cancelled() {
+            let _ = crate::signals::kill_process(pid);
+        }
+        Foreground {
+            token: self.clone(),
+            pid,
+        }
+    }
+
+    /// Kill the foreground processes being waited for
+    pub(crate) fn kill_foreground(&self) {
+        for &pid in lock_or_recover(&self.inner.children).iter() {
+            let _ = crate::signals::kill_process(pid);
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.inner.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A tracked foreground process; see [`CancelToken::track`]
+pub(crate) struct Foreground {
+    token: CancelToken,
+    pid: u32,
+}
+
+impl Drop for Foreground {
+    fn drop(&mut self) {
+        let mut children = lock_or_recover(&self.token.inner.children);
+        if let Some(i) = children.iter().position(|&pid| pid == self.pid) {
+            children.swap_remove(i);
+        }
+    }
+}
+
+impl Evaluator {
+    /// A token that cancels this evaluator's running eval from another
+    /// thread
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Fail with `Cancelled` if the token has been cancelled
+    pub(crate) fn check_cancelled(&self) -> Result<(), EvalError> {
+        if self.cancel.is_cancelled() {
+            Err(EvalError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Spawn `command` and wait for it, collecting whatever output was
+    /// piped, like `Command::output` but killed if the eval is cancelled.
+    /// Unlike `output`, stdio is inherited unless the caller sets it.
+    pub(crate) fn run_foreground(&self, command: &mut Command) -> io::Result<Output> {
+        let child = command.spawn()?;
+        let _foreground = self.cancel.track(child.id());
+        match &self.output_tap {
+            Some(tap) => tap.wait_with_output(child),
+            None => child.wait_with_output(),
+        }
+    }
+}
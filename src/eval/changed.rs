@@ -0,0 +1,70 @@
+//! `changed?`: whether a polled command's output differs from last time
+//!
+//! `#[kubectl get pods] changed?` runs the block with its output captured
+//! and compares it with what the same block printed the last time it ran.
+//! It pushes the difference, a line per change (`-` for a line that went,
+//! `+` for one that came), and exits 0 if there was any. An unchanged
+//! output pushes an empty diff, which is false to `if`:
+//!
+//! ```text
+//! #["pods changed" echo] #[kubectl get pods] changed? if
+//! ```
+//!
+//! Each block is its own key, and keys last for the session. The first run
+//! only records the output: it pushes an empty diff and exits 1.
+
+use super::patch::{line_ops, LineOp};
+use super::{EvalError, Evaluator};
+use crate::ast::{exprs_to_source, Value};
+
+/// The lines that went from `old` (`-`) and came in `new` (`+`), in order
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    line_ops(&old, &new)
+        .into_iter()
+        .filter_map(|op| match op {
+            LineOp::Same(_) => None,
+            LineOp::Went(line) => Some(format!("-{}", line)),
+            LineOp::Came(line) => Some(format!("+{}", line)),
+        })
+        .collect()
+}
+
+impl Evaluator {
+    /// changed?: #[block] changed? → diff
+    /// Run the block and push how its output differs from its last run;
+    /// exit 0 if it does
+    pub(crate) fn builtin_changed(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+        let key = exprs_to_source(&block);
+
+        let saved = std::mem::take(&mut self.stack);
+        let old_capture = self.capture_mode;
+        let mut outcome = Ok(());
+        for expr in &block {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
+            }
+        }
+        self.capture_mode = old_capture;
+        let produced = std::mem::replace(&mut self.stack, saved);
+        outcome?;
+
+        let output = produced
+            .iter()
+            .filter_map(Value::as_arg)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diff = match self.changed_outputs.insert(key, output.clone()) {
+            Some(previous) => line_diff(&previous, &output),
+            None => Vec::new(),
+        };
+
+        self.last_exit_code = if diff.is_empty() { 1 } else { 0 };
+        self.stack.push(Value::Literal(diff.join("\n")));
+        Ok(())
+    }
+}
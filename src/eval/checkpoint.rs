@@ -0,0 +1,223 @@
+//! Checkpoint/restore of evaluator state for resuming long scripts
+//!
+//! `"name" checkpoint` writes the stack, definitions, working directory, and
+//! the environment variables changed since startup to
+//! `~/.hsab/checkpoints/name.json` (or `$HSAB_CHECKPOINT_DIR`), along with
+//! the script line it was taken on. `hsab --resume name script.hsab`
+//! restores that state and continues after the `checkpoint` word.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Format marker stored in every checkpoint file
+const CHECKPOINT_VERSION: i64 = 2;
+
+/// Environment as the process started, for computing the env overlay
+pub(crate) fn startup_env() -> &'static HashMap<String, String> {
+    static ENV: OnceLock<HashMap<String, String>> = OnceLock::new();
+    ENV.get_or_init(|| super::env::vars().collect())
+}
+
+/// Where a script is executing, recorded into checkpoints
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScriptLocation {
+    pub(crate) path: String,
+    pub(crate) line: usize,
+    pub(crate) text: String,
+}
+
+/// The point a script resumes from after `restore_checkpoint`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResumePoint {
+    /// Script the checkpoint was taken in
+    pub script: String,
+    /// 1-based line holding the `checkpoint` word
+    pub line: usize,
+    /// Column of the top-level statement that took the checkpoint
+    pub col: usize,
+    /// Text of that line when the checkpoint was taken
+    pub text: String,
+}
+
+/// Name of the first value nested in `val` that can't be checkpointed
+fn unserializable(val: &Value) -> Option<&'static str> {
+    match val {
+        Value::Future { .. } => Some("future"),
+        Value::List(items) => items.iter().find_map(unserializable),
+        Value::Map(map) => map.values().find_map(unserializable),
+        Value::Table { rows, .. } => rows.iter().flatten().find_map(unserializable),
+        _ => None,
+    }
+}
+
+impl Evaluator {
+    /// Record the script line about to run, so checkpoints know where to
+    /// resume
+    pub fn set_script_location(&mut self, path: &str, line: usize, text: &str) {
+        self.script_location = Some(ScriptLocation {
+            path: path.to_string(),
+            line,
+            text: text.to_string(),
+        });
+    }
+
+    /// File a checkpoint name refers to: a path if it contains `/` or ends
+    /// in `.json`, otherwise `name.json` in the checkpoint directory
+    pub fn checkpoint_path(&self, name: &str) -> PathBuf {
+        if name.contains('/') || name.ends_with(".json") {
+            return self.cwd.join(name);
+        }
+        let dir = super::env::var("HSAB_CHECKPOINT_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                PathBuf::from(&self.home_dir)
+                    .join(".hsab")
+                    .join("checkpoints")
+            });
+        dir.join(format!("{}.json", name))
+    }
+
+    /// checkpoint: "name" checkpoint -> (state saved to disk)
+    pub(crate) fn builtin_checkpoint(&mut self) -> Result<(), EvalError> {
+        let name = self.pop_string()?;
+
+        // Every value that can't be written is reported together
+        let problems: Vec<String> = self
+            .stack
+            .iter()
+            .rev()
+            .enumerate()
+            .filter_map(|(i, val)| {
+                unserializable(val).map(|kind| format!("stack item {} holds a {}", i, kind))
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(EvalError::ExecError(format!(
+                "checkpoint '{}' not saved: {}; futures and handles can't be serialized (await or drop them first)",
+                name,
+                problems.join(", ")
+            )));
+        }
+
+        let mut definitions = JsonMap::new();
+        let mut names: Vec<&String> = self.definitions.keys().collect();
+        names.sort();
+        for def in names {
+            let source = crate::ast::exprs_to_source(&self.definitions[def]);
+            definitions.insert(def.clone(), json!(source));
+        }
+
+        // Overlay: variables set or changed since startup, null if removed
+        let startup = startup_env();
+        let mut env = JsonMap::new();
+        let mut current: Vec<(String, String)> = super::env::vars().collect();
+        current.sort();
+        for (k, v) in &current {
+            if startup.get(k) != Some(v) {
+                env.insert(k.clone(), json!(v));
+            }
+        }
+        let mut removed: Vec<&String> = startup
+            .keys()
+            .filter(|k| super::env::var(k).is_err())
+            .collect();
+        removed.sort();
+        for k in removed {
+            env.insert(k.clone(), JsonValue::Null);
+        }
+
+        let location = self.script_location.as_ref();
+        let doc = json!({
+            "hsab_checkpoint": CHECKPOINT_VERSION,
+            "name": name,
+            "script": location.map(|l| l.path.clone()),
+            "line": location.map(|l| l.line),
+            "col": self.current_span.map(|(_, col)| col),
+            "text": location.map(|l| l.text.clone()),
+            "cwd": self.cwd.to_string_lossy(),
+            "stack": crate::value_serde::to_json_value(&self.stack),
+            "definitions": definitions,
+            "env": env,
+        });
+
+        let path = self.checkpoint_path(&name);
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // Write then rename so a crash mid-write keeps the old checkpoint
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, serde_json::to_string_pretty(&doc)?)?;
+            std::fs::rename(&tmp, &path)
+        };
+        write()
+            .map_err(|e| EvalError::ExecError(format!("checkpoint: {}: {}", path.display(), e)))?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Restore the state saved by `checkpoint` and report where the script
+    /// should resume
+    pub fn restore_checkpoint(&mut self, name: &str) -> Result<ResumePoint, String> {
+        let path = self.checkpoint_path(name);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("checkpoint '{}': {}: {}", name, path.display(), e))?;
+        let doc: JsonValue =
+            serde_json::from_str(&text).map_err(|e| format!("checkpoint '{}': {}", name, e))?;
+        if doc["hsab_checkpoint"].as_i64() != Some(CHECKPOINT_VERSION) {
+            return Err(format!("{}: not an hsab checkpoint", path.display()));
+        }
+        let (Some(script), Some(line)) = (doc["script"].as_str(), doc["line"].as_u64()) else {
+            return Err(format!(
+                "checkpoint '{}' was not taken in a script and cannot be resumed",
+                name
+            ));
+        };
+
+        let stack = crate::value_serde::from_json_value(&doc["stack"])
+            .map_err(|e| format!("checkpoint '{}': bad stack: {}", name, e))?;
+
+        let mut definitions = HashMap::new();
+        if let Some(defs) = doc["definitions"].as_object() {
+            for (def, source) in defs {
+                let source = source.as_str().unwrap_or("");
+                let tokens = crate::lexer::lex(source)
+                    .map_err(|e| format!("definition '{}': {}", def, e))?;
+                let program = crate::parser::parse(tokens)
+                    .map_err(|e| format!("definition '{}': {}", def, e))?;
+                definitions.insert(def.clone(), program.expressions);
+            }
+        }
+
+        // Everything parsed; now apply it
+        if let Some(env) = doc["env"].as_object() {
+            for (k, v) in env {
+                match v.as_str() {
+                    Some(v) => super::env::set_var(k, v),
+                    None => super::env::remove_var(k),
+                }
+            }
+        }
+        if let Some(cwd) = doc["cwd"].as_str() {
+            let cwd = PathBuf::from(cwd);
+            std::env::set_current_dir(&cwd)
+                .map_err(|e| format!("checkpoint '{}': cd {}: {}", name, cwd.display(), e))?;
+            self.cwd = cwd;
+        }
+        self.definitions.extend(definitions);
+        self.stack = stack.into();
+
+        Ok(ResumePoint {
+            script: script.to_string(),
+            line: line as usize,
+            col: doc["col"].as_u64().unwrap_or(0) as usize,
+            text: doc["text"].as_str().unwrap_or("").to_string(),
+        })
+    }
+}
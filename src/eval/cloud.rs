@@ -0,0 +1,208 @@
+//! Cloud CLI bridges: `aws` and `gcloud` as structured builtins
+//!
+//! `"ec2 describe-instances" aws` runs `aws ec2 describe-instances --output
+//! json` and pushes what it printed as values rather than text. A response
+//! holding a single list (`{"Reservations": [...]}`) pushes that list, and a
+//! list of objects becomes a Table, so inventory composes with `where` and
+//! `select`:
+//!
+//! ```text
+//! "ec2 describe-key-pairs" aws #["KeyType" get "rsa" eq?] where
+//! "compute instances list" gcloud #["status" get "RUNNING" eq?] where
+//! ```
+//!
+//! When aws stops at a page (`--max-items`), the `NextToken` it returns is
+//! passed back as `--starting-token` until there are no more pages, and
+//! their lists are merged. gcloud pages by itself. Arguments are the words
+//! of a string, or the items of a list when one has spaces in it. Output
+//! that isn't JSON (`aws s3 cp`) is pushed as text.
+//!
+//! Anything else (`"ls" s3 aws`, `configure aws`, `auth login gcloud`) runs
+//! the real CLI as an external command, so its arguments, interactive
+//! prompts, and output are left alone.
+
+use std::process::{Command, Stdio};
+
+use serde_json::{Map, Value as JsonValue};
+
+use super::{EvalError, Evaluator};
+use crate::ast::{json_to_value, Value};
+
+/// Pages fetched before giving up on a token that never runs out
+const MAX_PAGES: usize = 1000;
+
+/// Merge a following page's lists into `merged`, keeping other fields from
+/// the first page
+pub(crate) fn merge_page(merged: &mut Map<String, JsonValue>, page: Map<String, JsonValue>) {
+    for (key, value) in page {
+        match (merged.get_mut(&key), value) {
+            (Some(JsonValue::Array(items)), JsonValue::Array(more)) => items.extend(more),
+            (None, value) => {
+                merged.insert(key, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A list of objects as a Table with every key as a column, in the order
+/// they first appear; any other list as a List
+pub(crate) fn list_value(items: Vec<JsonValue>) -> Value {
+    if items.is_empty() || !items.iter().all(JsonValue::is_object) {
+        return Value::List(items.into_iter().map(json_to_value).collect());
+    }
+    let mut columns: Vec<String> = Vec::new();
+    for item in &items {
+        for key in item.as_object().into_iter().flat_map(|o| o.keys()) {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let rows = items
+        .into_iter()
+        .map(|mut item| {
+            columns
+                .iter()
+                .map(|col| json_to_value(item[col.as_str()].take()))
+                .collect()
+        })
+        .collect();
+    Value::Table { columns, rows }
+}
+
+/// The value for a CLI's JSON response: the list inside a response that
+/// holds just one, else the response itself
+fn response_value(json: JsonValue) -> Value {
+    match json {
+        JsonValue::Array(items) => list_value(items),
+        JsonValue::Object(mut obj) => {
+            let lists: Vec<String> = obj
+                .iter()
+                .filter(|(_, v)| v.is_array())
+                .map(|(k, _)| k.clone())
+                .collect();
+            match lists.as_slice() {
+                [key] if obj.len() == 1 => match obj.remove(key) {
+                    Some(JsonValue::Array(items)) => list_value(items),
+                    _ => Value::Nil,
+                },
+                _ => json_to_value(JsonValue::Object(obj)),
+            }
+        }
+        other => json_to_value(other),
+    }
+}
+
+impl Evaluator {
+    /// Whether `aws`/`gcloud` should run as a bridge: the top of the stack
+    /// is a query in one string (`"ec2 describe-instances"`) or a list
+    pub(crate) fn cloud_is_native(&self) -> bool {
+        match self.stack.last() {
+            Some(Value::List(_)) => true,
+            Some(Value::Literal(s)) | Some(Value::Output(s)) => {
+                s.split_whitespace().nth(1).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// aws: "service operation args" aws → Table/Record
+    /// Run the AWS CLI with JSON output, following pages, as values
+    pub(crate) fn builtin_aws(&mut self) -> Result<(), EvalError> {
+        let mut args = self.pop_cli_args("aws")?;
+        args.extend(["--output".to_string(), "json".to_string()]);
+
+        let mut merged: Option<Map<String, JsonValue>> = None;
+        let mut token: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let mut page_args = args.clone();
+            if let Some(token) = token.take() {
+                page_args.extend(["--starting-token".to_string(), token]);
+            }
+            let text = self.run_cloud_cli("aws", &page_args)?;
+            let Ok(JsonValue::Object(mut page)) = serde_json::from_str::<JsonValue>(&text) else {
+                return self.push_cli_output(text);
+            };
+            token = match page.remove("NextToken") {
+                Some(JsonValue::String(next)) if !next.is_empty() => Some(next),
+                _ => None,
+            };
+            match merged.as_mut() {
+                Some(merged) => merge_page(merged, page),
+                None => merged = Some(page),
+            }
+            if token.is_none() {
+                break;
+            }
+        }
+
+        let value = response_value(JsonValue::Object(merged.unwrap_or_default()));
+        self.stack.push(value);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// gcloud: "group command args" gcloud → Table/Record
+    /// Run the Google Cloud CLI with JSON output, as values
+    pub(crate) fn builtin_gcloud(&mut self) -> Result<(), EvalError> {
+        let mut args = self.pop_cli_args("gcloud")?;
+        args.push("--format=json".to_string());
+        let text = self.run_cloud_cli("gcloud", &args)?;
+        self.push_cli_output(text)
+    }
+
+    /// The arguments for a CLI bridge: a string's words or a list's items
+    fn pop_cli_args(&mut self, word: &str) -> Result<Vec<String>, EvalError> {
+        let args: Vec<String> = match self.pop_value_or_err()? {
+            Value::List(items) => items.iter().filter_map(Value::as_arg).collect(),
+            other => other
+                .as_arg()
+                .ok_or_else(|| EvalError::TypeError {
+                    expected: "string".into(),
+                    got: other.type_name().to_string(),
+                })?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        };
+        if args.is_empty() {
+            return Err(EvalError::ExecError(format!(
+                "{}: no command given (e.g. \"ec2 describe-instances\" {})",
+                word, word
+            )));
+        }
+        Ok(args)
+    }
+
+    /// Run `cmd args`, returning what it printed or failing with its errors
+    fn run_cloud_cli(&mut self, cmd: &'static str, args: &[String]) -> Result<String, EvalError> {
+        let audit = self.start_process(cmd, cmd, args)?;
+        let output = Command::new(cmd)
+            .args(args)
+            .current_dir(&self.cwd)
+            .stdin(Stdio::inherit())
+            .output()
+            .map_err(|e| self.spawn_error(cmd, e))?;
+        let code = output.status.code().unwrap_or(-1);
+        audit.finish(code);
+        if !output.status.success() {
+            self.last_exit_code = code;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(EvalError::ExecError(format!("{}: {}", cmd, stderr.trim())));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Push a CLI's output as values if it is JSON, else as text
+    fn push_cli_output(&mut self, text: String) -> Result<(), EvalError> {
+        let value = match serde_json::from_str(&text) {
+            Ok(json) => response_value(json),
+            Err(_) if text.trim().is_empty() => Value::Nil,
+            Err(_) => Value::Output(text.trim_end().to_string()),
+        };
+        self.stack.push(value);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
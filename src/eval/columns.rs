@@ -0,0 +1,259 @@
+//! `columns`: parse whitespace-aligned output into a Table
+//!
+//! Commands like `ps`, `df`, `docker ps` and `kubectl get` print a header
+//! row and columns padded with spaces. `aux ps columns` finds the columns
+//! from where every line has a space, so a header with a space in it
+//! (`CONTAINER ID`) stays one column as long as the values under it fill
+//! the gap, and right-aligned numbers still land under their header.
+//! Columns with only one space between them where a value is wide are told
+//! apart by their header words, when every row has as many words there. Text
+//! under no header belongs to the column before it, which gives the last
+//! column the rest of the line (`ps`'s `COMMAND` with its arguments).
+//!
+//! A list before the word says how to split instead. Widths (`[8 6 10]`)
+//! cut every line at those places, the last column taking the rest; names
+//! (`["pid" "cmd"]`) are the header of output that has none, and each line
+//! is split on whitespace into that many fields, the last taking the rest,
+//! as `awk '{print $2}'` would see it.
+//!
+//! Numbers become Ints and Numbers as they would typed in; an empty cell is nil.
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// A line as characters, with tabs expanded to the next multiple of 8
+fn expand_tabs(line: &str) -> Vec<char> {
+    let mut chars = Vec::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            chars.push(' ');
+            while chars.len() % 8 != 0 {
+                chars.push(' ');
+            }
+        } else {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+/// The trimmed text of `line` from `start` up to `end` (or its end)
+fn slice(line: &[char], start: usize, end: Option<usize>) -> String {
+    let end = end.unwrap_or(line.len()).min(line.len());
+    if start >= end {
+        return String::new();
+    }
+    line[start..end]
+        .iter()
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Where each column starts, from the runs of characters between the
+/// places where every line has a space
+fn aligned_starts(lines: &[Vec<char>]) -> Vec<usize> {
+    let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let blank: Vec<bool> = (0..width)
+        .map(|i| {
+            lines
+                .iter()
+                .all(|line| line.get(i).is_none_or(|c| *c == ' '))
+        })
+        .collect();
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for (i, &is_blank) in blank.iter().enumerate() {
+        match spans.last_mut() {
+            Some(span) if !is_blank && span.1 == i => span.1 = i + 1,
+            _ if !is_blank => spans.push((i, i + 1)),
+            _ => {}
+        }
+    }
+
+    let (header, rows) = (&lines[0], &lines[1..]);
+    let mut starts: Vec<usize> = Vec::new();
+    for (start, end) in spans {
+        let named = !slice(header, start, Some(end)).is_empty();
+        // A header word with nothing under it ("on" of df's "Mounted on")
+        // is the rest of the header before it
+        let filled = rows
+            .iter()
+            .any(|row| !slice(row, start, Some(end)).is_empty());
+        if starts.is_empty() || (named && (filled || rows.is_empty())) {
+            starts.push(start);
+        }
+    }
+    starts
+}
+
+/// A row of cells, as a value each
+fn cells(fields: Vec<String>) -> Vec<Value> {
+    fields
+        .into_iter()
+        .map(|field| match field.as_str() {
+            "" => Value::Nil,
+            _ => Value::from_literal_word(&field),
+        })
+        .collect()
+}
+
+/// Split `line` on whitespace into at most `count` fields, the last taking
+/// the rest of the line
+fn split_fields(line: &str, count: usize) -> Vec<String> {
+    let mut fields = Vec::with_capacity(count);
+    let mut rest = line.trim_start();
+    while fields.len() + 1 < count && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    fields.push(rest.trim_end().to_string());
+    fields.resize(count, String::new());
+    fields
+}
+
+/// How the columns are found
+enum Spec {
+    /// From where the lines have spaces in common
+    Aligned,
+    /// Lines are cut at these widths
+    Widths(Vec<usize>),
+    /// Lines have no header and are split on whitespace
+    Names(Vec<String>),
+}
+
+impl Spec {
+    fn from_list(items: &[Value]) -> Option<Self> {
+        if items.is_empty() {
+            return None;
+        }
+        if let Some(widths) = items
+            .iter()
+            .map(|item| match item {
+                Value::Int(n) if *n > 0 => Some(*n as usize),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            return Some(Spec::Widths(widths));
+        }
+        items
+            .iter()
+            .map(|item| match item {
+                Value::Literal(s) | Value::Output(s) if !s.is_empty() => Some(s.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Spec::Names)
+    }
+}
+
+/// Each line's text in each column, where the columns start at `starts`
+/// and the last takes the rest of the line
+fn cut(lines: &[Vec<char>], starts: &[usize]) -> Vec<Vec<String>> {
+    lines
+        .iter()
+        .map(|line| {
+            starts
+                .iter()
+                .enumerate()
+                .map(|(i, &start)| slice(line, start, starts.get(i + 1).copied()))
+                .collect()
+        })
+        .collect()
+}
+
+/// The header and rows of aligned `lines`
+fn aligned(lines: &[Vec<char>]) -> (Vec<String>, Vec<Vec<Value>>) {
+    let fields = cut(lines, &aligned_starts(lines));
+    let (header, rows) = (&fields[0], &fields[1..]);
+    // Columns packed with single spaces (`ps`'s `VSZ RSS TTY` once a value
+    // is wide) share a run, so a run with several header words is split
+    // into that many columns when every row has that many words in it too
+    // (or more, in the last column); otherwise it's one column with a
+    // space in its name (`CONTAINER ID`)
+    let split: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(j, name)| {
+            let words = name.split_whitespace().count();
+            let last = j + 1 == header.len();
+            let fits = rows.iter().all(|row| {
+                let count = row[j].split_whitespace().count();
+                count == 0 || count == words || (last && count > words)
+            });
+            if words > 1 && fits {
+                words
+            } else {
+                1
+            }
+        })
+        .collect();
+    let split_line = |line: &Vec<String>| -> Vec<String> {
+        line.iter()
+            .zip(&split)
+            .flat_map(|(text, &count)| match count {
+                1 => vec![text.clone()],
+                _ => split_fields(text, count),
+            })
+            .collect()
+    };
+    let columns = split_line(header);
+    let rows = rows.iter().map(|row| cells(split_line(row))).collect();
+    (columns, rows)
+}
+
+impl Evaluator {
+    /// columns: text [spec] columns → Table
+    /// Parse space-aligned output with a header row into a Table
+    pub(crate) fn builtin_columns(&mut self) -> Result<(), EvalError> {
+        let spec = match self.stack.last() {
+            Some(Value::List(items)) => {
+                let spec = Spec::from_list(items).ok_or_else(|| {
+                    EvalError::ExecError(
+                        "columns: the spec is a list of widths ([8 6]) or of names ([\"pid\" \"cmd\"])"
+                            .into(),
+                    )
+                })?;
+                self.stack.pop();
+                spec
+            }
+            _ => Spec::Aligned,
+        };
+        let text = self.pop_string()?;
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+
+        let (columns, rows) = match spec {
+            Spec::Names(names) => {
+                let rows = lines
+                    .iter()
+                    .map(|line| cells(split_fields(line, names.len())))
+                    .collect();
+                (names, rows)
+            }
+            Spec::Widths(_) | Spec::Aligned if lines.is_empty() => (Vec::new(), Vec::new()),
+            Spec::Widths(widths) => {
+                let lines: Vec<Vec<char>> = lines.iter().map(|l| expand_tabs(l)).collect();
+                let starts: Vec<usize> = widths
+                    .iter()
+                    .scan(0, |at, width| {
+                        let start = *at;
+                        *at += width;
+                        Some(start)
+                    })
+                    .collect();
+                let mut fields = cut(&lines, &starts).into_iter();
+                let columns = fields.next().unwrap_or_default();
+                (columns, fields.map(cells).collect())
+            }
+            Spec::Aligned => {
+                let lines: Vec<Vec<char>> = lines.iter().map(|l| expand_tabs(l)).collect();
+                aligned(&lines)
+            }
+        };
+
+        self.stack.push(Value::Table { columns, rows });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -187,7 +187,7 @@ impl Evaluator {
 
         for attempt in 1..=max_tries {
             // Try executing the block
-            let result = (|| {
+            let result: Result<(), EvalError> = (|| {
                 for expr in &block_exprs {
                     self.eval_expr(expr)?;
                 }
@@ -206,6 +206,7 @@ impl Evaluator {
                         attempt, max_tries, self.last_exit_code
                     )));
                 }
+                Err(e) if e.is_fatal() => return Err(e),
                 Err(e) => {
                     last_error = Some(e);
                 }
@@ -224,13 +225,10 @@ impl Evaluator {
 
     /// retry-delay: Retry with configurable delay between attempts
     /// #[block] N ms retry-delay -> result
-    /// Stack: #[block] count delay_ms (delay on top)
+    /// Stack: #[block] count delay_ms (delay on top; also a duration like "2s")
     pub(crate) fn builtin_retry_delay(&mut self) -> Result<(), EvalError> {
         // Pop in LIFO order: delay_ms, count, block
-        let delay_ms = self
-            .stack
-            .pop()
-            .ok_or_else(|| EvalError::StackUnderflow("retry-delay: requires delay in ms".into()))?;
+        let delay_ms = self.pop_millis("retry-delay")?;
         let count = self
             .stack
             .pop()
@@ -267,22 +265,12 @@ impl Evaluator {
             }
         };
 
-        let delay: u64 = match delay_ms {
-            Value::Number(n) => n as u64,
-            Value::Int(i) => i as u64,
-            Value::Literal(s) | Value::Output(s) => {
-                s.parse::<u64>().map_err(|_| EvalError::TypeError {
-                    expected: "Number (milliseconds)".into(),
-                    got: "String".into(),
-                })?
-            }
-            _ => {
-                return Err(EvalError::TypeError {
-                    expected: "Number (milliseconds)".into(),
-                    got: delay_ms.type_name().to_string(),
-                })
+        let delay = std::time::Duration::try_from_secs_f64(delay_ms / 1000.0).map_err(|_| {
+            EvalError::TypeError {
+                expected: "Number (milliseconds)".into(),
+                got: delay_ms.to_string(),
             }
-        };
+        })?;
 
         if max_tries == 0 {
             return Err(EvalError::ExecError(
@@ -293,7 +281,7 @@ impl Evaluator {
         let mut last_error: Option<EvalError> = None;
 
         for attempt in 1..=max_tries {
-            let result = (|| {
+            let result: Result<(), EvalError> = (|| {
                 for expr in &block_exprs {
                     self.eval_expr(expr)?;
                 }
@@ -310,13 +298,14 @@ impl Evaluator {
                         attempt, max_tries, self.last_exit_code
                     )));
                 }
+                Err(e) if e.is_fatal() => return Err(e),
                 Err(e) => {
                     last_error = Some(e);
                 }
             }
 
             if attempt < max_tries {
-                std::thread::sleep(std::time::Duration::from_millis(delay));
+                std::thread::sleep(delay);
             }
         }
 
@@ -384,4 +373,42 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    /// and-then: #[a] #[b] and-then - run b only if a left a truthy,
+    /// non-error value; b starts with that value on the stack. Otherwise
+    /// a's value (the error) is the result.
+    pub(crate) fn builtin_and_then(&mut self) -> Result<(), EvalError> {
+        self.chain_blocks(true)
+    }
+
+    /// or-else: #[a] #[b] or-else - run b only if a left a falsy value or
+    /// an error; b starts with that value on the stack to recover from
+    pub(crate) fn builtin_or_else(&mut self) -> Result<(), EvalError> {
+        self.chain_blocks(false)
+    }
+
+    fn chain_blocks(&mut self, run_if_truthy: bool) -> Result<(), EvalError> {
+        let second = self.pop_block()?;
+        let first = self.pop_block()?;
+        let outer_capture_mode = self.capture_mode;
+
+        // The first block's value decides, so it is always captured
+        for expr in &first {
+            self.capture_mode = true;
+            self.eval_expr(expr)?;
+        }
+        let truthy = self.stack.last().is_some_and(Self::value_is_truthy);
+        if truthy == run_if_truthy {
+            for (i, expr) in second.iter().enumerate() {
+                let is_last = i == second.len() - 1;
+                self.capture_mode = if is_last { outer_capture_mode } else { true };
+                self.eval_expr(expr)?;
+            }
+        }
+        self.capture_mode = outer_capture_mode;
+
+        let ok = self.stack.last().is_some_and(Self::value_is_truthy);
+        self.last_exit_code = if ok { 0 } else { 1 };
+        Ok(())
+    }
 }
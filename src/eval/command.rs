@@ -1,17 +1,24 @@
+use super::confirm::Expansion;
+use super::encoding::TextEncoding;
+use super::log::LogLevel;
+use super::provenance::Origin;
 use super::{EvalError, Evaluator};
-use crate::ast::Value;
+use crate::ast::{Expr, Value};
+use std::path::Path;
 use std::process::{Command, Stdio};
 
 /// Convert captured stdout bytes to a stack value (issue #25).
 ///
-/// Empty output stays `Nil`, valid UTF-8 stays `Output`, and anything else
-/// is preserved byte-for-byte as `Bytes` (no lossy mangling).
-pub(crate) fn output_to_value(bytes: Vec<u8>) -> Value {
+/// Empty output stays `Nil`, valid UTF-8 stays `Output`. Anything else is
+/// decoded with a non-UTF-8 output encoding if one is set, and otherwise
+/// preserved byte-for-byte as `Bytes` (no lossy mangling).
+pub(crate) fn output_to_value(bytes: Vec<u8>, encoding: TextEncoding) -> Value {
     if bytes.is_empty() {
         return Value::Nil;
     }
     match String::from_utf8(bytes) {
         Ok(s) => Value::Output(s),
+        Err(e) if encoding != TextEncoding::UTF8 => Value::Output(encoding.decode(e.as_bytes())),
         Err(e) => Value::Bytes(e.into_bytes()),
     }
 }
@@ -19,8 +26,32 @@ pub(crate) fn output_to_value(bytes: Vec<u8>) -> Value {
 impl Evaluator {
     /// Execute a command, popping args from stack
     pub(crate) fn execute_command(&mut self, cmd: &str) -> Result<(), EvalError> {
+        self.check_restricted_word(cmd)?;
+        let stream_to = std::mem::take(&mut self.pipe_consumers);
+        let args = self.collect_command_args(cmd)?;
+
+        // Try builtin first
+        if let Some(result) = self.try_builtin(cmd, &args) {
+            return result;
+        }
+        self.spawn_command(cmd, args, stream_to)
+    }
+
+    /// Execute the PATH command `cmd`, even though a builtin has its name
+    /// (see `ExecPolicy::external`)
+    pub(crate) fn execute_external(&mut self, cmd: &str) -> Result<(), EvalError> {
+        self.check_restricted_word(cmd)?;
+        let stream_to = std::mem::take(&mut self.pipe_consumers);
+        let args = self.collect_command_args(cmd)?;
+        self.spawn_command(cmd, args, stream_to)
+    }
+
+    /// Pop a command's arguments from the stack, confirming large glob
+    /// expansions first if `cmd` is `rm` or `mv`
+    fn collect_command_args(&mut self, cmd: &str) -> Result<Vec<String>, EvalError> {
         // Collect args from stack (LIFO - pop until we hit a block, marker, or empty)
         let mut args = Vec::new();
+        let mut globs = Vec::new();
         while let Some(value) = self.stack.last() {
             match value {
                 Value::Block(_) => break,
@@ -32,19 +63,39 @@ impl Evaluator {
                 _ => {
                     if let Some(arg) = value.as_arg() {
                         // Expand globs and tilde for each argument
-                        args.extend(self.expand_arg(&arg));
+                        let expanded = self.expand_arg(&arg);
+                        if expanded.len() > 1 {
+                            globs.push(Expansion {
+                                pattern: arg,
+                                paths: expanded.clone(),
+                            });
+                        }
+                        args.extend(expanded);
                     }
                     self.stack.pop();
                 }
             }
         }
 
-        // Try builtin first
-        if let Some(result) = self.try_builtin(cmd, &args) {
-            return result;
+        let name = Path::new(cmd).file_name().and_then(|n| n.to_str());
+        if matches!(name, Some("rm" | "mv")) {
+            self.confirm_globs(cmd, &globs)?;
         }
+        Ok(args)
+    }
 
-        // Execute native command
+    /// Run `cmd` as a process and push its output, or the output of the
+    /// pipe consumers in `stream_to` reading it as it's written
+    fn spawn_command(
+        &mut self,
+        cmd: &str,
+        args: Vec<String>,
+        stream_to: Vec<Vec<Expr>>,
+    ) -> Result<(), EvalError> {
+        let streams = self.pipe_streams(cmd, &stream_to);
+        if streams > 0 {
+            return self.stream_pipe(cmd, args, &stream_to[..streams]);
+        }
         let argv = if args.is_empty() {
             cmd.to_string()
         } else {
@@ -70,7 +121,10 @@ impl Evaluator {
                 command: Some(argv),
             });
         } else {
-            self.stack.push(output_to_value(stdout));
+            self.push_from(
+                output_to_value(stdout, self.output_encoding),
+                Origin::Output,
+            );
         }
 
         Ok(())
@@ -87,7 +141,7 @@ impl Evaluator {
         args: Vec<String>,
     ) -> Result<(String, i32), EvalError> {
         let (stdout, _stderr, exit_code) = self.execute_native_raw(cmd, args)?;
-        Ok((String::from_utf8_lossy(&stdout).to_string(), exit_code))
+        Ok((self.decode_output(&stdout), exit_code))
     }
 
     /// Execute a native command, capturing raw stdout/stderr bytes (issue #25).
@@ -99,32 +153,54 @@ impl Evaluator {
         cmd: &str,
         args: Vec<String>,
     ) -> Result<(Vec<u8>, Vec<u8>, i32), EvalError> {
+        let cmd = &self.corrected(cmd);
+        let audit = self.start_process("command", cmd, &args)?;
+
         // Only run interactively if:
         // 1. capture_mode is false (nothing will consume the output)
         // 2. stdout is a TTY (we're in an interactive context)
-        let run_interactive = !self.capture_mode && Self::is_interactive();
+        // unless `live` or `capture` decided for this command, or
+        // `tty-guard` moves a captured interactive command to the terminal
+        let run_interactive = match self.capture_override.take() {
+            Some(capture) => !capture,
+            None if self.capture_mode => self.guarded_to_tty(cmd),
+            None => Self::is_interactive(),
+        };
 
         if run_interactive {
             // Run interactively - output goes directly to terminal
-            let status = Command::new(cmd)
-                .args(&args)
-                .current_dir(&self.cwd)
-                .stdin(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .status()
-                .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd, e)))?;
+            let output = self
+                .run_foreground(
+                    Command::new(cmd)
+                        .args(&args)
+                        .current_dir(&self.cwd)
+                        .stdin(Stdio::inherit())
+                        .stdout(Stdio::inherit())
+                        .stderr(Stdio::inherit()),
+                )
+                .map_err(|e| self.spawn_error(cmd, e))?;
 
-            Ok((Vec::new(), Vec::new(), status.code().unwrap_or(-1)))
+            let exit_code = output.status.code().unwrap_or(-1);
+            audit.finish(exit_code);
+            Ok((Vec::new(), Vec::new(), exit_code))
         } else {
             // Capture output (for piping, scripts, tests, or when output is consumed)
-            let output = Command::new(cmd)
-                .args(&args)
-                .current_dir(&self.cwd)
-                .output()
-                .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd, e)))?;
+            let output = self
+                .run_foreground(
+                    Command::new(cmd)
+                        .args(&args)
+                        .current_dir(&self.cwd)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped()),
+                )
+                .map_err(|e| self.spawn_error(cmd, e))?;
 
             let exit_code = output.status.code().unwrap_or(-1);
+            audit.finish(exit_code);
+            if let Some(sink) = &mut self.stderr_sink {
+                sink.extend_from_slice(&output.stderr);
+            }
 
             Ok((output.stdout, output.stderr, exit_code))
         }
@@ -183,6 +259,7 @@ impl Evaluator {
             ".alias" => Some(self.builtin_alias(args)),
             ".unalias" => Some(self.builtin_unalias(args)),
             ".trap" => Some(self.builtin_trap(args)),
+            ".edit-def" => Some(self.builtin_edit_def(args)),
             // Stack-native predicates
             "file?" => Some(self.builtin_file_predicate(args)),
             "dir?" => Some(self.builtin_dir_predicate(args)),
@@ -236,6 +313,9 @@ impl Evaluator {
     /// exception: `len` returns Ok(false) for non-Bytes values so the
     /// string-argument `len` in `try_builtin` can handle them.
     pub(crate) fn try_structured_builtin(&mut self, cmd: &str) -> Result<bool, EvalError> {
+        if self.nil_string_op(cmd) {
+            return Ok(true);
+        }
         match cmd {
             // Local variable (stack-native to preserve structured values)
             "local" | ".local" => {
@@ -256,6 +336,14 @@ impl Evaluator {
                 self.builtin_get()?;
                 Ok(true)
             }
+            "get?" => {
+                self.builtin_get_checked()?;
+                Ok(true)
+            }
+            "get-or" => {
+                self.builtin_get_or()?;
+                Ok(true)
+            }
             "set" => {
                 self.builtin_set()?;
                 Ok(true)
@@ -360,6 +448,34 @@ impl Evaluator {
                 self.builtin_throw()?;
                 Ok(true)
             }
+            "validate" => {
+                self.builtin_validate()?;
+                Ok(true)
+            }
+            // Exit statuses as values
+            // Only with a block to run; otherwise a word like `status git`
+            "status" if matches!(self.stack.last(), Some(Value::Block(_))) => {
+                self.builtin_status()?;
+                Ok(true)
+            }
+            "ok?" => {
+                self.builtin_status_predicate(true)?;
+                Ok(true)
+            }
+            "failed?" => {
+                self.builtin_status_predicate(false)?;
+                Ok(true)
+            }
+            // Anything but a status falls through, so `file.txt code`
+            // still runs an external `code`
+            "code" => {
+                if let Some(Value::Status(_) | Value::Error { .. }) = self.stack.last() {
+                    self.builtin_status_code()?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
             // Phase 4: Serialization
             // into-X = serialize (structured -> text), from-X = parse (text -> structured)
             "into-json" | "to-json" => {
@@ -394,6 +510,26 @@ impl Evaluator {
                 self.builtin_into_kv()?;
                 Ok(true)
             }
+            "from-ini" => {
+                self.builtin_from_ini()?;
+                Ok(true)
+            }
+            "into-ini" | "to-ini" => {
+                self.builtin_to_ini()?;
+                Ok(true)
+            }
+            "from-env" => {
+                self.builtin_from_env()?;
+                Ok(true)
+            }
+            "into-env" | "to-env" => {
+                self.builtin_to_env()?;
+                Ok(true)
+            }
+            "from-passwd" => {
+                self.builtin_from_passwd()?;
+                Ok(true)
+            }
             "into-tsv" | "to-tsv" => {
                 self.builtin_to_tsv()?;
                 Ok(true)
@@ -406,6 +542,14 @@ impl Evaluator {
                 self.builtin_to_delimited()?;
                 Ok(true)
             }
+            "to-clipboard-tsv" => {
+                self.builtin_to_clipboard_tsv()?;
+                Ok(true)
+            }
+            "to-xlsx" => {
+                self.builtin_to_xlsx()?;
+                Ok(true)
+            }
             // Phase 5: Stack utilities
             "tap" => {
                 self.builtin_tap()?;
@@ -497,6 +641,34 @@ impl Evaluator {
                 self.builtin_five_num()?;
                 Ok(true)
             }
+            "series" => {
+                self.builtin_series()?;
+                Ok(true)
+            }
+            "series-stats" => {
+                self.builtin_series_stats()?;
+                Ok(true)
+            }
+            "sparkline" => {
+                self.builtin_sparkline()?;
+                Ok(true)
+            }
+            "fake-name" => {
+                self.builtin_fake_name()?;
+                Ok(true)
+            }
+            "fake-email" => {
+                self.builtin_fake_email()?;
+                Ok(true)
+            }
+            "fake-rows" => {
+                self.builtin_fake_rows()?;
+                Ok(true)
+            }
+            "fake-seed" => {
+                self.builtin_fake_seed()?;
+                Ok(true)
+            }
             // Phase 8: Extended table ops
             "group-by" => {
                 self.builtin_group_by()?;
@@ -550,6 +722,10 @@ impl Evaluator {
                 self.builtin_duplicates()?;
                 Ok(true)
             }
+            "freq" => {
+                self.builtin_freq()?;
+                Ok(true)
+            }
             // Extended spread operations
             "fields" => {
                 self.builtin_fields()?;
@@ -575,6 +751,66 @@ impl Evaluator {
                 self.builtin_spread_to()?;
                 Ok(true)
             }
+            // List-native operations
+            "list-push" => {
+                self.builtin_list_push()?;
+                Ok(true)
+            }
+            "list-pop" => {
+                self.builtin_list_pop()?;
+                Ok(true)
+            }
+            "list-get" => {
+                self.builtin_list_get()?;
+                Ok(true)
+            }
+            "list-len" => {
+                self.builtin_list_len()?;
+                Ok(true)
+            }
+            "list-slice" => {
+                self.builtin_list_slice()?;
+                Ok(true)
+            }
+            "list-map" => {
+                self.builtin_list_map()?;
+                Ok(true)
+            }
+            "list-filter" => {
+                self.builtin_list_filter()?;
+                Ok(true)
+            }
+            "enumerate" => {
+                self.builtin_enumerate()?;
+                Ok(true)
+            }
+            // Numbers; flags (`-w`, `-s`) run the seq command
+            "seq" if self.seq_is_native() => {
+                self.builtin_seq()?;
+                Ok(true)
+            }
+            // Lists and spread items; anything else runs the sort command
+            "sort" => {
+                if self.sort_is_native() {
+                    self.builtin_sort()?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            // A count on a List, Table, or text; anything else runs the command
+            "head" | "tail" if self.head_tail_is_native() => {
+                if cmd == "head" {
+                    self.builtin_head()?;
+                } else {
+                    self.builtin_tail()?;
+                }
+                Ok(true)
+            }
+            "sample" => {
+                self.builtin_sample()?;
+                Ok(true)
+            }
             // Phase 9: Vector operations
             "dot-product" => {
                 self.builtin_dot_product()?;
@@ -613,15 +849,79 @@ impl Evaluator {
                 self.builtin_retry()?;
                 Ok(true)
             }
+            "cached" => {
+                self.builtin_cached()?;
+                Ok(true)
+            }
+            "refresh" => {
+                self.builtin_refresh()?;
+                Ok(true)
+            }
+            "cache-clear" => {
+                self.builtin_cache_clear()?;
+                Ok(true)
+            }
+            "rate-limit" => {
+                self.builtin_rate_limit()?;
+                Ok(true)
+            }
+            "per-second" => {
+                self.builtin_per_window("per-second", 1.0)?;
+                Ok(true)
+            }
+            "per-minute" => {
+                self.builtin_per_window("per-minute", 60.0)?;
+                Ok(true)
+            }
+            "per-hour" => {
+                self.builtin_per_window("per-hour", 3600.0)?;
+                Ok(true)
+            }
+            "edit-file" => {
+                self.builtin_edit_file()?;
+                Ok(true)
+            }
+            "edit-lines" => {
+                self.builtin_edit_lines()?;
+                Ok(true)
+            }
+            "make-patch" => {
+                self.builtin_make_patch()?;
+                Ok(true)
+            }
+            "apply-patch" => {
+                self.builtin_apply_patch()?;
+                Ok(true)
+            }
+            "apply-patch-file" => {
+                self.builtin_apply_patch_file()?;
+                Ok(true)
+            }
+            "changed?" => {
+                self.builtin_changed()?;
+                Ok(true)
+            }
             "compose" => {
                 self.builtin_compose()?;
                 Ok(true)
             }
+            "and-then" => {
+                self.builtin_and_then()?;
+                Ok(true)
+            }
+            "or-else" => {
+                self.builtin_or_else()?;
+                Ok(true)
+            }
             // Phase 11: Additional parsers (from-X aliases for parsing)
             "from-delimited" | "into-delimited" => {
                 self.builtin_into_delimited()?;
                 Ok(true)
             }
+            "columns" => {
+                self.builtin_columns()?;
+                Ok(true)
+            }
             // Structured builtins
             // Structured-returning core builtins (issue #27)
             "ls-t" => {
@@ -648,6 +948,22 @@ impl Evaluator {
                 self.builtin_ls_table()?;
                 Ok(true)
             }
+            "find-where" => {
+                self.builtin_find_where()?;
+                Ok(true)
+            }
+            "grep-table" => {
+                self.builtin_grep_table()?;
+                Ok(true)
+            }
+            "edit-at" => {
+                self.builtin_edit_at()?;
+                Ok(true)
+            }
+            "doc-view" => {
+                self.builtin_doc_view()?;
+                Ok(true)
+            }
             "open" => {
                 self.builtin_open()?;
                 Ok(true)
@@ -686,6 +1002,109 @@ impl Evaluator {
                 self.builtin_link_info()?;
                 Ok(true)
             }
+            "pipefail" => {
+                self.builtin_pipefail()?;
+                Ok(true)
+            }
+            "pipeline" => {
+                self.builtin_pipeline()?;
+                Ok(true)
+            }
+            "heredoc" => {
+                self.builtin_heredoc()?;
+                Ok(true)
+            }
+            // Not `config`, which is an argument to git, npm, gcloud, ...
+            "hsab-config" => {
+                self.builtin_config()?;
+                Ok(true)
+            }
+            // Capture-mode overrides for the next command
+            "live" => {
+                self.capture_override = Some(false);
+                Ok(true)
+            }
+            "capture" => {
+                self.capture_override = Some(true);
+                Ok(true)
+            }
+            "tty-guard" => {
+                self.builtin_tty_guard()?;
+                Ok(true)
+            }
+            ".import-watch" => {
+                self.builtin_import_watch()?;
+                Ok(true)
+            }
+            "with-stderr" => {
+                self.builtin_with_stderr()?;
+                Ok(true)
+            }
+            // Logging
+            "log-debug" => {
+                self.builtin_log(LogLevel::Debug)?;
+                Ok(true)
+            }
+            "log-info" => {
+                self.builtin_log(LogLevel::Info)?;
+                Ok(true)
+            }
+            "log-warn" => {
+                self.builtin_log(LogLevel::Warn)?;
+                Ok(true)
+            }
+            "log-error" => {
+                self.builtin_log(LogLevel::Error)?;
+                Ok(true)
+            }
+            "log-level" => {
+                self.builtin_log_level()?;
+                Ok(true)
+            }
+            "log-format" => {
+                self.builtin_log_format()?;
+                Ok(true)
+            }
+            // Checkpoint/resume
+            "checkpoint" => {
+                self.builtin_checkpoint()?;
+                Ok(true)
+            }
+            // Explain mode
+            "explain" => {
+                self.builtin_explain()?;
+                Ok(true)
+            }
+            "to-bash" => {
+                self.builtin_to_bash()?;
+                Ok(true)
+            }
+            // Script arguments
+            "args" => {
+                self.builtin_args()?;
+                Ok(true)
+            }
+            "parse-args" => {
+                self.builtin_parse_args()?;
+                Ok(true)
+            }
+            // ANSI text utilities
+            "strip-ansi" => {
+                self.builtin_strip_ansi()?;
+                Ok(true)
+            }
+            "ansi-length" => {
+                self.builtin_ansi_length()?;
+                Ok(true)
+            }
+            "colorize" => {
+                self.builtin_colorize()?;
+                Ok(true)
+            }
+            "gauge" => {
+                self.builtin_gauge()?;
+                Ok(true)
+            }
             // Clipboard operations (OSC 52)
             ".copy" => {
                 self.builtin_clip_copy()?;
@@ -724,6 +1143,64 @@ impl Evaluator {
                 self.builtin_read_bytes()?;
                 Ok(true)
             }
+            "read-file" => {
+                self.builtin_read_file()?;
+                Ok(true)
+            }
+            "bytes-len" => {
+                self.builtin_bytes_len()?;
+                Ok(true)
+            }
+            "bytes-slice" => {
+                self.builtin_bytes_slice()?;
+                Ok(true)
+            }
+            "bytes-concat" => {
+                self.builtin_bytes_concat()?;
+                Ok(true)
+            }
+            "bytes-find" => {
+                self.builtin_bytes_find()?;
+                Ok(true)
+            }
+            // Bytes; anything else runs the hexdump command
+            "hexdump" if matches!(self.stack.last(), Some(Value::Bytes(_))) => {
+                self.builtin_hexdump()?;
+                Ok(true)
+            }
+            // Bytes or text that isn't a file; otherwise the commands run
+            "gzip" if self.compress_is_native() => {
+                self.builtin_gzip()?;
+                Ok(true)
+            }
+            "gunzip" if self.compress_is_native() => {
+                self.builtin_gunzip()?;
+                Ok(true)
+            }
+            "zstd" if self.compress_is_native() => {
+                self.builtin_zstd()?;
+                Ok(true)
+            }
+            "unzstd" if self.compress_is_native() => {
+                self.builtin_unzstd()?;
+                Ok(true)
+            }
+            "encode" => {
+                self.builtin_encode()?;
+                Ok(true)
+            }
+            "decode" => {
+                self.builtin_decode()?;
+                Ok(true)
+            }
+            "set-encoding" => {
+                self.builtin_set_encoding()?;
+                Ok(true)
+            }
+            "encoding" => {
+                self.builtin_encoding()?;
+                Ok(true)
+            }
             // Hash functions (SHA-2)
             "sha256" => {
                 self.builtin_sha256()?;
@@ -839,6 +1316,14 @@ impl Evaluator {
                 self.builtin_ne_stack()?;
                 Ok(true)
             }
+            "eq-deep?" => {
+                self.builtin_eq_deep()?;
+                Ok(true)
+            }
+            "compare" => {
+                self.builtin_compare()?;
+                Ok(true)
+            }
             "=?" => {
                 self.builtin_num_eq_stack()?;
                 Ok(true)
@@ -898,6 +1383,14 @@ impl Evaluator {
                 self.builtin_decrement()?;
                 Ok(true)
             }
+            "inc!" => {
+                self.builtin_step_var("inc!", true)?;
+                Ok(true)
+            }
+            "dec!" => {
+                self.builtin_step_var("dec!", false)?;
+                Ok(true)
+            }
             "sqrt" => {
                 self.builtin_sqrt()?;
                 Ok(true)
@@ -975,6 +1468,22 @@ impl Evaluator {
                 self.builtin_futures_list()?;
                 Ok(true)
             }
+            "jobs-table" => {
+                self.builtin_jobs_table()?;
+                Ok(true)
+            }
+            "job-output" => {
+                self.builtin_job_output()?;
+                Ok(true)
+            }
+            "disown" => {
+                self.builtin_disown()?;
+                Ok(true)
+            }
+            "nohup" => {
+                self.builtin_nohup()?;
+                Ok(true)
+            }
             "future-map" => {
                 self.builtin_future_map()?;
                 Ok(true)
@@ -996,6 +1505,92 @@ impl Evaluator {
                 self.builtin_fetch_headers()?;
                 Ok(true)
             }
+            "file-part" => {
+                self.builtin_file_part()?;
+                Ok(true)
+            }
+            "text-part" => {
+                self.builtin_text_part()?;
+                Ok(true)
+            }
+            "http-post-multipart" => {
+                self.builtin_http_post_multipart()?;
+                Ok(true)
+            }
+            "oauth2-token" => {
+                self.builtin_oauth2_token()?;
+                Ok(true)
+            }
+            "dns-lookup" => {
+                self.builtin_dns_lookup()?;
+                Ok(true)
+            }
+            "dns-query" => {
+                self.builtin_dns_query()?;
+                Ok(true)
+            }
+            "rdns" => {
+                self.builtin_rdns()?;
+                Ok(true)
+            }
+            "reachable?" => {
+                self.builtin_reachable()?;
+                Ok(true)
+            }
+            "icmp-ping" => {
+                self.builtin_icmp_ping()?;
+                Ok(true)
+            }
+            "http-healthy?" => {
+                self.builtin_http_healthy()?;
+                Ok(true)
+            }
+            "url-parse" => {
+                self.builtin_url_parse()?;
+                Ok(true)
+            }
+            "url-build" => {
+                self.builtin_url_build()?;
+                Ok(true)
+            }
+            "url-encode" => {
+                self.builtin_url_encode()?;
+                Ok(true)
+            }
+            "url-decode" => {
+                self.builtin_url_decode()?;
+                Ok(true)
+            }
+            // Cloud CLI bridges; single words run the real CLI
+            "aws" if self.cloud_is_native() => {
+                self.builtin_aws()?;
+                Ok(true)
+            }
+            "gcloud" if self.cloud_is_native() => {
+                self.builtin_gcloud()?;
+                Ok(true)
+            }
+            "mail-send" => {
+                self.builtin_mail_send()?;
+                Ok(true)
+            }
+            // Code host APIs
+            "gh-api" => {
+                self.builtin_gh_api()?;
+                Ok(true)
+            }
+            "gl-api" => {
+                self.builtin_gl_api()?;
+                Ok(true)
+            }
+            "pr-list" => {
+                self.builtin_pr_list()?;
+                Ok(true)
+            }
+            "issue-create" => {
+                self.builtin_issue_create()?;
+                Ok(true)
+            }
             // Macro-generated builtins (proof of concept)
             "abs" => {
                 self.builtin_abs()?;
@@ -1061,6 +1656,20 @@ impl Evaluator {
                 self.builtin_watch()?;
                 Ok(true)
             }
+            #[cfg(feature = "plugins")]
+            "follow" => {
+                self.builtin_follow()?;
+                Ok(true)
+            }
+            #[cfg(feature = "plugins")]
+            "follow-paged" => {
+                self.builtin_follow_paged()?;
+                Ok(true)
+            }
+            "tail-file" => {
+                self.builtin_tail_file()?;
+                Ok(true)
+            }
             // Stack-native shell operations (override existing where applicable)
             "cd" | ".cd" => {
                 self.builtin_cd_native()?;
@@ -1094,6 +1703,23 @@ impl Evaluator {
                 self.builtin_mv()?;
                 Ok(true)
             }
+            // Glob confirmation
+            "force" => {
+                self.builtin_force()?;
+                Ok(true)
+            }
+            "confirm-glob" => {
+                self.builtin_confirm_glob()?;
+                Ok(true)
+            }
+            "autocorrect" => {
+                self.builtin_autocorrect()?;
+                Ok(true)
+            }
+            "rm" | "rm-r" if self.rm_trashes() => {
+                self.builtin_rm_to_trash()?;
+                Ok(true)
+            }
             "rm" => {
                 self.builtin_rm()?;
                 Ok(true)
@@ -1102,6 +1728,35 @@ impl Evaluator {
                 self.builtin_rm_r()?;
                 Ok(true)
             }
+            // Trash
+            "trash" => {
+                self.builtin_trash()?;
+                Ok(true)
+            }
+            "trash-list" => {
+                self.builtin_trash_list()?;
+                Ok(true)
+            }
+            "trash-restore" => {
+                self.builtin_trash_restore()?;
+                Ok(true)
+            }
+            "rm-trash" => {
+                self.builtin_rm_trash()?;
+                Ok(true)
+            }
+            "copy" => {
+                self.builtin_copy()?;
+                Ok(true)
+            }
+            "move" => {
+                self.builtin_move()?;
+                Ok(true)
+            }
+            "remove" => {
+                self.builtin_remove()?;
+                Ok(true)
+            }
             "ln" => {
                 self.builtin_ln()?;
                 Ok(true)
@@ -1114,6 +1769,26 @@ impl Evaluator {
                 self.builtin_which_native()?;
                 Ok(true)
             }
+            "sleep" => {
+                self.builtin_sleep()?;
+                Ok(true)
+            }
+            "duration" => {
+                self.builtin_duration()?;
+                Ok(true)
+            }
+            "size-bytes" => {
+                self.builtin_size_bytes()?;
+                Ok(true)
+            }
+            "to-unit" => {
+                self.builtin_to_unit()?;
+                Ok(true)
+            }
+            "printf" | ".printf" => {
+                self.builtin_printf_values()?;
+                Ok(true)
+            }
             // Note: dirname/basename handled by parser as Expr::Dirname/Basename
             "extname" => {
                 self.builtin_extname()?;
@@ -1127,6 +1802,45 @@ impl Evaluator {
                 self.builtin_ls_native()?;
                 Ok(true)
             }
+            // Users and permissions
+            "whoami" => {
+                self.builtin_whoami()?;
+                Ok(true)
+            }
+            "id-record" => {
+                self.builtin_id_record()?;
+                Ok(true)
+            }
+            "is-root?" => {
+                self.builtin_is_root()?;
+                Ok(true)
+            }
+            "chmod-sym" => {
+                self.builtin_chmod_sym()?;
+                Ok(true)
+            }
+            "chown" => {
+                self.builtin_chown()?;
+                Ok(true)
+            }
+            // Disk space
+            "min-free?" => {
+                self.builtin_min_free()?;
+                Ok(true)
+            }
+            "mount-point" => {
+                self.builtin_mount_point()?;
+                Ok(true)
+            }
+            "fs-type" => {
+                self.builtin_fs_type()?;
+                Ok(true)
+            }
+            // System information
+            "sysinfo" => {
+                self.builtin_sysinfo()?;
+                Ok(true)
+            }
             _ => Ok(false),
         }
     }
@@ -1197,7 +1911,7 @@ impl Evaluator {
     pub(crate) fn sync_stack_to_plugins(&self) {
         if let Ok(mut shared) = self.shared_stack.lock() {
             shared.clear();
-            shared.extend(self.stack.clone());
+            shared.extend(self.stack.iter().cloned());
         }
     }
 
@@ -1205,7 +1919,7 @@ impl Evaluator {
     #[cfg(feature = "plugins")]
     pub(crate) fn sync_stack_from_plugins(&mut self) {
         if let Ok(shared) = self.shared_stack.lock() {
-            self.stack = shared.clone();
+            self.stack = shared.clone().into();
         }
     }
 }
@@ -0,0 +1,98 @@
+//! Compression: `gzip`, `gunzip`, `zstd`, and `unzstd`
+//!
+//! ```text
+//! "app.log.gz" read-file gunzip spread     → one line per item
+//! "hello" gzip gunzip                      → "hello"
+//! "dump.sql" read-file zstd                → Bytes
+//! ```
+//!
+//! `gzip` and `zstd` take Bytes or text and give Bytes. `gunzip` and
+//! `unzstd` give text when what comes out is UTF-8 and Bytes when it isn't.
+//! These are the commands of the same names unless the top of the stack is
+//! Bytes, or text that is neither a flag nor an existing file: `"app.log"
+//! gzip` still compresses the file in place.
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+use std::io::{Read, Write};
+
+/// Text when the bytes are UTF-8, Bytes when they aren't
+fn text_or_bytes(data: Vec<u8>) -> Value {
+    match String::from_utf8(data) {
+        Ok(text) => Value::Literal(text),
+        Err(e) => Value::Bytes(e.into_bytes()),
+    }
+}
+
+impl Evaluator {
+    /// Whether `gzip` and friends are hsab's for the value on top
+    pub(crate) fn compress_is_native(&self) -> bool {
+        match self.stack.last() {
+            Some(Value::Bytes(_)) | Some(Value::Media { .. }) => true,
+            Some(Value::Literal(s)) | Some(Value::Output(s)) => {
+                !s.starts_with('-') && !std::path::Path::new(&self.expand_tilde(s)).exists()
+            }
+            _ => false,
+        }
+    }
+
+    /// gzip: value gzip → Bytes
+    pub(crate) fn builtin_gzip(&mut self) -> Result<(), EvalError> {
+        let data = self.pop_bytes("gzip")?;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let compressed = encoder
+            .write_all(&data)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| EvalError::ExecError(format!("gzip: {}", e)))?;
+        self.stack.push(Value::Bytes(compressed));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// gunzip: bytes gunzip → text or Bytes
+    /// Reads every member, as `zcat` does with concatenated .gz files
+    pub(crate) fn builtin_gunzip(&mut self) -> Result<(), EvalError> {
+        let data = self.pop_bytes("gunzip")?;
+        let mut out = Vec::new();
+        flate2::read::MultiGzDecoder::new(data.as_slice())
+            .read_to_end(&mut out)
+            .map_err(|e| EvalError::ExecError(format!("gunzip: {}", e)))?;
+        self.stack.push(text_or_bytes(out));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// zstd: value zstd → Bytes
+    pub(crate) fn builtin_zstd(&mut self) -> Result<(), EvalError> {
+        use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+        let data = self.pop_bytes("zstd")?;
+        let compressed = compress_to_vec(data.as_slice(), CompressionLevel::Fastest);
+        self.stack.push(Value::Bytes(compressed));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// unzstd: bytes unzstd → text or Bytes
+    /// Reads every frame, so concatenated .zst files come out whole
+    pub(crate) fn builtin_unzstd(&mut self) -> Result<(), EvalError> {
+        use ruzstd::decoding::StreamingDecoder;
+
+        let data = self.pop_bytes("unzstd")?;
+        let mut source = data.as_slice();
+        let mut out = Vec::new();
+        loop {
+            let mut decoder = StreamingDecoder::new(&mut source)
+                .map_err(|e| EvalError::ExecError(format!("unzstd: {}", e)))?;
+            decoder
+                .read_to_end(&mut out)
+                .map_err(|e| EvalError::ExecError(format!("unzstd: {}", e)))?;
+            if source.is_empty() {
+                break;
+            }
+        }
+        self.stack.push(text_or_bytes(out));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
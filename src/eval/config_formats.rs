@@ -0,0 +1,329 @@
+//! INI files, `.env` files, and the colon-separated tables of `/etc`
+//!
+//! `from-ini` parses an INI file into a record of sections, each a record
+//! of its keys; keys before the first section are top-level fields. `;` and
+//! `#` start comment lines, `key = value` and `key: value` both work, and a
+//! key alone has an empty value. `to-ini` writes such a record back.
+//!
+//! `from-env` parses a `.env` file into a record, as shells and dotenv
+//! libraries read it: `export` before a name is allowed, double-quoted
+//! values have escapes (`\n`, `\"`), single-quoted ones are taken as they
+//! are, and ` #` starts a comment after an unquoted value. `to-env` writes
+//! a record back, quoting the values that need it.
+//!
+//! `from-passwd` parses `/etc/passwd` (seven fields) or `/etc/group` (four,
+//! with `members` a list) into a Table, with ids as Ints.
+//!
+//! Values stay strings: none of these formats has types of its own.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+const PASSWD_COLUMNS: [&str; 7] = ["name", "password", "uid", "gid", "gecos", "home", "shell"];
+const GROUP_COLUMNS: [&str; 4] = ["name", "password", "gid", "members"];
+
+/// `value` without the quotes around it, if it has a matching pair
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+fn parse_ini(text: &str) -> Result<IndexMap<String, Value>, String> {
+    let mut record: IndexMap<String, Value> = IndexMap::new();
+    let mut section: Option<String> = None;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name
+                .strip_suffix(']')
+                .ok_or_else(|| format!("line {}: unclosed section header", n + 1))?
+                .trim()
+                .to_string();
+            // A section given twice is one section
+            record
+                .entry(name.clone())
+                .or_insert_with(|| Value::Map(IndexMap::new()));
+            section = Some(name);
+            continue;
+        }
+        let (key, value) = match line.find(['=', ':']) {
+            Some(at) => (line[..at].trim(), unquote(line[at + 1..].trim())),
+            None => (line, ""),
+        };
+        let value = Value::Literal(value.to_string());
+        match &section {
+            Some(name) => {
+                if let Some(Value::Map(keys)) = record.get_mut(name) {
+                    keys.insert(key.to_string(), value);
+                }
+            }
+            None => {
+                record.insert(key.to_string(), value);
+            }
+        }
+    }
+    Ok(record)
+}
+
+/// An INI value, quoted if its spaces or comment marks wouldn't survive
+fn ini_value(value: &Value) -> String {
+    let text = value.as_arg().unwrap_or_default();
+    if text != text.trim() || text.contains([';', '#']) {
+        format!("\"{}\"", text)
+    } else {
+        text
+    }
+}
+
+fn render_ini(record: &IndexMap<String, Value>) -> Result<String, EvalError> {
+    let mut out = String::new();
+    // Top-level fields come before the first section header
+    for (key, value) in record.iter().filter(|(_, v)| !matches!(v, Value::Map(_))) {
+        out.push_str(&format!("{} = {}\n", key, ini_value(value)));
+    }
+    for (name, value) in record {
+        let Value::Map(keys) = value else {
+            continue;
+        };
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", name));
+        for (key, value) in keys {
+            if let Value::Map(_) = value {
+                return Err(EvalError::ExecError(format!(
+                    "to-ini: {}.{}: sections can't nest",
+                    name, key
+                )));
+            }
+            out.push_str(&format!("{} = {}\n", key, ini_value(value)));
+        }
+    }
+    Ok(out)
+}
+
+fn parse_env(text: &str) -> Result<IndexMap<String, Value>, String> {
+    let mut record = IndexMap::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (name, raw) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected NAME=value", n + 1))?;
+        let raw = raw.trim_start();
+
+        let value = if let Some(rest) = raw.strip_prefix('"') {
+            // Double quotes may span lines and have escapes
+            let mut value = String::new();
+            let mut chars = rest.chars();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some(c) => value.push(c),
+                        None => value.push('\\'),
+                    },
+                    Some(c) => value.push(c),
+                    None => match lines.next() {
+                        Some((_, next)) => {
+                            value.push('\n');
+                            chars = next.chars();
+                        }
+                        None => return Err(format!("line {}: unclosed quote", n + 1)),
+                    },
+                }
+            }
+            value
+        } else if let Some(rest) = raw.strip_prefix('\'') {
+            rest.split_once('\'')
+                .ok_or_else(|| format!("line {}: unclosed quote", n + 1))?
+                .0
+                .to_string()
+        } else {
+            let end = raw.find(" #").unwrap_or(raw.len());
+            raw[..end].trim_end().to_string()
+        };
+        record.insert(name.trim().to_string(), Value::Literal(value));
+    }
+    Ok(record)
+}
+
+/// A `.env` value, double-quoted with escapes unless it's plain
+fn env_value(value: &Value) -> String {
+    let text = value.as_arg().unwrap_or_default();
+    let plain = text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.,/:@%+=".contains(c));
+    if plain {
+        return text;
+    }
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn render_env(record: &IndexMap<String, Value>) -> String {
+    record
+        .iter()
+        .map(|(name, value)| format!("{}={}\n", name, env_value(value)))
+        .collect()
+}
+
+fn parse_passwd(text: &str) -> Result<Value, String> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.starts_with('#'))
+        .collect();
+    let width = lines.first().map_or(7, |(_, l)| l.split(':').count());
+    let columns: &[&str] = match width {
+        7 => &PASSWD_COLUMNS,
+        4 => &GROUP_COLUMNS,
+        _ => {
+            return Err(format!(
+                "expected 7 fields (passwd) or 4 (group), got {}",
+                width
+            ))
+        }
+    };
+
+    let mut rows = Vec::new();
+    for (n, line) in lines {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() != width {
+            return Err(format!(
+                "line {}: expected {} fields, got {}",
+                n + 1,
+                width,
+                fields.len()
+            ));
+        }
+        let row = columns
+            .iter()
+            .zip(fields)
+            .map(|(column, field)| match *column {
+                "uid" | "gid" => field
+                    .parse::<i64>()
+                    .map(Value::Int)
+                    .unwrap_or_else(|_| Value::Literal(field.to_string())),
+                "members" => Value::List(
+                    field
+                        .split(',')
+                        .filter(|m| !m.is_empty())
+                        .map(|m| Value::Literal(m.to_string()))
+                        .collect(),
+                ),
+                _ => Value::Literal(field.to_string()),
+            })
+            .collect();
+        rows.push(row);
+    }
+    Ok(Value::Table {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        rows,
+    })
+}
+
+impl Evaluator {
+    /// Pop a record for `op` to write out
+    fn pop_record(&mut self, op: &str) -> Result<IndexMap<String, Value>, EvalError> {
+        match self.pop_value_or_err()? {
+            Value::Map(record) => Ok(record),
+            other => {
+                let got = other.type_name().to_string();
+                self.stack.push(other);
+                Err(EvalError::TypeError {
+                    expected: format!("record ({})", op),
+                    got,
+                })
+            }
+        }
+    }
+
+    /// from-ini: text from-ini → { section { key value } }
+    pub(crate) fn builtin_from_ini(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        let record =
+            parse_ini(&text).map_err(|e| EvalError::ExecError(format!("from-ini: {}", e)))?;
+        self.stack.push(Value::Map(record));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// to-ini: { section { key value } } to-ini → text
+    pub(crate) fn builtin_to_ini(&mut self) -> Result<(), EvalError> {
+        let record = self.pop_record("to-ini")?;
+        self.stack.push(Value::Output(render_ini(&record)?));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// from-env: text from-env → { NAME value }
+    pub(crate) fn builtin_from_env(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        let record =
+            parse_env(&text).map_err(|e| EvalError::ExecError(format!("from-env: {}", e)))?;
+        self.stack.push(Value::Map(record));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// to-env: { NAME value } to-env → text
+    pub(crate) fn builtin_to_env(&mut self) -> Result<(), EvalError> {
+        let record = self.pop_record("to-env")?;
+        self.stack.push(Value::Output(render_env(&record)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// from-passwd: text from-passwd → Table
+    /// Parse /etc/passwd or /etc/group lines
+    pub(crate) fn builtin_from_passwd(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        let table =
+            parse_passwd(&text).map_err(|e| EvalError::ExecError(format!("from-passwd: {}", e)))?;
+        self.stack.push(table);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
+
+/// The text `save` writes for a record to a `.ini` or `.env` file
+pub(crate) fn render_config(kind: &str, value: &Value) -> Option<Result<String, EvalError>> {
+    let Value::Map(record) = value else {
+        return None;
+    };
+    match kind {
+        "ini" => Some(render_ini(record)),
+        "env" => Some(Ok(render_env(record))),
+        _ => None,
+    }
+}
@@ -0,0 +1,115 @@
+//! Confirming destructive globs
+//!
+//! In the interactive shell, `rm` and `mv` (the builtins, or the PATH
+//! commands) list what a glob matched and ask before going on when it
+//! matched more than `confirm-glob`'s threshold of paths (10 unless set;
+//! `0 confirm-glob` turns the prompt off). Paths typed out in full never
+//! prompt, only those a pattern expanded to. `force` before the command
+//! skips the prompt once:
+//!
+//! ```text
+//! force *.log rm
+//! ```
+
+use std::io::{BufRead, Write};
+
+use super::{EvalError, Evaluator};
+
+/// Paths matched before `rm` or `mv` asks, unless `confirm-glob` says
+pub(crate) const DEFAULT_CONFIRM_GLOB: usize = 10;
+
+/// A glob pattern and the paths it expanded to
+#[derive(Debug, Clone)]
+pub(crate) struct Expansion {
+    pub(crate) pattern: String,
+    pub(crate) paths: Vec<String>,
+}
+
+impl Evaluator {
+    /// force: force rm
+    /// Skip the glob confirmation for the next `rm` or `mv`
+    pub(crate) fn builtin_force(&mut self) -> Result<(), EvalError> {
+        self.force_next = true;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// confirm-glob: N confirm-glob
+    /// Ask before `rm` or `mv` acts on a glob matching more than N paths
+    /// (0 never asks)
+    pub(crate) fn builtin_confirm_glob(&mut self) -> Result<(), EvalError> {
+        let threshold = self.pop_int("confirm-glob")?;
+        self.confirm_glob = usize::try_from(threshold).map_err(|_| {
+            EvalError::ExecError("confirm-glob: threshold can't be negative".into())
+        })?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// The first expansion that needs confirming, using up a pending `force`
+    pub(crate) fn unconfirmed_glob<'a>(&mut self, globs: &'a [Expansion]) -> Option<&'a Expansion> {
+        let forced = std::mem::take(&mut self.force_next);
+        if forced || !self.interactive || self.confirm_glob == 0 {
+            return None;
+        }
+        globs
+            .iter()
+            .find(|glob| glob.paths.len() > self.confirm_glob)
+    }
+
+    /// Ask on the terminal before `cmd` acts on large glob expansions,
+    /// failing if the answer isn't yes
+    pub(crate) fn confirm_globs(
+        &mut self,
+        cmd: &str,
+        globs: &[Expansion],
+    ) -> Result<(), EvalError> {
+        let Some(glob) = self.unconfirmed_glob(globs) else {
+            return Ok(());
+        };
+        let mut list = format!(
+            "{}: {} matches {} paths:\n",
+            cmd,
+            glob.pattern,
+            glob.paths.len()
+        );
+        for path in &glob.paths {
+            list.push_str(&format!("  {}\n", path));
+        }
+        if ask(&format!("{}{} them all?", list, cmd)) {
+            Ok(())
+        } else {
+            Err(EvalError::ExecError(format!(
+                "{}: not confirmed (use `force` to skip asking)",
+                cmd
+            )))
+        }
+    }
+
+    /// Check a pattern the `rm` builtins expand themselves
+    pub(crate) fn confirm_glob_paths(
+        &mut self,
+        cmd: &str,
+        pattern: &str,
+        paths: &[std::path::PathBuf],
+    ) -> Result<(), EvalError> {
+        let expansion = Expansion {
+            pattern: pattern.to_string(),
+            paths: paths
+                .iter()
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect(),
+        };
+        self.confirm_globs(cmd, &[expansion])
+    }
+}
+
+/// Ask a yes-or-no question on the terminal, defaulting to no
+pub(crate) fn ask(question: &str) -> bool {
+    let mut stderr = std::io::stderr().lock();
+    let _ = write!(stderr, "{} [y/N] ", question);
+    let _ = stderr.flush();
+    let mut answer = String::new();
+    let _ = std::io::stdin().lock().read_line(&mut answer);
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
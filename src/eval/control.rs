@@ -4,6 +4,7 @@ use crate::ast::Value;
 impl Evaluator {
     /// Check if a value is "truthy" for conditional purposes
     /// - Bool(true) => true, Bool(false) => false
+    /// - Status: success (0) => true
     /// - Number: non-zero => true
     /// - Literal/Output: non-empty => true
     /// - Nil => false
@@ -13,6 +14,7 @@ impl Evaluator {
             Value::Bool(b) => *b,
             Value::Number(n) => *n != 0.0,
             Value::Int(i) => *i != 0,
+            Value::Status(code) => *code == 0,
             Value::Literal(s) | Value::Output(s) => !s.is_empty(),
             Value::Nil => false,
             _ => true,
@@ -245,4 +247,67 @@ impl Evaluator {
 
         Ok(())
     }
+
+    /// Status: #[block] status - run the block and push its exit status.
+    /// The block's output is captured and dropped; a block that runs no
+    /// command succeeds.
+    pub(crate) fn builtin_status(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+
+        let outer_capture_mode = self.capture_mode;
+        self.last_exit_code = 0;
+        self.stack.push(Value::Marker);
+        let result = block.iter().try_for_each(|expr| {
+            self.capture_mode = true;
+            self.eval_expr(expr)
+        });
+        self.capture_mode = outer_capture_mode;
+
+        // Clean up anything the block pushed (until marker)
+        while let Some(v) = self.stack.pop() {
+            if v.is_marker() {
+                break;
+            }
+        }
+        result?;
+
+        self.stack.push(Value::Status(self.last_exit_code));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// ok? / failed?: status ok? - whether a status (or error) succeeded
+    pub(crate) fn builtin_status_predicate(&mut self, want_ok: bool) -> Result<(), EvalError> {
+        let op = if want_ok { "ok?" } else { "failed?" };
+        let ok = match self.pop_value_or_err()? {
+            Value::Status(code) => code == 0,
+            Value::Error { .. } => false,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: format!("Status for {}", op),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        self.stack.push(Value::Bool(ok == want_ok));
+        self.last_exit_code = if ok == want_ok { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// code: status code - the exit code of a status (or error) as an Int
+    pub(crate) fn builtin_status_code(&mut self) -> Result<(), EvalError> {
+        let code = match self.pop_value_or_err()? {
+            Value::Status(code) => code,
+            Value::Error { code, .. } => code.unwrap_or(1),
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "Status for code".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        self.stack.push(Value::Int(code as i64));
+        self.last_exit_code = 0;
+        Ok(())
+    }
 }
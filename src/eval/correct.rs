@@ -0,0 +1,67 @@
+//! Typos in command names
+//!
+//! A command that isn't on PATH fails with the closest names that are
+//! (`gti: command not found (did you mean git?)`), found by edit distance
+//! in the resolver's index of PATH. With `"on" autocorrect` (usually in
+//! `~/.hsabrc`), the interactive shell offers to run the closest one
+//! instead.
+
+use std::io;
+
+use super::confirm::ask;
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+impl Evaluator {
+    /// autocorrect: "on"|"off" autocorrect
+    /// Offer to run the closest command when one isn't found, interactively
+    pub(crate) fn builtin_autocorrect(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        self.autocorrect = match &setting {
+            Value::Bool(on) => *on,
+            other => match other.as_arg().as_deref() {
+                Some("on" | "true" | "1") => true,
+                Some("off" | "false" | "0") => false,
+                _ => {
+                    self.stack.push(setting);
+                    return Err(EvalError::ExecError(
+                        "autocorrect: usage: \"on\" autocorrect or \"off\" autocorrect".into(),
+                    ));
+                }
+            },
+        };
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// The command to run for `cmd`: itself, or with `autocorrect` on in
+    /// the interactive shell, the closest PATH command if the user agrees
+    pub(crate) fn corrected(&mut self, cmd: &str) -> String {
+        if !(self.autocorrect && self.interactive)
+            || cmd.contains('/')
+            || self.resolver.find_executable(cmd).is_some()
+        {
+            return cmd.to_string();
+        }
+        match self.resolver.suggest(cmd).into_iter().next() {
+            Some(best) if ask(&format!("{}: command not found. Run {}?", cmd, best)) => best,
+            _ => cmd.to_string(),
+        }
+    }
+
+    /// The error for `cmd` failing to start, suggesting commands it may
+    /// have been a typo for
+    pub(crate) fn spawn_error(&mut self, cmd: &str, e: io::Error) -> EvalError {
+        if e.kind() != io::ErrorKind::NotFound || cmd.contains('/') {
+            return EvalError::ExecError(format!("{}: {}", cmd, e));
+        }
+        match self.resolver.suggest(cmd).as_slice() {
+            [] => EvalError::ExecError(format!("{}: command not found", cmd)),
+            names => EvalError::ExecError(format!(
+                "{}: command not found (did you mean {}?)",
+                cmd,
+                names.join(", ")
+            )),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Step debugger: the interactive pause prompt, conditional breakpoints,
+//! definition watchpoints, and watch expressions
+
+use super::provenance::Origin;
+use super::{EvalError, Evaluator};
+use crate::ast::{Expr, Value};
+
+/// What the program should do after a debugger pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Pause again before the next expression
+    Step,
+    /// Run until the next breakpoint
+    Continue,
+    /// Stop the program
+    Terminate,
+}
+
+/// A debugger user interface the evaluator hands control to when it
+/// pauses. Without one installed the interactive stdin prompt is used;
+/// the DAP server is the other implementation.
+pub trait DebugFrontend: Send {
+    /// Called at each pause with the evaluator (for stack inspection and
+    /// evaluation), the reason (`step`, `breakpoint`, `function breakpoint`)
+    /// and a description of the expression about to run
+    fn on_pause(&mut self, eval: &mut Evaluator, reason: &str, expr: &str) -> DebugAction;
+}
+
+const DEBUG_PROMPT: &str = "\x1b[90m(n)ext, (c)ontinue, (s)tack, (q)uit debug: \x1b[0m";
+
+/// Parse debugger code such as `depth 5 gt?`, `[depth 5 gt?]`, or
+/// `"#[depth 5 gt?]"` into a block body
+fn parse_debug_code(code: &str) -> Result<Vec<Expr>, String> {
+    let mut src = code.trim();
+    if src.len() >= 2
+        && ((src.starts_with('"') && src.ends_with('"'))
+            || (src.starts_with('\'') && src.ends_with('\'')))
+    {
+        src = src[1..src.len() - 1].trim();
+    }
+    if let Some(inner) = src
+        .strip_prefix("#[")
+        .or_else(|| src.strip_prefix('['))
+        .and_then(|s| s.strip_suffix(']'))
+    {
+        src = inner.trim();
+    }
+    if src.is_empty() {
+        return Err("empty expression".into());
+    }
+    let tokens = crate::lex(src).map_err(|e| e.to_string())?;
+    let program = crate::parse(tokens).map_err(|e| e.to_string())?;
+    Ok(program.expressions)
+}
+
+impl Evaluator {
+    /// Add a breakpoint that pauses when `code` leaves `true` on top of the
+    /// stack (or succeeds with exit code 0 when it leaves no boolean)
+    pub fn add_conditional_breakpoint(&mut self, code: &str) -> Result<(), String> {
+        let block = parse_debug_code(code)?;
+        self.conditional_breakpoints
+            .push((code.trim().to_string(), block));
+        Ok(())
+    }
+
+    /// Pause on entry to and exit from the named definition
+    pub fn add_definition_breakpoint(&mut self, name: String) {
+        self.def_breakpoints.insert(name);
+    }
+
+    /// Print the value of `code` after every step in debug or trace mode
+    pub fn add_watch(&mut self, code: &str) -> Result<(), String> {
+        let block = parse_debug_code(code)?;
+        self.watches.push((code.trim().to_string(), block));
+        Ok(())
+    }
+
+    /// Remove all watch expressions
+    pub fn clear_watches(&mut self) {
+        self.watches.clear();
+    }
+
+    /// Human-readable list of every breakpoint kind, for `.breakpoints`
+    pub fn breakpoint_descriptions(&self) -> Vec<String> {
+        let mut out: Vec<String> = self.breakpoints.iter().cloned().collect();
+        out.sort();
+        out.extend(
+            self.conditional_breakpoints
+                .iter()
+                .map(|(src, _)| format!("if {}", src)),
+        );
+        let mut defs: Vec<_> = self.def_breakpoints.iter().collect();
+        defs.sort();
+        out.extend(defs.into_iter().map(|d| format!("def {}", d)));
+        out
+    }
+
+    /// Run `block` against a copy of the current stack and return what it
+    /// leaves on top plus its exit code. Evaluator state is restored, and
+    /// debugging and recording are suspended so conditions and watches
+    /// cannot trigger or record themselves.
+    fn eval_debug_probe(&mut self, block: &[Expr]) -> Result<(Option<Value>, i32), EvalError> {
+        let saved_stack = self.stack.clone();
+        let saved_exit = self.last_exit_code;
+        let (debug, trace) = (self.debug_mode, self.trace_mode);
+        let recorder = self.recorder.take();
+        self.debug_mode = false;
+        self.trace_mode = false;
+
+        let result = self.eval_exprs(block);
+        let top = self.stack.pop();
+        let exit = self.last_exit_code;
+
+        self.stack = saved_stack;
+        self.last_exit_code = saved_exit;
+        self.debug_mode = debug;
+        self.trace_mode = trace;
+        self.recorder = recorder;
+
+        result.map(|()| (top, exit))
+    }
+
+    /// True if any conditional breakpoint's condition currently holds: a
+    /// `true` on top, or exit code 0 when the top is not a boolean. Errors
+    /// count as not holding.
+    pub(crate) fn matches_conditional_breakpoint(&mut self) -> bool {
+        if self.conditional_breakpoints.is_empty() {
+            return false;
+        }
+        let conditions = self.conditional_breakpoints.clone();
+        conditions
+            .iter()
+            .any(|(_, block)| match self.eval_debug_probe(block) {
+                Ok((Some(Value::Bool(b)), _)) => b,
+                Ok((_, exit)) => exit == 0,
+                Err(_) => false,
+            })
+    }
+
+    /// Print each watch expression's value (called after every step)
+    pub(crate) fn print_watches(&mut self) {
+        if self.watches.is_empty() {
+            return;
+        }
+        let watches = self.watches.clone();
+        for (src, block) in &watches {
+            let shown = match self.eval_debug_probe(block) {
+                Ok((Some(v), _)) => crate::display::format_value_hint(&v),
+                Ok((None, _)) => "(nothing)".to_string(),
+                Err(e) => format!("error: {}", e),
+            };
+            self.log_trace(&format!("    \x1b[36mwatch\x1b[0m {} = {}", src, shown));
+        }
+    }
+
+    /// Install an external debugger frontend in place of the stdin prompt
+    pub fn set_debug_frontend(&mut self, frontend: Box<dyn DebugFrontend>) {
+        self.debug_frontend = Some(frontend);
+        self.debug_terminate = false;
+    }
+
+    /// Evaluate debugger code against a copy of the stack and return the
+    /// value it leaves on top (frontends use this for `evaluate`)
+    pub fn debug_evaluate(&mut self, code: &str) -> Result<Option<Value>, String> {
+        let block = parse_debug_code(code)?;
+        self.eval_debug_probe(&block)
+            .map(|(top, _)| top)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Hand control to the frontend, or the stdin prompt, at a pause
+    pub(crate) fn debug_pause(&mut self, reason: &str, expr: &Expr) {
+        match self.debug_frontend.take() {
+            Some(mut frontend) => {
+                let desc = self.expr_to_string(expr);
+                let action = frontend.on_pause(self, reason, &desc);
+                self.debug_frontend = Some(frontend);
+                self.apply_debug_action(action);
+            }
+            None => {
+                crate::display::eprintln_styled(&self.format_debug_state(expr));
+                self.debug_prompt();
+            }
+        }
+    }
+
+    fn apply_debug_action(&mut self, action: DebugAction) {
+        match action {
+            DebugAction::Step => self.step_mode = true,
+            DebugAction::Continue => self.step_mode = false,
+            DebugAction::Terminate => {
+                self.step_mode = false;
+                self.debug_terminate = true;
+            }
+        }
+    }
+
+    /// Pause at a definition watchpoint on entry (`entering`) or exit
+    pub(crate) fn debug_definition_event(&mut self, name: &str, entering: bool) {
+        if !self.debug_mode || !self.def_breakpoints.contains(name) {
+            return;
+        }
+        let arrow = if entering {
+            "→ entering"
+        } else {
+            "← leaving"
+        };
+        if let Some(mut frontend) = self.debug_frontend.take() {
+            let desc = format!("{} {}", arrow, name);
+            let action = frontend.on_pause(self, "function breakpoint", &desc);
+            self.debug_frontend = Some(frontend);
+            self.apply_debug_action(action);
+            return;
+        }
+        crate::display::eprintln_styled(&format!(
+            "\x1b[33m{} {}\x1b[0m (stack: {} items)",
+            arrow,
+            name,
+            self.stack.len()
+        ));
+        self.debug_prompt();
+    }
+
+    /// Read debugger commands from stdin until the user steps or continues
+    pub(crate) fn debug_prompt(&mut self) {
+        crate::display::eprintln_styled(DEBUG_PROMPT);
+        loop {
+            let mut input = String::new();
+            if std::io::stdin().read_line(&mut input).is_err() {
+                break;
+            }
+            let cmd = input.trim().to_lowercase();
+            match cmd.as_str() {
+                "n" | "next" | "" => {
+                    // Step to next expression
+                    self.step_mode = true;
+                    break;
+                }
+                "c" | "continue" => {
+                    // Continue until next breakpoint
+                    self.step_mode = false;
+                    break;
+                }
+                "s" | "stack" => {
+                    // Show full stack
+                    crate::display::eprintln_styled(&format!(
+                        "\x1b[33mStack ({} items):\x1b[0m",
+                        self.stack.len()
+                    ));
+                    for (idx, val) in self.stack.iter().enumerate() {
+                        match self.origin_at(idx) {
+                            Some(Origin::Secret) => eprintln!("  {}. <hidden> (secret)", idx),
+                            Some(origin) => eprintln!("  {}. {:?} ({})", idx, val, origin),
+                            None => eprintln!("  {}. {:?}", idx, val),
+                        }
+                    }
+                    crate::display::eprintln_styled(DEBUG_PROMPT);
+                }
+                "q" | "quit" => {
+                    // Quit debug mode
+                    self.debug_mode = false;
+                    self.step_mode = false;
+                    crate::display::eprintln_styled("\x1b[33mDebug mode disabled\x1b[0m");
+                    break;
+                }
+                "b" | "breakpoints" => {
+                    // List breakpoints
+                    let bps = self.breakpoint_descriptions();
+                    if bps.is_empty() {
+                        crate::display::eprintln_styled("\x1b[33mNo breakpoints set\x1b[0m");
+                    } else {
+                        crate::display::eprintln_styled("\x1b[33mBreakpoints:\x1b[0m");
+                        for bp in &bps {
+                            eprintln!("  - {}", bp);
+                        }
+                    }
+                    crate::display::eprintln_styled(DEBUG_PROMPT);
+                }
+                _ => {
+                    crate::display::eprintln_styled(&format!(
+                        "\x1b[31mUnknown command: {}\x1b[0m",
+                        cmd
+                    ));
+                    crate::display::eprintln_styled(DEBUG_PROMPT);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+//! DNS lookups: `dns-lookup`, `dns-query`, and `rdns`
+//!
+//! ```text
+//! "example.com" dns-lookup          → Table of its A and AAAA records
+//! "MX" "example.com" dns-query      → Table of its MX records
+//! "93.184.215.14" rdns              → Table of the IP's PTR records
+//! ```
+//!
+//! Each pushes a Table of `name type value ttl`, one row per record in the
+//! answer (a CNAME the name leads through is a row of its own). Values are
+//! written as `dig` writes them, without the trailing dot on names: an MX is
+//! `10 mail.example.com`, a TXT record its strings joined.
+//!
+//! Queries go straight to a name server over UDP (TCP when the answer is too
+//! long), so results don't depend on which tools are installed. The server
+//! is `DNS_SERVER` (`1.1.1.1`, or `[::1]:5353`) when it's set, else the
+//! first of `/etc/resolv.conf`'s nameservers that answers.
+
+// The WebAssembly build can't connect, so builds queries it never sends
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Record types by name, for `dns-query`
+const RECORD_TYPES: [(&str, u16); 11] = [
+    ("A", 1),
+    ("NS", 2),
+    ("CNAME", 5),
+    ("SOA", 6),
+    ("PTR", 12),
+    ("MX", 15),
+    ("TXT", 16),
+    ("AAAA", 28),
+    ("SRV", 33),
+    ("CAA", 257),
+    ("ANY", 255),
+];
+
+fn type_code(name: &str) -> Option<u16> {
+    let name = name.to_ascii_uppercase();
+    RECORD_TYPES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, code)| *code)
+        .or_else(|| name.strip_prefix("TYPE")?.parse().ok())
+}
+
+fn type_name(code: u16) -> String {
+    RECORD_TYPES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(n, _)| n.to_string())
+        .unwrap_or_else(|| format!("TYPE{}", code))
+}
+
+/// The name a reverse lookup of `ip` asks about
+fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::new();
+            for byte in v6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+/// A query for `name`'s records of type `qtype`, with recursion desired
+fn build_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let mut packet = Vec::with_capacity(name.len() + 18);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("{}: not a domain name", name));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    Ok(packet)
+}
+
+/// Reads the parts of a DNS message
+struct Reader<'a> {
+    packet: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .packet
+            .get(self.at..self.at + n)
+            .ok_or("the answer was cut short")?;
+        self.at += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A name, following compression pointers back into the message
+    fn name(&mut self) -> Result<String, String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut at = self.at;
+        let mut end = None;
+        // More labels and pointers than any real name has: the pointers
+        // in this message loop
+        for _ in 0..128 {
+            let len = *self.packet.get(at).ok_or("the answer was cut short")?;
+            match len {
+                0 => {
+                    self.at = end.unwrap_or(at + 1);
+                    return Ok(labels.join("."));
+                }
+                len if len & 0xc0 == 0xc0 => {
+                    let low = *self.packet.get(at + 1).ok_or("the answer was cut short")?;
+                    end.get_or_insert(at + 2);
+                    at = usize::from(len & 0x3f) << 8 | usize::from(low);
+                }
+                len => {
+                    let label = self
+                        .packet
+                        .get(at + 1..at + 1 + usize::from(len))
+                        .ok_or("the answer was cut short")?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    at += 1 + usize::from(len);
+                }
+            }
+        }
+        Err("a name in the answer loops".to_string())
+    }
+}
+
+/// A record's data, written as `dig` writes it
+fn rdata_text(reader: &mut Reader, rtype: u16, len: usize) -> Result<String, String> {
+    let end = reader.at + len;
+    let text = match rtype {
+        1 if len == 4 => {
+            let b = reader.bytes(4)?;
+            Ipv4Addr::new(b[0], b[1], b[2], b[3]).to_string()
+        }
+        28 if len == 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(reader.bytes(16)?);
+            Ipv6Addr::from(octets).to_string()
+        }
+        2 | 5 | 12 => reader.name()?,
+        15 => {
+            let preference = reader.u16()?;
+            format!("{} {}", preference, reader.name()?)
+        }
+        16 => {
+            let mut text = String::new();
+            while reader.at < end {
+                let n = reader.u8()?;
+                text.push_str(&String::from_utf8_lossy(reader.bytes(usize::from(n))?));
+            }
+            text
+        }
+        6 => {
+            let (mname, rname) = (reader.name()?, reader.name()?);
+            let mut numbers = Vec::new();
+            for _ in 0..5 {
+                numbers.push(reader.u32()?.to_string());
+            }
+            format!("{} {} {}", mname, rname, numbers.join(" "))
+        }
+        33 => {
+            let (priority, weight, port) = (reader.u16()?, reader.u16()?, reader.u16()?);
+            format!("{} {} {} {}", priority, weight, port, reader.name()?)
+        }
+        257 if len >= 2 => {
+            let flags = reader.u8()?;
+            let tag_len = usize::from(reader.u8()?);
+            let tag = String::from_utf8_lossy(reader.bytes(tag_len)?).into_owned();
+            let value = reader.bytes(end.saturating_sub(reader.at))?;
+            format!("{} {} \"{}\"", flags, tag, String::from_utf8_lossy(value))
+        }
+        // Types we don't know are written as RFC 3597 says
+        _ => format!("\\# {} {}", len, hex::encode(reader.bytes(len)?)),
+    };
+    reader.at = end;
+    Ok(text)
+}
+
+/// The rows for the records in an answer to query `id`
+fn parse_answer(packet: &[u8], id: u16) -> Result<Vec<Vec<Value>>, String> {
+    let mut reader = Reader { packet, at: 0 };
+    if reader.u16()? != id {
+        return Err("the answer is to another query".to_string());
+    }
+    let flags = reader.u16()?;
+    match flags & 0x000f {
+        0 => {}
+        1 => return Err("the server couldn't read the query".to_string()),
+        2 => return Err("the server failed".to_string()),
+        3 => return Err("no such domain".to_string()),
+        5 => return Err("the server refused".to_string()),
+        code => return Err(format!("the server answered with error {}", code)),
+    }
+    let (questions, answers) = (reader.u16()?, reader.u16()?);
+    reader.bytes(4)?;
+    for _ in 0..questions {
+        reader.name()?;
+        reader.bytes(4)?;
+    }
+
+    let mut rows = Vec::with_capacity(usize::from(answers));
+    for _ in 0..answers {
+        let name = reader.name()?;
+        let rtype = reader.u16()?;
+        let _class = reader.u16()?;
+        let ttl = reader.u32()?;
+        let len = usize::from(reader.u16()?);
+        let value = rdata_text(&mut reader, rtype, len)?;
+        rows.push(vec![
+            Value::Literal(name),
+            Value::Literal(type_name(rtype)),
+            Value::Literal(value),
+            Value::Int(i64::from(ttl)),
+        ]);
+    }
+    Ok(rows)
+}
+
+fn records_table(rows: Vec<Vec<Value>>) -> Value {
+    Value::Table {
+        columns: ["name", "type", "value", "ttl"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+        rows,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod transport {
+    use std::io::{Read, Write};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, UdpSocket};
+    use std::time::Duration;
+
+    /// How long to wait on a server before trying again
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// How many times each server is asked
+    const ATTEMPTS: usize = 2;
+
+    /// The servers to ask, in order
+    fn servers() -> Result<Vec<SocketAddr>, String> {
+        if let Ok(server) = super::super::env::var("DNS_SERVER") {
+            let server = server.trim();
+            if !server.is_empty() {
+                return server
+                    .parse()
+                    .or_else(|_| server.parse().map(|ip: IpAddr| SocketAddr::new(ip, 53)))
+                    .map(|addr| vec![addr])
+                    .map_err(|_| format!("DNS_SERVER: '{}' is not an address", server));
+            }
+        }
+        let resolv = std::fs::read_to_string("/etc/resolv.conf").unwrap_or_default();
+        let mut servers: Vec<SocketAddr> = resolv
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("nameserver"))
+            .filter_map(|rest| rest.trim().split('%').next()?.parse().ok())
+            .map(|ip: IpAddr| SocketAddr::new(ip, 53))
+            .collect();
+        if servers.is_empty() {
+            servers.push(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)), 53));
+        }
+        Ok(servers)
+    }
+
+    fn ask_udp(server: SocketAddr, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let local: SocketAddr = if server.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap_or(server)
+        } else {
+            "[::]:0".parse().unwrap_or(server)
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(TIMEOUT))?;
+        socket.connect(server)?;
+        socket.send(query)?;
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let n = socket.recv(&mut buf)?;
+            // Skip stray answers to earlier queries
+            if n >= 2 && buf[..2] == query[..2] {
+                buf.truncate(n);
+                return Ok(buf);
+            }
+        }
+    }
+
+    fn ask_tcp(server: SocketAddr, query: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect_timeout(&server, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut message = (query.len() as u16).to_be_bytes().to_vec();
+        message.extend_from_slice(query);
+        stream.write_all(&message)?;
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len)?;
+        let mut answer = vec![0u8; usize::from(u16::from_be_bytes(len))];
+        stream.read_exact(&mut answer)?;
+        Ok(answer)
+    }
+
+    /// The answer to `query` from the first server that gives one
+    pub(super) fn ask(query: &[u8], cancelled: &dyn Fn() -> bool) -> Result<Vec<u8>, String> {
+        let mut last_error = String::from("no name server answered");
+        for server in servers()? {
+            for _ in 0..ATTEMPTS {
+                if cancelled() {
+                    return Err("interrupted".to_string());
+                }
+                match ask_udp(server, query) {
+                    // The truncated bit: too long for UDP, so ask over TCP
+                    Ok(answer) if answer.len() > 2 && answer[2] & 0x02 != 0 => {
+                        return ask_tcp(server, query).map_err(|e| format!("{}: {}", server, e));
+                    }
+                    Ok(answer) => return Ok(answer),
+                    Err(e) => last_error = format!("{}: {}", server, e),
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+impl Evaluator {
+    /// The records of type `qtype` for `name`, as rows
+    fn dns_records(&self, op: &str, name: &str, qtype: u16) -> Result<Vec<Vec<Value>>, EvalError> {
+        let fail = |e: String| EvalError::ExecError(format!("{}: {}: {}", op, name, e));
+        let id = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u16 ^ std::process::id() as u16)
+            .unwrap_or_default();
+        let query = build_query(id, name, qtype).map_err(fail)?;
+        let answer = self.dns_ask(op, &query)?;
+        parse_answer(&answer, id).map_err(fail)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn dns_ask(&self, op: &str, query: &[u8]) -> Result<Vec<u8>, EvalError> {
+        transport::ask(query, &|| self.check_cancelled().is_err())
+            .map_err(|e| EvalError::ExecError(format!("{}: {}", op, e)))
+    }
+
+    /// The WebAssembly build has no network access
+    #[cfg(target_arch = "wasm32")]
+    fn dns_ask(&self, op: &str, _query: &[u8]) -> Result<Vec<u8>, EvalError> {
+        Err(EvalError::ExecError(format!(
+            "{}: not available in this build",
+            op
+        )))
+    }
+
+    /// dns-lookup: "host" dns-lookup → Table of its A and AAAA records
+    pub(crate) fn builtin_dns_lookup(&mut self) -> Result<(), EvalError> {
+        let host = self.pop_string()?;
+        let host = host.trim();
+        let mut rows = self.dns_records("dns-lookup", host, 1)?;
+        // The CNAMEs that led to the addresses came with the A records
+        for row in self.dns_records("dns-lookup", host, 28)? {
+            if !rows.iter().any(|seen| seen[..3] == row[..3]) {
+                rows.push(row);
+            }
+        }
+        self.stack.push(records_table(rows));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// dns-query: "TYPE" "name" dns-query → Table of its records of that type
+    pub(crate) fn builtin_dns_query(&mut self) -> Result<(), EvalError> {
+        let name = self.pop_string()?;
+        let rtype = self.pop_string()?;
+        let qtype = type_code(rtype.trim()).ok_or_else(|| {
+            EvalError::ExecError(format!("dns-query: unknown record type '{}'", rtype))
+        })?;
+        let rows = self.dns_records("dns-query", name.trim(), qtype)?;
+        self.stack.push(records_table(rows));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// rdns: "ip" rdns → Table of the names it points back to
+    pub(crate) fn builtin_rdns(&mut self) -> Result<(), EvalError> {
+        let ip = self.pop_string()?;
+        let addr: IpAddr = ip
+            .trim()
+            .parse()
+            .map_err(|_| EvalError::ExecError(format!("rdns: '{}' is not an IP address", ip)))?;
+        let rows = self.dns_records("rdns", &reverse_name(addr), 12)?;
+        self.stack.push(records_table(rows));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
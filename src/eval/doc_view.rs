@@ -0,0 +1,179 @@
+//! `doc-view`: documentation for a word without leaving the shell
+//!
+//! `"tar" doc-view` shows the man page for a command on PATH, or its tldr
+//! page when there is no man page. For an hsab builtin it shows what the
+//! reference (`docs/reference.md`, built in) says: its quick-reference
+//! rows, the sections named after it, and the examples using it. At a
+//! terminal the text goes to `$PAGER` (`less -R` unless set); otherwise it
+//! is pushed as output.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+use crate::resolver::ExecutableResolver;
+
+/// The language reference, searched for builtins' documentation
+pub(crate) const REFERENCE: &str = include_str!("../../docs/reference.md");
+
+/// Most example lines shown for a builtin
+const MAX_EXAMPLES: usize = 12;
+
+impl Evaluator {
+    /// doc-view: "word" doc-view
+    /// Show the reference for a builtin, or a command's man or tldr page
+    pub(crate) fn builtin_doc_view(&mut self) -> Result<(), EvalError> {
+        let word = self.pop_string()?;
+        // Builtins win over commands of the same name, as when running them
+        let text = if ExecutableResolver::is_hsab_builtin(&word) {
+            reference_for(&word)
+                .unwrap_or_else(|| format!("{}: hsab builtin (not in the reference)\n", word))
+        } else {
+            self.command_doc(&word)
+                .or_else(|| reference_for(&word))
+                .ok_or_else(|| {
+                    EvalError::ExecError(format!("doc-view: no documentation for {}", word))
+                })?
+        };
+
+        if !self.capture_mode && Self::is_interactive() && self.page(&text) {
+            self.last_exit_code = 0;
+            return Ok(());
+        }
+        self.stack.push(Value::Output(text));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// The man page for `cmd` as plain text, or else its tldr page
+    fn command_doc(&mut self, cmd: &str) -> Option<String> {
+        let run = |this: &mut Self, program: &str, args: &[&str]| -> Option<String> {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            let audit = this.start_process("doc-view", program, &args).ok()?;
+            let output = Command::new(program)
+                .args(&args)
+                .env("MANPAGER", "cat")
+                .env("MANWIDTH", "80")
+                .current_dir(&this.cwd)
+                .stderr(Stdio::null())
+                .output()
+                .ok()?;
+            audit.finish(output.status.code().unwrap_or(-1));
+            let text = String::from_utf8_lossy(&output.stdout);
+            (output.status.success() && !text.trim().is_empty()).then(|| strip_overstrike(&text))
+        };
+        run(self, "man", &[cmd]).or_else(|| run(self, "tldr", &[cmd]))
+    }
+
+    /// Show `text` in the pager, returning false if it couldn't be started
+    fn page(&mut self, text: &str) -> bool {
+        let pager = super::env::var("PAGER")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .unwrap_or_else(|| "less -R".to_string());
+        let words: Vec<String> = pager.split_whitespace().map(String::from).collect();
+        let Ok(audit) = self.start_process("doc-view", &words[0], &words[1..]) else {
+            return false;
+        };
+        let Ok(mut child) = Command::new(&words[0])
+            .args(&words[1..])
+            .stdin(Stdio::piped())
+            .spawn()
+        else {
+            return false;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            // The pager quitting early closes the pipe; that's fine
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        if let Ok(status) = child.wait() {
+            audit.finish(status.code().unwrap_or(-1));
+        }
+        true
+    }
+}
+
+/// Remove the backspace overstrikes man uses for bold and underline
+fn strip_overstrike(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\u{8}' {
+            out.pop();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Split reference text into words, keeping the characters hsab words use
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| c.is_whitespace() || "`|/(),[]{}\"".contains(c))
+        .filter(|t| !t.is_empty())
+}
+
+/// What the reference says about the builtin `word`
+pub(crate) fn reference_for(word: &str) -> Option<String> {
+    let mut rows = Vec::new();
+    let mut sections = Vec::new();
+    let mut examples = Vec::new();
+
+    // The section being copied, and the level of its heading
+    let mut copying: Option<usize> = None;
+    let mut in_code = false;
+    for line in REFERENCE.lines() {
+        if line.starts_with("```") {
+            in_code = !in_code;
+        }
+        let level = line.chars().take_while(|&c| c == '#').count();
+        if !in_code && level >= 2 && line[level..].starts_with(' ') {
+            if copying.is_some_and(|copied| level <= copied) {
+                copying = None;
+            }
+            if copying.is_none() && level >= 3 && tokens(&line[level..]).any(|t| t == word) {
+                copying = Some(level);
+            }
+        }
+        if copying.is_some() {
+            sections.push(line);
+            continue;
+        }
+
+        if !in_code && line.starts_with("| `") {
+            let cells: Vec<&str> = line.trim_matches('|').split(" | ").collect();
+            if tokens(cells[0]).any(|t| t == word) {
+                let cells: Vec<String> = cells.iter().map(|c| c.trim().replace('`', "")).collect();
+                rows.push(cells.join("  "));
+            }
+        } else if in_code && !line.starts_with("```") && !line.starts_with("# ") {
+            let code = line.split(" # ").next().unwrap_or(line);
+            if tokens(code).any(|t| t == word) && examples.len() < MAX_EXAMPLES {
+                examples.push(line);
+            }
+        }
+    }
+
+    if rows.is_empty() && sections.is_empty() && examples.is_empty() {
+        return None;
+    }
+    let mut parts = vec![format!("{}: hsab builtin", word)];
+    if !rows.is_empty() {
+        let rows: Vec<String> = rows
+            .iter()
+            .map(|row| format!("  {}", row.replace("\\|", "|")))
+            .collect();
+        parts.push(rows.join("\n"));
+    }
+    if !sections.is_empty() {
+        parts.push(sections.join("\n").trim_end().to_string());
+    }
+    if !examples.is_empty() {
+        let examples: Vec<String> = examples
+            .iter()
+            .map(|line| format!("  {}", line.trim()))
+            .collect();
+        parts.push(format!("Examples:\n{}", examples.join("\n")));
+    }
+    Some(parts.join("\n\n") + "\n")
+}
@@ -0,0 +1,159 @@
+//! Editing in `$EDITOR`: `.edit-def` and `edit-at` here, `.edit` in the REPL
+//!
+//! The editor is `$VISUAL`, then `$EDITOR`, then `vi`. It may include
+//! arguments (`code --wait`); the file to edit is appended.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// The editor command, split into words
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| super::env::var(var).ok())
+        .map(|cmd| {
+            cmd.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|words| !words.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+/// Arguments opening `path` at `line` (and `column`) in `editor`, written
+/// the way that editor expects
+pub(crate) fn editor_args(editor: &str, path: &str, line: i64, column: Option<i64>) -> Vec<String> {
+    let at = match column {
+        Some(column) => format!("{}:{}:{}", path, line, column),
+        None => format!("{}:{}", path, line),
+    };
+    let name = Path::new(editor)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "cursor" => vec!["-g".to_string(), at],
+        "subl" | "zed" | "hx" | "helix" | "micro" => vec![at],
+        // vi, vim, nvim, nano, emacs, and most others
+        _ => vec![format!("+{}", line), path.to_string()],
+    }
+}
+
+impl Evaluator {
+    /// Open `text` in the user's editor and return the saved contents, or
+    /// `None` if the editor exited with an error (edit abandoned)
+    pub fn edit_in_editor(&self, text: &str) -> Result<Option<String>, String> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let editor = editor_command();
+        let path = std::env::temp_dir().join(format!(
+            "hsab-edit-{}-{}.hsab",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut args = editor[1..].to_vec();
+        args.push(path.display().to_string());
+        let audit = self
+            .start_process("editor", &editor[0], &args)
+            .map_err(|e| e.to_string())?;
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let status = Command::new(&editor[0])
+            .args(&args)
+            .status()
+            .map_err(|e| format!("{}: {}", editor[0], e));
+        if let Ok(status) = &status {
+            audit.finish(status.code().unwrap_or(-1));
+        }
+        let result = match status {
+            Ok(status) if status.success() => std::fs::read_to_string(&path)
+                .map(Some)
+                .map_err(|e| format!("{}: {}", path.display(), e)),
+            Ok(_) => Ok(None),
+            Err(e) => Err(e),
+        };
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    /// .edit-def: Edit a definition's source in $EDITOR and redefine it
+    pub(crate) fn builtin_edit_def(&mut self, args: &[String]) -> Result<(), EvalError> {
+        let name = args
+            .first()
+            .ok_or_else(|| EvalError::ExecError(".edit-def: usage: \"name\" .edit-def".into()))?;
+        let body = self
+            .definitions
+            .get(name)
+            .ok_or_else(|| EvalError::ExecError(format!(".edit-def: {} is not defined", name)))?;
+        let source = crate::ast::exprs_to_source(body);
+        let Some(edited) = self
+            .edit_in_editor(&format!("{}\n", source))
+            .map_err(|e| EvalError::ExecError(format!(".edit-def: {}", e)))?
+        else {
+            eprintln!("{}: edit abandoned", name);
+            self.last_exit_code = 1;
+            return Ok(());
+        };
+        let edited = edited.trim();
+        if edited != source {
+            // A parse error keeps the old definition
+            let program = crate::lex(edited)
+                .map_err(|e| e.to_string())
+                .and_then(|tokens| crate::parse(tokens).map_err(|e| e.to_string()))
+                .map_err(|e| EvalError::ExecError(format!(".edit-def: {}: {}", name, e)))?;
+            self.definitions.insert(name.clone(), program.expressions);
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
+
+impl Evaluator {
+    /// edit-at: record edit-at
+    ///
+    /// Opens the `path` column of a record (or a table's first row) in
+    /// `$EDITOR` at its `line`, and `column` if there is one, as rows from
+    /// `grep-table` have
+    pub(crate) fn builtin_edit_at(&mut self) -> Result<(), EvalError> {
+        let value = self.pop_value_or_err()?;
+        let record = match value {
+            Value::Map(record) => record,
+            Value::Table { columns, rows } => match rows.into_iter().next() {
+                Some(row) => columns.into_iter().zip(row).collect(),
+                None => return Err(EvalError::ExecError("edit-at: empty table".into())),
+            },
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "Record or Table".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let path = record
+            .get("path")
+            .and_then(Value::as_arg)
+            .ok_or_else(|| EvalError::ExecError("edit-at: no path".into()))?;
+        let number = |key: &str| match record.get(key) {
+            Some(Value::Int(n)) => Some(*n),
+            Some(v) => v.as_arg().and_then(|s| s.parse().ok()),
+            None => None,
+        };
+        let line = number("line").unwrap_or(1);
+
+        let editor = editor_command();
+        let mut args = editor[1..].to_vec();
+        args.extend(editor_args(&editor[0], &path, line, number("column")));
+        let audit = self.start_process("editor", &editor[0], &args)?;
+        let status = Command::new(&editor[0])
+            .args(&args)
+            .current_dir(&self.cwd)
+            .status()
+            .map_err(|e| EvalError::ExecError(format!("edit-at: {}: {}", editor[0], e)))?;
+        self.last_exit_code = status.code().unwrap_or(1);
+        audit.finish(self.last_exit_code);
+        Ok(())
+    }
+}
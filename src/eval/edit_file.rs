@@ -0,0 +1,227 @@
+//! `edit-file` and `edit-lines`: transform a file in place
+//!
+//! `"app.conf" #["80" "8080" str-replace] edit-file` reads the file, runs
+//! the block with its contents on a stack of its own, and writes back what
+//! the block leaves on top. The new contents go to a file beside the old one
+//! that is then renamed over it, so nothing ever sees half a file, and the
+//! file keeps its permissions. With a suffix before the word
+//! (`"app.conf" #[...] ".bak" edit-file`) the old contents are kept in
+//! `app.conf.bak` first.
+//!
+//! A block that fails, leaves an error, or leaves nothing changes nothing.
+//! If the file ended with a newline and the block's result doesn't
+//! (captured output doesn't), the newline is kept.
+//!
+//! `"app.conf" "^port = (\d+)$" #[...] edit-lines` is the same for one line
+//! at a time, as `sed -i` would be: the file is read a line at a time, and
+//! the block runs only on the lines the regex matches, with the line on top
+//! of the list of its captures (the whole match first). What it leaves
+//! replaces the line, and leaving nothing deletes it. Other lines, and line
+//! endings, are copied as they are.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{EvalError, Evaluator};
+use crate::ast::{Expr, Value};
+
+/// `path` with `suffix` added to its file name
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// A file being written beside `path`, to be renamed over it once it's
+/// complete; dropped before then, it's removed and `path` is untouched.
+/// When `path` is a symlink, the file it points to is the one replaced, so
+/// the link stays a link.
+pub(crate) struct Replacement {
+    path: PathBuf,
+    tmp: PathBuf,
+    file: Option<BufWriter<File>>,
+}
+
+impl Replacement {
+    pub(crate) fn new(path: &Path) -> io::Result<Self> {
+        let path = std::fs::canonicalize(path)?;
+        let tmp = with_suffix(&path, ".hsab-tmp");
+        let file = BufWriter::new(File::create(&tmp)?);
+        Ok(Replacement {
+            path,
+            tmp,
+            file: Some(file),
+        })
+    }
+
+    /// Where the new contents go
+    pub(crate) fn writer(&mut self) -> &mut impl Write {
+        self.file.as_mut().expect("replacement already committed")
+    }
+
+    /// Put the new contents in place, keeping the old file's permissions,
+    /// after copying the old file to `backup` if given
+    pub(crate) fn commit(mut self, backup: Option<&Path>) -> io::Result<()> {
+        let file = self.file.take().expect("replacement already committed");
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        let permissions = std::fs::metadata(&self.path)?.permissions();
+        std::fs::set_permissions(&self.tmp, permissions)?;
+        if let Some(backup) = backup {
+            std::fs::copy(&self.path, backup)?;
+        }
+        // Write then rename so a reader never sees half a file
+        std::fs::rename(&self.tmp, &self.path)
+    }
+}
+
+impl Drop for Replacement {
+    fn drop(&mut self) {
+        if self.tmp.exists() {
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Replace the file at `path` with `contents` in one step, keeping its
+/// permissions, after copying it to `backup` if given
+pub(crate) fn replace_file(path: &Path, contents: &[u8], backup: Option<&Path>) -> io::Result<()> {
+    let mut replacement = Replacement::new(path)?;
+    replacement.writer().write_all(contents)?;
+    replacement.commit(backup)
+}
+
+impl Evaluator {
+    /// Run `block` with its output captured on a stack of its own that
+    /// starts as `stack`, and return what it leaves
+    pub(crate) fn run_block_alone(
+        &mut self,
+        block: &[Expr],
+        stack: Vec<Value>,
+    ) -> Result<Vec<Value>, EvalError> {
+        let saved = std::mem::replace(&mut self.stack, stack.into());
+        let old_capture = self.capture_mode;
+        let mut outcome = Ok(());
+        for expr in block {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
+            }
+        }
+        self.capture_mode = old_capture;
+        let produced = std::mem::replace(&mut self.stack, saved);
+        outcome.map(|_| produced.into_vec())
+    }
+
+    /// edit-file: "path" #[block] [".bak"] edit-file
+    /// Replace a file's contents with what the block makes of them
+    pub(crate) fn builtin_edit_file(&mut self) -> Result<(), EvalError> {
+        let suffix = match self.stack.last() {
+            Some(Value::Block(_)) => None,
+            _ => Some(self.pop_string()?),
+        };
+        let block = self.pop_block()?;
+        let name = self.pop_string()?;
+        let path = self.cwd.join(self.expand_tilde(&name));
+        let fail =
+            |e: &dyn std::fmt::Display| EvalError::ExecError(format!("edit-file: {}: {}", name, e));
+
+        let text = std::fs::read_to_string(&path).map_err(|e| fail(&e))?;
+        let ends_with_newline = text.ends_with('\n');
+
+        let mut produced = self.run_block_alone(&block, vec![Value::Literal(text)])?;
+        let mut edited = match produced.pop() {
+            Some(Value::Error { message, .. }) => return Err(fail(&message)),
+            Some(Value::Nil) | None => return Err(fail(&"the block left no text")),
+            Some(value) => value
+                .as_arg()
+                .ok_or_else(|| fail(&format!("the block left a {}", value.type_name())))?,
+        };
+        if ends_with_newline && !edited.ends_with('\n') {
+            edited.push('\n');
+        }
+        let backup = suffix.map(|suffix| with_suffix(&path, &suffix));
+        replace_file(&path, edited.as_bytes(), backup.as_deref()).map_err(|e| fail(&e))?;
+
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// edit-lines: "path" "regex" #[block] [".bak"] edit-lines
+    /// Rewrite the lines of a file that match a regex, one at a time
+    pub(crate) fn builtin_edit_lines(&mut self) -> Result<(), EvalError> {
+        let suffix = match self.stack.last() {
+            Some(Value::Block(_)) => None,
+            _ => Some(self.pop_string()?),
+        };
+        let block = self.pop_block()?;
+        let pattern = self.pop_string()?;
+        let name = self.pop_string()?;
+        let regex = regex::Regex::new(&pattern)
+            .map_err(|e| EvalError::ExecError(format!("edit-lines: {}", e)))?;
+        let path = self.cwd.join(self.expand_tilde(&name));
+        let fail = |e: &dyn std::fmt::Display| {
+            EvalError::ExecError(format!("edit-lines: {}: {}", name, e))
+        };
+
+        let mut reader = BufReader::new(File::open(&path).map_err(|e| fail(&e))?);
+        let mut replacement = Replacement::new(&path).map_err(|e| fail(&e))?;
+        let mut line = Vec::new();
+        let mut number = 0;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).map_err(|e| fail(&e))? == 0 {
+                break;
+            }
+            number += 1;
+            self.check_cancelled()?;
+            let ending = if line.ends_with(b"\r\n") {
+                "\r\n"
+            } else if line.ends_with(b"\n") {
+                "\n"
+            } else {
+                ""
+            };
+            // Lines that don't match, or aren't text, are copied as they are
+            let text = std::str::from_utf8(&line[..line.len() - ending.len()]).ok();
+            let Some((text, captures)) = text.and_then(|t| Some((t, regex.captures(t)?))) else {
+                replacement
+                    .writer()
+                    .write_all(&line)
+                    .map_err(|e| fail(&e))?;
+                continue;
+            };
+
+            let groups = captures
+                .iter()
+                .map(|group| match group {
+                    Some(group) => Value::Literal(group.as_str().to_string()),
+                    None => Value::Nil,
+                })
+                .collect();
+            let stack = vec![Value::List(groups), Value::Literal(text.to_string())];
+            let at_line = |e: &dyn std::fmt::Display| fail(&format!("line {}: {}", number, e));
+            let edited = match self.run_block_alone(&block, stack)?.pop() {
+                Some(Value::Error { message, .. }) => return Err(at_line(&message)),
+                // Nothing left deletes the line
+                Some(Value::Nil) | None => continue,
+                Some(value) => value
+                    .as_arg()
+                    .ok_or_else(|| at_line(&format!("the block left a {}", value.type_name())))?,
+            };
+            let writer = replacement.writer();
+            writer
+                .write_all(edited.as_bytes())
+                .and_then(|_| writer.write_all(ending.as_bytes()))
+                .map_err(|e| fail(&e))?;
+        }
+        let backup = suffix.map(|suffix| with_suffix(&path, &suffix));
+        replacement
+            .commit(backup.as_deref())
+            .map_err(|e| fail(&e))?;
+
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
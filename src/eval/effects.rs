@@ -0,0 +1,201 @@
+//! Stack effects: what a word takes from the stack and what it leaves
+//!
+//! A builtin's effect is the one the reference's quick-reference tables
+//! give it, in Forth notation with the deepest value first: `cp` is
+//! `src dst -- dst|nil`, so `a.txt b/ cp` copies `a.txt` to `b/`. A
+//! definition's effect is inferred by walking its body on a depth counter:
+//! literals push one value, builtins with a known effect pop and push
+//! theirs, and calls to other definitions use the callee's effect. Bodies
+//! that run external commands (which take as many arguments as the stack
+//! holds), or anything else whose effect isn't known, have none.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use super::doc_view::REFERENCE;
+use super::Evaluator;
+use crate::ast::Expr;
+use crate::resolver::ExecutableResolver;
+
+/// Nesting limit for definitions calling definitions
+const MAX_DEPTH: usize = 8;
+
+/// What a word pops and pushes, deepest value first on each side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackEffect {
+    /// Names of the values taken
+    pub inputs: Vec<String>,
+    /// Names of the values left
+    pub outputs: Vec<String>,
+}
+
+impl StackEffect {
+    /// An effect with `inputs` unnamed values taken and `outputs` left
+    fn counted(inputs: usize, outputs: usize) -> Self {
+        let names = |n: usize, prefix: &str| (1..=n).map(|i| format!("{}{}", prefix, i)).collect();
+        StackEffect {
+            inputs: names(inputs, "in"),
+            outputs: names(outputs, "out"),
+        }
+    }
+}
+
+impl fmt::Display for StackEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |names: &[String]| names.join(" ");
+        match (self.inputs.is_empty(), self.outputs.is_empty()) {
+            (true, true) => f.write_str("--"),
+            (true, false) => write!(f, "-- {}", side(&self.outputs)),
+            (false, true) => write!(f, "{} --", side(&self.inputs)),
+            (false, false) => write!(f, "{} -- {}", side(&self.inputs), side(&self.outputs)),
+        }
+    }
+}
+
+/// Split one side of an effect into names, keeping `(a+b)` and `[#[x]]`
+/// whole
+fn names(side: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut current = String::new();
+    let mut nesting = 0usize;
+    for c in side.chars() {
+        match c {
+            '(' | '[' => nesting += 1,
+            ')' | ']' => nesting = nesting.saturating_sub(1),
+            _ => {}
+        }
+        if c.is_whitespace() && nesting == 0 {
+            if !current.is_empty() {
+                names.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        names.push(current);
+    }
+    names
+}
+
+/// Parse `a b -- c`
+fn parse_effect(text: &str) -> Option<StackEffect> {
+    let text = format!(" {} ", text.replace("\\|", "|"));
+    let (inputs, outputs) = text.split_once(" -- ")?;
+    Some(StackEffect {
+        inputs: names(inputs),
+        outputs: names(outputs),
+    })
+}
+
+/// The text of a table cell written as code
+fn code(cell: &str) -> Option<&str> {
+    cell.trim().strip_prefix('`')?.strip_suffix('`')
+}
+
+/// Effects from the reference's quick-reference rows, by word
+fn reference_effects() -> &'static HashMap<String, StackEffect> {
+    static EFFECTS: OnceLock<HashMap<String, StackEffect>> = OnceLock::new();
+    EFFECTS.get_or_init(|| {
+        let mut effects = HashMap::new();
+        for line in REFERENCE.lines() {
+            let cells: Vec<&str> = line.trim_matches('|').split(" | ").collect();
+            let [word, effect, ..] = cells.as_slice() else {
+                continue;
+            };
+            if let (Some(word), Some(effect)) = (code(word), code(effect).and_then(parse_effect)) {
+                // A word documented twice keeps its first row
+                if !word.contains([' ', '`', ',']) {
+                    effects.entry(word.to_string()).or_insert(effect);
+                }
+            }
+        }
+        effects
+    })
+}
+
+/// The stack effect of a builtin, if the reference gives one
+pub fn builtin_effect(word: &str) -> Option<StackEffect> {
+    reference_effects().get(word).cloned()
+}
+
+impl Evaluator {
+    /// The inferred stack effect of every definition that has one
+    pub fn definition_effects(&mut self) -> HashMap<String, StackEffect> {
+        let mut known = HashMap::new();
+        let names: Vec<String> = self.definitions.keys().cloned().collect();
+        for name in names {
+            self.definition_effect(&name, &mut known, 0);
+        }
+        known
+            .into_iter()
+            .filter_map(|(name, effect)| Some((name, effect?)))
+            .collect()
+    }
+
+    /// The effect of the definition `name`, remembering each one worked
+    /// out in `known` (`None` for those that have no effect)
+    fn definition_effect(
+        &mut self,
+        name: &str,
+        known: &mut HashMap<String, Option<StackEffect>>,
+        depth: usize,
+    ) -> Option<StackEffect> {
+        if let Some(effect) = known.get(name) {
+            return effect.clone();
+        }
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let body = self.definitions.get(name)?.clone();
+        // Until the walk finishes, calls back to this definition have none
+        known.insert(name.to_string(), None);
+        let effect = self.body_effect(&body, known, depth);
+        known.insert(name.to_string(), effect.clone());
+        effect
+    }
+
+    /// Walk `body` on a depth counter, tracking how far below its start
+    /// it reaches
+    fn body_effect(
+        &mut self,
+        body: &[Expr],
+        known: &mut HashMap<String, Option<StackEffect>>,
+        depth: usize,
+    ) -> Option<StackEffect> {
+        let (mut height, mut lowest) = (0isize, 0isize);
+        for expr in body {
+            let effect = match expr {
+                Expr::Quoted { .. }
+                | Expr::Variable(_)
+                | Expr::Block(_)
+                | Expr::ArrayLiteral(_)
+                | Expr::RecordLiteral(_) => StackEffect::counted(0, 1),
+                Expr::Literal(word) if self.definitions.contains_key(word) => {
+                    self.definition_effect(word, known, depth + 1)?
+                }
+                Expr::Literal(word) if ExecutableResolver::is_hsab_builtin(word) => {
+                    builtin_effect(word)?
+                }
+                Expr::Literal(word) => {
+                    // Numbers push themselves; `3+` and friends are operators
+                    let operator = word.parse::<f64>().is_err()
+                        && word.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+                    if operator || self.resolver.is_executable(word) {
+                        return None;
+                    }
+                    StackEffect::counted(0, 1)
+                }
+                other => builtin_effect(&other.to_source())?,
+            };
+            height -= effect.inputs.len() as isize;
+            lowest = lowest.min(height);
+            height += effect.outputs.len() as isize;
+        }
+        Some(StackEffect::counted(
+            (-lowest) as usize,
+            (height - lowest) as usize,
+        ))
+    }
+}
@@ -1,5 +1,59 @@
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
+use num_bigint::Sign;
+
+/// Text encoding used to decode command output and `decode` input, and to
+/// write text back out with `encode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TextEncoding(&'static encoding_rs::Encoding);
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self::UTF8
+    }
+}
+
+impl TextEncoding {
+    pub(crate) const UTF8: Self = Self(encoding_rs::UTF_8);
+
+    /// Look up an encoding by its WHATWG label (`shift_jis`, `latin1`,
+    /// `utf-16le`, ...), case-insensitive, with `-`/`_` optional
+    pub(crate) fn from_label(label: &str) -> Option<Self> {
+        let squashed: String = label.chars().filter(|c| *c != '-' && *c != '_').collect();
+        encoding_rs::Encoding::for_label(label.trim().as_bytes())
+            .or_else(|| encoding_rs::Encoding::for_label(squashed.as_bytes()))
+            .map(Self)
+    }
+
+    pub(crate) fn name(self) -> String {
+        self.0.name().to_ascii_lowercase()
+    }
+
+    /// Decode bytes, replacing invalid sequences with U+FFFD
+    pub(crate) fn decode(self, bytes: &[u8]) -> String {
+        self.0.decode_without_bom_handling(bytes).0.into_owned()
+    }
+
+    /// Encode text, writing characters the encoding lacks as HTML numeric
+    /// character references (as encoding_rs does)
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        // UTF-16 can't be produced by encoding_rs's encoder
+        if self.0 == encoding_rs::UTF_16LE {
+            return text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        }
+        if self.0 == encoding_rs::UTF_16BE {
+            return text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        }
+        self.0.encode(text).0.into_owned()
+    }
+}
+
+fn unknown_encoding(label: &str) -> EvalError {
+    EvalError::ExecError(format!(
+        "unknown encoding '{}' (try utf-8, latin-1, windows-1252, shift_jis, euc-kr, gbk, utf-16le)",
+        label
+    ))
+}
 
 impl Evaluator {
     /// Convert Bytes/Media/string to base64 string: data to-base64 -> "base64..."
@@ -104,6 +158,7 @@ impl Evaluator {
     }
 
     /// Convert hex string to Bytes: "abcd..." from-hex -> Bytes
+    /// Spaces and colons between bytes ("de ad", "de:ad") are skipped
     pub(crate) fn builtin_from_hex(&mut self) -> Result<(), EvalError> {
         let hex_str = self.stack.pop().ok_or_else(|| {
             EvalError::ExecError("from-hex requires hex string on stack".to_string())
@@ -113,6 +168,10 @@ impl Evaluator {
             .as_arg()
             .ok_or_else(|| EvalError::ExecError("from-hex requires hex string".to_string()))?;
 
+        let hex: String = hex
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ':')
+            .collect();
         let data =
             hex::decode(&hex).map_err(|e| EvalError::ExecError(format!("Invalid hex: {}", e)))?;
 
@@ -163,9 +222,15 @@ impl Evaluator {
                 self.stack.push(Value::List(list));
                 self.last_exit_code = 0;
             }
+            Value::BigInt(n) if n.sign() == Sign::Minus => {
+                self.stack.push(Value::BigInt(n));
+                return Err(EvalError::ExecError(
+                    "to-bytes: negative BigInt has no byte form".to_string(),
+                ));
+            }
             Value::BigInt(n) => {
                 // Convert BigInt to Bytes (big-endian)
-                let data = n.to_bytes_be();
+                let (_, data) = n.to_bytes_be();
                 self.stack.push(Value::Bytes(data));
                 self.last_exit_code = 0;
             }
@@ -211,29 +276,6 @@ impl Evaluator {
         Ok(())
     }
 
-    /// Get length of Bytes: bytes len -> number
-    pub(crate) fn builtin_bytes_len(&mut self) -> Result<(), EvalError> {
-        let value = self
-            .stack
-            .pop()
-            .ok_or_else(|| EvalError::ExecError("len requires value on stack".to_string()))?;
-
-        match value {
-            Value::Bytes(data) => {
-                self.stack.push(Value::Int(data.len() as i64));
-                self.last_exit_code = 0;
-                Ok(())
-            }
-            other => {
-                // Put it back and let regular len handle it
-                self.stack.push(other);
-                Err(EvalError::ExecError(
-                    "Not bytes - fallback to string len".to_string(),
-                ))
-            }
-        }
-    }
-
     // ========================================
     // Hash functions (SHA-2 and SHA-3)
     // ========================================
@@ -469,6 +511,16 @@ impl Evaluator {
         Ok(())
     }
 
+    /// Read a whole file: "path" read-file -> Bytes
+    pub(crate) fn builtin_read_file(&mut self) -> Result<(), EvalError> {
+        let path = self.pop_string()?;
+        let data = std::fs::read(self.expand_tilde(&path))
+            .map_err(|e| EvalError::ExecError(format!("read-file: {}: {}", path, e)))?;
+        self.stack.push(Value::Bytes(data));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
     /// SHA3-256 hash of file: "path" sha3-256-file -> Bytes
     pub(crate) fn builtin_sha3_256_file(&mut self) -> Result<(), EvalError> {
         use sha3::{Digest, Sha3_256};
@@ -502,4 +554,60 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    /// Set the output encoding: "latin-1" set-encoding
+    pub(crate) fn builtin_set_encoding(&mut self) -> Result<(), EvalError> {
+        let label = self.pop_string()?;
+        self.output_encoding =
+            TextEncoding::from_label(&label).ok_or_else(|| unknown_encoding(&label))?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Push the current output encoding name: encoding -> "utf-8"
+    pub(crate) fn builtin_encoding(&mut self) -> Result<(), EvalError> {
+        self.stack.push(Value::Literal(self.output_encoding.name()));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Decode Bytes with a named encoding: bytes "latin-1" decode -> string
+    pub(crate) fn builtin_decode(&mut self) -> Result<(), EvalError> {
+        let label = self.pop_string()?;
+        let encoding = TextEncoding::from_label(&label).ok_or_else(|| unknown_encoding(&label))?;
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow("decode requires Bytes".into()))?;
+        match value {
+            Value::Bytes(data) => {
+                self.stack.push(Value::Literal(encoding.decode(&data)));
+                self.last_exit_code = 0;
+                Ok(())
+            }
+            other => {
+                let got = other.type_name().to_string();
+                self.stack.push(other);
+                Err(EvalError::TypeError {
+                    expected: "bytes".into(),
+                    got,
+                })
+            }
+        }
+    }
+
+    /// Encode text with a named encoding: "text" "shift_jis" encode -> Bytes
+    pub(crate) fn builtin_encode(&mut self) -> Result<(), EvalError> {
+        let label = self.pop_string()?;
+        let encoding = TextEncoding::from_label(&label).ok_or_else(|| unknown_encoding(&label))?;
+        let text = self.pop_string()?;
+        self.stack.push(Value::Bytes(encoding.encode(&text)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Decode captured command output with the evaluator's encoding
+    pub(crate) fn decode_output(&self, bytes: &[u8]) -> String {
+        self.output_encoding.decode(bytes)
+    }
 }
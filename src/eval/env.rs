@@ -0,0 +1,51 @@
+//! Environment variables, as the evaluator sees them
+//!
+//! Natively these are `std::env`'s functions. A browser has no process
+//! environment, and setting a variable there panics, so the
+//! `wasm32-unknown-unknown` build keeps variables in memory instead. They
+//! start out empty and are shared by every evaluator in the module.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::env::{remove_var, set_var, var, vars};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use memory::{remove_var, set_var, var, vars};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod memory {
+    use std::collections::BTreeMap;
+    use std::env::VarError;
+    use std::ffi::OsStr;
+    use std::sync::Mutex;
+
+    use crate::util::lock_or_recover;
+
+    static VARS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+    pub(crate) fn var<K: AsRef<OsStr>>(key: K) -> Result<String, VarError> {
+        let key = key.as_ref().to_string_lossy();
+        lock_or_recover(&VARS)
+            .get(key.as_ref())
+            .cloned()
+            .ok_or(VarError::NotPresent)
+    }
+
+    pub(crate) fn vars() -> std::vec::IntoIter<(String, String)> {
+        let vars: Vec<_> = lock_or_recover(&VARS)
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        vars.into_iter()
+    }
+
+    pub(crate) fn set_var<K: AsRef<OsStr>, V: AsRef<OsStr>>(key: K, value: V) {
+        lock_or_recover(&VARS).insert(
+            key.as_ref().to_string_lossy().into_owned(),
+            value.as_ref().to_string_lossy().into_owned(),
+        );
+    }
+
+    pub(crate) fn remove_var<K: AsRef<OsStr>>(key: K) {
+        lock_or_recover(&VARS).remove(key.as_ref().to_string_lossy().as_ref());
+    }
+}
@@ -0,0 +1,644 @@
+//! Explain mode: describe what a program would do, without running it
+//!
+//! `"dest src cp" explain` (or `hsab --explain -c 'dest src cp'`) walks the
+//! parsed program against a symbolic stack, printing one line per step and
+//! finishing with the bash command lines it amounts to:
+//!
+//! ```text
+//! dest  push dest          [dest]
+//! src   push src           [dest src]
+//! cp    run: cp src dest   [$(cp src dest)]
+//!
+//! bash:
+//!   cp src dest
+//! ```
+//!
+//! Commands are built the way the evaluator builds them: arguments are
+//! popped from the stack (top first) until a block or marker; blocks given
+//! to `|`, redirects and `&` pick their words like `block_to_cmd_args`;
+//! blocks given to `&&` and `||` are walked in full.
+//! Definitions are expanded in place. hsab builtins that work on stack
+//! values are named but not simulated; the stack after them is unknown.
+//!
+//! `#[block] to-bash` uses the same walk to translate a block into POSIX
+//! shell, refusing blocks that rely on anything the shell lacks.
+
+use std::collections::HashMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::{Expr, Value};
+
+/// Nesting limit for definitions that call themselves
+const MAX_DEPTH: usize = 8;
+
+/// Longest source shown in the first column
+const MAX_SOURCE: usize = 32;
+
+/// Builtins without a PATH counterpart that still take their arguments
+/// from the stack like external commands do
+const COMMAND_LIKE_BUILTINS: &[&str] = &["cd", "pwd", "echo", "true", "false", "test"];
+
+/// A stack item as far as explain can tell
+#[derive(Clone)]
+enum Sym {
+    /// A word, shell-quoted when shown
+    Word(String),
+    /// Bash text used as is (`"$HOME"`)
+    Raw(String),
+    /// Output of the command at this index
+    Output(usize),
+    Block(Vec<Expr>),
+    Marker,
+    /// Something explain doesn't model
+    Opaque(String),
+}
+
+struct Step {
+    depth: usize,
+    source: String,
+    what: String,
+    stack: String,
+}
+
+struct Explainer<'a> {
+    eval: &'a mut Evaluator,
+    stack: Vec<Sym>,
+    steps: Vec<Step>,
+    /// Command lines, and whether a later one embeds them
+    commands: Vec<(String, bool)>,
+    /// Definitions made by the program being explained
+    defined: HashMap<String, Vec<Expr>>,
+    calling: Vec<String>,
+    /// Steps with no shell equivalent
+    problems: Vec<String>,
+}
+
+/// Quote a word for bash if it needs it. Globs and `~` stay bare, since
+/// hsab expands them just as bash would.
+fn shell_word(word: &str) -> String {
+    // Variables inside the word expand in double quotes, where `\$` is
+    // still a literal `$`
+    if word.contains('$') {
+        return format!("\"{}\"", word.replace('"', "\\\""));
+    }
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-./:=@%+,~*?".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// A quoted hsab string as shell text; double quotes keep `$VAR`
+/// interpolation
+fn quote(content: &str, double: bool) -> String {
+    if double && content.contains('$') {
+        format!("\"{}\"", content.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        shell_word(content)
+    }
+}
+
+fn variable(name: &str) -> String {
+    let name = name
+        .trim_start_matches('$')
+        .trim_start_matches('{')
+        .trim_end_matches('}');
+    format!("\"${}\"", name)
+}
+
+fn short_source(expr: &Expr) -> String {
+    let source = expr.to_source();
+    if source.chars().count() > MAX_SOURCE {
+        let cut: String = source.chars().take(MAX_SOURCE - 1).collect();
+        format!("{}…", cut)
+    } else {
+        source
+    }
+}
+
+impl Explainer<'_> {
+    fn show(&self, sym: &Sym) -> String {
+        match sym {
+            Sym::Word(w) => shell_word(w),
+            Sym::Raw(text) => text.clone(),
+            Sym::Output(i) => format!("\"$({})\"", self.commands[*i].0),
+            Sym::Block(body) => format!("#[{}]", crate::ast::exprs_to_source(body)),
+            Sym::Marker => "marker".to_string(),
+            Sym::Opaque(what) => format!("<{}>", what),
+        }
+    }
+
+    fn step(&mut self, depth: usize, expr: &Expr, what: String) {
+        let items: Vec<String> = self.stack.iter().map(|s| self.show(s)).collect();
+        self.steps.push(Step {
+            depth,
+            source: short_source(expr),
+            what,
+            stack: format!("[{}]", items.join(" ")),
+        });
+    }
+
+    /// Record a command line; its output goes on the stack
+    fn run(&mut self, line: String) -> usize {
+        self.commands.push((line, false));
+        self.commands.len() - 1
+    }
+
+    /// Mark a value as used inside another command
+    fn embed(&mut self, sym: &Sym) {
+        if let Sym::Output(i) = sym {
+            self.commands[*i].1 = true;
+        }
+    }
+
+    fn pop_block(&mut self) -> Option<Vec<Expr>> {
+        match self.stack.pop() {
+            Some(Sym::Block(body)) => Some(body),
+            Some(other) => {
+                self.stack.push(other);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Shell text for a block handed to `|`, a redirect, or `&`. Words are
+    /// picked as `block_to_cmd_args` picks them (the last non-flag word is
+    /// the command), but globs and variables are left for the shell.
+    fn block_command(&mut self, body: &[Expr]) -> String {
+        let mut words: Vec<(String, String)> = Vec::new();
+        for expr in body {
+            let word = match expr {
+                Expr::Literal(s) => (s.clone(), shell_word(s)),
+                Expr::Quoted { content, double } => (content.clone(), quote(content, *double)),
+                Expr::Variable(name) => (name.clone(), variable(name)),
+                _ => {
+                    self.problems.push(format!(
+                        "#[{}] is not a simple command",
+                        crate::ast::exprs_to_source(body)
+                    ));
+                    break;
+                }
+            };
+            words.push(word);
+        }
+        let Some(cmd_idx) = words
+            .iter()
+            .rposition(|(raw, _)| !raw.starts_with('-'))
+            .or_else(|| words.len().checked_sub(1))
+        else {
+            self.problems.push("empty command block".into());
+            return "#[]".into();
+        };
+        let (_, cmd) = words.remove(cmd_idx);
+        std::iter::once(cmd)
+            .chain(words.into_iter().map(|(_, shown)| shown))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Shell text for a block that runs in full (`&&`, `||`): each
+    /// command it runs, in order. The block is walked on its own stack so
+    /// its commands stand alone, as they would in the shell.
+    fn block_commands(&mut self, body: &[Expr], depth: usize) -> String {
+        let start = self.commands.len();
+        let outer = std::mem::take(&mut self.stack);
+        self.walk(body, depth);
+        self.stack = outer;
+        let mut lines = Vec::new();
+        for (line, embedded) in &mut self.commands[start..] {
+            if !*embedded {
+                lines.push(line.clone());
+                *embedded = true;
+            }
+        }
+        match lines.len() {
+            0 => "true".to_string(),
+            1 => lines.remove(0),
+            _ => format!("{{ {}; }}", lines.join("; ")),
+        }
+    }
+
+    fn fail(&mut self, depth: usize, expr: &Expr, msg: String) {
+        self.problems.push(msg.clone());
+        self.step(depth, expr, format!("fail: {}", msg));
+    }
+
+    /// The stack is no longer known after something explain doesn't model
+    fn unknown(&mut self, depth: usize, expr: &Expr, what: String) {
+        self.problems.push(what.clone());
+        self.stack = vec![Sym::Opaque("…".into())];
+        self.step(
+            depth,
+            expr,
+            format!("{} (stack effect not simulated)", what),
+        );
+    }
+
+    fn walk(&mut self, exprs: &[Expr], depth: usize) {
+        for expr in exprs {
+            self.expr(expr, depth);
+        }
+    }
+
+    /// Take a command's arguments off the stack, as `execute_command` does
+    fn take_args(&mut self) -> Vec<String> {
+        let mut args = Vec::new();
+        while let Some(sym) = self.stack.last() {
+            if matches!(sym, Sym::Block(_) | Sym::Marker) {
+                break;
+            }
+            let sym = self.stack.pop().expect("checked above");
+            args.push(self.show(&sym));
+            self.embed(&sym);
+        }
+        args
+    }
+
+    fn word(&mut self, expr: &Expr, word: &str, depth: usize) {
+        let definition = self
+            .defined
+            .get(word)
+            .or_else(|| self.eval.definitions.get(word))
+            .cloned();
+        if let Some(body) = definition {
+            if depth >= MAX_DEPTH || self.calling.iter().any(|n| n == word) {
+                self.unknown(depth, expr, format!("call {} again (recursive)", word));
+                return;
+            }
+            self.step(depth, expr, format!("call definition {}:", word));
+            self.calling.push(word.to_string());
+            self.walk(&body, depth + 1);
+            self.calling.pop();
+            return;
+        }
+        if let Some(body) = self.eval.aliases.get(word).cloned() {
+            self.step(depth, expr, format!("expand alias {}:", word));
+            self.walk(&body, depth + 1);
+            return;
+        }
+        if self.eval.exec_policy.denies(word) {
+            self.fail(
+                depth,
+                expr,
+                format!("{} is not allowed by the exec policy", word),
+            );
+            return;
+        }
+        if word == "live" || word == "capture" {
+            let what = if word == "live" {
+                "run the next command on the terminal"
+            } else {
+                "capture the next command's output"
+            };
+            self.step(depth, expr, what.into());
+            return;
+        }
+        let builtin = crate::resolver::default_builtins().contains(word);
+        let command_like = COMMAND_LIKE_BUILTINS.contains(&word)
+            || (self.eval.resolver.is_executable(word)
+                && (!builtin || self.eval.resolver.find_executable(word).is_some()));
+        if command_like {
+            let args = self.take_args();
+            let line = std::iter::once(shell_word(word))
+                .chain(args)
+                .collect::<Vec<_>>()
+                .join(" ");
+            let what = format!("run: {}", line);
+            let idx = self.run(line);
+            self.stack.push(Sym::Output(idx));
+            self.step(depth, expr, what);
+        } else if builtin {
+            self.unknown(depth, expr, format!("hsab builtin {}", word));
+        } else {
+            self.stack.push(Sym::Word(word.to_string()));
+            self.step(depth, expr, format!("push {}", shell_word(word)));
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, depth: usize) {
+        match expr {
+            Expr::Literal(word) => self.word(expr, word, depth),
+            Expr::Quoted { content, double } => {
+                let shown = quote(content, *double);
+                self.stack.push(Sym::Raw(shown.clone()));
+                self.step(depth, expr, format!("push {}", shown));
+            }
+            Expr::Variable(name) => {
+                let shown = variable(name);
+                self.stack.push(Sym::Raw(shown.clone()));
+                self.step(
+                    depth,
+                    expr,
+                    format!("push the value of {}", shown.trim_matches('"')),
+                );
+            }
+            Expr::Block(body) => {
+                self.stack.push(Sym::Block(body.clone()));
+                self.step(depth, expr, "push a block (runs later)".into());
+            }
+            Expr::ArrayLiteral(_) | Expr::RecordLiteral(_) => {
+                let kind = if matches!(expr, Expr::ArrayLiteral(_)) {
+                    "list"
+                } else {
+                    "record"
+                };
+                self.stack.push(Sym::Opaque(kind.into()));
+                self.step(depth, expr, format!("push a {}", kind));
+            }
+            Expr::Marker => {
+                self.stack.push(Sym::Marker);
+                self.step(depth, expr, "push a marker".into());
+            }
+            Expr::Define(name) => match self.pop_block() {
+                Some(body) => {
+                    self.defined.insert(name.clone(), body);
+                    self.step(depth, expr, format!("define {} as the block", name));
+                }
+                None => self.fail(depth, expr, format!("{} needs a block", name)),
+            },
+            Expr::Apply => match self.pop_block() {
+                Some(body) => {
+                    self.step(depth, expr, "run the block:".into());
+                    self.walk(&body, depth + 1);
+                }
+                None => self.fail(depth, expr, "apply needs a block".into()),
+            },
+            Expr::Pipe => {
+                let (Some(consumer), Some(input)) = (self.pop_block(), self.stack.pop()) else {
+                    self.fail(depth, expr, "| needs input and a block".into());
+                    return;
+                };
+                let producer = match &input {
+                    Sym::Output(i) => self.commands[*i].0.clone(),
+                    other => format!("printf '%s\\n' {}", self.show(other)),
+                };
+                self.embed(&input);
+                let line = format!("{} | {}", producer, self.block_command(&consumer));
+                let what = format!("pipe: {}", line);
+                let idx = self.run(line);
+                self.stack.push(Sym::Output(idx));
+                self.step(depth, expr, what);
+            }
+            Expr::RedirectOut
+            | Expr::RedirectAppend
+            | Expr::RedirectIn
+            | Expr::RedirectErr
+            | Expr::RedirectErrAppend
+            | Expr::RedirectBoth => {
+                let (Some(target), Some(cmd)) = (self.pop_block(), self.pop_block()) else {
+                    self.fail(depth, expr, "redirect needs two blocks".into());
+                    return;
+                };
+                let files: Vec<String> = target
+                    .iter()
+                    .filter_map(|e| match e {
+                        Expr::Literal(s) => Some(shell_word(s)),
+                        Expr::Quoted { content, double } => Some(quote(content, *double)),
+                        _ => None,
+                    })
+                    .collect();
+                let command = self.block_command(&cmd);
+                let file = files.first().cloned().unwrap_or_default();
+                let line = match expr {
+                    // `&>` is a bashism
+                    Expr::RedirectBoth => format!("{} > {} 2>&1", command, file),
+                    // hsab writes to every file given; the shell needs tee
+                    _ if files.len() > 1 => {
+                        let append = if matches!(expr, Expr::RedirectAppend) {
+                            "-a "
+                        } else {
+                            ""
+                        };
+                        format!(
+                            "{} | tee {}{} > /dev/null",
+                            command,
+                            append,
+                            files.join(" ")
+                        )
+                    }
+                    _ => format!("{} {} {}", command, expr.to_source(), file),
+                };
+                self.step(depth, expr, format!("run: {}", line));
+                self.run(line);
+            }
+            Expr::HereString => {
+                let (Some(cmd), Some(input)) = (self.pop_block(), self.stack.pop()) else {
+                    self.fail(depth, expr, "<<< needs text and a block".into());
+                    return;
+                };
+                // `<<<` is a bashism
+                self.embed(&input);
+                let line = format!(
+                    "printf '%s\\n' {} | {}",
+                    self.show(&input),
+                    self.block_command(&cmd)
+                );
+                let what = format!("run: {}", line);
+                let idx = self.run(line);
+                self.stack.push(Sym::Output(idx));
+                self.step(depth, expr, what);
+            }
+            Expr::RedirectErrToOut => match self.pop_block() {
+                Some(cmd) => {
+                    let line = format!("{} 2>&1", self.block_command(&cmd));
+                    let what = format!("run: {}", line);
+                    let idx = self.run(line);
+                    self.stack.push(Sym::Output(idx));
+                    self.step(depth, expr, what);
+                }
+                None => self.fail(depth, expr, "2>&1 needs a block".into()),
+            },
+            Expr::Background => match self.pop_block() {
+                Some(cmd) => {
+                    let line = format!("{} &", self.block_command(&cmd));
+                    self.step(depth, expr, format!("run in the background: {}", line));
+                    self.run(line);
+                }
+                None => self.fail(depth, expr, "& needs a block".into()),
+            },
+            Expr::And | Expr::Or => {
+                let (Some(right), Some(left)) = (self.pop_block(), self.pop_block()) else {
+                    self.fail(depth, expr, "needs two blocks".into());
+                    return;
+                };
+                let op = expr.to_source();
+                let line = format!(
+                    "{} {} {}",
+                    self.block_commands(&left, depth + 1),
+                    op,
+                    self.block_commands(&right, depth + 1)
+                );
+                let when = if matches!(expr, Expr::And) {
+                    "if it succeeds"
+                } else {
+                    "if it fails"
+                };
+                self.step(
+                    depth,
+                    expr,
+                    format!("run the left block, then the right {}: {}", when, line),
+                );
+                self.run(line);
+            }
+            Expr::Dup => match self.stack.last().cloned() {
+                Some(top) => {
+                    self.stack.push(top);
+                    self.step(depth, expr, "duplicate the top item".into());
+                }
+                None => self.fail(depth, expr, "stack is empty".into()),
+            },
+            Expr::Drop => match self.stack.pop() {
+                Some(_) => self.step(depth, expr, "discard the top item".into()),
+                None => self.fail(depth, expr, "stack is empty".into()),
+            },
+            Expr::Swap | Expr::Over | Expr::Rot => {
+                let n = self.stack.len();
+                let (needed, what) = match expr {
+                    Expr::Swap => (2, "swap the top two items"),
+                    Expr::Over => (2, "copy the second item to the top"),
+                    _ => (3, "move the third item to the top"),
+                };
+                if n < needed {
+                    self.fail(depth, expr, format!("needs {} items", needed));
+                    return;
+                }
+                match expr {
+                    Expr::Swap => self.stack.swap(n - 1, n - 2),
+                    Expr::Over => {
+                        let second = self.stack[n - 2].clone();
+                        self.stack.push(second);
+                    }
+                    _ => {
+                        let third = self.stack.remove(n - 3);
+                        self.stack.push(third);
+                    }
+                }
+                self.step(depth, expr, what.into());
+            }
+            Expr::Depth => {
+                self.stack.push(Sym::Word(self.stack.len().to_string()));
+                self.step(depth, expr, "push the stack depth".into());
+            }
+            Expr::Peek | Expr::PeekAll => {
+                self.step(depth, expr, "print the stack without changing it".into())
+            }
+            other => {
+                let word = other.to_source();
+                self.unknown(depth, expr, format!("hsab {}", word));
+            }
+        }
+    }
+
+    fn render(self) -> String {
+        let width = self
+            .steps
+            .iter()
+            .map(|s| s.depth * 2 + s.source.chars().count())
+            .max()
+            .unwrap_or(0);
+        let what_width = self
+            .steps
+            .iter()
+            .map(|s| s.what.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut out = String::new();
+        for step in &self.steps {
+            let source = format!("{}{}", "  ".repeat(step.depth), step.source);
+            out.push_str(&format!(
+                "{:<width$}  {:<what_width$}  {}\n",
+                source,
+                step.what,
+                step.stack,
+                width = width,
+                what_width = what_width
+            ));
+        }
+        let lines: Vec<&String> = self
+            .commands
+            .iter()
+            .filter(|(_, embedded)| !embedded)
+            .map(|(line, _)| line)
+            .collect();
+        if lines.is_empty() {
+            out.push_str("\nbash: (no commands run)\n");
+        } else {
+            out.push_str("\nbash:\n");
+            for line in lines {
+                out.push_str(&format!("  {}\n", line));
+            }
+        }
+        out
+    }
+}
+
+impl Evaluator {
+    fn explainer(&mut self) -> Explainer<'_> {
+        Explainer {
+            eval: self,
+            stack: Vec::new(),
+            steps: Vec::new(),
+            commands: Vec::new(),
+            defined: HashMap::new(),
+            calling: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// Describe step by step what `source` would do, ending with the
+    /// equivalent bash command lines. Nothing is executed.
+    pub fn explain(&mut self, source: &str) -> Result<String, String> {
+        let tokens = crate::lex(source).map_err(|e| e.to_string())?;
+        let program = crate::parse(tokens).map_err(|e| e.to_string())?;
+        let mut explainer = self.explainer();
+        explainer.walk(&program.expressions, 0);
+        Ok(explainer.render())
+    }
+
+    /// POSIX shell equivalent of a block, one command per line. Fails if
+    /// the block does anything the shell can't (stack builtins, loops).
+    pub fn to_bash(&mut self, body: &[Expr]) -> Result<String, String> {
+        let mut explainer = self.explainer();
+        explainer.walk(body, 0);
+        if !explainer.problems.is_empty() {
+            return Err(format!(
+                "no shell equivalent for: {}",
+                explainer.problems.join("; ")
+            ));
+        }
+        let lines: Vec<String> = explainer
+            .commands
+            .into_iter()
+            .filter(|(_, embedded)| !embedded)
+            .map(|(line, _)| line)
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    /// explain: Describe a program given as a string, without running it
+    pub(crate) fn builtin_explain(&mut self) -> Result<(), EvalError> {
+        let source = self.pop_string()?;
+        let text = self
+            .explain(&source)
+            .map_err(|e| EvalError::ExecError(format!("explain: {}", e)))?;
+        self.stack.push(Value::Output(text));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// to-bash: Translate a block to shell commands
+    pub(crate) fn builtin_to_bash(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+        let text = self
+            .to_bash(&block)
+            .map_err(|e| EvalError::ExecError(format!("to-bash: {}", e)))?;
+        self.stack.push(Value::Literal(text));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,539 @@
+//! Test data: `fake-name`, `fake-email`, `fake-rows`, and `fake-seed`
+//!
+//! ```text
+//! fake-name                                        → "Grace Okafor"
+//! fake-email                                       → "kenji.lindqvist@example.org"
+//! 100 { id "id" name "name" email "email" age { type "int" min 18 max 90 }
+//!       plan ["free" "pro"] } fake-rows "users.csv" save
+//! ```
+//!
+//! `fake-rows` makes a Table with a column per schema key, in order. A
+//! column's spec is a generator name (see `GENERATORS`), a list to pick
+//! from, or a record with a `type` and, for `int` and `float`, `min` and
+//! `max`. The name, email, and username of a row belong to one person.
+//! Emails, hosts, and addresses use the reserved `example` domains and
+//! documentation IP ranges, and phone numbers the fictional 555-01xx range,
+//! so nothing generated reaches anyone real.
+//!
+//! The generator is seeded from the clock; `42 fake-seed` makes what
+//! follows the same on every run, `sample` included.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// The generators a column spec can name
+const GENERATORS: &[&str] = &[
+    "id",
+    "name",
+    "first-name",
+    "last-name",
+    "username",
+    "email",
+    "phone",
+    "company",
+    "street",
+    "city",
+    "country",
+    "word",
+    "sentence",
+    "url",
+    "ip",
+    "uuid",
+    "int",
+    "float",
+    "bool",
+    "date",
+    "datetime",
+];
+
+const FIRST_NAMES: &[&str] = &[
+    "Ada", "Amara", "Ana", "Arjun", "Ben", "Carlos", "Chen", "Chloe", "Dmitri", "Elena", "Emeka",
+    "Fatima", "Grace", "Hana", "Ines", "Ivan", "Jamal", "Jin", "Kai", "Kenji", "Lars", "Leila",
+    "Liam", "Lucia", "Maya", "Mateo", "Nadia", "Noah", "Olga", "Omar", "Priya", "Rosa", "Sam",
+    "Sofia", "Tariq", "Uma", "Victor", "Wei", "Yara", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Abara",
+    "Andersen",
+    "Bauer",
+    "Castillo",
+    "Chen",
+    "Costa",
+    "Dubois",
+    "Eriksen",
+    "Fischer",
+    "Garcia",
+    "Haddad",
+    "Hughes",
+    "Ivanova",
+    "Jensen",
+    "Kim",
+    "Kowalski",
+    "Lindqvist",
+    "Lopez",
+    "Martin",
+    "Moreau",
+    "Nakamura",
+    "Nguyen",
+    "Novak",
+    "Okafor",
+    "Olsen",
+    "Patel",
+    "Quinn",
+    "Rossi",
+    "Santos",
+    "Schmidt",
+    "Silva",
+    "Singh",
+    "Tanaka",
+    "Turner",
+    "Usman",
+    "Varga",
+    "Walsh",
+    "Yilmaz",
+    "Zhang",
+    "Zielinski",
+];
+
+const CITIES: &[&str] = &[
+    "Lisbon",
+    "Osaka",
+    "Nairobi",
+    "Toronto",
+    "Lyon",
+    "Austin",
+    "Krakow",
+    "Porto",
+    "Seoul",
+    "Denver",
+    "Oslo",
+    "Lagos",
+    "Melbourne",
+    "Bogota",
+    "Hamburg",
+    "Dublin",
+    "Pune",
+    "Valencia",
+    "Chicago",
+    "Auckland",
+];
+
+const COUNTRIES: &[&str] = &[
+    "Portugal",
+    "Japan",
+    "Kenya",
+    "Canada",
+    "France",
+    "United States",
+    "Poland",
+    "South Korea",
+    "Norway",
+    "Nigeria",
+    "Australia",
+    "Colombia",
+    "Germany",
+    "Ireland",
+    "India",
+    "Spain",
+    "New Zealand",
+    "Brazil",
+    "Sweden",
+    "Mexico",
+];
+
+const STREETS: &[&str] = &[
+    "Oak", "Maple", "Cedar", "Elm", "Pine", "Willow", "Harbor", "Mill", "Station", "Church",
+    "Park", "River", "Lake", "Hill", "Market", "Bridge",
+];
+
+const STREET_KINDS: &[&str] = &["St", "Ave", "Rd", "Ln", "Way", "Blvd"];
+
+const COMPANY_WORDS: &[&str] = &[
+    "Acme",
+    "Blue",
+    "Bright",
+    "Cobalt",
+    "Delta",
+    "Ember",
+    "Globe",
+    "Granite",
+    "Harbor",
+    "Iron",
+    "Lumen",
+    "Nimbus",
+    "Northwind",
+    "Orbit",
+    "Pioneer",
+    "Summit",
+    "Vertex",
+    "Zenith",
+];
+
+const COMPANY_KINDS: &[&str] = &[
+    "Labs",
+    "Systems",
+    "Logistics",
+    "Analytics",
+    "Foods",
+    "Partners",
+    "Health",
+    "Works",
+];
+
+const WORDS: &[&str] = &[
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "consectetur",
+    "adipiscing",
+    "elit",
+    "sed",
+    "do",
+    "eiusmod",
+    "tempor",
+    "incididunt",
+    "ut",
+    "labore",
+    "et",
+    "dolore",
+    "magna",
+    "aliqua",
+    "enim",
+    "minim",
+    "veniam",
+    "quis",
+    "nostrud",
+];
+
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// The first and last day `date` and `datetime` pick from
+const FIRST_DAY: (i32, u32, u32) = (2015, 1, 1);
+const LAST_DAY: (i32, u32, u32) = (2025, 12, 31);
+
+/// The next number from a splitmix64 generator
+fn next(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Generated floats have two decimal places, as prices and amounts do
+fn cents(n: f64) -> f64 {
+    (n * 100.0).round() / 100.0
+}
+
+/// A seed that differs from run to run
+fn clock_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    nanos ^ u64::from(std::process::id()).rotate_left(32)
+}
+
+/// Someone to put in a row
+struct Person {
+    first: &'static str,
+    last: &'static str,
+}
+
+struct Faker<'a> {
+    state: &'a mut u64,
+}
+
+impl Faker<'_> {
+    fn below(&mut self, n: u64) -> u64 {
+        next(self.state) % n
+    }
+
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        let span = max.abs_diff(min).saturating_add(1);
+        min.wrapping_add(self.below(span.max(1)) as i64)
+    }
+
+    /// A number from 0 to 1
+    fn unit(&mut self) -> f64 {
+        (next(self.state) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+
+    fn person(&mut self) -> Person {
+        Person {
+            first: self.pick(FIRST_NAMES),
+            last: self.pick(LAST_NAMES),
+        }
+    }
+
+    fn username(&mut self, person: &Person) -> String {
+        let first = person.first.to_lowercase();
+        let last = person.last.to_lowercase();
+        match self.below(3) {
+            0 => format!("{}.{}", first, last),
+            1 => format!("{}{}", &first[..1], last),
+            _ => format!("{}{}", first, self.range(1, 99)),
+        }
+    }
+
+    fn email(&mut self, person: &Person) -> String {
+        let user = self.username(person);
+        format!("{}@{}", user, self.pick(EMAIL_DOMAINS))
+    }
+
+    fn sentence(&mut self) -> String {
+        let count = self.range(4, 10) as usize;
+        let words: Vec<&str> = (0..count).map(|_| self.pick(WORDS)).collect();
+        let mut sentence = words.join(" ");
+        sentence[..1].make_ascii_uppercase();
+        sentence.push('.');
+        sentence
+    }
+
+    fn day(&mut self) -> chrono::NaiveDate {
+        let day = |(y, m, d)| chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap_or_default();
+        let (first, last) = (day(FIRST_DAY), day(LAST_DAY));
+        let days = self.range(0, (last - first).num_days());
+        first + chrono::Duration::days(days)
+    }
+
+    fn uuid(&mut self) -> String {
+        let (high, low) = (next(self.state), next(self.state));
+        // Version 4, variant 1
+        let high = (high & !0xf000) | 0x4000;
+        let low = (low & !(0b11 << 62)) | (0b10 << 62);
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            high >> 32,
+            (high >> 16) & 0xffff,
+            high & 0xffff,
+            low >> 48,
+            low & 0xffff_ffff_ffff
+        )
+    }
+
+    /// A value from the generator `kind`, for row `row` and its person
+    fn value(&mut self, kind: &str, row: usize, person: &Person) -> Value {
+        let text = |s: String| Value::Literal(s);
+        match kind {
+            "id" => Value::Int(row as i64 + 1),
+            "name" => text(format!("{} {}", person.first, person.last)),
+            "first-name" => text(person.first.to_string()),
+            "last-name" => text(person.last.to_string()),
+            "username" => text(self.username(person)),
+            "email" => text(self.email(person)),
+            "phone" => text(format!(
+                "+1-{}-555-01{:02}",
+                self.range(201, 989),
+                self.below(100)
+            )),
+            "company" => text(format!(
+                "{} {}",
+                self.pick(COMPANY_WORDS),
+                self.pick(COMPANY_KINDS)
+            )),
+            "street" => text(format!(
+                "{} {} {}",
+                self.range(1, 9999),
+                self.pick(STREETS),
+                self.pick(STREET_KINDS)
+            )),
+            "city" => text(self.pick(CITIES).to_string()),
+            "country" => text(self.pick(COUNTRIES).to_string()),
+            "word" => text(self.pick(WORDS).to_string()),
+            "sentence" => text(self.sentence()),
+            "url" => text(format!(
+                "https://{}.example.com/{}",
+                self.pick(WORDS),
+                self.pick(WORDS)
+            )),
+            "ip" => {
+                let net = self.pick(&["192.0.2", "198.51.100", "203.0.113"]);
+                text(format!("{}.{}", net, self.range(1, 254)))
+            }
+            "uuid" => text(self.uuid()),
+            "int" => Value::Int(self.range(0, 1000)),
+            "float" => Value::Number(cents(self.unit() * 1000.0)),
+            "bool" => Value::Bool(self.below(2) == 1),
+            "date" => text(self.day().format("%Y-%m-%d").to_string()),
+            "datetime" => {
+                let seconds = self.below(86_400);
+                text(format!(
+                    "{}T{:02}:{:02}:{:02}",
+                    self.day().format("%Y-%m-%d"),
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                ))
+            }
+            _ => Value::Nil,
+        }
+    }
+}
+
+/// A column's spec: what generates it
+enum Column {
+    Kind(String),
+    Between { float: bool, min: f64, max: f64 },
+    OneOf(Vec<Value>),
+}
+
+fn generator(column: &str, kind: &str) -> Result<String, EvalError> {
+    let kind = kind.trim();
+    if GENERATORS.contains(&kind) {
+        Ok(kind.to_string())
+    } else {
+        Err(EvalError::ExecError(format!(
+            "fake-rows: {}: unknown generator '{}' (one of {})",
+            column,
+            kind,
+            GENERATORS.join(", ")
+        )))
+    }
+}
+
+fn column(name: &str, spec: &Value) -> Result<Column, EvalError> {
+    let fail = |message: String| EvalError::ExecError(format!("fake-rows: {}: {}", name, message));
+    match spec {
+        Value::Literal(kind) | Value::Output(kind) => Ok(Column::Kind(generator(name, kind)?)),
+        Value::List(choices) if !choices.is_empty() => Ok(Column::OneOf(choices.clone())),
+        Value::Map(spec) => {
+            let kind = match spec.get("type") {
+                Some(Value::Literal(kind) | Value::Output(kind)) => generator(name, kind)?,
+                _ => return Err(fail("a spec record needs a type".into())),
+            };
+            if !spec.contains_key("min") && !spec.contains_key("max") {
+                return Ok(Column::Kind(kind));
+            }
+            let float = match kind.as_str() {
+                "int" => false,
+                "float" => true,
+                _ => {
+                    return Err(fail(format!(
+                        "min and max are for int and float, not {}",
+                        kind
+                    )))
+                }
+            };
+            let bound = |key: &str, default: f64| match spec.get(key) {
+                None => Ok(default),
+                Some(Value::Int(i)) => Ok(*i as f64),
+                Some(Value::Number(n)) => Ok(*n),
+                Some(other) => Err(fail(format!(
+                    "{} must be a number, not {}",
+                    key,
+                    other.type_name()
+                ))),
+            };
+            let (min, max) = (bound("min", 0.0)?, bound("max", 1000.0)?);
+            if min > max {
+                return Err(fail(format!("min {} is above max {}", min, max)));
+            }
+            Ok(Column::Between { float, min, max })
+        }
+        other => Err(fail(format!(
+            "a spec is a generator name, a list, or a record, not {}",
+            other.type_name()
+        ))),
+    }
+}
+
+impl Evaluator {
+    fn faker(&mut self) -> Faker<'_> {
+        Faker {
+            state: self.fake_rng.get_or_insert_with(clock_seed),
+        }
+    }
+
+    /// A random index below `n`, from the generator `fake-seed` seeds
+    pub(crate) fn random_below(&mut self, n: usize) -> usize {
+        self.faker().below(n.max(1) as u64) as usize
+    }
+
+    /// fake-name: fake-name → "First Last"
+    pub(crate) fn builtin_fake_name(&mut self) -> Result<(), EvalError> {
+        let mut faker = self.faker();
+        let person = faker.person();
+        let name = faker.value("name", 0, &person);
+        self.stack.push(name);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// fake-email: fake-email → "first.last@example.com"
+    pub(crate) fn builtin_fake_email(&mut self) -> Result<(), EvalError> {
+        let mut faker = self.faker();
+        let person = faker.person();
+        let email = faker.value("email", 0, &person);
+        self.stack.push(email);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// fake-rows: N schema fake-rows → Table
+    pub(crate) fn builtin_fake_rows(&mut self) -> Result<(), EvalError> {
+        let schema = match self.pop_value_or_err()? {
+            Value::Map(schema) => schema,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "schema record (fake-rows)".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let count = self.pop_int("fake-rows")?;
+        let count = usize::try_from(count)
+            .map_err(|_| EvalError::ExecError(format!("fake-rows: bad count {}", count)))?;
+        let columns = schema
+            .iter()
+            .map(|(name, spec)| Ok((name.clone(), column(name, spec)?)))
+            .collect::<Result<IndexMap<_, _>, EvalError>>()?;
+
+        let mut faker = self.faker();
+        let rows = (0..count)
+            .map(|row| {
+                let person = faker.person();
+                columns
+                    .values()
+                    .map(|column| match column {
+                        Column::Kind(kind) => faker.value(kind, row, &person),
+                        Column::Between {
+                            float: false,
+                            min,
+                            max,
+                        } => Value::Int(faker.range(min.ceil() as i64, max.floor() as i64)),
+                        Column::Between { min, max, .. } => {
+                            Value::Number(cents(min + faker.unit() * (max - min)))
+                        }
+                        Column::OneOf(choices) => {
+                            choices[faker.below(choices.len() as u64) as usize].clone()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        self.stack.push(Value::Table {
+            columns: columns.into_keys().collect(),
+            rows,
+        });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// fake-seed: N fake-seed → (the fake- words repeat from here)
+    pub(crate) fn builtin_fake_seed(&mut self) -> Result<(), EvalError> {
+        let seed = self.pop_int("fake-seed")?;
+        self.fake_rng = Some(seed as u64);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,372 @@
+//! Code host APIs: GitHub and GitLab
+//!
+//! `"repos/{owner}/{repo}/issues" gh-api` calls the GitHub REST API and
+//! pushes the response as values; `gl-api` does the same for GitLab, where
+//! `{project}` names the repository. The placeholders are filled from
+//! `GH_REPO` or `CI_PROJECT_PATH` when set, else from the `origin` remote
+//! of the current directory. A list that spans pages (followed through the
+//! `Link` header) is pushed whole, as a Table when it holds objects.
+//!
+//! Tokens come from `GH_TOKEN` or `GITHUB_TOKEN`, and `GITLAB_TOKEN`, and
+//! are only ever sent as headers, never pushed. `GITHUB_API_URL` and
+//! `CI_API_V4_URL` point the words at another server, as in CI. On top of
+//! these, `pr-list` and `issue-create` work with whichever host `origin`
+//! is on:
+//!
+//! ```text
+//! pr-list #["author" get "dependabot[bot]" eq?] where
+//! { title "Release 1.2" body "Checklist" } issue-create "url" get
+//! ```
+
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+use indexmap::IndexMap;
+use serde_json::{Map, Value as JsonValue};
+
+use super::cloud::{list_value, merge_page};
+use super::http::is_http_method;
+use super::{EvalError, Evaluator};
+use crate::ast::{json_to_value, value_to_json, Value};
+
+/// Pages fetched before giving up on a `next` link that never runs out
+const MAX_PAGES: usize = 1000;
+
+/// Columns of the table `pr-list` pushes
+const PR_COLUMNS: [&str; 5] = ["number", "title", "author", "branch", "url"];
+
+/// A code host with a REST API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Host {
+    GitHub,
+    GitLab,
+}
+
+impl Host {
+    /// The word calling this host's API, for messages
+    fn word(self) -> &'static str {
+        match self {
+            Host::GitHub => "gh-api",
+            Host::GitLab => "gl-api",
+        }
+    }
+
+    /// The API's root URL
+    fn base(self) -> String {
+        let (var, default) = match self {
+            Host::GitHub => ("GITHUB_API_URL", "https://api.github.com"),
+            Host::GitLab => ("CI_API_V4_URL", "https://gitlab.com/api/v4"),
+        };
+        super::env::var(var)
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// The headers every request carries, including the token if there is one
+    fn headers(self) -> HashMap<String, String> {
+        let mut headers = HashMap::from([("User-Agent".to_string(), "hsab".to_string())]);
+        let vars: &[&str] = match self {
+            Host::GitHub => &["GH_TOKEN", "GITHUB_TOKEN"],
+            Host::GitLab => &["GITLAB_TOKEN"],
+        };
+        let token = vars
+            .iter()
+            .find_map(|var| super::env::var(var).ok().filter(|t| !t.is_empty()));
+        match self {
+            Host::GitHub => {
+                headers.insert("Accept".into(), "application/vnd.github+json".into());
+                if let Some(token) = token {
+                    headers.insert("Authorization".into(), format!("Bearer {}", token));
+                }
+            }
+            Host::GitLab => {
+                if let Some(token) = token {
+                    headers.insert("PRIVATE-TOKEN".into(), token);
+                }
+            }
+        }
+        headers
+    }
+}
+
+/// The `owner/repo` path of a remote URL, in scp (`git@host:o/r.git`) or
+/// URL (`https://host/o/r`) form
+fn remote_path(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+    path.contains('/').then(|| path.to_string())
+}
+
+/// The URL of the page after this one, from a `Link` header
+fn next_link(headers: &HashMap<String, String>) -> Option<String> {
+    let (_, link) = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("link"))?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params.contains("rel=\"next\"").then(|| {
+            url.trim()
+                .trim_matches(|c| c == '<' || c == '>')
+                .to_string()
+        })
+    })
+}
+
+/// A field of a JSON object, following a path of keys
+fn field(item: &JsonValue, path: &[&str]) -> Value {
+    let found = path.iter().try_fold(item, |value, key| value.get(key));
+    found.cloned().map(json_to_value).unwrap_or(Value::Nil)
+}
+
+impl Evaluator {
+    /// gh-api: "path" gh-api → value; record "path" "POST" gh-api → value
+    /// Call the GitHub REST API, merging pages into one Table
+    pub(crate) fn builtin_gh_api(&mut self) -> Result<(), EvalError> {
+        self.forge_api(Host::GitHub)
+    }
+
+    /// gl-api: "path" gl-api → value; record "path" "POST" gl-api → value
+    /// Call the GitLab REST API, merging pages into one Table
+    pub(crate) fn builtin_gl_api(&mut self) -> Result<(), EvalError> {
+        self.forge_api(Host::GitLab)
+    }
+
+    /// pr-list: pr-list → Table
+    /// The open pull (or merge) requests of the repository `origin` is on
+    pub(crate) fn builtin_pr_list(&mut self) -> Result<(), EvalError> {
+        let host = self.origin_host();
+        let (path, fields): (_, [&[&str]; 5]) = match host {
+            Host::GitHub => (
+                "repos/{owner}/{repo}/pulls?state=open",
+                [
+                    &["number"],
+                    &["title"],
+                    &["user", "login"],
+                    &["head", "ref"],
+                    &["html_url"],
+                ],
+            ),
+            Host::GitLab => (
+                "projects/{project}/merge_requests?state=opened",
+                [
+                    &["iid"],
+                    &["title"],
+                    &["author", "username"],
+                    &["source_branch"],
+                    &["web_url"],
+                ],
+            ),
+        };
+        let items = match self.forge_request(host, "GET", path, None)? {
+            JsonValue::Array(items) => items,
+            _ => Vec::new(),
+        };
+        let rows = items
+            .iter()
+            .map(|item| fields.iter().map(|path| field(item, path)).collect())
+            .collect();
+        self.stack.push(Value::Table {
+            columns: PR_COLUMNS.iter().map(|c| c.to_string()).collect(),
+            rows,
+        });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// issue-create: { title ".." body ".." } issue-create → { number title url }
+    /// Open an issue on the repository `origin` is on
+    pub(crate) fn builtin_issue_create(&mut self) -> Result<(), EvalError> {
+        let record = match self.pop_value_or_err()? {
+            Value::Map(record) => record,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "Record".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        if !record.contains_key("title") {
+            return Err(EvalError::ExecError(
+                "issue-create: the record needs a title".into(),
+            ));
+        }
+        let host = self.origin_host();
+        let (path, body, number, url) = match host {
+            Host::GitHub => ("repos/{owner}/{repo}/issues", record, "number", "html_url"),
+            Host::GitLab => {
+                // GitLab calls the body a description
+                let record = record
+                    .into_iter()
+                    .map(|(k, v)| match k.as_str() {
+                        "body" => ("description".to_string(), v),
+                        _ => (k, v),
+                    })
+                    .collect();
+                ("projects/{project}/issues", record, "iid", "web_url")
+            }
+        };
+        let body = value_to_json(&Value::Map(body)).to_string();
+        let issue = self.forge_request(host, "POST", path, Some(body))?;
+
+        let mut created = IndexMap::new();
+        created.insert("number".to_string(), field(&issue, &[number]));
+        created.insert("title".to_string(), field(&issue, &["title"]));
+        created.insert("url".to_string(), field(&issue, &[url]));
+        self.stack.push(Value::Map(created));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Pop a path, an optional method, and a body record, and call `host`
+    fn forge_api(&mut self, host: Host) -> Result<(), EvalError> {
+        let top = self.pop_string()?;
+        let (method, path) = if is_http_method(&top) {
+            (top.to_uppercase(), self.pop_string()?)
+        } else {
+            ("GET".to_string(), top)
+        };
+        let body = match (method.as_str(), self.stack.last()) {
+            ("GET" | "HEAD" | "DELETE", _) => None,
+            (_, Some(Value::Map(_))) => self.stack.pop().map(|v| value_to_json(&v).to_string()),
+            _ => None,
+        };
+        let value = match self.forge_request(host, &method, &path, body)? {
+            JsonValue::Array(items) => list_value(items),
+            other => json_to_value(other),
+        };
+        self.stack.push(value);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Send a request to `host`, following `next` links for GET, and
+    /// return the response with every page's items together
+    fn forge_request(
+        &mut self,
+        host: Host,
+        method: &str,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<JsonValue, EvalError> {
+        let word = host.word();
+        let path = self.fill_repo(host, path)?;
+        let mut url = if path.starts_with("http://") || path.starts_with("https://") {
+            path
+        } else {
+            format!("{}/{}", host.base(), path.trim_start_matches('/'))
+        };
+        if method == "GET" && !url.contains("per_page=") {
+            let sep = if url.contains('?') { '&' } else { '?' };
+            url = format!("{}{}per_page=100", url, sep);
+        }
+        let headers = host.headers();
+
+        let mut pages: Vec<JsonValue> = Vec::new();
+        for _ in 0..MAX_PAGES {
+            let response = self.do_http_request(method, &url, body.as_deref(), Some(&headers))?;
+            let json: JsonValue = if response.body.trim().is_empty() {
+                JsonValue::Null
+            } else {
+                serde_json::from_str(&response.body).unwrap_or(JsonValue::String(response.body))
+            };
+            if response.status >= 400 {
+                let message = json["message"]
+                    .as_str()
+                    .or(json["error"].as_str())
+                    .or(json.as_str())
+                    .unwrap_or("request failed")
+                    .to_string();
+                return Err(EvalError::ExecError(format!(
+                    "{}: {} {}",
+                    word, response.status, message
+                )));
+            }
+            pages.push(json);
+            match next_link(&response.headers).filter(|_| method == "GET") {
+                Some(next) => url = next,
+                None => break,
+            }
+        }
+
+        if pages.len() == 1 {
+            return Ok(pages.remove(0));
+        }
+        if pages.iter().all(JsonValue::is_array) {
+            let items = pages
+                .into_iter()
+                .flat_map(|page| match page {
+                    JsonValue::Array(items) => items,
+                    _ => Vec::new(),
+                })
+                .collect();
+            return Ok(JsonValue::Array(items));
+        }
+        let mut merged = Map::new();
+        for page in pages {
+            if let JsonValue::Object(page) = page {
+                merge_page(&mut merged, page);
+            }
+        }
+        Ok(JsonValue::Object(merged))
+    }
+
+    /// Fill `{owner}`, `{repo}`, and `{project}` in `path`
+    fn fill_repo(&mut self, host: Host, path: &str) -> Result<String, EvalError> {
+        if !["{owner}", "{repo}", "{project}"]
+            .iter()
+            .any(|p| path.contains(p))
+        {
+            return Ok(path.to_string());
+        }
+        let var = match host {
+            Host::GitHub => "GH_REPO",
+            Host::GitLab => "CI_PROJECT_PATH",
+        };
+        let repo = super::env::var(var)
+            .ok()
+            .filter(|r| r.contains('/'))
+            .or_else(|| self.origin_url().as_deref().and_then(remote_path))
+            .ok_or_else(|| {
+                EvalError::ExecError(format!(
+                    "{}: no repository for {{owner}}/{{repo}} (set {} or add an origin remote)",
+                    host.word(),
+                    var
+                ))
+            })?;
+        let (owner, name) = repo.rsplit_once('/').unwrap_or(("", &repo));
+        Ok(path
+            .replace("{owner}", owner)
+            .replace("{repo}", name)
+            .replace("{project}", &repo.replace('/', "%2F")))
+    }
+
+    /// The URL of the current directory's `origin` remote
+    fn origin_url(&mut self) -> Option<String> {
+        let args = ["remote".to_string(), "get-url".into(), "origin".into()];
+        let audit = self.start_process("forge", "git", &args).ok()?;
+        let output = Command::new("git")
+            .args(&args)
+            .current_dir(&self.cwd)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        audit.finish(output.status.code().unwrap_or(-1));
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// The host `origin` is on: GitLab if its URL says so, else GitHub
+    fn origin_host(&mut self) -> Host {
+        match self.origin_url() {
+            Some(url) if url.contains("gitlab") => Host::GitLab,
+            _ => Host::GitHub,
+        }
+    }
+}
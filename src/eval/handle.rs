@@ -0,0 +1,101 @@
+//! A shareable handle to an evaluator running on its own thread
+//!
+//! `Evaluator` is `Send`, so it can be moved to another thread, but not
+//! `Sync`: only one caller can use it at a time. `EvaluatorHandle` owns an
+//! evaluator on a dedicated thread and passes work to it over a channel, so
+//! the handle can be cloned into as many threads or async tasks as needed.
+//! Calls run one at a time, in the order they arrive, against the same
+//! stack, definitions, and working directory.
+//!
+//! ```rust
+//! use hsab::EvaluatorHandle;
+//!
+//! let shell = EvaluatorHandle::default();
+//! shell.eval("#[dup plus] :double").unwrap();
+//! let worker = shell.clone();
+//! let result = std::thread::spawn(move || worker.eval("21 double").unwrap())
+//!     .join()
+//!     .unwrap();
+//! assert_eq!(result.output, "42");
+//! ```
+//!
+//! Calls block until the evaluator is done; from async code, call them from
+//! a blocking task (`tokio::task::spawn_blocking`). A call that runs too long
+//! can be stopped with the handle's `cancel_token()`. Environment variables
+//! belong to the process, so every evaluator in it shares them.
+
+use std::sync::mpsc;
+use std::thread;
+
+use super::{CancelToken, EvalResult, Evaluator};
+use crate::{lex, parse};
+
+type Job = Box<dyn FnOnce(&mut Evaluator) + Send>;
+
+/// A cloneable, thread-safe handle to an evaluator on its own thread. The
+/// thread exits when the last handle is dropped.
+#[derive(Clone)]
+pub struct EvaluatorHandle {
+    jobs: mpsc::Sender<Job>,
+    cancel: CancelToken,
+}
+
+impl EvaluatorHandle {
+    /// Move `evaluator` to a new thread and return a handle to it
+    pub fn new(mut evaluator: Evaluator) -> Self {
+        let cancel = evaluator.cancel_token();
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("hsab-evaluator".into())
+            .spawn(move || {
+                for job in queue {
+                    job(&mut evaluator);
+                }
+            })
+            .expect("failed to spawn evaluator thread");
+        EvaluatorHandle { jobs, cancel }
+    }
+
+    /// A token that cancels the eval running on the thread, for stopping
+    /// it without waiting for it to finish
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Lex, parse, and evaluate `source`, like [`crate::eval`] but keeping
+    /// state between calls
+    pub fn eval(&self, source: &str) -> Result<EvalResult, String> {
+        let source = source.to_string();
+        self.with(move |evaluator| {
+            let tokens = lex(&source).map_err(|e| e.to_string())?;
+            let program = parse(tokens).map_err(|e| e.to_string())?;
+            evaluator.eval(&program).map_err(|e| e.to_string())
+        })?
+    }
+
+    /// Run `f` with the evaluator on its thread and return what it returns,
+    /// for configuring it or reading its state. Fails only if the thread
+    /// has stopped because an earlier call panicked.
+    pub fn with<R, F>(&self, f: F) -> Result<R, String>
+    where
+        F: FnOnce(&mut Evaluator) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        self.jobs
+            .send(Box::new(move |evaluator| {
+                let _ = reply.send(f(evaluator));
+            }))
+            .map_err(|_| STOPPED.to_string())?;
+        result.recv().map_err(|_| STOPPED.to_string())
+    }
+}
+
+impl Default for EvaluatorHandle {
+    /// A handle to a new `Evaluator`
+    fn default() -> Self {
+        EvaluatorHandle::new(Evaluator::new())
+    }
+}
+
+const STOPPED: &str = "evaluator thread has stopped";
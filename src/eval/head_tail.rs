@@ -0,0 +1,186 @@
+//! `head`, `tail`, and `sample`: part of a List, Table, or text
+//!
+//! ```text
+//! [a b c d] 2 head             → [a, b]
+//! ps-t 5 tail                  → the last 5 rows
+//! "app.log" open 3 sample      → 3 random lines, in file order
+//! "app.log" 20 tail-file       → the last 20 lines, read from the end
+//! ```
+//!
+//! Text is taken line by line and stays text. `head` and `tail` only work
+//! on values when a count is on top of one; otherwise they're the head and
+//! tail commands, so `notes.txt -5 head` runs as before. `sample` draws from
+//! the generator `fake-seed` seeds.
+//!
+//! `tail-file` seeks to the end of the file and reads backwards a block at a
+//! time until it has enough lines, so a large log costs no more than its
+//! last lines.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// How much of the end of a file `tail-file` reads at a time
+const TAIL_BLOCK: u64 = 8192;
+
+/// Which items to keep
+#[derive(Clone, Copy)]
+enum Take {
+    Head,
+    Tail,
+    Sample,
+}
+
+impl Evaluator {
+    /// Whether `head` or `tail` takes from a value: a count on top of a
+    /// List, Table, or text. `-5` is the command's option, and so is text
+    /// like `-n` under a count.
+    pub(crate) fn head_tail_is_native(&self) -> bool {
+        let [.., value, count] = &self.stack[..] else {
+            return false;
+        };
+        let counts = match count {
+            Value::Int(n) => *n >= 0,
+            Value::Number(n) => *n >= 0.0,
+            _ => false,
+        };
+        counts
+            && match value {
+                Value::List(_) | Value::Table { .. } | Value::Output(_) => true,
+                Value::Literal(text) => !text.starts_with('-'),
+                _ => false,
+            }
+    }
+
+    /// head: value N head → its first N items, rows, or lines
+    pub(crate) fn builtin_head(&mut self) -> Result<(), EvalError> {
+        self.take_items("head", Take::Head)
+    }
+
+    /// tail: value N tail → its last N items, rows, or lines
+    pub(crate) fn builtin_tail(&mut self) -> Result<(), EvalError> {
+        self.take_items("tail", Take::Tail)
+    }
+
+    /// sample: value N sample → N of its items, rows, or lines at random,
+    /// in their original order
+    pub(crate) fn builtin_sample(&mut self) -> Result<(), EvalError> {
+        self.take_items("sample", Take::Sample)
+    }
+
+    fn take_items(&mut self, op: &str, take: Take) -> Result<(), EvalError> {
+        let n = self.pop_int(op)?.max(0) as usize;
+        let value = self.pop_value_or_err()?;
+        let result = match value {
+            Value::List(items) => Value::List(self.take(items, n, take)),
+            Value::Table { columns, rows } => Value::Table {
+                columns,
+                rows: self.take(rows, n, take),
+            },
+            Value::Output(text) => Value::Output(self.take_lines(&text, n, take)),
+            Value::Literal(text) => Value::Literal(self.take_lines(&text, n, take)),
+            other => {
+                let err = EvalError::TypeError {
+                    expected: format!("List, Table, or text ({})", op),
+                    got: other.type_name().to_string(),
+                };
+                self.stack.push(other);
+                return Err(err);
+            }
+        };
+        self.stack.push(result);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    fn take<T>(&mut self, mut items: Vec<T>, n: usize, take: Take) -> Vec<T> {
+        let len = items.len();
+        match take {
+            Take::Head => {
+                items.truncate(n);
+                items
+            }
+            Take::Tail => items.split_off(len.saturating_sub(n)),
+            Take::Sample => {
+                // A partial shuffle of the indices picks n without repeats
+                let mut indices: Vec<usize> = (0..len).collect();
+                let n = n.min(len);
+                for i in 0..n {
+                    let j = i + self.random_below(len - i);
+                    indices.swap(i, j);
+                }
+                let mut keep = vec![false; len];
+                for &i in &indices[..n] {
+                    keep[i] = true;
+                }
+                items
+                    .into_iter()
+                    .zip(keep)
+                    .filter_map(|(item, keep)| keep.then_some(item))
+                    .collect()
+            }
+        }
+    }
+
+    fn take_lines(&mut self, text: &str, n: usize, take: Take) -> String {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut kept = self.take(lines, n, take).join("\n");
+        if text.ends_with('\n') && !kept.is_empty() {
+            kept.push('\n');
+        }
+        kept
+    }
+
+    /// tail-file: "path" N tail-file → its last N lines
+    pub(crate) fn builtin_tail_file(&mut self) -> Result<(), EvalError> {
+        let n = self.pop_int("tail-file")?.max(0) as usize;
+        let name = self.pop_string()?;
+        let path = self.cwd.join(self.expand_tilde(&name));
+        let fail = |e: std::io::Error| EvalError::ExecError(format!("tail-file: {}: {}", name, e));
+
+        let mut file = std::fs::File::open(&path).map_err(fail)?;
+        let len = file.seek(SeekFrom::End(0)).map_err(fail)?;
+        let start = last_lines_start(&mut file, len, n).map_err(fail)?;
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(start)).map_err(fail)?;
+        file.read_to_end(&mut bytes).map_err(fail)?;
+
+        let text = self.decode_output(&bytes);
+        self.stack.push(if text.is_empty() {
+            Value::Nil
+        } else {
+            Value::Output(text)
+        });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
+
+/// Where the last `n` lines of a file of `len` bytes start, found by reading
+/// backwards from the end. A newline at the very end doesn't start a line.
+fn last_lines_start<F: Read + Seek>(file: &mut F, len: u64, n: usize) -> std::io::Result<u64> {
+    if n == 0 {
+        return Ok(len);
+    }
+    let mut end = len;
+    let mut newlines = 0;
+    let mut block = vec![0; TAIL_BLOCK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_BLOCK);
+        let chunk = &mut block[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for (i, &byte) in chunk.iter().enumerate().rev() {
+            let at = start + i as u64;
+            if byte == b'\n' && at + 1 != len {
+                newlines += 1;
+                if newlines == n {
+                    return Ok(at + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
@@ -0,0 +1,204 @@
+//! Health checks: `reachable?`, `icmp-ping`, and `http-healthy?`
+//!
+//! ```text
+//! "db.internal" 5432 reachable?               → true, exit 0
+//! "10.0.0.7" icmp-ping                        → { host address alive ms via }
+//! "https://api.example.com/health" http-healthy?   → true, exit 0
+//! ```
+//!
+//! Each answers without a tool to run or output to parse, so a loop can
+//! wait on a service with `#[...] until`. `reachable?` opens a TCP
+//! connection. `icmp-ping` sends an echo request where the system lets
+//! users do that without privileges (Linux's ping sockets, macOS), and
+//! otherwise knocks on port 443: an answer of any kind, even a refusal,
+//! means the host is up. `http-healthy?` is true for a 2xx or 3xx status,
+//! and its exit code tells a status that isn't (1) from no answer (2).
+
+// The WebAssembly build can't connect, so checks nothing
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// How long a connection or echo request is given
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long `http-healthy?` waits for a response
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The port `icmp-ping` knocks on when it can't send ICMP
+const KNOCK_PORT: u16 = 443;
+
+fn resolve(op: &str, host: &str, port: u16) -> Result<Vec<SocketAddr>, EvalError> {
+    (host, port)
+        .to_socket_addrs()
+        .map(|addrs| addrs.collect())
+        .map_err(|e| EvalError::ExecError(format!("{}: {}: {}", op, host, e)))
+}
+
+/// Whether a TCP connection to one of `addrs` opens
+fn connects(addrs: &[SocketAddr]) -> bool {
+    addrs
+        .iter()
+        .any(|addr| TcpStream::connect_timeout(addr, TIMEOUT).is_ok())
+}
+
+/// The round trip of an echo request to `ip`, or None without an answer;
+/// Err when the system won't let us send one
+#[cfg(unix)]
+fn echo(ip: IpAddr) -> std::io::Result<Option<Duration>> {
+    use std::net::UdpSocket;
+    use std::os::unix::io::FromRawFd;
+
+    let (domain, protocol, request, reply) = match ip {
+        IpAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP, 8u8, 0u8),
+        IpAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6, 128, 129),
+    };
+    // SAFETY: socket() has no preconditions; the descriptor it returns is
+    // owned by the UdpSocket, which closes it
+    let fd = unsafe { libc::socket(domain, libc::SOCK_DGRAM, protocol) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // A datagram ICMP socket sends and receives like a UDP one
+    let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.connect(SocketAddr::new(ip, 0))?;
+
+    let sequence = (std::process::id() as u16).to_be_bytes();
+    let mut packet = vec![request, 0, 0, 0, 0, 0, sequence[0], sequence[1]];
+    packet.extend_from_slice(b"hsab-ping");
+    // The kernel fills in ICMPv6's checksum; macOS wants ICMP's from us
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    let started = Instant::now();
+    socket.send(&packet)?;
+    let mut buf = [0u8; 512];
+    while let Some(left) = TIMEOUT.checked_sub(started.elapsed()) {
+        socket.set_read_timeout(Some(left.max(Duration::from_millis(1))))?;
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(e) => return Err(e),
+        };
+        // macOS hands back IPv4 replies with their IP header
+        let skip = if ip.is_ipv4() && n >= 20 && buf[0] >> 4 == 4 {
+            usize::from(buf[0] & 0x0f) * 4
+        } else {
+            0
+        };
+        let icmp = &buf[skip.min(n)..n];
+        if icmp.len() >= 8 && icmp[0] == reply && icmp[6..8] == sequence {
+            return Ok(Some(started.elapsed()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(not(unix))]
+fn echo(_ip: IpAddr) -> std::io::Result<Option<Duration>> {
+    Err(ErrorKind::Unsupported.into())
+}
+
+/// The Internet checksum of an ICMP message
+fn checksum(packet: &[u8]) -> u16 {
+    let mut sum: u32 = packet
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Whether `addr` answers on `KNOCK_PORT`, and how long it took
+fn knock(ip: IpAddr) -> Option<Duration> {
+    let started = Instant::now();
+    match TcpStream::connect_timeout(&SocketAddr::new(ip, KNOCK_PORT), TIMEOUT) {
+        Ok(_) => Some(started.elapsed()),
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => Some(started.elapsed()),
+        Err(_) => None,
+    }
+}
+
+impl Evaluator {
+    /// reachable?: "host" port reachable? → Bool
+    /// Whether a TCP connection to the port opens
+    pub(crate) fn builtin_reachable(&mut self) -> Result<(), EvalError> {
+        let port = self.pop_int("reachable?")?;
+        let host = self.pop_string()?;
+        let port = u16::try_from(port)
+            .map_err(|_| EvalError::ExecError(format!("reachable?: bad port {}", port)))?;
+        // A name that doesn't resolve isn't reachable either
+        let up = resolve("reachable?", host.trim(), port).is_ok_and(|addrs| connects(&addrs));
+        self.stack.push(Value::Bool(up));
+        self.last_exit_code = if up { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// icmp-ping: "host" icmp-ping → { host address alive ms via }
+    pub(crate) fn builtin_icmp_ping(&mut self) -> Result<(), EvalError> {
+        let host = self.pop_string()?;
+        let host = host.trim().to_string();
+        let ip = resolve("icmp-ping", &host, 0)?
+            .first()
+            .map(SocketAddr::ip)
+            .ok_or_else(|| EvalError::ExecError(format!("icmp-ping: {}: no addresses", host)))?;
+        self.check_cancelled()?;
+        let (round_trip, via) = match echo(ip) {
+            Ok(round_trip) => (round_trip, "icmp"),
+            Err(_) => (knock(ip), "tcp"),
+        };
+
+        let mut record = IndexMap::new();
+        record.insert("host".to_string(), Value::Literal(host));
+        record.insert("address".to_string(), Value::Literal(ip.to_string()));
+        record.insert("alive".to_string(), Value::Bool(round_trip.is_some()));
+        record.insert(
+            "ms".to_string(),
+            round_trip.map_or(Value::Nil, |d| Value::Number(d.as_secs_f64() * 1000.0)),
+        );
+        record.insert("via".to_string(), Value::Literal(via.to_string()));
+        self.stack.push(Value::Map(record));
+        self.last_exit_code = if round_trip.is_some() { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// http-healthy?: "url" http-healthy? → Bool
+    /// Exit 0 for a 2xx or 3xx status, 1 for another, 2 for no response
+    pub(crate) fn builtin_http_healthy(&mut self) -> Result<(), EvalError> {
+        let url = self.pop_string()?;
+        let code = match http_status(url.trim()) {
+            Some(200..=399) => 0,
+            Some(_) => 1,
+            None => 2,
+        };
+        self.stack.push(Value::Bool(code == 0));
+        self.last_exit_code = code;
+        Ok(())
+    }
+}
+
+/// The status `url` answers a GET with, or None if it doesn't
+#[cfg(not(target_arch = "wasm32"))]
+fn http_status(url: &str) -> Option<u16> {
+    match ureq::get(url).timeout(HTTP_TIMEOUT).call() {
+        Ok(response) => Some(response.status()),
+        Err(ureq::Error::Status(code, _)) => Some(code),
+        Err(_) => None,
+    }
+}
+
+/// The WebAssembly build has no network access
+#[cfg(target_arch = "wasm32")]
+fn http_status(_url: &str) -> Option<u16> {
+    None
+}
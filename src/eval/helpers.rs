@@ -2,33 +2,73 @@ use super::{EvalError, Evaluator};
 use crate::ast::{Expr, Value};
 use glob::glob;
 use indexmap::IndexMap;
-use num_bigint::BigUint;
+use num_bigint::BigInt;
 
 /// A popped numeric operand, before promotion (issue #24).
 ///
 /// Promotion rules (documented in docs/reference.md):
 /// - `Int op Int -> Int`, checked; on i64 overflow the result is promoted to
-///   `BigInt` when representable (non-negative), otherwise to a float.
+///   `BigInt`.
 /// - mixed `Int`/`Float` -> `Float`
-/// - `BigInt` operands stay `BigInt` where the result is non-negative,
-///   otherwise fall back to floats.
+/// - `BigInt` operands stay `BigInt`.
 #[derive(Debug, Clone)]
 pub(crate) enum Num {
     Int(i64),
     Float(f64),
-    Big(BigUint),
+    Big(BigInt),
 }
 
 impl Num {
+    /// The number a value holds: numbers as they are, and numeric text,
+    /// where digits too many for an i64 make a `BigInt` rather than a float
+    pub(crate) fn of(value: &Value) -> Option<Num> {
+        match value {
+            Value::Int(i) => Some(Num::Int(*i)),
+            Value::Number(n) => Some(Num::Float(*n)),
+            Value::BigInt(b) => Some(Num::Big(b.clone())),
+            Value::Literal(s) | Value::Output(s) => {
+                let t = s.trim();
+                if let Ok(i) = t.parse::<i64>() {
+                    Some(Num::Int(i))
+                } else if is_integer_text(t) {
+                    t.parse::<BigInt>().ok().map(Num::Big)
+                } else {
+                    t.parse::<f64>().ok().map(Num::Float)
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub(crate) fn to_f64(&self) -> f64 {
         match self {
             Num::Int(i) => *i as f64,
             Num::Float(f) => *f,
-            // Lossy but monotone: good enough for comparisons/float math
+            // Lossy but monotone: good enough for float math
             Num::Big(b) => b.to_string().parse::<f64>().unwrap_or(f64::INFINITY),
         }
     }
 
+    /// The number as a signed BigInt, when it is a whole one (not a float)
+    pub(crate) fn exact(&self) -> Option<BigInt> {
+        match self {
+            Num::Int(i) => Some(BigInt::from(*i)),
+            Num::Big(b) => Some(b.clone()),
+            Num::Float(_) => None,
+        }
+    }
+
+    /// Compare two numbers, exactly unless one is a float (None for NaN)
+    pub(crate) fn compare(&self, other: &Num) -> Option<std::cmp::Ordering> {
+        if let (Num::Int(x), Num::Int(y)) = (self, other) {
+            return Some(x.cmp(y));
+        }
+        match (self.exact(), other.exact()) {
+            (Some(x), Some(y)) => Some(x.cmp(&y)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+
     pub(crate) fn into_value(self) -> Value {
         match self {
             Num::Int(i) => Value::Int(i),
@@ -38,6 +78,127 @@ impl Num {
     }
 }
 
+/// Whether text is a whole number: digits after an optional sign
+pub(crate) fn is_integer_text(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Canonical structural key for a value, used for deep equality and
+/// structured dedup (`eq-deep?`, `unique`, `duplicates`).
+///
+/// Scalars collapse to their argument string (so `2`, `2.0` and `"2"` are
+/// equal, matching `eq?`); Lists compare element-wise; Records and Table
+/// columns compare by key regardless of insertion order.
+pub(crate) fn deep_key(v: &Value) -> String {
+    match v {
+        Value::List(items) => {
+            let parts: Vec<String> = items.iter().map(deep_key).collect();
+            format!("L[{}]", parts.join("\x1f"))
+        }
+        Value::Map(map) => {
+            let mut parts: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}\x1e{}", k, deep_key(v)))
+                .collect();
+            parts.sort();
+            format!("M{{{}}}", parts.join("\x1f"))
+        }
+        Value::Table { columns, rows } => {
+            let mut order: Vec<usize> = (0..columns.len()).collect();
+            order.sort_by(|a, b| columns[*a].cmp(&columns[*b]));
+            let cols: Vec<&str> = order.iter().map(|i| columns[*i].as_str()).collect();
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    let cells: Vec<String> = order
+                        .iter()
+                        .map(|i| row.get(*i).map(deep_key).unwrap_or_else(|| "nil".into()))
+                        .collect();
+                    cells.join("\x1f")
+                })
+                .collect();
+            format!("T<{}>[{}]", cols.join("\x1f"), rows.join("\x1d"))
+        }
+        Value::Block(exprs) => format!("B{:?}", exprs),
+        Value::Nil => "nil".to_string(),
+        Value::Marker => "marker".to_string(),
+        Value::Bytes(data) => format!("b:{}", hex::encode(data)),
+        Value::Error {
+            kind,
+            message,
+            code,
+            ..
+        } => format!("E:{}\x1f{}\x1f{:?}", kind, message, code),
+        other => format!("s:{}", other.as_arg().unwrap_or_default()),
+    }
+}
+
+/// Structural equality: order-insensitive for Record keys (see `deep_key`)
+pub(crate) fn deep_eq(a: &Value, b: &Value) -> bool {
+    deep_key(a) == deep_key(b)
+}
+
+/// Total-ish ordering for sortable values (`compare`).
+///
+/// Numbers (and numeric strings) compare numerically, strings
+/// lexicographically, booleans false < true, and Lists element-wise.
+/// Returns None for values with no natural order (Records, Blocks, ...).
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    use std::cmp::Ordering;
+
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::BigInt(x), Value::BigInt(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Nil, Value::Nil) => Some(Ordering::Equal),
+        (Value::List(xs), Value::List(ys)) => {
+            for (x, y) in xs.iter().zip(ys.iter()) {
+                match compare_values(x, y)? {
+                    Ordering::Equal => continue,
+                    other => return Some(other),
+                }
+            }
+            Some(xs.len().cmp(&ys.len()))
+        }
+        (Value::Bytes(x), Value::Bytes(y)) => Some(x.cmp(y)),
+        _ => {
+            if let (Some(x), Some(y)) = (Num::of(a), Num::of(b)) {
+                return x.compare(&y);
+            }
+            match (a, b) {
+                (
+                    Value::Literal(_) | Value::Output(_) | Value::Int(_) | Value::Number(_),
+                    Value::Literal(_) | Value::Output(_) | Value::Int(_) | Value::Number(_),
+                ) => Some(
+                    a.as_arg()
+                        .unwrap_or_default()
+                        .cmp(&b.as_arg().unwrap_or_default()),
+                ),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Modification time in seconds since the epoch, or 0 if unknown
+pub(crate) fn mtime(meta: &std::fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Resolve a possibly-negative index against a length (-1 is the last item)
+pub(crate) fn resolve_index(n: i64, len: usize) -> Option<usize> {
+    let idx = if n < 0 { len as i64 + n } else { n };
+    if idx < 0 || idx as usize >= len {
+        None
+    } else {
+        Some(idx as usize)
+    }
+}
+
 impl Evaluator {
     /// Expand tilde (~) to home directory
     pub(crate) fn expand_tilde(&self, path: &str) -> String {
@@ -51,16 +212,45 @@ impl Evaluator {
     }
 
     /// Interpolate variables in a double-quoted string
-    /// Supports $VAR and ${VAR} syntax
-    pub(crate) fn interpolate_string(&self, s: &str) -> String {
+    /// Supports $VAR and ${VAR} syntax, ${.N} for stack position N
+    /// (0 = top, not consumed), and ${= ...} for a nested postfix expression.
+    /// `$(` is left as it is, for strings meant for sh
+    pub(crate) fn interpolate_string(&mut self, s: &str) -> Result<String, EvalError> {
         let mut result = String::with_capacity(s.len());
         let mut chars = s.chars().peekable();
 
         while let Some(c) = chars.next() {
             if c == '$' {
                 if chars.peek() == Some(&'{') {
-                    // ${VAR} syntax
                     chars.next(); // consume '{'
+                    if chars.peek() == Some(&'=') {
+                        // ${= ...} nested postfix expression
+                        chars.next(); // consume '='
+                        let mut code = String::new();
+                        let mut depth = 1;
+                        for ch in chars.by_ref() {
+                            match ch {
+                                '{' => depth += 1,
+                                '}' => {
+                                    depth -= 1;
+                                    if depth == 0 {
+                                        break;
+                                    }
+                                }
+                                _ => {}
+                            }
+                            code.push(ch);
+                        }
+                        if depth != 0 {
+                            return Err(EvalError::ExecError(format!(
+                                "interpolation: unterminated ${{={}",
+                                code
+                            )));
+                        }
+                        result.push_str(&self.eval_interpolated_expr(&code)?);
+                        continue;
+                    }
+                    // ${VAR} syntax
                     let mut var_name = String::new();
                     while let Some(&ch) = chars.peek() {
                         if ch == '}' {
@@ -69,7 +259,11 @@ impl Evaluator {
                         }
                         var_name.push(chars.next().unwrap());
                     }
-                    if let Some(val) = self.lookup_var_as_string(&var_name) {
+                    if let Some(pos) = var_name.strip_prefix('.') {
+                        if let Some(val) = self.stack_value_as_string(pos) {
+                            result.push_str(&val);
+                        }
+                    } else if let Some(val) = self.lookup_var_as_string(&var_name) {
                         result.push_str(&val);
                     }
                 } else if chars
@@ -115,7 +309,102 @@ impl Evaluator {
             }
         }
 
-        result
+        Ok(result)
+    }
+
+    /// Expand `$VAR` and `${VAR}` inside an unquoted word (`$HOME/bin`,
+    /// `v$MAJOR.$MINOR`). `\$` is a literal `$`, as is a `$` that doesn't
+    /// start a variable name.
+    pub(crate) fn expand_word_vars(&self, word: &str) -> Result<String, EvalError> {
+        let is_name = |c: char| c.is_alphanumeric() || c == '_';
+        let mut result = String::with_capacity(word.len());
+        let mut rest = word;
+        while let Some(pos) = rest.find(['$', '\\']) {
+            result.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+            if let Some(after) = tail.strip_prefix("\\$") {
+                result.push('$');
+                rest = after;
+                continue;
+            }
+            if let Some(after) = tail.strip_prefix('\\') {
+                result.push('\\');
+                rest = after;
+                continue;
+            }
+            let body = &tail[1..];
+            let (name, after) = match body.strip_prefix('{') {
+                Some(inner) => match inner.find('}') {
+                    Some(end) if inner[..end].chars().all(is_name) => {
+                        (&inner[..end], &inner[end + 1..])
+                    }
+                    _ => ("", body),
+                },
+                None => {
+                    let len = body.find(|c: char| !is_name(c)).unwrap_or(body.len());
+                    (&body[..len], &body[len..])
+                }
+            };
+            if name.is_empty() {
+                result.push('$');
+                rest = body;
+                continue;
+            }
+            match self.lookup_var_as_string(name) {
+                Some(value) => result.push_str(&value),
+                None if self.strict_mode => {
+                    return Err(EvalError::ExecError(format!(
+                        "${} is not set (strict mode)",
+                        name
+                    )))
+                }
+                None => {}
+            }
+            rest = after;
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    /// Render the stack value at position `pos` (0 = top) for interpolation
+    fn stack_value_as_string(&self, pos: &str) -> Option<String> {
+        let n: usize = pos.parse().ok()?;
+        let idx = self.stack.len().checked_sub(n + 1)?;
+        self.stack[idx].as_arg()
+    }
+
+    /// Evaluate `${= ...}` code on an isolated stack with output captured,
+    /// joining the results with spaces
+    fn eval_interpolated_expr(&mut self, code: &str) -> Result<String, EvalError> {
+        let tokens = crate::lex(code)
+            .map_err(|e| EvalError::ExecError(format!("interpolation: parse error: {}", e)))?;
+        if tokens.is_empty() {
+            return Ok(String::new());
+        }
+        let program = crate::parse(tokens)
+            .map_err(|e| EvalError::ExecError(format!("interpolation: parse error: {}", e)))?;
+
+        let saved_stack = std::mem::take(&mut self.stack);
+        let old_capture = self.capture_mode;
+        let mut outcome = Ok(());
+        for expr in &program.expressions {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
+            }
+        }
+        self.capture_mode = old_capture;
+        let results = std::mem::replace(&mut self.stack, saved_stack);
+        outcome?;
+
+        Ok(results
+            .iter()
+            .filter_map(|v| v.as_arg())
+            .map(|s| s.trim_end_matches('\n').to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "))
     }
 
     /// Expand glob patterns in a string
@@ -196,34 +485,9 @@ impl Evaluator {
             .stack
             .pop()
             .ok_or_else(|| EvalError::StackUnderflow(format!("{} requires a number", op)))?;
-        match &value {
-            Value::Int(i) => Ok(Num::Int(*i)),
-            Value::Number(n) => Ok(Num::Float(*n)),
-            Value::BigInt(b) => Ok(Num::Big(b.clone())),
-            Value::Literal(s) | Value::Output(s) => {
-                let t = s.trim();
-                if let Ok(i) = t.parse::<i64>() {
-                    Ok(Num::Int(i))
-                } else if let Ok(f) = t.parse::<f64>() {
-                    Ok(Num::Float(f))
-                } else if !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit()) {
-                    // Huge positive integer literal beyond i64
-                    Ok(Num::Big(t.parse::<BigUint>().map_err(|_| {
-                        EvalError::TypeError {
-                            expected: format!("number ({})", op),
-                            got: value.type_name().to_string(),
-                        }
-                    })?))
-                } else {
-                    let err = EvalError::TypeError {
-                        expected: format!("number ({})", op),
-                        got: value.type_name().to_string(),
-                    };
-                    self.stack.push(value);
-                    Err(err)
-                }
-            }
-            _ => {
+        match Num::of(&value) {
+            Some(n) => Ok(n),
+            None => {
                 let err = EvalError::TypeError {
                     expected: format!("number ({})", op),
                     got: value.type_name().to_string(),
@@ -244,7 +508,7 @@ impl Evaluator {
     }
 
     /// Helper to pop a BigInt from stack
-    pub(crate) fn pop_bigint(&mut self, op: &str) -> Result<BigUint, EvalError> {
+    pub(crate) fn pop_bigint(&mut self, op: &str) -> Result<BigInt, EvalError> {
         let value = self
             .stack
             .pop()
@@ -258,6 +522,30 @@ impl Evaluator {
         }
     }
 
+    /// Helper: Pop a List from the stack (type error for anything else)
+    pub(crate) fn pop_list(&mut self, op: &str) -> Result<Vec<Value>, EvalError> {
+        match self.stack.pop() {
+            Some(Value::List(items)) => Ok(items),
+            Some(other) => {
+                let err = EvalError::TypeError {
+                    expected: format!("list ({})", op),
+                    got: other.type_name().to_string(),
+                };
+                self.stack.push(other);
+                Err(err)
+            }
+            None => Err(EvalError::StackUnderflow(format!("{} requires a list", op))),
+        }
+    }
+
+    /// Helper: Pop an integer (floats truncate toward zero)
+    pub(crate) fn pop_int(&mut self, op: &str) -> Result<i64, EvalError> {
+        Ok(match self.pop_numeric(op)? {
+            Num::Int(i) => i,
+            other => other.to_f64() as i64,
+        })
+    }
+
     /// Helper: Pop a numeric list from the stack
     pub(crate) fn pop_number_list(&mut self) -> Result<Vec<f64>, EvalError> {
         let val = self
@@ -299,6 +587,7 @@ impl Evaluator {
 
         for expr in exprs {
             match expr {
+                Expr::Literal(s) if s.contains('$') => parts.push(self.expand_word_vars(s)?),
                 Expr::Literal(s) => parts.push(s.clone()),
                 Expr::Quoted { content, .. } => parts.push(content.clone()),
                 Expr::Variable(s) => {
@@ -306,7 +595,7 @@ impl Evaluator {
                         .trim_start_matches('$')
                         .trim_start_matches('{')
                         .trim_end_matches('}');
-                    if let Ok(val) = std::env::var(var_name) {
+                    if let Ok(val) = super::env::var(var_name) {
                         parts.push(val);
                     }
                 }
@@ -448,6 +737,7 @@ impl Evaluator {
                 Expr::Variable(s) => s.clone(),
                 Expr::Block(inner) => format!("#[{}]", self.exprs_to_string(inner)),
                 Expr::ArrayLiteral(inner) => format!("[{}]", self.exprs_to_string(inner)),
+                Expr::RecordLiteral(inner) => format!("{{ {} }}", self.exprs_to_string(inner)),
                 _ => format!("{:?}", e),
             })
             .collect::<Vec<_>>()
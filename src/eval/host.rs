@@ -0,0 +1,56 @@
+//! Builtins supplied by the program embedding hsab
+//!
+//! An embedder can give scripts words of its own without writing a plugin:
+//! register a function under a name, and the word calls it with the
+//! evaluator, to pop its arguments and push its results.
+//!
+//! ```rust
+//! use hsab::{lex, parse, EvalError, Evaluator, Value};
+//!
+//! let mut eval = Evaluator::new();
+//! eval.register_builtin("shout", |eval| {
+//!     let text = eval.pop_value().and_then(|v| v.as_arg()).ok_or_else(|| {
+//!         EvalError::StackUnderflow("shout requires a string".into())
+//!     })?;
+//!     eval.push_value(Value::Literal(text.to_uppercase()));
+//!     Ok(())
+//! });
+//! let result = eval.eval(&parse(lex("hello shout").unwrap()).unwrap()).unwrap();
+//! assert_eq!(result.output, "HELLO");
+//! ```
+//!
+//! Definitions and hsab's own builtins take precedence over registered
+//! words, and the exec policy's `deny` list blocks them like any builtin.
+
+use std::sync::Arc;
+
+use super::{EvalError, Evaluator};
+
+/// A function registered with [`Evaluator::register_builtin`]
+pub type HostBuiltin = Arc<dyn Fn(&mut Evaluator) -> Result<(), EvalError> + Send + Sync>;
+
+impl Evaluator {
+    /// Make `name` a word that calls `f`, replacing any earlier function
+    /// registered under that name
+    pub fn register_builtin<F>(&mut self, name: impl Into<String>, f: F)
+    where
+        F: Fn(&mut Evaluator) -> Result<(), EvalError> + Send + Sync + 'static,
+    {
+        self.host_builtins.insert(name.into(), Arc::new(f));
+    }
+
+    /// Remove a registered word, returning whether there was one
+    pub fn unregister_builtin(&mut self, name: &str) -> bool {
+        self.host_builtins.remove(name).is_some()
+    }
+
+    /// Run the registered word `name`, if there is one (returns true if
+    /// handled)
+    pub(crate) fn try_host_builtin(&mut self, name: &str) -> Result<bool, EvalError> {
+        let Some(f) = self.host_builtins.get(name).cloned() else {
+            return Ok(false);
+        };
+        f(self)?;
+        Ok(true)
+    }
+}
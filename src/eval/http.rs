@@ -4,6 +4,8 @@
 //! - fetch: Make HTTP request, return body (auto-parse JSON)
 //! - fetch-status: Return status code as number
 //! - fetch-headers: Return response headers as Map
+//!
+//! Multipart uploads (`http-post-multipart`) are in `multipart.rs`.
 
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
@@ -102,7 +104,13 @@ impl Evaluator {
 
         // Make the request
         let response = self.do_http_request(&method, &url, body.as_deref(), headers.as_ref())?;
+        self.push_http_response(response);
+        Ok(())
+    }
 
+    /// Push a response's body (parsed if it's JSON) and set the exit code
+    /// from its status
+    pub(crate) fn push_http_response(&mut self, response: HttpResponse) {
         // Auto-parse JSON if content-type indicates it
         let content_type = response.content_type.unwrap_or_default();
         let result = if content_type.contains("application/json") {
@@ -117,7 +125,6 @@ impl Evaluator {
 
         self.stack.push(result);
         self.last_exit_code = if response.status >= 400 { 1 } else { 0 };
-        Ok(())
     }
 
     /// fetch-status: URL [method] fetch-status -> status_code
@@ -196,7 +203,8 @@ impl Evaluator {
     }
 
     /// Internal helper to make HTTP requests using ureq
-    fn do_http_request(
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn do_http_request(
         &self,
         method: &str,
         url: &str,
@@ -235,62 +243,98 @@ impl Evaluator {
             request.call()
         };
 
-        match response {
-            Ok(resp) => {
-                let status = resp.status();
-                let content_type = Some(resp.content_type().to_string());
+        into_http_response(response)
+    }
 
-                // Collect headers
-                let mut headers_map = HashMap::new();
-                for name in resp.headers_names() {
-                    if let Some(value) = resp.header(&name) {
-                        headers_map.insert(name, value.to_string());
-                    }
-                }
+    /// Internal helper to send a body of any type, such as a multipart form
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn do_http_send(
+        &self,
+        method: &str,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+        headers: &HashMap<String, String>,
+    ) -> Result<HttpResponse, EvalError> {
+        let mut request = ureq::request(method, url).set("Content-Type", content_type);
+        for (k, v) in headers {
+            request = request.set(k, v);
+        }
+        into_http_response(request.send_bytes(body))
+    }
+
+    /// The WebAssembly build has no network access
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn do_http_request(
+        &self,
+        _method: &str,
+        _url: &str,
+        _body: Option<&str>,
+        _headers: Option<&HashMap<String, String>>,
+    ) -> Result<HttpResponse, EvalError> {
+        Err(EvalError::ExecError(
+            "HTTP request failed: not available in this build".into(),
+        ))
+    }
+
+    /// The WebAssembly build has no network access
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn do_http_send(
+        &self,
+        _method: &str,
+        _url: &str,
+        _content_type: &str,
+        _body: &[u8],
+        _headers: &HashMap<String, String>,
+    ) -> Result<HttpResponse, EvalError> {
+        Err(EvalError::ExecError(
+            "HTTP request failed: not available in this build".into(),
+        ))
+    }
+}
 
-                // Read body
-                let body = resp.into_string().unwrap_or_default();
+/// A response, or an error status, as an HttpResponse
+#[cfg(not(target_arch = "wasm32"))]
+fn into_http_response(
+    response: Result<ureq::Response, ureq::Error>,
+) -> Result<HttpResponse, EvalError> {
+    let (status, resp) = match response {
+        Ok(resp) => (resp.status(), resp),
+        // HTTP error (4xx/5xx)
+        Err(ureq::Error::Status(code, resp)) => (code, resp),
+        Err(e) => return Err(EvalError::ExecError(format!("HTTP request failed: {}", e))),
+    };
+    let content_type = Some(resp.content_type().to_string());
 
-                Ok(HttpResponse {
-                    status,
-                    content_type,
-                    headers: headers_map,
-                    body,
-                })
-            }
-            Err(ureq::Error::Status(code, resp)) => {
-                // HTTP error (4xx/5xx)
-                let content_type = Some(resp.content_type().to_string());
-                let mut headers_map = HashMap::new();
-                for name in resp.headers_names() {
-                    if let Some(value) = resp.header(&name) {
-                        headers_map.insert(name, value.to_string());
-                    }
-                }
-                let body = resp.into_string().unwrap_or_default();
-
-                Ok(HttpResponse {
-                    status: code,
-                    content_type,
-                    headers: headers_map,
-                    body,
-                })
-            }
-            Err(e) => Err(EvalError::ExecError(format!("HTTP request failed: {}", e))),
+    // Collect headers
+    let mut headers = HashMap::new();
+    for name in resp.headers_names() {
+        if let Some(value) = resp.header(&name) {
+            headers.insert(name, value.to_string());
         }
     }
+
+    // Read body
+    let body = resp.into_string().unwrap_or_default();
+
+    Ok(HttpResponse {
+        status,
+        content_type,
+        headers,
+        body,
+    })
 }
 
 /// Response from an HTTP request
-struct HttpResponse {
-    status: u16,
-    content_type: Option<String>,
-    headers: HashMap<String, String>,
-    body: String,
+pub(crate) struct HttpResponse {
+    pub(crate) status: u16,
+    pub(crate) content_type: Option<String>,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: String,
 }
 
 /// Check if a string looks like an HTTP method
-fn is_http_method(s: &str) -> bool {
+pub(crate) fn is_http_method(s: &str) -> bool {
     matches!(
         s.to_uppercase().as_str(),
         "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS"
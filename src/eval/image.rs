@@ -19,7 +19,7 @@ impl Evaluator {
     pub(crate) fn load_image_from_path(&mut self, path: &str) -> Result<(), EvalError> {
         // Expand tilde if present
         let expanded_path = if path.starts_with('~') {
-            if let Ok(home) = std::env::var("HOME") {
+            if let Ok(home) = super::env::var("HOME") {
                 path.replacen('~', &home, 1)
             } else {
                 path.to_string()
@@ -0,0 +1,112 @@
+//! Resource limits for untrusted scripts, usually configured from `hsab.toml`
+//!
+//! Each call to `Evaluator::eval` gets a fresh budget: at most `steps`
+//! expressions and `seconds` of wall-clock time. Running out stops the eval
+//! before its next expression with `EvalError::ResourceExhausted`, which
+//! `try` can't catch. When the time runs out, foreground processes the eval
+//! is waiting for are killed as well, so a long `sleep` doesn't outlast it.
+//!
+//! ```toml
+//! [limits]
+//! steps = 100000
+//! seconds = 2.5
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{EvalError, Evaluator};
+
+/// How much work one eval may do
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// Most expressions evaluated, counting those inside definitions and
+    /// blocks
+    pub steps: Option<u64>,
+    /// Most wall-clock seconds
+    pub seconds: Option<f64>,
+}
+
+/// The budget left for the running eval
+pub(crate) struct Fuel {
+    steps_left: Option<u64>,
+    expired: Arc<AtomicBool>,
+    /// Dropping this stops the watchdog
+    _watchdog: Option<mpsc::Sender<()>>,
+}
+
+impl Evaluator {
+    /// Replace the resource limits, starting with the next eval
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// The current resource limits
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Fill the budget for an eval. With a time limit, a watchdog thread
+    /// marks it expired at the deadline and kills foreground processes.
+    pub(crate) fn refuel(&mut self) {
+        if self.limits == Limits::default() {
+            self.fuel = None;
+            return;
+        }
+        let expired = Arc::new(AtomicBool::new(false));
+        let watchdog = self.limits.seconds.map(|seconds| {
+            let (stop, stopped) = mpsc::channel::<()>();
+            let expired = expired.clone();
+            let cancel = self.cancel.clone();
+            let timeout = Duration::try_from_secs_f64(seconds.max(0.0)).unwrap_or(Duration::MAX);
+            std::thread::spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                    expired.store(true, Ordering::SeqCst);
+                    cancel.kill_foreground();
+                }
+            });
+            stop
+        });
+        self.fuel = Some(Fuel {
+            steps_left: self.limits.steps,
+            expired,
+            _watchdog: watchdog,
+        });
+    }
+
+    /// Fail if the time limit has run out, for waits that don't take steps
+    pub(crate) fn check_deadline(&self) -> Result<(), EvalError> {
+        match &self.fuel {
+            Some(fuel) if fuel.expired.load(Ordering::SeqCst) => {
+                Err(EvalError::ResourceExhausted(format!(
+                    "time limit of {}s reached",
+                    self.limits.seconds.unwrap_or_default()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Spend one step, failing if the budget is used up
+    pub(crate) fn burn_fuel(&mut self) -> Result<(), EvalError> {
+        self.check_deadline()?;
+        let Some(fuel) = &mut self.fuel else {
+            return Ok(());
+        };
+        match &mut fuel.steps_left {
+            Some(0) => Err(EvalError::ResourceExhausted(format!(
+                "step limit of {} reached",
+                self.limits.steps.unwrap_or_default()
+            ))),
+            Some(left) => {
+                *left -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
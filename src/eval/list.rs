@@ -1,6 +1,8 @@
+use super::helpers::{compare_values, resolve_index, Num};
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
 use indexmap::IndexMap;
+use std::cmp::Ordering;
 
 impl Evaluator {
     /// Spread: split a value into separate stack items
@@ -451,4 +453,428 @@ impl Evaluator {
         self.list_collect()?;
         Ok(())
     }
+
+    // ========================================
+    // List-native operations (no marker/spread round-trip)
+    // ========================================
+
+    /// list-push: [list] item list-push -> [list + item]
+    pub(crate) fn builtin_list_push(&mut self) -> Result<(), EvalError> {
+        let item = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow("list-push requires an item".into()))?;
+        let mut items = self.pop_list("list-push")?;
+        items.push(item);
+        self.stack.push(Value::List(items));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-pop: [list] list-pop -> [init] last
+    /// An empty list pops Nil
+    pub(crate) fn builtin_list_pop(&mut self) -> Result<(), EvalError> {
+        let mut items = self.pop_list("list-pop")?;
+        let last = items.pop().unwrap_or(Value::Nil);
+        self.stack.push(Value::List(items));
+        self.stack.push(last);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-get: [list] N list-get -> item (negative N counts from the end)
+    /// Out-of-range indices push Nil
+    pub(crate) fn builtin_list_get(&mut self) -> Result<(), EvalError> {
+        let n = self.pop_int("list-get")?;
+        let items = self.pop_list("list-get")?;
+        let item = resolve_index(n, items.len())
+            .and_then(|i| items.into_iter().nth(i))
+            .unwrap_or(Value::Nil);
+        self.stack.push(item);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-len: [list] list-len -> count
+    pub(crate) fn builtin_list_len(&mut self) -> Result<(), EvalError> {
+        let items = self.pop_list("list-len")?;
+        self.stack.push(Value::Int(items.len() as i64));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-slice: [list] START LEN list-slice -> [sublist]
+    /// Mirrors string `slice` (start + length); negative START counts from the end
+    pub(crate) fn builtin_list_slice(&mut self) -> Result<(), EvalError> {
+        let len = self.pop_int("list-slice")?.max(0) as usize;
+        let start = self.pop_int("list-slice")?;
+        let items = self.pop_list("list-slice")?;
+        let start = if start < 0 {
+            (items.len() as i64 + start).max(0) as usize
+        } else {
+            start as usize
+        };
+        let slice: Vec<Value> = items.into_iter().skip(start).take(len).collect();
+        self.stack.push(Value::List(slice));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-map: [list] #[block] list-map -> [results]
+    /// Each item runs on an isolated stack; every value it leaves is kept
+    pub(crate) fn builtin_list_map(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+        let items = self.pop_list("list-map")?;
+
+        let saved_stack = std::mem::take(&mut self.stack);
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            self.stack.push(item);
+            if let Err(e) = self.eval_exprs(&block) {
+                self.stack = saved_stack;
+                return Err(e);
+            }
+            results.extend(self.stack.drain(..).filter(|v| !v.is_nil()));
+        }
+        self.stack = saved_stack;
+
+        self.stack.push(Value::List(results));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// list-filter: [list] #[predicate] list-filter -> [kept]
+    /// Keeps items whose predicate exits 0 (or leaves `true` on top)
+    pub(crate) fn builtin_list_filter(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+        let items = self.pop_list("list-filter")?;
+
+        let saved_stack = std::mem::take(&mut self.stack);
+        let mut kept = Vec::new();
+        for item in items {
+            self.stack.push(item.clone());
+            if let Err(e) = self.eval_exprs(&block) {
+                self.stack = saved_stack;
+                return Err(e);
+            }
+            let pass = match self.stack.last() {
+                Some(Value::Bool(b)) => *b,
+                _ => self.last_exit_code == 0,
+            };
+            self.stack.clear();
+            if pass {
+                kept.push(item);
+            }
+        }
+        self.stack = saved_stack;
+
+        self.stack.push(Value::List(kept));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// enumerate: [list] enumerate -> [[0, a], [1, b], ...]
+    pub(crate) fn builtin_enumerate(&mut self) -> Result<(), EvalError> {
+        let items = self.pop_list("enumerate")?;
+        let pairs: Vec<Value> = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| Value::List(vec![Value::Int(i as i64), v]))
+            .collect();
+        self.stack.push(Value::List(pairs));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Whether `seq` makes a List: anything but a flag on top. `"-w" seq`
+    /// and the like are left to the seq command.
+    pub(crate) fn seq_is_native(&self) -> bool {
+        !matches!(
+            self.stack.last(),
+            Some(Value::Literal(s) | Value::Output(s))
+                if s.starts_with('-') && s.trim().parse::<f64>().is_err()
+        )
+    }
+
+    /// seq: [last [step]] first seq -> [first, ..., last]
+    /// Takes one to three numbers from the top of the stack, in the order
+    /// coreutils `seq` takes its arguments (`5 1 seq` is `seq 1 5`),
+    /// counting from 1 by 1 unless told otherwise. All integers give Ints,
+    /// otherwise Numbers. Ranges over SEQ_LIMIT items are an error.
+    pub(crate) fn builtin_seq(&mut self) -> Result<(), EvalError> {
+        let mut args = vec![self.pop_numeric("seq")?];
+        while args.len() < 3 && self.stack.last().is_some_and(is_numeric) {
+            args.push(self.pop_numeric("seq")?);
+        }
+        let (first, step, last) = match args.as_slice() {
+            [last] => (&Num::Int(1), &Num::Int(1), last),
+            [first, last] => (first, &Num::Int(1), last),
+            [first, step, last] => (first, step, last),
+            _ => unreachable!(),
+        };
+
+        let items = match (first, step, last) {
+            (Num::Int(first), Num::Int(step), Num::Int(last)) => {
+                if *step == 0 {
+                    return Err(EvalError::ExecError("seq: step can't be 0".into()));
+                }
+                let span = *last as i128 - *first as i128;
+                let count = if span == 0 || (span > 0) == (*step > 0) {
+                    span / *step as i128 + 1
+                } else {
+                    0
+                };
+                check_seq_count(count as f64)?;
+                (0..count)
+                    .map(|i| Value::Int((*first as i128 + i * *step as i128) as i64))
+                    .collect()
+            }
+            _ => {
+                let (first, step, last) = (first.to_f64(), step.to_f64(), last.to_f64());
+                if step == 0.0 || !step.is_finite() {
+                    return Err(EvalError::ExecError(
+                        "seq: step must be a non-zero number".into(),
+                    ));
+                }
+                // Count the items up front so rounding can't add or drop one
+                let count = ((last - first) / step + 1e-9).floor() + 1.0;
+                if count.is_nan() || count < 1.0 {
+                    Vec::new()
+                } else {
+                    check_seq_count(count)?;
+                    (0..count as u64)
+                        .map(|i| Value::Number(first + i as f64 * step))
+                        .collect()
+                }
+            }
+        };
+        self.stack.push(Value::List(items));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Whether `sort` sorts values on the stack: a List, or spread items,
+    /// under optional flags. Anything else (`"data.txt" sort`) is left to
+    /// the sort command.
+    pub(crate) fn sort_is_native(&self) -> bool {
+        let (_, flag_words) = SortOptions::from_top(self.stack.iter().rev());
+        let mut below = self.stack.iter().rev().skip(flag_words).peekable();
+        match below.peek() {
+            Some(Value::List(_)) => true,
+            _ => {
+                below
+                    .take_while(|v| !v.is_marker())
+                    .all(|v| !matches!(v, Value::Block(_) | Value::Nil))
+                    && self.stack.iter().any(Value::is_marker)
+            }
+        }
+    }
+
+    /// sort: [list] ["-nrfV"] sort -> [sorted], or spread items sorted in place
+    ///
+    /// Numbers sort numerically and strings lexicographically; the flags are
+    /// coreutils sort's: `-n` numeric (leading number of each string, 0 if
+    /// none), `-r` reverse, `-f` ignore case, `-V` natural order
+    /// ("file2" < "file10"), combined (`-nr`) or as separate words
+    /// (`-n -r`). The sort is stable.
+    pub(crate) fn builtin_sort(&mut self) -> Result<(), EvalError> {
+        let (options, flag_words) = SortOptions::from_top(self.stack.iter().rev());
+        for _ in 0..flag_words {
+            self.stack.pop();
+        }
+
+        if let Some(Value::List(items)) = self.stack.last() {
+            check_sortable(items)?;
+            let Some(Value::List(mut items)) = self.stack.pop() else {
+                unreachable!()
+            };
+            items.sort_by(|a, b| options.compare(a, b));
+            self.stack.push(Value::List(items));
+        } else {
+            let start = self
+                .stack
+                .iter()
+                .rposition(Value::is_marker)
+                .map_or(0, |i| i + 1);
+            check_sortable(&self.stack[start..])?;
+            self.stack
+                .tail_mut(start)
+                .sort_by(|a, b| options.compare(a, b));
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
+
+/// The most items `seq` will build into a List
+const SEQ_LIMIT: f64 = 10_000_000.0;
+
+fn check_seq_count(count: f64) -> Result<(), EvalError> {
+    if count > SEQ_LIMIT {
+        return Err(EvalError::ExecError(format!(
+            "seq: {} items is more than {} allows; use the seq command to stream them",
+            count, SEQ_LIMIT
+        )));
+    }
+    Ok(())
+}
+
+/// Whether `seq` should take `value` as one of its arguments
+fn is_numeric(value: &Value) -> bool {
+    match value {
+        Value::Int(_) | Value::Number(_) => true,
+        Value::Literal(s) | Value::Output(s) => s.trim().parse::<f64>().is_ok(),
+        _ => false,
+    }
+}
+
+/// How `sort` orders values, from its flags
+#[derive(Debug, Default, Clone, Copy)]
+struct SortOptions {
+    numeric: bool,
+    reverse: bool,
+    ignore_case: bool,
+    natural: bool,
+}
+
+impl SortOptions {
+    /// The flags in the words on top of the stack (given top first), and
+    /// how many words they are
+    fn from_top<'a>(words: impl Iterator<Item = &'a Value>) -> (SortOptions, usize) {
+        let mut options = SortOptions::default();
+        let mut count = 0;
+        for word in words {
+            if !options.add(word) {
+                break;
+            }
+            count += 1;
+        }
+        (options, count)
+    }
+
+    /// Add flags like "-n" or "-rV"; false (adding nothing) for anything else
+    fn add(&mut self, value: &Value) -> bool {
+        let Value::Literal(s) = value else {
+            return false;
+        };
+        let Some(flags) = s.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            return false;
+        };
+        if !flags.chars().all(|flag| "nrfV".contains(flag)) {
+            return false;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'n' => self.numeric = true,
+                'r' => self.reverse = true,
+                'f' => self.ignore_case = true,
+                _ => self.natural = true,
+            }
+        }
+        true
+    }
+
+    fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        let order = if self.numeric {
+            leading_number(a).total_cmp(&leading_number(b))
+        } else if self.natural {
+            natural_cmp(&self.text(a), &self.text(b))
+        } else if self.ignore_case {
+            compare_values(&self.folded(a), &self.folded(b)).unwrap_or(Ordering::Equal)
+        } else {
+            compare_values(a, b).unwrap_or(Ordering::Equal)
+        };
+        if self.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    }
+
+    fn text(&self, value: &Value) -> String {
+        let text = value.as_arg().unwrap_or_default();
+        if self.ignore_case {
+            text.to_lowercase()
+        } else {
+            text
+        }
+    }
+
+    fn folded(&self, value: &Value) -> Value {
+        match value {
+            Value::Literal(s) | Value::Output(s) => Value::Literal(s.to_lowercase()),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Records, tables, and blocks have no order of their own
+fn check_sortable(items: &[Value]) -> Result<(), EvalError> {
+    match items
+        .iter()
+        .find(|v| matches!(v, Value::Map(_) | Value::Table { .. } | Value::Block(_)))
+    {
+        Some(item) => Err(EvalError::TypeError {
+            expected: "sortable values (sort; use sort-by for records)".into(),
+            got: item.type_name().to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The number a value starts with, as `sort -n` reads it: 0 if none
+fn leading_number(value: &Value) -> f64 {
+    match value {
+        Value::Int(i) => *i as f64,
+        Value::Number(n) => *n,
+        other => {
+            let text = other.as_arg().unwrap_or_default();
+            let text = text.trim_start();
+            let mut end = 0;
+            let mut seen_dot = false;
+            for (i, c) in text.char_indices() {
+                match c {
+                    '-' | '+' if i == 0 => {}
+                    '.' if !seen_dot => seen_dot = true,
+                    '0'..='9' => {}
+                    _ => break,
+                }
+                end = i + c.len_utf8();
+            }
+            text[..end].parse().unwrap_or(0.0)
+        }
+    }
+}
+
+/// Natural order: runs of digits compare as numbers, so "file2" < "file10"
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut run = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        run.push(c);
+                    }
+                    run.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (digits(&mut a), digits(&mut b));
+                match x.len().cmp(&y.len()).then_with(|| x.cmp(&y)) {
+                    Ordering::Equal => continue,
+                    other => return other,
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
 }
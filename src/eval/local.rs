@@ -15,6 +15,7 @@ impl Evaluator {
 
         // Pop the variable name (must be a string)
         let name = self.pop_string()?;
+        self.check_env_write(&name)?;
 
         // Pop the value (preserve its type)
         let value = self
@@ -43,7 +44,7 @@ impl Evaluator {
             let current_scope = self.local_scopes.last_mut().unwrap();
             current_scope
                 .entry(name)
-                .or_insert_with_key(|name| std::env::var(name).ok());
+                .or_insert_with_key(|name| super::env::var(name).ok());
         } else {
             // Primitive value - use env vars for shell compatibility
             let string_value = match &value {
@@ -57,9 +58,9 @@ impl Evaluator {
 
             let current_scope = self.local_scopes.last_mut().unwrap();
             if !current_scope.contains_key(&name) {
-                current_scope.insert(name.clone(), std::env::var(&name).ok());
+                current_scope.insert(name.clone(), super::env::var(&name).ok());
             }
-            std::env::set_var(&name, string_value);
+            super::env::set_var(&name, string_value);
         }
 
         self.last_exit_code = 0;
@@ -0,0 +1,219 @@
+//! Leveled logging for scripts
+//!
+//! `"deploying" log-info` writes a timestamped line to stderr:
+//!
+//! ```text
+//! 2026-10-15T09:30:00.120Z INFO  deploying
+//! ```
+//!
+//! A record after the message adds fields (`"done" { version "1.2" }
+//! log-info`). Messages below the level are dropped; the level is `info`
+//! unless `HSAB_LOG` or `log-level` says otherwise. With
+//! `HSAB_LOG_FORMAT=json` or `"json" log-format`, each message is one JSON
+//! object per line instead.
+//!
+//! Trace output (`--trace`, and the diffs and watches of `.debug`) goes
+//! through the same sink: as before in the text format, and as `trace`
+//! records in JSON, so one stream carries both.
+
+use indexmap::IndexMap;
+use serde_json::{json, Value as JsonValue};
+
+use super::{EvalError, Evaluator};
+use crate::ast::{value_to_json, Value};
+
+/// How severe a message is, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    /// Above every message: nothing is written
+    Off,
+}
+
+impl LogLevel {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.trim().to_ascii_lowercase().as_str() {
+            "trace" => LogLevel::Trace,
+            "debug" => LogLevel::Debug,
+            "info" => LogLevel::Info,
+            "warn" | "warning" => LogLevel::Warn,
+            "error" => LogLevel::Error,
+            "off" | "none" => LogLevel::Off,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Off => "off",
+        }
+    }
+
+    /// The level's color in the text format
+    fn color(self) -> &'static str {
+        match self {
+            LogLevel::Trace | LogLevel::Debug => "\x1b[90m",
+            LogLevel::Info => "\x1b[36m",
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Error | LogLevel::Off => "\x1b[31m",
+        }
+    }
+
+    /// The level `HSAB_LOG` sets, else `info`
+    pub(crate) fn from_env() -> Self {
+        super::env::var("HSAB_LOG")
+            .ok()
+            .and_then(|name| Self::parse(&name))
+            .unwrap_or(LogLevel::Info)
+    }
+}
+
+/// How messages are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// The format `HSAB_LOG_FORMAT` sets, else text
+    pub(crate) fn from_env() -> Self {
+        match super::env::var("HSAB_LOG_FORMAT").as_deref() {
+            Ok("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+fn timestamp() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// A field's value in the text format, quoted if it has spaces
+fn field_text(value: &Value) -> String {
+    let text = value.as_arg().unwrap_or_else(|| "nil".to_string());
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        format!("{:?}", text)
+    } else {
+        text
+    }
+}
+
+impl Evaluator {
+    /// log-debug, log-info, log-warn, log-error: "message" [{fields}] log-info
+    /// Write a message to stderr if its level is at or above `log-level`
+    pub(crate) fn builtin_log(&mut self, level: LogLevel) -> Result<(), EvalError> {
+        let fields = match self.stack.last() {
+            Some(Value::Map(_)) if self.stack.len() > 1 => match self.stack.pop() {
+                Some(Value::Map(fields)) => fields,
+                _ => IndexMap::new(),
+            },
+            _ => IndexMap::new(),
+        };
+        let message = self.pop_value_or_err()?;
+        let message = message
+            .as_arg()
+            .unwrap_or_else(|| crate::display::format_value_hint(&message));
+        self.log(level, &message, &fields);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// log-level: "debug"|"info"|"warn"|"error"|"off" log-level
+    /// Set the least severe level that is written
+    pub(crate) fn builtin_log_level(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        match setting.as_arg().as_deref().and_then(LogLevel::parse) {
+            Some(level) => self.log_level = level,
+            None => {
+                self.stack.push(setting);
+                return Err(EvalError::ExecError(
+                    "log-level: usage: \"trace\"|\"debug\"|\"info\"|\"warn\"|\"error\"|\"off\" log-level"
+                        .into(),
+                ));
+            }
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// log-format: "text"|"json" log-format
+    /// Write log messages as text lines or as JSON objects
+    pub(crate) fn builtin_log_format(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        self.log_format = match setting.as_arg().as_deref() {
+            Some("text") => LogFormat::Text,
+            Some("json") => LogFormat::Json,
+            _ => {
+                self.stack.push(setting);
+                return Err(EvalError::ExecError(
+                    "log-format: usage: \"text\" log-format or \"json\" log-format".into(),
+                ));
+            }
+        };
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Write `message` at `level`, unless the level is below `log-level`
+    pub(crate) fn log(&self, level: LogLevel, message: &str, fields: &IndexMap<String, Value>) {
+        if level < self.log_level {
+            return;
+        }
+        match self.log_format {
+            LogFormat::Text => {
+                let mut line = format!(
+                    "\x1b[90m{}\x1b[0m {}{:<5}\x1b[0m {}",
+                    timestamp(),
+                    level.color(),
+                    level.name().to_uppercase(),
+                    message
+                );
+                for (key, value) in fields {
+                    line.push_str(&format!(" {}={}", key, field_text(value)));
+                }
+                crate::display::eprintln_styled(&line);
+            }
+            LogFormat::Json => {
+                let mut record = json!({
+                    "time": timestamp(),
+                    "level": level.name(),
+                    "msg": message,
+                });
+                if let JsonValue::Object(record) = &mut record {
+                    for (key, value) in fields {
+                        record.insert(key.clone(), value_to_json(value));
+                    }
+                }
+                eprintln!("{}", record);
+            }
+        }
+    }
+
+    /// Write a line of trace output (styled for the terminal)
+    pub(crate) fn log_trace(&self, line: &str) {
+        match self.log_format {
+            LogFormat::Text => crate::display::eprintln_styled(line),
+            LogFormat::Json => {
+                let message = crate::display::strip_sgr(line);
+                let record = json!({
+                    "time": timestamp(),
+                    "level": LogLevel::Trace.name(),
+                    "msg": message.trim(),
+                });
+                eprintln!("{}", record);
+            }
+        }
+    }
+}
@@ -1,18 +1,19 @@
 //! Builtins generated via the stack_builtin! macro (proof of concept)
 
+use super::helpers::Num;
+use super::math::{num_abs, num_max, num_min, num_neg};
 use super::{EvalError, Evaluator};
-use crate::ast::Value;
 
 impl Evaluator {
     // abs: absolute value of a number
-    stack_builtin!(builtin_abs, "abs", (n: Number) -> Number, n.abs());
+    stack_builtin!(builtin_abs, "abs", (n: Numeric) -> Numeric, num_abs(n));
 
     // negate: negate a number
-    stack_builtin!(builtin_negate, "negate", (n: Number) -> Number, -n);
+    stack_builtin!(builtin_negate, "negate", (n: Numeric) -> Numeric, num_neg(n));
 
     // max-of: maximum of two numbers
-    stack_builtin!(builtin_max_of, "max-of", (a: Number, b: Number) -> Number, a.max(b));
+    stack_builtin!(builtin_max_of, "max-of", (a: Numeric, b: Numeric) -> Numeric, num_max(a, b));
 
     // min-of: minimum of two numbers
-    stack_builtin!(builtin_min_of, "min-of", (a: Number, b: Number) -> Number, a.min(b));
+    stack_builtin!(builtin_min_of, "min-of", (a: Numeric, b: Numeric) -> Numeric, num_min(a, b));
 }
@@ -7,11 +7,13 @@
 ///
 /// Supported pop types:
 ///   Number     -> pop_number(op_name) -> f64
+///   Numeric    -> pop_numeric(op_name) -> Num (exact for Int and BigInt)
 ///   NumberList -> pop Value::List, extract f64s -> Vec<f64>
 ///   Value      -> stack.pop() -> Value (raw)
 ///
 /// Supported push types:
 ///   Number -> push Value::Number(result)
+///   Numeric -> push the Num as an Int, BigInt, or Number
 ///   Value  -> push result directly (must be a Value)
 macro_rules! stack_builtin {
     // Two Number params -> Number result
@@ -37,6 +39,29 @@ macro_rules! stack_builtin {
         }
     };
 
+    // Two Numeric params -> Numeric result
+    ($name:ident, $op:expr, ($a:ident : Numeric, $b:ident : Numeric) -> Numeric, $body:expr) => {
+        pub(crate) fn $name(&mut self) -> Result<(), EvalError> {
+            let $b = self.pop_numeric($op)?;
+            let $a = self.pop_numeric($op)?;
+            let result: Num = $body;
+            self.stack.push(result.into_value());
+            self.last_exit_code = 0;
+            Ok(())
+        }
+    };
+
+    // One Numeric param -> Numeric result
+    ($name:ident, $op:expr, ($a:ident : Numeric) -> Numeric, $body:expr) => {
+        pub(crate) fn $name(&mut self) -> Result<(), EvalError> {
+            let $a = self.pop_numeric($op)?;
+            let result: Num = $body;
+            self.stack.push(result.into_value());
+            self.last_exit_code = 0;
+            Ok(())
+        }
+    };
+
     // NumberList param -> Number result
     ($name:ident, $op:expr, ($list:ident : NumberList) -> Number, $body:expr) => {
         pub(crate) fn $name(&mut self) -> Result<(), EvalError> {
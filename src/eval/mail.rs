@@ -0,0 +1,482 @@
+//! Sending mail over SMTP: `mail-send`
+//!
+//! ```text
+//! { to "ops@example.com" subject "Nightly report" body $report
+//!   attachments [report.csv] } mail-send
+//! ```
+//!
+//! The record names the recipients (`to`, and optionally `cc` and `bcc`, each
+//! an address or a list of them), the `subject`, a plain `body`, an `html`
+//! body, and `attachments`: paths to files, or Bytes (named `attachment-1`
+//! and so on). `from` defaults to the configured sender.
+//!
+//! The server is configured by environment variables, usually exported
+//! from `~/.hsabrc`: `SMTP_HOST`, `SMTP_PORT` (587 unless set),
+//! `SMTP_USER` and `SMTP_PASSWORD` to log in, `SMTP_FROM` (else the user),
+//! and `SMTP_TLS`: `starttls`, `tls` (the default on port 465), or `none`.
+
+// The WebAssembly build can't connect, so builds messages it never sends
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+use base64::Engine;
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// A message ready to send
+#[derive(Debug, Clone)]
+struct Mail {
+    from: String,
+    to: Vec<String>,
+    cc: Vec<String>,
+    bcc: Vec<String>,
+    subject: String,
+    body: String,
+    html: Option<String>,
+    /// File names and contents
+    attachments: Vec<(String, Vec<u8>)>,
+}
+
+/// How the connection to the server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Security {
+    /// TLS from the first byte (port 465)
+    Tls,
+    /// Plain, then upgraded with STARTTLS
+    StartTls,
+    /// Plain throughout, for local relays
+    None,
+}
+
+/// Where and how to send
+#[derive(Debug, Clone)]
+struct SmtpConfig {
+    host: String,
+    port: u16,
+    security: Security,
+    login: Option<(String, String)>,
+    from: Option<String>,
+}
+
+impl SmtpConfig {
+    fn from_env() -> Result<Self, EvalError> {
+        let var = |name: &str| super::env::var(name).ok().filter(|v| !v.is_empty());
+        let host = var("SMTP_HOST").ok_or_else(|| {
+            EvalError::ExecError("mail-send: set SMTP_HOST to the mail server".into())
+        })?;
+        let port = match var("SMTP_PORT") {
+            Some(port) => port
+                .parse()
+                .map_err(|_| EvalError::ExecError(format!("mail-send: bad SMTP_PORT {}", port)))?,
+            None => 587,
+        };
+        let security = match var("SMTP_TLS").as_deref() {
+            Some("tls") => Security::Tls,
+            Some("starttls") => Security::StartTls,
+            Some("none") => Security::None,
+            Some(other) => {
+                return Err(EvalError::ExecError(format!(
+                    "mail-send: SMTP_TLS is starttls, tls, or none, not {}",
+                    other
+                )))
+            }
+            None if port == 465 => Security::Tls,
+            None => Security::StartTls,
+        };
+        let user = var("SMTP_USER");
+        let login = user.clone().zip(var("SMTP_PASSWORD"));
+        Ok(SmtpConfig {
+            host,
+            port,
+            security,
+            login,
+            from: var("SMTP_FROM").or(user),
+        })
+    }
+}
+
+/// The bare address in `Name <addr>` or `addr`
+fn address(mailbox: &str) -> &str {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// Refuse a header value with a line break, which would end the header
+/// (or SMTP command) early and let the rest pass as one of its own
+fn one_line(field: &str, value: &str) -> Result<(), EvalError> {
+    if value.contains(['\r', '\n']) {
+        return Err(EvalError::ExecError(format!(
+            "mail-send: {} can't contain a line break",
+            field
+        )));
+    }
+    Ok(())
+}
+
+/// A header value, encoded if it isn't plain ASCII
+fn header_text(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+        format!("=?UTF-8?B?{}?=", encoded)
+    }
+}
+
+/// Base64 in 76-character lines, as MIME bodies are written
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / 38);
+    for chunk in encoded.as_bytes().chunks(76) {
+        out.push_str(&String::from_utf8_lossy(chunk));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// A MIME part holding `data` with the given content type
+fn part(content_type: &str, disposition: Option<&str>, data: &[u8]) -> String {
+    let mut out = format!("Content-Type: {}\r\n", content_type);
+    if let Some(disposition) = disposition {
+        out.push_str(&format!("Content-Disposition: {}\r\n", disposition));
+    }
+    out.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    out.push_str(&base64_lines(data));
+    out
+}
+
+/// Parts joined under a multipart content type
+fn multipart(kind: &str, boundary: &str, parts: &[String]) -> String {
+    let mut out = format!(
+        "Content-Type: multipart/{}; boundary=\"{}\"\r\n\r\n",
+        kind, boundary
+    );
+    for part in parts {
+        out.push_str(&format!("--{}\r\n{}", boundary, part));
+    }
+    out.push_str(&format!("--{}--\r\n", boundary));
+    out
+}
+
+impl Mail {
+    /// The message as sent: headers, then a body with any HTML alternative
+    /// and attachments
+    fn render(&self, date: &str, boundary: &str) -> String {
+        let mut out = format!("From: {}\r\n", self.from);
+        out.push_str(&format!("To: {}\r\n", self.to.join(", ")));
+        if !self.cc.is_empty() {
+            out.push_str(&format!("Cc: {}\r\n", self.cc.join(", ")));
+        }
+        out.push_str(&format!("Subject: {}\r\n", header_text(&self.subject)));
+        out.push_str(&format!("Date: {}\r\nMIME-Version: 1.0\r\n", date));
+
+        let text = part("text/plain; charset=utf-8", None, self.body.as_bytes());
+        let content = match &self.html {
+            Some(html) => {
+                let html = part("text/html; charset=utf-8", None, html.as_bytes());
+                multipart("alternative", &format!("{}-alt", boundary), &[text, html])
+            }
+            None => text,
+        };
+        if self.attachments.is_empty() {
+            out.push_str(&content);
+            return out;
+        }
+        let mut parts = vec![content];
+        for (name, data) in &self.attachments {
+            let name = header_text(name).replace('"', "");
+            parts.push(part(
+                "application/octet-stream",
+                Some(&format!("attachment; filename=\"{}\"", name)),
+                data,
+            ));
+        }
+        out.push_str(&multipart("mixed", boundary, &parts));
+        out
+    }
+
+    /// Every address the message goes to
+    fn recipients(&self) -> impl Iterator<Item = &str> {
+        self.to
+            .iter()
+            .chain(&self.cc)
+            .chain(&self.bcc)
+            .map(|r| address(r))
+    }
+}
+
+/// Addresses from a record field: one string, or a list of them
+fn addresses(record: &IndexMap<String, Value>, key: &str) -> Vec<String> {
+    match record.get(key) {
+        Some(Value::List(items)) => items.iter().filter_map(Value::as_arg).collect(),
+        Some(value) => value.as_arg().into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+impl Evaluator {
+    /// mail-send: { to subject body attachments } mail-send
+    /// Send a message through the SMTP server set in the environment
+    pub(crate) fn builtin_mail_send(&mut self) -> Result<(), EvalError> {
+        let record = match self.pop_value_or_err()? {
+            Value::Map(record) => record,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "Record".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let config = SmtpConfig::from_env()?;
+        let mail = self.mail_from_record(&record, config.from.as_deref())?;
+        send(&config, &mail)?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// The message a `mail-send` record describes
+    fn mail_from_record(
+        &self,
+        record: &IndexMap<String, Value>,
+        default_from: Option<&str>,
+    ) -> Result<Mail, EvalError> {
+        let text = |key: &str| record.get(key).and_then(Value::as_arg);
+        let from = text("from")
+            .or(default_from.map(str::to_string))
+            .ok_or_else(|| {
+                EvalError::ExecError("mail-send: no sender (set SMTP_FROM or from)".into())
+            })?;
+        let to = addresses(record, "to");
+        if to.is_empty() {
+            return Err(EvalError::ExecError(
+                "mail-send: no recipients in to".into(),
+            ));
+        }
+
+        let items = match record.get("attachments") {
+            Some(Value::List(items)) => items.clone(),
+            Some(Value::Nil) | None => Vec::new(),
+            Some(other) => vec![other.clone()],
+        };
+        let mut attachments = Vec::new();
+        for (i, item) in items.into_iter().enumerate() {
+            match item {
+                Value::Bytes(data) => attachments.push((format!("attachment-{}", i + 1), data)),
+                other => {
+                    let path = other.as_arg().unwrap_or_default();
+                    let full = self.cwd.join(&path);
+                    let data = std::fs::read(&full)
+                        .map_err(|e| EvalError::ExecError(format!("mail-send: {}: {}", path, e)))?;
+                    let name = full
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or(path);
+                    attachments.push((name, data));
+                }
+            }
+        }
+
+        let mail = Mail {
+            from,
+            to,
+            cc: addresses(record, "cc"),
+            bcc: addresses(record, "bcc"),
+            subject: text("subject").unwrap_or_default(),
+            body: text("body").unwrap_or_default(),
+            html: text("html"),
+            attachments,
+        };
+        one_line("from", &mail.from)?;
+        one_line("subject", &mail.subject)?;
+        for (field, list) in [("to", &mail.to), ("cc", &mail.cc), ("bcc", &mail.bcc)] {
+            for address in list {
+                one_line(field, address)?;
+            }
+        }
+        for (name, _) in &mail.attachments {
+            one_line("an attachment name", name)?;
+        }
+        Ok(mail)
+    }
+}
+
+/// Deliver `mail` through the server in `config`
+#[cfg(not(target_arch = "wasm32"))]
+fn send(config: &SmtpConfig, mail: &Mail) -> Result<(), EvalError> {
+    smtp::send(config, mail).map_err(|e| EvalError::ExecError(format!("mail-send: {}", e)))
+}
+
+/// The WebAssembly build has no network access
+#[cfg(target_arch = "wasm32")]
+fn send(_config: &SmtpConfig, _mail: &Mail) -> Result<(), EvalError> {
+    Err(EvalError::ExecError(
+        "mail-send: not available in this build".into(),
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod smtp {
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use base64::Engine;
+
+    use super::{Mail, Security, SmtpConfig};
+
+    /// How long to wait on the server before giving up
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    type TlsStream = rustls::StreamOwned<rustls::ClientConnection, TcpStream>;
+
+    /// The connection, before or after TLS starts
+    enum Stream {
+        Plain(TcpStream),
+        Tls(Box<TlsStream>),
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self {
+                Stream::Plain(s) => s.read(buf),
+                Stream::Tls(s) => s.read(buf),
+            }
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self {
+                Stream::Plain(s) => s.write(buf),
+                Stream::Tls(s) => s.write(buf),
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            match self {
+                Stream::Plain(s) => s.flush(),
+                Stream::Tls(s) => s.flush(),
+            }
+        }
+    }
+
+    /// Start TLS over `tcp`, checking the certificate for `host`
+    fn tls(host: &str, tcp: TcpStream) -> io::Result<Stream> {
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.into(),
+        };
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let name = rustls::pki_types::ServerName::try_from(host.to_string())
+            .map_err(|e| io::Error::other(format!("{}: {}", host, e)))?;
+        let conn = rustls::ClientConnection::new(Arc::new(config), name)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Stream::Tls(Box::new(rustls::StreamOwned::new(conn, tcp))))
+    }
+
+    struct Session {
+        reader: BufReader<Stream>,
+    }
+
+    impl Session {
+        /// Read a reply, failing unless its code is one of `expected`
+        fn reply(&mut self, expected: &[u16]) -> io::Result<String> {
+            let mut text = String::new();
+            loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(io::Error::other("server closed the connection"));
+                }
+                text.push_str(&line);
+                // `250-` continues a reply; `250 ` ends it
+                if line.as_bytes().get(3) != Some(&b'-') {
+                    break;
+                }
+            }
+            let code = text.get(..3).and_then(|c| c.parse().ok()).unwrap_or(0);
+            if expected.contains(&code) {
+                Ok(text)
+            } else {
+                Err(io::Error::other(format!("server said {}", text.trim())))
+            }
+        }
+
+        fn command(&mut self, line: &str, expected: &[u16]) -> io::Result<String> {
+            let stream = self.reader.get_mut();
+            stream.write_all(format!("{}\r\n", line).as_bytes())?;
+            stream.flush()?;
+            self.reply(expected)
+        }
+    }
+
+    pub(super) fn send(config: &SmtpConfig, mail: &Mail) -> io::Result<()> {
+        let tcp = TcpStream::connect((config.host.as_str(), config.port))?;
+        tcp.set_read_timeout(Some(TIMEOUT))?;
+        tcp.set_write_timeout(Some(TIMEOUT))?;
+        let stream = match config.security {
+            Security::Tls => tls(&config.host, tcp)?,
+            Security::StartTls | Security::None => Stream::Plain(tcp),
+        };
+        let mut session = Session {
+            reader: BufReader::new(stream),
+        };
+        let me = hostname::get()
+            .map(|h| h.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "localhost".to_string());
+
+        session.reply(&[220])?;
+        session.command(&format!("EHLO {}", me), &[250])?;
+        if config.security == Security::StartTls {
+            session.command("STARTTLS", &[220])?;
+            let Stream::Plain(tcp) = session.reader.into_inner() else {
+                unreachable!("STARTTLS on a plain connection");
+            };
+            session = Session {
+                reader: BufReader::new(tls(&config.host, tcp)?),
+            };
+            session.command(&format!("EHLO {}", me), &[250])?;
+        }
+        if let Some((user, password)) = &config.login {
+            let token = base64::engine::general_purpose::STANDARD
+                .encode(format!("\0{}\0{}", user, password));
+            session.command(&format!("AUTH PLAIN {}", token), &[235])?;
+        }
+
+        session.command(
+            &format!("MAIL FROM:<{}>", super::address(&mail.from)),
+            &[250],
+        )?;
+        for rcpt in mail.recipients() {
+            session.command(&format!("RCPT TO:<{}>", rcpt), &[250, 251])?;
+        }
+        session.command("DATA", &[354])?;
+        let date = chrono::Local::now().to_rfc2822();
+        let boundary = format!(
+            "hsab-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        );
+        let mut data = String::new();
+        for line in mail.render(&date, &boundary).lines() {
+            // A line starting with a dot gets another, so it can't end DATA
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        data.push('.');
+        session.command(&data, &[250])?;
+        // The message is accepted; a failed goodbye doesn't matter
+        let _ = session.command("QUIT", &[221]);
+        Ok(())
+    }
+}
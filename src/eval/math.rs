@@ -1,13 +1,23 @@
-use super::helpers::Num;
+use super::helpers::{compare_values, deep_eq, Num};
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
-use num_bigint::BigUint;
+use num_bigint::{BigInt, Sign};
 use std::cmp::Ordering;
 use std::path::Path;
 
-/// Convert a non-negative i64 to BigUint (caller must check sign)
-fn big(u: i64) -> BigUint {
-    BigUint::from(u as u64)
+/// An exact result: an Int when it fits (unless a BigInt operand asked for
+/// a BigInt), and a BigInt when it's larger
+fn from_exact(n: BigInt, keep_big: bool) -> Num {
+    if !keep_big {
+        if let Ok(i) = i64::try_from(&n) {
+            return Num::Int(i);
+        }
+    }
+    Num::Big(n)
+}
+
+fn any_big(a: &Num, b: &Num) -> bool {
+    matches!(a, Num::Big(_)) || matches!(b, Num::Big(_))
 }
 
 /// Push an f64 as Int when it is exactly representable as i64, else Number.
@@ -23,156 +33,135 @@ fn num_is_zero(n: &Num) -> bool {
     match n {
         Num::Int(i) => *i == 0,
         Num::Float(f) => *f == 0.0,
-        Num::Big(b) => *b == BigUint::from(0u32),
+        Num::Big(b) => b.sign() == Sign::NoSign,
     }
 }
 
-/// Compare two numeric operands (exact for Int/Int and Big/Big)
+/// Compare two numeric operands, exactly unless one is a float
 fn num_cmp(a: &Num, b: &Num) -> Ordering {
-    match (a, b) {
-        (Num::Int(x), Num::Int(y)) => x.cmp(y),
-        (Num::Big(x), Num::Big(y)) => x.cmp(y),
-        _ => a
-            .to_f64()
-            .partial_cmp(&b.to_f64())
-            .unwrap_or(Ordering::Equal),
-    }
+    a.compare(b).unwrap_or(Ordering::Equal)
 }
 
 fn num_eq(a: &Num, b: &Num) -> bool {
     num_cmp(a, b) == Ordering::Equal
 }
 
-/// a + b with promotion (Int overflow -> BigInt when non-negative, else float)
-fn num_add(a: Num, b: Num) -> Num {
-    match (&a, &b) {
-        (Num::Int(x), Num::Int(y)) => match x.checked_add(*y) {
-            Some(r) => Num::Int(r),
-            None => {
-                if *x >= 0 && *y >= 0 {
-                    Num::Big(big(*x) + big(*y))
-                } else {
-                    Num::Float(*x as f64 + *y as f64)
-                }
-            }
-        },
-        (Num::Big(x), Num::Big(y)) => Num::Big(x + y),
-        (Num::Big(x), Num::Int(y)) | (Num::Int(y), Num::Big(x)) => {
-            if *y >= 0 {
-                Num::Big(x + big(*y))
-            } else {
-                {
-                    let y_big = BigUint::from(y.unsigned_abs());
-                    if *x >= y_big {
-                        Num::Big(x - y_big)
-                    } else {
-                        Num::Float(a.to_f64() + b.to_f64())
-                    }
-                }
-            }
+/// Apply an operation exactly when neither operand is a float: in i64 while
+/// it doesn't overflow, then as a BigInt
+fn exact_op(
+    a: Num,
+    b: Num,
+    small: fn(i64, i64) -> Option<i64>,
+    large: fn(BigInt, BigInt) -> BigInt,
+    float: fn(f64, f64) -> f64,
+) -> Num {
+    if let (Num::Int(x), Num::Int(y)) = (&a, &b) {
+        if let Some(r) = small(*x, *y) {
+            return Num::Int(r);
         }
-        _ => Num::Float(a.to_f64() + b.to_f64()),
     }
+    let keep_big = any_big(&a, &b);
+    match (a.exact(), b.exact()) {
+        (Some(x), Some(y)) => from_exact(large(x, y), keep_big),
+        _ => Num::Float(float(a.to_f64(), b.to_f64())),
+    }
+}
+
+/// a + b with promotion
+pub(crate) fn num_add(a: Num, b: Num) -> Num {
+    exact_op(a, b, i64::checked_add, |x, y| x + y, |x, y| x + y)
 }
 
 /// a - b with promotion
 fn num_sub(a: Num, b: Num) -> Num {
-    match (&a, &b) {
-        (Num::Int(x), Num::Int(y)) => match x.checked_sub(*y) {
-            Some(r) => Num::Int(r),
-            None => {
-                if *x >= 0 && *y < 0 {
-                    Num::Big(big(*x) + BigUint::from(y.unsigned_abs()))
-                } else {
-                    Num::Float(*x as f64 - *y as f64)
-                }
-            }
-        },
-        (Num::Big(x), Num::Big(y)) => {
-            if x >= y {
-                Num::Big(x - y)
-            } else {
-                Num::Float(a.to_f64() - b.to_f64())
-            }
-        }
-        (Num::Big(x), Num::Int(y)) => {
-            if *y >= 0 {
-                let y_big = big(*y);
-                if *x >= y_big {
-                    Num::Big(x - y_big)
-                } else {
-                    Num::Float(a.to_f64() - b.to_f64())
-                }
-            } else {
-                Num::Big(x + BigUint::from(y.unsigned_abs()))
-            }
-        }
-        (Num::Int(x), Num::Big(y)) => {
-            let x_big = if *x >= 0 { Some(big(*x)) } else { None };
-            match x_big {
-                Some(xb) if xb >= *y => Num::Big(xb - y),
-                _ => Num::Float(a.to_f64() - b.to_f64()),
-            }
-        }
-        _ => Num::Float(a.to_f64() - b.to_f64()),
-    }
+    exact_op(a, b, i64::checked_sub, |x, y| x - y, |x, y| x - y)
 }
 
 /// a * b with promotion
-fn num_mul(a: Num, b: Num) -> Num {
-    match (&a, &b) {
-        (Num::Int(x), Num::Int(y)) => match x.checked_mul(*y) {
-            Some(r) => Num::Int(r),
-            None => {
-                if (*x >= 0) == (*y >= 0) {
-                    Num::Big(BigUint::from(x.unsigned_abs()) * BigUint::from(y.unsigned_abs()))
-                } else {
-                    Num::Float(*x as f64 * *y as f64)
-                }
-            }
-        },
-        (Num::Big(x), Num::Big(y)) => Num::Big(x * y),
-        (Num::Big(x), Num::Int(y)) | (Num::Int(y), Num::Big(x)) => {
-            if *y >= 0 {
-                Num::Big(x * big(*y))
-            } else {
-                Num::Float(a.to_f64() * b.to_f64())
-            }
-        }
-        _ => Num::Float(a.to_f64() * b.to_f64()),
-    }
+pub(crate) fn num_mul(a: Num, b: Num) -> Num {
+    exact_op(a, b, i64::checked_mul, |x, y| x * y, |x, y| x * y)
 }
 
-/// a / b with promotion: exact Int division stays Int, otherwise float
-fn num_div(a: Num, b: Num) -> Num {
-    match (&a, &b) {
-        (Num::Int(x), Num::Int(y)) => {
-            if x % y == 0 {
-                match x.checked_div(*y) {
-                    Some(r) => Num::Int(r),
-                    None => Num::Float(*x as f64 / *y as f64),
-                }
-            } else {
-                Num::Float(*x as f64 / *y as f64)
-            }
-        }
-        (Num::Big(x), Num::Big(y)) => {
-            if x % y == BigUint::from(0u32) {
-                Num::Big(x / y)
-            } else {
-                Num::Float(a.to_f64() / b.to_f64())
-            }
+/// a / b with promotion: exact division of integers stays exact,
+/// otherwise float (b is non-zero)
+pub(crate) fn num_div(a: Num, b: Num) -> Num {
+    if let (Num::Int(x), Num::Int(y)) = (&a, &b) {
+        match (x.checked_rem(*y), x.checked_div(*y)) {
+            (Some(0), Some(r)) => return Num::Int(r),
+            (Some(_), _) => return Num::Float(*x as f64 / *y as f64),
+            _ => {}
         }
+    }
+    let keep_big = any_big(&a, &b);
+    match (a.exact(), b.exact()) {
+        (Some(x), Some(y)) if (&x % &y).sign() == Sign::NoSign => from_exact(x / y, keep_big),
         _ => Num::Float(a.to_f64() / b.to_f64()),
     }
 }
 
-/// a % b with promotion
+/// a % b with promotion, the sign following a's (b is non-zero)
 fn num_mod(a: Num, b: Num) -> Num {
-    match (&a, &b) {
-        (Num::Int(x), Num::Int(y)) => Num::Int(x.checked_rem(*y).unwrap_or(0)),
-        (Num::Big(x), Num::Big(y)) => Num::Big(x % y),
-        _ => Num::Float(a.to_f64() % b.to_f64()),
+    exact_op(a, b, i64::checked_rem, |x, y| x % y, |x, y| x % y)
+}
+
+/// a / b truncated toward zero, with promotion (b is non-zero)
+fn num_idiv(a: Num, b: Num) -> Num {
+    exact_op(a, b, i64::checked_div, |x, y| x / y, |x, y| (x / y).trunc())
+}
+
+/// Largest exact power computed before falling back to a float, in bits
+const MAX_EXACT_POW_BITS: u64 = 1 << 20;
+
+/// base ^ exp, exact for an integer base and a whole, non-negative exponent
+fn num_pow(base: Num, exp: Num) -> Num {
+    let exact_exp = exp.exact().and_then(|e| u32::try_from(&e).ok());
+    if let (Some(b), Some(e)) = (base.exact(), exact_exp) {
+        if b.bits().saturating_mul(e as u64) <= MAX_EXACT_POW_BITS {
+            return from_exact(b.pow(e), matches!(base, Num::Big(_)));
+        }
+    }
+    Num::Float(base.to_f64().powf(exp.to_f64()))
+}
+
+/// |n|, with i64::MIN promoted to a BigInt
+pub(crate) fn num_abs(n: Num) -> Num {
+    match n {
+        Num::Int(i) => i
+            .checked_abs()
+            .map(Num::Int)
+            .unwrap_or_else(|| Num::Big(BigInt::from(i).magnitude().clone().into())),
+        Num::Float(f) => Num::Float(f.abs()),
+        Num::Big(b) => Num::Big(b.magnitude().clone().into()),
+    }
+}
+
+/// -n, with -i64::MIN promoted to a BigInt
+pub(crate) fn num_neg(n: Num) -> Num {
+    match n {
+        Num::Int(i) => i
+            .checked_neg()
+            .map(Num::Int)
+            .unwrap_or_else(|| Num::Big(-BigInt::from(i))),
+        Num::Float(f) => Num::Float(-f),
+        Num::Big(b) => Num::Big(-b),
+    }
+}
+
+/// The larger of two numbers (a NaN loses, as with f64::max)
+pub(crate) fn num_max(a: Num, b: Num) -> Num {
+    match a.compare(&b) {
+        Some(Ordering::Less) => b,
+        Some(_) => a,
+        None => Num::Float(a.to_f64().max(b.to_f64())),
+    }
+}
+
+/// The smaller of two numbers (a NaN loses, as with f64::min)
+pub(crate) fn num_min(a: Num, b: Num) -> Num {
+    match a.compare(&b) {
+        Some(Ordering::Greater) => b,
+        Some(_) => a,
+        None => Num::Float(a.to_f64().min(b.to_f64())),
     }
 }
 
@@ -203,6 +192,36 @@ impl Evaluator {
         Ok(())
     }
 
+    /// Deep structural equality (Lists, Records, Tables)
+    /// Usage: a b eq-deep? -> Bool
+    pub(crate) fn builtin_eq_deep(&mut self) -> Result<(), EvalError> {
+        let b = self.pop_value_or_err()?;
+        let a = self.pop_value_or_err()?;
+        let result = deep_eq(&a, &b);
+        self.stack.push(Value::Bool(result));
+        self.last_exit_code = if result { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// Three-way comparison for sortable values
+    /// Usage: a b compare -> -1 | 0 | 1
+    pub(crate) fn builtin_compare(&mut self) -> Result<(), EvalError> {
+        let b = self.pop_value_or_err()?;
+        let a = self.pop_value_or_err()?;
+        let ordering = compare_values(&a, &b).ok_or_else(|| EvalError::TypeError {
+            expected: "two comparable values (compare)".into(),
+            got: format!("{} and {}", a.type_name(), b.type_name()),
+        })?;
+        let n = match ordering {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        };
+        self.stack.push(Value::Int(n));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
     /// Numeric equality (stack-native)
     /// Usage: 5 5 =? -> Bool
     pub(crate) fn builtin_num_eq_stack(&mut self) -> Result<(), EvalError> {
@@ -333,12 +352,12 @@ impl Evaluator {
     // Math primitives (for stats support)
     // ========================================
 
-    /// Float power: base exponent pow -> base^exponent
+    /// Power: base exponent pow -> base^exponent, exact for integers
+    /// raised to whole, non-negative exponents
     pub(crate) fn builtin_pow(&mut self) -> Result<(), EvalError> {
-        let exp = self.pop_number("pow")?;
-        let base = self.pop_number("pow")?;
-        let result = base.powf(exp);
-        self.stack.push(Value::Number(result));
+        let exp = self.pop_numeric("pow")?;
+        let base = self.pop_numeric("pow")?;
+        self.stack.push(num_pow(base, exp).into_value());
         self.last_exit_code = 0;
         Ok(())
     }
@@ -385,12 +404,16 @@ impl Evaluator {
     /// Integer division (truncates toward zero)
     /// Usage: 10 3 idiv -> 3
     pub(crate) fn builtin_idiv(&mut self) -> Result<(), EvalError> {
-        let b = self.pop_number("idiv")?;
-        let a = self.pop_number("idiv")?;
-        if b == 0.0 {
+        let b = self.pop_numeric("idiv")?;
+        let a = self.pop_numeric("idiv")?;
+        if num_is_zero(&b) {
             return Err(EvalError::ExecError("idiv: division by zero".to_string()));
         }
-        self.stack.push(float_to_value((a / b).trunc()));
+        let result = match num_idiv(a, b) {
+            Num::Float(f) => float_to_value(f),
+            exact => exact.into_value(),
+        };
+        self.stack.push(result);
         self.last_exit_code = 0;
         Ok(())
     }
@@ -403,27 +426,12 @@ impl Evaluator {
 
         match value {
             Value::List(mut items) => {
-                // Extract numeric values and sort
+                // Exact over Ints and BigInts; anything else sorts as NaN
+                let nan = Num::Float(f64::NAN);
                 items.sort_by(|a, b| {
-                    let a_num = match a {
-                        Value::Number(n) => *n,
-                        Value::Int(i) => *i as f64,
-                        Value::Literal(s) | Value::Output(s) => {
-                            s.trim().parse().unwrap_or(f64::NAN)
-                        }
-                        _ => f64::NAN,
-                    };
-                    let b_num = match b {
-                        Value::Number(n) => *n,
-                        Value::Int(i) => *i as f64,
-                        Value::Literal(s) | Value::Output(s) => {
-                            s.trim().parse().unwrap_or(f64::NAN)
-                        }
-                        _ => f64::NAN,
-                    };
-                    a_num
-                        .partial_cmp(&b_num)
-                        .unwrap_or(std::cmp::Ordering::Equal)
+                    let a_num = Num::of(a).unwrap_or_else(|| nan.clone());
+                    let b_num = Num::of(b).unwrap_or_else(|| nan.clone());
+                    a_num.compare(&b_num).unwrap_or(Ordering::Equal)
                 });
                 self.stack.push(Value::List(items));
                 self.last_exit_code = 0;
@@ -591,5 +599,68 @@ impl Evaluator {
         Ok(())
     }
 
+    /// Update a variable in place: NAME inc!, NAME dec!, or NAME N inc!
+    /// to step by N. An unset variable counts as 0. The variable is
+    /// updated where it lives (a local, else the environment).
+    pub(crate) fn builtin_step_var(&mut self, op: &str, up: bool) -> Result<(), EvalError> {
+        let top = self.pop_value_or_err()?;
+        let is_name = |s: &str| {
+            s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        let step = match top.as_arg() {
+            Some(s) if is_name(&s) => {
+                self.stack.push(top);
+                Num::Int(1)
+            }
+            _ => {
+                self.stack.push(top);
+                self.pop_numeric(op)?
+            }
+        };
+        let name = self
+            .stack
+            .pop()
+            .and_then(|v| v.as_arg())
+            .unwrap_or_default();
+        if !is_name(&name) {
+            return Err(EvalError::ExecError(format!(
+                "{}: expected a variable name, got {:?} (use \"i\" {}, not $i {})",
+                op, name, op, op
+            )));
+        }
+
+        let current = match self.lookup_var_as_string(&name) {
+            Some(s) if !s.trim().is_empty() => {
+                self.stack.push(Value::Literal(s));
+                self.pop_numeric(op)?
+            }
+            _ => Num::Int(0),
+        };
+        let updated = if up {
+            num_add(current, step)
+        } else {
+            num_sub(current, step)
+        }
+        .into_value();
+
+        match self
+            .local_values
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.contains_key(&name))
+        {
+            Some(scope) => {
+                scope.insert(name, updated);
+            }
+            None => {
+                self.check_env_write(&name)?;
+                super::env::set_var(&name, updated.as_arg().unwrap_or_default());
+            }
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
     // pop_number is defined in helpers.rs
 }
@@ -25,36 +25,100 @@
 #[macro_use]
 mod macros;
 mod aggregation;
+mod argparse;
 mod async_ops;
+mod audit;
 mod bigint;
+mod bytes;
+mod cache;
+mod calc;
+mod cancel;
+mod changed;
+mod checkpoint;
+mod cloud;
+mod columns;
 mod combinators;
 mod command;
+mod compress;
+mod config_formats;
+mod confirm;
 mod control;
+mod correct;
+mod debugger;
+mod dns;
+mod doc_view;
+mod edit;
+mod edit_file;
+mod effects;
 mod encoding;
+mod env;
+mod explain;
+mod fake;
+mod forge;
+mod handle;
+mod head_tail;
+mod health;
 mod helpers;
+mod host;
 mod http;
 mod image;
+mod limits;
 mod list;
 mod local;
+mod log;
 mod macro_builtins;
+mod mail;
 mod math;
+mod module_watch;
 mod modules;
+mod multipart;
+mod oauth2;
+mod patch;
 mod path;
 mod plugin;
+mod policy;
 mod process;
+mod provenance;
+mod rate_limit;
+mod recording;
 mod serialization;
+mod series;
+mod settings;
+mod shared_defs;
 mod shell;
 mod shell_native;
 mod snapshot;
+mod spreadsheet;
 mod stack;
 mod stats;
 mod string;
 mod structured;
 mod terminal;
 mod tests;
+mod trash;
+mod tty_guard;
+mod units;
+mod url;
+mod validate;
 mod vector;
 #[cfg(feature = "plugins")]
 mod watch;
+mod with_stderr;
+
+pub use audit::AuditConfig;
+pub use cancel::CancelToken;
+pub use checkpoint::ResumePoint;
+pub use debugger::{DebugAction, DebugFrontend};
+pub use effects::{builtin_effect, StackEffect};
+pub use handle::EvaluatorHandle;
+pub use host::HostBuiltin;
+pub use limits::Limits;
+pub use module_watch::ModuleReload;
+pub use patch::{merge3, Merge};
+pub use policy::ExecPolicy;
+pub use provenance::Origin;
+pub use recording::{RecordedStep, Recording};
+pub use shared_defs::SyncReport;
 
 use crate::ast::{Expr, Program, Value};
 use crate::resolver::ExecutableResolver;
@@ -62,8 +126,9 @@ use crate::util::lock_or_recover;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Child;
+use std::sync::Arc;
 #[cfg(feature = "plugins")]
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 use thiserror::Error;
 
 #[cfg(feature = "plugins")]
@@ -89,11 +154,30 @@ pub enum EvalError {
         col: usize,
         source: Box<EvalError>,
     },
+    /// The eval was stopped through its `CancelToken`
+    #[error("Cancelled")]
+    Cancelled,
+    /// The eval ran past its step or time limit
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
+    /// `exit` was called: unwinds to the top level, which ends the shell
+    #[error("exit {0}")]
+    Exit(i32),
     /// Internal: signals break from loop (not a real error)
     #[error("")]
     BreakLoop,
 }
 
+impl EvalError {
+    /// Errors that end the whole eval: `try` doesn't catch them
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            EvalError::Cancelled | EvalError::ResourceExhausted(_) | EvalError::Exit(_)
+        )
+    }
+}
+
 /// Result of evaluation
 #[derive(Debug, Clone)]
 pub struct EvalResult {
@@ -116,6 +200,28 @@ pub(crate) struct Job {
     /// `wait`, and `.fg` (issue #30)
     pub(crate) child: Option<Child>,
     pub(crate) status: JobStatus,
+    /// Audit record, written when the job's exit status is collected
+    pub(crate) audit: Option<audit::AuditEntry>,
+    /// When the job was started, for `jobs-table`
+    pub(crate) started: chrono::DateTime<chrono::Local>,
+    /// How long the job ran, once it has finished
+    pub(crate) runtime: Option<std::time::Duration>,
+    /// The job's stdout, collected as it runs, for `job-output`
+    pub(crate) output: Option<Arc<std::sync::Mutex<Vec<u8>>>>,
+}
+
+impl Job {
+    /// Mark the job done with `code`
+    pub(crate) fn finish(&mut self, code: i32) {
+        self.status = JobStatus::Done(code);
+        if self.runtime.is_none() {
+            let elapsed = chrono::Local::now() - self.started;
+            self.runtime = Some(elapsed.to_std().unwrap_or_default());
+        }
+        if let Some(entry) = self.audit.take() {
+            entry.finish(code);
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -127,9 +233,12 @@ pub(crate) enum JobStatus {
 }
 
 /// The evaluator maintains state and executes programs
+///
+/// An `Evaluator` is `Send` but not `Sync`; to share one between threads or
+/// async tasks, wrap it in an [`EvaluatorHandle`].
 pub struct Evaluator {
     /// The value stack
-    pub(crate) stack: Vec<Value>,
+    pub(crate) stack: provenance::Stack,
     /// Executable resolver for detecting commands
     pub(crate) resolver: ExecutableResolver,
     /// Last exit code
@@ -142,19 +251,53 @@ pub struct Evaluator {
     pub(crate) home_dir: String,
     /// Background jobs
     pub(crate) jobs: Vec<Job>,
+    /// Jobs reaped since `JOB_DONE` last ran, by id
+    pub(crate) finished_jobs: Vec<usize>,
     /// Next job ID
     pub(crate) next_job_id: usize,
     /// Exit codes from last pipeline
     pub(crate) pipestatus: Vec<i32>,
+    /// Stack depth and value a pipe last pushed, so a pipe consuming it
+    /// extends the same pipeline
+    pub(crate) pipe_tail: Option<(usize, Value)>,
+    /// The consumers of a `cmd #[a] | #[b] | ...` being evaluated, for the
+    /// command to stream its stdout into if it spawns a program
+    pub(crate) pipe_consumers: Vec<Vec<Expr>>,
+    /// How many of `pipe_consumers` the command streamed into, so their
+    /// blocks and `|`s are skipped
+    pub(crate) pipe_streamed: usize,
+    /// A pipeline's exit code is its first failing stage's (`pipefail`)
+    pub(crate) pipefail: bool,
+    /// Calculator mode (`.calc`, `hsab --rpn`): `ans` and numbers with units
+    pub(crate) calc_mode: bool,
+    /// The value on top of the stack when the last line finished, in
+    /// calculator mode
+    pub(crate) calc_ans: Value,
     /// Whether to capture command output (vs run interactively)
     /// True when output will be consumed by next command/operator
     pub(crate) capture_mode: bool,
+    /// Set by `live` (false) or `capture` (true): overrides capture_mode
+    /// for the next external command
+    pub(crate) capture_override: Option<bool>,
+    /// What to do when an interactive command's output would be captured
+    pub(crate) tty_guard: tty_guard::TtyGuard,
+    /// Set by `with-stderr`: collects the stderr of commands run meanwhile
+    pub(crate) stderr_sink: Option<Vec<u8>>,
+    /// Set for a block run by `parallel`: passes the lines its commands
+    /// print on to the live view
+    pub(crate) output_tap: Option<terminal::OutputTap>,
+    /// Read ends of `subst` pipes and the producers writing to them,
+    /// closed once the line finishes
+    #[cfg(unix)]
+    pub(crate) subst_streams: Vec<(std::os::fd::OwnedFd, Child, audit::AuditEntry)>,
     /// Directory stack for pushd/popd
     pub(crate) dir_stack: Vec<PathBuf>,
     /// Command aliases - maps name to expansion (block of expressions)
     pub(crate) aliases: HashMap<String, Vec<Expr>>,
     /// Signal traps (signal number -> block to execute)
     pub(crate) traps: HashMap<i32, Vec<Expr>>,
+    /// The code an eval that ended in `exit` asked the shell to end with
+    pub(crate) exit_requested: Option<i32>,
     /// Stack of local variable scopes (for nested definitions)
     /// Each scope maps var name -> original value (None if didn't exist)
     pub(crate) local_scopes: Vec<HashMap<String, Option<String>>>,
@@ -167,18 +310,84 @@ pub struct Evaluator {
     pub(crate) last_if_taken: bool,
     /// Trace mode - print stack after each operation
     pub(crate) trace_mode: bool,
+    /// Least severe level `log-info` and friends write
+    pub(crate) log_level: log::LogLevel,
+    /// Whether log messages are text lines or JSON objects
+    pub(crate) log_format: log::LogFormat,
     /// Debug mode - enable step debugger
     pub(crate) debug_mode: bool,
     /// Step mode - pause before each expression
     pub(crate) step_mode: bool,
     /// Breakpoints - expression patterns to pause on
     pub(crate) breakpoints: std::collections::HashSet<String>,
+    /// Conditional breakpoints - (source, condition block) checked before each step
+    pub(crate) conditional_breakpoints: Vec<(String, Vec<Expr>)>,
+    /// Definitions whose entry and exit pause the debugger
+    pub(crate) def_breakpoints: std::collections::HashSet<String>,
+    /// Watch expressions - (source, block) printed after each step
+    pub(crate) watches: Vec<(String, Vec<Expr>)>,
+    /// External debugger frontend (e.g. the DAP server); None uses the
+    /// stdin prompt
+    pub(crate) debug_frontend: Option<Box<dyn debugger::DebugFrontend>>,
+    /// Set when a frontend asks to stop the program
+    pub(crate) debug_terminate: bool,
+    /// Active `.record` session
+    pub(crate) recorder: Option<recording::Recorder>,
+    /// Script line being executed, for `checkpoint`
+    pub(crate) script_location: Option<checkpoint::ScriptLocation>,
+    /// Arguments passed to the running script, for `args` and `parse-args`
+    pub(crate) script_args: Vec<String>,
+    /// External commands that may be spawned
+    pub(crate) exec_policy: ExecPolicy,
+    /// Where spawned processes are recorded, when auditing is on
+    pub(crate) audit: Option<Arc<audit::AuditLog>>,
+    /// Unset variables are errors rather than empty strings
+    pub(crate) strict_mode: bool,
+    /// rbash-style restrictions, see `policy`
+    pub(crate) restricted: bool,
+    /// Set from another thread to stop the running eval
+    pub(crate) cancel: CancelToken,
+    /// Step and time limits for each eval
+    pub(crate) limits: Limits,
+    /// What is left of the limits during an eval
+    pub(crate) fuel: Option<limits::Fuel>,
+    /// Words registered by the embedding program
+    pub(crate) host_builtins: HashMap<String, HostBuiltin>,
+    /// Running the interactive shell rather than a script or `-c`
+    pub(crate) interactive: bool,
+    /// `rm` trashes instead of deleting when interactive
+    pub(crate) rm_trash: bool,
+    /// Glob matches over which interactive `rm` and `mv` ask (0 never)
+    pub(crate) confirm_glob: usize,
+    /// Set by `force`: the next `rm` or `mv` doesn't ask
+    pub(crate) force_next: bool,
+    /// Set by `refresh`: the next `cached` runs its block regardless, and
+    /// the next `oauth2-token` gets a new token
+    pub(crate) refresh_next: bool,
+    /// When each `rate-limit` bucket's blocks last ran, oldest first
+    pub(crate) rate_buckets: HashMap<String, std::collections::VecDeque<std::time::Instant>>,
+    /// What each `changed?` block printed when it last ran
+    pub(crate) changed_outputs: HashMap<String, String>,
+    /// The samples of each named `series`, oldest first
+    pub(crate) series: HashMap<String, Vec<Vec<Value>>>,
+    /// The generator behind the `fake-` words, seeded on first use
+    pub(crate) fake_rng: Option<u64>,
+    /// Offer to run the closest command when one isn't found
+    pub(crate) autocorrect: bool,
+    /// Extra module search directories (from hsab.toml), searched before
+    /// HSAB_PATH
+    pub(crate) module_paths: Vec<PathBuf>,
+    /// Definitions shared with other sessions, when enabled
+    pub(crate) shared_defs: Option<shared_defs::SharedDefs>,
     /// Loaded modules (by canonical path) to prevent double-loading
     pub(crate) loaded_modules: std::collections::HashSet<PathBuf>,
     /// Current definition call depth (for recursion limit)
     pub(crate) call_depth: usize,
     /// Maximum recursion depth (default 10000, configurable via HSAB_MAX_RECURSION)
     pub(crate) max_call_depth: usize,
+    /// Encoding for decoding command output (default UTF-8, configurable via
+    /// HSAB_ENCODING or set-encoding)
+    pub(crate) output_encoding: encoding::TextEncoding,
     /// Limbo storage for popped values awaiting resolution
     pub limbo: HashMap<String, Value>,
     /// Preview length for limbo references
@@ -201,6 +410,9 @@ pub struct Evaluator {
     /// included) so `futures-list` can enumerate them; see docs/async.md.
     pub(crate) futures:
         indexmap::IndexMap<String, std::sync::Arc<std::sync::Mutex<crate::ast::FutureState>>>,
+    /// Modules imported with `.import-watch`, reloaded when their files change
+    #[cfg(feature = "plugins")]
+    pub(crate) module_watches: Vec<module_watch::ModuleWatch>,
     /// Plugin host for WASM plugin support
     #[cfg(feature = "plugins")]
     pub(crate) plugin_host: Option<PluginHost>,
@@ -217,7 +429,10 @@ impl Default for Evaluator {
 
 impl Evaluator {
     pub fn new() -> Self {
-        let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+        // The first evaluator captures the startup environment that
+        // checkpoints diff against
+        checkpoint::startup_env();
+        let home = env::var("HOME").unwrap_or_else(|_| "/".to_string());
         let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
 
         // Create shared stack for plugins
@@ -243,35 +458,87 @@ impl Evaluator {
         };
 
         Evaluator {
-            stack: Vec::new(),
+            stack: provenance::Stack::default(),
             resolver: ExecutableResolver::new(),
             last_exit_code: 0,
             definitions: HashMap::new(),
             cwd,
             home_dir: home,
             jobs: Vec::new(),
+            finished_jobs: Vec::new(),
             next_job_id: 1,
             pipestatus: Vec::new(),
+            pipe_tail: None,
+            pipe_consumers: Vec::new(),
+            pipe_streamed: 0,
+            pipefail: env::var("HSAB_PIPEFAIL")
+                .map(|v| crate::config::is_on(&v))
+                .unwrap_or(false),
+            calc_mode: false,
+            calc_ans: Value::Int(0),
             capture_mode: false,
+            capture_override: None,
+            tty_guard: tty_guard::TtyGuard::Ask,
+            stderr_sink: None,
+            output_tap: None,
+            #[cfg(unix)]
+            subst_streams: Vec::new(),
             dir_stack: Vec::new(),
             aliases: HashMap::new(),
             traps: HashMap::new(),
+            exit_requested: None,
             local_scopes: Vec::new(),
             local_values: Vec::new(),
             returning: false,
             last_if_taken: false,
             trace_mode: false,
+            log_level: log::LogLevel::from_env(),
+            log_format: log::LogFormat::from_env(),
             debug_mode: false,
             step_mode: false,
             breakpoints: std::collections::HashSet::new(),
+            conditional_breakpoints: Vec::new(),
+            def_breakpoints: std::collections::HashSet::new(),
+            watches: Vec::new(),
+            debug_frontend: None,
+            debug_terminate: false,
+            recorder: None,
+            script_location: None,
+            script_args: Vec::new(),
+            exec_policy: ExecPolicy::default(),
+            audit: None,
+            strict_mode: env::var("HSAB_STRICT")
+                .map(|v| crate::config::is_on(&v))
+                .unwrap_or(false),
+            restricted: false,
+            cancel: CancelToken::default(),
+            limits: Limits::default(),
+            fuel: None,
+            host_builtins: HashMap::new(),
+            interactive: false,
+            rm_trash: false,
+            confirm_glob: confirm::DEFAULT_CONFIRM_GLOB,
+            force_next: false,
+            refresh_next: false,
+            rate_buckets: HashMap::new(),
+            changed_outputs: HashMap::new(),
+            series: HashMap::new(),
+            fake_rng: None,
+            autocorrect: false,
+            module_paths: Vec::new(),
+            shared_defs: None,
             loaded_modules: std::collections::HashSet::new(),
             call_depth: 0,
-            max_call_depth: std::env::var("HSAB_MAX_RECURSION")
+            max_call_depth: env::var("HSAB_MAX_RECURSION")
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(10000),
+            output_encoding: env::var("HSAB_ENCODING")
+                .ok()
+                .and_then(|s| encoding::TextEncoding::from_label(&s))
+                .unwrap_or_default(),
             limbo: HashMap::new(),
-            preview_len: std::env::var("HSAB_PREVIEW_LEN")
+            preview_len: env::var("HSAB_PREVIEW_LEN")
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(8),
@@ -283,6 +550,8 @@ impl Evaluator {
             future_handles: HashMap::new(),
             futures: indexmap::IndexMap::new(),
             #[cfg(feature = "plugins")]
+            module_watches: Vec::new(),
+            #[cfg(feature = "plugins")]
             plugin_host,
             #[cfg(feature = "plugins")]
             shared_stack,
@@ -299,6 +568,12 @@ impl Evaluator {
         self.trace_mode = enabled;
     }
 
+    /// Mark this evaluator as the interactive shell's, enabling settings
+    /// meant only for a person at the prompt (such as `rm-trash`)
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
     // === Debugger control methods ===
 
     /// Enable or disable debug mode
@@ -337,6 +612,8 @@ impl Evaluator {
     /// Clear all breakpoints
     pub fn clear_breakpoints(&mut self) {
         self.breakpoints.clear();
+        self.conditional_breakpoints.clear();
+        self.def_breakpoints.clear();
     }
 
     /// Get all breakpoints
@@ -356,60 +633,17 @@ impl Evaluator {
     /// Convert an expression to a string for breakpoint matching
     pub(crate) fn expr_to_string(&self, expr: &Expr) -> String {
         match expr {
-            Expr::Literal(s) => s.clone(),
             Expr::Quoted { content, .. } => format!("\"{}\"", content),
             Expr::Variable(s) => format!("${}", s),
             Expr::Block(_) => "#[block]".to_string(),
             Expr::ArrayLiteral(_) => "[array]".to_string(),
-            Expr::Apply => "apply".to_string(),
-            Expr::Peek => "peek".to_string(),
-            Expr::PeekAll => "peek-all".to_string(),
-            Expr::Pipe => "|".to_string(),
-            Expr::Dup => "dup".to_string(),
-            Expr::Swap => "swap".to_string(),
-            Expr::Drop => "drop".to_string(),
-            Expr::Over => "over".to_string(),
-            Expr::Rot => "rot".to_string(),
-            Expr::Depth => "depth".to_string(),
-            Expr::Join => "path-join".to_string(),
-            Expr::Suffix => "suffix".to_string(),
-            Expr::Dirname => "dirname".to_string(),
-            Expr::Basename => "basename".to_string(),
-            Expr::Realpath => "path-resolve".to_string(),
-            Expr::Split1 => "split1".to_string(),
-            Expr::Rsplit1 => "rsplit1".to_string(),
-            Expr::Marker => "marker".to_string(),
-            Expr::Spread => "spread".to_string(),
-            Expr::Each => "each".to_string(),
-            Expr::Keep => "keep".to_string(),
-            Expr::Collect => "collect".to_string(),
-            Expr::Map => "map".to_string(),
-            Expr::Filter => "filter".to_string(),
-            Expr::If => "if".to_string(),
-            Expr::ElseIf => "elseif".to_string(),
-            Expr::Else => "else".to_string(),
-            Expr::Times => "times".to_string(),
-            Expr::While => "while".to_string(),
-            Expr::Until => "until".to_string(),
-            Expr::Break => "break".to_string(),
-            Expr::Parallel => "parallel".to_string(),
-            Expr::Fork => "fork".to_string(),
-            Expr::Subst => "subst".to_string(),
-            Expr::Fifo => "fifo".to_string(),
-            Expr::Json => "json".to_string(),
-            Expr::Unjson => "unjson".to_string(),
-            Expr::Timeout => "timeout".to_string(),
-            Expr::Pipestatus => "pipestatus".to_string(),
-            Expr::Import => ".import".to_string(),
-            Expr::Background => "&".to_string(),
+            Expr::RecordLiteral(_) => "{record}".to_string(),
             Expr::Define(name) => format!(":{}:", name),
             Expr::RedirectOut | Expr::RedirectAppend | Expr::RedirectIn => ">".to_string(),
             Expr::RedirectErr | Expr::RedirectErrAppend | Expr::RedirectBoth => "2>".to_string(),
-            Expr::RedirectErrToOut => "2>&1".to_string(),
-            Expr::And => "&&".to_string(),
-            Expr::Or => "||".to_string(),
             Expr::ScopedBlock { .. } => "(...)".to_string(),
             Expr::LimboRef(id) => format!("`{}`", id),
+            other => other.to_source(),
         }
     }
 
@@ -449,6 +683,7 @@ impl Evaluator {
                     Value::Nil => "nil".to_string(),
                     Value::Marker => "|marker|".to_string(),
                     Value::Error { message, .. } => format!("Error:{}", message),
+                    Value::Status(code) => format!("status:{}", code),
                     Value::Media { data, .. } => format!("<media:{}B>", data.len()),
                     Value::Link { url, .. } => {
                         format!("<link:{}>", if url.len() > 20 { &url[..20] } else { url })
@@ -545,7 +780,7 @@ impl Evaluator {
 
     /// Restore stack from a saved state
     pub fn restore_stack(&mut self, stack: Vec<Value>) {
-        self.stack = stack;
+        self.stack = stack.into();
     }
 
     /// Get the last exit code
@@ -558,6 +793,16 @@ impl Evaluator {
         self.last_exit_code = code;
     }
 
+    /// The code to end the shell with, once an eval has ended in `exit`
+    pub fn exit_requested(&self) -> Option<i32> {
+        self.exit_requested
+    }
+
+    /// Take the EXIT trap's block, to run as the shell ends
+    pub fn take_exit_trap(&mut self) -> Option<Program> {
+        self.traps.remove(&0).map(Program::new)
+    }
+
     /// Get the number of background jobs
     pub fn job_count(&self) -> usize {
         self.jobs.len()
@@ -584,6 +829,37 @@ impl Evaluator {
         self.limbo.clear();
     }
 
+    /// Put the evaluator back in order after an eval panicked partway: the
+    /// stack as it was before, and nothing a half-run definition, capture,
+    /// or one-shot override left behind
+    pub fn recover_from_panic(&mut self, stack: Vec<Value>) {
+        self.stack = stack.into();
+        while let Some(scope) = self.local_scopes.pop() {
+            for (name, original) in scope {
+                match original {
+                    Some(value) => env::set_var(&name, value),
+                    None => env::remove_var(&name),
+                }
+            }
+        }
+        self.local_values.clear();
+        self.returning = false;
+        self.call_depth = 0;
+        self.capture_mode = false;
+        self.capture_override = None;
+        self.force_next = false;
+        self.refresh_next = false;
+        self.stderr_sink = None;
+        self.pipe_tail = None;
+        self.pipe_consumers.clear();
+        self.pipe_streamed = 0;
+        self.fuel = None;
+        self.pending_statement_spans.clear();
+        self.current_span = None;
+        self.close_substs();
+        self.last_exit_code = 1;
+    }
+
     /// Format a limbo reference with type and preview annotations
     pub fn format_limbo_ref(&self, id: &str, value: &Value) -> String {
         let formatted = self.format_limbo_preview(value);
@@ -658,6 +934,7 @@ impl Evaluator {
                 }
             }
             Value::Error { kind, .. } => format!("error:{}", kind),
+            Value::Status(code) => format!("status:{}", code),
             Value::Future { id, state } => {
                 use crate::ast::FutureState;
                 let guard = lock_or_recover(state);
@@ -706,7 +983,7 @@ impl Evaluator {
             }
         }
         // Fall back to environment variables
-        std::env::var(var_name).ok()
+        env::var(var_name).ok()
     }
 
     /// Evaluate a program
@@ -726,7 +1003,28 @@ impl Evaluator {
     }
 
     pub fn eval(&mut self, program: &Program) -> Result<EvalResult, EvalError> {
-        self.eval_exprs(&program.expressions)?;
+        self.refuel();
+        // A cancel that lands after the last expression still counts
+        let result = self
+            .eval_exprs(&program.expressions)
+            .and_then(|()| self.check_cancelled());
+        self.fuel = None;
+        // An override with no command after it doesn't carry to the next line
+        self.capture_override = None;
+        self.force_next = false;
+        self.refresh_next = false;
+        self.close_substs();
+        if let Err(EvalError::Cancelled) = result {
+            // This eval was the one cancelled; the next starts afresh
+            self.cancel.reset();
+        }
+        if let Err(EvalError::Exit(code)) = result {
+            self.exit_requested = Some(code);
+        }
+        result?;
+        if self.calc_mode {
+            self.remember_ans();
+        }
 
         // Collect output from stack
         let output = self
@@ -739,7 +1037,7 @@ impl Evaluator {
         Ok(EvalResult {
             output,
             exit_code: self.last_exit_code,
-            stack: self.stack.clone(),
+            stack: self.stack.to_vec(),
         })
     }
 
@@ -749,70 +1047,27 @@ impl Evaluator {
         // program invocation via eval_with_spans; nested calls see an empty
         // vec and inherit current_span from the enclosing statement.
         let stmt_spans = std::mem::take(&mut self.pending_statement_spans);
+        // The consumer block and `|` of a pipe whose command streamed
+        let mut skip = 0;
         for (i, expr) in exprs.iter().enumerate() {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
             if let Some(span) = stmt_spans.get(i) {
                 self.current_span = Some(*span);
             }
             // Debug mode: check for breakpoints and step mode
             if self.debug_mode {
-                let should_pause = self.step_mode || self.matches_breakpoint(expr);
+                let should_pause = self.step_mode
+                    || self.matches_breakpoint(expr)
+                    || self.matches_conditional_breakpoint();
                 if should_pause {
-                    // Show debug state
-                    eprintln!("{}", self.format_debug_state(expr));
-                    eprintln!("\x1b[90m(n)ext, (c)ontinue, (s)tack, (q)uit debug: \x1b[0m");
-
-                    // Read debug command from stdin
-                    loop {
-                        let mut input = String::new();
-                        if std::io::stdin().read_line(&mut input).is_ok() {
-                            let cmd = input.trim().to_lowercase();
-                            match cmd.as_str() {
-                                "n" | "next" | "" => {
-                                    // Step to next expression
-                                    self.step_mode = true;
-                                    break;
-                                }
-                                "c" | "continue" => {
-                                    // Continue until next breakpoint
-                                    self.step_mode = false;
-                                    break;
-                                }
-                                "s" | "stack" => {
-                                    // Show full stack
-                                    eprintln!("\x1b[33mStack ({} items):\x1b[0m", self.stack.len());
-                                    for (idx, val) in self.stack.iter().enumerate() {
-                                        eprintln!("  {}. {:?}", idx, val);
-                                    }
-                                    eprintln!("\x1b[90m(n)ext, (c)ontinue, (s)tack, (q)uit debug: \x1b[0m");
-                                }
-                                "q" | "quit" => {
-                                    // Quit debug mode
-                                    self.debug_mode = false;
-                                    self.step_mode = false;
-                                    eprintln!("\x1b[33mDebug mode disabled\x1b[0m");
-                                    break;
-                                }
-                                "b" | "breakpoints" => {
-                                    // List breakpoints
-                                    if self.breakpoints.is_empty() {
-                                        eprintln!("\x1b[33mNo breakpoints set\x1b[0m");
-                                    } else {
-                                        eprintln!("\x1b[33mBreakpoints:\x1b[0m");
-                                        for bp in &self.breakpoints {
-                                            eprintln!("  - {}", bp);
-                                        }
-                                    }
-                                    eprintln!("\x1b[90m(n)ext, (c)ontinue, (s)tack, (q)uit debug: \x1b[0m");
-                                }
-                                _ => {
-                                    eprintln!("\x1b[31mUnknown command: {}\x1b[0m", cmd);
-                                    eprintln!("\x1b[90m(n)ext, (c)ontinue, (s)tack, (q)uit debug: \x1b[0m");
-                                }
-                            }
-                        } else {
-                            break;
-                        }
-                    }
+                    let reason = if self.step_mode { "step" } else { "breakpoint" };
+                    self.debug_pause(reason, expr);
+                }
+                if self.debug_terminate {
+                    return Err(EvalError::ExecError("terminated by debugger".into()));
                 }
             }
 
@@ -821,12 +1076,37 @@ impl Evaluator {
             let remaining = &exprs[i + 1..];
             self.capture_mode = self.should_capture(remaining);
 
-            match self.eval_expr(expr) {
+            // Trace/debug mode: keep the top of the stack so a replaced
+            // structured value can be shown as a diff
+            let before = (self.trace_mode || self.debug_mode).then(|| self.stack_tail_snapshot());
+
+            // `cmd #[a] | #[b] |`: a program `cmd` runs can stream into them
+            self.pipe_consumers = match expr {
+                Expr::Literal(_) => remaining
+                    .chunks(2)
+                    .map_while(|pair| match pair {
+                        [Expr::Block(consumer), Expr::Pipe] => Some(consumer.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let result = self.eval_expr(expr);
+            self.pipe_consumers.clear();
+            skip = 2 * std::mem::take(&mut self.pipe_streamed);
+            if self.recorder.is_some() {
+                self.record_step(expr, &result);
+            }
+            match result {
                 Ok(()) => {
                     // Trace mode: print expression and stack state
                     if self.trace_mode {
                         self.print_trace(expr);
                     }
+                    if let Some((len, tail)) = before {
+                        self.print_structured_diff(len, &tail);
+                        self.print_watches();
+                    }
                 }
                 Err(EvalError::BreakLoop) => {
                     return Err(self.attach_span(EvalError::BreakOutsideLoop))
@@ -840,7 +1120,7 @@ impl Evaluator {
     /// Annotate an error with the current statement span, if known.
     /// Control-flow sentinels and already-annotated errors pass through.
     pub(crate) fn attach_span(&self, err: EvalError) -> EvalError {
-        if matches!(err, EvalError::BreakLoop | EvalError::At { .. }) {
+        if err.is_fatal() || matches!(err, EvalError::BreakLoop | EvalError::At { .. }) {
             return err;
         }
         match self.current_span {
@@ -853,6 +1133,32 @@ impl Evaluator {
         }
     }
 
+    /// Stack length plus a copy of the top few values, for diffing
+    fn stack_tail_snapshot(&self) -> (usize, Vec<Value>) {
+        let start = self.stack.len().saturating_sub(4);
+        (self.stack.len(), self.stack[start..].to_vec())
+    }
+
+    /// If the expression just evaluated consumed a structured value and pushed
+    /// a changed one of the same kind (`set`, `merge`, ...), print a diff
+    fn print_structured_diff(&self, old_len: usize, old_tail: &[Value]) {
+        let Some(new_top) = self.stack.last() else {
+            return;
+        };
+        // Values at or above the new top's position were consumed
+        let tail_start = old_len - old_tail.len();
+        let consumed_from = (self.stack.len() - 1).max(tail_start) - tail_start;
+        let Some(old) = old_tail
+            .get(consumed_from..)
+            .and_then(|vals| vals.iter().find(|v| v.type_name() == new_top.type_name()))
+        else {
+            return;
+        };
+        if let Some(diff) = crate::display::format_structured_diff(old, new_top) {
+            self.log_trace(&format!("    \x1b[90mΔ\x1b[0m {}", diff));
+        }
+    }
+
     /// Print trace output showing expression and stack state
     pub(crate) fn print_trace(&self, expr: &Expr) {
         // Format expression
@@ -862,6 +1168,7 @@ impl Evaluator {
             Expr::Variable(s) => s.clone(),
             Expr::Block(_) => "#[...]".to_string(),
             Expr::ArrayLiteral(_) => "[...]".to_string(),
+            Expr::RecordLiteral(_) => "{ ... }".to_string(),
             Expr::Apply => "apply".to_string(),
             Expr::Peek => "peek".to_string(),
             Expr::PeekAll => "peek-all".to_string(),
@@ -900,6 +1207,7 @@ impl Evaluator {
                 Value::Nil => "nil".to_string(),
                 Value::Marker => "|".to_string(),
                 Value::Error { .. } => "Error".to_string(),
+                Value::Status(code) => format!("?{}", code),
                 Value::Media { data, .. } => format!("<img:{}B>", data.len()),
                 Value::Link { .. } => "<link>".to_string(),
                 Value::Bytes(data) => format!("<bytes:{}B>", data.len()),
@@ -921,7 +1229,7 @@ impl Evaluator {
             stack_items.into_iter().rev().collect::<Vec<_>>().join(" ")
         };
 
-        eprintln!("\x1b[90m>>> {} │ {}\x1b[0m", expr_str, stack_str);
+        self.log_trace(&format!("\x1b[90m>>> {} │ {}\x1b[0m", expr_str, stack_str));
     }
 
     /// Determine if output should be captured based on what comes next
@@ -934,6 +1242,7 @@ impl Evaluator {
                 // These consume stack values
                 Expr::Pipe => true,
                 Expr::RedirectOut | Expr::RedirectAppend | Expr::RedirectIn => true,
+                Expr::HereString => true,
                 Expr::RedirectErr | Expr::RedirectErrAppend | Expr::RedirectBoth => true,
                 Expr::And | Expr::Or => true,
                 Expr::Apply => true,
@@ -972,6 +1281,9 @@ impl Evaluator {
                 Expr::Background => true,
                 Expr::Define(_) => true,
 
+                // Words with variables inside are values, like variables
+                Expr::Literal(s) if s.contains('$') => self.should_capture(&remaining[1..]),
+
                 // Literals: if it's an executable, it will consume args
                 Expr::Literal(s) => {
                     self.definitions.contains_key(s)
@@ -991,6 +1303,7 @@ impl Evaluator {
 
                 // Array literals evaluate to a list, look past them
                 Expr::ArrayLiteral(_) => self.should_capture(&remaining[1..]),
+                Expr::RecordLiteral(_) => self.should_capture(&remaining[1..]),
 
                 // Break doesn't consume
                 Expr::Break => false,
@@ -1013,8 +1326,20 @@ impl Evaluator {
 
     /// Evaluate a single expression
     pub(crate) fn eval_expr(&mut self, expr: &Expr) -> Result<(), EvalError> {
+        self.check_cancelled()?;
+        self.burn_fuel()?;
         match expr {
-            Expr::Literal(s) => {
+            Expr::Literal(word) => {
+                // Variables inside the word ($HOME/bin) expand first
+                let expanded;
+                let s = if word.contains('$') {
+                    expanded = self.expand_word_vars(word)?;
+                    &expanded
+                } else {
+                    word
+                };
+                // Only a program this word runs itself streams into a pipe
+                let stream_to = std::mem::take(&mut self.pipe_consumers);
                 // Check if it's a user-defined word first
                 if let Some(body) = self.definitions.get(s).cloned() {
                     // Check recursion limit before executing
@@ -1025,6 +1350,7 @@ impl Evaluator {
                         ));
                     }
                     self.call_depth += 1;
+                    self.debug_definition_event(s, true);
 
                     // Execute the defined word's body with local scope support
                     self.local_scopes.push(HashMap::new());
@@ -1046,8 +1372,8 @@ impl Evaluator {
                     if let Some(scope) = self.local_scopes.pop() {
                         for (name, original) in scope {
                             match original {
-                                Some(value) => std::env::set_var(&name, value),
-                                None => std::env::remove_var(&name),
+                                Some(value) => env::set_var(&name, value),
+                                None => env::remove_var(&name),
                             }
                         }
                     }
@@ -1056,6 +1382,7 @@ impl Evaluator {
 
                     // Decrement call depth after execution
                     self.call_depth -= 1;
+                    self.debug_definition_event(s, false);
 
                     // Return any error that occurred during execution
                     exec_result?;
@@ -1073,17 +1400,33 @@ impl Evaluator {
                     // Special literal: expands to clipboard contents (like $VAR but for clipboard)
                     let clipboard_value = self.query_clipboard()?;
                     self.stack.push(Value::Literal(clipboard_value));
+                } else if self.calc_mode && self.push_calc_word(s) {
+                    // Calculator mode: ans, or a number with units
+                } else if self.exec_policy.denies(s) {
+                    // Denied words fail whether they name a builtin or a command
+                    return Err(policy::policy_error(s));
+                } else if let Err(e) = self.check_restricted_word(s) {
+                    return Err(e);
+                } else if let Err(e) = self.check_file_writer(s) {
+                    return Err(e);
+                } else if self.uses_external(s) {
+                    // Opted out of the builtin in favor of the PATH command
+                    self.pipe_consumers = stream_to;
+                    self.execute_external(s)?;
                 } else if self.try_structured_builtin(s)? {
                     // Handled as structured data builtin (typeof, record, get, etc.)
                 } else if self.try_plugin_command_if_enabled(s)? {
                     // Handled as plugin command
+                } else if self.try_host_builtin(s)? {
+                    // Handled by a function the embedder registered
                 } else if self.resolver.is_executable(s) {
                     // Check if it's an executable
+                    self.pipe_consumers = stream_to;
                     self.execute_command(s)?;
                 } else {
                     // Push as literal; bare numeric words become typed
                     // numbers (Int/Number) per issue #24
-                    self.stack.push(Value::from_literal_word(s));
+                    self.push_from(Value::from_literal_word(s), Origin::Typed);
                 }
             }
 
@@ -1091,11 +1434,11 @@ impl Evaluator {
                 // Push the content without surrounding quotes - quotes are just delimiters
                 // Double-quoted strings support variable interpolation
                 let result = if *double {
-                    self.interpolate_string(content)
+                    self.interpolate_string(content)?
                 } else {
                     content.clone()
                 };
-                self.stack.push(Value::Literal(result));
+                self.push_from(Value::Literal(result), Origin::Typed);
             }
 
             Expr::Variable(s) => {
@@ -1117,8 +1460,14 @@ impl Evaluator {
 
                 // Fall back to environment variables
                 if !found {
-                    match std::env::var(var_name) {
+                    match env::var(var_name) {
                         Ok(value) => self.stack.push(Value::Literal(value)),
+                        Err(_) if self.strict_mode => {
+                            return Err(EvalError::ExecError(format!(
+                                "${} is not set (strict mode)",
+                                var_name
+                            )));
+                        }
                         Err(_) => self.stack.push(Value::Literal(String::new())),
                     }
                 }
@@ -1141,6 +1490,36 @@ impl Evaluator {
                 self.stack.push(Value::List(items));
             }
 
+            Expr::RecordLiteral(inner) => {
+                // Alternating key/value pairs; bare-word keys are taken
+                // literally, values are evaluated like array items
+                if inner.len() % 2 != 0 {
+                    return Err(EvalError::ExecError(
+                        "record literal: expected key/value pairs".into(),
+                    ));
+                }
+                let mut map = indexmap::IndexMap::new();
+                for pair in inner.chunks(2) {
+                    let key = match &pair[0] {
+                        Expr::Literal(s) => s.clone(),
+                        expr => {
+                            self.eval_expr(expr)?;
+                            let val = self.stack.pop().ok_or_else(|| {
+                                EvalError::ExecError("record literal: key produced no value".into())
+                            })?;
+                            val.as_arg().ok_or_else(|| EvalError::TypeError {
+                                expected: "string key".into(),
+                                got: val.type_name().to_string(),
+                            })?
+                        }
+                    };
+                    self.eval_expr(&pair[1])?;
+                    let val = self.stack.pop().unwrap_or(Value::Nil);
+                    map.insert(key, val);
+                }
+                self.stack.push(Value::Map(map));
+            }
+
             Expr::Apply => {
                 self.apply_block()?;
             }
@@ -1169,6 +1548,10 @@ impl Evaluator {
                 self.execute_redirect("<")?;
             }
 
+            Expr::HereString => {
+                self.execute_here_string()?;
+            }
+
             Expr::RedirectErr => {
                 self.execute_redirect_err("2>")?;
             }
@@ -1209,7 +1592,11 @@ impl Evaluator {
             // Path operations
             Expr::Join => self.path_join()?,
             Expr::Suffix => self.path_suffix()?,
+            Expr::Dirname if self.uses_external("dirname") => self.execute_external("dirname")?,
             Expr::Dirname => self.path_dirname()?,
+            Expr::Basename if self.uses_external("basename") => {
+                self.execute_external("basename")?
+            }
             Expr::Basename => self.path_basename()?,
             Expr::Realpath => self.path_realpath()?,
 
@@ -1295,13 +1682,16 @@ impl Evaluator {
         let mut saved_vars: Vec<(String, Option<String>)> = Vec::new();
 
         for (name, _) in assignments {
-            let current = std::env::var(name).ok();
+            self.check_env_write(name)?;
+        }
+        for (name, _) in assignments {
+            let current = env::var(name).ok();
             saved_vars.push((name.clone(), current));
         }
 
         // Set the new variable values
         for (name, value) in assignments {
-            std::env::set_var(name, value);
+            env::set_var(name, value);
         }
 
         // Execute the body
@@ -1310,8 +1700,8 @@ impl Evaluator {
         // Restore/unset variables
         for (name, original) in saved_vars {
             match original {
-                Some(value) => std::env::set_var(&name, value),
-                None => std::env::remove_var(&name),
+                Some(value) => env::set_var(&name, value),
+                None => env::remove_var(&name),
             }
         }
 
@@ -0,0 +1,216 @@
+//! `.import-watch`: re-import a module whenever its file changes
+//!
+//! `"mylib.hsab" .import-watch` imports the module as `.import` does, then
+//! watches its file. After the file is saved, the REPL loads it again before
+//! the next line runs and says what changed:
+//!
+//! ```text
+//! ↻ mylib.hsab: +mylib::triple ~mylib::double -mylib::half
+//! ```
+//!
+//! A reload replaces the namespace's words all at once. If the module no
+//! longer parses, or fails while loading, the old words stay as they were.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use super::{EvalError, Evaluator};
+
+/// What reloading a watched module changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleReload {
+    /// The module's file
+    pub path: PathBuf,
+    /// Words the module defines now and didn't before
+    pub added: Vec<String>,
+    /// Words whose bodies changed
+    pub changed: Vec<String>,
+    /// Words the module no longer defines
+    pub removed: Vec<String>,
+    /// Why the module couldn't be loaded (its old words were kept)
+    pub error: Option<String>,
+}
+
+impl fmt::Display for ModuleReload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string());
+        if let Some(error) = &self.error {
+            return write!(
+                f,
+                "{}: not reloaded, keeping the old words: {}",
+                name, error
+            );
+        }
+        let marked = |mark: char, words: &[String]| {
+            words
+                .iter()
+                .map(move |w| format!("{}{}", mark, w))
+                .collect::<Vec<_>>()
+        };
+        let mut changes = marked('+', &self.added);
+        changes.extend(marked('~', &self.changed));
+        changes.extend(marked('-', &self.removed));
+        if changes.is_empty() {
+            write!(f, "{}: no words changed", name)
+        } else {
+            write!(f, "{}: {}", name, changes.join(" "))
+        }
+    }
+}
+
+#[cfg(feature = "plugins")]
+mod watch_impl {
+    use super::*;
+    use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+    use std::sync::mpsc::{channel, Receiver};
+
+    /// A module imported with `.import-watch`
+    pub(crate) struct ModuleWatch {
+        /// The module's file (canonical)
+        path: PathBuf,
+        namespace: String,
+        /// The namespaced words it defined when last loaded
+        words: Vec<String>,
+        rx: Receiver<notify::Result<Event>>,
+        _watcher: RecommendedWatcher,
+    }
+
+    impl ModuleWatch {
+        /// Whether the file was written since the last check
+        fn changed(&self) -> bool {
+            let mut changed = false;
+            for event in self.rx.try_iter().flatten() {
+                if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == self.path.file_name())
+                {
+                    changed = true;
+                }
+            }
+            changed
+        }
+    }
+
+    impl Evaluator {
+        /// .import-watch: "path" [alias] .import-watch
+        /// Import a module and reload it whenever its file changes
+        pub(crate) fn builtin_import_watch(&mut self) -> Result<(), EvalError> {
+            let (resolved_path, namespace) = self.pop_module_spec()?;
+            let path = resolved_path
+                .canonicalize()
+                .unwrap_or_else(|_| resolved_path.clone());
+            if self.module_watches.iter().any(|w| w.path == path) {
+                self.last_exit_code = 0;
+                return Ok(());
+            }
+
+            // Editors often save by replacing the file, so watch its directory
+            let (tx, rx) = channel();
+            let mut watcher = RecommendedWatcher::new(
+                move |res| {
+                    let _ = tx.send(res);
+                },
+                Config::default(),
+            )
+            .map_err(|e| EvalError::ExecError(format!("import-watch: {}", e)))?;
+            let dir = path.parent().unwrap_or(&path);
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|e| {
+                    EvalError::ExecError(format!("import-watch: {}: {}", dir.display(), e))
+                })?;
+
+            self.loaded_modules.insert(path.clone());
+            let words = self.load_module(&path, &namespace)?;
+            self.module_watches.push(ModuleWatch {
+                path,
+                namespace,
+                words,
+                rx,
+                _watcher: watcher,
+            });
+            self.last_exit_code = 0;
+            Ok(())
+        }
+
+        /// Reload the watched modules whose files changed since the last
+        /// poll (checked before each line the REPL runs)
+        pub fn poll_module_reloads(&mut self) -> Vec<ModuleReload> {
+            let mut watches = std::mem::take(&mut self.module_watches);
+            let reloads = watches
+                .iter_mut()
+                .filter(|watch| watch.changed())
+                .map(|watch| self.reload_module(watch))
+                .collect();
+            // A reloaded module may have started watching another
+            watches.append(&mut self.module_watches);
+            self.module_watches = watches;
+            reloads
+        }
+
+        /// Load a watched module again on a stack of its own, swapping its
+        /// words for the new ones only if it loads cleanly
+        fn reload_module(&mut self, watch: &mut ModuleWatch) -> ModuleReload {
+            let before = self.definitions.clone();
+            let saved_stack = std::mem::take(&mut self.stack);
+            let saved_exit = self.last_exit_code;
+            let outcome = self.load_module(&watch.path, &watch.namespace);
+            self.stack = saved_stack;
+            self.last_exit_code = saved_exit;
+
+            let mut reload = ModuleReload {
+                path: watch.path.clone(),
+                ..ModuleReload::default()
+            };
+            let words = match outcome {
+                Ok(words) => words,
+                Err(e) => {
+                    self.definitions = before;
+                    reload.error = Some(e.to_string());
+                    return reload;
+                }
+            };
+
+            for old in &watch.words {
+                if !words.contains(old) {
+                    self.definitions.remove(old);
+                    reload.removed.push(old.clone());
+                }
+            }
+            for word in &words {
+                if !watch.words.contains(word) {
+                    reload.added.push(word.clone());
+                } else if before.get(word) != self.definitions.get(word) {
+                    reload.changed.push(word.clone());
+                }
+            }
+            watch.words = words;
+            reload
+        }
+    }
+}
+
+#[cfg(feature = "plugins")]
+pub(crate) use watch_impl::ModuleWatch;
+
+#[cfg(not(feature = "plugins"))]
+impl Evaluator {
+    /// .import-watch: requires plugins feature
+    pub(crate) fn builtin_import_watch(&mut self) -> Result<(), EvalError> {
+        Err(EvalError::ExecError(
+            "import-watch: requires 'plugins' feature (notify crate)".into(),
+        ))
+    }
+
+    /// Reload the watched modules whose files changed (none without the
+    /// plugins feature)
+    pub fn poll_module_reloads(&mut self) -> Vec<ModuleReload> {
+        Vec::new()
+    }
+}
@@ -1,10 +1,34 @@
 use super::{EvalError, Evaluator};
 use crate::ast::Expr;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 impl Evaluator {
     pub(crate) fn module_import(&mut self) -> Result<(), EvalError> {
+        let (resolved_path, namespace) = self.pop_module_spec()?;
+
+        // Get canonical path for tracking
+        let canonical = resolved_path
+            .canonicalize()
+            .unwrap_or_else(|_| resolved_path.clone());
+
+        // Skip if already loaded
+        if self.loaded_modules.contains(&canonical) {
+            self.last_exit_code = 0;
+            return Ok(());
+        }
+
+        // Mark as loaded before executing (handles circular imports)
+        self.loaded_modules.insert(canonical);
+
+        self.load_module(&resolved_path, &namespace)?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Pop a module path (or a path and an alias) and resolve it to the
+    /// module's file and namespace
+    pub(crate) fn pop_module_spec(&mut self) -> Result<(PathBuf, String), EvalError> {
         // Pop the top value - could be path or alias
         let top = self.pop_string()?;
 
@@ -21,20 +45,6 @@ impl Evaluator {
         // Resolve module path using search paths
         let resolved_path = self.resolve_module_path(&path_str)?;
 
-        // Get canonical path for tracking
-        let canonical = resolved_path
-            .canonicalize()
-            .unwrap_or_else(|_| resolved_path.clone());
-
-        // Skip if already loaded
-        if self.loaded_modules.contains(&canonical) {
-            self.last_exit_code = 0;
-            return Ok(());
-        }
-
-        // Mark as loaded before executing (handles circular imports)
-        self.loaded_modules.insert(canonical);
-
         // Determine namespace from filename or alias
         let namespace = match alias {
             Some(a) => a,
@@ -48,16 +58,25 @@ impl Evaluator {
             }
         };
 
+        Ok((resolved_path, namespace))
+    }
+
+    /// Run a module's file and move the words it defines under `namespace`,
+    /// returning their namespaced names
+    pub(crate) fn load_module(
+        &mut self,
+        path: &Path,
+        namespace: &str,
+    ) -> Result<Vec<String>, EvalError> {
         // Read and parse the module
-        let content = std::fs::read_to_string(&resolved_path)
-            .map_err(|e| EvalError::ExecError(format!("import: {}: {}", path_str, e)))?;
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| EvalError::ExecError(format!("import: {}: {}", path.display(), e)))?;
 
         let tokens = crate::lex(&content)
             .map_err(|e| EvalError::ExecError(format!("import: parse error: {}", e)))?;
 
         if tokens.is_empty() {
-            self.last_exit_code = 0;
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         let program = crate::parse(tokens)
@@ -85,6 +104,7 @@ impl Evaluator {
             .map(|(name, _)| name.clone())
             .collect();
 
+        let mut words = Vec::new();
         for name in module_defs {
             // Skip private definitions (underscore prefix)
             if name.starts_with('_') {
@@ -96,6 +116,7 @@ impl Evaluator {
             if let Some(block) = self.definitions.remove(&name) {
                 let namespaced = format!("{}::{}", namespace, name);
                 self.definitions.insert(namespaced.clone(), block);
+                words.push(namespaced);
 
                 // Restore the original definition if it existed
                 if let Some(original) = before_defs.get(&name) {
@@ -104,12 +125,17 @@ impl Evaluator {
             }
         }
 
-        self.last_exit_code = 0;
-        Ok(())
+        words.sort();
+        Ok(words)
+    }
+
+    /// Replace the extra module search directories (hsab.toml `module_path`)
+    pub fn set_module_paths(&mut self, paths: Vec<PathBuf>) {
+        self.module_paths = paths;
     }
 
     /// Resolve module path using search paths
-    /// Search order: . -> ./lib/ -> ~/.hsab/lib/ -> $HSAB_PATH
+    /// Search order: . -> ./lib/ -> workspace module_path -> ~/.hsab/lib/ -> $HSAB_PATH
     pub(crate) fn resolve_module_path(&self, path_str: &str) -> Result<PathBuf, EvalError> {
         let path = PathBuf::from(path_str);
 
@@ -129,14 +155,15 @@ impl Evaluator {
             self.cwd.clone(),     // Current directory
             self.cwd.join("lib"), // ./lib/
         ];
+        search_paths.extend(self.module_paths.iter().cloned());
 
         // Add ~/.hsab/lib/
-        if let Ok(home) = std::env::var("HOME") {
+        if let Ok(home) = super::env::var("HOME") {
             search_paths.push(PathBuf::from(home).join(".hsab").join("lib"));
         }
 
         // Add HSAB_PATH directories
-        if let Ok(hsab_path) = std::env::var("HSAB_PATH") {
+        if let Ok(hsab_path) = super::env::var("HSAB_PATH") {
             for dir in hsab_path.split(':') {
                 if !dir.is_empty() {
                     search_paths.push(PathBuf::from(dir));
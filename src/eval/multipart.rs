@@ -0,0 +1,217 @@
+//! multipart/form-data uploads
+//!
+//! `"file" "photo.png" file-part` and `"title" "Sunset" text-part` make the
+//! parts of a form, as records (`{ name filename path type }` and `{ name
+//! value }`), and `[parts] URL http-post-multipart` sends them the way a
+//! browser submits a form with a file input:
+//!
+//! ```text
+//! marker "file" "photo.png" file-part "title" "Sunset" text-part collect
+//!     "https://example.com/upload" http-post-multipart
+//! ```
+//!
+//! A file part's type comes from its extension (`application/octet-stream`
+//! if it's not one we know); set `type` on the record to override it. Files
+//! are read when the form is sent. A record of headers after the URL is
+//! sent with the request, and the response is pushed as `fetch` pushes it.
+
+use indexmap::IndexMap;
+use std::path::Path;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// The MIME type for a file name's extension
+pub(crate) fn mime_type_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "js" | "mjs" => "text/javascript",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A name or file name as a quoted Content-Disposition parameter
+fn quoted(text: &str) -> String {
+    let escaped = text
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    format!("\"{}\"", escaped)
+}
+
+/// A form's parts, each its headers and contents
+fn read_parts(parts: &[Value]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut read = Vec::with_capacity(parts.len());
+    for part in parts {
+        let Value::Map(part) = part else {
+            return Err(format!(
+                "a part is a record from file-part or text-part, not a {}",
+                part.type_name()
+            ));
+        };
+        let field = |key: &str| part.get(key).and_then(Value::as_arg);
+        let name = field("name").ok_or("a part has no name")?;
+        match field("path") {
+            Some(path) => {
+                let contents = std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let filename = field("filename").unwrap_or_else(|| {
+                    Path::new(&path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                });
+                let content_type = field("type")
+                    .unwrap_or_else(|| mime_type_for(Path::new(&filename)).to_string());
+                let headers = format!(
+                    "Content-Disposition: form-data; name={}; filename={}\r\nContent-Type: {}\r\n",
+                    quoted(&name),
+                    quoted(&filename),
+                    content_type
+                );
+                read.push((headers, contents));
+            }
+            None => {
+                let value = field("value").unwrap_or_default();
+                let headers = format!("Content-Disposition: form-data; name={}\r\n", quoted(&name));
+                read.push((headers, value.into_bytes()));
+            }
+        }
+    }
+    Ok(read)
+}
+
+/// A boundary that appears in none of the parts
+fn boundary_for(parts: &[(String, Vec<u8>)]) -> String {
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+        ^ u128::from(std::process::id());
+    (0u32..)
+        .map(|n| format!("hsab-boundary-{:x}{:x}", seed, n))
+        .find(|boundary| {
+            !parts.iter().any(|(_, contents)| {
+                contents
+                    .windows(boundary.len())
+                    .any(|w| w == boundary.as_bytes())
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// The body of a multipart/form-data request, and its boundary
+pub(crate) fn multipart_body(parts: &[Value]) -> Result<(Vec<u8>, String), String> {
+    let parts = read_parts(parts)?;
+    let boundary = boundary_for(&parts);
+    let mut body = Vec::new();
+    for (headers, contents) in &parts {
+        body.extend_from_slice(format!("--{}\r\n{}\r\n", boundary, headers).as_bytes());
+        body.extend_from_slice(contents);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    Ok((body, boundary))
+}
+
+impl Evaluator {
+    /// file-part: "name" "path" file-part → { name filename path type }
+    pub(crate) fn builtin_file_part(&mut self) -> Result<(), EvalError> {
+        let path = self.pop_string()?;
+        let name = self.pop_string()?;
+        let full = self.cwd.join(self.expand_tilde(&path));
+        let filename = full
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+
+        let mut part = IndexMap::new();
+        part.insert("name".to_string(), Value::Literal(name));
+        part.insert(
+            "type".to_string(),
+            Value::Literal(mime_type_for(&full).to_string()),
+        );
+        part.insert("filename".to_string(), Value::Literal(filename));
+        part.insert(
+            "path".to_string(),
+            Value::Literal(full.to_string_lossy().into_owned()),
+        );
+        self.stack.push(Value::Map(part));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// text-part: "name" "value" text-part → { name value }
+    pub(crate) fn builtin_text_part(&mut self) -> Result<(), EvalError> {
+        let value = self.pop_string()?;
+        let name = self.pop_string()?;
+        let mut part = IndexMap::new();
+        part.insert("name".to_string(), Value::Literal(name));
+        part.insert("value".to_string(), Value::Literal(value));
+        self.stack.push(Value::Map(part));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// http-post-multipart: [parts] URL [{headers}] http-post-multipart → response
+    /// POST a multipart/form-data form
+    pub(crate) fn builtin_http_post_multipart(&mut self) -> Result<(), EvalError> {
+        let fail =
+            |e: &dyn std::fmt::Display| EvalError::ExecError(format!("http-post-multipart: {}", e));
+        let mut headers = std::collections::HashMap::new();
+        if let Some(Value::Map(_)) = self.stack.last() {
+            if let Some(Value::Map(given)) = self.stack.pop() {
+                for (key, value) in given {
+                    if let Some(value) = value.as_arg() {
+                        headers.insert(key, value);
+                    }
+                }
+            }
+        }
+        let url = self.pop_string()?;
+        let parts = match self.pop_value_or_err()? {
+            Value::List(parts) => parts,
+            part @ Value::Map(_) => vec![part],
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "list of parts".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+
+        let (body, boundary) = multipart_body(&parts).map_err(|e| fail(&e))?;
+        let content_type = format!("multipart/form-data; boundary={}", boundary);
+        let response = self.do_http_send("POST", &url, &content_type, &body, &headers)?;
+        self.push_http_response(response);
+        Ok(())
+    }
+}
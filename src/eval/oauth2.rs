@@ -0,0 +1,368 @@
+//! `oauth2-token`: get an OAuth2 access token, and keep it until it expires
+//!
+//! ```text
+//! { token_url "https://auth.example.com/token" client_id "id" client_secret "s" }
+//!     oauth2-token → "eyJhbGciOi..."
+//! ```
+//!
+//! With a `client_secret` the token comes from the client-credentials
+//! grant. With a `device_url` it comes from the device-code grant: the user
+//! is told where to go and what code to enter, and the token endpoint is
+//! polled until they have. `scope` (a string, or a list of scopes) and
+//! `params` (a record of extra form fields, like `audience`) are sent along;
+//! `auth "basic"` sends the client's id and secret in an Authorization
+//! header instead of in the form.
+//!
+//! Tokens are kept in the `cached` store, readable only by the user, until
+//! a minute before they expire (an hour if the server doesn't say). An
+//! expired token with a refresh token is refreshed; if that fails, or
+//! there's none, the grant runs again. `refresh` before the word gets a new
+//! token even if the kept one is fresh.
+
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use serde_json::Value as JsonValue;
+
+use super::cache::{self, now_secs};
+use super::provenance::Origin;
+use super::url::build_query;
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// How long before it expires a token stops being used
+const EXPIRY_MARGIN: f64 = 60.0;
+
+/// How long a token lasts when the server doesn't say
+const DEFAULT_LIFETIME: f64 = 3600.0;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+fn fail(message: impl std::fmt::Display) -> EvalError {
+    EvalError::ExecError(format!("oauth2-token: {}", message))
+}
+
+/// What a token for `key` is kept under in the cache
+fn cache_key(key: &str) -> String {
+    format!("oauth2-token\n{}", key)
+}
+
+/// A token as the server gave it
+struct Token {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: f64,
+}
+
+impl Token {
+    /// A token from a token endpoint's response, keeping `refresh_token`
+    /// if the response has no new one
+    fn from_response(doc: &JsonValue, refresh_token: Option<String>) -> Result<Self, EvalError> {
+        let access_token = doc["access_token"]
+            .as_str()
+            .ok_or_else(|| fail("the response has no access_token"))?
+            .to_string();
+        let lifetime = doc["expires_in"]
+            .as_f64()
+            .or_else(|| doc["expires_in"].as_str()?.parse().ok())
+            .unwrap_or(DEFAULT_LIFETIME);
+        Ok(Token {
+            access_token,
+            refresh_token: doc["refresh_token"]
+                .as_str()
+                .map(str::to_string)
+                .or(refresh_token),
+            expires_at: now_secs() + lifetime,
+        })
+    }
+
+    fn is_fresh(&self) -> bool {
+        now_secs() + EXPIRY_MARGIN < self.expires_at
+    }
+
+    fn load(key: &str) -> Option<Self> {
+        let values = cache::lookup(&cache_key(key), f64::INFINITY)?;
+        let Some(Value::Map(record)) = values.first() else {
+            return None;
+        };
+        let number = |field: &str| match record.get(field)? {
+            Value::Number(n) => Some(*n),
+            Value::Int(n) => Some(*n as f64),
+            _ => None,
+        };
+        Some(Token {
+            access_token: record.get("access_token")?.as_arg()?,
+            refresh_token: record.get("refresh_token").and_then(Value::as_arg),
+            expires_at: number("expires_at")?,
+        })
+    }
+
+    fn store(&self, key: &str) -> std::io::Result<()> {
+        let mut record = IndexMap::new();
+        record.insert(
+            "access_token".to_string(),
+            Value::Literal(self.access_token.clone()),
+        );
+        if let Some(refresh_token) = &self.refresh_token {
+            record.insert(
+                "refresh_token".to_string(),
+                Value::Literal(refresh_token.clone()),
+            );
+        }
+        record.insert("expires_at".to_string(), Value::Number(self.expires_at));
+        cache::store(&cache_key(key), &[Value::Map(record)])
+    }
+}
+
+/// What `oauth2-token` was given
+struct Client {
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    device_url: Option<String>,
+    scope: Option<String>,
+    params: IndexMap<String, Value>,
+    basic_auth: bool,
+}
+
+impl Client {
+    fn from_record(record: &IndexMap<String, Value>) -> Result<Self, EvalError> {
+        let field = |key: &str| record.get(key).and_then(Value::as_arg);
+        let required =
+            |key: &str| field(key).ok_or_else(|| fail(format!("the record needs {}", key)));
+        let client = Client {
+            token_url: required("token_url")?,
+            client_id: required("client_id")?,
+            client_secret: field("client_secret"),
+            device_url: field("device_url"),
+            scope: match record.get("scope") {
+                Some(Value::List(scopes)) => Some(
+                    scopes
+                        .iter()
+                        .filter_map(Value::as_arg)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => field("scope"),
+            },
+            params: match record.get("params") {
+                Some(Value::Map(params)) => params.clone(),
+                _ => IndexMap::new(),
+            },
+            basic_auth: field("auth").as_deref() == Some("basic"),
+        };
+        if client.client_secret.is_none() && client.device_url.is_none() {
+            return Err(fail(
+                "the record needs a client_secret (client credentials) or a device_url (device code)",
+            ));
+        }
+        Ok(client)
+    }
+
+    /// What tokens for this client are kept under
+    fn key(&self) -> String {
+        let flow = if self.device_url.is_some() {
+            "device_code"
+        } else {
+            "client_credentials"
+        };
+        [
+            self.token_url.as_str(),
+            &self.client_id,
+            self.scope.as_deref().unwrap_or_default(),
+            flow,
+        ]
+        .join("\n")
+    }
+}
+
+impl Evaluator {
+    /// oauth2-token: { token_url client_id client_secret|device_url [scope] } oauth2-token → token
+    /// Push an access token, from the kept ones if still fresh
+    pub(crate) fn builtin_oauth2_token(&mut self) -> Result<(), EvalError> {
+        let record = match self.pop_value_or_err()? {
+            Value::Map(record) => record,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "record (oauth2-token)".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let refresh = std::mem::take(&mut self.refresh_next);
+        let client = Client::from_record(&record)?;
+        let key = client.key();
+
+        let kept = Token::load(&key);
+        let token = match kept {
+            Some(token) if token.is_fresh() && !refresh => token,
+            kept => {
+                let refreshed = kept
+                    .and_then(|t| t.refresh_token)
+                    .and_then(|refresh_token| self.refresh_token(&client, refresh_token).ok());
+                let token = match refreshed {
+                    Some(token) => token,
+                    None if client.device_url.is_some() => self.device_code_token(&client)?,
+                    None => self.client_credentials_token(&client)?,
+                };
+                if let Err(e) = token.store(&key) {
+                    eprintln!("oauth2-token: couldn't keep the token: {}", e);
+                }
+                token
+            }
+        };
+
+        self.push_from(Value::Literal(token.access_token), Origin::Secret);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// POST a form to one of the client's endpoints, with its credentials,
+    /// and parse the JSON that comes back (with the status)
+    fn oauth2_post(
+        &self,
+        client: &Client,
+        url: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<(u16, JsonValue), EvalError> {
+        let mut form: IndexMap<String, Value> = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::Literal(v.to_string())))
+            .collect();
+        let mut headers = std::collections::HashMap::new();
+        headers.insert("Accept".to_string(), "application/json".to_string());
+        match (&client.client_secret, client.basic_auth) {
+            (Some(secret), true) => {
+                use base64::Engine;
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", client.client_id, secret));
+                headers.insert(
+                    "Authorization".to_string(),
+                    format!("Basic {}", credentials),
+                );
+            }
+            (secret, _) => {
+                form.insert(
+                    "client_id".to_string(),
+                    Value::Literal(client.client_id.clone()),
+                );
+                if let Some(secret) = secret {
+                    form.insert("client_secret".to_string(), Value::Literal(secret.clone()));
+                }
+            }
+        }
+        if let Some(scope) = &client.scope {
+            form.insert("scope".to_string(), Value::Literal(scope.clone()));
+        }
+        for (key, value) in &client.params {
+            form.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        let body = build_query(&form);
+        let response = self.do_http_send(
+            "POST",
+            url,
+            "application/x-www-form-urlencoded",
+            body.as_bytes(),
+            &headers,
+        )?;
+        let doc: JsonValue = serde_json::from_str(&response.body).map_err(|_| {
+            fail(format!(
+                "{}: HTTP {}: not JSON: {}",
+                url,
+                response.status,
+                response.body.trim()
+            ))
+        })?;
+        Ok((response.status, doc))
+    }
+
+    /// The error a token endpoint gave, as an EvalError
+    fn oauth2_error(url: &str, status: u16, doc: &JsonValue) -> EvalError {
+        let error = doc["error"].as_str().unwrap_or("error");
+        match doc["error_description"].as_str() {
+            Some(description) => fail(format!(
+                "{}: HTTP {}: {}: {}",
+                url, status, error, description
+            )),
+            None => fail(format!("{}: HTTP {}: {}", url, status, error)),
+        }
+    }
+
+    fn client_credentials_token(&self, client: &Client) -> Result<Token, EvalError> {
+        let (status, doc) = self.oauth2_post(
+            client,
+            &client.token_url,
+            &[("grant_type", "client_credentials")],
+        )?;
+        if status >= 400 || doc.get("error").is_some() {
+            return Err(Self::oauth2_error(&client.token_url, status, &doc));
+        }
+        Token::from_response(&doc, None)
+    }
+
+    fn refresh_token(&self, client: &Client, refresh_token: String) -> Result<Token, EvalError> {
+        let (status, doc) = self.oauth2_post(
+            client,
+            &client.token_url,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ],
+        )?;
+        if status >= 400 || doc.get("error").is_some() {
+            return Err(Self::oauth2_error(&client.token_url, status, &doc));
+        }
+        Token::from_response(&doc, Some(refresh_token))
+    }
+
+    /// Ask for a device code, tell the user where to enter it, and poll
+    /// until they have
+    fn device_code_token(&self, client: &Client) -> Result<Token, EvalError> {
+        let device_url = client.device_url.as_deref().unwrap_or_default();
+        let (status, doc) = self.oauth2_post(client, device_url, &[])?;
+        if status >= 400 || doc.get("error").is_some() {
+            return Err(Self::oauth2_error(device_url, status, &doc));
+        }
+        let device_code = doc["device_code"]
+            .as_str()
+            .ok_or_else(|| fail(format!("{}: the response has no device_code", device_url)))?;
+        let user_code = doc["user_code"].as_str().unwrap_or_default();
+        // Some servers say verification_url
+        let uri = doc["verification_uri"]
+            .as_str()
+            .or_else(|| doc["verification_url"].as_str())
+            .unwrap_or_default();
+        match doc["verification_uri_complete"].as_str() {
+            Some(complete) => eprintln!(
+                "To sign in, open {} (or open {} and enter the code {})",
+                complete, uri, user_code
+            ),
+            None => eprintln!("To sign in, open {} and enter the code {}", uri, user_code),
+        }
+
+        let mut interval = doc["interval"].as_u64().unwrap_or(5);
+        let expires_in = doc["expires_in"].as_u64().unwrap_or(900);
+        let deadline = Instant::now() + Duration::from_secs(expires_in);
+        loop {
+            self.pause(Duration::from_secs(interval))?;
+            let (status, doc) = self.oauth2_post(
+                client,
+                &client.token_url,
+                &[
+                    ("grant_type", DEVICE_CODE_GRANT),
+                    ("device_code", device_code),
+                ],
+            )?;
+            match doc["error"].as_str() {
+                None if status < 400 => return Token::from_response(&doc, None),
+                Some("authorization_pending") => {}
+                Some("slow_down") => interval += 5,
+                _ => return Err(Self::oauth2_error(&client.token_url, status, &doc)),
+            }
+            if Instant::now() >= deadline {
+                return Err(fail("the device code expired before sign-in finished"));
+            }
+        }
+    }
+}
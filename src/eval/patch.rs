@@ -0,0 +1,428 @@
+//! Unified diffs of text values
+//!
+//! `old new make-patch` pushes a unified diff that turns `old` into `new`,
+//! with three lines of context around each change, as `diff -u` writes it.
+//! `text patch apply-patch` applies one to a string, and `"path" patch
+//! apply-patch-file` to a file: the file is replaced in one step, after the
+//! old contents are kept in `path.orig`. A patch that doesn't match the text
+//! is an error and changes nothing. A hunk whose lines moved is found by
+//! searching out from where it says it starts, as `patch` does, and a patch
+//! made from captured output, which lost its final newline, still applies
+//! to the file the output came from.
+
+use super::edit_file::{replace_file, with_suffix};
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Lines of unchanged text around each change in a patch
+const CONTEXT: usize = 3;
+
+/// Largest table of line pairs compared before a change is shown as all of
+/// the old lines going and all of the new ones coming
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// One line of an edit from old text to new
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LineOp<'a> {
+    Same(&'a str),
+    Went(&'a str),
+    Came(&'a str),
+}
+
+/// The shortest edit from `old` to `new`, line by line
+pub(crate) fn line_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<LineOp<'a>> {
+    // Lines shared at the start and end aren't part of the change
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops: Vec<LineOp> = old[..prefix].iter().map(|l| LineOp::Same(l)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        ops.extend(a.iter().map(|l| LineOp::Went(l)));
+        ops.extend(b.iter().map(|l| LineOp::Came(l)));
+    } else {
+        // Longest common subsequence, from the end
+        let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                common[i][j] = if a[i] == b[j] {
+                    common[i + 1][j + 1] + 1
+                } else {
+                    common[i + 1][j].max(common[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(LineOp::Same(a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+                ops.push(LineOp::Went(a[i]));
+                i += 1;
+            } else {
+                ops.push(LineOp::Came(b[j]));
+                j += 1;
+            }
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| LineOp::Same(l)));
+    ops
+}
+
+/// A hunk's line range as `start,count`, where an empty range starts at the
+/// line before it
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// A patch line, noting when the text's last line has no newline
+fn patch_line(out: &mut String, mark: char, line: &str) {
+    out.push(mark);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// A unified diff turning `old` into `new`; empty if they're the same
+pub(crate) fn make_patch(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = line_ops(&old_lines, &new_lines);
+
+    // Runs of ops to show: each change with its context, merged when the
+    // context of one reaches the next
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], LineOp::Same(_)))
+        .collect();
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(ops.len());
+        match runs.last_mut() {
+            Some(run) if start <= run.1 => run.1 = end,
+            _ => runs.push((start, end)),
+        }
+    }
+    if runs.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::from("--- old\n+++ new\n");
+    let (mut old_at, mut new_at, mut op_at) = (0, 0, 0);
+    for (start, end) in runs {
+        for op in &ops[op_at..start] {
+            match op {
+                LineOp::Same(_) => {
+                    old_at += 1;
+                    new_at += 1;
+                }
+                LineOp::Went(_) => old_at += 1,
+                LineOp::Came(_) => new_at += 1,
+            }
+        }
+        let hunk = &ops[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, LineOp::Came(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, LineOp::Went(_)))
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_at, old_count),
+            range(new_at, new_count)
+        ));
+        for op in hunk {
+            match op {
+                LineOp::Same(line) => patch_line(&mut out, ' ', line),
+                LineOp::Went(line) => patch_line(&mut out, '-', line),
+                LineOp::Came(line) => patch_line(&mut out, '+', line),
+            }
+        }
+        old_at += old_count;
+        new_at += new_count;
+        op_at = end;
+    }
+    out
+}
+
+/// A hunk of a parsed patch
+struct Hunk {
+    /// Where its old lines start, counting from 0
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// The `-start` of a hunk header `@@ -start,count +start,count @@`
+fn hunk_start(header: &str) -> Option<usize> {
+    let old = header.strip_prefix("@@ -")?.split(' ').next()?;
+    let (start, count) = match old.split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+        None => (old.parse().ok()?, 1),
+    };
+    Some(if count == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    })
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<Hunk>, String> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    // Which sides (old, new) a "\ No newline" marker applies to
+    let mut last = (false, false);
+    for (n, line) in patch.split_inclusive('\n').enumerate() {
+        if line.starts_with("@@") {
+            let old_start =
+                hunk_start(line).ok_or_else(|| format!("line {}: bad hunk header", n + 1))?;
+            hunks.push(Hunk {
+                old_start,
+                old: Vec::new(),
+                new: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            // Headers (`---`, `+++`, `diff`, ...) before the first hunk
+            continue;
+        };
+        match line.chars().next() {
+            Some(' ') => {
+                hunk.old.push(line[1..].to_string());
+                hunk.new.push(line[1..].to_string());
+                last = (true, true);
+            }
+            Some('-') => {
+                hunk.old.push(line[1..].to_string());
+                last = (true, false);
+            }
+            Some('+') => {
+                hunk.new.push(line[1..].to_string());
+                last = (false, true);
+            }
+            Some('\\') => {
+                let sides = [(last.0, &mut hunk.old), (last.1, &mut hunk.new)];
+                for (_, lines) in sides.into_iter().filter(|(applies, _)| *applies) {
+                    if let Some(line) = lines.last_mut() {
+                        if line.ends_with('\n') {
+                            line.pop();
+                        }
+                    }
+                }
+            }
+            // An empty line is an empty context line to some editors
+            Some('\n') => {
+                hunk.old.push("\n".to_string());
+                hunk.new.push("\n".to_string());
+                last = (true, true);
+            }
+            _ => return Err(format!("line {}: not part of a hunk", n + 1)),
+        }
+    }
+    Ok(hunks)
+}
+
+/// `text` with `patch` applied, or why it doesn't apply
+pub(crate) fn apply_patch(text: &str, patch: &str) -> Result<String, String> {
+    let hunks = parse_patch(patch)?;
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut at = 0;
+    // How far hunks found so far were from where they said they'd be
+    let mut offset: isize = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let fits = |start: usize| {
+            start >= at
+                && start + hunk.old.len() <= lines.len()
+                && hunk
+                    .old
+                    .iter()
+                    .zip(&lines[start..])
+                    .all(|(want, have)| want == have || have.strip_suffix('\n') == Some(want))
+        };
+        let expected = (hunk.old_start as isize + offset).max(0) as usize;
+        let start = (0..=lines.len())
+            .flat_map(|d| [expected.checked_add(d), expected.checked_sub(d)])
+            .flatten()
+            .find(|&start| fits(start))
+            .ok_or_else(|| format!("hunk {} doesn't match the text", n + 1))?;
+        offset = start as isize - hunk.old_start as isize;
+        out.extend(lines[at..start].iter().map(|l| l.to_string()));
+        out.extend(hunk.new.iter().cloned());
+        at = start + hunk.old.len();
+        // A patch made from text whose final newline was dropped (as
+        // captured output's is) keeps the newline the text has. When the
+        // patch's old side has its newline, its "\ No newline" marker on
+        // the new side is followed instead.
+        let old_lost_newline = hunk.old.last().is_some_and(|l| !l.ends_with('\n'));
+        if old_lost_newline && lines[at - 1].ends_with('\n') {
+            if let Some(last) = out.last_mut().filter(|l| !l.ends_with('\n')) {
+                last.push('\n');
+            }
+        }
+    }
+    out.extend(lines[at..].iter().map(|l| l.to_string()));
+    Ok(out.concat())
+}
+
+/// The text a three-way merge made, and how many conflicts it marked
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+/// What one side did to each line of the base: whether it kept it, and
+/// the lines it put before it (the last entry is for after the end)
+struct Side<'a> {
+    kept: Vec<bool>,
+    before: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Side<'a> {
+    fn new(base: &[&'a str], lines: &[&'a str]) -> Self {
+        let mut side = Side {
+            kept: vec![false; base.len()],
+            before: vec![Vec::new(); base.len() + 1],
+        };
+        let mut at = 0;
+        for op in line_ops(base, lines) {
+            match op {
+                LineOp::Same(_) => {
+                    side.kept[at] = true;
+                    at += 1;
+                }
+                LineOp::Went(_) => at += 1,
+                LineOp::Came(line) => side.before[at].push(line),
+            }
+        }
+        side
+    }
+
+    /// This side's lines for the base lines `from..to`
+    fn lines(&self, base: &[&'a str], from: usize, to: usize) -> Vec<&'a str> {
+        let mut lines = self.before[from].clone();
+        for (at, line) in base.iter().enumerate().take(to).skip(from) {
+            if self.kept[at] {
+                lines.push(*line);
+            }
+            lines.extend(&self.before[at + 1]);
+        }
+        lines
+    }
+}
+
+/// Merge the changes `ours` and `theirs` each made to `base`, line by
+/// line, as `diff3 -m` does: a part of the text only one side changed
+/// takes that side's change, and a part both changed differently is a
+/// conflict, marked with both versions under the given labels. Without a
+/// base, the lines the two sides share stand in for it.
+pub fn merge3(base: Option<&str>, ours: &str, theirs: &str, labels: (&str, &str)) -> Merge {
+    let ours: Vec<&str> = ours.lines().collect();
+    let theirs: Vec<&str> = theirs.lines().collect();
+    let base: Vec<&str> = match base {
+        Some(base) => base.lines().collect(),
+        None => line_ops(&ours, &theirs)
+            .into_iter()
+            .filter_map(|op| match op {
+                LineOp::Same(line) => Some(line),
+                _ => None,
+            })
+            .collect(),
+    };
+    let (a, b) = (Side::new(&base, &ours), Side::new(&base, &theirs));
+
+    let mut out: Vec<String> = Vec::new();
+    let mut conflicts = 0;
+    let mut from = 0;
+    // Lines both sides kept split the text into parts to merge
+    let stable = (0..base.len()).filter(|&at| a.kept[at] && b.kept[at]);
+    for to in stable.chain([base.len()]) {
+        let original = &base[from..to];
+        let (mine, yours) = (a.lines(&base, from, to), b.lines(&base, from, to));
+        let merged = if mine == yours || yours == original {
+            mine
+        } else if mine == original {
+            yours
+        } else {
+            conflicts += 1;
+            out.push(format!("<<<<<<< {}", labels.0));
+            out.extend(mine.iter().map(|l| l.to_string()));
+            out.push("=======".to_string());
+            out.extend(yours.iter().map(|l| l.to_string()));
+            out.push(format!(">>>>>>> {}", labels.1));
+            Vec::new()
+        };
+        out.extend(merged.iter().map(|l| l.to_string()));
+        if to < base.len() {
+            out.push(base[to].to_string());
+        }
+        from = to + 1;
+    }
+    let mut text = out.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    Merge { text, conflicts }
+}
+
+impl Evaluator {
+    /// make-patch: old new make-patch → patch
+    /// Push a unified diff that turns old into new
+    pub(crate) fn builtin_make_patch(&mut self) -> Result<(), EvalError> {
+        let new = self.pop_string()?;
+        let old = self.pop_string()?;
+        self.stack.push(Value::Literal(make_patch(&old, &new)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// apply-patch: text patch apply-patch → text
+    /// Apply a unified diff to a string
+    pub(crate) fn builtin_apply_patch(&mut self) -> Result<(), EvalError> {
+        let patch = self.pop_string()?;
+        let text = self.pop_string()?;
+        let patched = apply_patch(&text, &patch)
+            .map_err(|e| EvalError::ExecError(format!("apply-patch: {}", e)))?;
+        self.stack.push(Value::Literal(patched));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// apply-patch-file: "path" patch apply-patch-file → path
+    /// Apply a unified diff to a file, keeping the old contents in path.orig
+    pub(crate) fn builtin_apply_patch_file(&mut self) -> Result<(), EvalError> {
+        let patch = self.pop_string()?;
+        let name = self.pop_string()?;
+        let path = self.cwd.join(self.expand_tilde(&name));
+        let fail = |e: &dyn std::fmt::Display| {
+            EvalError::ExecError(format!("apply-patch-file: {}: {}", name, e))
+        };
+
+        let text = std::fs::read_to_string(&path).map_err(|e| fail(&e))?;
+        let patched = apply_patch(&text, &patch).map_err(|e| fail(&e))?;
+        let backup = with_suffix(&path, ".orig");
+        replace_file(&path, patched.as_bytes(), Some(&backup)).map_err(|e| fail(&e))?;
+
+        self.stack.push(Value::Literal(name));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -76,6 +76,10 @@ impl Evaluator {
     }
 
     pub(crate) fn path_suffix(&mut self) -> Result<(), EvalError> {
+        if self.take_nil_subject(2) {
+            self.stack.push(Value::Nil);
+            return Ok(());
+        }
         let suffix = self.pop_string()?;
         let base = self.pop_string()?;
         self.stack
@@ -0,0 +1,224 @@
+//! Execution policy and strict mode, usually configured from `hsab.toml`
+//!
+//! The exec policy decides which commands may run. `allow` limits the
+//! external commands that may be spawned and is checked at every place the
+//! evaluator starts a process, so pipes, redirects, background jobs, and
+//! `timeout` are covered as well as plain command calls. `deny` also blocks
+//! builtins of the same name (hsab's native `rm`, `cp`, `cd`, ...), so a
+//! denied word can't run either way. Definitions are never affected.
+//!
+//! Where hsab has a native version of a common command (`sleep`, `seq`,
+//! `ls`, `dirname`, ...), the native one runs instead of the PATH binary,
+//! saving a process per call. `external` opts words out of that: they run
+//! the PATH command when there is one.
+//!
+//! Native builtins that change files (`remove`, `save`, `edit-file`, ...,
+//! listed in `FILE_WRITERS`) answer to the policy as the commands they
+//! stand in for: with an `allow` list they run only when it names them or
+//! their command, so `allow = ["ls"]` can't be sidestepped with `remove`.
+//!
+//! Restricted mode (`hsab --restricted`, like `rbash`) is switched on after
+//! startup files have run and can't be switched off. It refuses changing
+//! directory, running commands named by a path, redirecting output to
+//! files, writing files with the `FILE_WRITERS` builtins, and changing
+//! `PATH` and the other variables in `RESTRICTED_VARS`.
+
+use serde::Deserialize;
+
+use super::{EvalError, Evaluator};
+
+/// Words a restricted shell refuses, and why
+const RESTRICTED_WORDS: &[(&str, &str)] = &[
+    ("cd", CHANGES_DIRECTORY),
+    (".cd", CHANGES_DIRECTORY),
+    ("pushd", CHANGES_DIRECTORY),
+    (".pushd", CHANGES_DIRECTORY),
+    ("popd", CHANGES_DIRECTORY),
+    (".popd", CHANGES_DIRECTORY),
+    (".edit-def", RUNS_EDITOR),
+    ("edit-at", RUNS_EDITOR),
+];
+
+const CHANGES_DIRECTORY: &str = "can't change directory";
+
+/// An editor can start a shell of its own
+const RUNS_EDITOR: &str = "editors can run other commands";
+
+/// Native builtins that create, change, or delete files, and the command
+/// each stands in for
+const FILE_WRITERS: &[(&str, Option<&str>)] = &[
+    ("touch", Some("touch")),
+    ("mkdir", Some("mkdir")),
+    ("mkdir-p", Some("mkdir")),
+    ("mktemp", Some("mktemp")),
+    ("mktemp-d", Some("mktemp")),
+    ("cp", Some("cp")),
+    ("copy", Some("cp")),
+    ("mv", Some("mv")),
+    ("move", Some("mv")),
+    ("rm", Some("rm")),
+    ("rm-r", Some("rm")),
+    ("remove", Some("rm")),
+    ("trash", None),
+    ("trash-restore", None),
+    ("rm-trash", None),
+    ("ln", Some("ln")),
+    ("chmod-sym", Some("chmod")),
+    ("chown", Some("chown")),
+    ("save", None),
+    ("edit-file", None),
+    ("edit-lines", None),
+    ("apply-patch-file", Some("patch")),
+    ("checkpoint", None),
+    ("to-xlsx", None),
+    ("nohup", Some("nohup")),
+];
+
+/// Variables a restricted shell may not set or unset
+const RESTRICTED_VARS: &[&str] = &["PATH", "SHELL", "ENV", "HSAB_PATH"];
+
+/// Which commands may run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecPolicy {
+    /// When set, only these commands may run
+    pub allow: Option<Vec<String>>,
+    /// Commands that may never run (checked before `allow`)
+    pub deny: Vec<String>,
+    /// Commands to run from PATH even though hsab has a builtin of the
+    /// same name
+    pub external: Vec<String>,
+}
+
+impl ExecPolicy {
+    /// Check if the external command `cmd` may be spawned. Names match the
+    /// command as written or its file name, so denying `rm` also denies
+    /// `/bin/rm`.
+    pub fn permits(&self, cmd: &str) -> bool {
+        !self.denies(cmd) && self.allow.as_deref().is_none_or(|names| listed(names, cmd))
+    }
+
+    /// Check if `cmd` is on the deny list
+    pub fn denies(&self, cmd: &str) -> bool {
+        listed(&self.deny, cmd)
+    }
+
+    /// Check if `cmd` should skip hsab's builtin for the PATH command
+    pub fn prefers_external(&self, cmd: &str) -> bool {
+        self.external.iter().any(|name| name == cmd)
+    }
+}
+
+fn listed(names: &[String], cmd: &str) -> bool {
+    let base = std::path::Path::new(cmd)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(cmd);
+    names.iter().any(|n| n == cmd || n == base)
+}
+
+impl Evaluator {
+    /// Replace the exec policy
+    pub fn set_exec_policy(&mut self, policy: ExecPolicy) {
+        self.exec_policy = policy;
+    }
+
+    /// Check if the word `cmd` should run the PATH command instead of
+    /// hsab's builtin
+    pub(crate) fn uses_external(&mut self, cmd: &str) -> bool {
+        self.exec_policy.prefers_external(cmd) && self.resolver.find_executable(cmd).is_some()
+    }
+
+    /// Enable or disable strict mode: expanding an unset variable is an
+    /// error instead of an empty string
+    pub fn set_strict_mode(&mut self, strict: bool) {
+        self.strict_mode = strict;
+    }
+
+    /// Check if strict mode is on
+    pub fn is_strict_mode(&self) -> bool {
+        self.strict_mode
+    }
+
+    /// Enable or disable pipefail: a pipeline's exit code is its first
+    /// failing stage's rather than the last stage's
+    pub fn set_pipefail(&mut self, pipefail: bool) {
+        self.pipefail = pipefail;
+    }
+
+    /// Turn on restricted mode. There is no way to turn it off again.
+    pub fn set_restricted(&mut self) {
+        self.restricted = true;
+    }
+
+    /// Check if restricted mode is on
+    pub fn is_restricted(&self) -> bool {
+        self.restricted
+    }
+
+    /// Fail unless the exec policy lets `cmd` be spawned
+    pub(crate) fn check_exec(&self, cmd: &str) -> Result<(), EvalError> {
+        if self.restricted && cmd.contains('/') {
+            Err(restricted_error(cmd, "commands can't be run by path"))
+        } else if self.exec_policy.permits(cmd) {
+            Ok(())
+        } else {
+            Err(policy_error(cmd))
+        }
+    }
+
+    /// Fail if restricted mode refuses the word `word`
+    pub(crate) fn check_restricted_word(&self, word: &str) -> Result<(), EvalError> {
+        match RESTRICTED_WORDS.iter().find(|(w, _)| *w == word) {
+            Some((_, why)) if self.restricted => Err(restricted_error(word, why)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail if `word` is a builtin that writes files and the exec policy or
+    /// restricted mode refuses it
+    pub(crate) fn check_file_writer(&self, word: &str) -> Result<(), EvalError> {
+        let Some((_, command)) = FILE_WRITERS.iter().find(|(w, _)| *w == word) else {
+            return Ok(());
+        };
+        if self.restricted {
+            return Err(restricted_error(word, "can't write files"));
+        }
+        let allowed = self.exec_policy.allow.as_deref().is_none_or(|names| {
+            listed(names, word) || command.is_some_and(|cmd| listed(names, cmd))
+        });
+        let denied = command.is_some_and(|cmd| self.exec_policy.denies(cmd));
+        if allowed && !denied {
+            Ok(())
+        } else {
+            Err(policy_error(word))
+        }
+    }
+
+    /// Fail if restricted mode refuses the output redirect `op`
+    pub(crate) fn check_restricted_redirect(&self, op: &str) -> Result<(), EvalError> {
+        if self.restricted {
+            Err(restricted_error(op, "can't redirect output to files"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fail if restricted mode refuses setting or unsetting `name`
+    pub(crate) fn check_env_write(&self, name: &str) -> Result<(), EvalError> {
+        if self.restricted && RESTRICTED_VARS.contains(&name) {
+            Err(restricted_error(name, "read-only in a restricted shell"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn restricted_error(what: &str, why: &str) -> EvalError {
+    EvalError::ExecError(format!("{}: restricted: {}", what, why))
+}
+
+/// Error for a command the exec policy refuses
+pub(crate) fn policy_error(cmd: &str) -> EvalError {
+    EvalError::ExecError(format!("{}: not allowed by the exec policy", cmd))
+}
@@ -1,11 +1,18 @@
+use super::terminal::{OutputTap, ParallelDisplay, ParallelView};
 use super::{EvalError, Evaluator, Job, JobStatus};
 use crate::ast::{Expr, Value};
+use crate::resolver::ExecutableResolver;
+use crate::util::lock_or_recover;
 use std::fs::File;
-use std::io::Write;
-use std::process::{Command, Stdio};
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// How much of a background job's output is kept for `job-output`
+const MAX_JOB_OUTPUT: usize = 16 * 1024 * 1024;
+
 impl Evaluator {
     /// Apply a block to args on the stack
     pub(crate) fn apply_block(&mut self) -> Result<(), EvalError> {
@@ -38,20 +45,55 @@ impl Evaluator {
         let consumer = self.pop_block()?;
         let input = self.pop_value_or_err()?;
 
+        // A failed producer left an Error; input a pipe just pushed means
+        // this pipe extends that pipeline
+        let producer_code = match &input {
+            Value::Error { code, .. } => code.unwrap_or(1),
+            _ => 0,
+        };
+        let extends = matches!(&self.pipe_tail, Some((depth, tail)) if *depth == self.stack.len() + 1 && *tail == input);
+
+        // A consumer hsab runs itself gets the value, not its text
+        if self.pipe_threads_value(&consumer, &input) {
+            let produced = self.run_block_alone(&consumer, vec![input])?;
+            let code = self.last_exit_code;
+            self.record_pipestatus(extends, producer_code, code);
+            let result_at = self.stack.len() + produced.len().max(1);
+            let result = match produced.last() {
+                Some(last) => last.clone(),
+                None => Value::Nil,
+            };
+            if produced.is_empty() {
+                self.stack.push(Value::Nil);
+            } else {
+                self.stack.extend(produced);
+            }
+            self.pipe_tail = Some((result_at, result));
+            return Ok(());
+        }
+
         // Get input as string
         let input_str = input.as_arg().unwrap_or_default();
 
         // Build consumer command from block
         let (cmd, args) = self.block_to_cmd_args(&consumer)?;
+        let audit = self.start_process("pipe", &cmd, &args)?;
 
         // Execute with stdin piped
+        let stderr = if self.stderr_sink.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        };
         let mut child = Command::new(&cmd)
             .args(&args)
             .current_dir(&self.cwd)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .stderr(stderr)
             .spawn()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd, e)))?;
+            .map_err(|e| self.spawn_error(&cmd, e))?;
+        let _foreground = self.cancel.track(child.id());
 
         // Write input to stdin
         if let Some(mut stdin) = child.stdin.take() {
@@ -61,23 +103,225 @@ impl Evaluator {
         let output = child
             .wait_with_output()
             .map_err(|e| EvalError::ExecError(e.to_string()))?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        self.last_exit_code = output.status.code().unwrap_or(-1);
+        let code = output.status.code().unwrap_or(-1);
+        audit.finish(code);
+        if let Some(sink) = &mut self.stderr_sink {
+            sink.extend_from_slice(&output.stderr);
+        }
 
-        // Track pipestatus
-        self.pipestatus.clear();
-        self.pipestatus.push(self.last_exit_code);
+        self.record_pipestatus(extends, producer_code, code);
+        self.push_pipe_output(&output.stdout);
 
-        // Push result
-        if stdout.is_empty() {
-            self.stack.push(Value::Nil);
-        } else {
-            self.stack.push(Value::Output(stdout));
+        Ok(())
+    }
+
+    /// How many of the consumers of `cmd #[a] | #[b] | ...` run with `cmd`
+    /// as processes joined by OS pipes: the leading ones that are programs
+    /// rather than something hsab runs on a value, unless `cmd` is moved to
+    /// the terminal (`live`, tty-guard)
+    pub(crate) fn pipe_streams(&mut self, cmd: &str, consumers: &[Vec<Expr>]) -> usize {
+        if self.capture_override == Some(false) {
+            return 0;
+        }
+        let programs = consumers
+            .iter()
+            .take_while(|consumer| !self.pipe_threads_value(consumer, &Value::Nil))
+            .count();
+        if programs > 0 && self.capture_override.is_none() && self.guarded_to_tty(cmd) {
+            // Already asked; run it on the terminal without asking again
+            self.capture_override = Some(false);
+            return 0;
+        }
+        programs
+    }
+
+    /// Run `cmd` with its stdout connected to the first consumer's stdin and
+    /// so on down the line, so each reads output as it's written rather than
+    /// after the stage before exits, and push what the last one prints
+    pub(crate) fn stream_pipe(
+        &mut self,
+        cmd: &str,
+        args: Vec<String>,
+        consumers: &[Vec<Expr>],
+    ) -> Result<(), EvalError> {
+        self.capture_override = None;
+        let mut stages = vec![(self.corrected(cmd), args)];
+        for consumer in consumers {
+            stages.push(self.block_to_cmd_args(consumer)?);
         }
+        let (stdout, codes) = self.spawn_pipeline(&stages)?;
+        self.pipestatus = codes;
+        self.settle_pipeline();
+        self.push_pipe_output(&stdout);
+        self.pipe_streamed = consumers.len();
+        Ok(())
+    }
 
+    /// Pipeline: #[cmd1] #[cmd2] #[cmd3] pipeline -> output of the last
+    /// Every block on top of the stack is a stage; all run at once, joined
+    /// by OS pipes, and `pipestatus` gets each one's exit code
+    pub(crate) fn builtin_pipeline(&mut self) -> Result<(), EvalError> {
+        let mut blocks = Vec::new();
+        while let Some(Value::Block(_)) = self.stack.last() {
+            blocks.push(self.pop_block()?);
+        }
+        if blocks.is_empty() {
+            return Err(EvalError::StackUnderflow(
+                "pipeline requires blocks of commands".into(),
+            ));
+        }
+        blocks.reverse();
+
+        let mut stages = Vec::with_capacity(blocks.len());
+        for block in &blocks {
+            let (cmd, args) = self.block_to_cmd_args(block)?;
+            let hsabs = self.definitions.contains_key(&cmd)
+                || (ExecutableResolver::is_hsab_builtin(&cmd)
+                    && self.resolver.find_executable(&cmd).is_none());
+            if hsabs {
+                return Err(EvalError::ExecError(format!(
+                    "pipeline: {} isn't a program; pipe into it with |",
+                    cmd
+                )));
+            }
+            stages.push((cmd, args));
+        }
+        let (stdout, codes) = self.spawn_pipeline(&stages)?;
+        self.pipestatus = codes;
+        self.settle_pipeline();
+        self.push_pipe_output(&stdout);
         Ok(())
     }
 
+    /// Spawn every stage at once, each one's stdout feeding the next one's
+    /// stdin, and wait for them all: the last stage's output and each
+    /// stage's exit code
+    fn spawn_pipeline(
+        &mut self,
+        stages: &[(String, Vec<String>)],
+    ) -> Result<(Vec<u8>, Vec<i32>), EvalError> {
+        let mut audits = Vec::with_capacity(stages.len());
+        for (cmd, args) in stages {
+            audits.push(self.start_process("pipe", cmd, args)?);
+        }
+
+        let sink = self.stderr_sink.is_some();
+        let mut children: Vec<Child> = Vec::with_capacity(stages.len());
+        let mut foreground = Vec::with_capacity(stages.len());
+        let mut stderr_readers = Vec::new();
+        let mut upstream = None;
+        for (cmd, args) in stages {
+            let stdin = upstream.take().map_or_else(Stdio::null, Stdio::from);
+            let spawned = Command::new(cmd)
+                .args(args)
+                .current_dir(&self.cwd)
+                .stdin(stdin)
+                .stdout(Stdio::piped())
+                .stderr(if sink {
+                    Stdio::piped()
+                } else {
+                    Stdio::inherit()
+                })
+                .spawn();
+            let mut child = match spawned {
+                Ok(child) => child,
+                Err(e) => {
+                    for mut started in children {
+                        let _ = started.kill();
+                        let _ = started.wait();
+                    }
+                    return Err(self.spawn_error(cmd, e));
+                }
+            };
+            foreground.push(self.cancel.track(child.id()));
+            // Drained alongside the pipeline so a chatty stage can't block
+            if let Some(mut err) = child.stderr.take() {
+                stderr_readers.push(std::thread::spawn(move || {
+                    let mut buf = Vec::new();
+                    let _ = err.read_to_end(&mut buf);
+                    buf
+                }));
+            }
+            upstream = child.stdout.take();
+            children.push(child);
+        }
+
+        let mut stdout = Vec::new();
+        if let Some(mut last) = upstream {
+            last.read_to_end(&mut stdout)?;
+        }
+        let mut codes = Vec::with_capacity(children.len());
+        for (child, audit) in children.iter_mut().zip(audits) {
+            let code = child.wait()?.code().unwrap_or(-1);
+            audit.finish(code);
+            codes.push(code);
+        }
+        if let Some(sink) = &mut self.stderr_sink {
+            for reader in stderr_readers {
+                if let Ok(err) = reader.join() {
+                    sink.extend_from_slice(&err);
+                }
+            }
+        }
+        Ok((stdout, codes))
+    }
+
+    /// Push a pipe's output, remembering it so a pipe consuming it extends
+    /// the same pipeline
+    fn push_pipe_output(&mut self, stdout: &[u8]) {
+        let stdout = self.decode_output(stdout);
+        let result = if stdout.is_empty() {
+            Value::Nil
+        } else {
+            Value::Output(stdout)
+        };
+        self.pipe_tail = Some((self.stack.len() + 1, result.clone()));
+        self.stack.push(result);
+    }
+
+    /// Whether `|` runs `consumer` on `input` itself instead of spawning it
+    /// with the input's text on stdin: when its command is a definition,
+    /// a builtin no program on PATH shares a name with (`select`, `where`),
+    /// or any builtin given a structured value (a Table to `sort`)
+    fn pipe_threads_value(&mut self, consumer: &[Expr], input: &Value) -> bool {
+        let Ok((cmd, _)) = self.block_to_cmd_args(consumer) else {
+            return false;
+        };
+        if self.definitions.contains_key(&cmd) {
+            return true;
+        }
+        if !ExecutableResolver::is_hsab_builtin(&cmd) || self.uses_external(&cmd) {
+            return false;
+        }
+        let structured = matches!(
+            input,
+            Value::Table { .. } | Value::Map(_) | Value::List(_) | Value::Bytes(_)
+        );
+        structured || self.resolver.find_executable(&cmd).is_none()
+    }
+
+    /// Record a pipe stage's exit code in `pipestatus`, starting over unless
+    /// the pipe `extends` the last one, and set the pipeline's exit code
+    fn record_pipestatus(&mut self, extends: bool, producer_code: i32, code: i32) {
+        if !extends {
+            self.pipestatus.clear();
+            self.pipestatus.push(producer_code);
+        }
+        self.pipestatus.push(code);
+        self.settle_pipeline();
+    }
+
+    /// Set the exit code of the pipeline in `pipestatus`: its last stage's,
+    /// or with `pipefail` its first failing stage's
+    fn settle_pipeline(&mut self) {
+        let mut codes = self.pipestatus.iter().copied();
+        self.last_exit_code = if self.pipefail {
+            codes.find(|&c| c != 0).unwrap_or(0)
+        } else {
+            codes.last().unwrap_or(0)
+        };
+    }
+
     /// Execute redirect (supports multiple files via writing to each)
     pub(crate) fn execute_redirect(&mut self, mode: &str) -> Result<(), EvalError> {
         let file_block = self.pop_block()?;
@@ -104,6 +348,7 @@ impl Evaluator {
         if mode == "<" {
             return self.execute_stdin_redirect(&cmd, &files[0]);
         }
+        self.check_restricted_redirect(mode)?;
 
         // Execute command
         let (cmd_name, args) = self.block_to_cmd_args(&cmd)?;
@@ -133,30 +378,107 @@ impl Evaluator {
         input_file: &str,
     ) -> Result<(), EvalError> {
         let (cmd_name, args) = self.block_to_cmd_args(cmd)?;
+        let audit = self.start_process("redirect", &cmd_name, &args)?;
 
         // Open the input file
         let file = File::open(input_file)
             .map_err(|e| EvalError::ExecError(format!("{}: {}", input_file, e)))?;
 
         // Execute command with stdin from file
-        let output = Command::new(&cmd_name)
-            .args(&args)
-            .current_dir(&self.cwd)
-            .stdin(Stdio::from(file))
-            .output()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd_name, e)))?;
+        let output = self
+            .run_foreground(
+                Command::new(&cmd_name)
+                    .args(&args)
+                    .current_dir(&self.cwd)
+                    .stdin(Stdio::from(file))
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped()),
+            )
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
 
         self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
 
         // Push stdout to stack
         if !output.stdout.is_empty() {
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            let stdout = self.decode_output(&output.stdout);
             self.stack.push(Value::Output(stdout));
         }
 
         Ok(())
     }
 
+    /// Execute here-string: "text" [cmd] <<<
+    /// The text, with a final newline as in bash, is the command's stdin
+    pub(crate) fn execute_here_string(&mut self) -> Result<(), EvalError> {
+        let cmd = self.pop_block()?;
+        let input = self.pop_value_or_err()?;
+        self.run_with_stdin("here-string", &cmd, stdin_text(input))
+    }
+
+    /// heredoc: "text" [cmd] heredoc
+    /// `<<<` for multi-line text, dropping the indentation its lines share
+    pub(crate) fn builtin_heredoc(&mut self) -> Result<(), EvalError> {
+        let cmd = self.pop_block()?;
+        let input = match self.pop_value_or_err()? {
+            Value::Bytes(bytes) => Value::Bytes(bytes),
+            other => Value::Literal(dedent(&other.as_arg().unwrap_or_default())),
+        };
+        self.run_with_stdin("heredoc", &cmd, stdin_text(input))
+    }
+
+    /// Run the command in a block with `input` as its stdin, pushing what it
+    /// prints
+    fn run_with_stdin(
+        &mut self,
+        via: &'static str,
+        cmd: &[Expr],
+        input: Vec<u8>,
+    ) -> Result<(), EvalError> {
+        let (cmd_name, args) = self.block_to_cmd_args(cmd)?;
+        let audit = self.start_process(via, &cmd_name, &args)?;
+
+        let stderr = if self.stderr_sink.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        };
+        let mut child = Command::new(&cmd_name)
+            .args(&args)
+            .current_dir(&self.cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .spawn()
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
+        let _foreground = self.cancel.track(child.id());
+
+        // Written from a thread, so a command that prints before it has read
+        // everything can't fill its stdout and stall both sides
+        let writer = child.stdin.take().map(|mut stdin| {
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            })
+        });
+        let output = child
+            .wait_with_output()
+            .map_err(|e| EvalError::ExecError(e.to_string()))?;
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+
+        self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
+        if let Some(sink) = &mut self.stderr_sink {
+            sink.extend_from_slice(&output.stderr);
+        }
+        if !output.stdout.is_empty() {
+            let stdout = self.decode_output(&output.stdout);
+            self.stack.push(Value::Output(stdout));
+        }
+        Ok(())
+    }
+
     /// Execute stderr redirect
     pub(crate) fn execute_redirect_err(&mut self, mode: &str) -> Result<(), EvalError> {
         let file_block = self.pop_block()?;
@@ -180,7 +502,9 @@ impl Evaluator {
         }
 
         // Execute command, capturing stderr separately
+        self.check_restricted_redirect(mode)?;
         let (cmd_name, args) = self.block_to_cmd_args(&cmd)?;
+        let audit = self.start_process("redirect", &cmd_name, &args)?;
 
         let file = match mode {
             "2>" => File::create(&files[0])?,
@@ -191,16 +515,21 @@ impl Evaluator {
             _ => return Err(EvalError::ExecError("Invalid redirect mode".into())),
         };
 
-        let output = Command::new(&cmd_name)
-            .args(&args)
-            .current_dir(&self.cwd)
-            .stderr(Stdio::from(file))
-            .output()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd_name, e)))?;
+        let output = self
+            .run_foreground(
+                Command::new(&cmd_name)
+                    .args(&args)
+                    .current_dir(&self.cwd)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::from(file)),
+            )
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
 
         self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stdout = self.decode_output(&output.stdout);
         if !stdout.is_empty() {
             self.stack.push(Value::Output(stdout));
         }
@@ -231,20 +560,26 @@ impl Evaluator {
         }
 
         // Execute command
+        self.check_restricted_redirect("&>")?;
         let (cmd_name, args) = self.block_to_cmd_args(&cmd)?;
+        let audit = self.start_process("redirect", &cmd_name, &args)?;
 
         let file = File::create(&files[0])?;
         let file_clone = file.try_clone()?;
 
-        let output = Command::new(&cmd_name)
-            .args(&args)
-            .current_dir(&self.cwd)
-            .stdout(Stdio::from(file))
-            .stderr(Stdio::from(file_clone))
-            .output()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd_name, e)))?;
+        let output = self
+            .run_foreground(
+                Command::new(&cmd_name)
+                    .args(&args)
+                    .current_dir(&self.cwd)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::from(file))
+                    .stderr(Stdio::from(file_clone)),
+            )
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
 
         self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
 
         Ok(())
     }
@@ -253,21 +588,26 @@ impl Evaluator {
     pub(crate) fn execute_redirect_err_to_out(&mut self) -> Result<(), EvalError> {
         let cmd = self.pop_block()?;
         let (cmd_name, args) = self.block_to_cmd_args(&cmd)?;
+        let audit = self.start_process("redirect", &cmd_name, &args)?;
 
         // Execute command with stderr merged into stdout
-        let output = Command::new(&cmd_name)
-            .args(&args)
-            .current_dir(&self.cwd)
-            .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
-            .output()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd_name, e)))?;
+        let output = self
+            .run_foreground(
+                Command::new(&cmd_name)
+                    .args(&args)
+                    .current_dir(&self.cwd)
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .stdout(Stdio::piped()),
+            )
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
 
         self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
 
         // Combine stdout and stderr
-        let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut combined = self.decode_output(&output.stdout);
+        let stderr = self.decode_output(&output.stderr);
         if !stderr.is_empty() {
             combined.push_str(&stderr);
         }
@@ -283,35 +623,120 @@ impl Evaluator {
     pub(crate) fn execute_background(&mut self) -> Result<(), EvalError> {
         let cmd = self.pop_block()?;
         let (cmd_name, args) = self.block_to_cmd_args(&cmd)?;
+        let audit = self.start_process("background", &cmd_name, &args)?;
         let cmd_str = format!("{} {}", cmd_name, args.join(" "));
 
         let child = Command::new(&cmd_name)
             .args(&args)
             .current_dir(&self.cwd)
             .stdin(Stdio::null())
-            .stdout(Stdio::null())
+            .stdout(Stdio::piped())
             .stderr(Stdio::null())
             .spawn()
-            .map_err(|e| EvalError::ExecError(e.to_string()))?;
+            .map_err(|e| self.spawn_error(&cmd_name, e))?;
+
+        let (job_id, pid) = self.start_job(cmd_str, child, audit);
+
+        // Print job info like bash does
+        eprintln!("[{}] {}", job_id, pid);
+
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// nohup: #[cmd] nohup, or #[cmd] "file" nohup
+    /// Start cmd in the background detached from the terminal, in a session
+    /// of its own with SIGHUP ignored, its stdout and stderr appended to
+    /// `nohup.out` (or the file given), so it outlives the shell
+    pub(crate) fn builtin_nohup(&mut self) -> Result<(), EvalError> {
+        let file = match self.stack.last() {
+            Some(Value::Block(_)) => "nohup.out".to_string(),
+            _ => self.pop_string()?,
+        };
+        let block = self.pop_block()?;
+        let (cmd, args) = self.block_to_cmd_args(&block)?;
+        let audit = self.start_process("nohup", &cmd, &args)?;
+
+        let path = self.cwd.join(self.expand_tilde(&file));
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| EvalError::ExecError(format!("nohup: {}: {}", path.display(), e)))?;
+        let err_log = log.try_clone()?;
+
+        let mut command = Command::new(&cmd);
+        command
+            .args(&args)
+            .current_dir(&self.cwd)
+            .stdin(Stdio::null())
+            .stdout(log)
+            .stderr(err_log);
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // A new session has no controlling terminal to hang up
+            unsafe {
+                command.pre_exec(|| {
+                    libc::setsid();
+                    libc::signal(libc::SIGHUP, libc::SIG_IGN);
+                    Ok(())
+                });
+            }
+        }
+        let child = command.spawn().map_err(|e| self.spawn_error(&cmd, e))?;
+
+        let cmd_str = format!("{} {}", cmd, args.join(" "));
+        let (job_id, pid) = self.start_job(cmd_str, child, audit);
+        eprintln!("[{}] {}", job_id, pid);
+        eprintln!("nohup: output to {}", path.display());
+
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Track a spawned background child as a job, collecting its stdout
+    /// for `job-output`. Returns the job id and pid.
+    pub(crate) fn start_job(
+        &mut self,
+        command: String,
+        mut child: Child,
+        audit: super::audit::AuditEntry,
+    ) -> (usize, u32) {
+        let output = child.stdout.take().map(|mut stdout| {
+            let buffer = Arc::new(Mutex::new(Vec::new()));
+            let sink = Arc::clone(&buffer);
+            std::thread::spawn(move || {
+                let mut chunk = [0u8; 8192];
+                while let Ok(n) = stdout.read(&mut chunk) {
+                    if n == 0 {
+                        break;
+                    }
+                    let mut kept = lock_or_recover(&sink);
+                    // Past the cap the job still runs; the rest is dropped
+                    let room = MAX_JOB_OUTPUT.saturating_sub(kept.len());
+                    kept.extend_from_slice(&chunk[..n.min(room)]);
+                }
+            });
+            buffer
+        });
 
         let pid = child.id();
-        let job_id = self.next_job_id;
+        let id = self.next_job_id;
         self.next_job_id += 1;
-
         self.jobs.push(Job {
-            id: job_id,
+            id,
             pid,
             pgid: pid, // Process group ID same as PID for background jobs
-            command: cmd_str.clone(),
+            command,
             child: Some(child),
             status: JobStatus::Running,
+            audit: Some(audit),
+            started: chrono::Local::now(),
+            runtime: None,
+            output,
         });
-
-        // Print job info like bash does
-        eprintln!("[{}] {}", job_id, pid);
-
-        self.last_exit_code = 0;
-        Ok(())
+        (id, pid)
     }
 
     /// Execute && (and)
@@ -353,54 +778,141 @@ impl Evaluator {
     }
 
     /// Parallel: #[#[cmd1] #[cmd2] ...] parallel - run blocks in parallel, wait for all
+    /// Each block runs on its own thread in a sub-evaluator, so definitions
+    /// work as well as external commands. Pushes a table of `index`,
+    /// `exit_code`, and `output`, a row per block in the order the blocks
+    /// were given, whichever finished first
     pub(crate) fn exec_parallel(&mut self) -> Result<(), EvalError> {
-        let blocks = self.pop_block()?;
-
-        // Extract commands from inner blocks
-        let mut cmds: Vec<(String, Vec<String>)> = Vec::new();
-        for expr in blocks {
-            if let Expr::Block(inner) = expr {
-                if let Ok((cmd, args)) = self.block_to_cmd_args(&inner) {
-                    cmds.push((cmd, args));
-                }
-            }
-        }
+        let blocks: Vec<Vec<Expr>> = self
+            .pop_block()?
+            .into_iter()
+            .filter_map(|expr| match expr {
+                Expr::Block(inner) => Some(inner),
+                _ => None,
+            })
+            .collect();
 
-        if cmds.is_empty() {
+        if blocks.is_empty() {
             return Ok(());
         }
 
-        // Spawn all commands
-        let cwd = self.cwd.clone();
-        let handles: Vec<_> = cmds
+        // Inside another parallel's block, lines go to that block's pane
+        let view = match self.output_tap {
+            Some(_) => ParallelView::Off,
+            None => ParallelView::from_env(),
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handles: Vec<_> = blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                let mut eval = self.sub_evaluator();
+                let tap = (view != ParallelView::Off).then(|| OutputTap::new(index, tx.clone()));
+                eval.output_tap = tap.clone().or_else(|| self.output_tap.clone());
+                let block = block.clone();
+                let handle = std::thread::spawn(move || {
+                    let (code, output) = eval.run_parallel_block(&block);
+                    if let Some(tap) = tap {
+                        tap.done(code);
+                    }
+                    (code, output)
+                });
+                (index, handle)
+            })
+            .collect();
+        drop(tx);
+
+        if view != ParallelView::Off {
+            let labels = blocks
+                .iter()
+                .map(|block| crate::ast::exprs_to_source(block))
+                .collect();
+            ParallelDisplay::new(view, labels).run(rx);
+        }
+
+        // Wait for all, collecting each block's results on its own row
+        let rows = handles
             .into_iter()
-            .map(|(cmd, args)| {
-                let cwd = cwd.clone();
-                std::thread::spawn(move || {
-                    Command::new(&cmd)
-                        .args(&args)
-                        .current_dir(&cwd)
-                        .output()
-                        .map(|o| String::from_utf8_lossy(&o.stdout).to_string())
-                        .unwrap_or_default()
-                })
+            .map(|(index, handle)| {
+                let (code, output) = handle
+                    .join()
+                    .unwrap_or_else(|_| (-1, Value::Literal(String::new())));
+                vec![
+                    Value::Int(index as i64),
+                    Value::Int(i64::from(code)),
+                    output,
+                ]
             })
             .collect();
 
-        // Wait for all and collect output
-        let mut combined_output = String::new();
-        for handle in handles {
-            if let Ok(output) = handle.join() {
-                combined_output.push_str(&output);
+        self.stack.push(Value::Table {
+            columns: vec!["index".into(), "exit_code".into(), "output".into()],
+            rows,
+        });
+
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// An evaluator for a block run on another thread: the parent's
+    /// definitions, aliases, locals, directory, and restrictions
+    pub(crate) fn sub_evaluator(&self) -> Evaluator {
+        let mut eval = Evaluator::new();
+        eval.cwd = self.cwd.clone();
+        eval.definitions = self.definitions.clone();
+        eval.aliases = self.aliases.clone();
+        eval.local_values = self.local_values.clone();
+        eval.exec_policy = self.exec_policy.clone();
+        eval.restricted = self.restricted;
+        eval.strict_mode = self.strict_mode;
+        eval.audit = self.audit.clone();
+        eval.output_encoding = self.output_encoding;
+        eval.cancel = self.cancel.clone();
+        eval
+    }
+
+    /// Run one of `parallel`'s blocks with its output captured, returning
+    /// the exit code and what it left: one value as is, several as a list
+    fn run_parallel_block(&mut self, block: &[Expr]) -> (i32, Value) {
+        let mut outcome = Ok(());
+        for expr in block {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
             }
         }
-
-        if !combined_output.is_empty() {
-            self.stack.push(Value::Output(combined_output));
+        if let Err(e) = outcome {
+            let code = if self.last_exit_code == 0 {
+                1
+            } else {
+                self.last_exit_code
+            };
+            return (
+                code,
+                Value::Error {
+                    kind: "EvalError".into(),
+                    message: e.to_string(),
+                    code: Some(code),
+                    source: None,
+                    command: None,
+                },
+            );
         }
 
-        self.last_exit_code = 0;
-        Ok(())
+        let mut produced: Vec<Value> = std::mem::take(&mut self.stack)
+            .into_iter()
+            .map(|v| match v {
+                Value::Output(s) => Value::Output(s.trim_end_matches('\n').to_string()),
+                other => other,
+            })
+            .collect();
+        let output = match produced.len() {
+            0 => Value::Literal(String::new()),
+            1 => produced.remove(0),
+            _ => Value::List(produced),
+        };
+        (self.last_exit_code, output)
     }
 
     /// Fork: #[cmd1] #[cmd2] ... N fork - background N blocks from stack
@@ -416,29 +928,19 @@ impl Evaluator {
         for _ in 0..n {
             let block = self.pop_block()?;
             let (cmd, args) = self.block_to_cmd_args(&block)?;
+            let audit = self.start_process("fork", &cmd, &args)?;
             let cmd_str = format!("{} {}", cmd, args.join(" "));
 
             let child = Command::new(&cmd)
                 .args(&args)
                 .current_dir(&self.cwd)
                 .stdin(Stdio::null())
-                .stdout(Stdio::null())
+                .stdout(Stdio::piped())
                 .stderr(Stdio::null())
                 .spawn()
-                .map_err(|e| EvalError::ExecError(e.to_string()))?;
-
-            let pid = child.id();
-            let job_id = self.next_job_id;
-            self.next_job_id += 1;
-
-            self.jobs.push(Job {
-                id: job_id,
-                pid,
-                pgid: pid, // Process group ID same as PID for background jobs
-                command: cmd_str,
-                child: Some(child),
-                status: JobStatus::Running,
-            });
+                .map_err(|e| self.spawn_error(&cmd, e))?;
+
+            let (job_id, pid) = self.start_job(cmd_str, child, audit);
 
             eprintln!("[{}] {}", job_id, pid);
         }
@@ -447,38 +949,104 @@ impl Evaluator {
         Ok(())
     }
 
-    /// Subst: #[cmd] subst - run cmd, push temp file path
+    /// Subst: #[cmd] subst - push a path that reads cmd's output, like
+    /// bash's `<(cmd)`. On Unix the path is `/dev/fd/N` for a pipe that cmd
+    /// writes to as it runs; the pipe stays open until the line finishes.
     pub(crate) fn process_subst(&mut self) -> Result<(), EvalError> {
         let block = self.pop_block()?;
         let (cmd, args) = self.block_to_cmd_args(&block)?;
+        let audit = self.start_process("subst", &cmd, &args)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+            use std::os::unix::process::CommandExt;
 
-        // Create unique temp file
-        static COUNTER: AtomicU64 = AtomicU64::new(0);
-        let suffix = COUNTER.fetch_add(1, Ordering::SeqCst);
-        let temp_path = format!("/tmp/hsab_subst_{}_{}", std::process::id(), suffix);
+            let mut fds = [0; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                let err = std::io::Error::last_os_error();
+                return Err(EvalError::ExecError(format!("subst: pipe failed: {}", err)));
+            }
+            let (read, write) =
+                unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+            // Commands run later inherit the read end (that is what the
+            // path names); only the producer gets the write end
+            unsafe { libc::fcntl(write.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+            let read_fd = read.as_raw_fd();
+
+            let mut command = Command::new(&cmd);
+            command
+                .args(&args)
+                .current_dir(&self.cwd)
+                .stdout(Stdio::from(write));
+            // The producer must not hold the read end, or it never sees
+            // the reader go away
+            unsafe {
+                command.pre_exec(move || {
+                    libc::close(read_fd);
+                    Ok(())
+                });
+            }
+            let child = command.spawn().map_err(|e| self.spawn_error(&cmd, e))?;
+            drop(command);
 
-        // Run command, write output to temp file
-        let output = Command::new(&cmd)
-            .args(&args)
-            .current_dir(&self.cwd)
-            .output()
-            .map_err(|e| EvalError::ExecError(e.to_string()))?;
+            self.subst_streams.push((read, child, audit));
+            self.stack
+                .push(Value::Literal(format!("/dev/fd/{}", read_fd)));
+            self.last_exit_code = 0;
+        }
 
-        self.last_exit_code = output.status.code().unwrap_or(-1);
+        #[cfg(not(unix))]
+        {
+            // Create unique temp file
+            static COUNTER: AtomicU64 = AtomicU64::new(0);
+            let suffix = COUNTER.fetch_add(1, Ordering::SeqCst);
+            let temp_path = format!("/tmp/hsab_subst_{}_{}", std::process::id(), suffix);
+
+            // Run command, write output to temp file
+            let output = Command::new(&cmd)
+                .args(&args)
+                .current_dir(&self.cwd)
+                .output()
+                .map_err(|e| self.spawn_error(&cmd, e))?;
 
-        let mut f = File::create(&temp_path)?;
-        f.write_all(&output.stdout)?;
+            self.last_exit_code = output.status.code().unwrap_or(-1);
+            audit.finish(self.last_exit_code);
 
-        // Push temp file path to stack
-        self.stack.push(Value::Literal(temp_path));
+            let mut f = File::create(&temp_path)?;
+            f.write_all(&output.stdout)?;
+
+            // Push temp file path to stack
+            self.stack.push(Value::Literal(temp_path));
+        }
 
         Ok(())
     }
 
+    /// Close the pipes `subst` opened and reap their producers. A producer
+    /// still writing gets SIGPIPE, as in bash.
+    pub(crate) fn close_substs(&mut self) {
+        #[cfg(unix)]
+        for (read, mut child, audit) in std::mem::take(&mut self.subst_streams) {
+            drop(read);
+            if let Ok(status) = child.wait() {
+                audit.finish(status.code().unwrap_or(-1));
+            }
+        }
+    }
+
+    /// Fifo: #[cmd] fifo - without named pipes, the same as subst
+    #[cfg(not(unix))]
+    pub(crate) fn process_fifo(&mut self) -> Result<(), EvalError> {
+        self.process_subst()
+    }
+
     /// Fifo: #[cmd] fifo - create named pipe, spawn cmd writing to it, push path
+    #[cfg(unix)]
     pub(crate) fn process_fifo(&mut self) -> Result<(), EvalError> {
         let block = self.pop_block()?;
         let (cmd, args) = self.block_to_cmd_args(&block)?;
+        let audit = self.start_process("fifo", &cmd, &args)?;
 
         // Create unique fifo path
         static NEXT_FIFO_ID: AtomicU64 = AtomicU64::new(0);
@@ -486,46 +1054,39 @@ impl Evaluator {
         let fifo_path = format!("/tmp/hsab_fifo_{}_{}", std::process::id(), suffix);
 
         // Create the named pipe using mkfifo
-        #[cfg(unix)]
-        {
-            use std::ffi::CString;
-
-            let c_path = CString::new(fifo_path.clone())
-                .map_err(|e| EvalError::ExecError(format!("fifo: invalid path: {}", e)))?;
-
-            // mkfifo with permissions 0644
-            let result = unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) };
-            if result != 0 {
-                let err = std::io::Error::last_os_error();
-                return Err(EvalError::ExecError(format!(
-                    "fifo: mkfifo failed: {}",
-                    err
-                )));
-            }
-
-            // Spawn command in background, redirecting stdout to the fifo
-            // Run command first, then open fifo to write (opening blocks until reader opens)
-            let fifo_path_clone = fifo_path.clone();
-            let cwd = self.cwd.clone();
-            std::thread::spawn(move || {
-                // Run the command first to get output
-                if let Ok(output) = Command::new(&cmd).args(&args).current_dir(&cwd).output() {
-                    // Now open fifo and write (this blocks until a reader opens)
-                    if let Ok(mut fifo) = std::fs::OpenOptions::new()
-                        .write(true)
-                        .open(&fifo_path_clone)
-                    {
-                        let _ = fifo.write_all(&output.stdout);
-                    }
-                }
-            });
+        let c_path = std::ffi::CString::new(fifo_path.clone())
+            .map_err(|e| EvalError::ExecError(format!("fifo: invalid path: {}", e)))?;
+
+        // mkfifo with permissions 0644
+        let result = unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) };
+        if result != 0 {
+            let err = std::io::Error::last_os_error();
+            return Err(EvalError::ExecError(format!(
+                "fifo: mkfifo failed: {}",
+                err
+            )));
         }
 
-        #[cfg(not(unix))]
-        {
-            // On non-Unix, fall back to subst behavior
-            return self.process_subst();
-        }
+        // Spawn command in background, redirecting stdout to the fifo
+        let fifo_path_clone = fifo_path.clone();
+        let cwd = self.cwd.clone();
+        std::thread::spawn(move || {
+            // Opening blocks until a reader opens; the command then
+            // streams into the fifo as it runs
+            if let Ok(fifo) = std::fs::OpenOptions::new()
+                .write(true)
+                .open(&fifo_path_clone)
+            {
+                if let Ok(status) = Command::new(&cmd)
+                    .args(&args)
+                    .current_dir(&cwd)
+                    .stdout(fifo)
+                    .status()
+                {
+                    audit.finish(status.code().unwrap_or(-1));
+                }
+            }
+        });
 
         // Push fifo path to stack
         self.stack.push(Value::Literal(fifo_path));
@@ -535,34 +1096,37 @@ impl Evaluator {
 
     pub(crate) fn builtin_timeout(&mut self) -> Result<(), EvalError> {
         let block = self.pop_block()?;
-        let seconds_str = self.pop_string()?;
-
-        let seconds: u64 = seconds_str.parse().map_err(|_| EvalError::TypeError {
-            expected: "integer seconds".into(),
-            got: seconds_str,
+        let seconds = self.pop_seconds("timeout")?;
+        let timeout = Duration::try_from_secs_f64(seconds).map_err(|_| EvalError::TypeError {
+            expected: "duration (timeout)".into(),
+            got: seconds.to_string(),
         })?;
 
         let (cmd, args) = self.block_to_cmd_args(&block)?;
+        let audit = self.start_process("timeout", &cmd, &args)?;
 
         let mut child = Command::new(&cmd)
             .args(&args)
             .current_dir(&self.cwd)
             .spawn()
-            .map_err(|e| EvalError::ExecError(e.to_string()))?;
+            .map_err(|e| self.spawn_error(&cmd, e))?;
+        let _foreground = self.cancel.track(child.id());
 
-        let timeout = Duration::from_secs(seconds);
         let start = Instant::now();
 
         loop {
             match child.try_wait() {
                 Ok(Some(status)) => {
                     self.last_exit_code = status.code().unwrap_or(-1);
+                    audit.finish(self.last_exit_code);
                     return Ok(());
                 }
                 Ok(None) => {
                     if start.elapsed() > timeout {
                         let _ = child.kill();
+                        let _ = child.wait();
                         self.last_exit_code = 124; // Standard timeout exit code
+                        audit.finish(self.last_exit_code);
                         return Ok(());
                     }
                     std::thread::sleep(Duration::from_millis(100));
@@ -572,6 +1136,27 @@ impl Evaluator {
         }
     }
 
+    /// pipefail: "on" pipefail / "off" pipefail - make a pipeline fail with
+    /// its first failing stage instead of reporting only the last stage
+    pub(crate) fn builtin_pipefail(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        self.pipefail = match &setting {
+            Value::Bool(on) => *on,
+            other => match other.as_arg().as_deref() {
+                Some("on" | "true" | "1") => true,
+                Some("off" | "false" | "0") => false,
+                _ => {
+                    self.stack.push(setting);
+                    return Err(EvalError::ExecError(
+                        "pipefail: usage: \"on\" pipefail or \"off\" pipefail".into(),
+                    ));
+                }
+            },
+        };
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
     pub(crate) fn builtin_pipestatus(&mut self) -> Result<(), EvalError> {
         let list: Vec<Value> = self
             .pipestatus
@@ -583,3 +1168,44 @@ impl Evaluator {
         Ok(())
     }
 }
+
+/// What a here-string feeds a command: Bytes as they are, and text ending
+/// in a newline
+fn stdin_text(input: Value) -> Vec<u8> {
+    match input {
+        Value::Bytes(bytes) => bytes,
+        other => {
+            let mut text = other.as_arg().unwrap_or_default();
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.into_bytes()
+        }
+    }
+}
+
+/// A heredoc's text without the line break after its opening quotes, and
+/// without the indentation its lines share, so it can be indented with the
+/// script around it
+fn dedent(text: &str) -> String {
+    let text = text
+        .strip_prefix("\r\n")
+        .or_else(|| text.strip_prefix('\n'))
+        .unwrap_or(text);
+    let indent = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    text.split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                ""
+            } else {
+                &line[indent..]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
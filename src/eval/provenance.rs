@@ -0,0 +1,195 @@
+//! Where stack values came from
+//!
+//! Safety features need to tell a path someone typed from one a glob or a
+//! command produced, so values pushed from those sources are tagged with
+//! an `Origin`. Tags live beside the values rather than in `Value`: the
+//! evaluator's `Stack` keeps one slot per value, filled by `push_from` and
+//! emptied by every other push, so a tag goes when its value is popped.
+//! `dup`, `over`, `swap`, and `rot` carry tags along with the values they
+//! move; anything else that replaces a value drops its tag.
+
+use std::fmt;
+use std::ops::{Deref, RangeBounds};
+
+use super::Evaluator;
+use crate::ast::Value;
+
+/// Where a value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Written in the source: a word or a quoted string
+    Typed,
+    /// Paths a glob pattern matched
+    Glob,
+    /// A command's standard output
+    Output,
+    /// A credential, such as an `oauth2-token` access token, and not to
+    /// be shown
+    Secret,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::Typed => "typed",
+            Origin::Glob => "glob",
+            Origin::Output => "output",
+            Origin::Secret => "secret",
+        })
+    }
+}
+
+/// The value stack, with the origin of each value beside it
+///
+/// Reads go through `Deref` to the values; every change goes through a
+/// method here, so the origins stay in step with the values.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Stack {
+    values: Vec<Value>,
+    origins: Vec<Option<Origin>>,
+}
+
+impl Stack {
+    pub(crate) fn push(&mut self, value: Value) {
+        self.push_tagged(value, None);
+    }
+
+    fn push_tagged(&mut self, value: Value, origin: Option<Origin>) {
+        self.values.push(value);
+        self.origins.push(origin);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Value> {
+        self.origins.pop();
+        self.values.pop()
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, value: Value) {
+        self.values.insert(index, value);
+        self.origins.insert(index, None);
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> Value {
+        self.origins.remove(index);
+        self.values.remove(index)
+    }
+
+    pub(crate) fn swap(&mut self, a: usize, b: usize) {
+        self.values.swap(a, b);
+        self.origins.swap(a, b);
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.values.truncate(len);
+        self.origins.truncate(len);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub(crate) fn split_off(&mut self, at: usize) -> Vec<Value> {
+        self.origins.truncate(at);
+        self.values.split_off(at)
+    }
+
+    pub(crate) fn drain<R: RangeBounds<usize> + Clone>(
+        &mut self,
+        range: R,
+    ) -> std::vec::Drain<'_, Value> {
+        self.origins.drain(range.clone());
+        self.values.drain(range)
+    }
+
+    /// The values from `start` to the top, to change in place; they lose
+    /// their origins
+    pub(crate) fn tail_mut(&mut self, start: usize) -> &mut [Value] {
+        for origin in &mut self.origins[start..] {
+            *origin = None;
+        }
+        &mut self.values[start..]
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<Value> {
+        self.values
+    }
+}
+
+impl Deref for Stack {
+    type Target = [Value];
+
+    fn deref(&self) -> &[Value] {
+        &self.values
+    }
+}
+
+impl From<Vec<Value>> for Stack {
+    fn from(values: Vec<Value>) -> Self {
+        let origins = vec![None; values.len()];
+        Stack { values, origins }
+    }
+}
+
+impl From<Stack> for Vec<Value> {
+    fn from(stack: Stack) -> Self {
+        stack.values
+    }
+}
+
+impl Extend<Value> for Stack {
+    fn extend<I: IntoIterator<Item = Value>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl IntoIterator for Stack {
+    type Item = Value;
+    type IntoIter = std::vec::IntoIter<Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Stack {
+    type Item = &'a Value;
+    type IntoIter = std::slice::Iter<'a, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl Evaluator {
+    /// Where the value `index` places from the top of the stack came from,
+    /// if known (0 is the top, as in `${.0}`)
+    pub fn origin(&self, index: usize) -> Option<Origin> {
+        let depth = self.stack.len().checked_sub(index + 1)?;
+        self.origin_at(depth)
+    }
+
+    /// The origin of the value at `depth` from the bottom
+    pub(crate) fn origin_at(&self, depth: usize) -> Option<Origin> {
+        self.stack.origins.get(depth).copied().flatten()
+    }
+
+    /// Push `value`, recording where it came from
+    pub(crate) fn push_from(&mut self, value: Value, origin: Origin) {
+        self.stack.push_tagged(value, Some(origin));
+    }
+
+    /// Record the origin of the value now at `depth`, after a stack
+    /// operation moved or copied it there
+    pub(crate) fn set_origin(&mut self, depth: usize, origin: Option<Origin>) {
+        if let Some(slot) = self.stack.origins.get_mut(depth) {
+            *slot = origin;
+        }
+    }
+
+    /// The origins of the values from `depth` to the top, bottom first
+    pub(crate) fn origins_from(&self, depth: usize) -> Vec<Option<Origin>> {
+        self.stack.origins.get(depth..).unwrap_or_default().to_vec()
+    }
+}
@@ -0,0 +1,82 @@
+//! Rate limits: running a block at most N times per time window
+//!
+//! `#[url fetch] 5 per-minute` runs the block, first waiting if it already
+//! ran 5 times in the last minute. Each block is its own bucket unless
+//! `rate-limit` names one, so different blocks can share a limit:
+//!
+//! ```text
+//! #[... gh-api] "github" 30 60 rate-limit   # 30 per 60 seconds, shared
+//! #[... gh-api] "github" 5000 "1h" rate-limit   # 5000 an hour
+//! ```
+//!
+//! Buckets last for the session. Attempts by `retry` and `retry-delay`
+//! count, so a limited block inside them stays within its rate.
+
+use std::time::{Duration, Instant};
+
+use super::{EvalError, Evaluator};
+use crate::ast::{exprs_to_source, Expr};
+
+impl Evaluator {
+    /// rate-limit: #[block] "bucket" N seconds rate-limit
+    /// Run the block, first waiting until the bucket has run fewer than N
+    /// blocks in the last `seconds`, which may be a duration like "1m"
+    pub(crate) fn builtin_rate_limit(&mut self) -> Result<(), EvalError> {
+        let seconds = self.pop_seconds("rate-limit")?;
+        let limit = self.pop_int("rate-limit")?;
+        let bucket = self.pop_string()?;
+        let block = self.pop_block()?;
+        self.run_rate_limited("rate-limit", &block, bucket, limit, seconds)
+    }
+
+    /// per-second, per-minute, per-hour: #[block] N per-minute
+    /// Run the block at most N times per window, waiting when needed
+    pub(crate) fn builtin_per_window(&mut self, word: &str, seconds: f64) -> Result<(), EvalError> {
+        let limit = self.pop_int(word)?;
+        let block = self.pop_block()?;
+        let bucket = exprs_to_source(&block);
+        self.run_rate_limited(word, &block, bucket, limit, seconds)
+    }
+
+    /// Wait for a slot in `bucket`, then run `block`
+    fn run_rate_limited(
+        &mut self,
+        word: &str,
+        block: &[Expr],
+        bucket: String,
+        limit: i64,
+        seconds: f64,
+    ) -> Result<(), EvalError> {
+        let limit = usize::try_from(limit)
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| EvalError::ExecError(format!("{}: count must be > 0", word)))?;
+        let window = Duration::try_from_secs_f64(seconds)
+            .map_err(|_| EvalError::ExecError(format!("{}: bad time window {}", word, seconds)))?;
+
+        loop {
+            let now = Instant::now();
+            let runs = self.rate_buckets.entry(bucket.clone()).or_default();
+            while runs
+                .front()
+                .is_some_and(|&ran| now.duration_since(ran) >= window)
+            {
+                runs.pop_front();
+            }
+            match runs.front() {
+                Some(&oldest) if runs.len() >= limit => {
+                    self.pause(window.saturating_sub(now.duration_since(oldest)))?;
+                }
+                _ => {
+                    runs.push_back(now);
+                    break;
+                }
+            }
+        }
+
+        for expr in block {
+            self.eval_expr(expr)?;
+        }
+        Ok(())
+    }
+}
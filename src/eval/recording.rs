@@ -0,0 +1,225 @@
+//! Time-travel recording: `.record` writes one JSON line per evaluated
+//! expression (its source, the stack delta, exit code, and any error) so a
+//! run can be stepped through later with `.replay` without re-executing
+//! anything.
+//!
+//! Steps are the expressions the debugger steps through. Deltas are taken
+//! against the stack as of the previous recorded step, so applying them in
+//! order rebuilds the stack at every step even when builtins rearrange it
+//! between steps (`map`, `each`, ...). Values use the tagged encoding from
+//! [`crate::value_serde`], so replayed stacks keep their exact types.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use super::{EvalError, Evaluator};
+use crate::ast::{Expr, Value};
+
+/// Format marker on a recording's first line
+const RECORDING_VERSION: i64 = 2;
+
+/// An active recording: the open file and the stack as last written
+pub(crate) struct Recorder {
+    file: File,
+    last_stack: Vec<Value>,
+    steps: usize,
+}
+
+/// One step of a recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedStep {
+    /// Source of the expression that ran
+    pub expr: String,
+    /// Values removed from the top of the stack
+    pub pop: usize,
+    /// Values pushed afterwards, bottom first
+    pub push: Vec<Value>,
+    /// Exit code after the step
+    pub exit_code: i32,
+    /// Error message if the step failed
+    pub error: Option<String>,
+}
+
+/// A loaded recording, for offline replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    /// Stack when recording started
+    pub initial: Vec<Value>,
+    pub steps: Vec<RecordedStep>,
+}
+
+impl Recording {
+    /// Read a recording written by `.record` or `--record`
+    pub fn load(path: &str) -> Result<Recording, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut lines = BufReader::new(file).lines();
+        let header: JsonValue = match lines.next() {
+            Some(line) => serde_json::from_str(&line.map_err(|e| e.to_string())?)
+                .map_err(|e| e.to_string())?,
+            None => return Err(format!("{}: empty recording", path)),
+        };
+        if header["hsab_recording"].as_i64() != Some(RECORDING_VERSION) {
+            return Err(format!("{}: not an hsab recording", path));
+        }
+        let initial = values_from_json(&header["stack"]);
+
+        let lines: Vec<String> = lines.collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+        let mut steps = Vec::new();
+        for (n, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Ok(step) = serde_json::from_str::<JsonValue>(line) else {
+                // A run killed mid-write leaves a truncated last line
+                if n + 1 == lines.len() {
+                    break;
+                }
+                return Err(format!("{}: bad step at line {}", path, n + 2));
+            };
+            steps.push(RecordedStep {
+                expr: step["expr"].as_str().unwrap_or("").to_string(),
+                pop: step["pop"].as_u64().unwrap_or(0) as usize,
+                push: values_from_json(&step["push"]),
+                exit_code: step["exit"].as_i64().unwrap_or(0) as i32,
+                error: step["error"].as_str().map(str::to_string),
+            });
+        }
+        Ok(Recording { initial, steps })
+    }
+
+    /// Stack after the first `n` steps (0 = when recording started)
+    pub fn stack_at(&self, n: usize) -> Vec<Value> {
+        let mut stack = self.initial.clone();
+        for step in self.steps.iter().take(n) {
+            stack.truncate(stack.len().saturating_sub(step.pop));
+            stack.extend(step.push.iter().cloned());
+        }
+        stack
+    }
+
+    /// Describe step `n` (1-based; 0 = start) with the stack after it
+    pub fn format_step(&self, n: usize) -> String {
+        let stack: Vec<String> = self
+            .stack_at(n)
+            .iter()
+            .map(crate::display::format_value_hint)
+            .collect();
+        let header = match n.checked_sub(1).and_then(|i| self.steps.get(i)) {
+            Some(step) => {
+                let status = match &step.error {
+                    Some(e) => format!("\x1b[31merror: {}\x1b[0m", e),
+                    None => format!("exit {}", step.exit_code),
+                };
+                format!(
+                    "\x1b[36m[{}/{}]\x1b[0m {}  ({})",
+                    n,
+                    self.steps.len(),
+                    step.expr,
+                    status
+                )
+            }
+            None => format!("\x1b[36m[0/{}]\x1b[0m (start)", self.steps.len()),
+        };
+        format!("{}\n  stack: [{}]", header, stack.join(", "))
+    }
+
+    /// Step through the recording interactively on stdin
+    pub fn replay_prompt(&self) {
+        const PROMPT: &str = "\x1b[90m(n)ext, (p)rev, (g)oto N, (e)nd, (q)uit replay: \x1b[0m";
+        let last = self.steps.len();
+        let mut pos = 0;
+        loop {
+            crate::display::eprintln_styled(&self.format_step(pos));
+            crate::display::eprintln_styled(PROMPT);
+            let mut input = String::new();
+            match std::io::stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let cmd = input.trim().to_lowercase();
+            match cmd.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] | ["n"] | ["next"] => pos = (pos + 1).min(last),
+                ["p"] | ["prev"] => pos = pos.saturating_sub(1),
+                ["e"] | ["end"] => pos = last,
+                ["g" | "goto", n] => match n.parse::<usize>() {
+                    Ok(n) => pos = n.min(last),
+                    Err(_) => eprintln!("goto needs a step number"),
+                },
+                ["q"] | ["quit"] => break,
+                _ => crate::display::eprintln_styled(&format!(
+                    "\x1b[31mUnknown command: {}\x1b[0m",
+                    cmd
+                )),
+            }
+        }
+    }
+}
+
+fn values_from_json(json: &JsonValue) -> Vec<Value> {
+    Vec::<Value>::deserialize(json).unwrap_or_default()
+}
+
+impl Evaluator {
+    /// Start recording every step to `path` (truncating it). A restricted
+    /// shell can't, since that writes a file.
+    pub fn start_recording(&mut self, path: &str) -> Result<(), String> {
+        if self.is_restricted() {
+            return Err("restricted: can't write files".into());
+        }
+        let mut file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let header = json!({
+            "hsab_recording": RECORDING_VERSION,
+            "stack": json!(&self.stack[..]),
+        });
+        writeln!(file, "{}", header).map_err(|e| e.to_string())?;
+        self.recorder = Some(Recorder {
+            file,
+            last_stack: self.stack.to_vec(),
+            steps: 0,
+        });
+        Ok(())
+    }
+
+    /// Stop recording, returning the number of steps written
+    pub fn stop_recording(&mut self) -> Option<usize> {
+        self.recorder.take().map(|r| r.steps)
+    }
+
+    /// Check if a recording is active
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Append one step (called after each expression while recording)
+    pub(crate) fn record_step(&mut self, expr: &Expr, result: &Result<(), EvalError>) {
+        let Some(mut recorder) = self.recorder.take() else {
+            return;
+        };
+        let common = recorder
+            .last_stack
+            .iter()
+            .zip(&self.stack)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut step = json!({
+            "expr": self.expr_to_string(expr),
+            "pop": recorder.last_stack.len() - common,
+            "push": json!(self.stack[common..]),
+            "exit": self.last_exit_code,
+        });
+        if let Err(e) = result {
+            step["error"] = json!(e.to_string());
+        }
+        // Flush each step so a crashed run still leaves its history
+        if let Err(e) = writeln!(recorder.file, "{}", step).and_then(|_| recorder.file.flush()) {
+            eprintln!("Recording stopped: {}", e);
+            return;
+        }
+        recorder.steps += 1;
+        recorder.last_stack = self.stack.to_vec();
+        self.recorder = Some(recorder);
+    }
+}
@@ -2,7 +2,25 @@ use super::{EvalError, Evaluator};
 use crate::ast::Value;
 use indexmap::IndexMap;
 use serde_json::Value as JsonValue;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use super::config_formats::render_config;
+
+/// The format a file's name says it has: its extension, lowercased, or
+/// `env` for `.env` files (`.env`, `.env.local`), which have none of their own
+fn file_format(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    if name == ".env" || name.starts_with(".env.") {
+        return "env".to_string();
+    }
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default()
+}
 
 impl Evaluator {
     pub(crate) fn json_parse(&mut self) -> Result<(), EvalError> {
@@ -333,7 +351,7 @@ impl Evaluator {
     }
 
     /// open: Open a file and parse it based on extension
-    /// Supports: .json, .csv, .tsv, plain text
+    /// Supports: .json, .csv, .tsv, .ini, .env, plain text
     pub(crate) fn builtin_open(&mut self) -> Result<(), EvalError> {
         use std::fs;
 
@@ -355,11 +373,7 @@ impl Evaluator {
         })?;
 
         // Determine format based on extension
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_lowercase())
-            .unwrap_or_default();
+        let ext = file_format(&path);
 
         match ext.as_str() {
             "json" => {
@@ -377,6 +391,14 @@ impl Evaluator {
                 self.stack.push(Value::Literal(content));
                 self.builtin_into_tsv()?;
             }
+            "ini" => {
+                self.stack.push(Value::Literal(content));
+                self.builtin_from_ini()?;
+            }
+            "env" => {
+                self.stack.push(Value::Literal(content));
+                self.builtin_from_env()?;
+            }
             _ => {
                 // Plain text - just push as output
                 self.stack.push(Value::Output(content));
@@ -409,11 +431,7 @@ impl Evaluator {
         let path = PathBuf::from(self.expand_tilde(&path_str));
 
         // Determine format based on extension
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_lowercase())
-            .unwrap_or_default();
+        let ext = file_format(&path);
 
         let content = match ext.as_str() {
             "json" => {
@@ -452,6 +470,10 @@ impl Evaluator {
                     _ => data_val.as_arg().unwrap_or_default(),
                 }
             }
+            "ini" | "env" => match render_config(&ext, &data_val) {
+                Some(text) => text?,
+                None => data_val.as_arg().unwrap_or_default(),
+            },
             _ => {
                 // Plain text
                 data_val.as_arg().unwrap_or_default()
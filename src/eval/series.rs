@@ -0,0 +1,170 @@
+//! Time series for watching a number: `series`, `series-stats`, `sparkline`
+//!
+//! ```text
+//! #["/proc/loadavg" open " " split1 drop "load" swap series 5 sleep] 60 times
+//! "load" series-stats → { count first last min max mean stdev change }
+//! "load" sparkline    → "▁▂▂▃▅▇█▆▃▂"
+//! ```
+//!
+//! `series` appends a sample, stamped with the local time, and drops the
+//! oldest past the bound (100, or an Int after the value), so a loop can
+//! poll forever. Samples go into a series kept under a name, which outlives
+//! each pass of a loop, or into one on the stack: a Table of `time value`,
+//! started from `[]`. `series-stats` and `sparkline` take either, or a list
+//! of numbers; a sparkline is drawn by `display`, to fit the terminal.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// How many samples a series keeps unless told otherwise
+const DEFAULT_BOUND: usize = 100;
+
+/// A sample's value as a number, if it is one
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Number(n) => Some(*n),
+        Value::Literal(s) | Value::Output(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn series_table(rows: Vec<Vec<Value>>) -> Value {
+    Value::Table {
+        columns: vec!["time".to_string(), "value".to_string()],
+        rows,
+    }
+}
+
+impl Evaluator {
+    /// The numbers in a named series, a series Table, or a list
+    fn series_values(&mut self, op: &str) -> Result<Vec<f64>, EvalError> {
+        let (rows, at) = match self.pop_value_or_err()? {
+            Value::Literal(name) | Value::Output(name) => {
+                let name = name.trim();
+                let rows = self.series.get(name).ok_or_else(|| {
+                    EvalError::ExecError(format!("{}: no series named '{}'", op, name))
+                })?;
+                (rows.clone(), 1)
+            }
+            Value::Table { columns, rows } => {
+                let at = columns.iter().position(|c| c == "value").ok_or_else(|| {
+                    EvalError::ExecError(format!("{}: the table has no value column", op))
+                })?;
+                (rows, at)
+            }
+            Value::List(items) => return Ok(items.iter().filter_map(number).collect()),
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: format!("series name, series, or list of numbers ({})", op),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        Ok(rows.iter().filter_map(|row| number(row.get(at)?)).collect())
+    }
+
+    /// series: target value [bound] series
+    /// Append a sample to the series named by target, or to the series
+    /// target is (pushing it back)
+    pub(crate) fn builtin_series(&mut self) -> Result<(), EvalError> {
+        // With a bound there are two numbers on top, not one
+        let len = self.stack.len();
+        let bounded = len >= 3 && number(&self.stack[len - 2]).is_some();
+        let bound = if bounded {
+            let bound = self.pop_int("series")?;
+            usize::try_from(bound)
+                .ok()
+                .filter(|b| *b > 0)
+                .ok_or_else(|| EvalError::ExecError(format!("series: bad bound {}", bound)))?
+        } else {
+            DEFAULT_BOUND
+        };
+        let sample = match self.pop_value_or_err()? {
+            sample @ (Value::Int(_) | Value::Number(_)) => sample,
+            other => Value::Number(number(&other).ok_or_else(|| EvalError::TypeError {
+                expected: "number (series)".into(),
+                got: other.type_name().to_string(),
+            })?),
+        };
+        let time = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f");
+        let row = vec![Value::Literal(time.to_string()), sample];
+        let append = |rows: &mut Vec<Vec<Value>>| {
+            rows.push(row);
+            if rows.len() > bound {
+                rows.drain(..rows.len() - bound);
+            }
+        };
+
+        match self.pop_value_or_err()? {
+            Value::Literal(name) | Value::Output(name) => {
+                append(self.series.entry(name.trim().to_string()).or_default());
+            }
+            Value::Table { columns, mut rows } if columns == ["time", "value"] => {
+                append(&mut rows);
+                self.stack.push(series_table(rows));
+            }
+            Value::List(items) if items.is_empty() => {
+                let mut rows = Vec::new();
+                append(&mut rows);
+                self.stack.push(series_table(rows));
+            }
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "series name, or series (a time/value Table, or [] to start one)"
+                        .into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// series-stats: series series-stats → { count first last min max mean stdev change }
+    pub(crate) fn builtin_series_stats(&mut self) -> Result<(), EvalError> {
+        let numbers = self.series_values("series-stats")?;
+        let count = numbers.len();
+        let number_or_nil = |n: Option<f64>| n.map_or(Value::Nil, Value::Number);
+
+        let mean = (count > 0).then(|| numbers.iter().sum::<f64>() / count as f64);
+        let stdev = mean.map(|mean| {
+            let variance = numbers.iter().map(|n| (n - mean).powi(2)).sum::<f64>() / count as f64;
+            variance.sqrt()
+        });
+        let (first, last) = (numbers.first().copied(), numbers.last().copied());
+
+        let mut stats = IndexMap::new();
+        stats.insert("count".to_string(), Value::Int(count as i64));
+        stats.insert("first".to_string(), number_or_nil(first));
+        stats.insert("last".to_string(), number_or_nil(last));
+        stats.insert(
+            "min".to_string(),
+            number_or_nil(numbers.iter().copied().reduce(f64::min)),
+        );
+        stats.insert(
+            "max".to_string(),
+            number_or_nil(numbers.iter().copied().reduce(f64::max)),
+        );
+        stats.insert("mean".to_string(), number_or_nil(mean));
+        stats.insert("stdev".to_string(), number_or_nil(stdev));
+        stats.insert(
+            "change".to_string(),
+            number_or_nil(first.zip(last).map(|(first, last)| last - first)),
+        );
+        self.stack.push(Value::Map(stats));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// sparkline: series sparkline → "▁▃▅▇"
+    pub(crate) fn builtin_sparkline(&mut self) -> Result<(), EvalError> {
+        let numbers = self.series_values("sparkline")?;
+        let line = crate::display::sparkline(&numbers, super::terminal::terminal_width());
+        self.stack.push(Value::Literal(line));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+//! The `hsab-config` builtin: hsab's settings as a record
+//!
+//! ```text
+//! hsab-config                   → {max_recursion 10000 preview_len 8 strict false ...}
+//! hsab-config "preview_len" get → 8
+//! ```
+//!
+//! It isn't plain `config`, so `user.name config git` still runs
+//! `git config user.name`.
+//!
+//! A setting is read as `hsab config` reads it: its environment variable,
+//! then `~/.hsab/config.toml`, then the default, and Nil if none of those
+//! has it. `strict`, `pipefail`, `max_recursion`, and `preview_len` are
+//! what this evaluator is using, since hsab.toml and `pipefail` change them
+//! after startup.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+use crate::config::{Kind, UserConfig, SETTINGS};
+
+impl Evaluator {
+    /// hsab-config: hsab-config → {setting value ...}
+    pub(crate) fn builtin_config(&mut self) -> Result<(), EvalError> {
+        let file =
+            UserConfig::load().map_err(|e| EvalError::ExecError(format!("config: {}", e)))?;
+        let mut record = IndexMap::new();
+        for setting in SETTINGS {
+            let value = match setting.key {
+                "max_recursion" => Value::Number(self.max_call_depth as f64),
+                "preview_len" => Value::Number(self.preview_len as f64),
+                "strict" => Value::Bool(self.strict_mode),
+                "pipefail" => Value::Bool(self.pipefail),
+                _ => {
+                    let text = super::env::var(setting.var)
+                        .ok()
+                        .or_else(|| file.get(setting.key))
+                        .or_else(|| setting.default.map(str::to_string));
+                    match (text, setting.kind) {
+                        (None, _) => Value::Nil,
+                        (Some(text), Kind::Bool) => Value::Bool(crate::config::is_on(&text)),
+                        (Some(text), Kind::Int) => match text.trim().parse::<f64>() {
+                            Ok(n) => Value::Number(n),
+                            Err(_) => Value::Literal(text),
+                        },
+                        (Some(text), Kind::Text) => Value::Literal(text),
+                    }
+                }
+            };
+            record.insert(setting.key.to_string(), value);
+        }
+        self.stack.push(Value::Map(record));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
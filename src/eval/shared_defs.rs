@@ -0,0 +1,230 @@
+//! Definitions shared live between concurrent REPLs
+//!
+//! With sharing on, every definition made in a session is published to
+//! `~/.hsab/session-defs`, and definitions other sessions published there
+//! are picked up. Each entry carries the time it was written; when two
+//! sessions redefine the same word, the later write wins. Definitions that
+//! were loaded at startup (stdlib, rc files) are not published until they
+//! are redefined. A sync holds a lock on `~/.hsab/session-defs.lock`, so
+//! sessions syncing at once don't overwrite each other's definitions.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::Evaluator;
+
+/// Format marker stored in the shared definitions file
+const SHARED_DEFS_VERSION: i64 = 1;
+
+/// State of an active sharing session
+pub(crate) struct SharedDefs {
+    path: PathBuf,
+    /// Source of each definition as of the last sync
+    synced: HashMap<String, String>,
+    /// File modification time when last read
+    mtime: Option<SystemTime>,
+}
+
+/// What a sync exchanged with the shared file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    /// Local definitions written to the file
+    pub published: Vec<String>,
+    /// Definitions taken from other sessions
+    pub received: Vec<String>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Entries in the shared file: name -> (source, write time)
+fn read_shared(path: &Path) -> Result<HashMap<String, (String, u64)>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let doc: JsonValue =
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if doc["hsab_shared_defs"].as_i64() != Some(SHARED_DEFS_VERSION) {
+        return Err(format!("{}: not an hsab definitions file", path.display()));
+    }
+    let mut entries = HashMap::new();
+    if let Some(defs) = doc["defs"].as_object() {
+        for (name, entry) in defs {
+            if let Some(source) = entry["source"].as_str() {
+                let at = entry["at"].as_u64().unwrap_or(0);
+                entries.insert(name.clone(), (source.to_string(), at));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Lock `<path>.lock` until the returned file is dropped, so a session's
+/// read, merge, and write isn't interleaved with another's
+fn lock_shared(path: &Path) -> Result<File, String> {
+    let lock_path = path.with_extension("lock");
+    let lock = || -> std::io::Result<File> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        file.lock()?;
+        Ok(file)
+    };
+    lock().map_err(|e| format!("{}: {}", lock_path.display(), e))
+}
+
+fn write_shared(path: &Path, entries: &HashMap<String, (String, u64)>) -> Result<(), String> {
+    let mut names: Vec<&String> = entries.keys().collect();
+    names.sort();
+    let mut defs = JsonMap::new();
+    for name in names {
+        let (source, at) = &entries[name];
+        defs.insert(name.clone(), json!({ "source": source, "at": at }));
+    }
+    let doc = json!({ "hsab_shared_defs": SHARED_DEFS_VERSION, "defs": defs });
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Per-process temp file, renamed into place so readers never see
+        // a half-written file
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_string_pretty(&doc)?)?;
+        std::fs::rename(&tmp, path)
+    };
+    write().map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+impl Evaluator {
+    /// Source of every current definition, by name
+    pub fn definition_sources(&self) -> HashMap<String, String> {
+        self.definitions
+            .iter()
+            .map(|(name, body)| (name.clone(), crate::ast::exprs_to_source(body)))
+            .collect()
+    }
+
+    /// Start sharing definitions through `path`. Definitions that still
+    /// match `baseline` (from [`Evaluator::definition_sources`], typically
+    /// taken after startup files load) stay private; the rest are published
+    /// and anything already in the file is applied.
+    pub fn enable_shared_definitions(
+        &mut self,
+        path: &Path,
+        baseline: HashMap<String, String>,
+    ) -> Result<SyncReport, String> {
+        // Syncing writes the shared file
+        if self.is_restricted() {
+            return Err("restricted: can't write files".into());
+        }
+        self.shared_defs = Some(SharedDefs {
+            path: path.to_path_buf(),
+            synced: baseline,
+            mtime: None,
+        });
+        self.sync_shared_definitions()
+    }
+
+    /// Check if definitions are being shared
+    pub fn shares_definitions(&self) -> bool {
+        self.shared_defs.is_some()
+    }
+
+    /// Sync only if something could have changed: a definition was made
+    /// here, or another session wrote the file (checked before each prompt)
+    pub fn poll_shared_definitions(&mut self) -> Result<SyncReport, String> {
+        let Some(shared) = &self.shared_defs else {
+            return Ok(SyncReport::default());
+        };
+        let file_changed = modified(&shared.path) != shared.mtime;
+        let local_changed = self.definitions.iter().any(|(name, body)| {
+            shared.synced.get(name) != Some(&crate::ast::exprs_to_source(body))
+        });
+        if file_changed || local_changed {
+            self.sync_shared_definitions()
+        } else {
+            Ok(SyncReport::default())
+        }
+    }
+
+    /// Publish definitions made since the last sync and take in those other
+    /// sessions wrote. A word changed both here and elsewhere keeps the
+    /// later write, which is ours: it happened after we last read the file.
+    pub fn sync_shared_definitions(&mut self) -> Result<SyncReport, String> {
+        let Some(mut shared) = self.shared_defs.take() else {
+            return Ok(SyncReport::default());
+        };
+        let result = self.sync_with(&mut shared);
+        self.shared_defs = Some(shared);
+        result
+    }
+
+    fn sync_with(&mut self, shared: &mut SharedDefs) -> Result<SyncReport, String> {
+        let _lock = lock_shared(&shared.path)?;
+        let mut entries = read_shared(&shared.path)?;
+        let mut report = SyncReport::default();
+
+        let now = now_millis();
+        for (name, body) in &self.definitions {
+            let source = crate::ast::exprs_to_source(body);
+            if shared.synced.get(name) != Some(&source) {
+                entries.insert(name.clone(), (source, now));
+                report.published.push(name.clone());
+            }
+        }
+
+        for (name, (source, _)) in &entries {
+            if report.published.contains(name) || shared.synced.get(name) == Some(source) {
+                continue;
+            }
+            let body = crate::lex(source)
+                .map_err(|e| e.to_string())
+                .and_then(|tokens| crate::parse(tokens).map_err(|e| e.to_string()));
+            match body {
+                Ok(program) => {
+                    self.definitions.insert(name.clone(), program.expressions);
+                    report.received.push(name.clone());
+                }
+                Err(e) => eprintln!("Skipping shared definition '{}': {}", name, e),
+            }
+        }
+
+        if !report.published.is_empty() {
+            write_shared(&shared.path, &entries)?;
+        }
+        for name in report.published.iter().chain(&report.received) {
+            if let Some(body) = self.definitions.get(name) {
+                shared
+                    .synced
+                    .insert(name.clone(), crate::ast::exprs_to_source(body));
+            }
+        }
+        // Remember unparseable entries too, so they aren't retried each prompt
+        for (name, (source, _)) in entries {
+            shared.synced.entry(name).or_insert(source);
+        }
+        shared.mtime = modified(&shared.path);
+        report.published.sort();
+        report.received.sort();
+        Ok(report)
+    }
+}
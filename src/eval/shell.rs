@@ -1,9 +1,38 @@
-use super::{EvalError, Evaluator, JobStatus};
-use crate::ast::Value;
+use super::helpers::Num;
+use super::{EvalError, Evaluator, Job, JobStatus};
+use crate::ast::{Expr, Value};
 use crate::resolver::ExecutableResolver;
+use crate::util::lock_or_recover;
+use num_bigint::BigInt;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
+/// The columns of `jobs-table`, and the fields of `JOB_DONE`'s record
+const JOB_COLUMNS: [&str; 6] = ["id", "pid", "status", "started", "command", "runtime"];
+
+/// A job's row in `jobs-table`
+fn job_row(job: &Job) -> Vec<Value> {
+    let status = match &job.status {
+        JobStatus::Running => "running".to_string(),
+        JobStatus::Stopped => "stopped".to_string(),
+        JobStatus::Done(0) => "done".to_string(),
+        JobStatus::Done(code) => format!("exit {}", code),
+    };
+    let runtime = job.runtime.unwrap_or_else(|| {
+        (chrono::Local::now() - job.started)
+            .to_std()
+            .unwrap_or_default()
+    });
+    vec![
+        Value::Int(job.id as i64),
+        Value::Int(i64::from(job.pid)),
+        Value::Literal(status),
+        Value::Literal(job.started.format("%Y-%m-%d %H:%M:%S").to_string()),
+        Value::Literal(job.command.clone()),
+        Value::Number((runtime.as_secs_f64() * 1000.0).round() / 1000.0),
+    ]
+}
+
 impl Evaluator {
     pub(crate) fn builtin_pwd(&mut self) -> Result<(), EvalError> {
         self.stack
@@ -91,11 +120,13 @@ impl Evaluator {
     pub(crate) fn builtin_export(&mut self, args: &[String]) -> Result<(), EvalError> {
         for arg in args.iter() {
             if let Some((key, value)) = arg.split_once('=') {
-                std::env::set_var(key, value);
+                self.check_env_write(key)?;
+                super::env::set_var(key, value);
             } else if args.len() >= 2 {
                 let name = &args[0];
                 let value = &args[1];
-                std::env::set_var(name, value);
+                self.check_env_write(name)?;
+                super::env::set_var(name, value);
                 break;
             }
         }
@@ -105,7 +136,8 @@ impl Evaluator {
 
     pub(crate) fn builtin_unset(&mut self, args: &[String]) -> Result<(), EvalError> {
         for var in args {
-            std::env::remove_var(var);
+            self.check_env_write(var)?;
+            super::env::remove_var(var);
         }
         self.last_exit_code = 0;
         Ok(())
@@ -113,7 +145,7 @@ impl Evaluator {
 
     pub(crate) fn builtin_env(&mut self) -> Result<(), EvalError> {
         let mut output = String::new();
-        for (key, value) in std::env::vars() {
+        for (key, value) in super::env::vars() {
             output.push_str(&format!("{}={}\n", key, value));
         }
         self.stack.push(Value::Output(output));
@@ -150,6 +182,119 @@ impl Evaluator {
         Ok(())
     }
 
+    /// jobs-table: jobs-table → Table
+    /// The background jobs as a table of id, pid, status, started, command,
+    /// and runtime (seconds)
+    pub(crate) fn builtin_jobs_table(&mut self) -> Result<(), EvalError> {
+        self.update_job_statuses();
+        let columns = JOB_COLUMNS.iter().map(|c| c.to_string()).collect();
+        let rows = self.jobs.iter().map(job_row).collect();
+        self.stack.push(Value::Table { columns, rows });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// job-output: %N job-output → Output
+    /// What a finished background job wrote to stdout
+    pub(crate) fn builtin_job_output(&mut self) -> Result<(), EvalError> {
+        let spec = self.pop_string()?;
+        let id: usize = spec
+            .trim_start_matches('%')
+            .parse()
+            .map_err(|_| EvalError::ExecError(format!("job-output: invalid job: {}", spec)))?;
+        self.update_job_statuses();
+        let job =
+            self.jobs.iter().find(|j| j.id == id).ok_or_else(|| {
+                EvalError::ExecError(format!("job-output: no such job: {}", spec))
+            })?;
+        if !matches!(job.status, JobStatus::Done(_)) {
+            return Err(EvalError::ExecError(format!(
+                "job-output: job {} is still running",
+                id
+            )));
+        }
+        let bytes = job
+            .output
+            .as_ref()
+            .map(|buffer| lock_or_recover(buffer).clone())
+            .unwrap_or_default();
+        if bytes.is_empty() {
+            self.stack.push(Value::Nil);
+        } else {
+            self.stack
+                .push(Value::Output(self.output_encoding.decode(&bytes)));
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// disown: %N disown, or disown for the most recent job
+    /// Stop managing a job without killing it: it leaves `jobs`, and is
+    /// reaped quietly when it exits
+    pub(crate) fn builtin_disown(&mut self) -> Result<(), EvalError> {
+        let index = match self.stack.last().and_then(Value::as_arg) {
+            Some(spec) if spec.starts_with('%') => {
+                self.stack.pop();
+                let id: usize = spec[1..]
+                    .parse()
+                    .map_err(|_| EvalError::ExecError(format!("disown: invalid job: {}", spec)))?;
+                self.jobs
+                    .iter()
+                    .position(|j| j.id == id)
+                    .ok_or_else(|| EvalError::ExecError(format!("disown: no such job: {}", spec)))?
+            }
+            _ => self
+                .jobs
+                .len()
+                .checked_sub(1)
+                .ok_or_else(|| EvalError::ExecError("disown: no current job".into()))?,
+        };
+
+        let mut job = self.jobs.remove(index);
+        if let (JobStatus::Running | JobStatus::Stopped, Some(mut child)) =
+            (&job.status, job.child.take())
+        {
+            let audit = job.audit.take();
+            std::thread::spawn(move || {
+                let code = child.wait().ok().and_then(|s| s.code()).unwrap_or(-1);
+                if let Some(entry) = audit {
+                    entry.finish(code);
+                }
+            });
+        }
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Run the `JOB_DONE` definition, if there is one, for each background
+    /// job reaped since the last call, with the job's record (the columns of
+    /// `jobs-table`) on an otherwise empty stack. The stack and exit code
+    /// are left as they were.
+    pub fn run_job_done_hook(&mut self) {
+        let finished = std::mem::take(&mut self.finished_jobs);
+        if !self.definitions.contains_key("JOB_DONE") {
+            return;
+        }
+        for id in finished {
+            let Some(job) = self.jobs.iter().find(|j| j.id == id) else {
+                continue;
+            };
+            let record = JOB_COLUMNS
+                .iter()
+                .map(|c| c.to_string())
+                .zip(job_row(job))
+                .collect();
+            let saved_stack = std::mem::replace(&mut self.stack, vec![Value::Map(record)].into());
+            let saved_exit_code = self.last_exit_code;
+            self.capture_mode = false;
+            if let Err(e) = self.eval_expr(&Expr::Literal("JOB_DONE".into())) {
+                eprintln!("JOB_DONE: {}", e);
+            }
+            self.stack = saved_stack;
+            self.last_exit_code = saved_exit_code;
+        }
+    }
+
     pub(crate) fn update_job_statuses(&mut self) {
         let _ = self.reap_jobs();
     }
@@ -171,7 +316,8 @@ impl Evaluator {
                     match child.try_wait() {
                         Ok(Some(status)) => {
                             let code = status.code().unwrap_or(-1);
-                            job.status = JobStatus::Done(code);
+                            job.finish(code);
+                            self.finished_jobs.push(job.id);
                             let label = if code == 0 {
                                 "Done".to_string()
                             } else {
@@ -181,7 +327,7 @@ impl Evaluator {
                         }
                         Ok(None) => {}
                         Err(_) => {
-                            job.status = JobStatus::Done(-1);
+                            job.finish(-1);
                         }
                     }
                 }
@@ -216,11 +362,12 @@ impl Evaluator {
                 if let Some(ref mut child) = job.child {
                     // Track the foreground pid while we block on it
                     crate::signals::set_foreground_pid(job.pid as i32);
+                    let _foreground = self.cancel.track(job.pid);
                     let wait_result = child.wait();
                     crate::signals::clear_foreground_pid();
                     let status = wait_result.map_err(|e| EvalError::ExecError(e.to_string()))?;
                     self.last_exit_code = status.code().unwrap_or(-1);
-                    job.status = JobStatus::Done(self.last_exit_code);
+                    job.finish(self.last_exit_code);
                 }
                 Ok(())
             }
@@ -267,8 +414,10 @@ impl Evaluator {
         let code = args
             .first()
             .and_then(|s| s.parse::<i32>().ok())
-            .unwrap_or(0);
-        std::process::exit(code);
+            .unwrap_or(self.last_exit_code);
+        // Unwind to the top level, which runs the EXIT trap and ends
+        self.last_exit_code = code;
+        Err(EvalError::Exit(code))
     }
 
     pub(crate) fn builtin_tty(&mut self, args: &[String]) -> Result<(), EvalError> {
@@ -278,17 +427,21 @@ impl Evaluator {
 
         let cmd = &args[args.len() - 1];
         let cmd_args = &args[..args.len() - 1];
-
-        let status = Command::new(cmd)
-            .args(cmd_args)
-            .current_dir(&self.cwd)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
-            .map_err(|e| EvalError::ExecError(format!("{}: {}", cmd, e)))?;
-
-        self.last_exit_code = status.code().unwrap_or(-1);
+        let audit = self.start_process("tty", cmd, cmd_args)?;
+
+        let output = self
+            .run_foreground(
+                Command::new(cmd)
+                    .args(cmd_args)
+                    .current_dir(&self.cwd)
+                    .stdin(Stdio::inherit())
+                    .stdout(Stdio::inherit())
+                    .stderr(Stdio::inherit()),
+            )
+            .map_err(|e| self.spawn_error(cmd, e))?;
+
+        self.last_exit_code = output.status.code().unwrap_or(-1);
+        audit.finish(self.last_exit_code);
         Ok(())
     }
 
@@ -526,7 +679,8 @@ impl Evaluator {
                     self.stack.push(Value::Output(value));
                 } else {
                     let var_name = &args[0];
-                    std::env::set_var(var_name, &value);
+                    self.check_env_write(var_name)?;
+                    super::env::set_var(var_name, &value);
                 }
                 self.last_exit_code = 0;
             }
@@ -545,69 +699,47 @@ impl Evaluator {
             ));
         }
 
-        let format = &args[0];
-        let printf_args = &args[1..];
+        let values: Vec<Value> = args[1..]
+            .iter()
+            .map(|a| Value::Literal(a.clone()))
+            .collect();
+        self.stack
+            .push(Value::Output(format_printf(&args[0], &values)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
 
-        let mut output = String::new();
-        let mut arg_idx = 0;
-        let mut chars = format.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '%' {
-                match chars.next() {
-                    Some('s') => {
-                        if arg_idx < printf_args.len() {
-                            output.push_str(&printf_args[arg_idx]);
-                            arg_idx += 1;
-                        }
-                    }
-                    Some('d') | Some('i') => {
-                        if arg_idx < printf_args.len() {
-                            if let Ok(n) = printf_args[arg_idx].parse::<i64>() {
-                                output.push_str(&n.to_string());
-                            } else {
-                                output.push_str(&printf_args[arg_idx]);
-                            }
-                            arg_idx += 1;
-                        }
-                    }
-                    Some('f') => {
-                        if arg_idx < printf_args.len() {
-                            if let Ok(n) = printf_args[arg_idx].parse::<f64>() {
-                                output.push_str(&format!("{:.6}", n));
-                            } else {
-                                output.push_str(&printf_args[arg_idx]);
-                            }
-                            arg_idx += 1;
-                        }
-                    }
-                    Some('%') => output.push('%'),
-                    Some('n') => output.push('\n'),
-                    Some('t') => output.push('\t'),
-                    Some(other) => {
-                        output.push('%');
-                        output.push(other);
-                    }
-                    None => output.push('%'),
+    /// printf taking its arguments straight off the stack, so structured
+    /// values reach %j intact. Pops like a command: until a block, marker,
+    /// or the bottom of the stack; scalar args still get glob/tilde expansion.
+    pub(crate) fn builtin_printf_values(&mut self) -> Result<(), EvalError> {
+        let mut values = Vec::new();
+        while let Some(value) = self.stack.last() {
+            match value {
+                Value::Block(_) | Value::Marker => break,
+                Value::Nil => {
+                    self.stack.pop();
+                }
+                Value::List(_) | Value::Map(_) | Value::Table { .. } => {
+                    values.push(self.stack.pop().unwrap());
                 }
-            } else if c == '\\' {
-                match chars.next() {
-                    Some('n') => output.push('\n'),
-                    Some('t') => output.push('\t'),
-                    Some('r') => output.push('\r'),
-                    Some('\\') => output.push('\\'),
-                    Some(other) => {
-                        output.push('\\');
-                        output.push(other);
+                _ => {
+                    if let Some(arg) = value.as_arg() {
+                        values.extend(self.expand_arg(&arg).into_iter().map(Value::Literal));
                     }
-                    None => output.push('\\'),
+                    self.stack.pop();
                 }
-            } else {
-                output.push(c);
             }
         }
 
-        self.stack.push(Value::Output(output));
+        if values.is_empty() {
+            return Err(EvalError::ExecError(
+                "printf: format string required".into(),
+            ));
+        }
+        let format = values.remove(0).as_arg().unwrap_or_default();
+        self.stack
+            .push(Value::Output(format_printf(&format, &values)));
         self.last_exit_code = 0;
         Ok(())
     }
@@ -620,7 +752,7 @@ impl Evaluator {
                     match child.wait() {
                         Ok(status) => {
                             last_exit = status.code().unwrap_or(-1);
-                            job.status = JobStatus::Done(last_exit);
+                            job.finish(last_exit);
                         }
                         Err(e) => {
                             return Err(EvalError::ExecError(format!("wait: {}", e)));
@@ -642,7 +774,7 @@ impl Evaluator {
                     match child.wait() {
                         Ok(status) => {
                             let exit_code = status.code().unwrap_or(-1);
-                            job.status = JobStatus::Done(exit_code);
+                            job.finish(exit_code);
                             self.last_exit_code = exit_code;
                         }
                         Err(e) => {
@@ -661,6 +793,14 @@ impl Evaluator {
         Ok(())
     }
 
+    #[cfg(not(unix))]
+    pub(crate) fn builtin_kill(&mut self, _args: &[String]) -> Result<(), EvalError> {
+        Err(EvalError::ExecError(
+            "kill: not supported on this platform".into(),
+        ))
+    }
+
+    #[cfg(unix)]
     pub(crate) fn builtin_kill(&mut self, args: &[String]) -> Result<(), EvalError> {
         if args.is_empty() {
             return Err(EvalError::ExecError(
@@ -705,20 +845,10 @@ impl Evaluator {
                 .map_err(|_| EvalError::ExecError(format!("kill: invalid pid: {}", pid_str)))?
         };
 
-        #[cfg(unix)]
-        {
-            let result = unsafe { libc::kill(pid, signal) };
-            if result != 0 {
-                let err = std::io::Error::last_os_error();
-                return Err(EvalError::ExecError(format!("kill: {}", err)));
-            }
-        }
-
-        #[cfg(not(unix))]
-        {
-            return Err(EvalError::ExecError(
-                "kill: not supported on this platform".into(),
-            ));
+        let result = unsafe { libc::kill(pid, signal) };
+        if result != 0 {
+            let err = std::io::Error::last_os_error();
+            return Err(EvalError::ExecError(format!("kill: {}", err)));
         }
 
         self.last_exit_code = 0;
@@ -967,3 +1097,205 @@ impl Evaluator {
         Ok(())
     }
 }
+
+/// A parsed printf conversion spec: `%[flags][width][.precision]conv`
+#[derive(Default)]
+struct PrintfSpec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl PrintfSpec {
+    /// Pad `s` to the field width (zero padding goes after any sign)
+    fn pad(&self, s: String, numeric: bool) -> String {
+        let len = s.chars().count();
+        if len >= self.width {
+            return s;
+        }
+        let fill = self.width - len;
+        if self.left {
+            format!("{}{}", s, " ".repeat(fill))
+        } else if self.zero && numeric {
+            let split = if s.starts_with(['-', '+', ' ']) { 1 } else { 0 };
+            format!("{}{}{}", &s[..split], "0".repeat(fill), &s[split..])
+        } else {
+            format!("{}{}", " ".repeat(fill), s)
+        }
+    }
+
+    /// Zero-fill the digits of `s` to the precision, if one was given
+    fn min_digits(&self, s: String) -> String {
+        let (sign, digits) = s.split_at(if s.starts_with('-') { 1 } else { 0 });
+        match self.precision {
+            Some(0) if digits == "0" => sign.to_string(),
+            Some(p) if digits.len() < p => {
+                format!("{}{}{}", sign, "0".repeat(p - digits.len()), digits)
+            }
+            _ => s,
+        }
+    }
+
+    fn sign(&self, s: String) -> String {
+        if s.starts_with('-') {
+            s
+        } else if self.plus {
+            format!("+{}", s)
+        } else if self.space {
+            format!(" {}", s)
+        } else {
+            s
+        }
+    }
+}
+
+/// An integer argument, exact for BigInts and long digit strings; a float
+/// is truncated
+fn printf_int(v: &Value) -> Option<BigInt> {
+    match Num::of(v)? {
+        Num::Float(n) => Some(BigInt::from(n as i64)),
+        exact => exact.exact(),
+    }
+}
+
+fn printf_float(v: &Value) -> Option<f64> {
+    match v {
+        Value::Int(i) => Some(*i as f64),
+        Value::Number(n) => Some(*n),
+        other => other.as_arg()?.trim().parse::<f64>().ok(),
+    }
+}
+
+/// Quote a string so a POSIX shell reads it back as one word
+pub(crate) fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_./:=@%+,-".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// Render a printf format string against already-collected arguments
+///
+/// Supports flags `-0+ `, width, `.precision`, and the conversions
+/// `s d i f e x X o c`, plus `%j` (JSON-encode) and `%q` (shell-quote).
+fn format_printf(format: &str, args: &[Value]) -> String {
+    let mut output = String::new();
+    let mut args = args.iter();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let mut spec = PrintfSpec::default();
+            while let Some(&f) = chars.peek() {
+                match f {
+                    '-' => spec.left = true,
+                    '0' => spec.zero = true,
+                    '+' => spec.plus = true,
+                    ' ' => spec.space = true,
+                    '#' => {}
+                    _ => break,
+                }
+                chars.next();
+            }
+            while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                spec.width = spec.width * 10 + d as usize;
+                chars.next();
+            }
+            if chars.peek() == Some(&'.') {
+                chars.next();
+                let mut p = 0;
+                while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    p = p * 10 + d as usize;
+                    chars.next();
+                }
+                spec.precision = Some(p);
+            }
+
+            match chars.next() {
+                Some(conv @ ('s' | 'q' | 'j' | 'c')) => {
+                    let Some(arg) = args.next() else { continue };
+                    let mut text = match conv {
+                        'j' => crate::ast::value_to_json(arg).to_string(),
+                        'q' => shell_quote(&arg.as_arg().unwrap_or_default()),
+                        'c' => arg.as_arg().unwrap_or_default().chars().take(1).collect(),
+                        _ => arg.as_arg().unwrap_or_default(),
+                    };
+                    if let (Some(p), 's') = (spec.precision, conv) {
+                        text = text.chars().take(p).collect();
+                    }
+                    output.push_str(&spec.pad(text, false));
+                }
+                Some(conv @ ('d' | 'i' | 'x' | 'X' | 'o')) => {
+                    let Some(arg) = args.next() else { continue };
+                    match printf_int(arg) {
+                        Some(n) => {
+                            // Negatives that fit an i64 print in two's
+                            // complement, as bash prints them
+                            let text = match (conv, i64::try_from(&n)) {
+                                ('x', Ok(i)) => format!("{:x}", i),
+                                ('X', Ok(i)) => format!("{:X}", i),
+                                ('o', Ok(i)) => format!("{:o}", i),
+                                ('x', _) => format!("{:x}", n),
+                                ('X', _) => format!("{:X}", n),
+                                ('o', _) => format!("{:o}", n),
+                                _ => n.to_string(),
+                            };
+                            let text = spec.min_digits(text);
+                            let text = match conv {
+                                'd' | 'i' => spec.sign(text),
+                                _ => text,
+                            };
+                            // With a precision, `0` pads with spaces
+                            output.push_str(&spec.pad(text, spec.precision.is_none()));
+                        }
+                        None => output.push_str(&spec.pad(arg.as_arg().unwrap_or_default(), false)),
+                    }
+                }
+                Some(conv @ ('f' | 'F' | 'e')) => {
+                    let Some(arg) = args.next() else { continue };
+                    match printf_float(arg) {
+                        Some(n) => {
+                            let p = spec.precision.unwrap_or(6);
+                            let text = if conv == 'e' {
+                                format!("{:.*e}", p, n)
+                            } else {
+                                format!("{:.*}", p, n)
+                            };
+                            output.push_str(&spec.pad(spec.sign(text), true));
+                        }
+                        None => output.push_str(&spec.pad(arg.as_arg().unwrap_or_default(), false)),
+                    }
+                }
+                Some('%') => output.push('%'),
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some(other) => {
+                    output.push('%');
+                    output.push(other);
+                }
+                None => output.push('%'),
+            }
+        } else if c == '\\' {
+            match chars.next() {
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some('r') => output.push('\r'),
+                Some('\\') => output.push('\\'),
+                Some(other) => {
+                    output.push('\\');
+                    output.push(other);
+                }
+                None => output.push('\\'),
+            }
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
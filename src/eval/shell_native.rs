@@ -3,8 +3,9 @@
 //! These operations return useful values to the stack instead of being side-effect only.
 //! On error, they return nil (compositional, pipelines don't break).
 
-use super::{EvalError, Evaluator};
-use crate::ast::Value;
+use super::provenance::Origin;
+use super::{units, EvalError, Evaluator};
+use crate::ast::{Expr, Value};
 use std::fs;
 use std::path::Path;
 
@@ -192,14 +193,11 @@ impl Evaluator {
         // Check if it's a glob pattern
         if path_str.contains('*') || path_str.contains('?') || path_str.contains('[') {
             // Glob expansion
-            let mut count = 0;
-            if let Ok(entries) = glob::glob(&path_str) {
-                for entry in entries.flatten() {
-                    if entry.is_file() && fs::remove_file(&entry).is_ok() {
-                        count += 1;
-                    }
-                }
-            }
+            let files: Vec<_> = glob::glob(&path_str)
+                .map(|entries| entries.flatten().filter(|p| p.is_file()).collect())
+                .unwrap_or_default();
+            self.confirm_glob_paths("rm", &path_str, &files)?;
+            let count = files.iter().filter(|f| fs::remove_file(f).is_ok()).count();
             if count > 0 {
                 self.stack.push(Value::Int(count as i64));
             } else {
@@ -207,6 +205,7 @@ impl Evaluator {
             }
         } else {
             // Single file
+            self.confirm_globs("rm", &[])?;
             match fs::remove_file(path) {
                 Ok(_) => {
                     self.stack.push(Value::Int(1));
@@ -489,7 +488,7 @@ impl Evaluator {
                         .unwrap_or_default()
                         .cmp(&b.as_arg().unwrap_or_default())
                 });
-                self.stack.push(Value::List(paths));
+                self.push_from(Value::List(paths), Origin::Glob);
             }
             Err(_) => {
                 self.stack.push(Value::Nil);
@@ -509,7 +508,6 @@ impl Evaluator {
     /// Structured directory listing. `type` is file/dir/symlink/other
     /// (symlinks are not followed). Additive: plain `ls` is unchanged.
     pub(crate) fn builtin_ls_t(&mut self) -> Result<(), EvalError> {
-        use std::os::unix::fs::MetadataExt;
         use std::path::PathBuf;
 
         let dir_path = if let Some(val) = self.stack.last() {
@@ -556,7 +554,11 @@ impl Evaluator {
                     } else {
                         "other"
                     };
-                    (ft.to_string(), meta.len() as i64, meta.mtime())
+                    (
+                        ft.to_string(),
+                        meta.len() as i64,
+                        super::helpers::mtime(&meta),
+                    )
                 }
                 Err(_) => ("unknown".to_string(), 0, 0),
             };
@@ -582,7 +584,7 @@ impl Evaluator {
 
     /// env-t: env-t -> Record of environment variables (insertion order)
     pub(crate) fn builtin_env_t(&mut self) -> Result<(), EvalError> {
-        let map: indexmap::IndexMap<String, Value> = std::env::vars()
+        let map: indexmap::IndexMap<String, Value> = super::env::vars()
             .map(|(k, v)| (k, Value::Literal(v)))
             .collect();
         self.stack.push(Value::Map(map));
@@ -625,7 +627,7 @@ impl Evaluator {
         let columns = vec!["index".to_string(), "command".to_string()];
         let mut rows: Vec<Vec<Value>> = Vec::new();
 
-        let history_path = std::env::var("HOME")
+        let history_path = super::env::var("HOME")
             .map(|h| Path::new(&h).join(".hsab_history"))
             .ok();
 
@@ -729,10 +731,16 @@ impl Evaluator {
         ];
         let mut rows: Vec<Vec<Value>> = Vec::new();
 
+        let ps_args = [
+            "-axo".to_string(),
+            "pid=,cputime=,rss=,state=,comm=".to_string(),
+        ];
+        let audit = self.start_process("ps-t", "ps", &ps_args)?;
         let output = Command::new("ps")
-            .args(["-axo", "pid=,cputime=,rss=,state=,comm="])
+            .args(&ps_args)
             .output()
             .map_err(|e| EvalError::ExecError(format!("ps-t: {}", e)))?;
+        audit.finish(output.status.code().unwrap_or(-1));
 
         for line in String::from_utf8_lossy(&output.stdout).lines() {
             let fields: Vec<&str> = line.split_whitespace().collect();
@@ -781,4 +789,915 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    // ============================================
+    // Users and Permissions
+    // ============================================
+
+    /// whoami: whoami -> "name" (the effective user, or nil if unknown)
+    pub(crate) fn builtin_whoami(&mut self) -> Result<(), EvalError> {
+        #[cfg(unix)]
+        let name = users::user_name(unsafe { libc::geteuid() });
+        #[cfg(not(unix))]
+        let name = super::env::var("USERNAME").ok();
+        self.stack
+            .push(name.map(Value::Literal).unwrap_or(Value::Nil));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// id-record: id-record -> Record{uid, gid, user, group, groups}
+    ///
+    /// uid and gid are the effective ids; groups lists every group id the
+    /// process belongs to. Names are nil when they can't be resolved.
+    #[cfg(unix)]
+    pub(crate) fn builtin_id_record(&mut self) -> Result<(), EvalError> {
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        let name = |n: Option<String>| n.map(Value::Literal).unwrap_or(Value::Nil);
+
+        let mut map = indexmap::IndexMap::new();
+        map.insert("uid".to_string(), Value::Int(uid as i64));
+        map.insert("gid".to_string(), Value::Int(gid as i64));
+        map.insert("user".to_string(), name(users::user_name(uid)));
+        map.insert("group".to_string(), name(users::group_name(gid)));
+        map.insert(
+            "groups".to_string(),
+            Value::List(
+                users::group_ids()
+                    .into_iter()
+                    .map(|g| Value::Int(g as i64))
+                    .collect(),
+            ),
+        );
+        self.stack.push(Value::Map(map));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn builtin_id_record(&mut self) -> Result<(), EvalError> {
+        self.stack.push(Value::Nil);
+        self.last_exit_code = 1;
+        Ok(())
+    }
+
+    /// is-root?: is-root? -> Bool (exit 0 if running as root)
+    pub(crate) fn builtin_is_root(&mut self) -> Result<(), EvalError> {
+        #[cfg(unix)]
+        let result = unsafe { libc::geteuid() } == 0;
+        #[cfg(not(unix))]
+        let result = false;
+        self.stack.push(Value::Bool(result));
+        self.last_exit_code = if result { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// chmod-sym: "mode" "path" chmod-sym -> path (or nil on error)
+    ///
+    /// The mode is symbolic like chmod's (`u+x`, `go-w`, `a=r,u+w`, `g=u`)
+    /// or octal (`755`). An invalid mode is an error.
+    #[cfg(unix)]
+    pub(crate) fn builtin_chmod_sym(&mut self) -> Result<(), EvalError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path_str = self.pop_string()?;
+        let spec = self.pop_string()?;
+        let Ok(meta) = fs::metadata(&path_str) else {
+            self.stack.push(Value::Nil);
+            return Ok(());
+        };
+        let mode = users::apply_mode(&spec, meta.permissions().mode() & 0o7777, meta.is_dir())
+            .ok_or_else(|| EvalError::ExecError(format!("chmod-sym: invalid mode '{}'", spec)))?;
+
+        match fs::set_permissions(&path_str, fs::Permissions::from_mode(mode)) {
+            Ok(_) => self.stack.push(Value::Literal(path_str)),
+            Err(_) => self.stack.push(Value::Nil),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn builtin_chmod_sym(&mut self) -> Result<(), EvalError> {
+        let _ = self.pop_string()?;
+        let _ = self.pop_string()?;
+        self.stack.push(Value::Nil);
+        Ok(())
+    }
+
+    /// chown: "owner" "path" chown -> path (or nil on error)
+    ///
+    /// The owner is `user`, `user:group`, or `:group`, each a name or a
+    /// numeric id. An unknown user or group is an error.
+    #[cfg(unix)]
+    pub(crate) fn builtin_chown(&mut self) -> Result<(), EvalError> {
+        let path_str = self.pop_string()?;
+        let spec = self.pop_string()?;
+        let (user, group) = match spec.split_once(':') {
+            Some((user, group)) => (user, group),
+            None => (spec.as_str(), ""),
+        };
+        let unknown = |kind: &str, name: &str| {
+            EvalError::ExecError(format!("chown: unknown {} '{}'", kind, name))
+        };
+        let uid = match user {
+            "" => None,
+            name => Some(users::lookup_uid(name).ok_or_else(|| unknown("user", name))?),
+        };
+        let gid = match group {
+            "" => None,
+            name => Some(users::lookup_gid(name).ok_or_else(|| unknown("group", name))?),
+        };
+        if uid.is_none() && gid.is_none() {
+            return Err(EvalError::ExecError(
+                "chown: usage: \"user[:group]\" \"path\" chown".into(),
+            ));
+        }
+
+        match std::os::unix::fs::chown(&path_str, uid, gid) {
+            Ok(_) => self.stack.push(Value::Literal(path_str)),
+            Err(_) => self.stack.push(Value::Nil),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn builtin_chown(&mut self) -> Result<(), EvalError> {
+        let _ = self.pop_string()?;
+        let _ = self.pop_string()?;
+        self.stack.push(Value::Nil);
+        Ok(())
+    }
+
+    // ============================================
+    // Disk Space
+    // ============================================
+
+    /// min-free?: "path" "size" min-free? -> Bool (exit 0 if enough space)
+    ///
+    /// The size is bytes or a number with a `K`/`M`/`G`/`T`/`P` suffix
+    /// (powers of 1024, or of 1000 for `KB` and the like; see `size-bytes`).
+    /// Space is what an unprivileged user
+    /// can write; an unreadable path is false. An invalid size is an error.
+    pub(crate) fn builtin_min_free(&mut self) -> Result<(), EvalError> {
+        let size = self.pop_string()?;
+        let path_str = self.pop_string()?;
+        let want = units::parse_size(&size)
+            .map(|bytes| bytes as u64)
+            .ok_or_else(|| EvalError::ExecError(format!("min-free?: invalid size '{}'", size)))?;
+        let result = disks::available(Path::new(&path_str)).is_some_and(|free| free >= want);
+        self.stack.push(Value::Bool(result));
+        self.last_exit_code = if result { 0 } else { 1 };
+        Ok(())
+    }
+
+    /// mount-point: "path" mount-point -> path (or nil on error)
+    /// The directory where the filesystem holding path is mounted
+    pub(crate) fn builtin_mount_point(&mut self) -> Result<(), EvalError> {
+        let path_str = self.pop_string()?;
+        match disks::mount(Path::new(&path_str)) {
+            Some((mount, _)) => self.stack.push(Value::Literal(mount)),
+            None => self.stack.push(Value::Nil),
+        }
+        Ok(())
+    }
+
+    /// fs-type: "path" fs-type -> "ext4" (or nil on error)
+    pub(crate) fn builtin_fs_type(&mut self) -> Result<(), EvalError> {
+        let path_str = self.pop_string()?;
+        match disks::mount(Path::new(&path_str)).and_then(|(_, fs_type)| fs_type) {
+            Some(fs_type) => self.stack.push(Value::Literal(fs_type)),
+            None => self.stack.push(Value::Nil),
+        }
+        Ok(())
+    }
+
+    // ============================================
+    // System Information
+    // ============================================
+
+    /// sysinfo: sysinfo -> Record{os, kernel, arch, hostname, uptime,
+    /// loadavg, cpu_count, mem_total, mem_free}
+    ///
+    /// uptime is in seconds, loadavg is the 1, 5, and 15 minute averages,
+    /// and memory is in bytes (mem_free counts memory that can be reclaimed).
+    /// Anything the platform can't report is nil.
+    pub(crate) fn builtin_sysinfo(&mut self) -> Result<(), EvalError> {
+        let int = |n: Option<u64>| n.map(|n| Value::Int(n as i64)).unwrap_or(Value::Nil);
+        let (mem_total, mem_free) = system::memory();
+
+        let mut map = indexmap::IndexMap::new();
+        map.insert(
+            "os".to_string(),
+            Value::Literal(std::env::consts::OS.to_string()),
+        );
+        map.insert(
+            "kernel".to_string(),
+            system::kernel().map(Value::Literal).unwrap_or(Value::Nil),
+        );
+        map.insert(
+            "arch".to_string(),
+            Value::Literal(std::env::consts::ARCH.to_string()),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        let host = hostname::get().ok();
+        #[cfg(target_arch = "wasm32")]
+        let host: Option<std::ffi::OsString> = None;
+        map.insert(
+            "hostname".to_string(),
+            host.map(|h| Value::Literal(h.to_string_lossy().into_owned()))
+                .unwrap_or(Value::Nil),
+        );
+        map.insert("uptime".to_string(), int(system::uptime()));
+        map.insert(
+            "loadavg".to_string(),
+            system::loadavg()
+                .map(|avg| Value::List(avg.into_iter().map(Value::Number).collect()))
+                .unwrap_or(Value::Nil),
+        );
+        map.insert(
+            "cpu_count".to_string(),
+            int(std::thread::available_parallelism()
+                .ok()
+                .map(|n| n.get() as u64)),
+        );
+        map.insert("mem_total".to_string(), int(mem_total));
+        map.insert("mem_free".to_string(), int(mem_free));
+        self.stack.push(Value::Map(map));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    // ============================================
+    // Copy, Move, and Remove
+    // ============================================
+
+    /// copy: "src" "dst" [#[progress]] → Record{path, files, dirs, bytes}
+    /// (or nil on error)
+    ///
+    /// Copies a file or a whole directory tree, keeping permissions and
+    /// copying symlinks as symlinks. Like `cp`, a `dst` that is an existing
+    /// directory receives `src` inside it. The optional block is called
+    /// after each file with a progress record (see `report_progress`).
+    pub(crate) fn builtin_copy(&mut self) -> Result<(), EvalError> {
+        let progress = self.pop_progress_block();
+        let dst = self.pop_string()?;
+        let src = self.pop_string()?;
+        let (src, dst) = (Path::new(&src), tree::destination(Path::new(&src), &dst));
+
+        let result = match tree::walk(src) {
+            Ok(entries) => self.copy_entries(src, &dst, &entries, progress.as_deref())?,
+            Err(_) => None,
+        };
+        self.push_tree_result(&dst, result);
+        Ok(())
+    }
+
+    /// move: "src" "dst" [#[progress]] → Record{path, files, dirs, bytes}
+    /// (or nil on error)
+    ///
+    /// Renames when it can, and copies then removes when `dst` is on
+    /// another filesystem. A `dst` that is an existing directory receives
+    /// `src` inside it.
+    pub(crate) fn builtin_move(&mut self) -> Result<(), EvalError> {
+        let progress = self.pop_progress_block();
+        let dst = self.pop_string()?;
+        let src = self.pop_string()?;
+        let (src, dst) = (Path::new(&src), tree::destination(Path::new(&src), &dst));
+
+        let Ok(entries) = tree::walk(src) else {
+            self.push_tree_result(&dst, None);
+            return Ok(());
+        };
+        let total = tree::Tally::of(&entries);
+        let result = match fs::rename(src, &dst) {
+            Ok(()) => {
+                if let Some(block) = &progress {
+                    self.report_progress(block, src, &total, &total)?;
+                }
+                Some(total)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                match self.copy_entries(src, &dst, &entries, progress.as_deref())? {
+                    Some(tally) if tree::remove(src, &entries).is_ok() => Some(tally),
+                    _ => None,
+                }
+            }
+            Err(_) => None,
+        };
+        self.push_tree_result(&dst, result);
+        Ok(())
+    }
+
+    /// remove: "path" [#[progress]] → Record{path, files, dirs, bytes}
+    /// (or nil on error)
+    ///
+    /// Removes a file or a whole directory tree. The optional block is
+    /// called after each file.
+    pub(crate) fn builtin_remove(&mut self) -> Result<(), EvalError> {
+        let progress = self.pop_progress_block();
+        let path_str = self.pop_string()?;
+        let path = Path::new(&path_str);
+
+        let Ok(entries) = tree::walk(path) else {
+            self.stack.push(Value::Nil);
+            return Ok(());
+        };
+        let total = tree::Tally::of(&entries);
+        let mut done = tree::Tally::default();
+        let mut ok = true;
+        for entry in entries.iter().filter(|e| e.kind != tree::Kind::Dir) {
+            self.check_cancelled()?;
+            let file = tree::under(path, &entry.path);
+            if fs::remove_file(&file).is_err() {
+                ok = false;
+                break;
+            }
+            done.add(entry);
+            if let Some(block) = &progress {
+                self.report_progress(block, &file, &done, &total)?;
+            }
+        }
+        for entry in entries.iter().rev().filter(|e| e.kind == tree::Kind::Dir) {
+            if !ok || fs::remove_dir(tree::under(path, &entry.path)).is_err() {
+                ok = false;
+                break;
+            }
+            done.add(entry);
+        }
+        let result = ok.then_some(done);
+        self.push_tree_result(path, result);
+        Ok(())
+    }
+
+    /// Copy `entries`, walked from `src`, to `dst`, reporting progress after
+    /// each file. Returns what was copied, or None if anything failed.
+    fn copy_entries(
+        &mut self,
+        src: &Path,
+        dst: &Path,
+        entries: &[tree::Entry],
+        progress: Option<&[Expr]>,
+    ) -> Result<Option<tree::Tally>, EvalError> {
+        let total = tree::Tally::of(entries);
+        let mut done = tree::Tally::default();
+        for entry in entries {
+            self.check_cancelled()?;
+            let (from, to) = (tree::under(src, &entry.path), tree::under(dst, &entry.path));
+            if tree::copy_entry(entry, &from, &to).is_err() {
+                return Ok(None);
+            }
+            done.add(entry);
+            if let (Some(block), false) = (progress, entry.kind == tree::Kind::Dir) {
+                self.report_progress(block, &from, &done, &total)?;
+            }
+        }
+        // Directory permissions last, so read-only ones could be filled
+        for entry in entries.iter().rev().filter(|e| e.kind == tree::Kind::Dir) {
+            if let Ok(meta) = fs::metadata(tree::under(src, &entry.path)) {
+                let _ = fs::set_permissions(tree::under(dst, &entry.path), meta.permissions());
+            }
+        }
+        Ok(Some(done))
+    }
+
+    /// Pop the optional progress block of copy, move, and remove
+    fn pop_progress_block(&mut self) -> Option<Vec<Expr>> {
+        match self.stack.last() {
+            Some(Value::Block(_)) => self.pop_block().ok(),
+            _ => None,
+        }
+    }
+
+    /// Run a progress block on its own stack with a Record{path, files,
+    /// bytes, total_files, total_bytes} for the file just handled. Text it
+    /// leaves goes to stderr, so progress doesn't mix with results.
+    fn report_progress(
+        &mut self,
+        block: &[Expr],
+        path: &Path,
+        done: &tree::Tally,
+        total: &tree::Tally,
+    ) -> Result<(), EvalError> {
+        let mut map = indexmap::IndexMap::new();
+        map.insert(
+            "path".to_string(),
+            Value::Literal(path.to_string_lossy().into_owned()),
+        );
+        map.insert("files".to_string(), Value::Int(done.files as i64));
+        map.insert("bytes".to_string(), Value::Int(done.bytes as i64));
+        map.insert("total_files".to_string(), Value::Int(total.files as i64));
+        map.insert("total_bytes".to_string(), Value::Int(total.bytes as i64));
+
+        let saved_stack = std::mem::replace(&mut self.stack, vec![Value::Map(map)].into());
+        let result = self.eval_exprs(block);
+        let left = std::mem::replace(&mut self.stack, saved_stack);
+        for text in left.iter().filter_map(Value::as_arg) {
+            eprintln!("{}", text.trim_end_matches('\n'));
+        }
+        result
+    }
+
+    /// Push the result record of copy, move, or remove, or nil
+    fn push_tree_result(&mut self, path: &Path, result: Option<tree::Tally>) {
+        let Some(tally) = result else {
+            self.stack.push(Value::Nil);
+            return;
+        };
+        let path = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .into_owned();
+        let mut map = indexmap::IndexMap::new();
+        map.insert("path".to_string(), Value::Literal(path));
+        map.insert("files".to_string(), Value::Int(tally.files as i64));
+        map.insert("dirs".to_string(), Value::Int(tally.dirs as i64));
+        map.insert("bytes".to_string(), Value::Int(tally.bytes as i64));
+        self.stack.push(Value::Map(map));
+    }
+}
+
+/// User and group lookups, and symbolic modes
+#[cfg(unix)]
+mod users {
+    use std::ffi::{CStr, CString};
+
+    /// Name of the user with `uid`
+    pub(super) fn user_name(uid: libc::uid_t) -> Option<String> {
+        let pw = unsafe { libc::getpwuid(uid) };
+        if pw.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr((*pw).pw_name) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    /// Name of the group with `gid`
+    pub(super) fn group_name(gid: libc::gid_t) -> Option<String> {
+        let gr = unsafe { libc::getgrgid(gid) };
+        if gr.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr((*gr).gr_name) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+
+    /// A user id from a name or a number
+    pub(super) fn lookup_uid(name: &str) -> Option<u32> {
+        if let Ok(uid) = name.parse() {
+            return Some(uid);
+        }
+        let c_name = CString::new(name).ok()?;
+        let pw = unsafe { libc::getpwnam(c_name.as_ptr()) };
+        (!pw.is_null()).then(|| unsafe { (*pw).pw_uid })
+    }
+
+    /// A group id from a name or a number
+    pub(super) fn lookup_gid(name: &str) -> Option<u32> {
+        if let Ok(gid) = name.parse() {
+            return Some(gid);
+        }
+        let c_name = CString::new(name).ok()?;
+        let gr = unsafe { libc::getgrnam(c_name.as_ptr()) };
+        (!gr.is_null()).then(|| unsafe { (*gr).gr_gid })
+    }
+
+    /// The process's group ids, effective group first
+    pub(super) fn group_ids() -> Vec<u32> {
+        let mut ids = vec![unsafe { libc::getegid() }];
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        if count > 0 {
+            let mut groups = vec![0; count as usize];
+            let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+            groups.truncate(count.max(0) as usize);
+            for gid in groups {
+                if !ids.contains(&gid) {
+                    ids.push(gid);
+                }
+            }
+        }
+        ids
+    }
+
+    /// Apply a chmod mode, symbolic or octal, to `mode`. With no `ugoa`,
+    /// a clause applies to everyone.
+    pub(super) fn apply_mode(spec: &str, mut mode: u32, is_dir: bool) -> Option<u32> {
+        if !spec.is_empty() && spec.chars().all(|c| c.is_digit(8)) {
+            return u32::from_str_radix(spec, 8).ok().filter(|m| *m <= 0o7777);
+        }
+        for clause in spec.split(',') {
+            let mut chars = clause.chars().peekable();
+            let mut who = 0;
+            while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+                who |= match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    'o' => 0o1007,
+                    _ => 0o7777,
+                };
+            }
+            if who == 0 {
+                who = 0o7777;
+            }
+            // At least one operator per clause
+            chars.peek().filter(|c| "+-=".contains(**c))?;
+            while let Some(op) = chars.next() {
+                if !"+-=".contains(op) {
+                    return None;
+                }
+                let mut bits = 0;
+                while let Some(c) = chars.next_if(|c| !"+-=".contains(*c)) {
+                    bits |= match c {
+                        'r' => 0o444,
+                        'w' => 0o222,
+                        'x' => 0o111,
+                        'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                        'X' => 0,
+                        's' => 0o6000,
+                        't' => 0o1000,
+                        // Copy another class's bits: g=u
+                        'u' => ((mode >> 6) & 7) * 0o111,
+                        'g' => ((mode >> 3) & 7) * 0o111,
+                        'o' => (mode & 7) * 0o111,
+                        _ => return None,
+                    };
+                }
+                let bits = bits & who;
+                mode = match op {
+                    '+' => mode | bits,
+                    '-' => mode & !bits,
+                    _ => (mode & !who) | bits,
+                };
+            }
+        }
+        Some(mode)
+    }
+}
+
+/// Free space and mount lookups
+mod disks {
+    use std::path::Path;
+
+    /// Bytes an unprivileged user can still write on path's filesystem
+    #[cfg(unix)]
+    // Field widths differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    pub(super) fn available(path: &Path) -> Option<u64> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+        if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let stat = unsafe { stat.assume_init() };
+        Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn available(_path: &Path) -> Option<u64> {
+        None
+    }
+
+    /// The mount point and filesystem type for path. On Linux this is the
+    /// longest mount in /proc/self/mounts containing path, the last one
+    /// winning when several are stacked on the same directory.
+    #[cfg(target_os = "linux")]
+    pub(super) fn mount(path: &Path) -> Option<(String, Option<String>)> {
+        let path = path.canonicalize().ok()?;
+        let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+        let mut best: Option<(String, String)> = None;
+        for line in mounts.lines() {
+            let mut fields = line.split(' ').skip(1);
+            let (Some(dir), Some(fs_type)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let dir = unescape(dir);
+            let longer = best.as_ref().is_none_or(|(b, _)| dir.len() >= b.len());
+            if longer && path.starts_with(&dir) {
+                best = Some((dir, fs_type.to_string()));
+            }
+        }
+        best.map(|(dir, fs_type)| (dir, Some(fs_type)))
+    }
+
+    /// /proc/self/mounts writes spaces and tabs in paths as octal escapes
+    #[cfg(target_os = "linux")]
+    fn unescape(field: &str) -> String {
+        let mut out = Vec::with_capacity(field.len());
+        let bytes = field.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let octal = bytes.get(i + 1..i + 4).and_then(|d| {
+                std::str::from_utf8(d)
+                    .ok()
+                    .and_then(|d| u8::from_str_radix(d, 8).ok())
+            });
+            match octal {
+                Some(b) if bytes[i] == b'\\' => {
+                    out.push(b);
+                    i += 4;
+                }
+                _ => {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// The mount point and filesystem type for path, from statfs
+    #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd"))]
+    pub(super) fn mount(path: &Path) -> Option<(String, Option<String>)> {
+        use std::ffi::CStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+        if unsafe { libc::statfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let stat = unsafe { stat.assume_init() };
+        let text = |s: &[libc::c_char]| {
+            unsafe { CStr::from_ptr(s.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        Some((text(&stat.f_mntonname), Some(text(&stat.f_fstypename))))
+    }
+
+    /// The mount point for path: the highest directory above it on the
+    /// same device. The filesystem type isn't known.
+    #[cfg(all(
+        unix,
+        not(any(
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "openbsd"
+        ))
+    ))]
+    pub(super) fn mount(path: &Path) -> Option<(String, Option<String>)> {
+        use std::os::unix::fs::MetadataExt;
+
+        let path = path.canonicalize().ok()?;
+        let dev = std::fs::metadata(&path).ok()?.dev();
+        let mount = path
+            .ancestors()
+            .take_while(|dir| std::fs::metadata(dir).is_ok_and(|m| m.dev() == dev))
+            .last()?;
+        Some((mount.to_string_lossy().into_owned(), None))
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn mount(_path: &Path) -> Option<(String, Option<String>)> {
+        None
+    }
+}
+
+/// Kernel, uptime, load, and memory figures
+mod system {
+    /// The kernel release, as `uname -r` prints it
+    #[cfg(unix)]
+    pub(super) fn kernel() -> Option<String> {
+        let mut uts = std::mem::MaybeUninit::<libc::utsname>::uninit();
+        if unsafe { libc::uname(uts.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let uts = unsafe { uts.assume_init() };
+        let release = unsafe { std::ffi::CStr::from_ptr(uts.release.as_ptr()) };
+        Some(release.to_string_lossy().into_owned())
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn kernel() -> Option<String> {
+        None
+    }
+
+    /// The 1, 5, and 15 minute load averages
+    #[cfg(unix)]
+    pub(super) fn loadavg() -> Option<Vec<f64>> {
+        let mut avg = [0.0; 3];
+        (unsafe { libc::getloadavg(avg.as_mut_ptr(), 3) } == 3).then(|| avg.to_vec())
+    }
+
+    #[cfg(not(unix))]
+    pub(super) fn loadavg() -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Seconds since boot
+    #[cfg(target_os = "linux")]
+    pub(super) fn uptime() -> Option<u64> {
+        let uptime = std::fs::read_to_string("/proc/uptime").ok()?;
+        let seconds: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+        Some(seconds as u64)
+    }
+
+    /// Total and available memory in bytes, from /proc/meminfo
+    #[cfg(target_os = "linux")]
+    pub(super) fn memory() -> (Option<u64>, Option<u64>) {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+        let field = |name: &str| {
+            meminfo.lines().find_map(|line| {
+                let kb = line.strip_prefix(name)?.strip_prefix(':')?;
+                let kb: u64 = kb.trim().trim_end_matches("kB").trim().parse().ok()?;
+                Some(kb * 1024)
+            })
+        };
+        (field("MemTotal"), field("MemAvailable"))
+    }
+
+    /// Seconds since boot, from the kern.boottime sysctl
+    #[cfg(target_os = "macos")]
+    pub(super) fn uptime() -> Option<u64> {
+        let boot: libc::timeval = sysctl("kern.boottime")?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?;
+        now.as_secs().checked_sub(boot.tv_sec as u64)
+    }
+
+    /// Total and free memory in bytes, from sysctl
+    #[cfg(target_os = "macos")]
+    pub(super) fn memory() -> (Option<u64>, Option<u64>) {
+        let total: Option<u64> = sysctl("hw.memsize");
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let free = sysctl::<u32>("vm.page_free_count").map(|pages| pages as u64 * page_size);
+        (total, free)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn sysctl<T: Copy>(name: &str) -> Option<T> {
+        let name = std::ffi::CString::new(name).ok()?;
+        let mut value = std::mem::MaybeUninit::<T>::uninit();
+        let mut len = std::mem::size_of::<T>();
+        let status = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                &mut len,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        (status == 0 && len == std::mem::size_of::<T>()).then(|| unsafe { value.assume_init() })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub(super) fn uptime() -> Option<u64> {
+        None
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub(super) fn memory() -> (Option<u64>, Option<u64>) {
+        (None, None)
+    }
+}
+
+/// Walking, copying, and removing directory trees
+pub(super) mod tree {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) enum Kind {
+        Dir,
+        File,
+        Symlink,
+    }
+
+    /// One item of a tree, relative to its root (the root itself is empty)
+    pub(super) struct Entry {
+        pub(super) path: PathBuf,
+        pub(super) kind: Kind,
+        pub(super) size: u64,
+    }
+
+    /// Files (and symlinks), directories, and file bytes
+    #[derive(Debug, Clone, Copy, Default)]
+    pub(super) struct Tally {
+        pub(super) files: u64,
+        pub(super) dirs: u64,
+        pub(super) bytes: u64,
+    }
+
+    impl Tally {
+        pub(super) fn of(entries: &[Entry]) -> Tally {
+            let mut tally = Tally::default();
+            for entry in entries {
+                tally.add(entry);
+            }
+            tally
+        }
+
+        pub(super) fn add(&mut self, entry: &Entry) {
+            match entry.kind {
+                Kind::Dir => self.dirs += 1,
+                Kind::File | Kind::Symlink => {
+                    self.files += 1;
+                    self.bytes += entry.size;
+                }
+            }
+        }
+    }
+
+    /// `rel` under `root`, or `root` itself for the empty path
+    pub(super) fn under(root: &Path, rel: &Path) -> PathBuf {
+        if rel.as_os_str().is_empty() {
+            root.to_path_buf()
+        } else {
+            root.join(rel)
+        }
+    }
+
+    /// Where `src` lands for `dst`: inside `dst` if it is a directory
+    pub(super) fn destination(src: &Path, dst: &str) -> PathBuf {
+        let dst = Path::new(dst);
+        match src.file_name() {
+            Some(name) if dst.is_dir() => dst.join(name),
+            _ => dst.to_path_buf(),
+        }
+    }
+
+    /// Everything under `root`, each directory before its contents.
+    /// Symlinks are not followed.
+    pub(super) fn walk(root: &Path) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        walk_into(root, PathBuf::new(), &mut entries)?;
+        Ok(entries)
+    }
+
+    fn walk_into(root: &Path, rel: PathBuf, entries: &mut Vec<Entry>) -> io::Result<()> {
+        let meta = fs::symlink_metadata(under(root, &rel))?;
+        let kind = if meta.is_dir() {
+            Kind::Dir
+        } else if meta.file_type().is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+        let size = if kind == Kind::File { meta.len() } else { 0 };
+        entries.push(Entry {
+            path: rel.clone(),
+            kind,
+            size,
+        });
+        if kind == Kind::Dir {
+            let mut children: Vec<_> = fs::read_dir(under(root, &rel))?
+                .map(|e| e.map(|e| e.file_name()))
+                .collect::<io::Result<_>>()?;
+            children.sort();
+            for name in children {
+                walk_into(root, rel.join(name), entries)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy one entry; `fs::copy` keeps a file's permissions
+    pub(super) fn copy_entry(entry: &Entry, from: &Path, to: &Path) -> io::Result<()> {
+        match entry.kind {
+            Kind::Dir => fs::create_dir_all(to),
+            Kind::File => fs::copy(from, to).map(|_| ()),
+            #[cfg(unix)]
+            Kind::Symlink => std::os::unix::fs::symlink(fs::read_link(from)?, to),
+            #[cfg(not(unix))]
+            Kind::Symlink => fs::copy(from, to).map(|_| ()),
+        }
+    }
+
+    /// Remove the tree at `root`, walked into `entries`
+    pub(super) fn remove(root: &Path, entries: &[Entry]) -> io::Result<()> {
+        for entry in entries.iter().filter(|e| e.kind != Kind::Dir) {
+            fs::remove_file(under(root, &entry.path))?;
+        }
+        for entry in entries.iter().rev().filter(|e| e.kind == Kind::Dir) {
+            fs::remove_dir(under(root, &entry.path))?;
+        }
+        Ok(())
+    }
+
+    /// Move `src` to `dst`, copying and removing it if `dst` is on another
+    /// filesystem
+    pub(in crate::eval) fn relocate(src: &Path, dst: &Path) -> io::Result<()> {
+        match fs::rename(src, dst) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                let entries = walk(src)?;
+                for entry in &entries {
+                    copy_entry(entry, &under(src, &entry.path), &under(dst, &entry.path))?;
+                }
+                remove(src, &entries)
+            }
+            result => result,
+        }
+    }
 }
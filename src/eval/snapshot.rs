@@ -61,7 +61,7 @@ impl Evaluator {
         let name = &args[0];
         match self.snapshots.get(name) {
             Some(saved_stack) => {
-                self.stack = saved_stack.clone();
+                self.stack = saved_stack.clone().into();
                 self.last_exit_code = 0;
                 Ok(())
             }
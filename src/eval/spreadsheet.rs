@@ -0,0 +1,114 @@
+//! Getting a Table into a spreadsheet: `to-clipboard-tsv` and `to-xlsx`
+//!
+//! `to-clipboard-tsv` copies a Table as tab-separated text, which Sheets,
+//! Excel, and Numbers all split into cells on paste. A cell with a tab, a
+//! line break, or a leading quote is quoted as spreadsheets expect, and
+//! lists and records are written as JSON.
+//!
+//! `to-xlsx` writes an Excel workbook, with the header row bold and frozen,
+//! numbers and booleans as themselves, and the columns sized to fit. It
+//! needs hsab built with `--features xlsx`.
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// A cell's text for TSV, quoted if pasting would split it
+fn tsv_cell(value: &Value) -> String {
+    let text = match value {
+        Value::Nil => String::new(),
+        Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Value::List(_) | Value::Map(_) | Value::Table { .. } => {
+            crate::ast::value_to_json(value).to_string()
+        }
+        other => other.as_arg().unwrap_or_default(),
+    };
+    if text.contains(['\t', '\n', '\r']) || text.starts_with('"') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn table_tsv(columns: &[String], rows: &[Vec<Value>]) -> String {
+    let mut text = String::new();
+    let header: Vec<Value> = columns.iter().map(|c| Value::Literal(c.clone())).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let cells: Vec<String> = row.iter().map(tsv_cell).collect();
+        text.push_str(&cells.join("\t"));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(feature = "xlsx")]
+fn write_xlsx(
+    path: &std::path::Path,
+    columns: &[String],
+    rows: &[Vec<Value>],
+) -> Result<(), rust_xlsxwriter::XlsxError> {
+    use rust_xlsxwriter::{Format, Workbook};
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let bold = Format::new().set_bold();
+    for (c, name) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, c as u16, name, &bold)?;
+    }
+    for (r, row) in rows.iter().enumerate() {
+        let r = r as u32 + 1;
+        for (c, value) in row.iter().enumerate() {
+            let c = c as u16;
+            match value {
+                Value::Nil => {}
+                Value::Int(i) => {
+                    sheet.write_number(r, c, *i as f64)?;
+                }
+                Value::Number(n) => {
+                    sheet.write_number(r, c, *n)?;
+                }
+                Value::Bool(b) => {
+                    sheet.write_boolean(r, c, *b)?;
+                }
+                Value::List(_) | Value::Map(_) | Value::Table { .. } => {
+                    sheet.write_string(r, c, crate::ast::value_to_json(value).to_string())?;
+                }
+                other => {
+                    sheet.write_string(r, c, other.as_arg().unwrap_or_default())?;
+                }
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    workbook.save(path)
+}
+
+impl Evaluator {
+    /// to-clipboard-tsv: table to-clipboard-tsv → (copied for pasting into a spreadsheet)
+    pub(crate) fn builtin_to_clipboard_tsv(&mut self) -> Result<(), EvalError> {
+        let (columns, rows) = self.pop_table("to-clipboard-tsv")?;
+        super::terminal::copy_to_clipboard(&table_tsv(&columns, &rows));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// to-xlsx: table "file.xlsx" to-xlsx → (workbook written)
+    #[cfg(feature = "xlsx")]
+    pub(crate) fn builtin_to_xlsx(&mut self) -> Result<(), EvalError> {
+        let path = self.pop_string()?;
+        let (columns, rows) = self.pop_table("to-xlsx")?;
+        let full = self.cwd.join(self.expand_tilde(&path));
+        write_xlsx(&full, &columns, &rows)
+            .map_err(|e| EvalError::ExecError(format!("to-xlsx: {}: {}", path, e)))?;
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Without the `xlsx` feature there's no workbook writer
+    #[cfg(not(feature = "xlsx"))]
+    pub(crate) fn builtin_to_xlsx(&mut self) -> Result<(), EvalError> {
+        Err(EvalError::ExecError(
+            "to-xlsx: not available in this build (build hsab with --features xlsx)".into(),
+        ))
+    }
+}
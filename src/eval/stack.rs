@@ -8,7 +8,9 @@ impl Evaluator {
             .last()
             .cloned()
             .ok_or_else(|| EvalError::StackUnderflow("dup".into()))?;
+        let origin = self.origin(0);
         self.stack.push(top);
+        self.set_origin(self.stack.len() - 1, origin);
         Ok(())
     }
 
@@ -34,7 +36,9 @@ impl Evaluator {
             return Err(EvalError::StackUnderflow("over".into()));
         }
         let second = self.stack[len - 2].clone();
+        let origin = self.origin_at(len - 2);
         self.stack.push(second);
+        self.set_origin(len, origin);
         Ok(())
     }
 
@@ -43,8 +47,12 @@ impl Evaluator {
         if len < 3 {
             return Err(EvalError::StackUnderflow("rot".into()));
         }
+        let origins = self.origins_from(len - 3);
         let third = self.stack.remove(len - 3);
         self.stack.push(third);
+        self.set_origin(len - 3, origins[1]);
+        self.set_origin(len - 2, origins[2]);
+        self.set_origin(len - 1, origins[0]);
         Ok(())
     }
 
@@ -96,6 +104,7 @@ impl Evaluator {
             Value::Map(m) => format!("{{record:{}}}", m.len()),
             Value::Table { columns, rows } => format!("<table:{}x{}>", columns.len(), rows.len()),
             Value::Error { message, .. } => format!("Error: {}", message),
+            Value::Status(code) => format!("<status:{}>", code),
             Value::Media {
                 mime_type, data, ..
             } => format!("<media:{}:{}B>", mime_type, data.len()),
@@ -138,6 +147,7 @@ impl Evaluator {
                     format!("<table:{}x{}>", columns.len(), rows.len())
                 }
                 Value::Error { message, .. } => format!("Error: {}", message),
+                Value::Status(code) => format!("<status:{}>", code),
                 Value::Media {
                     mime_type, data, ..
                 } => format!("<media:{}:{}B>", mime_type, data.len()),
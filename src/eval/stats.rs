@@ -1,3 +1,5 @@
+use super::helpers::Num;
+use super::math::num_mul;
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
 use std::collections::HashMap;
@@ -32,9 +34,17 @@ impl Evaluator {
             .stack
             .pop()
             .ok_or_else(|| EvalError::StackUnderflow("product requires a list".into()))?;
-        let nums = extract_numbers(&val, "product")?;
-        let result = nums.iter().fold(1.0, |acc, &x| acc * x);
-        self.stack.push(Value::Number(result));
+        // Exact over Ints and BigInts, like sum
+        let result = match val {
+            Value::List(items) => items.iter().filter_map(Num::of).fold(Num::Int(1), num_mul),
+            _ => {
+                return Err(EvalError::TypeError {
+                    expected: "List".into(),
+                    got: val.type_name().to_string(),
+                })
+            }
+        };
+        self.stack.push(result.into_value());
         self.last_exit_code = 0;
         Ok(())
     }
@@ -1,11 +1,54 @@
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
 
+/// String operations that pass a nil subject through as nil, with how many
+/// stack values each takes (the subject is the deepest of them)
+const NIL_SAFE_OPS: &[(&str, usize)] =
+    &[("len", 1), ("slice", 3), ("indexof", 2), ("str-replace", 3)];
+
+/// String predicates that are false for a nil subject
+const NIL_FALSE_PREDICATES: &[&str] = &["contains?", "starts?", "ends?"];
+
 impl Evaluator {
+    /// If the value `arity` places from the top is nil, drop it and the
+    /// operands above it and return true. A missing optional field then
+    /// flows through string operations instead of failing them.
+    pub(crate) fn take_nil_subject(&mut self, arity: usize) -> bool {
+        let Some(idx) = self.stack.len().checked_sub(arity) else {
+            return false;
+        };
+        if !matches!(self.stack[idx], Value::Nil) {
+            return false;
+        }
+        self.stack.truncate(idx);
+        true
+    }
+
+    /// Run a string operation on a nil subject: nil for operations, false
+    /// for predicates. Returns false for anything else.
+    pub(crate) fn nil_string_op(&mut self, cmd: &str) -> bool {
+        if let Some(&(_, arity)) = NIL_SAFE_OPS.iter().find(|(name, _)| *name == cmd) {
+            if self.take_nil_subject(arity) {
+                self.stack.push(Value::Nil);
+                self.last_exit_code = 0;
+                return true;
+            }
+        } else if NIL_FALSE_PREDICATES.contains(&cmd) && self.take_nil_subject(2) {
+            self.stack.push(Value::Bool(false));
+            self.last_exit_code = 1;
+            return true;
+        }
+        false
+    }
+
     /// Split at first occurrence of delimiter
     /// "a.b.c" "." split1 -> "a", "b.c"
     /// If not found: "abc" "." split1 -> "abc", ""
     pub(crate) fn string_split1(&mut self) -> Result<(), EvalError> {
+        if self.take_nil_subject(2) {
+            self.stack.extend([Value::Nil, Value::Nil]);
+            return Ok(());
+        }
         let delim = self.pop_string()?;
         let s = self.pop_string()?;
 
@@ -28,6 +71,10 @@ impl Evaluator {
     /// "a.b.c" "." rsplit1 -> "a.b", "c"
     /// If not found: "abc" "." rsplit1 -> "", "abc"
     pub(crate) fn string_rsplit1(&mut self) -> Result<(), EvalError> {
+        if self.take_nil_subject(2) {
+            self.stack.extend([Value::Nil, Value::Nil]);
+            return Ok(());
+        }
         let delim = self.pop_string()?;
         let s = self.pop_string()?;
 
@@ -67,51 +67,80 @@ impl Evaluator {
     }
 
     pub(crate) fn builtin_get(&mut self) -> Result<(), EvalError> {
-        let key_val = self
+        let key = self.pop_key("get")?;
+        let target = self
             .stack
             .pop()
-            .ok_or_else(|| EvalError::StackUnderflow("get requires key".into()))?;
-        let key = key_val.as_arg().ok_or_else(|| EvalError::TypeError {
-            expected: "String".into(),
-            got: key_val.type_name().to_string(),
-        })?;
+            .ok_or_else(|| EvalError::StackUnderflow("get requires record/table".into()))?;
+        let value = self.lookup(target, &key)?;
+        self.stack.push(value);
+        self.last_exit_code = 0;
+        Ok(())
+    }
 
+    /// get?: record "key" get? - like get, but a missing key (nil) sets
+    /// exit code 1
+    pub(crate) fn builtin_get_checked(&mut self) -> Result<(), EvalError> {
+        self.builtin_get()?;
+        let missing = matches!(self.stack.last(), Some(Value::Nil));
+        self.last_exit_code = if missing { 1 } else { 0 };
+        Ok(())
+    }
+
+    /// get-or: record "key" fallback get-or - the value, or fallback when
+    /// the key is missing or nil
+    pub(crate) fn builtin_get_or(&mut self) -> Result<(), EvalError> {
+        let fallback = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow("get-or requires fallback".into()))?;
+        let key = self.pop_key("get-or")?;
         let target = self
             .stack
             .pop()
-            .ok_or_else(|| EvalError::StackUnderflow("get requires record/table".into()))?;
+            .ok_or_else(|| EvalError::StackUnderflow("get-or requires record/table".into()))?;
+        let value = match self.lookup(target, &key)? {
+            Value::Nil => fallback,
+            value => value,
+        };
+        self.stack.push(value);
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    fn pop_key(&mut self, op: &str) -> Result<String, EvalError> {
+        let key_val = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow(format!("{} requires key", op)))?;
+        key_val.as_arg().ok_or_else(|| EvalError::TypeError {
+            expected: "String".into(),
+            got: key_val.type_name().to_string(),
+        })
+    }
 
+    /// The value at `key` in a record, list, table, or error; nil when it
+    /// is missing. A nil target is nil too, so lookups chain through
+    /// optional fields.
+    fn lookup(&self, target: Value, key: &str) -> Result<Value, EvalError> {
         if key.contains('.') {
-            let result = self.deep_get(&target, &key);
-            self.stack.push(result);
-            self.last_exit_code = 0;
-            return Ok(());
+            return Ok(self.deep_get(&target, key));
         }
 
-        match target {
-            Value::Map(map) => match map.get(&key) {
-                Some(v) => self.stack.push(v.clone()),
-                None => self.stack.push(Value::Nil),
+        let value = match target {
+            Value::Map(map) => map.get(key).cloned().unwrap_or(Value::Nil),
+            Value::List(items) => match key.parse::<usize>() {
+                Ok(idx) => items.get(idx).cloned().unwrap_or(Value::Nil),
+                Err(_) => Value::Nil,
             },
-            Value::List(items) => {
-                if let Ok(idx) = key.parse::<usize>() {
-                    self.stack
-                        .push(items.get(idx).cloned().unwrap_or(Value::Nil));
-                } else {
-                    self.stack.push(Value::Nil);
-                }
-            }
-            Value::Table { columns, rows } => {
-                if let Some(col_idx) = columns.iter().position(|c| c == &key) {
-                    let values: Vec<Value> = rows
-                        .iter()
+            Value::Table { columns, rows } => match columns.iter().position(|c| c == key) {
+                Some(col_idx) => Value::List(
+                    rows.iter()
                         .map(|row| row.get(col_idx).cloned().unwrap_or(Value::Nil))
-                        .collect();
-                    self.stack.push(Value::List(values));
-                } else {
-                    self.stack.push(Value::Nil);
-                }
-            }
+                        .collect(),
+                ),
+                None => Value::Nil,
+            },
             Value::Error {
                 kind,
                 message,
@@ -119,7 +148,7 @@ impl Evaluator {
                 source,
                 command,
             } => {
-                let field = match key.as_str() {
+                let field = match key {
                     "kind" => Some(Value::Literal(kind)),
                     "message" => Some(Value::Literal(message)),
                     "code" => code.map(|c| Value::Int(c as i64)),
@@ -127,18 +156,17 @@ impl Evaluator {
                     "command" => command.map(Value::Literal),
                     _ => None,
                 };
-                self.stack.push(field.unwrap_or(Value::Nil));
+                field.unwrap_or(Value::Nil)
             }
+            Value::Nil => Value::Nil,
             _ => {
                 return Err(EvalError::TypeError {
                     expected: "Record, Table, List, or Error".into(),
                     got: target.type_name().to_string(),
                 })
             }
-        }
-
-        self.last_exit_code = 0;
-        Ok(())
+        };
+        Ok(value)
     }
 
     pub(crate) fn builtin_set(&mut self) -> Result<(), EvalError> {
@@ -411,9 +439,21 @@ impl Evaluator {
                     rows: filtered_rows,
                 });
             }
+            // A single record is kept, or becomes Nil
+            Value::Map(record) => {
+                let saved_stack = std::mem::take(&mut self.stack);
+                self.stack.push(Value::Map(record.clone()));
+                for expr in &pred_block {
+                    self.eval_expr(expr)?;
+                }
+                let keep = self.last_exit_code == 0;
+                self.stack = saved_stack;
+                self.stack
+                    .push(if keep { Value::Map(record) } else { Value::Nil });
+            }
             _ => {
                 return Err(EvalError::TypeError {
-                    expected: "Table".into(),
+                    expected: "Table or Record".into(),
                     got: table.type_name().to_string(),
                 })
             }
@@ -539,7 +579,10 @@ impl Evaluator {
     }
 
     /// Pop a Table from the stack (helper for table ops, issue #26)
-    fn pop_table(&mut self, op: &str) -> Result<(Vec<String>, Vec<Vec<Value>>), EvalError> {
+    pub(crate) fn pop_table(
+        &mut self,
+        op: &str,
+    ) -> Result<(Vec<String>, Vec<Vec<Value>>), EvalError> {
         let val = self
             .stack
             .pop()
@@ -967,6 +1010,8 @@ impl Evaluator {
             Ok(()) => {
                 self.last_exit_code = 0;
             }
+            // Cancelling or running out of fuel stops the whole eval
+            Err(e) if e.is_fatal() => return Err(e),
             Err(e) => {
                 self.stack = saved_stack;
                 self.stack.push(Value::Error {
@@ -1146,7 +1191,7 @@ impl Evaluator {
     /// Determine if a value is truthy
     /// Truthy: true, non-zero numbers, non-empty strings/lists/maps
     /// Falsy: false, 0, nil, empty strings/lists/maps, errors
-    fn value_is_truthy(val: &Value) -> bool {
+    pub(crate) fn value_is_truthy(val: &Value) -> bool {
         match val {
             Value::Bool(b) => *b,
             Value::Number(n) => *n != 0.0,
@@ -1156,13 +1201,14 @@ impl Evaluator {
             Value::List(items) => !items.is_empty(),
             Value::Map(map) => !map.is_empty(),
             Value::Error { .. } => false,
+            Value::Status(code) => *code == 0,
             Value::Block(_) => true,
             Value::Table { rows, .. } => !rows.is_empty(),
             Value::Marker => false,
             Value::Media { .. } => true,
             Value::Link { .. } => true,
             Value::Bytes(data) => !data.is_empty(),
-            Value::BigInt(n) => !n.to_bytes_be().is_empty(),
+            Value::BigInt(n) => !n.to_bytes_be().1.is_empty(),
             Value::Future { .. } => true,
         }
     }
@@ -1246,7 +1292,6 @@ impl Evaluator {
 
     pub(crate) fn builtin_ls_table(&mut self) -> Result<(), EvalError> {
         use std::fs;
-        use std::os::unix::fs::MetadataExt;
 
         let dir_path = if let Some(val) = self.stack.last() {
             if let Some(s) = val.as_arg() {
@@ -1292,7 +1337,7 @@ impl Evaluator {
                     "other"
                 };
                 let sz = meta.len();
-                let mod_time = meta.mtime();
+                let mod_time = super::helpers::mtime(meta);
                 (ft.to_string(), sz, mod_time)
             } else {
                 ("unknown".to_string(), 0, 0)
@@ -1316,4 +1361,158 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    /// find-where: "dir" #[predicate] find-where → Table{path, name, type, size, modified}
+    ///
+    /// Walks the tree under `dir` one directory at a time, running the
+    /// predicate on each entry's record and keeping those it passes (exit
+    /// code 0), so only matches are held in memory. Symlinks aren't
+    /// followed, and unreadable directories are skipped.
+    pub(crate) fn builtin_find_where(&mut self) -> Result<(), EvalError> {
+        use std::fs;
+
+        let predicate = self.pop_block()?;
+        let root = self.pop_string()?;
+        let root = PathBuf::from(self.expand_tilde(&root));
+        let read = |dir: &Path, cwd: &Path| -> std::io::Result<Vec<fs::DirEntry>> {
+            let mut entries: Vec<_> = fs::read_dir(cwd.join(dir))?.flatten().collect();
+            entries.sort_by_key(|entry| entry.file_name());
+            Ok(entries)
+        };
+        let top = read(&root, &self.cwd).map_err(|e| {
+            EvalError::IoError(std::io::Error::new(
+                e.kind(),
+                format!("{}: {}", root.display(), e),
+            ))
+        })?;
+
+        let columns = vec![
+            "path".to_string(),
+            "name".to_string(),
+            "type".to_string(),
+            "size".to_string(),
+            "modified".to_string(),
+        ];
+        let mut rows = Vec::new();
+        // Directories still being read, each with its entries left, in
+        // reverse so the next is at the end
+        let mut pending = vec![(root, top.into_iter().rev().collect::<Vec<_>>())];
+        while let Some((dir, entries)) = pending.last_mut() {
+            let Some(entry) = entries.pop() else {
+                pending.pop();
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = dir.join(&name);
+            let (file_type, size, modified) = match entry.metadata() {
+                Ok(meta) => {
+                    let ft = if meta.is_symlink() {
+                        "symlink"
+                    } else if meta.is_dir() {
+                        "dir"
+                    } else if meta.is_file() {
+                        "file"
+                    } else {
+                        "other"
+                    };
+                    (ft, meta.len(), super::helpers::mtime(&meta))
+                }
+                Err(_) => ("unknown", 0, 0),
+            };
+            let row = vec![
+                Value::Literal(path.to_string_lossy().into_owned()),
+                Value::Literal(name),
+                Value::Literal(file_type.to_string()),
+                Value::Int(size as i64),
+                Value::Int(modified),
+            ];
+            let record = columns.iter().cloned().zip(row.iter().cloned()).collect();
+
+            let saved_stack = std::mem::take(&mut self.stack);
+            self.stack.push(Value::Map(record));
+            let result = predicate.iter().try_for_each(|expr| self.eval_expr(expr));
+            let keep = self.last_exit_code == 0;
+            self.stack = saved_stack;
+            result?;
+
+            if keep {
+                rows.push(row);
+            }
+            if file_type == "dir" {
+                if let Ok(children) = read(&path, &self.cwd) {
+                    pending.push((path, children.into_iter().rev().collect()));
+                }
+            }
+        }
+
+        self.stack.push(Value::Table { columns, rows });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// grep-table: "pattern" "dir" grep-table → Table{path, line, column, match, context}
+    ///
+    /// Searches the files under `dir` (or `dir` itself, if it is a file)
+    /// for the regex `pattern`, a row per match. Like ripgrep, it skips
+    /// hidden files, binary files, and what `.gitignore` and `.ignore` files
+    /// exclude. `line` and `column` count from 1, `column` in characters,
+    /// and `context` is the whole line.
+    pub(crate) fn builtin_grep_table(&mut self) -> Result<(), EvalError> {
+        let dir = self.pop_string()?;
+        let pattern = self.pop_string()?;
+        let regex = regex::Regex::new(&pattern)
+            .map_err(|e| EvalError::ExecError(format!("grep-table: {}", e)))?;
+        let dir = PathBuf::from(self.expand_tilde(&dir));
+        let root = self.cwd.join(&dir);
+        if !root.exists() {
+            return Err(EvalError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{}: No such file or directory", dir.display()),
+            )));
+        }
+
+        let walker = ignore::WalkBuilder::new(&root)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+        let mut rows = Vec::new();
+        for entry in walker.flatten() {
+            self.check_cancelled()?;
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let Ok(bytes) = std::fs::read(entry.path()) else {
+                continue;
+            };
+            if bytes[..bytes.len().min(8192)].contains(&0) {
+                continue;
+            }
+            // Paths start with `dir` as given
+            let path = match entry.path().strip_prefix(&root) {
+                Ok(rel) if !rel.as_os_str().is_empty() => dir.join(rel),
+                _ => dir.clone(),
+            };
+            let path = path.to_string_lossy().into_owned();
+            let text = String::from_utf8_lossy(&bytes);
+            for (number, line) in text.lines().enumerate() {
+                for found in regex.find_iter(line) {
+                    let column = line[..found.start()].chars().count() + 1;
+                    rows.push(vec![
+                        Value::Literal(path.clone()),
+                        Value::Int(number as i64 + 1),
+                        Value::Int(column as i64),
+                        Value::Literal(found.as_str().to_string()),
+                        Value::Literal(line.to_string()),
+                    ]);
+                }
+            }
+        }
+
+        let columns = ["path", "line", "column", "match", "context"]
+            .map(String::from)
+            .to_vec();
+        self.stack.push(Value::Table { columns, rows });
+        self.last_exit_code = 0;
+        Ok(())
+    }
 }
@@ -1,3 +1,10 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::process::{Child, Output};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
 use super::{EvalError, Evaluator};
 use crate::ast::Value;
 
@@ -76,8 +83,6 @@ impl Evaluator {
     /// Copy value to system clipboard using OSC 52
     /// value .copy -> (value unchanged, data copied to clipboard)
     pub(crate) fn builtin_clip_copy(&mut self) -> Result<(), EvalError> {
-        use base64::{engine::general_purpose::STANDARD, Engine as _};
-
         let value = self
             .stack
             .pop()
@@ -89,14 +94,7 @@ impl Evaluator {
             EvalError::ExecError(".copy requires a value with string representation".to_string())
         })?;
 
-        // Encode as base64 for OSC 52
-        let b64 = STANDARD.encode(text.as_bytes());
-
-        // Send OSC 52 sequence to copy to clipboard
-        // Format: ESC ] 52 ; c ; <base64-data> BEL
-        // 'c' means the clipboard selection (as opposed to primary selection)
-        print!("\x1b]52;c;{}\x07", b64);
-        std::io::Write::flush(&mut std::io::stdout()).ok();
+        copy_to_clipboard(&text);
 
         // Push value back (non-destructive)
         self.stack.push(value);
@@ -107,8 +105,6 @@ impl Evaluator {
     /// Copy value to clipboard and drop it from stack (destructive)
     /// value .cut -> ()
     pub(crate) fn builtin_clip_cut(&mut self) -> Result<(), EvalError> {
-        use base64::{engine::general_purpose::STANDARD, Engine as _};
-
         let value = self
             .stack
             .pop()
@@ -118,9 +114,7 @@ impl Evaluator {
             EvalError::ExecError(".cut requires a value with string representation".to_string())
         })?;
 
-        let b64 = STANDARD.encode(text.as_bytes());
-        print!("\x1b]52;c;{}\x07", b64);
-        std::io::Write::flush(&mut std::io::stdout()).ok();
+        copy_to_clipboard(&text);
 
         // Don't push value back (destructive)
         self.last_exit_code = 0;
@@ -263,4 +257,337 @@ impl Evaluator {
         self.last_exit_code = 0;
         Ok(())
     }
+
+    /// Remove ANSI escape sequences: text strip-ansi -> text
+    pub(crate) fn builtin_strip_ansi(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        self.stack
+            .push(Value::Literal(crate::display::strip_ansi(&text)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Terminal column width, ignoring ANSI escapes: text ansi-length -> Int
+    pub(crate) fn builtin_ansi_length(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        self.stack
+            .push(Value::Int(crate::display::display_width(&text) as i64));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Wrap text in color/style codes: text "bold red" colorize -> text
+    /// Plain text is returned when color is disabled (not a TTY, NO_COLOR)
+    pub(crate) fn builtin_colorize(&mut self) -> Result<(), EvalError> {
+        let spec = self.pop_string()?;
+        let text = self.pop_string()?;
+
+        let mut codes = Vec::new();
+        for name in spec.split([' ', ',', '+']).filter(|n| !n.is_empty()) {
+            let code = crate::display::sgr_code(name).ok_or_else(|| {
+                EvalError::ExecError(format!("colorize: unknown color '{}'", name))
+            })?;
+            codes.push(code);
+        }
+
+        let result = if codes.is_empty() || !crate::display::color_enabled() {
+            text
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+        };
+        self.stack.push(Value::Literal(result));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Draw a bar filled to a fraction: value max gauge -> "███████▍░░ 74%"
+    /// Colored by how full it is when color is enabled
+    pub(crate) fn builtin_gauge(&mut self) -> Result<(), EvalError> {
+        let max = self.pop_number("gauge")?;
+        let value = self.pop_number("gauge")?;
+        if max <= 0.0 {
+            return Err(EvalError::ExecError(format!(
+                "gauge: the max must be above 0, got {}",
+                max
+            )));
+        }
+        let bar = crate::display::gauge(value, max, terminal_width());
+        self.stack.push(Value::Literal(bar));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
+
+/// Put text on the system clipboard with OSC 52
+pub(crate) fn copy_to_clipboard(text: &str) {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    // Format: ESC ] 52 ; c ; <base64-data> BEL
+    // 'c' means the clipboard selection (as opposed to primary selection)
+    print!("\x1b]52;c;{}\x07", STANDARD.encode(text.as_bytes()));
+    std::io::Write::flush(&mut std::io::stdout()).ok();
+}
+
+/// How `parallel` shows its blocks' output while they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParallelView {
+    /// A pane per block, redrawn in place
+    Panes,
+    /// Each line prefixed with its block's label as it arrives, like
+    /// `docker compose logs`
+    Lines,
+    /// Nothing until the table is pushed
+    Off,
+}
+
+impl ParallelView {
+    /// The view `HSAB_PARALLEL_VIEW` asks for, else panes when stderr is a
+    /// terminal and prefixed lines when it isn't
+    pub(crate) fn from_env() -> Self {
+        match super::env::var("HSAB_PARALLEL_VIEW").as_deref() {
+            Ok("panes") => ParallelView::Panes,
+            Ok("lines") => ParallelView::Lines,
+            Ok("off") => ParallelView::Off,
+            _ if io::stderr().is_terminal() && super::env::var("TERM").as_deref() != Ok("dumb") => {
+                ParallelView::Panes
+            }
+            _ => ParallelView::Lines,
+        }
+    }
+}
+
+/// What a block running under `parallel` tells the view
+pub(crate) enum BlockEvent {
+    /// A line one of its commands printed
+    Line(usize, String),
+    /// The block finished with this exit code
+    Done(usize, i32),
+}
+
+/// Passes the lines a block's commands print on to `parallel`'s view
+#[derive(Clone)]
+pub(crate) struct OutputTap {
+    block: usize,
+    tx: Sender<BlockEvent>,
+}
+
+impl OutputTap {
+    pub(crate) fn new(block: usize, tx: Sender<BlockEvent>) -> Self {
+        OutputTap { block, tx }
+    }
+
+    /// Report that the block finished
+    pub(crate) fn done(&self, code: i32) {
+        let _ = self.tx.send(BlockEvent::Done(self.block, code));
+    }
+
+    /// Wait for `child` like `wait_with_output`, passing each line of its
+    /// piped stdout and stderr on as it's printed
+    pub(crate) fn wait_with_output(&self, mut child: Child) -> io::Result<Output> {
+        let stdout = child.stdout.take().map(|s| self.forward(s));
+        let stderr = child.stderr.take().map(|s| self.forward(s));
+        let status = child.wait()?;
+        let collect = |reader: Option<JoinHandle<Vec<u8>>>| {
+            reader
+                .map(|r| r.join().unwrap_or_default())
+                .unwrap_or_default()
+        };
+        Ok(Output {
+            status,
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+        })
+    }
+
+    fn forward(&self, stream: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+        let tap = self.clone();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut all = Vec::new();
+            let mut line = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                let text = String::from_utf8_lossy(&line)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string();
+                let _ = tap.tx.send(BlockEvent::Line(tap.block, text));
+                all.append(&mut line);
+            }
+            all
+        })
+    }
+}
+
+/// Lines of output each pane keeps in view
+const PANE_LINES: usize = 3;
+
+/// Colors the blocks' labels cycle through
+const PANE_COLORS: [&str; 6] = ["36", "33", "35", "32", "34", "91"];
+
+/// Longest label shown for a block
+const LABEL_WIDTH: usize = 24;
+
+struct Pane {
+    label: String,
+    color: &'static str,
+    tail: VecDeque<String>,
+    /// Exit code and run time, once done
+    done: Option<(i32, Duration)>,
+}
+
+/// `parallel`'s live display on stderr
+pub(crate) struct ParallelDisplay {
+    view: ParallelView,
+    color: bool,
+    panes: Vec<Pane>,
+    started: Instant,
+    /// Lines drawn last time, to move back over
+    drawn: usize,
+}
+
+impl ParallelDisplay {
+    /// A display for blocks labeled with (the start of) their source
+    pub(crate) fn new(view: ParallelView, labels: Vec<String>) -> Self {
+        let panes = labels
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| Pane {
+                label: crate::display::truncate_str(
+                    label.lines().next().unwrap_or_default(),
+                    LABEL_WIDTH,
+                ),
+                color: PANE_COLORS[i % PANE_COLORS.len()],
+                tail: VecDeque::new(),
+                done: None,
+            })
+            .collect();
+        ParallelDisplay {
+            view,
+            color: crate::display::stderr_color_enabled(),
+            panes,
+            started: Instant::now(),
+            drawn: 0,
+        }
+    }
+
+    /// Show what the blocks report until every one is done
+    pub(crate) fn run(mut self, rx: Receiver<BlockEvent>) {
+        let mut running = self.panes.len();
+        while running > 0 {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(event) => {
+                    for event in std::iter::once(event).chain(rx.try_iter()) {
+                        if matches!(event, BlockEvent::Done(..)) {
+                            running -= 1;
+                        }
+                        self.apply(event);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                // Every block's thread has ended
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            self.draw();
+        }
+        self.draw();
+    }
+
+    fn apply(&mut self, event: BlockEvent) {
+        let width = self
+            .panes
+            .iter()
+            .map(|p| crate::display::display_width(&p.label))
+            .max()
+            .unwrap_or(0);
+        let elapsed = self.started.elapsed();
+        let (block, text) = match event {
+            BlockEvent::Line(block, text) => (block, text),
+            BlockEvent::Done(block, code) => {
+                if let Some(pane) = self.panes.get_mut(block) {
+                    pane.done = Some((code, elapsed));
+                }
+                (block, format!("exited with code {}", code))
+            }
+        };
+        let Some(pane) = self.panes.get_mut(block) else {
+            return;
+        };
+        match self.view {
+            ParallelView::Lines => {
+                let padding = width - crate::display::display_width(&pane.label);
+                let prefix = format!("{}{} |", pane.label, " ".repeat(padding));
+                let line = if self.color {
+                    format!("\x1b[{}m{}\x1b[0m {}", pane.color, prefix, text)
+                } else {
+                    format!("{} {}", prefix, crate::display::strip_sgr(&text))
+                };
+                let _ = writeln!(io::stderr().lock(), "{}", line);
+            }
+            // The header says when a pane's block is done
+            ParallelView::Panes if pane.done.is_none() => {
+                pane.tail
+                    .push_back(crate::display::strip_sgr(&text).replace('\t', "    "));
+                if pane.tail.len() > PANE_LINES {
+                    pane.tail.pop_front();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Redraw every pane over the last drawing (panes view only)
+    fn draw(&mut self) {
+        if self.view != ParallelView::Panes {
+            return;
+        }
+        let width = terminal_width().saturating_sub(3).max(10);
+        let now = self.started.elapsed();
+        let mut lines = Vec::new();
+        for pane in &self.panes {
+            let status = match pane.done {
+                None => format!("\x1b[90mrunning {:.1}s\x1b[0m", now.as_secs_f64()),
+                Some((0, took)) => format!("\x1b[32m✓ {:.1}s\x1b[0m", took.as_secs_f64()),
+                Some((code, took)) => {
+                    format!("\x1b[31m✗ exit {} {:.1}s\x1b[0m", code, took.as_secs_f64())
+                }
+            };
+            lines.push(format!(
+                "\x1b[1;{}m▌ {}\x1b[0m  {}",
+                pane.color, pane.label, status
+            ));
+            for i in 0..PANE_LINES {
+                let text = pane.tail.get(i).map(String::as_str).unwrap_or("");
+                lines.push(format!(
+                    "\x1b[{}m│\x1b[0m {}",
+                    pane.color,
+                    crate::display::truncate_str(text, width)
+                ));
+            }
+        }
+
+        let mut out = String::new();
+        if self.drawn > 0 {
+            out.push_str(&format!("\x1b[{}A", self.drawn));
+        }
+        for line in &lines {
+            let line = if self.color {
+                line.clone()
+            } else {
+                crate::display::strip_sgr(line)
+            };
+            out.push_str(&format!("\r\x1b[2K{}\n", line));
+        }
+        self.drawn = lines.len();
+        let mut stderr = io::stderr().lock();
+        let _ = stderr.write_all(out.as_bytes());
+        let _ = stderr.flush();
+    }
+}
+
+/// Columns in the terminal, defaulting to 80
+pub(crate) fn terminal_width() -> usize {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some((width, _)) = terminal_size::terminal_size_of(io::stderr()) {
+        return width.0 as usize;
+    }
+    80
 }
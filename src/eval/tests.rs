@@ -186,6 +186,636 @@ mod tests {
         assert!(!eval.matches_breakpoint(&ls_expr));
     }
 
+    #[test]
+    fn test_debugger_conditional_breakpoint() {
+        let mut eval = Evaluator::new();
+        eval.add_conditional_breakpoint("\"[depth 2 gt?]\"")
+            .expect("parse condition");
+        eval.stack.push(Value::Int(1));
+        eval.stack.push(Value::Int(2));
+        assert!(!eval.matches_conditional_breakpoint());
+
+        eval.stack.push(Value::Int(3));
+        assert!(eval.matches_conditional_breakpoint());
+        // Checking a condition never changes the stack
+        assert_eq!(eval.stack.len(), 3);
+        assert_eq!(
+            eval.breakpoint_descriptions(),
+            vec!["if \"[depth 2 gt?]\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_debugger_conditional_breakpoint_rejects_empty() {
+        let mut eval = Evaluator::new();
+        assert!(eval.add_conditional_breakpoint("[]").is_err());
+    }
+
+    #[test]
+    fn test_debugger_clear_breakpoints_all_kinds() {
+        let mut eval = Evaluator::new();
+        eval.add_breakpoint("echo".to_string());
+        eval.add_definition_breakpoint("square".to_string());
+        eval.add_conditional_breakpoint("depth 0 gt?").unwrap();
+        assert_eq!(eval.breakpoint_descriptions().len(), 3);
+        eval.clear_breakpoints();
+        assert!(eval.breakpoint_descriptions().is_empty());
+    }
+
+    /// Frontend that records pauses and steps until `limit`, then stops
+    struct RecordingFrontend {
+        pauses: std::sync::Arc<std::sync::Mutex<Vec<(String, String, usize)>>>,
+        limit: usize,
+    }
+
+    impl DebugFrontend for RecordingFrontend {
+        fn on_pause(&mut self, eval: &mut Evaluator, reason: &str, expr: &str) -> DebugAction {
+            let mut pauses = self.pauses.lock().unwrap();
+            pauses.push((reason.to_string(), expr.to_string(), eval.stack().len()));
+            if pauses.len() >= self.limit {
+                DebugAction::Terminate
+            } else {
+                DebugAction::Step
+            }
+        }
+    }
+
+    #[test]
+    fn test_debugger_frontend_receives_pauses() {
+        let pauses = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut eval = Evaluator::new();
+        eval.set_debug_mode(true);
+        eval.add_breakpoint("2".to_string());
+        eval.set_debug_frontend(Box::new(RecordingFrontend {
+            pauses: pauses.clone(),
+            limit: 2,
+        }));
+        let program = parse(lex("1 2 plus 10 mul").expect("lex")).expect("parse");
+        let err = eval.eval(&program).unwrap_err();
+        assert!(err.to_string().contains("terminated by debugger"));
+
+        let pauses = pauses.lock().unwrap();
+        assert_eq!(pauses.len(), 2);
+        assert_eq!(pauses[0], ("breakpoint".to_string(), "2".to_string(), 1));
+        assert_eq!(pauses[1], ("step".to_string(), "plus".to_string(), 2));
+    }
+
+    #[test]
+    fn test_debugger_evaluate_uses_stack_copy() {
+        let mut eval = Evaluator::new();
+        let program = parse(lex("4 5").expect("lex")).expect("parse");
+        eval.eval(&program).unwrap();
+        let top = eval.debug_evaluate("plus").unwrap();
+        assert_eq!(top.and_then(|v| v.as_arg()).as_deref(), Some("9"));
+        assert_eq!(eval.stack.len(), 2);
+        assert!(eval.debug_evaluate("[]").is_err());
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.jsonl");
+        let path = path.to_str().unwrap();
+        let mut eval = Evaluator::new();
+        eval.stack.push(Value::Literal("seed".into()));
+        eval.start_recording(path).unwrap();
+        let program = parse(lex("1 2 plus #[2 mul] drop 1 0 div").expect("lex")).expect("parse");
+        assert!(eval.eval(&program).is_err());
+        assert_eq!(eval.stop_recording(), Some(8));
+        assert!(!eval.is_recording());
+
+        let rec = Recording::load(path).unwrap();
+        assert_eq!(rec.initial, vec![Value::Literal("seed".into())]);
+        assert_eq!(rec.steps[2].expr, "plus");
+        match rec.steps[3].push.as_slice() {
+            [Value::Block(body)] => assert_eq!(crate::ast::exprs_to_source(body), "2 mul"),
+            other => panic!("expected a block, got {:?}", other),
+        }
+        assert!(rec.steps[7].error.is_some());
+        let top = |n: usize| rec.stack_at(n).last().and_then(|v| v.as_arg());
+        assert_eq!(rec.stack_at(0).len(), 1);
+        assert_eq!(top(3).as_deref(), Some("3"));
+        assert_eq!(rec.stack_at(5).len(), 2);
+        let shown = crate::display::strip_ansi(&rec.format_step(3));
+        assert!(shown.contains("[3/8] plus  (exit 0)"));
+    }
+
+    #[test]
+    fn test_recording_tolerates_truncated_last_line() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.jsonl");
+        std::fs::write(
+            &path,
+            "{\"hsab_recording\":2,\"stack\":[]}\n{\"expr\":\"1\",\"pop\":0,\"push\":[{\"int\":1}],\"exit\":0}\n{\"expr\":",
+        )
+        .unwrap();
+        let rec = Recording::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(rec.steps.len(), 1);
+
+        std::fs::write(&path, "not a recording\n").unwrap();
+        assert!(Recording::load(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_exprs_to_source_round_trips() {
+        for src in [
+            r#"#[dup mul] :sq 3 sq"#,
+            r#""say \"hi\"\n" 'it' $HOME ${USER} echo"#,
+            r#"[1 2] { name x } "out.txt" > ls | wc 2>&1"#,
+            r#"#[#[a] #[b] 1 if] apply path-join"#,
+        ] {
+            let exprs = parse(lex(src).expect("lex")).expect("parse").expressions;
+            let source = crate::ast::exprs_to_source(&exprs);
+            let again = parse(lex(&source).expect("relex"))
+                .expect("reparse")
+                .expressions;
+            assert_eq!(exprs, again, "round trip of {:?} via {:?}", src, source);
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_restores_state() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("ck.json");
+        let name = path.to_str().unwrap().to_string();
+
+        let mut eval = Evaluator::new();
+        let program = parse(
+            lex(r#"#[dup plus] :double "txt" 42 2.5 #[1 double] [1 "a" { k true }] "#)
+                .expect("lex"),
+        )
+        .expect("parse");
+        eval.eval(&program).unwrap();
+        eval.stack.push(Value::Output("captured\n".into()));
+        eval.stack.push(Value::Bytes(vec![0, 255]));
+        eval.stack.push(Value::Table {
+            columns: vec!["n".into()],
+            rows: vec![vec![Value::Int(1)], vec![Value::Nil]],
+        });
+        let saved = eval.stack.to_vec();
+        eval.set_script_location("job.hsab", 7, "step checkpoint more");
+        eval.current_span = Some((1, 6));
+        eval.stack.push(Value::Literal(name.clone()));
+        eval.builtin_checkpoint().unwrap();
+        assert_eq!(eval.stack.to_vec(), saved);
+
+        let mut fresh = Evaluator::new();
+        let point = fresh.restore_checkpoint(&name).unwrap();
+        assert_eq!(fresh.stack.to_vec(), saved);
+        assert_eq!(
+            fresh.definitions.get("double"),
+            eval.definitions.get("double")
+        );
+        assert_eq!(
+            point,
+            ResumePoint {
+                script: "job.hsab".into(),
+                line: 7,
+                col: 6,
+                text: "step checkpoint more".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_reports_unserializable_values() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("ck.json");
+        let mut eval = Evaluator::new();
+        eval.stack.push(Value::Future {
+            id: "f1".into(),
+            state: std::sync::Arc::new(std::sync::Mutex::new(crate::ast::FutureState::Pending)),
+        });
+        eval.stack.push(Value::Int(1));
+        eval.stack
+            .push(Value::Literal(path.to_str().unwrap().to_string()));
+        let err = eval.builtin_checkpoint().unwrap_err().to_string();
+        assert!(err.contains("stack item 1 holds a future"), "{}", err);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_checkpoint_outside_script_cannot_resume() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("ck.json");
+        let name = path.to_str().unwrap().to_string();
+        let mut eval = Evaluator::new();
+        eval.stack.push(Value::Literal(name.clone()));
+        eval.builtin_checkpoint().unwrap();
+        let err = Evaluator::new().restore_checkpoint(&name).unwrap_err();
+        assert!(err.contains("not taken in a script"));
+    }
+
+    #[test]
+    fn test_shared_definitions_between_sessions() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("session-defs");
+        let run = |eval: &mut Evaluator, src: &str| {
+            let program = parse(lex(src).expect("lex")).expect("parse");
+            eval.eval(&program).expect("eval");
+        };
+
+        let mut a = Evaluator::new();
+        run(&mut a, "#[1 plus] :startup-only");
+        let baseline = a.definition_sources();
+        a.enable_shared_definitions(&path, baseline).unwrap();
+        let mut b = Evaluator::new();
+        let baseline = b.definition_sources();
+        b.enable_shared_definitions(&path, baseline).unwrap();
+
+        run(&mut a, "#[2 mul] :double");
+        let report = a.poll_shared_definitions().unwrap();
+        assert_eq!(report.published, vec!["double".to_string()]);
+
+        let report = b.poll_shared_definitions().unwrap();
+        assert_eq!(report.received, vec!["double".to_string()]);
+        run(&mut b, "21 double");
+        assert_eq!(
+            b.stack.last().and_then(|v| v.as_arg()).as_deref(),
+            Some("42")
+        );
+        assert!(!b.definitions.contains_key("startup-only"));
+
+        // Last writer wins
+        run(&mut b, "#[3 mul] :double");
+        b.poll_shared_definitions().unwrap();
+        a.poll_shared_definitions().unwrap();
+        a.stack.clear();
+        run(&mut a, "2 double");
+        assert_eq!(
+            a.stack.last().and_then(|v| v.as_arg()).as_deref(),
+            Some("6")
+        );
+
+        // Nothing changed: no sync needed
+        assert_eq!(a.poll_shared_definitions().unwrap(), SyncReport::default());
+    }
+
+    #[test]
+    fn test_shared_definitions_survive_concurrent_syncs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("session-defs");
+        let sessions: Vec<_> = (0..4)
+            .map(|session| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut eval = Evaluator::new();
+                    let baseline = eval.definition_sources();
+                    eval.enable_shared_definitions(&path, baseline).unwrap();
+                    for i in 0..25 {
+                        let src = format!("#[{}] :w{}_{}", i, session, i);
+                        eval.eval(&parse(lex(&src).expect("lex")).expect("parse"))
+                            .expect("eval");
+                        eval.sync_shared_definitions().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for session in sessions {
+            session.join().unwrap();
+        }
+
+        let mut eval = Evaluator::new();
+        let baseline = eval.definition_sources();
+        let report = eval.enable_shared_definitions(&path, baseline).unwrap();
+        assert_eq!(report.received.len(), 100);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        const SPEC: &str = r#"{ name greet positional "NAME..." flags {
+            loud false
+            count { default 1 short c help "Repeat count" }
+            ratio 0.5
+            lang { required true }
+            tag [] } }"#;
+        let parse_with = |eval: &mut Evaluator, argv: &str| {
+            eval.stack.clear();
+            let src = format!("{} {} parse-args", SPEC, argv);
+            eval.eval(&parse(lex(&src).expect("lex")).expect("parse"))
+                .expect("eval");
+            eval.stack.pop().expect("result")
+        };
+        let mut eval = Evaluator::new();
+
+        let Value::Map(opts) = parse_with(
+            &mut eval,
+            "[--lang=en -c3 --loud --tag a --tag b --ratio 2.5 x \"--\" -y]",
+        ) else {
+            panic!("expected a record");
+        };
+        assert_eq!(opts["lang"], Value::Literal("en".into()));
+        assert_eq!(opts["count"], Value::Int(3));
+        assert_eq!(opts["loud"], Value::Bool(true));
+        assert_eq!(opts["ratio"], Value::Number(2.5));
+        let strs =
+            |xs: &[&str]| Value::List(xs.iter().map(|x| Value::Literal(x.to_string())).collect());
+        assert_eq!(opts["tag"], strs(&["a", "b"]));
+        assert_eq!(opts["args"], strs(&["x", "-y"]));
+
+        // Defaults, --no-, and negative numbers as positionals
+        let Value::Map(opts) = parse_with(&mut eval, "[--lang fr --no-loud -5]") else {
+            panic!("expected a record");
+        };
+        assert_eq!(opts["count"], Value::Int(1));
+        assert_eq!(opts["loud"], Value::Bool(false));
+        assert_eq!(opts["tag"], Value::List(vec![]));
+        assert_eq!(opts["args"], strs(&["-5"]));
+        assert_eq!(eval.last_exit_code, 0);
+
+        for (argv, expected) in [
+            ("[--lang en --bogus]", "unknown option '--bogus'"),
+            ("[--lang en -c x]", "--count expects an integer, got 'x'"),
+            ("[--lang]", "--lang requires a value"),
+            ("[]", "missing required option --lang"),
+        ] {
+            match parse_with(&mut eval, argv) {
+                Value::Error {
+                    kind,
+                    message,
+                    code,
+                    ..
+                } => {
+                    assert_eq!(kind, "usage");
+                    assert_eq!(message, expected);
+                    assert_eq!(code, Some(2));
+                }
+                other => panic!("{}: expected a usage error, got {:?}", argv, other),
+            }
+            assert_eq!(eval.last_exit_code, 2);
+        }
+
+        let help = parse_with(&mut eval, "[--help]").as_arg().unwrap();
+        assert!(
+            help.starts_with("Usage: greet [OPTIONS] NAME..."),
+            "{}",
+            help
+        );
+        assert!(help.contains("-c, --count <INT>"), "{}", help);
+        assert!(help.contains("Repeat count (default: 1)"), "{}", help);
+        assert!(help.contains("--lang <VALUE>"), "{}", help);
+        assert!(help.contains("(required)"), "{}", help);
+
+        // Without an argv list, the script's arguments are parsed
+        eval.set_script_args(vec!["--lang".into(), "de".into()]);
+        eval.stack.clear();
+        let src = format!("{} parse-args \"lang\" get args", SPEC);
+        eval.eval(&parse(lex(&src).expect("lex")).expect("parse"))
+            .expect("eval");
+        assert_eq!(
+            eval.stack.to_vec(),
+            vec![Value::Literal("de".into()), strs(&["--lang", "de"])]
+        );
+
+        let bad = parse(lex("{ flags { x { short xy } } } [] parse-args").unwrap()).unwrap();
+        assert!(eval.eval(&bad).is_err());
+    }
+
+    #[test]
+    fn test_explain_shows_bash_without_running() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let marker = dir.path().join("ran");
+        let mut eval = Evaluator::new();
+
+        let text = eval.explain("dest src cp").unwrap();
+        assert!(text.contains("run: cp src dest"), "{}", text);
+        assert!(text.ends_with("bash:\n  cp src dest\n"), "{}", text);
+
+        // Command output used as an argument is nested, not repeated
+        let text = eval.explain("-l ls wc").unwrap();
+        assert!(text.contains("bash:\n  wc \"$(ls -l)\"\n"), "{}", text);
+
+        let text = eval
+            .explain("#[make] #[\"build failed\" echo] || \"a b\" dup rm")
+            .unwrap();
+        assert!(text.contains("  make || echo 'build failed'\n"), "{}", text);
+        assert!(text.contains("  rm 'a b' 'a b'\n"), "{}", text);
+
+        // Definitions, including ones the program makes, are expanded
+        let text = eval.explain("#[$HOME cd] :home home").unwrap();
+        assert!(text.contains("push the value of $HOME"), "{}", text);
+        assert!(text.contains("bash:\n  cd \"$HOME\"\n"), "{}", text);
+        assert!(!eval.definitions.contains_key("home"));
+
+        let src = format!("{} touch", marker.display());
+        let text = eval.explain(&src).unwrap();
+        assert!(text.contains("touch"), "{}", text);
+        assert!(!marker.exists(), "explain must not run anything");
+
+        let text = eval.explain("1 2 plus").unwrap();
+        assert!(text.contains("not simulated"), "{}", text);
+        assert!(text.contains("bash: (no commands run)"), "{}", text);
+
+        eval.stack.clear();
+        eval.eval(&parse(lex("\"hi echo\" explain").unwrap()).unwrap())
+            .unwrap();
+        assert!(matches!(&eval.stack[..], [Value::Output(t)] if t.contains("echo hi")));
+    }
+
+    #[test]
+    fn test_to_bash() {
+        let to_bash = |src: &str| -> Result<String, EvalError> {
+            let mut eval = Evaluator::new();
+            eval.eval(&parse(lex(&format!("#[{}] to-bash", src)).unwrap()).unwrap())?;
+            Ok(eval
+                .stack
+                .pop()
+                .and_then(|v| v.as_arg())
+                .unwrap_or_default())
+        };
+        assert_eq!(to_bash("dest src cp").unwrap(), "cp src dest");
+        assert_eq!(
+            to_bash("#[make] #[-rf build rm] && \"$HOME/x\" cat #[-c wc] |").unwrap(),
+            "make && rm build -rf\ncat \"$HOME/x\" | wc -c"
+        );
+        // Globs are left to the shell; bashisms are avoided
+        assert_eq!(
+            to_bash("#[*.log ls] #[out.txt] &> #[\"a b\" echo] #[x y] >> #[5 sleep] &").unwrap(),
+            "ls *.log > out.txt 2>&1\necho 'a b' | tee -a x y > /dev/null\nsleep 5 &"
+        );
+        assert_eq!(
+            to_bash("#[#[a echo] #[b echo] &&] #[c echo] ||").unwrap(),
+            "echo a && echo b || echo c"
+        );
+        assert_eq!(
+            to_bash("\"some text\" #[-l wc] <<<").unwrap(),
+            "printf '%s\\n' 'some text' | wc -l"
+        );
+        let err = to_bash("1 2 plus echo").unwrap_err().to_string();
+        assert!(
+            err.contains("no shell equivalent for: hsab builtin plus"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_glob_confirmation_needs_interactive_and_threshold() {
+        let globs = [confirm::Expansion {
+            pattern: "*.log".into(),
+            paths: (0..11).map(|i| format!("{}.log", i)).collect(),
+        }];
+        let mut eval = Evaluator::new();
+        assert!(eval.unconfirmed_glob(&globs).is_none());
+
+        eval.set_interactive(true);
+        assert_eq!(eval.unconfirmed_glob(&globs).unwrap().pattern, "*.log");
+
+        // force skips asking once
+        eval.eval(&parse(lex("force").unwrap()).unwrap()).unwrap();
+        assert!(!eval.force_next, "force must not outlive the line");
+        eval.force_next = true;
+        assert!(eval.unconfirmed_glob(&globs).is_none());
+        assert!(eval.unconfirmed_glob(&globs).is_some());
+
+        eval.confirm_glob = 11;
+        assert!(eval.unconfirmed_glob(&globs).is_none());
+        eval.confirm_glob = 0;
+        assert!(eval.unconfirmed_glob(&globs).is_none());
+    }
+
+    #[test]
+    fn test_editor_args_follow_each_editor() {
+        use crate::eval::edit::editor_args;
+        assert_eq!(editor_args("vim", "a.rs", 3, Some(5)), ["+3", "a.rs"]);
+        assert_eq!(
+            editor_args("/usr/bin/code", "a.rs", 3, Some(5)),
+            ["-g", "a.rs:3:5"]
+        );
+        assert_eq!(editor_args("hx", "a.rs", 3, None), ["a.rs:3"]);
+    }
+
+    #[test]
+    fn test_capture_override_applies_to_next_command_only() {
+        let mut eval = Evaluator::new();
+        eval.eval(&parse(lex("live").unwrap()).unwrap()).unwrap();
+        assert_eq!(
+            eval.capture_override, None,
+            "override must not outlive the line"
+        );
+
+        eval.eval(&parse(lex("capture 1 expr").unwrap()).unwrap())
+            .unwrap();
+        assert!(matches!(&eval.stack[..], [Value::Output(t)] if t.trim() == "1"));
+        assert_eq!(eval.capture_override, None);
+
+        let text = eval.explain("live 1 expr").unwrap();
+        assert!(
+            text.contains("run the next command on the terminal"),
+            "{}",
+            text
+        );
+        assert!(text.contains("bash:\n  expr 1\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_tty_guard_settings_and_interactive_commands() {
+        use crate::eval::tty_guard::{is_interactive_command, TtyGuard};
+        assert!(is_interactive_command("vim"));
+        assert!(is_interactive_command("/usr/bin/less"));
+        assert!(!is_interactive_command("grep"));
+
+        let mut eval = Evaluator::new();
+        assert_eq!(eval.tty_guard, TtyGuard::Ask);
+        eval.eval(&parse(lex(r#""tty" tty-guard"#).unwrap()).unwrap())
+            .unwrap();
+        assert_eq!(eval.tty_guard, TtyGuard::Tty);
+        assert!(eval
+            .eval(&parse(lex(r#""maybe" tty-guard"#).unwrap()).unwrap())
+            .is_err());
+        assert_eq!(eval.tty_guard, TtyGuard::Tty);
+
+        // Outside the interactive shell the guard never steps in
+        assert!(!eval.guarded_to_tty("vim"));
+    }
+
+    #[test]
+    fn test_status_values() {
+        let run = |src: &str| {
+            let mut eval = Evaluator::new();
+            eval.eval(&parse(lex(src).unwrap()).unwrap()).unwrap();
+            eval.stack.into_vec()
+        };
+        assert_eq!(run("#[true] status"), [Value::Status(0)]);
+        // The block's output is dropped, and it can't reach below itself
+        assert_eq!(
+            run("a #[1 seq] status"),
+            [Value::Literal("a".into()), Value::Status(0)]
+        );
+        // A stale failure doesn't leak into a block that runs nothing
+        assert_eq!(
+            run("false #[] status ok?"),
+            [Value::Bool(false), Value::Bool(true)]
+        );
+        assert_eq!(run("#[false] status failed?"), [Value::Bool(true)]);
+        // Without a block it's a plain word, an argument as in `status git`
+        assert_eq!(
+            run("--short status echo"),
+            [Value::Output("status --short\n".into())]
+        );
+        assert_eq!(run("#[\"exit 3\" -c sh] status code"), [Value::Int(3)]);
+        assert_eq!(
+            run("#[no echo] #[yes echo] #[false] status if"),
+            [Value::Output("no\n".into())]
+        );
+
+        let mut eval = Evaluator::new();
+        let err = eval
+            .eval(&parse(lex("1 ok?").unwrap()).unwrap())
+            .unwrap_err();
+        assert!(err.to_string().contains("Status for ok?"), "{}", err);
+    }
+
+    #[test]
+    fn test_exec_policy() {
+        let policy = ExecPolicy {
+            allow: Some(vec!["ls".into(), "git".into()]),
+            deny: vec!["git".into()],
+            ..Default::default()
+        };
+        assert!(policy.permits("ls"));
+        assert!(policy.permits("/bin/ls"));
+        assert!(!policy.permits("cat"));
+        assert!(!policy.permits("git"));
+        assert!(ExecPolicy::default().permits("anything"));
+
+        let mut eval = Evaluator::new();
+        eval.set_exec_policy(ExecPolicy {
+            allow: None,
+            deny: vec!["rm".into()],
+            ..Default::default()
+        });
+        let program = parse(lex("somefile rm").expect("lex")).expect("parse");
+        let err = eval.eval(&program).unwrap_err();
+        assert!(err.to_string().contains("not allowed by the exec policy"));
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unset_variables() {
+        let mut eval = Evaluator::new();
+        let program = parse(lex("$HSAB_SURELY_UNSET_VAR").expect("lex")).expect("parse");
+        eval.eval(&program).expect("lenient by default");
+        eval.set_strict_mode(true);
+        let err = eval.eval(&program).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("$HSAB_SURELY_UNSET_VAR is not set"));
+    }
+
+    #[test]
+    fn test_debugger_watch_leaves_state_untouched() {
+        let mut eval = Evaluator::new();
+        eval.add_watch("depth").unwrap();
+        eval.set_trace_mode(true);
+        let tokens = lex("1 2 plus").expect("lex");
+        let program = parse(tokens).expect("parse");
+        eval.eval(&program).expect("eval");
+        assert_eq!(eval.stack.len(), 1);
+        assert_eq!(eval.stack[0].as_arg().unwrap(), "3");
+        eval.clear_watches();
+        assert!(eval.watches.is_empty());
+    }
+
     #[test]
     fn test_debugger_expr_to_string() {
         let eval = Evaluator::new();
@@ -1082,6 +1712,199 @@ mod tests {
         );
     }
 
+    /// Serve `pages` in turn from a local server, each linking to the next
+    /// the way GitHub paginates if `linked`. Returns the base URL and a
+    /// receiver for the requests served.
+    fn serve_pages(
+        pages: Vec<&'static str>,
+        linked: bool,
+    ) -> (String, std::sync::mpsc::Receiver<String>) {
+        use std::io::Write;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+        let base = format!("http://{}", listener.local_addr().expect("local addr"));
+        let (tx, rx) = std::sync::mpsc::channel();
+        let link_base = base.clone();
+        std::thread::spawn(move || {
+            let count = pages.len();
+            for (i, body) in pages.into_iter().enumerate() {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let _ = tx.send(read_http_request(&mut stream));
+                let link = if linked && i + 1 < count {
+                    format!("Link: <{}/page/{}>; rel=\"next\"\r\n", link_base, i + 2)
+                } else {
+                    String::new()
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    link,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        (base, rx)
+    }
+
+    #[test]
+    fn test_gh_api_fills_repo_merges_pages_and_creates_issues() {
+        std::env::set_var("GH_TOKEN", "t0ken");
+        std::env::set_var("GH_REPO", "octo/hello");
+        let run = |code: &str| {
+            let mut eval = Evaluator::new();
+            eval.eval(&parse(lex(code).expect("lex")).expect("parse"))
+                .expect("eval");
+            eval.stack.pop().expect("a result")
+        };
+
+        let (base, requests) = serve_pages(
+            vec![
+                r#"[{"number": 1, "title": "a"}]"#,
+                r#"[{"number": 2, "title": "b"}]"#,
+            ],
+            true,
+        );
+        let issues = run(&format!(
+            "\"{}/repos/{{owner}}/{{repo}}/issues\" gh-api",
+            base
+        ));
+        let Value::Table { columns, rows } = issues else {
+            panic!("expected a table, got {:?}", issues);
+        };
+        assert_eq!(columns, vec!["number", "title"]);
+        assert_eq!(rows.len(), 2);
+        let first = requests.recv().unwrap();
+        assert!(
+            first.starts_with("GET /repos/octo/hello/issues?per_page=100 "),
+            "{}",
+            first
+        );
+        assert!(first.to_lowercase().contains("authorization: bearer t0ken"));
+        assert!(requests.recv().unwrap().starts_with("GET /page/2 "));
+
+        // The high-level words reach the API through GITHUB_API_URL
+        let (base, requests) = serve_pages(
+            vec![
+                r#"[{"number": 3, "title": "Fix", "user": {"login": "ana"}, "head": {"ref": "fix"}, "html_url": "u3"}]"#,
+                r#"{"number": 9, "title": "Release", "html_url": "u9"}"#,
+            ],
+            false,
+        );
+        std::env::set_var("GITHUB_API_URL", &base);
+        let prs = run("pr-list");
+        let Value::Table { columns, rows } = prs else {
+            panic!("expected a table, got {:?}", prs);
+        };
+        assert_eq!(columns, vec!["number", "title", "author", "branch", "url"]);
+        assert_eq!(rows[0][2].as_arg().as_deref(), Some("ana"));
+        assert_eq!(rows[0][3].as_arg().as_deref(), Some("fix"));
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("GET /repos/octo/hello/pulls?"));
+
+        let created = run(r#"{ title "Release" body "notes" } issue-create"#);
+        let Value::Map(created) = created else {
+            panic!("expected a record, got {:?}", created);
+        };
+        assert_eq!(created["number"].as_arg().as_deref(), Some("9"));
+        assert_eq!(created["url"].as_arg().as_deref(), Some("u9"));
+        let post = requests.recv().unwrap();
+        assert!(
+            post.starts_with("POST /repos/octo/hello/issues "),
+            "{}",
+            post
+        );
+        assert!(post.contains(r#""body":"notes""#), "{}", post);
+        std::env::remove_var("GITHUB_API_URL");
+    }
+
+    #[test]
+    fn test_mail_send_delivers_over_smtp() {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind test server");
+        let port = listener.local_addr().expect("local addr").port();
+        let (tx, received) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut writer = stream.try_clone().expect("clone");
+            let mut reader = BufReader::new(stream);
+            let _ = writer.write_all(b"220 test ready\r\n");
+            let mut transcript = String::new();
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                transcript.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line == ".\r\n" {
+                        in_data = false;
+                        b"250 queued\r\n"
+                    } else {
+                        b""
+                    }
+                } else if line.starts_with("EHLO") {
+                    b"250-test\r\n250 SIZE 1000000\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    let _ = writer.write_all(b"221 bye\r\n");
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                let _ = writer.write_all(reply);
+                line.clear();
+            }
+            let _ = tx.send(transcript);
+        });
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("report.csv"), "a,b\n1,2\n").expect("write");
+        std::env::set_var("SMTP_HOST", "127.0.0.1");
+        std::env::set_var("SMTP_PORT", port.to_string());
+        std::env::set_var("SMTP_TLS", "none");
+        std::env::set_var("SMTP_FROM", "Reports <reports@example.com>");
+
+        let mut eval = Evaluator::new();
+        eval.cwd = dir.path().to_path_buf();
+        let code = r#"{ to [ops@example.com dev@example.com] subject "Nightly"
+            body "All green" attachments [report.csv] } mail-send"#;
+        let result = eval.eval(&parse(lex(code).expect("lex")).expect("parse"));
+        assert!(result.is_ok(), "mail-send should succeed: {:?}", result);
+
+        let transcript = received.recv().expect("transcript");
+        assert!(transcript.contains("MAIL FROM:<reports@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<ops@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<dev@example.com>\r\n"));
+        assert!(transcript.contains("Subject: Nightly\r\n"));
+        assert!(transcript.contains("filename=\"report.csv\""));
+        // The attachment's contents, base64-encoded
+        assert!(transcript.contains("YSxiCjEsMgo="), "{}", transcript);
+
+        // A line break would start a header or SMTP command of its own
+        for record in [
+            r#"{ to ops@example.com subject "Hi\r\nBcc: all@example.com" }"#,
+            r#"{ to "ops@example.com>\r\nRCPT TO:<all@example.com" }"#,
+            r#"{ to ops@example.com cc ["dev@example.com\nBcc: x"] }"#,
+            r#"{ to ops@example.com from "me@example.com\r\nX: y" }"#,
+        ] {
+            let code = format!("{} mail-send", record);
+            let err = eval
+                .eval(&parse(lex(&code).expect("lex")).expect("parse"))
+                .unwrap_err();
+            assert!(err.to_string().contains("line break"), "{}", err);
+        }
+
+        std::env::remove_var("SMTP_HOST");
+        let result = eval.eval(&parse(lex(code).expect("lex")).expect("parse"));
+        assert!(result.is_err(), "mail-send needs a server");
+    }
+
     // === Watch Mode Tests ===
     // Note: Full watch tests require file system interaction
     // These tests verify basic argument handling
@@ -0,0 +1,282 @@
+//! Trash: deleting files so they can be restored
+//!
+//! `trash` moves files to the XDG trash (`$XDG_DATA_HOME/Trash`, normally
+//! `~/.local/share/Trash`), shared with desktop file managers, and records
+//! where each one came from in a `.trashinfo` file. macOS and Windows don't
+//! use the XDG trash, so there the same layout lives in `~/.hsab/trash`.
+//! `trash-list` shows what is in the trash and `trash-restore` puts an item
+//! back.
+//!
+//! `"on" rm-trash` (usually in `~/.hsabrc`) makes `rm` and `rm-r` trash
+//! instead of delete, but only in the interactive shell: scripts and `-c`
+//! still delete, so they behave the same wherever they run.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::shell_native::tree;
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+impl Evaluator {
+    /// trash: "path" → trashed_path (or nil on error)
+    /// Moves a file or directory to the trash
+    pub(crate) fn builtin_trash(&mut self) -> Result<(), EvalError> {
+        let path = self.pop_string()?;
+        let result = trash_dir().and_then(|dir| trash(&dir, Path::new(&path)).ok());
+        self.stack.push(match result {
+            Some(trashed) => Value::Literal(trashed.to_string_lossy().into_owned()),
+            None => Value::Nil,
+        });
+        Ok(())
+    }
+
+    /// trash-list: trash-list → Table{name, path, deleted}
+    /// What is in the trash, oldest first
+    pub(crate) fn builtin_trash_list(&mut self) -> Result<(), EvalError> {
+        let columns = vec![
+            "name".to_string(),
+            "path".to_string(),
+            "deleted".to_string(),
+        ];
+        let rows = trash_dir()
+            .map(|dir| list(&dir))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| {
+                vec![
+                    Value::Literal(item.name),
+                    Value::Literal(item.path.to_string_lossy().into_owned()),
+                    Value::Literal(item.deleted),
+                ]
+            })
+            .collect();
+        self.stack.push(Value::Table { columns, rows });
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// trash-restore: "name-or-path" → restored_path (or nil on error)
+    ///
+    /// Takes a name from `trash-list` or the original path (restoring the
+    /// latest item deleted from there). Nothing is overwritten: if the
+    /// original path exists again, the item stays in the trash.
+    pub(crate) fn builtin_trash_restore(&mut self) -> Result<(), EvalError> {
+        let wanted = self.pop_string()?;
+        let result = trash_dir().and_then(|dir| restore(&dir, &wanted).ok());
+        self.stack.push(match result {
+            Some(path) => Value::Literal(path.to_string_lossy().into_owned()),
+            None => Value::Nil,
+        });
+        Ok(())
+    }
+
+    /// rm-trash: "on"|"off" rm-trash
+    /// Make `rm` and `rm-r` trash instead of delete in the interactive shell
+    pub(crate) fn builtin_rm_trash(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        self.rm_trash = match &setting {
+            Value::Bool(on) => *on,
+            other => match other.as_arg().as_deref() {
+                Some("on" | "true" | "1") => true,
+                Some("off" | "false" | "0") => false,
+                _ => {
+                    self.stack.push(setting);
+                    return Err(EvalError::ExecError(
+                        "rm-trash: usage: \"on\" rm-trash or \"off\" rm-trash".into(),
+                    ));
+                }
+            },
+        };
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Check if `rm` and `rm-r` should trash rather than delete
+    pub(crate) fn rm_trashes(&self) -> bool {
+        self.rm_trash && self.interactive
+    }
+
+    /// rm and rm-r with `rm-trash` on: "path" → count (or nil on error)
+    /// Trashes a file, directory, or glob's matches, returning how many
+    pub(crate) fn builtin_rm_to_trash(&mut self) -> Result<(), EvalError> {
+        let pattern = self.pop_string()?;
+        let paths: Vec<PathBuf> = if pattern.contains(['*', '?', '[']) {
+            glob::glob(&pattern)
+                .map(|paths| paths.flatten().collect())
+                .unwrap_or_default()
+        } else {
+            vec![PathBuf::from(&pattern)]
+        };
+        self.confirm_glob_paths("rm", &pattern, &paths)?;
+        let count = match trash_dir() {
+            Some(dir) => paths.iter().filter(|p| trash(&dir, p).is_ok()).count(),
+            None => 0,
+        };
+        self.stack.push(if count > 0 {
+            Value::Int(count as i64)
+        } else {
+            Value::Nil
+        });
+        Ok(())
+    }
+}
+
+/// The trash directory, holding `files/` and `info/`
+fn trash_dir() -> Option<PathBuf> {
+    let home = super::env::var("HOME").ok().filter(|h| !h.is_empty());
+    if cfg!(any(target_os = "macos", windows)) {
+        return home.map(|h| Path::new(&h).join(".hsab").join("trash"));
+    }
+    match super::env::var("XDG_DATA_HOME") {
+        Ok(data) if !data.is_empty() => Some(Path::new(&data).join("Trash")),
+        _ => home.map(|h| Path::new(&h).join(".local/share/Trash")),
+    }
+}
+
+/// An item in the trash
+struct Item {
+    /// Its name under `files/`
+    name: String,
+    /// Where it was deleted from
+    path: PathBuf,
+    /// When, as local time `YYYY-MM-DDThh:mm:ss`
+    deleted: String,
+}
+
+/// Move `path` to the trash, returning where it went
+fn trash(dir: &Path, path: &Path) -> io::Result<PathBuf> {
+    fs::symlink_metadata(path)?;
+    let original = std::path::absolute(path)?;
+    let name = original
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "nothing to trash"))?
+        .to_string_lossy()
+        .into_owned();
+    let (files, info) = (dir.join("files"), dir.join("info"));
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    // Creating the info file claims the name
+    let (name, mut info_file) = (1..)
+        .map(|n| match n {
+            1 => name.clone(),
+            n => format!("{}.{}", name, n),
+        })
+        .filter(|candidate| fs::symlink_metadata(files.join(candidate)).is_err())
+        .find_map(|candidate| {
+            let info_path = info.join(format!("{}.trashinfo", candidate));
+            match fs::File::create_new(&info_path) {
+                Ok(file) => Some(Ok((candidate, file))),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .expect("names run out")?;
+    let info_path = info.join(format!("{}.trashinfo", name));
+    let written = write!(
+        info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&original),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    let trashed = files.join(&name);
+    match written.and_then(|()| tree::relocate(&original, &trashed)) {
+        Ok(()) => Ok(trashed),
+        Err(e) => {
+            let _ = fs::remove_file(info_path);
+            Err(e)
+        }
+    }
+}
+
+/// Everything in the trash with readable info, oldest first
+fn list(dir: &Path) -> Vec<Item> {
+    let Ok(entries) = fs::read_dir(dir.join("info")) else {
+        return Vec::new();
+    };
+    let mut items: Vec<Item> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = file_name.strip_suffix(".trashinfo")?.to_string();
+            let text = fs::read_to_string(entry.path()).ok()?;
+            let field = |key: &str| {
+                text.lines()
+                    .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            };
+            Some(Item {
+                path: percent_decode(field("Path")?),
+                deleted: field("DeletionDate").unwrap_or_default().to_string(),
+                name,
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| a.deleted.cmp(&b.deleted).then_with(|| a.name.cmp(&b.name)));
+    items
+}
+
+/// Put an item back where it came from, returning that path
+fn restore(dir: &Path, wanted: &str) -> io::Result<PathBuf> {
+    let items = list(dir);
+    let wanted_path = std::path::absolute(wanted)?;
+    let item = items
+        .iter()
+        .find(|item| item.name == wanted)
+        .or_else(|| items.iter().rev().find(|item| item.path == wanted_path))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the trash"))?;
+    if fs::symlink_metadata(&item.path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "original path exists",
+        ));
+    }
+    if let Some(parent) = item.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    tree::relocate(&dir.join("files").join(&item.name), &item.path)?;
+    fs::remove_file(dir.join("info").join(format!("{}.trashinfo", item.name)))?;
+    Ok(item.path.clone())
+}
+
+/// Escape a path for a `.trashinfo` file, as a URL path is escaped
+fn percent_encode(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(decoded) if b == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    #[cfg(unix)]
+    let path = <std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(bytes);
+    #[cfg(not(unix))]
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    PathBuf::from(path)
+}
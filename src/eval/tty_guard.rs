@@ -0,0 +1,87 @@
+//! Interactive commands whose output would be captured
+//!
+//! hsab captures a command's output when something after it on the line
+//! consumes it, which leaves `vim`, `less`, or `ssh` waiting on a terminal
+//! they don't have. In the interactive shell, a command on the
+//! `INTERACTIVE_COMMANDS` list that is about to be captured is caught
+//! first, as `tty-guard` says:
+//!
+//! - `"ask"` (the default): ask whether to run it on the terminal instead
+//! - `"tty"`: run it on the terminal without asking
+//! - `"warn"`: say so, then capture it anyway
+//! - `"off"`: capture it as before
+//!
+//! `live` and `capture` before the command decide without the guard.
+
+use std::path::Path;
+
+use super::confirm::ask;
+use super::{EvalError, Evaluator};
+
+/// Commands that need the terminal to be of any use
+const INTERACTIVE_COMMANDS: &[&str] = &[
+    "vi", "vim", "nvim", "nano", "emacs", "micro", "hx", "helix", "less", "more", "most", "man",
+    "ssh", "mosh", "telnet", "top", "htop", "btop", "atop", "tmux", "screen", "fzf",
+];
+
+/// What to do when an interactive command would be captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TtyGuard {
+    Ask,
+    Tty,
+    Warn,
+    Off,
+}
+
+/// Whether `cmd` (a name or a path) is on the interactive list
+pub(crate) fn is_interactive_command(cmd: &str) -> bool {
+    Path::new(cmd)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| INTERACTIVE_COMMANDS.contains(&name))
+}
+
+impl Evaluator {
+    /// tty-guard: "ask"|"tty"|"warn"|"off" tty-guard
+    /// What to do when an interactive command's output would be captured
+    pub(crate) fn builtin_tty_guard(&mut self) -> Result<(), EvalError> {
+        let setting = self.pop_value_or_err()?;
+        self.tty_guard = match setting.as_arg().as_deref() {
+            Some("ask") => TtyGuard::Ask,
+            Some("tty") => TtyGuard::Tty,
+            Some("warn") => TtyGuard::Warn,
+            Some("off") => TtyGuard::Off,
+            _ => {
+                self.stack.push(setting);
+                return Err(EvalError::ExecError(
+                    "tty-guard: usage: \"ask\"|\"tty\"|\"warn\"|\"off\" tty-guard".into(),
+                ));
+            }
+        };
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Whether `cmd`, about to have its output captured, should run on the
+    /// terminal instead
+    pub(crate) fn guarded_to_tty(&mut self, cmd: &str) -> bool {
+        if !self.interactive || !Self::is_interactive() || !is_interactive_command(cmd) {
+            return false;
+        }
+        match self.tty_guard {
+            TtyGuard::Off => false,
+            TtyGuard::Tty => true,
+            TtyGuard::Warn => {
+                eprintln!(
+                    "{}: output captured for what follows; use `live` to run it on the terminal",
+                    cmd
+                );
+                false
+            }
+            TtyGuard::Ask => ask(&format!(
+                "{}: output would be captured for what follows. Run it on the terminal?",
+                cmd
+            )),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Human units: durations like `"5m"` and sizes like `"1.5G"`
+//!
+//! ```text
+//! "5m" duration                  → 300 (seconds)
+//! "1h30m" duration               → 5400
+//! "250ms" duration               → 0.25
+//! "1.5G" size-bytes              → 1610612736
+//! "1.5GB" size-bytes             → 1500000000
+//! 5400 "h" to-unit               → 1.5
+//! ```
+//!
+//! Words that take seconds or bytes (`sleep`, `timeout`, `cached`,
+//! `rate-limit`, `min-free?`) read these forms directly, as do the ones
+//! that take milliseconds (`delay`, `delay-async`, `retry-delay`), where a
+//! bare number still means milliseconds.
+
+use super::helpers::Num;
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// A length of time or a size: finite and not negative
+fn amount(n: f64) -> Option<f64> {
+    Some(n).filter(|n| n.is_finite() && *n >= 0.0)
+}
+
+/// Parse a duration into seconds: a bare number, or one or more numbers
+/// suffixed with `ms`, `s`, `m`, `h`, or `d` (`"90s"`, `"1h30m"`). NaN,
+/// infinities, and negative numbers aren't durations.
+pub(crate) fn parse_duration(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<f64>() {
+        return amount(seconds);
+    }
+    let mut rest = text;
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest)?;
+        let end = after
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(after.len());
+        let unit = match &after[..end] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return None,
+        };
+        total += number * unit;
+        rest = &after[end..];
+    }
+    amount(total)
+}
+
+/// Parse a size into bytes: a bare number, or a number suffixed with `K`,
+/// `M`, `G`, `T`, or `P`. Those and `Ki`/`KiB` are powers of 1024, while
+/// `KB` and the like are powers of 1000, as `dd` reads them.
+pub(crate) fn parse_size(text: &str) -> Option<f64> {
+    let (number, unit) = split_number(text.trim())?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let binary = unit.strip_suffix("IB").or_else(|| unit.strip_suffix('I'));
+    let (prefix, base) = match (binary, unit.strip_suffix('B')) {
+        (Some(prefix), _) if !prefix.is_empty() => (prefix, 1024f64),
+        (Some(_), _) => return None,
+        (None, Some(prefix)) => (prefix, 1000f64),
+        (None, None) => (unit.as_str(), 1024f64),
+    };
+    let power = match prefix {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => return None,
+    };
+    Some(number * base.powi(power))
+}
+
+/// Split a leading unsigned decimal number from the unit after it
+fn split_number(text: &str) -> Option<(f64, &str)> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    Some((number.parse().ok()?, unit))
+}
+
+/// Parse milliseconds: a bare number is milliseconds, and a number with a
+/// unit is read as a duration
+pub(crate) fn parse_millis(text: &str) -> Option<f64> {
+    match text.trim().parse::<f64>() {
+        Ok(ms) => amount(ms),
+        Err(_) => parse_duration(text).map(|seconds| seconds * 1000.0),
+    }
+}
+
+impl Evaluator {
+    /// duration: "5m" duration → 300
+    /// Seconds in a duration with an ms, s, m, h, or d suffix
+    pub(crate) fn builtin_duration(&mut self) -> Result<(), EvalError> {
+        let seconds = self.pop_seconds("duration")?;
+        self.stack.push(Value::Number(seconds));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// size-bytes: "1.5G" size-bytes → 1610612736
+    /// Bytes in a size with a K, M, G, T, or P suffix (KB and the like are
+    /// decimal)
+    pub(crate) fn builtin_size_bytes(&mut self) -> Result<(), EvalError> {
+        let bytes = self.pop_in_units("size-bytes", "size", parse_size)?;
+        self.stack.push(Value::Number(bytes));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// to-unit: 5400 "h" to-unit → 1.5
+    /// Seconds or bytes in another unit: a duration unit (ms, s, m, h, d)
+    /// or a size unit (K, MiB, GB, ...)
+    pub(crate) fn builtin_to_unit(&mut self) -> Result<(), EvalError> {
+        let unit = self.pop_string()?;
+        let n = self.pop_number("to-unit")?;
+        let one = format!("1{}", unit.trim());
+        let size = Some(unit.trim())
+            .filter(|u| !u.is_empty() && u.chars().all(|c| c.is_ascii_alphabetic()))
+            .and_then(|_| parse_duration(&one).or_else(|| parse_size(&one)))
+            .ok_or_else(|| EvalError::ExecError(format!("to-unit: unknown unit '{}'", unit)))?;
+        self.stack.push(Value::Number(n / size));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// Helper: pop seconds, given as a number or a duration like "5m"
+    pub(crate) fn pop_seconds(&mut self, op: &str) -> Result<f64, EvalError> {
+        self.pop_in_units(op, "duration", parse_duration)
+    }
+
+    /// Helper: pop milliseconds, given as a number or a duration like "2s"
+    pub(crate) fn pop_millis(&mut self, op: &str) -> Result<f64, EvalError> {
+        self.pop_in_units(op, "duration", parse_millis)
+    }
+
+    /// Pop a non-negative number, or text that `parse` reads, putting
+    /// anything else back
+    fn pop_in_units(
+        &mut self,
+        op: &str,
+        kind: &str,
+        parse: fn(&str) -> Option<f64>,
+    ) -> Result<f64, EvalError> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| EvalError::StackUnderflow(format!("{} requires a {}", op, kind)))?;
+        let parsed = match &value {
+            Value::Literal(s) | Value::Output(s) => parse(s),
+            other => Num::of(other).and_then(|n| amount(n.to_f64())),
+        };
+        match parsed {
+            Some(n) => Ok(n),
+            None => {
+                let err = EvalError::TypeError {
+                    expected: format!("{} ({})", kind, op),
+                    got: value
+                        .as_arg()
+                        .unwrap_or_else(|| value.type_name().to_string()),
+                };
+                self.stack.push(value);
+                Err(err)
+            }
+        }
+    }
+}
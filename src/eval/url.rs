@@ -0,0 +1,280 @@
+//! URLs as records, and percent-encoding
+//!
+//! `url-parse` splits a URL into `{ scheme user password host port path
+//! query fragment }`, with parts it doesn't have as nil, the port as an
+//! Int, and the query as a record of decoded values (a key given more than
+//! once has a list). `url-build` is the inverse, encoding what needs it, so
+//! a script can change one part and put the URL back together:
+//!
+//! ```text
+//! "https://api.example.com/search" url-parse
+//!     "query" { q "rust lang" page 2 } set url-build fetch
+//! ```
+//!
+//! `url-encode` percent-encodes a string as a URL component, or a record as
+//! a query string (`a=1&b=2`), and `url-decode` undoes it.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// Percent-encode everything but the characters left alone in any part of
+/// a URL, and those in `keep`
+fn encode(text: &str, keep: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            _ if keep.as_bytes().contains(&b) => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Undo percent-encoding, and in a query `+` for a space
+fn decode(text: &str, plus_is_space: bool) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(decoded) if b == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+                continue;
+            }
+            _ if b == b'+' && plus_is_space => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+        rest = tail;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// A query string as a record; a key given more than once has a list
+fn parse_query(query: &str) -> IndexMap<String, Value> {
+    let mut record: IndexMap<String, Value> = IndexMap::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (key, value) = (decode(key, true), Value::Literal(decode(value, true)));
+        match record.get_mut(&key) {
+            Some(Value::List(values)) => values.push(value),
+            Some(first) => *first = Value::List(vec![first.clone(), value]),
+            None => {
+                record.insert(key, value);
+            }
+        }
+    }
+    record
+}
+
+/// A record as a query string; a list repeats its key, and nil leaves the
+/// key without a value
+pub(crate) fn build_query(record: &IndexMap<String, Value>) -> String {
+    let mut pairs = Vec::new();
+    for (key, value) in record {
+        let values = match value {
+            Value::List(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+        for value in values {
+            pairs.push(match value.as_arg() {
+                Some(value) => format!("{}={}", encode(key, ""), encode(&value, "")),
+                None => encode(key, ""),
+            });
+        }
+    }
+    pairs.join("&")
+}
+
+fn parse_url(url: &str) -> Result<IndexMap<String, Value>, String> {
+    let literal = |s: &str| Value::Literal(s.to_string());
+    let mut record = IndexMap::new();
+
+    let (rest, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(decode(fragment, false))),
+        None => (url, None),
+    };
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(parse_query(query))),
+        None => (rest, None),
+    };
+    let (scheme, rest) = match rest.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme.to_ascii_lowercase()), Some(rest)),
+        None => (None, None),
+    };
+    let (authority, path) = match rest {
+        Some(rest) => match rest.find('/') {
+            Some(at) => (Some(&rest[..at]), &rest[at..]),
+            None => (Some(rest), ""),
+        },
+        // No `scheme://`: all of it is a path
+        None => (None, url.split(['?', '#']).next().unwrap_or_default()),
+    };
+
+    let (mut user, mut password, mut host, mut port) =
+        (Value::Nil, Value::Nil, Value::Nil, Value::Nil);
+    if let Some(authority) = authority {
+        let (userinfo, hostport) = match authority.rsplit_once('@') {
+            Some((userinfo, hostport)) => (Some(userinfo), hostport),
+            None => (None, authority),
+        };
+        if let Some(userinfo) = userinfo {
+            let (name, pass) = match userinfo.split_once(':') {
+                Some((name, pass)) => (name, Some(pass)),
+                None => (userinfo, None),
+            };
+            user = literal(&decode(name, false));
+            password = pass.map_or(Value::Nil, |p| literal(&decode(p, false)));
+        }
+        // An IPv6 host is in brackets, since it has colons of its own
+        let (name, port_text) = match hostport.strip_prefix('[') {
+            Some(v6) => {
+                let (name, after) = v6
+                    .split_once(']')
+                    .ok_or_else(|| format!("{}: unclosed '[' in host", url))?;
+                (name, after.strip_prefix(':'))
+            }
+            None => match hostport.rsplit_once(':') {
+                Some((name, port)) => (name, Some(port)),
+                None => (hostport, None),
+            },
+        };
+        if name.is_empty() && scheme.as_deref() != Some("file") {
+            return Err(format!("{}: no host", url));
+        }
+        host = literal(&name.to_ascii_lowercase());
+        if let Some(text) = port_text.filter(|p| !p.is_empty()) {
+            let number: u16 = text
+                .parse()
+                .map_err(|_| format!("{}: bad port '{}'", url, text))?;
+            port = Value::Int(i64::from(number));
+        }
+    }
+
+    record.insert("scheme".into(), scheme.map_or(Value::Nil, |s| literal(&s)));
+    record.insert("user".into(), user);
+    record.insert("password".into(), password);
+    record.insert("host".into(), host);
+    record.insert("port".into(), port);
+    record.insert("path".into(), literal(&decode(path, false)));
+    record.insert("query".into(), query.map_or(Value::Nil, Value::Map));
+    record.insert(
+        "fragment".into(),
+        fragment.map_or(Value::Nil, |f| literal(&f)),
+    );
+    Ok(record)
+}
+
+fn build_url(record: &IndexMap<String, Value>) -> Result<String, String> {
+    let part = |key: &str| record.get(key).and_then(Value::as_arg);
+    let mut url = String::new();
+
+    if let Some(host) = part("host") {
+        let scheme = part("scheme").unwrap_or_else(|| "https".to_string());
+        url.push_str(&format!("{}://", scheme));
+        if let Some(user) = part("user") {
+            url.push_str(&encode(&user, "!$&'()*+,;="));
+            if let Some(password) = part("password") {
+                url.push(':');
+                url.push_str(&encode(&password, "!$&'()*+,;="));
+            }
+            url.push('@');
+        }
+        if host.contains(':') {
+            url.push_str(&format!("[{}]", host));
+        } else {
+            url.push_str(&host);
+        }
+        if let Some(port) = part("port") {
+            url.push_str(&format!(":{}", port));
+        }
+    } else if part("scheme").is_some() {
+        return Err("a URL with a scheme needs a host".to_string());
+    }
+
+    let path = part("path").unwrap_or_default();
+    if !url.is_empty() && !path.is_empty() && !path.starts_with('/') {
+        url.push('/');
+    }
+    url.push_str(&encode(&path, "/!$&'()*+,;=:@"));
+
+    match record.get("query") {
+        Some(Value::Map(query)) if !query.is_empty() => {
+            url.push('?');
+            url.push_str(&build_query(query));
+        }
+        Some(Value::Map(_)) | Some(Value::Nil) | None => {}
+        // A query already written out is used as it is
+        Some(query) => {
+            if let Some(query) = query.as_arg().filter(|q| !q.is_empty()) {
+                url.push('?');
+                url.push_str(query.trim_start_matches('?'));
+            }
+        }
+    }
+    if let Some(fragment) = part("fragment") {
+        url.push('#');
+        url.push_str(&encode(&fragment, "/?!$&'()*+,;=:@"));
+    }
+    Ok(url)
+}
+
+impl Evaluator {
+    /// url-parse: "url" url-parse → { scheme user password host port path query fragment }
+    pub(crate) fn builtin_url_parse(&mut self) -> Result<(), EvalError> {
+        let url = self.pop_string()?;
+        let record =
+            parse_url(url.trim()).map_err(|e| EvalError::ExecError(format!("url-parse: {}", e)))?;
+        self.stack.push(Value::Map(record));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// url-build: { scheme host port path query fragment } url-build → "url"
+    pub(crate) fn builtin_url_build(&mut self) -> Result<(), EvalError> {
+        let record = match self.pop_value_or_err()? {
+            Value::Map(record) => record,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "record".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let url =
+            build_url(&record).map_err(|e| EvalError::ExecError(format!("url-build: {}", e)))?;
+        self.stack.push(Value::Literal(url));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// url-encode: "text" url-encode → "text%20encoded", or record → query string
+    pub(crate) fn builtin_url_encode(&mut self) -> Result<(), EvalError> {
+        let encoded = match self.pop_value_or_err()? {
+            Value::Map(record) => build_query(&record),
+            value => {
+                let text = value.as_arg().ok_or_else(|| EvalError::TypeError {
+                    expected: "string or record".into(),
+                    got: value.type_name().to_string(),
+                })?;
+                encode(&text, "")
+            }
+        };
+        self.stack.push(Value::Literal(encoded));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+
+    /// url-decode: "text%20encoded" url-decode → "text encoded"
+    pub(crate) fn builtin_url_decode(&mut self) -> Result<(), EvalError> {
+        let text = self.pop_string()?;
+        self.stack.push(Value::Literal(decode(&text, false)));
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+//! Checking a Record or Table against a schema: `validate`
+//!
+//! ```text
+//! config { name "string" port "int" debug "boolean?" tags ["string"]
+//!          db { host "string" user { type "string" pattern "^[a-z]+$" } } } validate
+//! ```
+//!
+//! A schema is a record of the keys a value must have. Each key's spec is
+//! one of:
+//!
+//! - a type name: `string`, `int`, `float`, `number`, `boolean`, `list`,
+//!   `record`, `table`, `nil`, or `any`, or several joined with `|`; a
+//!   trailing `?` makes the key optional (missing or nil is fine)
+//! - a record with a `type` key, for constraints: `required`, `pattern` (a
+//!   regex a string must match), `one-of`, `min`/`max` (a number's value, or
+//!   a string's or list's length), `fields` (a schema for a record, or for
+//!   each row of a table), and `items` (a spec for each item of a list)
+//! - any other record: a nested schema the value must satisfy
+//! - a list of one spec, which every item of a list must satisfy
+//!
+//! Keys the schema doesn't mention are allowed. A value that passes is left
+//! on the stack (exit 0); one that doesn't is replaced by a `validation`
+//! Error that lists every violation, each with the path to it (exit 1).
+//! A Table is checked row by row.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+/// The keys a constraint record may have
+const CONSTRAINTS: &[&str] = &[
+    "type", "required", "pattern", "one-of", "min", "max", "fields", "items",
+];
+
+/// Problems found so far, and where
+struct Check {
+    violations: Vec<String>,
+}
+
+fn schema_error(path: &str, message: impl std::fmt::Display) -> EvalError {
+    let at = if path.is_empty() { "schema" } else { path };
+    EvalError::ExecError(format!("validate: {}: {}", at, message))
+}
+
+fn key_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Whether `value` is of the type `name`, or None for an unknown name
+fn is_type(name: &str, value: &Value) -> Option<bool> {
+    Some(match name {
+        "any" => true,
+        "string" => matches!(value, Value::Literal(_) | Value::Output(_)),
+        "int" => matches!(value, Value::Int(_) | Value::BigInt(_)),
+        "float" => matches!(value, Value::Number(_)),
+        "number" => matches!(value, Value::Int(_) | Value::BigInt(_) | Value::Number(_)),
+        "boolean" | "bool" => matches!(value, Value::Bool(_)),
+        "list" => matches!(value, Value::List(_)),
+        "record" => matches!(value, Value::Map(_)),
+        "table" => matches!(value, Value::Table { .. }),
+        "nil" => matches!(value, Value::Nil),
+        _ => return None,
+    })
+}
+
+/// A type spec's alternatives, and whether it's optional
+fn parse_type(path: &str, spec: &str) -> Result<(Vec<String>, bool), EvalError> {
+    let spec = spec.trim();
+    let (spec, optional) = match spec.strip_suffix('?') {
+        Some(rest) => (rest, true),
+        None => (spec, false),
+    };
+    let names: Vec<String> = spec.split('|').map(|n| n.trim().to_string()).collect();
+    for name in &names {
+        if is_type(name, &Value::Nil).is_none() {
+            return Err(schema_error(path, format!("unknown type '{}'", name)));
+        }
+    }
+    Ok((names, optional))
+}
+
+/// A number, or the length of a string or list, for `min` and `max`
+fn measure(value: &Value) -> Option<(f64, &'static str)> {
+    match value {
+        Value::Int(i) => Some((*i as f64, "")),
+        Value::Number(n) => Some((*n, "")),
+        Value::Literal(s) | Value::Output(s) => Some((s.chars().count() as f64, "length ")),
+        Value::List(items) => Some((items.len() as f64, "length ")),
+        _ => None,
+    }
+}
+
+fn bound(path: &str, key: &str, value: &Value) -> Result<f64, EvalError> {
+    match value {
+        Value::Int(i) => Ok(*i as f64),
+        Value::Number(n) => Ok(*n),
+        other => Err(schema_error(
+            path,
+            format!("{} must be a number, not {}", key, other.type_name()),
+        )),
+    }
+}
+
+impl Check {
+    fn violation(&mut self, path: &str, message: impl std::fmt::Display) {
+        if path.is_empty() {
+            self.violations.push(message.to_string());
+        } else {
+            self.violations.push(format!("{}: {}", path, message));
+        }
+    }
+
+    /// Whether a missing key with this spec is a violation
+    fn required(path: &str, spec: &Value) -> Result<bool, EvalError> {
+        Ok(match spec {
+            Value::Literal(s) | Value::Output(s) => !parse_type(path, s)?.1,
+            Value::Map(map) if map.contains_key("type") => {
+                let optional = match map.get("type") {
+                    Some(Value::Literal(s) | Value::Output(s)) => parse_type(path, s)?.1,
+                    _ => false,
+                };
+                match map.get("required") {
+                    Some(Value::Bool(required)) => *required,
+                    Some(other) => {
+                        return Err(schema_error(
+                            path,
+                            format!("required must be a boolean, not {}", other.type_name()),
+                        ))
+                    }
+                    None => !optional,
+                }
+            }
+            _ => true,
+        })
+    }
+
+    /// Check the keys of a record against a schema
+    fn record(
+        &mut self,
+        path: &str,
+        record: &IndexMap<String, Value>,
+        schema: &IndexMap<String, Value>,
+    ) -> Result<(), EvalError> {
+        for (key, spec) in schema {
+            let at = key_path(path, key);
+            match record.get(key) {
+                Some(value) => self.value(&at, value, spec)?,
+                None if Self::required(&at, spec)? => self.violation(&at, "missing"),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a record, or each row of a table, against a schema
+    fn records(
+        &mut self,
+        path: &str,
+        value: &Value,
+        schema: &IndexMap<String, Value>,
+    ) -> Result<(), EvalError> {
+        match value {
+            Value::Map(record) => self.record(path, record, schema),
+            Value::Table { columns, rows } => {
+                for (i, row) in rows.iter().enumerate() {
+                    let record = columns.iter().cloned().zip(row.iter().cloned()).collect();
+                    self.record(&format!("{}[{}]", path, i), &record, schema)?;
+                }
+                Ok(())
+            }
+            other => {
+                self.violation(path, format!("expected record, got {}", other.type_name()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Check one value against its spec
+    fn value(&mut self, path: &str, value: &Value, spec: &Value) -> Result<(), EvalError> {
+        match spec {
+            Value::Literal(s) | Value::Output(s) => {
+                self.typed(path, value, s)?;
+            }
+            Value::Map(map) if map.contains_key("type") => self.constrained(path, value, map)?,
+            Value::Map(schema) => self.records(path, value, schema)?,
+            Value::List(specs) if specs.len() == 1 => self.items(path, value, &specs[0])?,
+            other => {
+                return Err(schema_error(
+                    path,
+                    format!(
+                        "a spec is a type name, a record, or a list of one spec, not {}",
+                        other.type_name()
+                    ),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a value's type, returning whether it has one of the types
+    /// (nil counts for an optional spec)
+    fn typed(&mut self, path: &str, value: &Value, spec: &str) -> Result<bool, EvalError> {
+        let (names, optional) = parse_type(path, spec)?;
+        if optional && matches!(value, Value::Nil) {
+            return Ok(true);
+        }
+        if names.iter().any(|name| is_type(name, value) == Some(true)) {
+            return Ok(true);
+        }
+        self.violation(
+            path,
+            format!("expected {}, got {}", names.join(" or "), value.type_name()),
+        );
+        Ok(false)
+    }
+
+    /// Check each item of a list against a spec
+    fn items(&mut self, path: &str, value: &Value, spec: &Value) -> Result<(), EvalError> {
+        match value {
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.value(&format!("{}[{}]", path, i), item, spec)?;
+                }
+            }
+            other => self.violation(path, format!("expected list, got {}", other.type_name())),
+        }
+        Ok(())
+    }
+
+    /// Check a value against a constraint record
+    fn constrained(
+        &mut self,
+        path: &str,
+        value: &Value,
+        spec: &IndexMap<String, Value>,
+    ) -> Result<(), EvalError> {
+        if let Some(key) = spec.keys().find(|k| !CONSTRAINTS.contains(&k.as_str())) {
+            return Err(schema_error(path, format!("unknown constraint '{}'", key)));
+        }
+        let type_spec = match &spec["type"] {
+            Value::Literal(s) | Value::Output(s) => s.clone(),
+            other => {
+                return Err(schema_error(
+                    path,
+                    format!("type must be a string, not {}", other.type_name()),
+                ))
+            }
+        };
+        // Nil passes an optional spec without meeting its constraints
+        if matches!(value, Value::Nil) && !Self::required(path, &Value::Map(spec.clone()))? {
+            return Ok(());
+        }
+        if !self.typed(path, value, &type_spec)? {
+            return Ok(());
+        }
+
+        if let Some(pattern) = spec.get("pattern") {
+            let pattern = pattern
+                .as_arg()
+                .ok_or_else(|| schema_error(path, "pattern must be a string"))?;
+            let regex = regex::Regex::new(&pattern)
+                .map_err(|e| schema_error(path, format!("bad pattern: {}", e)))?;
+            match value {
+                Value::Literal(s) | Value::Output(s)
+                    if !regex.is_match(s.trim_end_matches('\n')) =>
+                {
+                    self.violation(path, format!("'{}' doesn't match /{}/", s, pattern));
+                }
+                Value::Literal(_) | Value::Output(_) => {}
+                other => self.violation(
+                    path,
+                    format!("pattern needs a string, got {}", other.type_name()),
+                ),
+            }
+        }
+
+        if let Some(allowed) = spec.get("one-of") {
+            let Value::List(allowed) = allowed else {
+                return Err(schema_error(path, "one-of must be a list"));
+            };
+            let json = crate::ast::value_to_json(value);
+            if !allowed.iter().any(|a| crate::ast::value_to_json(a) == json) {
+                let choices: Vec<String> = allowed
+                    .iter()
+                    .map(|a| crate::ast::value_to_json(a).to_string())
+                    .collect();
+                self.violation(
+                    path,
+                    format!("{} is not one of {}", json, choices.join(", ")),
+                );
+            }
+        }
+
+        for (key, side) in [("min", "below"), ("max", "above")] {
+            let Some(limit) = spec.get(key) else {
+                continue;
+            };
+            let limit = bound(path, key, limit)?;
+            match measure(value) {
+                Some((n, what)) if (key == "min" && n < limit) || (key == "max" && n > limit) => {
+                    self.violation(path, format!("{}{} is {} {} {}", what, n, side, key, limit));
+                }
+                Some(_) => {}
+                None => self.violation(
+                    path,
+                    format!(
+                        "{} needs a number, string, or list, got {}",
+                        key,
+                        value.type_name()
+                    ),
+                ),
+            }
+        }
+
+        if let Some(fields) = spec.get("fields") {
+            let Value::Map(schema) = fields else {
+                return Err(schema_error(path, "fields must be a record"));
+            };
+            self.records(path, value, schema)?;
+        }
+        if let Some(items) = spec.get("items") {
+            self.items(path, value, items)?;
+        }
+        Ok(())
+    }
+}
+
+impl Evaluator {
+    /// validate: value schema validate → value, or a validation Error
+    pub(crate) fn builtin_validate(&mut self) -> Result<(), EvalError> {
+        let schema = match self.pop_value_or_err()? {
+            Value::Map(schema) => schema,
+            other => {
+                return Err(EvalError::TypeError {
+                    expected: "schema record (validate)".into(),
+                    got: other.type_name().to_string(),
+                })
+            }
+        };
+        let value = self.pop_value_or_err()?;
+        if !matches!(value, Value::Map(_) | Value::Table { .. }) {
+            return Err(EvalError::TypeError {
+                expected: "record or table (validate)".into(),
+                got: value.type_name().to_string(),
+            });
+        }
+
+        let mut check = Check {
+            violations: Vec::new(),
+        };
+        check.records("", &value, &schema)?;
+        if check.violations.is_empty() {
+            self.stack.push(value);
+            self.last_exit_code = 0;
+            return Ok(());
+        }
+        let count = check.violations.len();
+        self.stack.push(Value::Error {
+            kind: "validation".to_string(),
+            message: format!(
+                "{} violation{}: {}",
+                count,
+                if count == 1 { "" } else { "s" },
+                check.violations.join("; ")
+            ),
+            code: Some(1),
+            source: None,
+            command: Some("validate".to_string()),
+        });
+        self.last_exit_code = 1;
+        Ok(())
+    }
+}
@@ -5,6 +5,9 @@
 //! Usage:
 //!   "src/*.rs" [cargo build] watch        # Watch with defaults
 //!   "src/*.rs" [cargo build] 500 watch    # Watch with 500ms debounce
+//!   "app.log" [echo] follow               # Run a block on each new line
+//!   "app.log" #[from-json #["level" get "error" eq?] where] follow-paged
+//!                                         # Only errors, in the pager
 
 use super::{EvalError, Evaluator};
 use crate::ast::{Expr, Value};
@@ -12,12 +15,20 @@ use crate::ast::{Expr, Value};
 #[cfg(feature = "plugins")]
 mod watch_impl {
     use super::*;
+    use crate::ast::value_to_json;
+    use crate::display::{format_value, styled};
+    use crate::signals::InterruptGuard;
     use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
     use std::collections::HashSet;
+    use std::io::{Read, Seek, SeekFrom, Write};
     use std::path::Path;
-    use std::sync::mpsc::{channel, RecvTimeoutError};
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
     use std::time::{Duration, Instant};
 
+    /// How long `follow` waits for a change before checking for cancellation
+    const FOLLOW_POLL: Duration = Duration::from_millis(250);
+
     impl Evaluator {
         /// watch: "pattern" #[block] watch -> (blocks until Ctrl+C)
         /// Watch files matching pattern, re-run block on changes
@@ -113,22 +124,22 @@ mod watch_impl {
             })?;
 
             // Print initial message
-            eprintln!("\x1b[36m◉ Watching: {}\x1b[0m", pattern);
-            eprintln!("\x1b[90m  Press Ctrl+C to stop\x1b[0m");
+            crate::display::eprintln_styled(&format!("\x1b[36m◉ Watching: {}\x1b[0m", pattern));
+            crate::display::eprintln_styled("\x1b[90m  Press Ctrl+C to stop\x1b[0m");
 
             // Run block initially
-            eprintln!("\x1b[33m▶ Running initial build...\x1b[0m");
+            crate::display::eprintln_styled("\x1b[33m▶ Running initial build...\x1b[0m");
             let start = Instant::now();
             match self.run_block_capture(block) {
                 Ok(_) => {
                     let elapsed = start.elapsed();
-                    eprintln!(
+                    crate::display::eprintln_styled(&format!(
                         "\x1b[32m✓ Completed in {:.2}s\x1b[0m",
                         elapsed.as_secs_f64()
-                    );
+                    ));
                 }
                 Err(e) => {
-                    eprintln!("\x1b[31m✗ Failed: {}\x1b[0m", e);
+                    crate::display::eprintln_styled(&format!("\x1b[31m✗ Failed: {}\x1b[0m", e));
                 }
             }
 
@@ -158,7 +169,7 @@ mod watch_impl {
                         if !pending_changes.is_empty() && last_run.elapsed() >= debounce {
                             // Clear terminal line and show what changed
                             let changed: Vec<_> = pending_changes.drain().collect();
-                            eprintln!(
+                            crate::display::eprintln_styled(&format!(
                                 "\n\x1b[33m▶ Changed: {}\x1b[0m",
                                 changed
                                     .iter()
@@ -168,20 +179,23 @@ mod watch_impl {
                                         .unwrap_or_else(|| p.clone()))
                                     .collect::<Vec<_>>()
                                     .join(", ")
-                            );
+                            ));
 
                             // Run the block
                             let start = Instant::now();
                             match self.run_block_capture(block) {
                                 Ok(_) => {
                                     let elapsed = start.elapsed();
-                                    eprintln!(
+                                    crate::display::eprintln_styled(&format!(
                                         "\x1b[32m✓ Completed in {:.2}s\x1b[0m",
                                         elapsed.as_secs_f64()
-                                    );
+                                    ));
                                 }
                                 Err(e) => {
-                                    eprintln!("\x1b[31m✗ Failed: {}\x1b[0m", e);
+                                    crate::display::eprintln_styled(&format!(
+                                        "\x1b[31m✗ Failed: {}\x1b[0m",
+                                        e
+                                    ));
                                 }
                             }
 
@@ -199,7 +213,7 @@ mod watch_impl {
                 // A more sophisticated approach would use a shared atomic flag
             }
 
-            eprintln!("\n\x1b[36m◉ Watch stopped\x1b[0m");
+            crate::display::eprintln_styled("\n\x1b[36m◉ Watch stopped\x1b[0m");
             Ok(())
         }
 
@@ -291,6 +305,173 @@ mod watch_impl {
             false
         }
 
+        /// follow: "path" #[block] follow -> (runs until Ctrl+C)
+        /// Like `tail -f`: from the end of the file, run the block on each
+        /// line as it's appended and show what it leaves. A file that is
+        /// truncated is followed from its new start.
+        pub(crate) fn builtin_follow(&mut self) -> Result<(), EvalError> {
+            let block = self.pop_block()?;
+            let name = self.pop_string()?;
+            self.follow_file(&name, &block, &mut print_live, true)
+        }
+
+        /// follow-paged: "path" #[block] follow-paged -> (runs until the pager quits)
+        /// `follow` into `$PAGER` (`less -R +F` unless set), so what has
+        /// scrolled by can be searched. Without a terminal it is `follow`.
+        pub(crate) fn builtin_follow_paged(&mut self) -> Result<(), EvalError> {
+            let block = self.pop_block()?;
+            let name = self.pop_string()?;
+            if self.capture_mode || !Self::is_interactive() {
+                return self.follow_file(&name, &block, &mut print_live, true);
+            }
+
+            let pager = crate::eval::env::var("PAGER")
+                .ok()
+                .filter(|p| !p.trim().is_empty())
+                .unwrap_or_else(|| "less -R +F".to_string());
+            let mut words: Vec<String> = pager.split_whitespace().map(String::from).collect();
+            // less only keeps reading its input in follow mode
+            if Path::new(&words[0]).file_name() == Some("less".as_ref())
+                && !words.iter().any(|w| w == "+F")
+            {
+                words.push("+F".into());
+            }
+            let audit = self.start_process("follow-paged", &words[0], &words[1..])?;
+            let mut child = Command::new(&words[0])
+                .args(&words[1..])
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| EvalError::ExecError(format!("follow-paged: {}: {}", words[0], e)))?;
+            let Some(mut stdin) = child.stdin.take() else {
+                return Err(EvalError::ExecError("follow-paged: no pager input".into()));
+            };
+
+            // Ctrl+C is the pager's, to stop following and look around
+            let result = self.follow_file(
+                &name,
+                &block,
+                &mut |text| {
+                    matches!(child.try_wait(), Ok(None)) && stdin.write_all(text.as_bytes()).is_ok()
+                },
+                false,
+            );
+            drop(stdin);
+            if let Ok(status) = child.wait() {
+                audit.finish(status.code().unwrap_or(-1));
+            }
+            result
+        }
+
+        /// Follow `name`, handing what the block shows for each line to
+        /// `show` until it returns false, the evaluation is cancelled, or
+        /// (when `interruptible`) Ctrl+C is pressed
+        fn follow_file(
+            &mut self,
+            name: &str,
+            block: &[Expr],
+            show: &mut dyn FnMut(&str) -> bool,
+            interruptible: bool,
+        ) -> Result<(), EvalError> {
+            let path = self.cwd.join(self.expand_tilde(name));
+            let fail = |e: std::io::Error| EvalError::ExecError(format!("follow: {}: {}", name, e));
+
+            let mut offset = std::fs::metadata(&path).map_err(fail)?.len();
+            let changes = FileChanges::new(&path);
+            let interrupts = InterruptGuard::new();
+            let mut partial = Vec::new();
+            self.last_exit_code = 0;
+            loop {
+                self.check_cancelled()?;
+                if interruptible && interrupts.interrupted() {
+                    self.last_exit_code = 0;
+                    return Ok(());
+                }
+                changes.wait(FOLLOW_POLL);
+                // Rotated away: wait for the file to come back
+                let Ok(len) = std::fs::metadata(&path).map(|m| m.len()) else {
+                    continue;
+                };
+                if len < offset {
+                    offset = 0;
+                    partial.clear();
+                }
+                if len == offset {
+                    continue;
+                }
+                let mut file = std::fs::File::open(&path).map_err(fail)?;
+                file.seek(SeekFrom::Start(offset)).map_err(fail)?;
+                let read = file
+                    .take(len - offset)
+                    .read_to_end(&mut partial)
+                    .map_err(fail)?;
+                offset += read as u64;
+
+                while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = partial.drain(..=end).collect();
+                    let text = self.decode_output(&line[..end]);
+                    let text = text.strip_suffix('\r').unwrap_or(&text).to_string();
+                    let shown = self.follow_line(&text, block);
+                    if !shown.is_empty() && !show(&shown) {
+                        self.last_exit_code = 0;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        /// Run the block on one line, on a stack of its own, and render
+        /// what it leaves: text as it is, records and lists as a line of
+        /// JSON, tables as tables. A Bool on top filters: true shows the
+        /// values under it (or the line itself), false shows nothing. An
+        /// error is reported and the next line goes on.
+        fn follow_line(&mut self, line: &str, block: &[Expr]) -> String {
+            let saved_stack = std::mem::take(&mut self.stack);
+            let old_capture = self.capture_mode;
+            self.capture_mode = true;
+            self.stack.push(Value::Literal(line.to_string()));
+            let ran = block.iter().try_for_each(|expr| self.eval_expr(expr));
+            self.capture_mode = old_capture;
+            let mut results = std::mem::replace(&mut self.stack, saved_stack);
+
+            if let Err(e) = ran {
+                eprintln!("follow: {}", e);
+                return String::new();
+            }
+            match results.last() {
+                Some(Value::Bool(false)) => return String::new(),
+                Some(Value::Bool(true)) => {
+                    results.pop();
+                    if results.is_empty() {
+                        return format!("{}\n", line);
+                    }
+                }
+                _ => {}
+            }
+
+            let width = crate::eval::terminal::terminal_width();
+            let mut out = String::new();
+            for value in &results {
+                let text = match value {
+                    Value::Nil | Value::Marker => continue,
+                    Value::List(items) if items.is_empty() => continue,
+                    Value::Table { rows, .. } if rows.is_empty() => continue,
+                    Value::Output(s) | Value::Literal(s) => s.clone(),
+                    // One line each, like the log they came from
+                    Value::Map(_) | Value::List(_) => value_to_json(value).to_string(),
+                    Value::Table { .. } | Value::Error { .. } => styled(format_value(value, width)),
+                    other => match other.as_arg() {
+                        Some(s) => s,
+                        None => continue,
+                    },
+                };
+                out.push_str(&text);
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            out
+        }
+
         /// Run a block and print output (for watch mode)
         fn run_block_capture(&mut self, block: &[Expr]) -> Result<(), EvalError> {
             // Save capture mode
@@ -322,10 +503,55 @@ mod watch_impl {
                     other => new_stack.push(other),
                 }
             }
-            self.stack = new_stack;
+            self.stack = new_stack.into();
             let _ = std::io::stdout().flush();
         }
     }
+
+    /// Wakes `follow` when anything in its file's directory changes, and
+    /// otherwise after a timeout, so it can notice a cancel
+    struct FileChanges {
+        // Dropping the watcher stops the events
+        _watcher: Option<RecommendedWatcher>,
+        events: Receiver<()>,
+    }
+
+    /// Print what `follow` shows as it comes
+    fn print_live(text: &str) -> bool {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(text.as_bytes()).is_ok() && stdout.flush().is_ok()
+    }
+
+    impl FileChanges {
+        fn new(path: &Path) -> FileChanges {
+            let (tx, events) = channel();
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let watcher = RecommendedWatcher::new(
+                move |res: Result<Event, notify::Error>| {
+                    if res.is_ok() {
+                        let _ = tx.send(());
+                    }
+                },
+                Config::default(),
+            )
+            .and_then(|mut w| w.watch(dir, RecursiveMode::NonRecursive).map(|_| w))
+            .ok();
+            FileChanges {
+                _watcher: watcher,
+                events,
+            }
+        }
+
+        /// Wait for a change, or until `timeout` passes
+        fn wait(&self, timeout: Duration) {
+            match self.events.recv_timeout(timeout) {
+                Ok(()) => while self.events.try_recv().is_ok() {},
+                // No watcher: check the file on a timer instead
+                Err(RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        }
+    }
 }
 
 #[cfg(not(feature = "plugins"))]
@@ -0,0 +1,80 @@
+//! `with-stderr`: a block's output and errors as separate values
+//!
+//! A command whose output is captured normally loses its stderr when it
+//! succeeds, and a failed one leaves an Error carrying it. `#[block]
+//! with-stderr` runs the block with its output captured and pushes
+//! `{ stdout stderr exit }` instead: what the block produced as text, what
+//! its commands wrote to stderr, and the last exit code, so a script can
+//! branch on failure without `2>&1` mixing the two:
+//!
+//! ```text
+//! #[make build] with-stderr "exit" get 0 eq?
+//! ```
+//!
+//! The block runs on a stack of its own. An error it raises ends it, and
+//! its message joins `stderr`.
+
+use indexmap::IndexMap;
+
+use super::{EvalError, Evaluator};
+use crate::ast::Value;
+
+impl Evaluator {
+    /// with-stderr: #[block] with-stderr → { stdout stderr exit }
+    /// Run the block, keeping what its commands print to stdout and stderr apart
+    pub(crate) fn builtin_with_stderr(&mut self) -> Result<(), EvalError> {
+        let block = self.pop_block()?;
+
+        let saved = std::mem::take(&mut self.stack);
+        let old_capture = self.capture_mode;
+        let old_sink = self.stderr_sink.replace(Vec::new());
+        self.last_exit_code = 0;
+        let mut outcome = Ok(());
+        for expr in &block {
+            self.capture_mode = true;
+            outcome = self.eval_expr(expr);
+            if outcome.is_err() {
+                break;
+            }
+        }
+        self.capture_mode = old_capture;
+        let stderr = std::mem::replace(&mut self.stderr_sink, old_sink).unwrap_or_default();
+        let produced = std::mem::replace(&mut self.stack, saved);
+
+        let mut stderr = String::from_utf8_lossy(&stderr).into_owned();
+        if let Err(e) = outcome {
+            if e.is_fatal() || matches!(e, EvalError::BreakLoop) {
+                return Err(e);
+            }
+            if self.last_exit_code == 0 {
+                self.last_exit_code = 1;
+            }
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            stderr.push_str(&format!("{}\n", e));
+        }
+
+        // A failed command's Error repeats the stderr already kept
+        let stdout: Vec<String> = produced
+            .iter()
+            .filter(|v| !matches!(v, Value::Error { kind, .. } if kind == "command"))
+            .filter_map(Value::as_arg)
+            .collect();
+
+        let mut record = IndexMap::new();
+        record.insert("stdout".to_string(), Value::Literal(stdout.join("\n")));
+        record.insert(
+            "stderr".to_string(),
+            Value::Literal(stderr.trim_end_matches('\n').to_string()),
+        );
+        record.insert(
+            "exit".to_string(),
+            Value::Int(i64::from(self.last_exit_code)),
+        );
+        self.stack.push(Value::Map(record));
+        // The record carries the failure, so the script goes on
+        self.last_exit_code = 0;
+        Ok(())
+    }
+}
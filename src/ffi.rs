@@ -0,0 +1,187 @@
+//! C API for embedding hsab in programs not written in Rust
+//!
+//! Built with the `ffi` feature as a shared or static library, with the
+//! declarations in `include/hsab.h`:
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! Values cross the boundary as JSON (as `to-json` writes them), and every
+//! string hsab returns is the caller's to free with `hsab_string_free`.
+//! Python's `ctypes` or `cffi`, Node's `ffi-napi`, and similar binding
+//! generators can wrap this directly.
+//!
+//! ```c
+//! hsab *h = hsab_new();
+//! char *result = hsab_eval(h, "1 2 plus");
+//! /* {"stack":[3],"output":"3","exit_code":0} */
+//! hsab_string_free(result);
+//! hsab_free(h);
+//! ```
+//!
+//! A handle must only be used by one thread at a time.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+use crate::ast::{json_to_value, value_to_json};
+use crate::{lex, parse, EvalError, Evaluator};
+
+/// A builtin implemented by the host. It is called with the handle, to pop
+/// its arguments and push its results with `hsab_pop` and `hsab_push`, and
+/// the `userdata` it was registered with. Returning nonzero fails the eval.
+pub type HsabBuiltinFn = unsafe extern "C" fn(h: *mut Evaluator, userdata: *mut c_void) -> c_int;
+
+/// The host's `userdata`, which hsab only passes back to it
+struct UserData(*mut c_void);
+
+// The handle is used by one thread at a time, so its builtins are called
+// from whichever thread is evaluating; the host is responsible for making
+// its userdata safe to use from there.
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    /// Closures capturing this (rather than the bare pointer) keep it `Send`
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Create a new evaluator. Free it with `hsab_free`.
+#[no_mangle]
+pub extern "C" fn hsab_new() -> *mut Evaluator {
+    Box::into_raw(Box::new(Evaluator::new()))
+}
+
+/// Free an evaluator created by `hsab_new`
+///
+/// # Safety
+///
+/// `h` must be null or a handle from `hsab_new` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_free(h: *mut Evaluator) {
+    if !h.is_null() {
+        drop(Box::from_raw(h));
+    }
+}
+
+/// Evaluate `source`, returning `{"stack": [...], "output": "...",
+/// "exit_code": n}` or `{"error": "..."}` as a JSON string. Returns null if
+/// either argument is null or `source` isn't UTF-8.
+///
+/// # Safety
+///
+/// `h` must be a live handle and `source` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_eval(h: *mut Evaluator, source: *const c_char) -> *mut c_char {
+    let (Some(evaluator), Some(source)) = (h.as_mut(), str_arg(source)) else {
+        return std::ptr::null_mut();
+    };
+    let result = lex(source)
+        .map_err(|e| e.to_string())
+        .and_then(|tokens| parse(tokens).map_err(|e| e.to_string()))
+        .and_then(|program| evaluator.eval(&program).map_err(|e| e.to_string()));
+    let json = match result {
+        Ok(result) => serde_json::json!({
+            "stack": result.stack.iter().map(value_to_json).collect::<Vec<_>>(),
+            "output": result.output,
+            "exit_code": result.exit_code,
+        }),
+        Err(message) => serde_json::json!({ "error": message }),
+    };
+    into_c_string(json.to_string())
+}
+
+/// Make `name` a word that calls `f` with `userdata`, replacing any earlier
+/// builtin registered under that name. Returns 0, or -1 if `h` or `name` is
+/// null or `name` isn't UTF-8.
+///
+/// # Safety
+///
+/// `h` must be a live handle and `name` a NUL-terminated string. `userdata`
+/// must stay valid for as long as the builtin is registered.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_register_builtin(
+    h: *mut Evaluator,
+    name: *const c_char,
+    f: HsabBuiltinFn,
+    userdata: *mut c_void,
+) -> c_int {
+    let (Some(evaluator), Some(name)) = (h.as_mut(), str_arg(name)) else {
+        return -1;
+    };
+    let word = name.to_string();
+    let userdata = UserData(userdata);
+    evaluator.register_builtin(name, move |evaluator| {
+        let status = f(evaluator, userdata.get());
+        if status == 0 {
+            Ok(())
+        } else {
+            Err(EvalError::ExecError(format!(
+                "{}: builtin failed with status {}",
+                word, status
+            )))
+        }
+    });
+    0
+}
+
+/// Pop the top of the stack, returning it as a JSON string, or null if the
+/// stack is empty
+///
+/// # Safety
+///
+/// `h` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_pop(h: *mut Evaluator) -> *mut c_char {
+    match h.as_mut().and_then(Evaluator::pop_value) {
+        Some(value) => into_c_string(value_to_json(&value).to_string()),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Push the value written as JSON in `json`. Returns 0, or -1 if an argument
+/// is null or `json` isn't valid JSON.
+///
+/// # Safety
+///
+/// `h` must be a live handle and `json` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_push(h: *mut Evaluator, json: *const c_char) -> c_int {
+    let (Some(evaluator), Some(json)) = (h.as_mut(), str_arg(json)) else {
+        return -1;
+    };
+    match serde_json::from_str(json) {
+        Ok(json) => {
+            evaluator.push_value(json_to_value(json));
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// Free a string returned by hsab
+///
+/// # Safety
+///
+/// `s` must be null or a string from this API that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hsab_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+/// Hand `s` to C. JSON escapes control characters, so it can't contain NUL.
+fn into_c_string(s: String) -> *mut c_char {
+    CString::new(s).map_or(std::ptr::null_mut(), CString::into_raw)
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ============================================
+// Command frecency for completion ranking
+// ============================================
+//
+// Every command word the REPL runs (builtins, definitions, and PATH
+// commands; never arguments) is counted in `~/.hsab/frecency`, one
+// `word<TAB>count<TAB>last-used` line each. A word's score is its count
+// weighted by how recently it was last used, as zoxide ranks directories,
+// so `gi<TAB>` offers git before gimp once git is what you run. The file
+// is re-read before each write, so concurrent sessions add up rather than
+// overwrite each other. When the counts grow past `MAX_TOTAL` they are all
+// scaled down, letting words that fell out of use drop away.
+
+/// Total count above which every count is aged
+const MAX_TOTAL: u64 = 10_000;
+
+/// Seconds in an hour, a day, and a week
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// How often and how lately a word was run
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Use {
+    count: u64,
+    /// Unix seconds
+    last: u64,
+}
+
+/// Usage counts for command words, ranking completion candidates
+#[derive(Debug, Default)]
+pub(crate) struct Frecency {
+    /// Where the counts are kept; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    uses: HashMap<String, Use>,
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_uses(path: &Path) -> HashMap<String, Use> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let word = fields.next()?.to_string();
+            let count = fields.next()?.parse().ok()?;
+            let last = fields.next()?.parse().ok()?;
+            Some((word, Use { count, last }))
+        })
+        .collect()
+}
+
+impl Frecency {
+    /// Counts kept in `path`, starting from what it already holds
+    pub(crate) fn load(path: PathBuf) -> Self {
+        Frecency {
+            uses: read_uses(&path),
+            path: Some(path),
+        }
+    }
+
+    /// Count a run of each of `words` at `now`, saving to the file
+    pub(crate) fn record(&mut self, words: &[String], now: u64) {
+        if words.is_empty() {
+            return;
+        }
+        // Take in what other sessions recorded since we last looked
+        if let Some(path) = &self.path {
+            self.uses = read_uses(path);
+        }
+        for word in words {
+            let entry = self.uses.entry(word.clone()).or_insert(Use {
+                count: 0,
+                last: now,
+            });
+            entry.count += 1;
+            entry.last = now;
+        }
+        if self.uses.values().map(|u| u.count).sum::<u64>() > MAX_TOTAL {
+            for entry in self.uses.values_mut() {
+                entry.count = entry.count * 9 / 10;
+            }
+            self.uses.retain(|_, u| u.count > 0);
+        }
+        if let Err(e) = self.save() {
+            if let Some(path) = &self.path {
+                eprintln!("Could not save command counts to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut words: Vec<_> = self.uses.iter().collect();
+        words.sort_by(|a, b| a.0.cmp(b.0));
+        let text: String = words
+            .into_iter()
+            .map(|(word, u)| format!("{}\t{}\t{}\n", word, u.count, u.last))
+            .collect();
+        // Write then rename so an interrupted save keeps the previous counts
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// How strongly `word` should be preferred at `now` (0 if never run)
+    pub(crate) fn score(&self, word: &str, now: u64) -> f64 {
+        let Some(u) = self.uses.get(word) else {
+            return 0.0;
+        };
+        let age = now.saturating_sub(u.last);
+        let weight = if age < HOUR {
+            4.0
+        } else if age < DAY {
+            2.0
+        } else if age < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        u.count as f64 * weight
+    }
+
+    /// Order candidates by score, keeping the existing order among ties
+    pub(crate) fn rank(&self, candidates: &mut [String], now: u64) {
+        candidates.sort_by(|a, b| self.score(b, now).total_cmp(&self.score(a, now)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_prefers_frequent_then_recent() {
+        let now = 100 * WEEK;
+        let mut frecency = Frecency::default();
+        for _ in 0..5 {
+            frecency.record(&["git".to_string()], now - 2 * WEEK);
+        }
+        frecency.record(&["gimp".to_string()], now - 2 * WEEK);
+
+        let mut candidates = vec!["gimp".to_string(), "gio".to_string(), "git".to_string()];
+        frecency.rank(&mut candidates, now);
+        assert_eq!(candidates, vec!["git", "gimp", "gio"]);
+
+        // A burst of recent use outranks older, more frequent use
+        frecency.record(&["gimp".to_string(), "gimp".to_string()], now);
+        frecency.rank(&mut candidates, now);
+        assert_eq!(candidates, vec!["gimp", "git", "gio"]);
+    }
+
+    #[test]
+    fn test_counts_persist_and_merge_between_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frecency");
+        let mut first = Frecency::load(path.clone());
+        let mut second = Frecency::load(path.clone());
+        first.record(&["git".to_string()], 10);
+        second.record(&["git".to_string(), "ls".to_string()], 20);
+
+        let reloaded = Frecency::load(path);
+        assert_eq!(reloaded.uses["git"], Use { count: 2, last: 20 });
+        assert_eq!(reloaded.uses["ls"], Use { count: 1, last: 20 });
+    }
+
+    #[test]
+    fn test_counts_age_past_the_limit() {
+        let mut frecency = Frecency::default();
+        frecency.uses.insert(
+            "git".to_string(),
+            Use {
+                count: MAX_TOTAL,
+                last: 0,
+            },
+        );
+        frecency.record(&["gimp".to_string()], 1);
+        assert_eq!(frecency.uses["git"].count, MAX_TOTAL * 9 / 10);
+        // One run ages to nothing
+        assert!(!frecency.uses.contains_key("gimp"));
+    }
+}
@@ -21,6 +21,7 @@ pub enum Operator {
     Write,      // >
     Append,     // >>
     Read,       // <
+    HereString, // <<<
     Background, // &
     WriteErr,   // 2>
     AppendErr,  // 2>>
@@ -38,6 +39,10 @@ pub enum Token {
     BlockEnd,
     /// Array literal start: [
     ArrayStart,
+    /// Record literal start: `{` followed by whitespace
+    RecordStart,
+    /// Record literal end: `}` standing alone
+    RecordEnd,
     /// An operator
     Operator(Operator),
     /// A double-quoted string
@@ -184,6 +189,33 @@ fn array_start(input: &str) -> IResult<&str, Token> {
     value(Token::ArrayStart, char('['))(input)
 }
 
+/// Parse a record literal start: `{` followed by whitespace.
+/// Attached braces (`{a,b}`, `file{1..3}`, find's `{}`) stay words.
+fn record_start(input: &str) -> IResult<&str, Token> {
+    let (rest, _) = char('{')(input)?;
+    match rest.chars().next() {
+        Some(c) if c.is_whitespace() => Ok((rest, Token::RecordStart)),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Char,
+        ))),
+    }
+}
+
+/// Parse a record literal end: a standalone `}` (followed by whitespace,
+/// `]`, `}`, or end of input)
+fn record_end(input: &str) -> IResult<&str, Token> {
+    let (rest, _) = char('}')(input)?;
+    match rest.chars().next() {
+        None => Ok((rest, Token::RecordEnd)),
+        Some(c) if c.is_whitespace() || c == ']' || c == '}' => Ok((rest, Token::RecordEnd)),
+        _ => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Char,
+        ))),
+    }
+}
+
 /// Parse a block end: ]
 fn block_end(input: &str) -> IResult<&str, Token> {
     value(Token::BlockEnd, char(']'))(input)
@@ -229,6 +261,11 @@ fn write_op(input: &str) -> IResult<&str, Token> {
     value(Token::Operator(Operator::Write), char('>'))(input)
 }
 
+/// Parse <<< operator (must come before <)
+fn here_string_op(input: &str) -> IResult<&str, Token> {
+    value(Token::Operator(Operator::HereString), tag("<<<"))(input)
+}
+
 /// Parse < operator
 fn read_op(input: &str) -> IResult<&str, Token> {
     value(Token::Operator(Operator::Read), char('<'))(input)
@@ -253,9 +290,10 @@ fn background_op(input: &str) -> IResult<&str, Token> {
     }
 }
 
-/// Parse a variable: $VAR or ${VAR}
+/// Parse a variable: $VAR or ${VAR}, standing alone. One that runs into
+/// more of a word (`$HOME/bin`) is lexed as part of that word instead.
 fn variable(input: &str) -> IResult<&str, Token> {
-    alt((
+    let (rest, token) = alt((
         // ${VAR} form
         map(
             preceded(
@@ -275,7 +313,14 @@ fn variable(input: &str) -> IResult<&str, Token> {
             ),
             |s: &str| Token::Variable(format!("${}", s)),
         ),
-    ))(input)
+    ))(input)?;
+    if rest.starts_with(is_word_char) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    Ok((rest, token))
 }
 
 /// Parse a definition: :name
@@ -321,26 +366,28 @@ fn backtick_sequence(input: &str) -> IResult<&str, Token> {
     }
 }
 
+/// Whether `c` can be part of a word. `$` can: variables inside a word
+/// (`$HOME/bin`, `v$MAJOR`) are expanded when the word is evaluated.
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace()
+        && c != '['
+        && c != ']'
+        && c != '#'
+        && c != '&'
+        && c != '|'
+        && c != '>'
+        && c != '<'
+        && c != '"'
+        && c != '\''
+        && c != ';'
+        && c != '`'
+}
+
 /// Parse a word (command name or argument)
 fn word(input: &str) -> IResult<&str, Token> {
-    map(
-        take_while1(|c: char| {
-            !c.is_whitespace()
-                && c != '['
-                && c != ']'
-                && c != '#'
-                && c != '$'
-                && c != '&'
-                && c != '|'
-                && c != '>'
-                && c != '<'
-                && c != '"'
-                && c != '\''
-                && c != ';'
-                && c != '`'
-        }),
-        |s: &str| Token::Word(s.to_string()),
-    )(input)
+    map(take_while1(is_word_char), |s: &str| {
+        Token::Word(s.to_string())
+    })(input)
 }
 
 /// Parse any single token
@@ -352,17 +399,20 @@ fn token(input: &str) -> IResult<&str, Token> {
             alt((
                 and_op,
                 or_op,
-                err_to_out_op, // 2>&1 before 2>> and 2>
-                append_err_op, // 2>> before 2>
-                write_err_op,  // 2>
-                append_op,     // >> before >
-                write_both_op, // &> before &
+                err_to_out_op,  // 2>&1 before 2>> and 2>
+                append_err_op,  // 2>> before 2>
+                write_err_op,   // 2>
+                append_op,      // >> before >
+                write_both_op,  // &> before &
+                here_string_op, // <<< before <
             )),
             // Group 2: Block markers, strings, and backtick sequences
             alt((
-                block_start, // #[ must come before array_start [
-                array_start, // [ for array literals
-                block_end,   // ] closes both blocks and arrays
+                block_start,  // #[ must come before array_start [
+                array_start,  // [ for array literals
+                block_end,    // ] closes both blocks and arrays
+                record_start, // { opens a record literal
+                record_end,   // } closes it
                 triple_double_quoted_string,
                 triple_single_quoted_string,
                 double_quoted_string,
@@ -828,6 +878,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tokenize_variable_inside_word() {
+        let tokens = lex("$HOME/bin prefix-${USER} \\$5 $PATH]").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("$HOME/bin".to_string()),
+                Token::Word("prefix-${USER}".to_string()),
+                Token::Word("\\$5".to_string()),
+                Token::Variable("$PATH".to_string()),
+                Token::BlockEnd,
+            ]
+        );
+    }
+
     #[test]
     fn tokenize_stderr_redirects() {
         let tokens = lex("cmd 2> file").unwrap();
@@ -870,6 +935,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tokenize_record_literal() {
+        let tokens = lex("{ a 1 } { }").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::RecordStart,
+                Token::Word("a".to_string()),
+                Token::Word("1".to_string()),
+                Token::RecordEnd,
+                Token::RecordStart,
+                Token::RecordEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_attached_braces_stay_words() {
+        let tokens = lex("x{1,2} {}").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("x1".to_string()),
+                Token::Word("x2".to_string()),
+                Token::Word("{}".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn tokenize_nested_blocks() {
         let tokens = lex("#[#[inner] outer]").unwrap();
@@ -55,10 +55,18 @@
 //! let result = eval.eval(&program).unwrap();
 //! // result.output contains "hello\n"
 //! ```
+//!
+//! To share an evaluator between threads or async tasks, use
+//! [`EvaluatorHandle`], which runs one on a thread of its own.
 
 pub mod ast;
+pub mod config;
+#[cfg(feature = "dap")]
+pub mod dap;
 pub mod display;
 pub mod eval;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod lexer;
 pub mod parser;
 #[cfg(feature = "plugins")]
@@ -66,10 +74,17 @@ pub mod plugin;
 pub mod resolver;
 pub mod signals;
 pub mod util;
+pub mod value_serde;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
+pub mod workspace;
 
 // Re-export commonly used items
 pub use ast::{Expr, FutureState, Program, Value};
-pub use eval::{EvalError, EvalResult, Evaluator};
+pub use eval::{
+    builtin_effect, CancelToken, EvalError, EvalResult, Evaluator, EvaluatorHandle, HostBuiltin,
+    Limits, Origin, StackEffect,
+};
 pub use lexer::{lex, lex_spanned, LexError, Operator, Span, Token};
 pub use parser::{parse, parse_with_spans, ParseError};
 #[cfg(feature = "plugins")]
@@ -83,3 +98,22 @@ pub fn eval(input: &str) -> Result<EvalResult, String> {
     let mut evaluator = Evaluator::new();
     evaluator.eval(&program).map_err(|e| e.to_string())
 }
+
+/// Evaluate an hsab expression without running external commands. Words that
+/// name a command are refused by the exec policy, so only builtins and
+/// definitions run. This is what the WebAssembly build exposes.
+pub fn eval_pure(input: &str) -> Result<EvalResult, String> {
+    let tokens = lex(input).map_err(|e| e.to_string())?;
+    let program = parse(tokens).map_err(|e| e.to_string())?;
+    pure_evaluator().eval(&program).map_err(|e| e.to_string())
+}
+
+/// An evaluator whose exec policy allows no external commands
+fn pure_evaluator() -> Evaluator {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_exec_policy(eval::ExecPolicy {
+        allow: Some(Vec::new()),
+        ..Default::default()
+    });
+    evaluator
+}
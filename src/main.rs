@@ -6,6 +6,7 @@
 //!   hsab script.hsab  Execute a script file
 
 mod cli;
+mod frecency;
 mod prompt;
 mod rcfile;
 mod repl;
@@ -18,6 +19,29 @@ fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
     let cli = cli::parse_args(&args);
 
+    if let Some(when) = &cli.color {
+        match hsab::display::ColorChoice::parse(when) {
+            Some(choice) => hsab::display::set_color_choice(choice),
+            None => {
+                eprintln!(
+                    "hsab: invalid --color value '{}' (expected auto, always, never)",
+                    when
+                );
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    if let Some(args) = &cli.config {
+        return cli::run_config(args);
+    }
+
+    // Settings from ~/.hsab/config.toml, under whatever the environment sets
+    match hsab::config::UserConfig::load() {
+        Ok(config) => config.apply_env(),
+        Err(e) => eprintln!("hsab: {}", e),
+    }
+
     if cli.help {
         cli::print_help();
         return ExitCode::SUCCESS;
@@ -29,19 +53,44 @@ fn main() -> ExitCode {
     }
 
     if cli.init {
-        return cli::run_init();
+        return cli::run_init(cli.upgrade);
+    }
+
+    if let Some(args) = &cli.run {
+        return cli::run_tasks(args);
+    }
+
+    if cli.dap {
+        return cli::run_dap();
+    }
+
+    if cli.explain {
+        return cli::explain_program(cli.command.as_deref(), cli.script.as_deref());
     }
 
     if let Some(cmd) = cli.command {
-        return cli::execute_command_with_login(&cmd, cli.login, cli.trace);
+        return cli::execute_command_with_login(
+            &cmd,
+            cli.login,
+            cli.trace,
+            cli.restricted,
+            cli.record.as_deref(),
+        );
     }
 
     if let Some(script) = cli.script {
-        return cli::execute_script(&script, cli.trace);
+        return cli::execute_script(
+            &script,
+            &cli.script_args,
+            cli.trace,
+            cli.restricted,
+            cli.record.as_deref(),
+            cli.resume.as_deref(),
+        );
     }
 
-    match repl::run_repl_with_login(cli.login, cli.trace) {
-        Ok(()) => ExitCode::SUCCESS,
+    match repl::run_repl_with_login(cli.login, cli.trace, cli.restricted, cli.rpn) {
+        Ok(code) => ExitCode::from(code.clamp(0, 255) as u8),
         Err(e) => {
             eprintln!("REPL error: {}", e);
             ExitCode::FAILURE
@@ -20,6 +20,10 @@ pub enum ParseError {
     UnmatchedArrayStart,
     #[error("Unmatched block/array end ']'")]
     UnmatchedBlockEnd,
+    #[error("Unmatched record start '{{'")]
+    UnmatchedRecordStart,
+    #[error("Unmatched record end '}}'")]
+    UnmatchedRecordEnd,
     #[error("Empty input")]
     EmptyInput,
 }
@@ -316,7 +320,9 @@ impl Parser {
             Token::Variable(s) => Ok(vec![Expr::Variable(s)]),
             Token::BlockStart => self.parse_block().map(|e| vec![e]),
             Token::ArrayStart => self.parse_array_literal().map(|e| vec![e]),
+            Token::RecordStart => self.parse_record_literal().map(|e| vec![e]),
             Token::BlockEnd => Err(ParseError::UnmatchedBlockEnd),
+            Token::RecordEnd => Err(ParseError::UnmatchedRecordEnd),
             Token::Operator(op) => Ok(vec![self.operator_to_expr(op)]),
             Token::Define(name) => Ok(vec![Expr::Define(name)]),
             Token::LimboRef(id) => Ok(vec![Expr::LimboRef(id)]),
@@ -405,6 +411,7 @@ impl Parser {
             Operator::Write => Expr::RedirectOut,
             Operator::Append => Expr::RedirectAppend,
             Operator::Read => Expr::RedirectIn,
+            Operator::HereString => Expr::HereString,
             Operator::WriteErr => Expr::RedirectErr,
             Operator::AppendErr => Expr::RedirectErrAppend,
             Operator::WriteBoth => Expr::RedirectBoth,
@@ -461,6 +468,30 @@ impl Parser {
 
         Err(ParseError::UnmatchedArrayStart)
     }
+
+    /// Parse a record literal (everything between { and }); the evaluator
+    /// pairs the inner expressions up as key/value
+    fn parse_record_literal(&mut self) -> Result<Expr, ParseError> {
+        let mut inner = Vec::new();
+
+        while !self.is_at_end() {
+            match self.peek() {
+                Some(Token::RecordEnd) => {
+                    self.advance(); // consume the }
+                    return Ok(Expr::RecordLiteral(inner));
+                }
+                Some(_) => {
+                    let exprs = self.parse_expr()?;
+                    inner.extend(exprs);
+                }
+                None => {
+                    return Err(ParseError::UnmatchedRecordStart);
+                }
+            }
+        }
+
+        Err(ParseError::UnmatchedRecordStart)
+    }
 }
 
 /// Parse tokens into a Program
@@ -643,6 +674,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_here_string() {
+        let tokens = lex("<<< <").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(
+            program.expressions,
+            vec![Expr::HereString, Expr::RedirectIn]
+        );
+        assert_eq!(Expr::HereString.to_source(), "<<<");
+    }
+
     #[test]
     fn parse_quoted_strings() {
         let tokens = lex("\"hello world\" 'literal'").unwrap();
@@ -693,6 +735,31 @@ mod tests {
         assert!(matches!(result, Err(ParseError::UnmatchedBlockEnd)));
     }
 
+    #[test]
+    fn parse_record_literal() {
+        let tokens = lex("{ name \"x\" tags [a b] }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(
+            program.expressions,
+            vec![Expr::RecordLiteral(vec![
+                Expr::Literal("name".into()),
+                Expr::Quoted {
+                    content: "x".into(),
+                    double: true
+                },
+                Expr::Literal("tags".into()),
+                Expr::ArrayLiteral(vec![Expr::Literal("a".into()), Expr::Literal("b".into())]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_unmatched_record_start() {
+        let tokens = lex("{ name x").unwrap();
+        let result = parse(tokens);
+        assert!(matches!(result, Err(ParseError::UnmatchedRecordStart)));
+    }
+
     #[test]
     fn parse_definition() {
         let tokens = lex("#[dup .bak suffix cp] :backup").unwrap();
@@ -205,6 +205,7 @@ pub fn value_to_json(value: &crate::Value) -> String {
             }
             serde_json::to_string(&json_obj).unwrap_or_else(|_| "null".to_string())
         }
+        Value::Status(code) => format!(r#"{{"__type":"status","code":{}}}"#, code),
         Value::Link { url, text } => {
             let mut json_obj = serde_json::Map::new();
             json_obj.insert("__type".to_string(), serde_json::json!("link"));
@@ -1,4 +1,5 @@
 use crate::terminal::execute_line;
+use hsab::workspace::WorkspaceConfig;
 use hsab::Evaluator;
 use std::env;
 use std::fs;
@@ -12,10 +13,67 @@ pub(crate) fn dirs_home() -> Option<std::path::PathBuf> {
 }
 
 /// Get the stdlib path (~/.hsab/lib/stdlib.hsabrc)
-fn stdlib_path() -> Option<std::path::PathBuf> {
+pub(crate) fn stdlib_path() -> Option<std::path::PathBuf> {
     dirs_home().map(|h| h.join(".hsab").join("lib").join("stdlib.hsabrc"))
 }
 
+/// The first line of an installed stdlib, naming the hsab it came from
+const STDLIB_HEADER: &str = "# hsab-stdlib-version: ";
+
+/// The embedded stdlib as `hsab init` installs it, with a version header
+pub(crate) fn stamped_stdlib(content: &str) -> String {
+    format!(
+        "{}{}\n{}",
+        STDLIB_HEADER,
+        env!("CARGO_PKG_VERSION"),
+        content
+    )
+}
+
+/// An installed stdlib's version (None if it predates the header) and the
+/// content after the header
+pub(crate) fn split_stdlib_header(installed: &str) -> (Option<&str>, &str) {
+    match installed.split_once('\n') {
+        Some((first, rest)) if first.starts_with(STDLIB_HEADER) => {
+            (Some(first[STDLIB_HEADER.len()..].trim()), rest)
+        }
+        _ => (None, installed),
+    }
+}
+
+/// Whether a stdlib has conflicts left by `hsab init --upgrade`
+pub(crate) fn has_conflict_markers(content: &str) -> bool {
+    content.lines().any(|line| {
+        line.starts_with("<<<<<<< ") || line == "=======" || line.starts_with(">>>>>>> ")
+    })
+}
+
+/// Whether dotted version `a` comes before `b`
+fn older_version(a: &str, b: &str) -> bool {
+    let parts = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    parts(a) < parts(b)
+}
+
+/// Warn when the installed stdlib is from an older hsab than this one
+pub(crate) fn check_stdlib_version() {
+    let Some(path) = stdlib_path() else { return };
+    let Ok(installed) = fs::read_to_string(&path) else {
+        return;
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    let from = match split_stdlib_header(&installed).0 {
+        Some(version) if !older_version(version, current) => return,
+        Some(version) => format!("hsab {}", version),
+        None => "an older hsab".to_string(),
+    };
+    eprintln!(
+        "Warning: {} is from {}, and this is hsab {}; run 'hsab init --upgrade' to update it",
+        path.display(),
+        from,
+        current
+    );
+}
+
 /// Load and execute ~/.hsabrc if it exists
 pub(crate) fn load_hsabrc(eval: &mut Evaluator) {
     let rc_path = match dirs_home() {
@@ -31,6 +89,16 @@ pub(crate) fn load_hsabrc(eval: &mut Evaluator) {
     load_rc_content(eval, &content, "~/.hsabrc");
 }
 
+/// Find the project's hsab.toml (searching upward from the working
+/// directory) and apply its settings
+pub(crate) fn load_workspace(eval: &mut Evaluator) -> Result<Option<WorkspaceConfig>, String> {
+    let config = WorkspaceConfig::discover(eval.cwd())?;
+    if let Some(config) = &config {
+        config.apply(eval);
+    }
+    Ok(config)
+}
+
 /// Load and execute ~/.hsab_profile if it exists (for login shells)
 pub(crate) fn load_hsab_profile(eval: &mut Evaluator) {
     // Profile search paths in order of priority
@@ -58,6 +126,9 @@ pub(crate) fn load_hsab_profile(eval: &mut Evaluator) {
 
                     // Clear the stack after each line in profile
                     eval.clear_stack();
+                    if eval.exit_requested().is_some() {
+                        break;
+                    }
                 }
             }
             break; // Only source first found profile
@@ -80,6 +151,15 @@ pub(crate) fn load_stdlib(eval: &mut Evaluator) {
         Err(_) => return, // Silently skip if not installed
     };
 
+    // Half a merge would define half of each side's words
+    if has_conflict_markers(&content) {
+        eprintln!(
+            "Warning: {} has merge conflicts from 'hsab init --upgrade' and was not loaded",
+            path.display()
+        );
+        return;
+    }
+
     load_rc_content(eval, &content, "stdlib");
 }
 
@@ -161,6 +241,10 @@ fn load_rc_content(eval: &mut Evaluator, content: &str, source: &str) {
             }
             eval.clear_stack();
             buffer.clear();
+            // `exit` ends the file, and the shell with it
+            if eval.exit_requested().is_some() {
+                return;
+            }
         }
     }
 
@@ -1,4 +1,4 @@
-use hsab::{Evaluator, ExecutableResolver, FutureState, Value};
+use hsab::{Evaluator, ExecutableResolver, FutureState, StackEffect, Token, Value};
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
@@ -10,13 +10,18 @@ use rustyline::{
 };
 use rustyline::{Helper, Result as RlResult};
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 use crate::cli::print_help;
+use crate::frecency::{now_secs, Frecency};
 use crate::prompt::{eval_prompt_definition, extract_hint_format, set_prompt_context};
-use crate::rcfile::{dirs_home, load_hsab_profile, load_hsabrc, load_stdlib};
-use crate::terminal::{execute_line, is_triple_quotes_balanced};
+use crate::rcfile::{
+    check_stdlib_version, dirs_home, load_hsab_profile, load_hsabrc, load_stdlib, load_workspace,
+};
+use crate::terminal::{
+    execute_line_recovering, install_panic_hook, is_triple_quotes_balanced, run_exit_trap,
+};
 use hsab::util::lock_or_recover;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -281,6 +286,7 @@ impl SharedState {
                 }
             }
             Value::Error { kind, .. } => format!("error:{}", kind),
+            Value::Status(code) => format!("status:{}", code),
             Value::Future { id: fid, state } => {
                 let guard = lock_or_recover(state);
                 let status = match &*guard {
@@ -328,6 +334,7 @@ impl SharedState {
                         Value::Int(i) => Some(format!("{}(num)", i)),
                         Value::Bool(b) => Some(format!("{}(bool)", b)),
                         Value::Error { message, .. } => Some(format!("ERR:{}", message)),
+                        Value::Status(code) => Some(format!("{}(status)", code)),
                         Value::Media { data, .. } => Some(format!("<img:{}B>(media)", data.len())),
                         Value::Link { url, .. } => Some(format!(
                             "<link:{}>(link)",
@@ -737,8 +744,12 @@ struct HsabHelper {
     state: Arc<Mutex<SharedState>>,
     builtins: HashSet<&'static str>,
     definitions: HashSet<String>,
+    /// Inferred stack effects of definitions, for the effect hint
+    definition_effects: HashMap<String, StackEffect>,
     /// PATH-based executable resolver for syntax highlighting
     resolver: Mutex<ExecutableResolver>,
+    /// How often and lately commands were run, for ranking completions
+    frecency: Mutex<Frecency>,
 }
 
 impl Helper for HsabHelper {}
@@ -796,6 +807,10 @@ impl Completer for HsabHelper {
                 files
             };
 
+        // Commands run often and lately come first
+        let mut completions = completions;
+        lock_or_recover(&self.frecency).rank(&mut completions, now_secs());
+
         let pairs: Vec<Pair> = completions
             .into_iter()
             .map(|c| Pair {
@@ -809,6 +824,139 @@ impl Completer for HsabHelper {
 }
 
 impl HsabHelper {
+    /// Whether `word` runs something rather than pushing itself
+    fn is_command(&self, word: &str) -> bool {
+        self.builtins.contains(word)
+            || self.definitions.contains(word)
+            || Self::is_dynamic_pattern(word)
+            || lock_or_recover(&self.resolver).is_executable(word)
+    }
+
+    /// What the last word before the cursor will take from the stack and
+    /// leave on it, naming the values it would take when they're known:
+    /// `cp: pops 2 (src=a.txt dst=b/) → pushes dst|nil`
+    fn effect_hint(&self, before_cursor: &str, stack: &[Value]) -> Option<String> {
+        let tokens = hsab::lex(before_cursor).ok()?;
+        let (Token::Word(word), earlier) = tokens.split_last()? else {
+            return None;
+        };
+
+        // The stack as it will be when the word runs, if the words before
+        // it only push themselves
+        let mut values: Option<Vec<String>> =
+            Some(stack.iter().filter_map(Value::as_arg).collect());
+        for token in earlier {
+            let pushed = match token {
+                Token::Word(w) if !self.is_command(w) => Some(w.clone()),
+                Token::DoubleQuoted(s) | Token::SingleQuoted(s) => Some(s.clone()),
+                _ => None,
+            };
+            match (pushed, values.as_mut()) {
+                (Some(value), Some(values)) => values.push(value),
+                _ => values = None,
+            }
+        }
+        let shown = |value: &String| match value.chars().count() {
+            n if n > 20 => format!("{}...", value.chars().take(17).collect::<String>()),
+            _ => value.clone(),
+        };
+
+        let (effect, named) = if let Some(effect) = self.definition_effects.get(word) {
+            (effect.clone(), false)
+        } else if self.definitions.contains(word) {
+            return None;
+        } else if let Some(effect) = hsab::builtin_effect(word) {
+            (effect, true)
+        } else if ExecutableResolver::is_hsab_builtin(word)
+            || !lock_or_recover(&self.resolver).is_executable(word)
+        {
+            return None;
+        } else {
+            // External commands take everything down to a block or marker,
+            // top of the stack first
+            return Some(match values {
+                Some(values) if !values.is_empty() => {
+                    let args: Vec<String> = values.iter().rev().map(shown).collect();
+                    format!(
+                        "{}: runs `{} {}` → pushes output",
+                        word,
+                        word,
+                        args.join(" ")
+                    )
+                }
+                _ => format!(
+                    "{}: runs with the stack's values as arguments, top first → pushes output",
+                    word
+                ),
+            });
+        };
+
+        let wanted = effect.inputs.len();
+        let taken = match &values {
+            Some(values) => {
+                let missing = wanted.saturating_sub(values.len());
+                let bound: Vec<String> = std::iter::repeat_n("?".to_string(), missing)
+                    .chain(
+                        values[values.len() - (wanted - missing)..]
+                            .iter()
+                            .map(shown),
+                    )
+                    .collect();
+                match named {
+                    true => effect
+                        .inputs
+                        .iter()
+                        .zip(bound)
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect(),
+                    false => bound,
+                }
+            }
+            None if named => effect.inputs.clone(),
+            None => Vec::new(),
+        };
+        let pops = match (wanted, taken.is_empty()) {
+            (0, _) => "pops nothing".to_string(),
+            (n, true) => format!("pops {}", n),
+            (n, false) => format!("pops {} ({})", n, taken.join(" ")),
+        };
+        let pushes = match (effect.outputs.len(), named) {
+            (0, _) => "pushes nothing".to_string(),
+            (_, true) => format!("pushes {}", effect.outputs.join(" ")),
+            (n, false) => format!("pushes {}", n),
+        };
+        Some(format!("{}: {} → {}", word, pops, pushes))
+    }
+
+    /// Count the commands `line` ran, for ranking completions
+    fn record_commands(&self, line: &str, eval: &Evaluator) {
+        let Ok(tokens) = hsab::lex(line) else {
+            return;
+        };
+        let mut resolver = lock_or_recover(&self.resolver);
+        let words: Vec<String> = tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Word(word) => Some(word),
+                _ => None,
+            })
+            .filter(|word| {
+                self.builtins.contains(word.as_str())
+                    || eval.has_definition(word)
+                    || resolver.is_executable(word)
+            })
+            .collect();
+        drop(resolver);
+        lock_or_recover(&self.frecency).record(&words, now_secs());
+    }
+
+    /// Take up the definitions `eval` has now, so words a line just made
+    /// (or imported) complete at the next prompt
+    fn sync_definitions(&mut self, eval: &mut Evaluator) {
+        self.definitions = eval.definition_names();
+        self.definition_effects = eval.definition_effects();
+    }
+
     /// Complete files in the current directory (for postfix value-first completion)
     fn complete_current_dir(&self, prefix: &str) -> Vec<String> {
         let mut completions = Vec::new();
@@ -840,10 +988,14 @@ impl HsabHelper {
             }
         }
 
-        // Check user definitions
+        // Check user definitions. An imported word completes to its
+        // namespace first (`myl` → `mylib::`), and `mylib::` lists the words
         for d in &self.definitions {
-            if d.starts_with(prefix) {
-                completions.push(d.clone());
+            if let Some(rest) = d.strip_prefix(prefix) {
+                match rest.find("::") {
+                    Some(end) => completions.push(d[..prefix.len() + end + 2].to_string()),
+                    None => completions.push(d.clone()),
+                }
             }
         }
 
@@ -938,12 +1090,16 @@ fn completion_builtins() -> HashSet<&'static str> {
 impl Hinter for HsabHelper {
     type Hint = String;
 
-    fn hint(&self, _line: &str, _pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
-        // Stack hint on next line
-        if let Ok(state) = self.state.lock() {
-            state.compute_hint()
-        } else {
-            None
+    fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
+        let state = self.state.lock().ok()?;
+        // Effect of the word just typed, then the stack, on lines below
+        let effect = state
+            .hint_visible
+            .then(|| self.effect_hint(&line[..pos], &state.stack))
+            .flatten();
+        match (effect, state.compute_hint()) {
+            (None, stack) => stack,
+            (Some(effect), stack) => Some(format!("\n{}{}", effect, stack.unwrap_or_default())),
         }
     }
 }
@@ -955,7 +1111,7 @@ impl Highlighter for HsabHelper {
             .lock()
             .map(|s| s.highlight_enabled)
             .unwrap_or(false);
-        if !highlight_enabled || line.is_empty() {
+        if !highlight_enabled || line.is_empty() || !hsab::display::color_enabled() {
             return Cow::Borrowed(line);
         }
 
@@ -1002,6 +1158,9 @@ impl Highlighter for HsabHelper {
 
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
         // Dim the stack hint
+        if !hsab::display::color_enabled() {
+            return Cow::Borrowed(hint);
+        }
         Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint))
     }
 }
@@ -1247,12 +1406,105 @@ impl HsabHelper {
 
 impl Validator for HsabHelper {}
 
+// ============================================
+// Stack persistence (HSAB_PERSIST_STACK)
+// ============================================
+
+/// File the stack is kept in between sessions, if HSAB_PERSIST_STACK is on
+fn persisted_stack_path() -> Option<std::path::PathBuf> {
+    let enabled = std::env::var("HSAB_PERSIST_STACK")
+        .map(|v| hsab::config::is_on(&v))
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+    dirs_home().map(|h| h.join(".hsab").join("stack.json"))
+}
+
+/// Whether HSAB_SHARE_DEFS asks for definitions shared between sessions
+fn shared_defs_enabled() -> bool {
+    std::env::var("HSAB_SHARE_DEFS")
+        .map(|v| hsab::config::is_on(&v))
+        .unwrap_or(false)
+}
+
+/// File concurrent sessions share definitions through
+fn shared_defs_path() -> Option<std::path::PathBuf> {
+    dirs_home().map(|h| h.join(".hsab").join("session-defs"))
+}
+
+/// Mention definitions that arrived from other sessions
+fn report_received_definitions(report: &hsab::eval::SyncReport) {
+    if !report.received.is_empty() {
+        eprintln!(
+            "\x1b[90m↻ from another session: {}\x1b[0m",
+            report.received.join(", ")
+        );
+    }
+}
+
+/// Push the stack saved by the last session, listing what came back
+fn restore_persisted_stack(eval: &mut Evaluator, path: &std::path::Path) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+    let values = match hsab::value_serde::from_json(&text) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("Could not restore stack from {}: {}", path.display(), e);
+            return;
+        }
+    };
+    if values.is_empty() {
+        return;
+    }
+    let shown: Vec<String> = values
+        .iter()
+        .map(hsab::display::format_value_hint)
+        .collect();
+    println!(
+        "Restored {} stack item{}: [{}]",
+        values.len(),
+        if values.len() == 1 { "" } else { "s" },
+        shown.join(", ")
+    );
+    for value in values {
+        eval.push_value(value);
+    }
+}
+
+/// Save the stack for the next session (an empty stack removes the file)
+fn save_persisted_stack(eval: &Evaluator, path: &std::path::Path) {
+    if eval.stack().is_empty() {
+        let _ = std::fs::remove_file(path);
+        return;
+    }
+    let save = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Write then rename so an interrupted save keeps the previous stack
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, hsab::value_serde::to_json(eval.stack()))?;
+        std::fs::rename(&tmp, path)
+    };
+    if let Err(e) = save() {
+        eprintln!("Could not save stack to {}: {}", path.display(), e);
+    }
+}
+
 // ============================================
 // REPL main loop
 // ============================================
 
-/// Run the REPL with optional login shell mode
-pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
+/// Run the REPL with optional login shell mode. Returns the last exit code,
+/// which `exit` and Ctrl-D leave with, as in other shells.
+pub(crate) fn run_repl_with_login(
+    is_login: bool,
+    trace: bool,
+    restricted: bool,
+    rpn: bool,
+) -> RlResult<i32> {
     // Set up signal handlers for job control
     hsab::signals::setup_signal_handlers();
 
@@ -1266,7 +1518,12 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
         state: Arc::clone(&shared_state),
         builtins: completion_builtins(),
         definitions: HashSet::new(),
+        definition_effects: HashMap::new(),
         resolver: Mutex::new(ExecutableResolver::new()),
+        frecency: Mutex::new(match dirs_home() {
+            Some(home) => Frecency::load(home.join(".hsab").join("frecency")),
+            None => Frecency::default(),
+        }),
     }));
 
     // Stack manipulation shortcuts:
@@ -1369,8 +1626,12 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
         })),
     );
 
+    // A panic in a line is reported and the session goes on
+    install_panic_hook();
+
     let mut eval = Evaluator::new();
     eval.set_trace_mode(trace);
+    eval.set_interactive(true);
 
     // Load profile if login shell
     if is_login {
@@ -1379,10 +1640,22 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
 
     // Load stdlib first (provides defaults)
     load_stdlib(&mut eval);
+    check_stdlib_version();
 
     // Load ~/.hsabrc (user customizations override stdlib)
     load_hsabrc(&mut eval);
 
+    // Project settings from hsab.toml (output format only affects -c/scripts)
+    if let Err(e) = load_workspace(&mut eval) {
+        eprintln!("Warning: {}", e);
+    }
+
+    // Restrictions start once the startup files have run
+    if restricted {
+        eval.set_restricted();
+    }
+    eval.set_calc_mode(rpn);
+
     // Extract hint format from STACK_HINT definition (for real-time stack display)
     {
         let format = extract_hint_format(&mut eval);
@@ -1396,8 +1669,8 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
         let _ = rl.load_history(path);
     }
 
-    // Show banner only if HSAB_BANNER is set
-    if std::env::var("HSAB_BANNER").is_ok() {
+    // Show banner only if HSAB_BANNER is on
+    if std::env::var("HSAB_BANNER").is_ok_and(|v| hsab::config::is_on(&v)) {
         println!(
             "hsab-{}£ Hash Backwards - stack-based postfix shell",
             VERSION
@@ -1405,6 +1678,24 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
         println!("  Type 'exit' or Ctrl-D to quit, '.help' for usage");
     }
 
+    // Share definitions with other live REPLs when asked. Startup
+    // definitions are the baseline that stays private, also for .sync later.
+    let startup_defs = eval.definition_sources();
+    if shared_defs_enabled() {
+        if let Some(path) = shared_defs_path() {
+            match eval.enable_shared_definitions(&path, startup_defs.clone()) {
+                Ok(report) => report_received_definitions(&report),
+                Err(e) => eprintln!("Definition sharing disabled: {}", e),
+            }
+        }
+    }
+
+    // Bring back the previous session's stack when persistence is on
+    let stack_path = persisted_stack_path();
+    if let Some(ref path) = stack_path {
+        restore_persisted_stack(&mut eval, path);
+    }
+
     // Track items to pre-fill the next prompt (from .use command or Ctrl+Alt+Right)
     let mut prefill = String::new();
     // Track multiline input (for triple-quoted strings)
@@ -1417,12 +1708,24 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
     let fallback_multiline = format!("hsab-{}… ", VERSION);
 
     loop {
+        // `exit` (in a line or a startup file) ends the session
+        if eval.exit_requested().is_some() {
+            break;
+        }
+
+        // Publish definitions made by the last line before each prompt
+        match eval.poll_shared_definitions() {
+            Ok(report) => report_received_definitions(&report),
+            Err(e) => eprintln!("Definition sync failed: {}", e),
+        }
+
         // Reap finished background jobs when SIGCHLD was flagged (issue #30)
         if hsab::signals::check_sigchld() {
             for notice in eval.reap_jobs() {
                 eprintln!("{}", notice);
             }
         }
+        eval.run_job_done_hook();
 
         // Check if Ctrl+U requested limbo values to be returned to stack
         {
@@ -1450,7 +1753,7 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
 
         // Update definitions in helper for tab completion
         if let Some(helper) = rl.helper_mut() {
-            helper.definitions = eval.definition_names();
+            helper.sync_definitions(&mut eval);
         }
 
         // Set prompt context variables before generating prompt
@@ -1484,6 +1787,12 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
 
         match readline {
             Ok(line) => {
+                // Pick up definitions other sessions made while we waited
+                match eval.poll_shared_definitions() {
+                    Ok(report) => report_received_definitions(&report),
+                    Err(e) => eprintln!("Definition sync failed: {}", e),
+                }
+
                 // Process any pending pushes from Ctrl+\ (before executing the line)
                 // and apply pending pops from Ctrl+] to the real evaluator stack
                 {
@@ -1518,7 +1827,11 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                             }
                         }
 
-                        let result = execute_line(&mut eval, &complete_input, true);
+                        let result = execute_line_recovering(&mut eval, &complete_input, true);
+                        if let Some(helper) = rl.helper_mut() {
+                            helper.sync_definitions(&mut eval);
+                            helper.record_commands(&complete_input, &eval);
+                        }
 
                         // Clear limbo and pending state after execution
                         {
@@ -1529,7 +1842,7 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
 
                         match result {
                             Ok(exit_code) => {
-                                if exit_code != 0 {
+                                if exit_code != 0 && eval.exit_requested().is_none() {
                                     eprintln!("Exit code: {}", exit_code);
                                 }
                             }
@@ -1652,6 +1965,13 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                         );
                         continue;
                     }
+                    ".calc" => {
+                        // Toggle calculator mode: ans, and numbers with units
+                        let enabled = !eval.is_calc_mode();
+                        eval.set_calc_mode(enabled);
+                        println!("Calculator mode: {}", if enabled { "ON" } else { "OFF" });
+                        continue;
+                    }
                     // === Debugger commands ===
                     ".debug" | ".d" => {
                         // Toggle debug mode
@@ -1679,7 +1999,7 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                     }
                     ".breakpoints" | ".bl" => {
                         // List all breakpoints
-                        let bps = eval.breakpoints();
+                        let bps = eval.breakpoint_descriptions();
                         if bps.is_empty() {
                             println!("No breakpoints set");
                         } else {
@@ -1717,6 +2037,167 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                         }
                         continue;
                     }
+                    _ if trimmed.starts_with(".break-if ") => {
+                        // Conditional breakpoint: pause when the condition holds
+                        let code = trimmed.strip_prefix(".break-if ").unwrap_or("");
+                        match eval.add_conditional_breakpoint(code) {
+                            Ok(()) => {
+                                if !eval.is_debug_mode() {
+                                    eval.set_debug_mode(true);
+                                    println!("Debug mode auto-enabled");
+                                }
+                                println!("Conditional breakpoint set: {}", code.trim());
+                            }
+                            Err(e) => {
+                                println!(
+                                    "Usage: .break-if <condition>  (e.g. .break-if depth 5 gt?)"
+                                );
+                                println!("  {}", e);
+                            }
+                        }
+                        continue;
+                    }
+                    _ if trimmed.starts_with(".break-def ") => {
+                        // Watchpoint on a definition's entry and exit
+                        let name = trimmed.strip_prefix(".break-def ").unwrap_or("").trim();
+                        if name.is_empty() {
+                            println!("Usage: .break-def <definition>");
+                        } else {
+                            eval.add_definition_breakpoint(name.to_string());
+                            if !eval.is_debug_mode() {
+                                eval.set_debug_mode(true);
+                                println!("Debug mode auto-enabled");
+                            }
+                            println!("Definition watchpoint set: {}", name);
+                        }
+                        continue;
+                    }
+                    ".record" => {
+                        // Stop an active recording
+                        match eval.stop_recording() {
+                            Some(steps) => println!("Recording stopped ({} steps)", steps),
+                            None => println!("Usage: .record <file>  (.record again to stop)"),
+                        }
+                        continue;
+                    }
+                    _ if trimmed.starts_with(".record ") => {
+                        if eval.is_restricted() {
+                            println!(".record: restricted: can't write files");
+                            continue;
+                        }
+                        // Record every step to a file for later .replay
+                        let path = trimmed.strip_prefix(".record ").unwrap_or("").trim();
+                        if let Some(steps) = eval.stop_recording() {
+                            println!("Previous recording stopped ({} steps)", steps);
+                        }
+                        match eval.start_recording(path) {
+                            Ok(()) => println!("Recording to {} (.record to stop)", path),
+                            Err(e) => println!("Cannot record: {}", e),
+                        }
+                        continue;
+                    }
+                    _ if trimmed.starts_with(".replay ") => {
+                        // Step through a recording without re-executing it
+                        let path = trimmed.strip_prefix(".replay ").unwrap_or("").trim();
+                        match hsab::eval::Recording::load(path) {
+                            Ok(recording) => recording.replay_prompt(),
+                            Err(e) => println!("Cannot replay: {}", e),
+                        }
+                        continue;
+                    }
+                    ".sync" => {
+                        if eval.is_restricted() {
+                            println!(".sync: restricted: can't write files");
+                            continue;
+                        }
+                        // Sync definitions now, starting sharing if it was off
+                        let result = if eval.shares_definitions() {
+                            eval.sync_shared_definitions()
+                        } else {
+                            match shared_defs_path() {
+                                Some(path) => {
+                                    eval.enable_shared_definitions(&path, startup_defs.clone())
+                                }
+                                None => Err("HOME is not set".to_string()),
+                            }
+                        };
+                        match result {
+                            Ok(report) => println!(
+                                "Definitions synced ({} published, {} received)",
+                                report.published.len(),
+                                report.received.len()
+                            ),
+                            Err(e) => println!("Cannot sync: {}", e),
+                        }
+                        continue;
+                    }
+                    _ if trimmed == ".edit" || trimmed.starts_with(".edit ") => {
+                        if eval.is_restricted() {
+                            println!(".edit: restricted: editors can run other commands");
+                            continue;
+                        }
+                        // Edit the given code, or the last command, in $EDITOR
+                        // and run it once saved
+                        let code = match trimmed.strip_prefix(".edit").map(str::trim) {
+                            Some(code) if !code.is_empty() => Some(code.to_string()),
+                            _ => rl
+                                .history()
+                                .iter()
+                                .rev()
+                                .find(|entry| {
+                                    entry.as_str() != ".edit" && !entry.starts_with(".edit ")
+                                })
+                                .cloned(),
+                        };
+                        let edited = match eval.edit_in_editor(code.as_deref().unwrap_or("")) {
+                            Ok(Some(text)) => text.trim().to_string(),
+                            Ok(None) => {
+                                println!("Edit abandoned");
+                                continue;
+                            }
+                            Err(e) => {
+                                println!("Cannot edit: {}", e);
+                                continue;
+                            }
+                        };
+                        if edited.is_empty() {
+                            continue;
+                        }
+                        println!("{}", edited);
+                        let _ = rl.add_history_entry(edited.as_str());
+                        let result = execute_line_recovering(&mut eval, &edited, true);
+                        if let Some(helper) = rl.helper_mut() {
+                            helper.sync_definitions(&mut eval);
+                            helper.record_commands(&edited, &eval);
+                        }
+                        match result {
+                            Ok(exit_code) => {
+                                cmd_num += 1;
+                                if exit_code != 0 && eval.exit_requested().is_none() {
+                                    eprintln!("Exit code: {}", exit_code);
+                                }
+                            }
+                            Err(e) => eprintln!("Error: {}", e),
+                        }
+                        continue;
+                    }
+                    ".unwatch" => {
+                        eval.clear_watches();
+                        println!("All watches cleared");
+                        continue;
+                    }
+                    _ if trimmed.starts_with(".watch ") => {
+                        // Print an expression's value after each step
+                        let code = trimmed.strip_prefix(".watch ").unwrap_or("");
+                        match eval.add_watch(code) {
+                            Ok(()) => println!("Watching: {}", code.trim()),
+                            Err(e) => {
+                                println!("Usage: .watch <expression>  (e.g. .watch depth)");
+                                println!("  {}", e);
+                            }
+                        }
+                        continue;
+                    }
                     _ if trimmed.starts_with(".delbreak ") || trimmed.starts_with(".db ") => {
                         // Remove a breakpoint
                         let pattern = trimmed
@@ -1763,7 +2244,11 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                 }
 
                 // Execute the line
-                let result = execute_line(&mut eval, trimmed, true);
+                let result = execute_line_recovering(&mut eval, trimmed, true);
+                if let Some(helper) = rl.helper_mut() {
+                    helper.sync_definitions(&mut eval);
+                    helper.record_commands(trimmed, &eval);
+                }
 
                 // Clear limbo and pending state after execution (refs are consumed or lost)
                 {
@@ -1777,7 +2262,7 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
                         // Increment command counter
                         cmd_num += 1;
                         // Stack persists between lines - use .use to move items to input
-                        if exit_code != 0 {
+                        if exit_code != 0 && eval.exit_requested().is_none() {
                             eprintln!("Exit code: {}", exit_code);
                         }
                     }
@@ -1820,12 +2305,19 @@ pub(crate) fn run_repl_with_login(is_login: bool, trace: bool) -> RlResult<()> {
         let _ = rl.save_history(path);
     }
 
-    Ok(())
+    let exit_code = eval.last_exit_code();
+    let exit_code = run_exit_trap(&mut eval, exit_code);
+
+    if let Some(ref path) = stack_path {
+        save_persisted_stack(&eval, path);
+    }
+
+    Ok(exit_code)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::completion_builtins;
+    use super::*;
 
     /// Drift guard (issue #32): the REPL completion set must offer every
     /// builtin in the authoritative registry. `complete_command` iterates
@@ -1879,4 +2371,111 @@ mod tests {
             );
         }
     }
+
+    fn helper() -> HsabHelper {
+        HsabHelper {
+            state: Arc::new(Mutex::new(SharedState::new())),
+            builtins: completion_builtins(),
+            definitions: HashSet::from(["double".to_string()]),
+            definition_effects: HashMap::from([(
+                "double".to_string(),
+                StackEffect {
+                    inputs: vec!["in1".to_string()],
+                    outputs: vec!["out1".to_string()],
+                },
+            )]),
+            resolver: Mutex::new(ExecutableResolver::new()),
+            frecency: Mutex::new(Frecency::default()),
+        }
+    }
+
+    /// The effect hint names the values a word would take, in stack order
+    #[test]
+    fn test_effect_hint_binds_values_to_inputs() {
+        let helper = helper();
+        assert_eq!(
+            helper.effect_hint("a.txt b/ cp", &[]).as_deref(),
+            Some("cp: pops 2 (src=a.txt dst=b/) → pushes dst|nil")
+        );
+        // Values already on the stack come before those typed
+        assert_eq!(
+            helper
+                .effect_hint("b/ cp", &[Value::Literal("a.txt".into())])
+                .as_deref(),
+            Some("cp: pops 2 (src=a.txt dst=b/) → pushes dst|nil")
+        );
+        assert_eq!(
+            helper.effect_hint("b/ cp", &[]).as_deref(),
+            Some("cp: pops 2 (src=? dst=b/) → pushes dst|nil")
+        );
+        // After a word that runs something, the values aren't known
+        assert_eq!(
+            helper.effect_hint("1 2 plus 3 plus", &[]).as_deref(),
+            Some("plus: pops 2 (a b) → pushes (a+b)")
+        );
+        assert_eq!(
+            helper.effect_hint("21 double", &[]).as_deref(),
+            Some("double: pops 1 (21) → pushes 1")
+        );
+        assert_eq!(helper.effect_hint("hello", &[]), None);
+        assert_eq!(helper.effect_hint("\"cp", &[]), None);
+    }
+
+    /// Words from `.import` complete as soon as the line has run, by
+    /// namespace first and then word by word
+    #[test]
+    fn test_completion_of_imported_module_words() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = dir.path().join("mylib.hsab");
+        std::fs::write(
+            &module,
+            "#[dup plus] :double\n#[dup dup times times] :cube\n",
+        )
+        .unwrap();
+        let mut eval = Evaluator::new();
+        let line = format!("\"{}\" .import", module.display());
+        assert_eq!(execute_line_recovering(&mut eval, &line, false), Ok(0));
+
+        let mut helper = helper();
+        helper.sync_definitions(&mut eval);
+        let completions = helper.complete_command("myl");
+        assert!(
+            completions.contains(&"mylib::".to_string()),
+            "{:?}",
+            completions
+        );
+        assert!(!completions.iter().any(|c| c.starts_with("mylib::d")));
+
+        let completions = helper.complete_command("mylib::");
+        assert!(completions.contains(&"mylib::double".to_string()));
+        assert!(completions.contains(&"mylib::cube".to_string()));
+        assert_eq!(helper.complete_command("mylib::c"), vec!["mylib::cube"]);
+    }
+
+    #[test]
+    fn test_panicking_line_is_reported_and_session_goes_on() {
+        install_panic_hook();
+        let mut eval = Evaluator::new();
+        eval.register_builtin("boom", |eval| {
+            eval.push_value(Value::Literal("half done".into()));
+            panic!("boom went off");
+        });
+        assert_eq!(execute_line_recovering(&mut eval, "1 2", false), Ok(0));
+
+        let err = execute_line_recovering(&mut eval, "3 boom", false).unwrap_err();
+        assert!(
+            err.contains("internal error: boom went off at src/repl.rs"),
+            "{}",
+            err
+        );
+        assert!(err.contains("This is a bug in hsab"), "{}", err);
+        assert_eq!(
+            eval.stack().len(),
+            2,
+            "the stack is as it was before the line"
+        );
+
+        assert_eq!(execute_line_recovering(&mut eval, "plus", false), Ok(0));
+        assert_eq!(eval.stack().len(), 1);
+    }
 }
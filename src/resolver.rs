@@ -74,6 +74,8 @@ pub struct ExecutableResolver {
     path_cache: HashMap<String, bool>,
     /// Parsed PATH directories
     path_dirs: Vec<String>,
+    /// Every command name on PATH, sorted, listed when first needed
+    path_index: Option<Vec<String>>,
 }
 
 impl Default for ExecutableResolver {
@@ -95,6 +97,7 @@ impl ExecutableResolver {
             builtins: default_builtins().clone(),
             path_cache: HashMap::new(),
             path_dirs,
+            path_index: None,
         }
     }
 
@@ -105,6 +108,7 @@ impl ExecutableResolver {
             builtins: default_builtins().clone(),
             path_cache: HashMap::new(),
             path_dirs,
+            path_index: None,
         }
     }
 
@@ -258,6 +262,59 @@ impl ExecutableResolver {
     /// Clear the PATH cache
     pub fn clear_cache(&mut self) {
         self.path_cache.clear();
+        self.path_index = None;
+    }
+
+    /// Every command name on PATH, sorted
+    pub fn path_commands(&mut self) -> &[String] {
+        let dirs = &self.path_dirs;
+        self.path_index.get_or_insert_with(|| {
+            let mut names: Vec<String> = dirs
+                .iter()
+                .filter_map(|dir| std::fs::read_dir(dir).ok())
+                .flat_map(|entries| entries.flatten())
+                .filter(|entry| {
+                    // Following symlinks, which many PATH commands are
+                    let Ok(meta) = std::fs::metadata(entry.path()) else {
+                        return false;
+                    };
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::PermissionsExt;
+                        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+                    }
+                    #[cfg(not(unix))]
+                    {
+                        meta.is_file()
+                    }
+                })
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names.dedup();
+            names
+        })
+    }
+
+    /// Commands on PATH within two edits of `word` (a typo's likely
+    /// meaning), closest first, at most three
+    pub fn suggest(&mut self, word: &str) -> Vec<String> {
+        let mut close: Vec<(usize, &String)> = self
+            .path_commands()
+            .iter()
+            .filter_map(|name| {
+                let distance = edit_distance(word, name);
+                // Any two-letter name is two edits from any other
+                let near = distance <= 2 && distance < word.chars().count();
+                (near && name != word).then_some((distance, name))
+            })
+            .collect();
+        close.sort();
+        close
+            .into_iter()
+            .take(3)
+            .map(|(_, name)| name.clone())
+            .collect()
     }
 
     /// Force resolve a command and cache it (for hash builtin)
@@ -288,6 +345,28 @@ impl ExecutableResolver {
     }
 }
 
+/// Edits (insertions, deletions, substitutions, and swaps of neighbouring
+/// characters) turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows for the two previous prefixes of `a` and the current one
+    let mut before: Vec<usize> = Vec::new();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (prev[j] + 1).min(row[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut prev, row);
+    }
+    prev[b.len()]
+}
+
 /// Default set of hsab builtin command names.
 /// This is the SINGLE SOURCE OF TRUTH for all hsab builtins.
 /// Builtins are dispatched in two ways:
@@ -359,6 +438,7 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             ".alias",
             ".unalias",
             ".trap",
+            ".edit-def",
             // Stack-native predicates
             "file?",
             "dir?",
@@ -366,6 +446,8 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "empty?",
             "eq?",
             "ne?",
+            "eq-deep?",
+            "compare",
             "=?",
             "!=?",
             "lt?",
@@ -401,12 +483,19 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "**",
             "++",
             "--",
+            "inc!",
+            "dec!",
             // String primitives
             "len",
             "slice",
             "indexof",
             "str-replace",
             "format",
+            "make-patch",
+            "apply-patch",
+            "apply-patch-file",
+            "edit-file",
+            "edit-lines",
             // Path operations (also in PATH_OPS constant for parser)
             "reext",
             // Phase 0: Type introspection
@@ -414,6 +503,8 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             // Phase 1: Record operations
             "record",
             "get",
+            "get?",
+            "get-or",
             "set",
             "del",
             "has?",
@@ -432,6 +523,16 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "try",
             "error?",
             "throw",
+            "validate",
+            // Exit statuses as values (`#[block] status` is structured-only,
+            // so a bare `status` stays a word, as in `status git`)
+            "ok?",
+            "failed?",
+            "code",
+            "pipefail",
+            "pipeline",
+            "heredoc",
+            "hsab-config",
             // Phase 4: Serialization bridge
             // into-X = serialize (structured -> text), from-X = parse (text -> structured)
             "into-json",
@@ -440,18 +541,28 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "into-kv",
             "into-tsv",
             "into-delimited",
+            "into-ini",
+            "into-env",
             "to-json",
             "to-csv",
             "to-lines",
             "to-kv",
             "to-tsv",
             "to-delimited",
+            "to-clipboard-tsv",
+            "to-xlsx",
+            "to-ini",
+            "to-env",
             "from-json",
             "from-csv",
             "from-lines",
             "from-kv",
             "from-tsv",
             "from-delimited",
+            "from-ini",
+            "from-env",
+            "from-passwd",
+            "columns",
             // Phase 5: Stack utilities
             "tap",
             "dip",
@@ -479,6 +590,13 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "sample-stdev",
             "percentile",
             "five-num",
+            "series",
+            "series-stats",
+            "sparkline",
+            "fake-name",
+            "fake-email",
+            "fake-rows",
+            "fake-seed",
             // Phase 8: Extended table/list ops
             "group-by",
             "join-on",
@@ -493,6 +611,7 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "reject",
             "reject-where",
             "duplicates",
+            "freq",
             // Extended spread operations
             "fields",
             "fields-keys",
@@ -500,6 +619,20 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "spread-tail",
             "spread-n",
             "spread-to",
+            // List-native operations
+            "list-push",
+            "list-pop",
+            "list-get",
+            "list-len",
+            "list-slice",
+            "list-map",
+            "list-filter",
+            "enumerate",
+            "sort",
+            "head",
+            "tail",
+            "sample",
+            "seq",
             // Phase 9: Vector operations (for embeddings)
             "dot-product",
             "magnitude",
@@ -511,15 +644,31 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "zip",
             "cross",
             "retry",
+            "cached",
+            "refresh",
+            "cache-clear",
+            "rate-limit",
+            "per-second",
+            "per-minute",
+            "per-hour",
+            "changed?",
             "compose",
+            "and-then",
+            "or-else",
             // Plugin management
             ".plugin-load",
             ".plugin-unload",
             ".plugin-reload",
             ".plugins",
             ".plugin-info",
+            // Modules
+            ".import-watch",
             // Structured builtins
             "ls-table",
+            "find-where",
+            "grep-table",
+            "edit-at",
+            "doc-view",
             // Structured-returning core builtins (issue #27)
             "ls-t",
             "ps-t",
@@ -537,6 +686,31 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             // Link operations (OSC 8)
             "link",
             "link-info",
+            // Checkpoint/resume
+            "checkpoint",
+            // Capture-mode overrides
+            "live",
+            "capture",
+            "tty-guard",
+            "with-stderr",
+            // Logging
+            "log-debug",
+            "log-info",
+            "log-warn",
+            "log-error",
+            "log-level",
+            "log-format",
+            // Explain mode
+            "explain",
+            "to-bash",
+            // Script arguments
+            "args",
+            "parse-args",
+            // ANSI text utilities
+            "strip-ansi",
+            "ansi-length",
+            "colorize",
+            "gauge",
             // Clipboard operations (OSC 52)
             ".copy",
             ".cut",
@@ -548,6 +722,15 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "to-bytes",
             "to-string",
             "read-bytes",
+            "read-file",
+            "bytes-len",
+            "bytes-slice",
+            "bytes-concat",
+            "bytes-find",
+            "encode",
+            "decode",
+            "set-encoding",
+            "encoding",
             // Hash functions (SHA-2)
             "sha256",
             "sha384",
@@ -614,6 +797,10 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "future-cancel",
             "delay",
             "delay-async",
+            "sleep",
+            "duration",
+            "size-bytes",
+            "to-unit",
             "future-map",
             "future-await-n",
             "parallel-n",
@@ -622,13 +809,43 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "await-all",
             "future-race",
             "futures-list",
+            "jobs-table",
+            "job-output",
+            "disown",
+            "nohup",
             "retry-delay",
             // HTTP client operations
             "fetch",
             "fetch-status",
             "fetch-headers",
+            "file-part",
+            "text-part",
+            "http-post-multipart",
+            "oauth2-token",
+            "dns-lookup",
+            "dns-query",
+            "rdns",
+            "reachable?",
+            "icmp-ping",
+            "http-healthy?",
+            "url-parse",
+            "url-build",
+            "url-encode",
+            "url-decode",
+            "mail-send",
+            // Cloud CLI bridges
+            "aws",
+            "gcloud",
+            // Code host APIs
+            "gh-api",
+            "gl-api",
+            "pr-list",
+            "issue-create",
             // Watch mode
             "watch",
+            "follow",
+            "follow-paged",
+            "tail-file",
             // Stack-native shell operations
             "touch",
             "mkdir",
@@ -639,12 +856,34 @@ pub fn default_builtins() -> &'static HashSet<&'static str> {
             "mv",
             "rm",
             "rm-r",
+            "copy",
+            "move",
+            "remove",
+            "trash",
+            "trash-list",
+            "trash-restore",
+            "rm-trash",
+            "force",
+            "confirm-glob",
+            "autocorrect",
             "ln",
             "realpath",
             "which",
             "extname",
             "glob",
             "ls",
+            // Users and permissions
+            "whoami",
+            "id-record",
+            "is-root?",
+            "chmod-sym",
+            "chown",
+            // Disk space
+            "min-free?",
+            "mount-point",
+            "fs-type",
+            // System information
+            "sysinfo",
         ]
         .into_iter()
         .collect()
@@ -672,6 +911,31 @@ mod tests {
         assert!(!resolver.is_executable("-n"));
     }
 
+    #[test]
+    fn test_suggest_close_path_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["git", "gist", "grep", "ls"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        let mut resolver =
+            ExecutableResolver::with_path(vec![dir.path().to_string_lossy().into_owned()]);
+
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("gerp", "grep"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(resolver.suggest("gti"), vec!["git"]);
+        assert_eq!(resolver.suggest("gsit"), vec!["gist", "git"]);
+        assert_eq!(resolver.suggest("sl"), vec!["ls"]);
+        assert!(resolver.suggest("git").iter().all(|name| name != "git"));
+        assert!(resolver.suggest("xyzzy").is_empty());
+    }
+
     #[test]
     fn test_random_words_not_executable() {
         let mut resolver = ExecutableResolver::with_path(vec![]);
@@ -7,6 +7,10 @@
 //!   `.jobs`/`wait` builtins then reap finished background jobs with a
 //!   non-blocking wait (issue #30)
 //!
+//! - SIGINT (Ctrl+C), only while an [`InterruptGuard`] is alive: the
+//!   handler sets `SIGINT_RECEIVED`, so a builtin that runs until
+//!   interrupted (`follow`) can stop cleanly instead of the shell dying
+//!
 //! SIGCONT is *sent* (by `.fg`/`.bg` via `continue_process`), not handled.
 //! Handlers are async-signal-safe: they only flip an atomic flag; all
 //! reaping happens in normal code.
@@ -28,6 +32,68 @@ pub static SIGTSTP_RECEIVED: AtomicBool = AtomicBool::new(false);
 /// loop checks this to reap finished background jobs (issue #30)
 pub static SIGCHLD_RECEIVED: AtomicBool = AtomicBool::new(false);
 
+/// Flag indicating SIGINT was received while an `InterruptGuard` was alive
+pub static SIGINT_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl+C for as long as it lives, then puts back whatever
+/// handled it before. Check it with `interrupted`.
+pub struct InterruptGuard {
+    #[cfg(unix)]
+    previous: Option<nix::sys::signal::SigAction>,
+}
+
+impl InterruptGuard {
+    /// Start catching SIGINT
+    #[cfg(unix)]
+    pub fn new() -> InterruptGuard {
+        use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet};
+
+        extern "C" fn on_sigint(_: libc::c_int) {
+            SIGINT_RECEIVED.store(true, Ordering::SeqCst);
+        }
+
+        SIGINT_RECEIVED.store(false, Ordering::SeqCst);
+        let action = SigAction::new(
+            SigHandler::Handler(on_sigint),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        // Safety: the handler only stores to an atomic
+        let previous = unsafe { sigaction(Signal::SIGINT, &action) }.ok();
+        InterruptGuard { previous }
+    }
+
+    /// Start catching SIGINT (a no-op on non-Unix)
+    #[cfg(not(unix))]
+    pub fn new() -> InterruptGuard {
+        SIGINT_RECEIVED.store(false, Ordering::SeqCst);
+        InterruptGuard {}
+    }
+
+    /// Whether Ctrl+C has been pressed since the guard was made
+    pub fn interrupted(&self) -> bool {
+        SIGINT_RECEIVED.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for InterruptGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(previous) = self.previous.take() {
+            // Safety: restores the action that was there before
+            unsafe {
+                let _ = nix::sys::signal::sigaction(Signal::SIGINT, &previous);
+            }
+        }
+    }
+}
+
 /// Set up signal handlers for the shell
 #[cfg(unix)]
 pub fn setup_signal_handlers() {
@@ -118,3 +184,15 @@ pub fn terminate_process(pid: u32) -> Result<(), String> {
 pub fn terminate_process(_pid: u32) -> Result<(), String> {
     Err("Signal handling not supported on this platform".into())
 }
+
+/// Send SIGKILL to a process
+#[cfg(unix)]
+pub fn kill_process(pid: u32) -> Result<(), String> {
+    let pid = Pid::from_raw(pid as i32);
+    kill(pid, Signal::SIGKILL).map_err(|e| format!("Failed to kill process {}: {}", pid, e))
+}
+
+#[cfg(not(unix))]
+pub fn kill_process(_pid: u32) -> Result<(), String> {
+    Err("Signal handling not supported on this platform".into())
+}
@@ -1,4 +1,16 @@
-use hsab::{display, lex_spanned, parse_with_spans, Evaluator, Value};
+use hsab::workspace::OutputFormat;
+use hsab::{display, lex_spanned, parse_with_spans, EvalError, Evaluator, Program, Span, Value};
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Print results as JSON (hsab.toml `output = "json"`)
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Choose how -c, scripts, and `hsab run` print results
+pub(crate) fn set_output_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
 
 /// Execute a single line of hsab code
 pub(crate) fn execute_line(
@@ -24,30 +36,158 @@ pub(crate) fn execute_line_with_options(
     }
 
     let (program, spans) = parse_with_spans(tokens).map_err(|e| e.to_string())?;
-    let result = eval
-        .eval_with_spans(&program, &spans)
-        .map_err(|e| e.to_string())?;
+    run_program(eval, &program, &spans, print_output, use_format)
+}
+
+thread_local! {
+    /// Set while `execute_line_recovering` runs a line on this thread
+    static RECOVERING: Cell<bool> = const { Cell::new(false) };
+    /// Where the panic it caught happened, noted by the panic hook
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Keep panics that `execute_line_recovering` catches from printing on
+/// their own, noting where they happened for it to report. Any other panic
+/// still goes to the default hook.
+pub(crate) fn install_panic_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !RECOVERING.with(Cell::get) {
+            return default(info);
+        }
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        PANIC_LOCATION.with(|slot| *slot.borrow_mut() = location);
+    }));
+}
 
-    if print_output {
+/// Execute a line, surviving a panic in the evaluator: the stack goes back
+/// to what it was before the line, and the panic becomes an error that says
+/// what to put in a bug report, so the session goes on. Modules watched
+/// with `.import-watch` whose files changed are reloaded first.
+pub(crate) fn execute_line_recovering(
+    eval: &mut Evaluator,
+    input: &str,
+    print_output: bool,
+) -> Result<i32, String> {
+    for reload in eval.poll_module_reloads() {
+        eprintln!("\x1b[90m↻ {}\x1b[0m", reload);
+    }
+    let before = eval.stack().to_vec();
+    RECOVERING.with(|flag| flag.set(true));
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| execute_line(eval, input, print_output)));
+    RECOVERING.with(|flag| flag.set(false));
+    let payload = match outcome {
+        Ok(result) => return result,
+        Err(payload) => payload,
+    };
+    eval.recover_from_panic(before);
+
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic".to_string());
+    let location = PANIC_LOCATION
+        .with(|slot| slot.borrow_mut().take())
+        .map(|l| format!(" at {}", l))
+        .unwrap_or_default();
+    Err(format!(
+        "internal error: {}{}\n\
+         This is a bug in hsab {}. The line was abandoned and the stack is as it was before it.\n\
+         Please report it, with the line above and this message.",
+        message,
+        location,
+        env!("CARGO_PKG_VERSION")
+    ))
+}
+
+/// Execute only the statements of a line that start after column `col`
+/// (resuming from a checkpoint taken mid-line)
+pub(crate) fn execute_line_after(
+    eval: &mut Evaluator,
+    input: &str,
+    col: usize,
+) -> Result<i32, String> {
+    let tokens = lex_spanned(input).map_err(|e| e.to_string())?;
+    let (program, spans) = parse_with_spans(tokens).map_err(|e| e.to_string())?;
+    let skip = spans.iter().take_while(|(_, c)| *c <= col).count();
+    let rest = Program::new(program.expressions[skip..].to_vec());
+    run_program(eval, &rest, &spans[skip..], true, true)
+}
+
+/// Evaluate a parsed line and print the resulting stack
+fn run_program(
+    eval: &mut Evaluator,
+    program: &Program,
+    spans: &[Span],
+    print_output: bool,
+    use_format: bool,
+) -> Result<i32, String> {
+    let exit_code = match eval.eval_with_spans(program, spans) {
+        Ok(result) => result.exit_code,
+        // `exit` ends the line early; what it left is still printed
+        Err(EvalError::Exit(code)) => code,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if print_output && JSON_OUTPUT.load(Ordering::Relaxed) {
+        for val in eval.stack() {
+            match val {
+                // Command output without its trailing newline, as text mode prints it
+                Value::Output(s) => {
+                    println!("{}", serde_json::json!(s.trim_end_matches('\n')))
+                }
+                _ if val.as_arg().is_some() => println!("{}", hsab::ast::value_to_json(val)),
+                _ => {}
+            }
+        }
+    } else if print_output {
         // Get terminal width for formatting
         let term_width = terminal_width();
 
         // Format and print each stack item
-        for val in &result.stack {
+        for val in eval.stack() {
             if val.as_arg().is_none() {
                 continue; // Skip nil/marker
             }
 
             // Use pretty formatting for Tables, Records, and Errors when in REPL
             if use_format && is_structured(val) {
-                println!("{}", display::format_value(val, term_width));
+                println!(
+                    "{}",
+                    display::styled(display::format_value(val, term_width))
+                );
             } else if let Some(s) = val.as_arg() {
                 println!("{}", s);
             }
         }
     }
 
-    Ok(result.exit_code)
+    Ok(exit_code)
+}
+
+/// Run the EXIT trap as the shell ends with `code`, on a stack of its own.
+/// Returns the code to end with: the trap's own if it calls `exit`.
+pub(crate) fn run_exit_trap(eval: &mut Evaluator, code: i32) -> i32 {
+    let Some(trap) = eval.take_exit_trap() else {
+        return code;
+    };
+    let exiting = eval.exit_requested();
+    let saved = eval.stack().to_vec();
+    eval.clear_stack();
+    eval.set_last_exit_code(code);
+    let result = run_program(eval, &trap, &[], true, true);
+    eval.restore_stack(saved);
+    match result {
+        Ok(trap_code) if eval.exit_requested() != exiting => trap_code,
+        Ok(_) => code,
+        Err(e) => {
+            eprintln!("Error in EXIT trap: {}", e);
+            code
+        }
+    }
 }
 
 /// Check if a value is a structured type that benefits from formatting
@@ -0,0 +1,326 @@
+//! Faithful serialization of stack values
+//!
+//! `Value` derives `Serialize`/`Deserialize` using the field adapters in this
+//! module, so any serde format can hold it. Each value is tagged with its
+//! variant name (`{"int": 3}`, `{"list": [...]}`, `"nil"`), which keeps
+//! types that JSON can't tell apart (strings vs. output, ints vs. floats)
+//! distinct on the way back in:
+//!
+//! - bytes and media data are base64 strings
+//! - big integers are decimal strings
+//! - blocks are their source text, re-parsed on load
+//! - non-finite floats are the strings `"NaN"`, `"inf"`, and `"-inf"`
+//! - futures keep their id and settled result; a future that was still
+//!   pending loads as failed, since the computation behind it is gone
+//!
+//! [`to_json`] and [`from_json`] wrap a list of values in a versioned
+//! envelope for storing on disk or sending to other processes.
+
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::ast::Value;
+
+/// Version of the value encoding, stored in every envelope
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Wrap `values` in a versioned envelope
+pub fn to_json_value(values: &[Value]) -> JsonValue {
+    json!({
+        "hsab_values": FORMAT_VERSION,
+        "values": values,
+    })
+}
+
+/// Serialize `values` to a versioned JSON document
+pub fn to_json(values: &[Value]) -> String {
+    to_json_value(values).to_string()
+}
+
+/// Read values from an envelope written by [`to_json_value`]. Envelopes from
+/// a newer hsab are refused rather than misread.
+pub fn from_json_value(doc: &JsonValue) -> Result<Vec<Value>, String> {
+    let version = doc["hsab_values"]
+        .as_u64()
+        .ok_or("not an hsab value document")?;
+    if version > FORMAT_VERSION as u64 {
+        return Err(format!(
+            "value format version {} is newer than this hsab supports ({})",
+            version, FORMAT_VERSION
+        ));
+    }
+    Vec::<Value>::deserialize(&doc["values"]).map_err(|e| e.to_string())
+}
+
+/// Parse a versioned JSON document written by [`to_json`]
+pub fn from_json(text: &str) -> Result<Vec<Value>, String> {
+    let doc: JsonValue = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    from_json_value(&doc)
+}
+
+/// Blocks as their source text
+pub(crate) mod block_source {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::ast::Expr;
+
+    pub fn serialize<S: Serializer>(body: &[Expr], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&crate::ast::exprs_to_source(body))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Expr>, D::Error> {
+        let src = String::deserialize(d)?;
+        let tokens = crate::lex(&src).map_err(|e| D::Error::custom(e.to_string()))?;
+        crate::parse(tokens)
+            .map(|program| program.expressions)
+            .map_err(|e| D::Error::custom(e.to_string()))
+    }
+}
+
+/// Binary data as standard base64
+pub(crate) mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(d)?;
+        STANDARD.decode(text).map_err(D::Error::custom)
+    }
+}
+
+/// Big integers as decimal strings
+pub(crate) mod bigint_decimal {
+    use num_bigint::BigInt;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &BigInt, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&n.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<BigInt, D::Error> {
+        let text = String::deserialize(d)?;
+        text.parse()
+            .map_err(|_| D::Error::custom(format!("invalid big integer: {}", text)))
+    }
+}
+
+/// Floats as numbers, with non-finite values as strings
+pub(crate) mod float {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Number(f64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(n: &f64, s: S) -> Result<S::Ok, S::Error> {
+        if n.is_nan() {
+            s.serialize_str("NaN")
+        } else if n.is_infinite() {
+            s.serialize_str(if *n > 0.0 { "inf" } else { "-inf" })
+        } else {
+            s.serialize_f64(*n)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        match Repr::deserialize(d)? {
+            Repr::Number(n) => Ok(n),
+            Repr::Text(t) => match t.as_str() {
+                "NaN" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("invalid number: {}", t))),
+            },
+        }
+    }
+}
+
+/// Future state as a snapshot of its status
+pub(crate) mod future_state {
+    use std::sync::{Arc, Mutex};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::ast::{FutureState, Value};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "status", rename_all = "lowercase")]
+    enum Snapshot {
+        Pending,
+        Completed { value: Box<Value> },
+        Failed { error: String },
+        Cancelled,
+    }
+
+    pub fn serialize<S: Serializer>(
+        state: &Arc<Mutex<FutureState>>,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        let snapshot = match &*state.lock().unwrap_or_else(|e| e.into_inner()) {
+            FutureState::Pending => Snapshot::Pending,
+            FutureState::Completed(v) => Snapshot::Completed { value: v.clone() },
+            FutureState::Failed(e) => Snapshot::Failed { error: e.clone() },
+            FutureState::Cancelled => Snapshot::Cancelled,
+        };
+        snapshot.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Arc<Mutex<FutureState>>, D::Error> {
+        let state = match Snapshot::deserialize(d)? {
+            // Nothing is left running to complete it
+            Snapshot::Pending => {
+                FutureState::Failed("future was still pending when it was saved".into())
+            }
+            Snapshot::Completed { value } => FutureState::Completed(value),
+            Snapshot::Failed { error } => FutureState::Failed(error),
+            Snapshot::Cancelled => FutureState::Cancelled,
+        };
+        Ok(Arc::new(Mutex::new(state)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::FutureState;
+    use indexmap::IndexMap;
+    use num_bigint::BigInt;
+    use std::sync::{Arc, Mutex};
+
+    fn round_trip(val: Value) -> Value {
+        let text = serde_json::to_string(&val).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_scalar_types_are_preserved() {
+        for val in [
+            Value::Literal("42".into()),
+            Value::Output("42".into()),
+            Value::Int(42),
+            Value::Number(42.0),
+            Value::Bool(true),
+            Value::Nil,
+            Value::Marker,
+        ] {
+            let back = round_trip(val.clone());
+            assert_eq!(back, val);
+            assert_eq!(back.type_name(), val.type_name());
+        }
+        assert_eq!(
+            serde_json::to_string(&Value::Int(3)).unwrap(),
+            r#"{"int":3}"#
+        );
+    }
+
+    #[test]
+    fn test_encodings() {
+        let bytes = Value::Bytes(vec![0, 255, 16]);
+        assert_eq!(
+            serde_json::to_string(&bytes).unwrap(),
+            r#"{"bytes":"AP8Q"}"#
+        );
+        assert_eq!(round_trip(bytes.clone()), bytes);
+
+        let big: BigInt = "-123456789012345678901234567890".parse().unwrap();
+        let json = serde_json::to_string(&Value::BigInt(big.clone())).unwrap();
+        assert_eq!(json, r#"{"bigint":"-123456789012345678901234567890"}"#);
+        assert_eq!(round_trip(Value::BigInt(big.clone())), Value::BigInt(big));
+
+        match round_trip(Value::Number(f64::NAN)) {
+            Value::Number(n) => assert!(n.is_nan()),
+            other => panic!("expected number, got {:?}", other),
+        }
+        assert_eq!(
+            round_trip(Value::Number(f64::NEG_INFINITY)),
+            Value::Number(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_blocks_as_source() {
+        let tokens = crate::lex(r#"dup "a b" echo [1 2] #[2 mul] map"#).unwrap();
+        let body = crate::parse(tokens).unwrap().expressions;
+        let json = serde_json::to_value(Value::Block(body)).unwrap();
+        assert_eq!(json["block"], r#"dup "a b" echo [1 2] #[2 mul] map"#);
+        match serde_json::from_value::<Value>(json.clone()).unwrap() {
+            Value::Block(back) => {
+                assert_eq!(crate::ast::exprs_to_source(&back), json["block"])
+            }
+            other => panic!("expected block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nested_structures() {
+        let mut map = IndexMap::new();
+        map.insert("n".to_string(), Value::Int(1));
+        map.insert("xs".to_string(), Value::List(vec![Value::Bool(false)]));
+        let table = Value::Table {
+            columns: vec!["a".into()],
+            rows: vec![vec![Value::Number(1.5)]],
+        };
+        let media = Value::Media {
+            mime_type: "image/png".into(),
+            data: vec![137, 80, 78, 71],
+            width: Some(1),
+            height: None,
+            alt: None,
+            source: Some("x.png".into()),
+        };
+        let val = Value::List(vec![Value::Map(map), table, media]);
+        assert_eq!(round_trip(val.clone()), val);
+    }
+
+    #[test]
+    fn test_futures_snapshot_their_state() {
+        let done = Value::Future {
+            id: "f1".into(),
+            state: Arc::new(Mutex::new(FutureState::Completed(Box::new(Value::Int(7))))),
+        };
+        match round_trip(done) {
+            Value::Future { id, state } => {
+                assert_eq!(id, "f1");
+                assert!(matches!(
+                    &*state.lock().unwrap(),
+                    FutureState::Completed(v) if **v == Value::Int(7)
+                ));
+            }
+            other => panic!("expected future, got {:?}", other),
+        }
+
+        let pending = Value::Future {
+            id: "f2".into(),
+            state: Arc::new(Mutex::new(FutureState::Pending)),
+        };
+        let json = serde_json::to_value(&pending).unwrap();
+        assert_eq!(json["future"]["state"]["status"], "pending");
+        match serde_json::from_value::<Value>(json).unwrap() {
+            Value::Future { state, .. } => {
+                assert!(matches!(&*state.lock().unwrap(), FutureState::Failed(_)))
+            }
+            other => panic!("expected future, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_envelope_versioning() {
+        let values = vec![Value::Int(1), Value::Literal("x".into())];
+        let text = to_json(&values);
+        assert!(text.contains(r#""hsab_values":1"#));
+        assert_eq!(from_json(&text).unwrap(), values);
+
+        let newer = r#"{"hsab_values": 99, "values": []}"#;
+        assert!(from_json(newer).unwrap_err().contains("newer"));
+        assert!(from_json(r#"{"values": []}"#).is_err());
+    }
+}
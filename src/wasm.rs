@@ -0,0 +1,73 @@
+//! JavaScript bindings for the WebAssembly build
+//!
+//! The core (lexer, parser, evaluator) builds for `wasm32-unknown-unknown`
+//! without plugins, the REPL, or network access:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/hsab.wasm
+//! ```
+//!
+//! ```js
+//! import init, { evalPure, Session } from "./pkg/hsab.js";
+//! await init();
+//! evalPure("1 2 plus");          // "3"
+//! const session = new Session();
+//! session.eval("#[dup mul] :square");
+//! session.eval("7 square");      // "49"
+//! ```
+//!
+//! There are no external commands in the browser, so words that would run
+//! one push themselves like any other literal. Nor is there a process
+//! environment: variables set with `.export` or `local` live in memory,
+//! start out empty, and are shared by every session.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{lex, parse, Evaluator};
+
+/// Evaluate `input` in a fresh evaluator and return the stack, one item per
+/// line. Errors are thrown as strings.
+#[wasm_bindgen(js_name = evalPure)]
+pub fn eval_pure(input: &str) -> Result<String, String> {
+    crate::eval_pure(input).map(|result| result.output)
+}
+
+/// An evaluator that keeps its stack and definitions between calls, for a
+/// REPL-style playground
+#[wasm_bindgen]
+pub struct Session {
+    evaluator: Evaluator,
+}
+
+#[wasm_bindgen]
+impl Session {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Session {
+        Session {
+            evaluator: crate::pure_evaluator(),
+        }
+    }
+
+    /// Evaluate `input` and return the stack, one item per line
+    pub fn eval(&mut self, input: &str) -> Result<String, String> {
+        let tokens = lex(input).map_err(|e| e.to_string())?;
+        let program = parse(tokens).map_err(|e| e.to_string())?;
+        self.evaluator
+            .eval(&program)
+            .map(|result| result.output)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Empty the stack
+    pub fn clear(&mut self) {
+        self.evaluator.clear_stack();
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
@@ -0,0 +1,338 @@
+//! Project-level configuration: `hsab.toml`
+//!
+//! The nearest `hsab.toml` in the working directory or one of its parents
+//! configures hsab for that project, on top of `~/.hsabrc`:
+//!
+//! ```toml
+//! strict = true                 # unset $VARS are errors
+//! pipefail = true               # pipelines fail with their first failing stage
+//! module_path = ["lib/hsab"]    # searched by .import, relative to this file
+//! output = "json"               # how -c, scripts, and `hsab run` print results
+//!
+//! [exec]
+//! allow = ["git", "cargo"]      # only these external commands may run
+//! deny = ["rm"]
+//!
+//! [audit]
+//! file = "audit.log"            # record every spawned process, relative to this file
+//! syslog = true                 # ...and send the records to syslog
+//!
+//! [limits]
+//! steps = 100000                # expressions per eval
+//! seconds = 5                   # wall-clock time per eval
+//!
+//! [scripts]
+//! fmt = "fmt cargo"
+//!
+//! [tasks]
+//! build = { run = "build cargo", deps = ["fmt"] }
+//! test = { run = "test cargo", deps = ["build"], description = "Run tests" }
+//! lint = "clippy cargo"
+//! ci = { deps = ["test", "lint"] }
+//! ```
+//!
+//! Scripts are tasks without dependencies. `hsab run` runs the named tasks
+//! after their dependencies, running independent ones in parallel.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::eval::{AuditConfig, ExecPolicy, Limits};
+use crate::Evaluator;
+
+/// Name of the project config file
+pub const WORKSPACE_FILE: &str = "hsab.toml";
+
+/// How results left on the stack are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Plain text, tables and records formatted for the terminal
+    #[default]
+    Text,
+    /// One JSON document per stack item
+    Json,
+}
+
+/// A task: hsab code to run once the tasks it depends on have finished
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "TaskSpec")]
+pub struct Task {
+    /// hsab code, run line by line
+    pub run: String,
+    /// Tasks that must succeed first
+    pub deps: Vec<String>,
+    /// Shown by `hsab run --list`
+    pub description: Option<String>,
+}
+
+/// A task as written: just code, or a table
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TaskSpec {
+    Code(String),
+    Table {
+        #[serde(default)]
+        run: String,
+        #[serde(default)]
+        deps: Vec<String>,
+        description: Option<String>,
+    },
+}
+
+impl From<TaskSpec> for Task {
+    fn from(spec: TaskSpec) -> Task {
+        match spec {
+            TaskSpec::Code(run) => Task {
+                run,
+                ..Task::default()
+            },
+            TaskSpec::Table {
+                run,
+                deps,
+                description,
+            } => Task {
+                run,
+                deps,
+                description,
+            },
+        }
+    }
+}
+
+/// A parsed `hsab.toml`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Treat unset variables as errors
+    pub strict: bool,
+    /// Pipelines report their first failing stage
+    pub pipefail: bool,
+    /// Extra module search directories
+    pub module_path: Vec<PathBuf>,
+    /// Output format for non-interactive runs
+    pub output: OutputFormat,
+    /// Which external commands may run
+    pub exec: ExecPolicy,
+    /// Where spawned processes are recorded
+    pub audit: AuditConfig,
+    /// Step and time limits for each eval
+    pub limits: Limits,
+    /// Named hsab snippets for `hsab run NAME`
+    pub scripts: IndexMap<String, String>,
+    /// Tasks with dependencies for `hsab run NAME`
+    pub tasks: IndexMap<String, Task>,
+    /// Directory holding the config file
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+/// Find the nearest `hsab.toml` in `start` or its parents
+pub fn find(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(WORKSPACE_FILE))
+        .find(|path| path.is_file())
+}
+
+impl WorkspaceConfig {
+    /// Read a config file; relative module paths are resolved against its
+    /// directory
+    pub fn load(path: &Path) -> Result<WorkspaceConfig, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut config: WorkspaceConfig =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        config.root = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        config.module_path = config
+            .module_path
+            .iter()
+            .map(|dir| config.root.join(dir))
+            .collect();
+        config.audit.file = config.audit.file.map(|file| config.root.join(file));
+        if let Some(name) = config
+            .scripts
+            .keys()
+            .find(|n| config.tasks.contains_key(*n))
+        {
+            return Err(format!(
+                "{}: '{}' is both a script and a task",
+                path.display(),
+                name
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Look up a task or script by name
+    pub fn task(&self, name: &str) -> Option<Task> {
+        self.tasks.get(name).cloned().or_else(|| {
+            self.scripts.get(name).map(|run| Task {
+                run: run.clone(),
+                ..Task::default()
+            })
+        })
+    }
+
+    /// Names of all scripts and tasks, in file order
+    pub fn task_names(&self) -> Vec<&String> {
+        self.scripts.keys().chain(self.tasks.keys()).collect()
+    }
+
+    /// Every task needed to run `targets`, each after its dependencies
+    pub fn plan(&self, targets: &[String]) -> Result<Vec<String>, String> {
+        fn visit(
+            config: &WorkspaceConfig,
+            name: &str,
+            needed_by: Option<&str>,
+            path: &mut Vec<String>,
+            done: &mut HashSet<String>,
+            order: &mut Vec<String>,
+        ) -> Result<(), String> {
+            if done.contains(name) {
+                return Ok(());
+            }
+            if let Some(pos) = path.iter().position(|n| n == name) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.push(name.to_string());
+                return Err(format!("dependency cycle: {}", cycle.join(" -> ")));
+            }
+            let task = config.task(name).ok_or_else(|| match needed_by {
+                Some(parent) => format!("no task named '{}' (needed by '{}')", name, parent),
+                None => format!("no task named '{}'", name),
+            })?;
+            path.push(name.to_string());
+            for dep in &task.deps {
+                visit(config, dep, Some(name), path, done, order)?;
+            }
+            path.pop();
+            done.insert(name.to_string());
+            order.push(name.to_string());
+            Ok(())
+        }
+
+        let mut done = HashSet::new();
+        let mut order = Vec::new();
+        for target in targets {
+            visit(self, target, None, &mut Vec::new(), &mut done, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Load the nearest config above `start`, if there is one
+    pub fn discover(start: &Path) -> Result<Option<WorkspaceConfig>, String> {
+        find(start).map(|path| Self::load(&path)).transpose()
+    }
+
+    /// Apply the evaluator settings: strict mode, pipefail, exec policy,
+    /// audit log, limits, module paths
+    pub fn apply(&self, eval: &mut Evaluator) {
+        // Only turn these on, so HSAB_STRICT and HSAB_PIPEFAIL still count
+        if self.strict {
+            eval.set_strict_mode(true);
+        }
+        if self.pipefail {
+            eval.set_pipefail(true);
+        }
+        eval.set_exec_policy(self.exec.clone());
+        eval.set_audit(self.audit.clone());
+        eval.set_limits(self.limits.clone());
+        eval.set_module_paths(self.module_path.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovers_nearest_config_upward() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            dir.path().join(WORKSPACE_FILE),
+            r#"
+strict = true
+module_path = ["lib"]
+output = "json"
+
+[exec]
+deny = ["rm"]
+
+[scripts]
+build = "build cargo"
+test = "test cargo"
+"#,
+        )
+        .unwrap();
+
+        let config = WorkspaceConfig::discover(&nested).unwrap().unwrap();
+        assert_eq!(config.root, dir.path());
+        assert!(config.strict);
+        assert_eq!(config.output, OutputFormat::Json);
+        assert_eq!(config.module_path, vec![dir.path().join("lib")]);
+        assert!(config.exec.denies("/bin/rm"));
+        let names: Vec<&String> = config.scripts.keys().collect();
+        assert_eq!(names, ["build", "test"]);
+
+        let mut eval = Evaluator::new();
+        config.apply(&mut eval);
+        assert!(eval.is_strict_mode());
+    }
+
+    #[test]
+    fn test_task_plan_orders_dependencies() {
+        let config: WorkspaceConfig = toml::from_str(
+            r#"
+[scripts]
+fmt = "fmt cargo"
+
+[tasks]
+gen = "gen echo"
+build = { run = "build cargo", deps = ["gen", "fmt"] }
+test = { run = "test cargo", deps = ["build", "gen"], description = "Run tests" }
+all = { deps = ["test"] }
+loop-a = { deps = ["loop-b"] }
+loop-b = { deps = ["loop-a"] }
+broken = { deps = ["missing"] }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.plan(&["all".into()]).unwrap(),
+            ["gen", "fmt", "build", "test", "all"]
+        );
+        assert_eq!(config.task("fmt").unwrap().run, "fmt cargo");
+        assert_eq!(
+            config.task("test").unwrap().description.as_deref(),
+            Some("Run tests")
+        );
+        assert_eq!(config.task_names().len(), 8);
+
+        let err = config.plan(&["loop-a".into()]).unwrap_err();
+        assert_eq!(err, "dependency cycle: loop-a -> loop-b -> loop-a");
+        let err = config.plan(&["broken".into()]).unwrap_err();
+        assert!(err.contains("'missing' (needed by 'broken')"));
+    }
+
+    #[test]
+    fn test_missing_and_invalid_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(WORKSPACE_FILE), "").unwrap();
+        let config = WorkspaceConfig::discover(dir.path()).unwrap().unwrap();
+        assert_eq!(config.output, OutputFormat::Text);
+        assert!(config.scripts.is_empty());
+
+        std::fs::write(dir.path().join(WORKSPACE_FILE), "colour = true\n").unwrap();
+        let err = WorkspaceConfig::discover(dir.path()).unwrap_err();
+        assert!(err.contains("colour"), "{}", err);
+    }
+}
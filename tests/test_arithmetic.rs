@@ -174,3 +174,33 @@ fn test_unicode_ge() {
     let exit_code = eval_exit_code("10 5 ≥");
     assert_eq!(exit_code, 0);
 }
+
+#[test]
+fn test_inc_dec_update_variable_in_place() {
+    let output = eval(r#""HSAB_STEP_A" inc! "HSAB_STEP_A" inc! $HSAB_STEP_A"#).unwrap();
+    assert_eq!(output.trim(), "2", "unset counts as 0");
+    let output = eval(r#""HSAB_STEP_B" 10 inc! "HSAB_STEP_B" 3 dec! $HSAB_STEP_B"#).unwrap();
+    assert_eq!(output.trim(), "7");
+    std::env::remove_var("HSAB_STEP_A");
+    std::env::remove_var("HSAB_STEP_B");
+}
+
+#[test]
+fn test_inc_counts_a_while_loop() {
+    let output = eval(
+        r#"HSAB_STEP_C=0 .export #[$HSAB_STEP_C 3 lt?] #["HSAB_STEP_C" inc!] while $HSAB_STEP_C"#,
+    )
+    .unwrap();
+    assert_eq!(output.trim(), "3");
+    std::env::remove_var("HSAB_STEP_C");
+}
+
+#[test]
+fn test_inc_rejects_variable_value() {
+    std::env::set_var("HSAB_STEP_D", "4");
+    let err = eval(r#"$HSAB_STEP_D inc!"#).unwrap_err();
+    assert!(err.contains("expected a variable name"), "{}", err);
+    std::env::set_var("HSAB_STEP_D", "four");
+    assert!(eval(r#""HSAB_STEP_D" inc!"#).is_err());
+    std::env::remove_var("HSAB_STEP_D");
+}
@@ -21,6 +21,17 @@ fn test_delay_zero() {
     assert_eq!(exit_code, 0);
 }
 
+#[test]
+fn test_sleep_is_native() {
+    let start = std::time::Instant::now();
+    assert_eq!(eval(r#"0.2 sleep "done""#).unwrap(), "done");
+    assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+    assert_eq!(eval_exit_code("0.01s sleep"), 0);
+    assert_eq!(eval_exit_code("0m sleep"), 0);
+    assert!(eval("soon sleep").unwrap_err().contains("duration"));
+    assert!(eval("-1 sleep").is_err());
+}
+
 // === async/await tests ===
 
 #[test]
@@ -294,6 +305,29 @@ fn test_parallel_preserves_order() {
     assert!(output.contains("1"));
 }
 
+#[test]
+fn test_parallel_rows_follow_block_order() {
+    // The slow first block still gets the first row
+    let output = eval(
+        r#"#[#["sleep 0.3; echo slow" -c sh] #[quick echo] #["exit 3" -c sh]] parallel "exit_code" get to-json"#,
+    )
+    .unwrap();
+    assert_eq!(output, "[0,0,3]");
+    let output =
+        eval(r#"#[#["sleep 0.3; echo slow" -c sh] #[quick echo]] parallel "output" get to-json"#)
+            .unwrap();
+    assert_eq!(output, r#"["slow","quick"]"#);
+}
+
+#[test]
+fn test_parallel_runs_definitions() {
+    let output = eval(
+        r#"#["hello, " swap suffix] :greet #[#[ann greet] #[bob greet] #[1 2 plus]] parallel "output" get to-json"#,
+    )
+    .unwrap();
+    assert_eq!(output, r#"["hello, ann","hello, bob",3]"#);
+}
+
 // === parallel-map tests ===
 
 #[test]
@@ -707,3 +707,120 @@ fn test_bigint_mod_smaller_than_divisor() {
     let output = eval(r#""5" to-bigint "10" to-bigint big-mod to-string"#).unwrap();
     assert_eq!(output.trim(), "5");
 }
+
+// === Promotion in plain arithmetic ===
+
+#[test]
+fn test_comparisons_are_exact_beyond_f64() {
+    // Both round to the same f64; the comparison must not
+    let output =
+        eval(r#""123456789012345678901234567890" "123456789012345678901234567891" lt?"#).unwrap();
+    assert_eq!(output.trim(), "true");
+    let output =
+        eval(r#""123456789012345678901234567890" "123456789012345678901234567891" =?"#).unwrap();
+    assert_eq!(output.trim(), "false");
+    let output = eval("9223372036854775807 to-bigint 9223372036854775807 =?").unwrap();
+    assert_eq!(output.trim(), "true");
+}
+
+#[test]
+fn test_arithmetic_promotes_big_operands() {
+    let output = eval("123456789012345678901234567890 1 plus").unwrap();
+    assert_eq!(output.trim(), "123456789012345678901234567891");
+    let output = eval("9223372036854775807 1 plus typeof").unwrap();
+    assert_eq!(output.trim(), "bigint");
+    let output = eval("100000000000000000000 7 mod").unwrap();
+    assert_eq!(output.trim(), "2");
+    let output = eval("100000000000000000000 4 div").unwrap();
+    assert_eq!(output.trim(), "25000000000000000000");
+}
+
+#[test]
+fn test_negative_big_results_stay_exact() {
+    let output = eval("5 to-bigint 10 minus").unwrap();
+    assert_eq!(output.trim(), "-5");
+    let output = eval("5 to-bigint 10 minus typeof").unwrap();
+    assert_eq!(output.trim(), "bigint");
+    let output = eval("-9223372036854775807 2 minus").unwrap();
+    assert_eq!(output.trim(), "-9223372036854775809");
+    let output = eval("-9223372036854775807 2 minus typeof").unwrap();
+    assert_eq!(output.trim(), "bigint");
+    let output = eval("-99999999999999999999 1 plus").unwrap();
+    assert_eq!(output.trim(), "-99999999999999999998");
+}
+
+#[test]
+fn test_negative_big_comparisons() {
+    let output = eval("-99999999999999999999 -99999999999999999998 lt?").unwrap();
+    assert_eq!(output.trim(), "true");
+    let output = eval("-99999999999999999999 1 gt?").unwrap();
+    assert_eq!(output.trim(), "false");
+    let output = eval("-99999999999999999999 99999999999999999999 compare").unwrap();
+    assert_eq!(output.trim(), "-1");
+}
+
+#[test]
+fn test_pow_is_exact_for_integers() {
+    assert_eq!(eval("2 64 pow").unwrap().trim(), "18446744073709551616");
+    assert_eq!(eval("-3 41 pow").unwrap().trim(), "-36472996377170786403");
+    assert_eq!(eval("2 10 pow typeof").unwrap().trim(), "int");
+    assert_eq!(eval("2 -1 pow").unwrap().trim(), "0.5");
+    assert_eq!(eval("4 0.5 pow").unwrap().trim(), "2");
+}
+
+#[test]
+fn test_abs_and_negate_keep_big_values_exact() {
+    let output = eval("99999999999999999999 abs").unwrap();
+    assert_eq!(output.trim(), "99999999999999999999");
+    let output = eval("-99999999999999999999 abs").unwrap();
+    assert_eq!(output.trim(), "99999999999999999999");
+    let output = eval("-9223372036854775808 abs").unwrap();
+    assert_eq!(output.trim(), "9223372036854775808");
+    let output = eval("99999999999999999999 negate").unwrap();
+    assert_eq!(output.trim(), "-99999999999999999999");
+    assert_eq!(eval("-5 abs typeof").unwrap().trim(), "int");
+}
+
+#[test]
+fn test_idiv_is_exact_on_big_values() {
+    let output = eval("99999999999999999999 2 idiv").unwrap();
+    assert_eq!(output.trim(), "49999999999999999999");
+    let output = eval("-99999999999999999999 2 idiv").unwrap();
+    assert_eq!(output.trim(), "-49999999999999999999");
+    assert_eq!(eval("7 -2 idiv").unwrap().trim(), "-3");
+    assert_eq!(eval("7.5 2 idiv").unwrap().trim(), "3");
+    assert!(eval("99999999999999999999 0 idiv").is_err());
+}
+
+#[test]
+fn test_min_and_max_keep_big_values_exact() {
+    let output = eval("[99999999999999999999 1] max").unwrap();
+    assert_eq!(output.trim(), "99999999999999999999");
+    let output = eval("[-99999999999999999999 -99999999999999999998] min").unwrap();
+    assert_eq!(output.trim(), "-99999999999999999999");
+    let output = eval("[99999999999999999998 99999999999999999999] max").unwrap();
+    assert_eq!(output.trim(), "99999999999999999999");
+    let output = eval("99999999999999999999 99999999999999999998 min-of").unwrap();
+    assert_eq!(output.trim(), "99999999999999999998");
+    let output = eval("-99999999999999999999 1 max-of").unwrap();
+    assert_eq!(output.trim(), "1");
+    assert_eq!(eval("[3 1 2] max typeof").unwrap().trim(), "int");
+    assert_eq!(eval("[] min typeof").unwrap().trim(), "nil");
+}
+
+#[test]
+fn test_product_keeps_big_values_exact() {
+    let output = eval("[9223372036854775807 -2] product").unwrap();
+    assert_eq!(output.trim(), "-18446744073709551614");
+}
+
+#[test]
+fn test_sum_and_avg_keep_big_values_exact() {
+    let output =
+        eval(r#"[9223372036854775807 9223372036854775807 "18446744073709551616"] sum"#).unwrap();
+    assert_eq!(output.trim(), "36893488147419103230");
+    let output = eval("[9223372036854775807 9223372036854775807] avg").unwrap();
+    assert_eq!(output.trim(), "9223372036854775807");
+    assert_eq!(eval("[1 2 3] sum typeof").unwrap().trim(), "int");
+    assert_eq!(eval("[1 2] avg").unwrap().trim(), "1.5");
+}
@@ -0,0 +1,74 @@
+//! Tests for `cached`
+//!
+//! The cache lives under HOME, which every test in this file shares, so it
+//! is set once and the steps run in one test.
+
+use std::fs;
+
+mod common;
+use common::{lex, parse, Evaluator};
+
+fn run(evaluator: &mut Evaluator, source: &str) -> String {
+    evaluator.clear_stack();
+    let program = parse(lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap().output
+}
+
+#[test]
+fn test_cached_reuses_fresh_results() {
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+    let source = home.path().join("source.txt");
+    fs::write(&source, "one").unwrap();
+    let cat = format!(r#"#["{}" cat]"#, source.display());
+    let mut evaluator = Evaluator::new();
+
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"{} "k" 60 cached"#, cat)),
+        "one"
+    );
+    fs::write(&source, "two").unwrap();
+    // Fresh: the kept value, without running the block
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"{} "k" 60 cached"#, cat)),
+        "one"
+    );
+    // Another session sees it too
+    assert_eq!(
+        run(&mut Evaluator::new(), &format!(r#"{} "k" 60 cached"#, cat)),
+        "one"
+    );
+    // Stale after the time given
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"{} "k" 0 cached"#, cat)),
+        "two"
+    );
+    fs::write(&source, "three").unwrap();
+    // refresh runs the block and keeps the new value
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"refresh {} "k" 60 cached"#, cat)),
+        "three"
+    );
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"{} "k" 60 cached"#, cat)),
+        "three"
+    );
+
+    // The block can't reach below its own stack
+    assert_eq!(
+        run(&mut evaluator, r#"below #[1 2 plus] "sum" 60 cached"#),
+        "below\n3"
+    );
+
+    // Failures aren't kept
+    run(&mut evaluator, r#"#[false] "failing" 60 cached"#);
+    assert_eq!(run(&mut evaluator, "cache-clear"), "2");
+    assert!(fs::read_dir(home.path().join(".hsab/cache"))
+        .unwrap()
+        .next()
+        .is_none());
+    assert_eq!(
+        run(&mut evaluator, &format!(r#"{} "k" 60 cached"#, cat)),
+        "three"
+    );
+}
@@ -0,0 +1,57 @@
+//! Tests for calculator mode: `ans` and numbers written with units
+
+#[path = "common/mod.rs"]
+mod common;
+use common::{eval, lex, parse, Evaluator};
+
+/// Run each line in turn on one evaluator in calculator mode, returning
+/// the output of the last
+fn calc(lines: &[&str]) -> Result<String, String> {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_calc_mode(true);
+    let mut output = String::new();
+    for line in lines {
+        let program = parse(lex(line).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        output = evaluator.eval(&program).map_err(|e| e.to_string())?.output;
+    }
+    Ok(output)
+}
+
+#[test]
+fn test_ans_is_the_last_lines_result() {
+    assert_eq!(calc(&["3 4 +", "drop ans 2 *"]).unwrap(), "14");
+    assert_eq!(calc(&["ans"]).unwrap(), "0");
+    // A line that leaves nothing keeps the answer before it
+    assert_eq!(calc(&["6 7 *", "drop", "ans"]).unwrap(), "42");
+}
+
+#[test]
+fn test_numbers_with_units() {
+    assert_eq!(calc(&["1h30m"]).unwrap(), "5400");
+    assert_eq!(calc(&["250ms 4 *"]).unwrap(), "1");
+    assert_eq!(calc(&["1.5G 512M /"]).unwrap(), "3");
+    assert_eq!(calc(&["2KB"]).unwrap(), "2000");
+    assert_eq!(calc(&["-1m"]).unwrap(), "-60");
+    // Plain numbers and other words are left alone
+    assert_eq!(calc(&["7 2 /"]).unwrap(), "3.5");
+    assert_eq!(calc(&["2x"]).unwrap(), "2x");
+}
+
+#[test]
+fn test_to_unit_converts() {
+    assert_eq!(calc(&["1h30m m to-unit"]).unwrap(), "90");
+    assert_eq!(calc(&["1.5G MiB to-unit"]).unwrap(), "1536");
+    assert_eq!(eval(r#"5400 "h" to-unit"#).unwrap(), "1.5");
+    assert_eq!(
+        eval(r#""1.5G" size-bytes "MB" to-unit"#).unwrap(),
+        "1610.612736"
+    );
+    let err = eval(r#"3 "parsecs" to-unit"#).unwrap_err();
+    assert!(err.contains("unknown unit 'parsecs'"), "{}", err);
+}
+
+#[test]
+fn test_calc_mode_is_off_by_default() {
+    assert_eq!(eval("1h30m").unwrap(), "1h30m");
+    assert_eq!(eval("ans").unwrap(), "ans");
+}
@@ -111,6 +111,51 @@ fn test_script_is_line_oriented_multiline_blocks_error() {
         .stderr(predicate::str::contains("line 1"));
 }
 
+#[test]
+fn test_live_runs_next_command_with_inherited_stdout() {
+    // Not a terminal, so commands are captured onto the stack by default
+    hsab()
+        .args(["-c", "1 expr depth"])
+        .assert()
+        .success()
+        .stdout("1\n1\n");
+
+    // `live` hands stdout to the next command only
+    hsab()
+        .args(["-c", "live 1 expr 2 expr depth"])
+        .assert()
+        .success()
+        .stdout("1\n2\n1\n");
+}
+
+#[test]
+fn test_explain_flag_describes_without_running() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("test.hsab");
+    std::fs::write(&script, "# copy\nnew.txt old.txt cp\nnew.txt rm\n").expect("write script");
+
+    hsab()
+        .current_dir(dir.path())
+        .args(["--explain", script.to_str().expect("utf8 path")])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("line 2: new.txt old.txt cp"))
+        .stdout(predicate::str::contains("  cp old.txt new.txt"))
+        .stdout(predicate::str::contains("  rm new.txt"));
+
+    hsab()
+        .args(["--explain", "-c", "dest src cp"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("bash:\n  cp src dest"));
+
+    hsab()
+        .arg("--explain")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("needs -c"));
+}
+
 #[test]
 fn test_script_missing_file() {
     hsab()
@@ -136,6 +181,43 @@ fn test_script_stops_on_failing_line() {
         .stderr(predicate::str::contains("line 2"));
 }
 
+#[test]
+fn test_script_parse_args() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("greet.hsab");
+    std::fs::write(
+        &script,
+        "#!/usr/bin/env hsab\n\
+         #[{ about \"Greet someone\" flags { loud false count { default 1 short c } } } parse-args] :opts\n\
+         opts \"count\" get echo\n\
+         opts \"args\" get\n",
+    )
+    .expect("write script");
+    let script = script.to_str().expect("utf8 path");
+
+    hsab()
+        .args([script, "-c", "3", "--loud", "bob"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3"))
+        .stdout(predicate::str::contains("bob"));
+
+    hsab()
+        .args([script, "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Usage: greet.hsab [OPTIONS]"))
+        .stdout(predicate::str::contains("Greet someone"))
+        .stdout(predicate::str::contains("-c, --count <INT>"));
+
+    hsab()
+        .args([script, "--count", "many"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--count expects an integer"))
+        .stdout(predicate::str::is_empty());
+}
+
 // === hsab init ===
 
 #[test]
@@ -177,6 +259,118 @@ fn test_init_refuses_to_overwrite() {
     );
 }
 
+/// A home with a stdlib installed by `hsab init`, and its lib directory
+fn home_with_stdlib() -> (tempfile::TempDir, std::path::PathBuf) {
+    let home = tempfile::tempdir().expect("tempdir");
+    hsab()
+        .env("HOME", home.path())
+        .arg("init")
+        .assert()
+        .success();
+    let lib = home.path().join(".hsab/lib");
+    (home, lib)
+}
+
+const HINT: &str = r#"#["\n" " " str-replace] :STACK_HINT"#;
+
+#[test]
+fn test_init_upgrade_keeps_edits_and_takes_new_lines() {
+    let (home, lib) = home_with_stdlib();
+    // As if the stdlib had been installed by an older hsab whose
+    // STACK_HINT differed, and then edited
+    let base = std::fs::read_to_string(lib.join(".stdlib-base.hsabrc")).unwrap();
+    let old = base.replace(HINT, r#"#["\n" "," str-replace] :STACK_HINT"#);
+    std::fs::write(lib.join(".stdlib-base.hsabrc"), &old).unwrap();
+    let edited = format!("# hsab-stdlib-version: 0.1.0\n{}#[1 plus] :my-inc\n", old);
+    std::fs::write(lib.join("stdlib.hsabrc"), &edited).unwrap();
+
+    hsab()
+        .env("HOME", home.path())
+        .args(["init", "--upgrade"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("keeping your edits"));
+
+    let upgraded = std::fs::read_to_string(lib.join("stdlib.hsabrc")).unwrap();
+    assert!(upgraded.starts_with(&format!(
+        "# hsab-stdlib-version: {}\n",
+        env!("CARGO_PKG_VERSION")
+    )));
+    assert!(upgraded.contains(HINT), "{}", upgraded);
+    assert!(upgraded.contains("#[1 plus] :my-inc"), "{}", upgraded);
+    assert_eq!(
+        std::fs::read_to_string(lib.join("stdlib.hsabrc.orig")).unwrap(),
+        edited
+    );
+}
+
+#[test]
+fn test_init_upgrade_marks_conflicts_and_skips_loading() {
+    let (home, lib) = home_with_stdlib();
+    let base = std::fs::read_to_string(lib.join(".stdlib-base.hsabrc")).unwrap();
+    let old = base.replace(HINT, r#"#["\n" "," str-replace] :STACK_HINT"#);
+    std::fs::write(lib.join(".stdlib-base.hsabrc"), &old).unwrap();
+    let edited = base.replace(HINT, r#"#["\n" ";" str-replace] :STACK_HINT"#);
+    std::fs::write(lib.join("stdlib.hsabrc"), &edited).unwrap();
+
+    hsab()
+        .env("HOME", home.path())
+        .args(["init", "--upgrade"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("with 1 conflict"));
+    let upgraded = std::fs::read_to_string(lib.join("stdlib.hsabrc")).unwrap();
+    let conflict = format!(
+        "<<<<<<< your stdlib\n#[\"\\n\" \";\" str-replace] :STACK_HINT\n=======\n{}\n>>>>>>> hsab {}\n",
+        HINT,
+        env!("CARGO_PKG_VERSION")
+    );
+    assert!(upgraded.contains(&conflict), "{}", upgraded);
+
+    hsab()
+        .env("HOME", home.path())
+        .write_stdin(".exit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("merge conflicts"));
+}
+
+#[test]
+fn test_repl_warns_about_an_older_stdlib() {
+    let (home, lib) = home_with_stdlib();
+    hsab()
+        .env("HOME", home.path())
+        .write_stdin(".exit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("init --upgrade").not());
+
+    let stdlib = lib.join("stdlib.hsabrc");
+    let installed = std::fs::read_to_string(&stdlib).unwrap();
+    let (_, body) = installed.split_once('\n').unwrap();
+    std::fs::write(&stdlib, format!("# hsab-stdlib-version: 0.0.1\n{}", body)).unwrap();
+    hsab()
+        .env("HOME", home.path())
+        .write_stdin(".exit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "is from hsab 0.0.1, and this is hsab",
+        ));
+
+    hsab()
+        .env("HOME", home.path())
+        .args(["init", "--upgrade"])
+        .assert()
+        .success();
+    hsab()
+        .env("HOME", home.path())
+        .write_stdin(".exit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("init --upgrade").not());
+}
+
 // === REPL smoke tests (piped stdin) ===
 
 #[test]
@@ -197,8 +391,1156 @@ fn test_repl_smoke_stack_and_clear() {
         .stdout(predicate::str::contains("8"));
 }
 
+#[test]
+fn test_repl_rpn_and_calc_toggle() {
+    hsab()
+        .arg("--rpn")
+        .write_stdin("1h 4 /\nclear-stack\nans 60 /\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("15"));
+    hsab()
+        .write_stdin(".calc\n2KB\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Calculator mode: ON"))
+        .stdout(predicate::str::contains("2000"));
+}
+
 #[test]
 fn test_repl_smoke_eof_exits_cleanly() {
     // Ctrl-D / EOF on stdin should exit without error
     hsab().write_stdin("hello echo\n").assert().success();
 }
+
+#[test]
+fn test_repl_persists_stack_when_enabled() {
+    let home = tempfile::tempdir().unwrap();
+    hsab()
+        .env("HOME", home.path())
+        .env("HSAB_PERSIST_STACK", "1")
+        .write_stdin("1 2 plus \"note\"\n")
+        .assert()
+        .success();
+    let saved = home.path().join(".hsab").join("stack.json");
+    assert!(saved.exists());
+
+    hsab()
+        .env("HOME", home.path())
+        .env("HSAB_PERSIST_STACK", "1")
+        .write_stdin("drop 10 plus peek\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored 2 stack items"))
+        .stdout(predicate::str::contains("13"));
+
+    // Persistence is opt-in
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_PERSIST_STACK")
+        .write_stdin("depth peek\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored").not());
+}
+
+#[test]
+fn test_repl_edit_runs_last_command_after_editing() {
+    let home = tempfile::tempdir().unwrap();
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("VISUAL")
+        .env("EDITOR", "sed -i s/plus/mul/")
+        .write_stdin("6 7 plus peek\n.edit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("13"))
+        .stdout(predicate::str::contains("6 7 mul peek"))
+        .stdout(predicate::str::contains("42"));
+}
+
+#[test]
+fn test_edit_def_redefines_from_editor() {
+    hsab()
+        .env_remove("VISUAL")
+        .env("EDITOR", "sed -i s/mul/plus/")
+        .args(["-c", "#[dup mul] :sq \"sq\" .edit-def 5 sq"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("10"));
+
+    // A failing editor leaves the definition alone
+    hsab()
+        .env_remove("VISUAL")
+        .env("EDITOR", "false")
+        .args(["-c", "#[dup mul] :sq \"sq\" .edit-def 5 sq"])
+        .assert()
+        .stdout(predicate::str::contains("25"))
+        .stderr(predicate::str::contains("edit abandoned"));
+
+    hsab()
+        .args(["-c", "\"nope\" .edit-def"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("nope is not defined"));
+}
+
+#[test]
+fn test_edit_at_opens_row_at_line() {
+    hsab()
+        .env_remove("VISUAL")
+        .env("EDITOR", "echo")
+        .args(["-c", r#"{ "path" "a.rs" "line" 3 } edit-at"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+3 a.rs"));
+
+    hsab()
+        .args(["-c", r#"{ "line" 3 } edit-at"#])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("edit-at: no path"));
+}
+
+#[test]
+fn test_doc_view_shows_builtin_reference() {
+    hsab()
+        .args(["-c", r#""trash-list" doc-view"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("trash-list: hsab builtin"))
+        .stdout(predicate::str::contains("What is in the trash"))
+        .stdout(predicate::str::contains(
+            "#### trash / trash-list / trash-restore",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doc_view_uses_man_then_tldr() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = tempfile::tempdir().unwrap();
+    let script = |name: &str, body: &str| {
+        let path = bin.path().join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    script(
+        "man",
+        r#"[ "$1" = frob ] && echo "FROB(1) manual" || exit 16"#,
+    );
+    script("tldr", r#"echo "tldr page for $1""#);
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", r#""frob" doc-view"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("FROB(1) manual"));
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", r#""zork" doc-view"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tldr page for zork"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_missing_command_suggests_close_names() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = tempfile::tempdir().unwrap();
+    let git = bin.path().join("git");
+    std::fs::write(&git, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&git, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    hsab()
+        .env("PATH", bin.path())
+        .args(["-c", r#""x" #[status gti] |"#])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "gti: command not found (did you mean git?)",
+        ));
+    hsab()
+        .env("PATH", bin.path())
+        .args(["-c", r#""x" #[xyzzy] |"#])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("xyzzy: command not found"))
+        .stderr(predicate::str::contains("did you mean").not());
+}
+
+#[test]
+fn test_repl_counts_commands_for_completion() {
+    let home = tempfile::tempdir().unwrap();
+    hsab()
+        .env("HOME", home.path())
+        .write_stdin("\"a\" echo\n\"b\" echo\nhello\n")
+        .assert()
+        .success();
+    let counts = std::fs::read_to_string(home.path().join(".hsab").join("frecency")).unwrap();
+    assert!(counts.starts_with("echo\t2\t"), "{}", counts);
+    // Arguments aren't commands and aren't counted
+    assert!(!counts.contains("hello"));
+}
+
+#[test]
+fn test_repl_shares_definitions() {
+    let home = tempfile::tempdir().unwrap();
+    hsab()
+        .env("HOME", home.path())
+        .env("HSAB_SHARE_DEFS", "1")
+        .write_stdin("#[2 mul] :double\n")
+        .assert()
+        .success();
+    assert!(home.path().join(".hsab").join("session-defs").exists());
+
+    hsab()
+        .env("HOME", home.path())
+        .env("HSAB_SHARE_DEFS", "1")
+        .write_stdin("21 double peek\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("42"))
+        .stderr(predicate::str::contains("from another session: double"));
+
+    // Without the opt-in, .sync turns sharing on for the session
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_SHARE_DEFS")
+        .write_stdin(".sync\n5 double peek\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 received"))
+        .stdout(predicate::str::contains("10"));
+}
+
+// === Restricted mode ===
+
+#[test]
+fn test_restricted_mode_refuses_escapes() {
+    let dir = tempfile::tempdir().unwrap();
+    let refused = [
+        ("/tmp cd", "cd: restricted"),
+        ("/bin/sh", "/bin/sh: restricted"),
+        ("#[hi echo] #[out.txt] >", ">: restricted"),
+        ("#[hi echo] #[out.txt] &>", "&>: restricted"),
+        ("PATH=/tmp .export", "PATH: restricted"),
+        ("PATH=/tmp; hi echo", "PATH: restricted"),
+    ];
+    for (code, message) in refused {
+        hsab()
+            .current_dir(dir.path())
+            .args(["--restricted", "-c", code])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(message));
+    }
+    assert!(!dir.path().join("out.txt").exists());
+
+    // Nor do the builtins that write files
+    std::fs::create_dir_all(dir.path().join("tree/deep")).unwrap();
+    std::fs::write(dir.path().join("keep.txt"), "keep\n").unwrap();
+    for code in [
+        "tree remove",
+        "tree rm-r",
+        r#""hi" out.txt save"#,
+        "keep.txt out.txt copy",
+        "keep.txt out.txt move",
+        "keep.txt trash",
+        "out.txt touch",
+        "newdir mkdir-p",
+        r#"keep.txt #["x" echo] edit-file"#,
+        r#"#[hi echo] nohup"#,
+    ] {
+        hsab()
+            .current_dir(dir.path())
+            .args(["--restricted", "-c", code])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("restricted: can't write files"));
+    }
+    assert!(dir.path().join("tree/deep").is_dir());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("keep.txt")).unwrap(),
+        "keep\n"
+    );
+    assert!(!dir.path().join("out.txt").exists());
+    assert!(!dir.path().join("newdir").exists());
+    assert!(!dir.path().join("nohup.out").exists());
+
+    // Nor the REPL commands that write files
+    hsab()
+        .current_dir(dir.path())
+        .arg("--restricted")
+        .write_stdin(".record out.txt\n.sync\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".record: restricted"))
+        .stdout(predicate::str::contains(".sync: restricted"));
+    assert!(!dir.path().join("out.txt").exists());
+
+    // Everything else still works
+    hsab()
+        .current_dir(dir.path())
+        .args(["-r", "-c", r#"OTHER=1 .export "x" #[cat] | $OTHER"#])
+        .assert()
+        .success()
+        .stdout("x\n1\n");
+}
+
+// === Workspace config (hsab.toml) ===
+
+fn workspace(config: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("hsab.toml"), config).unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    dir
+}
+
+#[test]
+fn test_hsab_run_lists_and_runs_scripts() {
+    let dir = workspace("[scripts]\ngreet = \"hello echo\"\nfail = \"false\"\n");
+    let sub = dir.path().join("sub");
+    hsab()
+        .current_dir(&sub)
+        .arg("run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("greet"));
+    hsab()
+        .current_dir(&sub)
+        .args(["run", "greet"])
+        .assert()
+        .success()
+        .stdout("hello\n");
+    hsab()
+        .current_dir(&sub)
+        .args(["run", "fail"])
+        .assert()
+        .failure();
+    hsab()
+        .current_dir(&sub)
+        .args(["run", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no task named 'nope'"));
+}
+
+#[test]
+fn test_hsab_run_tasks_with_dependencies() {
+    let dir = workspace(
+        r#"
+[tasks]
+a = "0.5 sleep a echo"
+b = "0.5 sleep b echo"
+c = { run = "c echo", deps = ["a", "b"], description = "After a and b" }
+bad = { run = "false", deps = ["a"] }
+never = { run = "never echo", deps = ["bad"] }
+loop = { deps = ["loop"] }
+"#,
+    );
+    let start = std::time::Instant::now();
+    let out = hsab()
+        .current_dir(dir.path())
+        .args(["run", "-j", "2", "c"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[2], "c");
+    // a and b ran side by side
+    assert!(start.elapsed() < std::time::Duration::from_millis(950));
+
+    hsab()
+        .current_dir(dir.path())
+        .args(["run", "never"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("never").not())
+        .stderr(predicate::str::contains("task 'bad' failed"));
+    hsab()
+        .current_dir(dir.path())
+        .args(["run", "loop"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("dependency cycle: loop -> loop"));
+    hsab()
+        .current_dir(dir.path())
+        .args(["run", "--list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("After a and b  (after a, b)"));
+}
+
+#[test]
+fn test_workspace_settings_apply_to_commands() {
+    let dir = workspace("strict = true\noutput = \"json\"\n[exec]\ndeny = [\"rm\"]\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "'{\"a\":1}' json 2"])
+        .assert()
+        .success()
+        .stdout("{\"a\":1}\n2\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "hsab.toml rm"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not allowed by the exec policy"));
+    assert!(dir.path().join("hsab.toml").exists());
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "$HSAB_SURELY_UNSET_VAR"])
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspace_external_skips_builtins() {
+    hsab()
+        .args(["-c", "3 seq typeof /x/y /a/b/c dirname"])
+        .assert()
+        .success()
+        .stdout("list\n/x/y\n/a/b\n");
+    let dir = workspace("[exec]\nexternal = [\"seq\", \"dirname\"]\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "3 seq typeof"])
+        .assert()
+        .success()
+        .stdout("string\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "/x/y /a/b/c dirname"])
+        .assert()
+        .success()
+        .stdout("/a/b\n/x\n");
+}
+
+#[test]
+fn test_audit_log_records_spawned_processes() {
+    let dir = workspace("[audit]\nfile = \"audit.log\"\n");
+    hsab()
+        .current_dir(dir.path().join("sub"))
+        .args([
+            "-c",
+            r#"AUDIT_DEMO=1 .export "exit 3" -c sh drop "x" #[cat] |"#,
+        ])
+        .assert()
+        .success();
+    let log = std::fs::read_to_string(dir.path().join("audit.log")).unwrap();
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2, "{}", log);
+    assert_eq!(records[0]["via"], "command");
+    assert_eq!(
+        records[0]["argv"],
+        serde_json::json!(["sh", "-c", "exit 3"])
+    );
+    assert_eq!(records[0]["exit"], 3);
+    assert_eq!(records[0]["env"]["AUDIT_DEMO"], "1");
+    assert!(records[0]["cwd"].as_str().unwrap().ends_with("sub"));
+    assert!(records[0]["duration_ms"].is_u64());
+    assert_eq!(records[1]["via"], "pipe");
+    assert_eq!(records[1]["argv"], serde_json::json!(["cat"]));
+    assert_eq!(records[1]["exit"], 0);
+}
+
+#[test]
+fn test_audit_log_records_builtin_helpers() {
+    let dir = workspace("[audit]\nfile = \"audit.log\"\n");
+    hsab()
+        .current_dir(dir.path())
+        .env("EDITOR", "true")
+        .env_remove("VISUAL")
+        .args(["-c", r#""path" "notes.txt" "line" 3 record edit-at"#])
+        .assert()
+        .success();
+    let log = std::fs::read_to_string(dir.path().join("audit.log")).unwrap();
+    let record: serde_json::Value = serde_json::from_str(log.trim()).unwrap();
+    assert_eq!(record["via"], "editor");
+    assert_eq!(
+        record["argv"],
+        serde_json::json!(["true", "+3", "notes.txt"])
+    );
+    assert_eq!(record["exit"], 0);
+}
+
+#[test]
+fn test_exec_policy_covers_builtin_helpers() {
+    let dir = workspace("[exec]\nallow = [\"ls\"]\n");
+    hsab()
+        .current_dir(dir.path())
+        .env("EDITOR", "true")
+        .env_remove("VISUAL")
+        .args(["-c", r#""path" "notes.txt" record edit-at"#])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "true: not allowed by the exec policy",
+        ));
+}
+
+#[test]
+fn test_exec_allowlist_covers_builtins_that_write_files() {
+    let dir = workspace("[exec]\nallow = [\"ls\", \"touch\"]\n");
+    std::fs::create_dir_all(dir.path().join("tree/deep")).unwrap();
+    for code in ["tree remove", r#""hi" out.txt save"#, "tree copied copy"] {
+        hsab()
+            .current_dir(dir.path())
+            .args(["-c", code])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("not allowed by the exec policy"));
+    }
+    assert!(dir.path().join("tree/deep").is_dir());
+    assert!(!dir.path().join("out.txt").exists());
+    assert!(!dir.path().join("copied").exists());
+
+    // The commands it allows still have their builtins
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "new.txt touch"])
+        .assert()
+        .success();
+    assert!(dir.path().join("new.txt").exists());
+}
+
+#[test]
+fn test_workspace_limits_stop_runaway_code() {
+    let dir = workspace("[limits]\nsteps = 1000\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "#[1 drop] 10 times \"ok\" echo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ok"));
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "#[1 drop] 100000 times"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("step limit of 1000 reached"));
+}
+
+#[test]
+fn test_invalid_workspace_config_is_reported() {
+    let dir = workspace("output = 3\n");
+    hsab()
+        .current_dir(dir.path())
+        .args(["-c", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("hsab.toml"));
+}
+
+// === Color policy ===
+
+#[test]
+fn test_color_auto_plain_when_piped() {
+    hsab()
+        .env_remove("CLICOLOR_FORCE")
+        .args(["-c", "{ a 1 }"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_color_always_flag() {
+    hsab()
+        .args(["--color=always", "-c", "x red colorize"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[31mx\x1b[0m"));
+}
+
+#[test]
+fn test_clicolor_force_env() {
+    hsab()
+        .env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .args(["-c", "x red colorize"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[31m"));
+}
+
+#[test]
+fn test_no_color_beats_clicolor_force() {
+    hsab()
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .args(["-c", "{ a 1 }"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_color_never_strips_trace() {
+    hsab()
+        .args(["--color", "never", "--trace", "-c", "1 2 plus"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(">>> plus"))
+        .stderr(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_color_invalid_value() {
+    hsab()
+        .args(["--color=sometimes", "-c", "1"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid --color"));
+}
+
+#[test]
+fn test_trace_shows_record_diff() {
+    hsab()
+        .args([
+            "--color=never",
+            "--trace",
+            "-c",
+            r#"{ name a } "name" b set"#,
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(r#"Δ ~name: "a" → "b""#));
+}
+
+// === hsab --dap ===
+
+#[cfg(not(feature = "dap"))]
+#[test]
+fn test_dap_flag_requires_feature() {
+    hsab()
+        .arg("--dap")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("built without DAP support"));
+}
+
+// === hsab --record ===
+
+#[test]
+fn test_record_script_steps() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("test.hsab");
+    let rec = dir.path().join("run.jsonl");
+    std::fs::write(&script, "1 2 plus\n").expect("write script");
+
+    hsab()
+        .args(["--record", rec.to_str().expect("utf8 path")])
+        .arg(script.to_str().expect("utf8 path"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3"));
+
+    let recording = hsab::eval::Recording::load(rec.to_str().unwrap()).expect("load");
+    let exprs: Vec<&str> = recording.steps.iter().map(|s| s.expr.as_str()).collect();
+    assert_eq!(exprs, ["1", "2", "plus"]);
+    assert_eq!(recording.steps[2].pop, 2);
+}
+
+#[test]
+fn test_record_unwritable_path_fails() {
+    hsab()
+        .args(["--record", "/nonexistent/dir/run.jsonl", "-c", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot record"));
+}
+
+// === checkpoint / hsab --resume ===
+
+#[test]
+fn test_resume_from_checkpoint() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("job.hsab");
+    let ckpt = dir.path().join("ck.json");
+    let body = format!(
+        "#[dup plus] :double\nSTAGE=loaded .export\n5 double \"{}\" checkpoint double\n$STAGE echo\nfalse\n",
+        ckpt.display()
+    );
+    std::fs::write(&script, &body).expect("write script");
+
+    // First run dies at the last line, after the checkpoint
+    hsab()
+        .arg(script.to_str().expect("utf8 path"))
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("20"));
+
+    // Fix the failing step and resume: state comes from the checkpoint
+    std::fs::write(&script, body.replace("false", "done echo")).expect("rewrite");
+    hsab()
+        .env_remove("STAGE")
+        .args(["--resume", ckpt.to_str().unwrap()])
+        .arg(script.to_str().expect("utf8 path"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("20\nloaded\ndone"));
+}
+
+#[test]
+fn test_resume_refuses_changed_line() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("job.hsab");
+    let ckpt = dir.path().join("ck.json");
+    std::fs::write(&script, format!("1 \"{}\" checkpoint\n", ckpt.display()))
+        .expect("write script");
+    hsab().arg(&script).assert().success();
+
+    std::fs::write(&script, format!("2 \"{}\" checkpoint\n", ckpt.display())).expect("rewrite");
+    hsab()
+        .args(["--resume", ckpt.to_str().unwrap()])
+        .arg(script.to_str().expect("utf8 path"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("script changed since checkpoint"));
+}
+
+#[test]
+fn test_aws_and_gcloud_push_tables() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = tempfile::tempdir().unwrap();
+    let script = |name: &str, body: &str| {
+        let path = bin.path().join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    // Two pages, joined by a NextToken
+    script(
+        "aws",
+        r#"case "$*" in
+  *"--output json --starting-token t1") echo '{"KeyPairs": [{"KeyName": "b", "KeyType": "ed25519"}]}' ;;
+  *"--output json") echo '{"KeyPairs": [{"KeyName": "a", "KeyType": "rsa"}], "NextToken": "t1"}' ;;
+  "s3 ls") echo "2024-01-01 logs" ;;
+  configure) read key; echo "saved $key" ;;
+  *) exit 2 ;;
+esac
+"#,
+    );
+    script(
+        "gcloud",
+        r#"[ "$1" = config ] && { echo "ERROR: no project" >&2; exit 1; }
+echo '[{"name": "web", "status": "RUNNING"}, {"name": "db", "status": "STOPPED"}]'
+"#,
+    );
+    let path = format!("{}:/usr/bin:/bin", bin.path().display());
+
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", r#""ec2 describe-key-pairs" aws count"#])
+        .assert()
+        .success()
+        .stdout("2\n");
+    hsab()
+        .env("PATH", &path)
+        .args([
+            "-c",
+            r#""ec2 describe-key-pairs" aws #["KeyType" get "rsa" eq?] where count"#,
+        ])
+        .assert()
+        .success()
+        .stdout("1\n");
+    hsab()
+        .env("PATH", &path)
+        .args([
+            "-c",
+            r#""compute instances list" gcloud #["status" get "RUNNING" eq?] where count"#,
+        ])
+        .assert()
+        .success()
+        .stdout("1\n");
+    // Separate words run the real CLI, with stdin and every argument
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", r#""ls" s3 aws"#])
+        .assert()
+        .success()
+        .stdout("2024-01-01 logs\n");
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", "live configure aws"])
+        .write_stdin("AKIA\n")
+        .assert()
+        .success()
+        .stdout("saved AKIA\n");
+    hsab()
+        .env("PATH", &path)
+        .args(["-c", r#""config list" gcloud"#])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("gcloud: ERROR: no project"));
+}
+
+#[test]
+fn test_log_words_filter_by_level() {
+    hsab()
+        .env_remove("HSAB_LOG")
+        .env_remove("HSAB_LOG_FORMAT")
+        .args([
+            "--color=never",
+            "-c",
+            r#""starting" log-info "noise" log-debug "careful" { disk "91%" } log-warn"#,
+        ])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("INFO  starting"))
+        .stderr(predicate::str::contains("WARN  careful disk=91%"))
+        .stderr(predicate::str::contains("noise").not());
+    hsab()
+        .env("HSAB_LOG", "debug")
+        .env_remove("HSAB_LOG_FORMAT")
+        .args([
+            "-c",
+            r#""noise" log-debug "error" log-level "hidden" log-warn"#,
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("noise"))
+        .stderr(predicate::str::contains("hidden").not());
+}
+
+#[test]
+fn test_log_json_format_carries_fields_and_trace() {
+    let output = hsab()
+        .env_remove("HSAB_LOG")
+        .env("HSAB_LOG_FORMAT", "json")
+        .args(["-c", r#""deployed" { version "1.2" } log-error"#])
+        .output()
+        .unwrap();
+    let record: serde_json::Value =
+        serde_json::from_slice(&output.stderr).expect("a JSON log line");
+    assert_eq!(record["level"], "error");
+    assert_eq!(record["msg"], "deployed");
+    assert_eq!(record["version"], "1.2");
+
+    hsab()
+        .env_remove("HSAB_LOG_FORMAT")
+        .args(["--trace", "-c", r#""json" log-format 1 2 plus"#])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            r#""level":"trace","msg":">>> plus"#,
+        ));
+}
+
+#[test]
+fn test_exit_runs_exit_trap_and_keeps_code() {
+    hsab()
+        .args([
+            "-c",
+            r#"#["cleaning up" echo] EXIT .trap 3 .exit "unreached" echo"#,
+        ])
+        .assert()
+        .code(3)
+        .stdout("cleaning up\n");
+    hsab()
+        .args([
+            "-c",
+            r#"#[2 .exit] EXIT .trap #[4 .exit] try "unreached" echo"#,
+        ])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_exit_ends_script_after_trap() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("exit.hsab");
+    std::fs::write(
+        &script,
+        "#[\"bye\" echo] EXIT .trap\n\"first\" echo\n0 .exit\n\"second\" echo\n",
+    )
+    .expect("write script");
+    hsab()
+        .arg(script.to_str().expect("utf8 path"))
+        .assert()
+        .success()
+        .stdout("first\nbye\n");
+}
+
+#[test]
+fn test_parallel_streams_labeled_lines_when_not_a_tty() {
+    let output = hsab()
+        .args([
+            "-c",
+            r#"#[#["echo to-err >&2" -c sh] #[hi /bin/echo]] parallel"#,
+        ])
+        .output()
+        .expect("run hsab");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    for line in [
+        "\"echo to-err >&2\" -c sh | to-err",
+        "\"echo to-err >&2\" -c sh | exited with code 0",
+        "hi /bin/echo            | hi",
+        "hi /bin/echo            | exited with code 0",
+    ] {
+        assert!(lines.contains(&line), "{}", stderr);
+    }
+
+    hsab()
+        .env("HSAB_PARALLEL_VIEW", "off")
+        .args(["-c", r#"#[#[hi /bin/echo]] parallel"#])
+        .assert()
+        .success()
+        .stderr("");
+}
+
+// === Spreadsheet export ===
+
+#[test]
+fn test_to_clipboard_tsv_copies_quoted_tsv() {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    let output = hsab()
+        .args([
+            "-c",
+            r#"marker "name" "a	b" "n" 1 record marker "name" "\"q" "n" 2.5 record table to-clipboard-tsv"#,
+        ])
+        .output()
+        .expect("run hsab");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let payload = stdout
+        .split_once("\x1b]52;c;")
+        .and_then(|(_, rest)| rest.split_once('\x07'))
+        .map(|(payload, _)| payload)
+        .unwrap_or_else(|| panic!("no OSC 52 sequence in {:?}", stdout));
+    let tsv = String::from_utf8(STANDARD.decode(payload).unwrap()).unwrap();
+    assert_eq!(tsv, "name\tn\n\"a\tb\"\t1\n\"\"\"q\"\t2.5\n");
+}
+
+// === User config ===
+
+#[test]
+fn test_config_set_get_and_env_override() {
+    let home = tempfile::tempdir().expect("tempdir");
+    hsab()
+        .env("HOME", home.path())
+        .args(["config", "set", "preview_len", "20"])
+        .assert()
+        .success();
+    let saved = std::fs::read_to_string(home.path().join(".hsab/config.toml")).unwrap();
+    assert_eq!(saved, "preview_len = 20\n");
+
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_PREVIEW_LEN")
+        .args(["config", "get", "preview_len"])
+        .assert()
+        .success()
+        .stdout("20\n");
+    hsab()
+        .env("HOME", home.path())
+        .env("HSAB_PREVIEW_LEN", "5")
+        .args(["config", "get", "preview_len"])
+        .assert()
+        .success()
+        .stdout("5\n");
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_PREVIEW_LEN")
+        .arg("config")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^preview_len +20 +file$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^max_recursion +10000 +default$").unwrap());
+
+    hsab()
+        .env("HOME", home.path())
+        .args(["config", "unset", "preview_len"])
+        .assert()
+        .success();
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_PREVIEW_LEN")
+        .args(["config", "get", "preview_len"])
+        .assert()
+        .success()
+        .stdout("8\n");
+}
+
+#[test]
+fn test_config_rejects_unknown_keys_and_bad_values() {
+    let home = tempfile::tempdir().expect("tempdir");
+    hsab()
+        .env("HOME", home.path())
+        .args(["config", "set", "preview_length", "20"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no setting 'preview_length'"));
+    hsab()
+        .env("HOME", home.path())
+        .args(["config", "set", "strict", "sometimes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("strict is true or false"));
+    assert!(!home.path().join(".hsab/config.toml").exists());
+}
+
+#[test]
+fn test_config_file_applies_at_startup() {
+    let home = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir_all(home.path().join(".hsab")).unwrap();
+    std::fs::write(
+        home.path().join(".hsab/config.toml"),
+        "strict = true\npreview_len = 3\n",
+    )
+    .unwrap();
+
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_STRICT")
+        .env_remove("HSAB_PREVIEW_LEN")
+        .args([
+            "-c",
+            r#"hsab-config "preview_len" get hsab-config "strict" get"#,
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3\ntrue"));
+    hsab()
+        .env("HOME", home.path())
+        .env_remove("HSAB_PREVIEW_LEN")
+        .env("HSAB_STRICT", "0")
+        .args(["-c", r#"hsab-config "strict" get"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("false"));
+}
+
+#[test]
+fn test_config_is_left_to_commands() {
+    hsab()
+        .args(["-c", "user.name config echo"])
+        .assert()
+        .success()
+        .stdout("config user.name\n");
+}
+
+// === follow ===
+
+#[test]
+fn test_follow_runs_the_block_on_appended_lines() {
+    use std::io::{BufRead, Write};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let log = dir.path().join("app.log");
+    std::fs::write(&log, "old line\n").unwrap();
+
+    #[allow(deprecated)]
+    let bin = assert_cmd::cargo::cargo_bin("hsab");
+    let mut child = std::process::Command::new(bin)
+        .args([
+            "-c",
+            &format!(r#""{}" #["got " swap suffix echo] follow"#, log.display()),
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("run hsab");
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+        {
+            let _ = tx.send(line);
+        }
+    });
+
+    std::thread::sleep(Duration::from_millis(300));
+    let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    file.write_all(b"first\nsec").unwrap();
+    file.flush().unwrap();
+    std::thread::sleep(Duration::from_millis(300));
+    file.write_all(b"ond\n").unwrap();
+    file.flush().unwrap();
+
+    let got: Vec<String> = (0..2)
+        .map_while(|_| rx.recv_timeout(Duration::from_secs(5)).ok())
+        .collect();
+    let _ = child.kill();
+    let _ = child.wait();
+    assert_eq!(got, ["got first", "got second"]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_follow_filters_lines_and_stops_on_ctrl_c() {
+    use std::io::{BufRead, Write};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let log = dir.path().join("app.jsonl");
+    std::fs::write(&log, "").unwrap();
+
+    #[allow(deprecated)]
+    let bin = assert_cmd::cargo::cargo_bin("hsab");
+    let mut child = std::process::Command::new(bin)
+        .args([
+            "-c",
+            &format!(
+                r#""{}" #[from-json #["level" get "error" eq?] where] follow"#,
+                log.display()
+            ),
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("run hsab");
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout)
+            .lines()
+            .map_while(Result::ok)
+        {
+            let _ = tx.send(line);
+        }
+    });
+
+    std::thread::sleep(Duration::from_millis(300));
+    let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    file.write_all(
+        b"{\"level\":\"info\",\"msg\":\"up\"}\nnot json\n{\"level\":\"error\",\"msg\":\"down\"}\n",
+    )
+    .unwrap();
+    file.flush().unwrap();
+
+    let got = rx.recv_timeout(Duration::from_secs(5)).unwrap_or_default();
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("send SIGINT");
+    let status = child.wait().expect("wait for hsab");
+    assert!(got.contains("down") && !got.contains("up"), "{}", got);
+    assert!(
+        status.success(),
+        "Ctrl+C stops follow cleanly: {:?}",
+        status
+    );
+}
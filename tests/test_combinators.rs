@@ -730,3 +730,93 @@ fn test_retry_float_count_errors() {
     // Floats passed as strings don't parse to usize
     assert!(result.is_err(), "Should error with float count");
 }
+
+#[test]
+fn test_and_then_runs_on_success_value() {
+    // and-then: second block starts with the first block's value
+    let output = eval(r#"#["hello"] #[len] and-then"#).unwrap();
+    assert_eq!(output.trim(), "5");
+}
+
+#[test]
+fn test_and_then_short_circuits_on_error() {
+    // An error value is the result; the second block never runs
+    let output = eval(r#"#[#["boom" throw] try] #["ran" echo] and-then typeof"#).unwrap();
+    assert_eq!(output.trim(), "error");
+    assert_eq!(
+        eval_exit_code(r#"#[#["boom" throw] try] #[len] and-then"#),
+        1
+    );
+}
+
+#[test]
+fn test_or_else_recovers_from_error() {
+    let output = eval(r#"#[#["boom" throw] try] #[drop "fallback"] or-else"#).unwrap();
+    assert_eq!(output.trim(), "fallback");
+    // A truthy value skips the recovery block
+    let output = eval(r#"#["fine"] #[drop "fallback"] or-else"#).unwrap();
+    assert_eq!(output.trim(), "fine");
+}
+
+#[test]
+fn test_and_then_or_else_chain() {
+    let output = eval(r#"#[#[0] #["unreached" echo] and-then] #[drop "default"] or-else"#).unwrap();
+    assert_eq!(output.trim(), "default");
+}
+
+#[test]
+fn test_rate_limit_waits_for_a_slot() {
+    // Two runs fit in the window; the third waits for the first to age out
+    let start = std::time::Instant::now();
+    let output = eval(
+        r#"#["a"] "b" 2 0.3 rate-limit #["b"] "b" 2 0.3 rate-limit #["c"] "b" 2 0.3 rate-limit"#,
+    )
+    .unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(250));
+    assert!(output.contains('c'), "Block should run: {}", output);
+}
+
+#[test]
+fn test_per_second_counts_each_block_separately() {
+    let start = std::time::Instant::now();
+    let output = eval(r#"#[1] 1 per-second #[2] 1 per-second #[1 2 plus] 1 per-second"#).unwrap();
+    assert_eq!(output.trim().lines().last(), Some("3"));
+    assert!(start.elapsed() < std::time::Duration::from_millis(500));
+    assert!(eval(r#"#[1] 0 per-minute"#).is_err());
+}
+
+#[test]
+fn test_changed_diffs_against_the_last_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let pods = dir.path().join("pods.txt");
+    std::fs::write(&pods, "web-1 Running\nweb-2 Running\ndb Running\n").unwrap();
+
+    let mut evaluator = Evaluator::new();
+    let mut run = |input: &str| {
+        let program = parse(lex(input).unwrap()).unwrap();
+        evaluator.eval(&program).unwrap()
+    };
+    let check = format!(r#"#["{}" cat] changed?"#, pods.display());
+
+    // The first run only records the output
+    let result = run(&check);
+    assert_eq!(result.exit_code, 1);
+    assert_eq!(result.output, "");
+    let result = run(&format!("drop {}", check));
+    assert_eq!(result.exit_code, 1, "nothing changed");
+
+    std::fs::write(
+        &pods,
+        "web-1 Running\nweb-2 Pending\ndb Running\nweb-3 Pending\n",
+    )
+    .unwrap();
+    let result = run(&format!("drop {}", check));
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+        result.output,
+        "-web-2 Running\n+web-2 Pending\n+web-3 Pending"
+    );
+
+    let result = run(&format!(r#"drop #["changed" echo] {} if"#, check));
+    assert_eq!(result.output.trim(), "", "an unchanged run is false to if");
+}
@@ -0,0 +1,283 @@
+//! Debug Adapter Protocol server tests (feature `dap`)
+#![cfg(feature = "dap")]
+
+use serde_json::{json, Value as Json};
+use std::io::{Read, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+/// Client end of the request pipe
+struct PipeReader {
+    rx: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.buf.is_empty() {
+            match self.rx.recv() {
+                Ok(bytes) => self.buf = bytes,
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.buf.len());
+        out[..n].copy_from_slice(&self.buf[..n]);
+        self.buf.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Server end of the response pipe
+struct PipeWriter(Sender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let _ = self.0.send(bytes.to_vec());
+        Ok(bytes.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Session {
+    requests: Sender<Vec<u8>>,
+    responses: Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+    seq: i64,
+    server: std::thread::JoinHandle<i32>,
+}
+
+impl Session {
+    fn start() -> Session {
+        let (req_tx, req_rx) = channel();
+        let (resp_tx, resp_rx) = channel();
+        let server = std::thread::spawn(move || {
+            let mut eval = hsab::Evaluator::new();
+            let reader = PipeReader {
+                rx: req_rx,
+                buf: Vec::new(),
+            };
+            hsab::dap::serve(&mut eval, reader, PipeWriter(resp_tx))
+        });
+        Session {
+            requests: req_tx,
+            responses: resp_rx,
+            pending: Vec::new(),
+            seq: 0,
+            server,
+        }
+    }
+
+    fn send(&mut self, command: &str, arguments: Json) {
+        self.seq += 1;
+        let body = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.requests.send(frame.into_bytes()).unwrap();
+    }
+
+    /// Next message from the server, failing the test after a timeout
+    fn next_message(&mut self) -> Json {
+        loop {
+            if let Some(header_end) = find(&self.pending, b"\r\n\r\n") {
+                let header = String::from_utf8_lossy(&self.pending[..header_end]).to_string();
+                let len: usize = header
+                    .trim_start_matches("Content-Length:")
+                    .trim()
+                    .parse()
+                    .unwrap();
+                let start = header_end + 4;
+                if self.pending.len() >= start + len {
+                    let msg = serde_json::from_slice(&self.pending[start..start + len]).unwrap();
+                    self.pending.drain(..start + len);
+                    return msg;
+                }
+            }
+            let chunk = self
+                .responses
+                .recv_timeout(Duration::from_secs(10))
+                .expect("timed out waiting for DAP message");
+            self.pending.extend(chunk);
+        }
+    }
+
+    /// Skip messages until one matches `kind` (a command or event name)
+    fn expect(&mut self, kind: &str) -> Json {
+        loop {
+            let msg = self.next_message();
+            if msg["command"] == kind || msg["event"] == kind {
+                return msg;
+            }
+        }
+    }
+
+    /// Run through the configuration phase for `program`
+    fn launch(&mut self, program: &str, breakpoints: &[u64]) {
+        self.send("initialize", json!({ "adapterID": "hsab" }));
+        let init = self.expect("initialize");
+        assert_eq!(init["body"]["supportsConfigurationDoneRequest"], true);
+        self.expect("initialized");
+        self.send("launch", json!({ "program": program }));
+        self.expect("launch");
+        let bps: Vec<Json> = breakpoints.iter().map(|l| json!({ "line": l })).collect();
+        self.send(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": bps }),
+        );
+        let resp = self.expect("setBreakpoints");
+        assert_eq!(
+            resp["body"]["breakpoints"].as_array().unwrap().len(),
+            breakpoints.len()
+        );
+    }
+
+    fn finish(mut self) -> i32 {
+        self.send("disconnect", json!({}));
+        self.expect("disconnect");
+        drop(self.requests);
+        self.server.join().unwrap()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn script(content: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn test_dap_breakpoint_stack_and_continue() {
+    let file = script("1 2 plus\n# comment\n10 20 plus\n");
+    let path = file.path().to_str().unwrap().to_string();
+    let mut session = Session::start();
+    session.launch(&path, &[3]);
+    session.send("configurationDone", json!({}));
+
+    let out = session.expect("output");
+    assert_eq!(out["body"]["output"], "3\n");
+    let stopped = session.expect("stopped");
+    assert_eq!(stopped["body"]["reason"], "breakpoint");
+
+    session.send("stackTrace", json!({ "threadId": 1 }));
+    let trace = session.expect("stackTrace");
+    assert_eq!(trace["body"]["stackFrames"][0]["line"], 3);
+
+    // Step past both literals, then inspect the stack
+    session.send("next", json!({ "threadId": 1 }));
+    session.expect("stopped");
+    session.send("next", json!({ "threadId": 1 }));
+    session.expect("stopped");
+    session.send("variables", json!({ "variablesReference": 1 }));
+    let vars = session.expect("variables");
+    assert_eq!(vars["body"]["variables"][0]["name"], "0");
+    assert_eq!(vars["body"]["variables"][0]["value"], "20");
+    assert_eq!(vars["body"]["variables"][1]["value"], "10");
+
+    session.send("evaluate", json!({ "expression": "plus" }));
+    let result = session.expect("evaluate");
+    assert_eq!(result["body"]["result"], "30");
+
+    session.send("continue", json!({ "threadId": 1 }));
+    let out = session.expect("output");
+    assert_eq!(out["body"]["output"], "30\n");
+    let exited = session.expect("exited");
+    assert_eq!(exited["body"]["exitCode"], 0);
+    session.expect("terminated");
+    assert_eq!(session.finish(), 0);
+}
+
+#[test]
+fn test_dap_nested_variables() {
+    let file = script("'[1,{\"a\":2}]' json dup\n");
+    let path = file.path().to_str().unwrap().to_string();
+    let mut session = Session::start();
+    session.launch(&path, &[]);
+    session.send("launch", json!({ "program": path, "stopOnEntry": true }));
+    session.expect("launch");
+    session.send("configurationDone", json!({}));
+    let stopped = session.expect("stopped");
+    assert_eq!(stopped["body"]["reason"], "entry");
+
+    session.send("next", json!({ "threadId": 1 }));
+    session.expect("stopped");
+    session.send("next", json!({ "threadId": 1 }));
+    session.expect("stopped");
+    session.send("variables", json!({ "variablesReference": 1 }));
+    let vars = session.expect("variables");
+    let list = &vars["body"]["variables"][0];
+    assert_eq!(list["type"], "list");
+    let reference = list["variablesReference"].as_i64().unwrap();
+    assert!(reference > 1);
+
+    session.send("variables", json!({ "variablesReference": reference }));
+    let items = session.expect("variables");
+    assert_eq!(items["body"]["variables"][0]["value"], "1");
+    assert_eq!(items["body"]["variables"][1]["type"], "record");
+
+    session.send("continue", json!({ "threadId": 1 }));
+    session.expect("terminated");
+    assert_eq!(session.finish(), 0);
+}
+
+#[test]
+fn test_dap_function_breakpoint() {
+    let file = script("#[2 mul] :double\n21 double\n");
+    let path = file.path().to_str().unwrap().to_string();
+    let mut session = Session::start();
+    session.launch(&path, &[]);
+    session.send(
+        "setFunctionBreakpoints",
+        json!({ "breakpoints": [{ "name": "double" }] }),
+    );
+    session.expect("setFunctionBreakpoints");
+    session.send("configurationDone", json!({}));
+
+    let stopped = session.expect("stopped");
+    assert_eq!(stopped["body"]["reason"], "function breakpoint");
+    session.send("continue", json!({ "threadId": 1 }));
+    // Leaving the definition pauses again
+    session.expect("stopped");
+    session.send("continue", json!({ "threadId": 1 }));
+    let out = session.expect("output");
+    assert_eq!(out["body"]["output"], "42\n");
+    session.expect("terminated");
+    assert_eq!(session.finish(), 0);
+}
+
+#[test]
+fn test_dap_disconnect_while_paused() {
+    let file = script("1\n2\n");
+    let path = file.path().to_str().unwrap().to_string();
+    let mut session = Session::start();
+    session.launch(&path, &[1]);
+    session.send("configurationDone", json!({}));
+    session.expect("stopped");
+    session.send("disconnect", json!({}));
+    session.expect("disconnect");
+    let exited = session.expect("exited");
+    assert_eq!(exited["body"]["exitCode"], 1);
+    drop(session.requests);
+    assert_eq!(session.server.join().unwrap(), 1);
+}
+
+#[test]
+fn test_dap_missing_program() {
+    let mut session = Session::start();
+    session.launch("/nonexistent/script.hsab", &[]);
+    session.send("configurationDone", json!({}));
+    let out = session.expect("output");
+    assert_eq!(out["body"]["category"], "stderr");
+    let exited = session.expect("exited");
+    assert_eq!(exited["body"]["exitCode"], 1);
+    assert_eq!(session.finish(), 1);
+}
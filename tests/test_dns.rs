@@ -0,0 +1,136 @@
+//! Tests for the DNS builtins, against a name server on localhost
+//!
+//! The server comes from DNS_SERVER, which every test in this file shares,
+//! so it is set once and the lookups run in one test.
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, UdpSocket};
+
+#[path = "common/mod.rs"]
+mod common;
+use common::eval;
+
+/// A record in an answer: type, TTL, and data
+type Record = (u16, u32, Vec<u8>);
+
+fn name(text: &str) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for label in text.split('.') {
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// The answer the test server gives to a query, and whether it's too long
+/// for UDP
+fn answer(qname: &str, qtype: u16) -> (u8, Vec<Record>, bool) {
+    let cname = (5, 60, name("example.test"));
+    match (qname, qtype) {
+        ("www.example.test", 1) => (0, vec![cname, (1, 300, vec![192, 0, 2, 1])], false),
+        ("www.example.test", 28) => {
+            let mut v6 = vec![0x20, 0x01, 0x0d, 0xb8];
+            v6.extend_from_slice(&[0; 11]);
+            v6.push(1);
+            (0, vec![cname, (28, 300, v6)], false)
+        }
+        ("example.test", 15) => {
+            let mut mx = vec![0, 10];
+            // The mail server's name ends with a pointer to the question's
+            mx.extend_from_slice(&[4, b'm', b'a', b'i', b'l', 0xc0, 12]);
+            (0, vec![(15, 3600, mx)], false)
+        }
+        ("example.test", 16) => (0, vec![(16, 120, b"\x06v=spf1\x05 -all".to_vec())], true),
+        ("1.2.0.192.in-addr.arpa", 12) => (0, vec![(12, 86400, name("host.example.test"))], false),
+        _ => (3, vec![], false),
+    }
+}
+
+/// The reply to `query`, cut to the header and question over UDP when the
+/// answer is long
+fn reply(query: &[u8], over_udp: bool) -> Vec<u8> {
+    let mut at = 12;
+    let mut labels = Vec::new();
+    while query[at] != 0 {
+        let len = query[at] as usize;
+        labels.push(String::from_utf8_lossy(&query[at + 1..at + 1 + len]).to_string());
+        at += 1 + len;
+    }
+    let qtype = u16::from_be_bytes([query[at + 1], query[at + 2]]);
+    let question = &query[12..at + 5];
+    let (rcode, records, long) = answer(&labels.join("."), qtype);
+    let truncated = long && over_udp;
+
+    let mut packet = query[..2].to_vec();
+    packet.extend_from_slice(&[0x81 | if truncated { 0x02 } else { 0 }, 0x80 | rcode]);
+    let count = if truncated { 0 } else { records.len() as u16 };
+    packet.extend_from_slice(&[0, 1]);
+    packet.extend_from_slice(&count.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 0]);
+    packet.extend_from_slice(question);
+    for (rtype, ttl, data) in records.into_iter().take(count as usize) {
+        packet.extend_from_slice(&[0xc0, 12]);
+        packet.extend_from_slice(&rtype.to_be_bytes());
+        packet.extend_from_slice(&[0, 1]);
+        packet.extend_from_slice(&ttl.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&data);
+    }
+    packet
+}
+
+fn serve() -> String {
+    let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = udp.local_addr().unwrap();
+    let tcp = TcpListener::bind(addr).unwrap();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        loop {
+            let (n, from) = udp.recv_from(&mut buf).unwrap();
+            udp.send_to(&reply(&buf[..n], true), from).unwrap();
+        }
+    });
+    std::thread::spawn(move || {
+        for stream in tcp.incoming() {
+            let mut stream = stream.unwrap();
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query).unwrap();
+            let packet = reply(&query, false);
+            stream
+                .write_all(&(packet.len() as u16).to_be_bytes())
+                .unwrap();
+            stream.write_all(&packet).unwrap();
+        }
+    });
+    addr.to_string()
+}
+
+#[test]
+fn test_dns_lookups_against_a_local_server() {
+    std::env::set_var("DNS_SERVER", serve());
+
+    // A and AAAA, with the CNAME that led to them once
+    let output = eval(r#""www.example.test" dns-lookup to-json"#).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"[{"name":"www.example.test","type":"CNAME","value":"example.test","ttl":60},{"name":"www.example.test","type":"A","value":"192.0.2.1","ttl":300},{"name":"www.example.test","type":"AAAA","value":"2001:db8::1","ttl":300}]"#
+    );
+
+    let output = eval(r#""MX" "example.test" dns-query "value" get to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"["10 mail.example.test"]"#);
+
+    // Too long for UDP, so asked again over TCP
+    let output = eval(r#""txt" "example.test" dns-query "value" get to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"["v=spf1 -all"]"#);
+
+    let output = eval(r#""192.0.2.1" rdns "value" get to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"["host.example.test"]"#);
+
+    let err = eval(r#""missing.example.test" dns-lookup"#).unwrap_err();
+    assert!(err.contains("no such domain"), "{}", err);
+    assert!(eval(r#""BOGUS" "example.test" dns-query"#).is_err());
+    assert!(eval(r#""not-an-ip" rdns"#).is_err());
+}
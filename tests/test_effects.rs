@@ -0,0 +1,56 @@
+//! Tests for stack effects of builtins and definitions
+
+mod common;
+use common::{lex, parse, Evaluator};
+use hsab::{builtin_effect, StackEffect};
+
+fn effect(inputs: &[&str], outputs: &[&str]) -> StackEffect {
+    StackEffect {
+        inputs: inputs.iter().map(|s| s.to_string()).collect(),
+        outputs: outputs.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_builtin_effects_come_from_the_reference() {
+    assert_eq!(
+        builtin_effect("cp"),
+        Some(effect(&["src", "dst"], &["dst|nil"]))
+    );
+    assert_eq!(
+        builtin_effect("plus"),
+        Some(effect(&["a", "b"], &["(a+b)"]))
+    );
+    assert_eq!(builtin_effect("depth"), Some(effect(&[], &["n"])));
+    assert_eq!(
+        builtin_effect("cp").unwrap().to_string(),
+        "src dst -- dst|nil"
+    );
+    assert_eq!(builtin_effect("no-such-word"), None);
+}
+
+#[test]
+fn test_definition_effects_are_inferred() {
+    let mut evaluator = Evaluator::new();
+    let source = r#"
+        #[2 mul] :double
+        #[double double] :quadruple
+        #[swap drop] :nip
+        #[plus plus] :sum3
+        #[cat] :listing
+        #[recurse] :recurse
+    "#;
+    evaluator
+        .eval(&parse(lex(source).unwrap()).unwrap())
+        .unwrap();
+    let effects = evaluator.definition_effects();
+
+    let counts = |name: &str| effects.get(name).map(|e| (e.inputs.len(), e.outputs.len()));
+    assert_eq!(counts("double"), Some((1, 1)));
+    assert_eq!(counts("quadruple"), Some((1, 1)));
+    assert_eq!(counts("nip"), Some((2, 1)));
+    assert_eq!(counts("sum3"), Some((3, 1)));
+    // External commands take what the stack holds, so there's no effect
+    assert_eq!(counts("listing"), None);
+    assert_eq!(counts("recurse"), None);
+}
@@ -0,0 +1,249 @@
+//! Tests for embedding hsab: threads, cancellation, limits, pure evaluation,
+//! and host builtins
+
+use hsab::{Evaluator, EvaluatorHandle};
+
+#[test]
+fn test_evaluator_is_send() {
+    let mut evaluator = Evaluator::new();
+    let result = std::thread::spawn(move || {
+        let program = hsab::parse(hsab::lex("1 2 plus").unwrap()).unwrap();
+        evaluator.eval(&program).unwrap()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(result.output, "3");
+}
+
+#[test]
+fn test_handle_keeps_state_across_threads() {
+    let shell = EvaluatorHandle::default();
+    shell.eval("#[dup plus] :double").unwrap();
+
+    let workers: Vec<_> = (1..=4)
+        .map(|n| {
+            let shell = shell.clone();
+            std::thread::spawn(move || shell.eval(&format!("{} double", n)).unwrap())
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    // Every call ran against the same stack
+    assert_eq!(shell.with(|eval| eval.stack_len()).unwrap(), 4);
+    assert_eq!(shell.eval("plus plus plus").unwrap().output, "20");
+}
+
+#[test]
+fn test_handle_reports_errors_and_runs_closures() {
+    let shell = EvaluatorHandle::new(Evaluator::new());
+    assert!(shell.eval("#[unclosed").is_err());
+
+    shell.with(|eval| eval.set_strict_mode(true)).unwrap();
+    assert!(shell.with(|eval| eval.is_strict_mode()).unwrap());
+    let err = shell.eval("$HSAB_EMBEDDING_UNSET echo").unwrap_err();
+    assert!(err.contains("HSAB_EMBEDDING_UNSET"), "{}", err);
+}
+
+// ============================================
+// Cancellation
+// ============================================
+
+fn cancel_after(token: hsab::CancelToken, ms: u64) {
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+        token.cancel();
+    });
+}
+
+#[test]
+fn test_cancel_stops_a_loop() {
+    let mut evaluator = Evaluator::new();
+    cancel_after(evaluator.cancel_token(), 50);
+    let program = hsab::parse(hsab::lex("#[1 drop] 1000000000 times").unwrap()).unwrap();
+    assert!(matches!(
+        evaluator.eval(&program),
+        Err(hsab::EvalError::Cancelled)
+    ));
+
+    // The next eval runs normally
+    evaluator.clear_stack();
+    let program = hsab::parse(hsab::lex("1 2 plus").unwrap()).unwrap();
+    assert_eq!(evaluator.eval(&program).unwrap().output, "3");
+}
+
+#[test]
+fn test_cancel_is_not_caught_by_try() {
+    let mut evaluator = Evaluator::new();
+    cancel_after(evaluator.cancel_token(), 50);
+    let program =
+        hsab::parse(hsab::lex("#[#[1 drop] 1000000000 times] try \"after\" echo").unwrap())
+            .unwrap();
+    assert!(matches!(
+        evaluator.eval(&program),
+        Err(hsab::EvalError::Cancelled)
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cancel_kills_foreground_process() {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_exec_policy(hsab::eval::ExecPolicy {
+        external: vec!["sleep".into()],
+        ..Default::default()
+    });
+    let shell = EvaluatorHandle::new(evaluator);
+    cancel_after(shell.cancel_token(), 100);
+    let start = std::time::Instant::now();
+    let err = shell.eval("30 sleep").unwrap_err();
+    assert_eq!(err, "Cancelled");
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+}
+
+#[test]
+fn test_cancel_before_eval_cancels_the_next_one() {
+    let mut evaluator = Evaluator::new();
+    let token = evaluator.cancel_token();
+    token.cancel();
+    assert!(token.is_cancelled());
+    let program = hsab::parse(hsab::lex("1 2 plus").unwrap()).unwrap();
+    assert!(matches!(
+        evaluator.eval(&program),
+        Err(hsab::EvalError::Cancelled)
+    ));
+    assert!(!token.is_cancelled());
+}
+
+// ============================================
+// Limits
+// ============================================
+
+fn eval_with_limits(limits: hsab::Limits, source: &str) -> Result<String, hsab::EvalError> {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_limits(limits);
+    let program = hsab::parse(hsab::lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).map(|r| r.output)
+}
+
+#[test]
+fn test_step_limit() {
+    let steps = |n| hsab::Limits {
+        steps: Some(n),
+        ..Default::default()
+    };
+    assert_eq!(eval_with_limits(steps(100), "1 2 plus").unwrap(), "3");
+    let err = eval_with_limits(steps(100), "#[1 drop] 1000 times").unwrap_err();
+    assert!(matches!(err, hsab::EvalError::ResourceExhausted(_)));
+    assert_eq!(
+        err.to_string(),
+        "Resource exhausted: step limit of 100 reached"
+    );
+
+    // try can't swallow it
+    let err = eval_with_limits(steps(100), "#[#[1 drop] 1000 times] try").unwrap_err();
+    assert!(matches!(err, hsab::EvalError::ResourceExhausted(_)));
+}
+
+#[test]
+fn test_step_limit_is_per_eval() {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_limits(hsab::Limits {
+        steps: Some(50),
+        ..Default::default()
+    });
+    let program = hsab::parse(hsab::lex("#[1 drop] 10 times").unwrap()).unwrap();
+    for _ in 0..10 {
+        evaluator.eval(&program).unwrap();
+    }
+}
+
+#[test]
+fn test_time_limit() {
+    let seconds = hsab::Limits {
+        seconds: Some(0.1),
+        ..Default::default()
+    };
+    let start = std::time::Instant::now();
+    let err = eval_with_limits(seconds.clone(), "#[1 drop] 1000000000 times").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Resource exhausted: time limit of 0.1s reached"
+    );
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+    #[cfg(unix)]
+    {
+        let start = std::time::Instant::now();
+        let err = eval_with_limits(seconds, "30 sleep \"done\" echo").unwrap_err();
+        assert!(matches!(err, hsab::EvalError::ResourceExhausted(_)));
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+}
+
+// ============================================
+// Pure evaluation
+// ============================================
+
+#[test]
+fn test_eval_pure_runs_builtins_but_no_commands() {
+    assert_eq!(hsab::eval_pure("1 2 plus").unwrap().output, "3");
+    assert_eq!(
+        hsab::eval_pure("#[dup mul] :square 7 square")
+            .unwrap()
+            .output,
+        "49"
+    );
+    let err = hsab::eval_pure("\"hi\" cat").unwrap_err();
+    assert!(err.contains("not allowed by the exec policy"), "{}", err);
+}
+
+// ============================================
+// Host builtins
+// ============================================
+
+fn eval_on(evaluator: &mut Evaluator, source: &str) -> Result<String, hsab::EvalError> {
+    let program = hsab::parse(hsab::lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).map(|r| r.output)
+}
+
+#[test]
+fn test_register_builtin() {
+    let mut evaluator = Evaluator::new();
+    evaluator.register_builtin("shout", |eval| {
+        let text = eval
+            .pop_value()
+            .and_then(|v| v.as_arg())
+            .unwrap_or_default();
+        eval.push_value(hsab::Value::Literal(text.to_uppercase()));
+        Ok(())
+    });
+    assert_eq!(eval_on(&mut evaluator, "hello shout").unwrap(), "HELLO");
+    evaluator.clear_stack();
+    assert_eq!(
+        eval_on(&mut evaluator, "#[shout] :loud hi loud").unwrap(),
+        "HI"
+    );
+
+    assert!(evaluator.unregister_builtin("shout"));
+    evaluator.clear_stack();
+    assert_eq!(eval_on(&mut evaluator, "hi shout").unwrap(), "hi\nshout");
+}
+
+#[test]
+fn test_register_builtin_errors_and_precedence() {
+    let mut evaluator = Evaluator::new();
+    evaluator.register_builtin("boom", |_| {
+        Err(hsab::EvalError::ExecError("boom went off".into()))
+    });
+    let err = eval_on(&mut evaluator, "boom").unwrap_err();
+    assert!(err.to_string().contains("boom went off"), "{}", err);
+    assert_eq!(eval_on(&mut evaluator, "#[boom] try drop 1").unwrap(), "1");
+
+    // Definitions shadow registered words
+    evaluator.clear_stack();
+    assert_eq!(
+        eval_on(&mut evaluator, "#[\"defined\"] :boom boom").unwrap(),
+        "defined"
+    );
+}
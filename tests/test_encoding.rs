@@ -934,3 +934,199 @@ fn test_read_bytes_zero() {
     let output = eval(r#""/dev/urandom" 0 read-bytes len"#).unwrap();
     assert_eq!(output.trim(), "0");
 }
+
+#[test]
+fn test_decode_latin1() {
+    let output = eval(r#""e974e9" from-hex "latin-1" decode"#).unwrap();
+    assert_eq!(output.trim(), "été");
+}
+
+#[test]
+fn test_decode_windows_1252() {
+    let output = eval(r#""80" from-hex "cp1252" decode"#).unwrap();
+    assert_eq!(output.trim(), "€");
+}
+
+#[test]
+fn test_decode_utf16le() {
+    let output = eval(r#""68006900" from-hex "utf-16le" decode"#).unwrap();
+    assert_eq!(output.trim(), "hi");
+}
+
+#[test]
+fn test_decode_shift_jis() {
+    let output = eval(r#""93fa967b" from-hex "shift-jis" decode"#).unwrap();
+    assert_eq!(output.trim(), "日本");
+}
+
+#[test]
+fn test_shift_jis_round_trip() {
+    let output = eval(r#""日本語テキスト" "shift_jis" encode to-hex"#).unwrap();
+    assert_eq!(output.trim(), "93fa967b8cea8365834c83588367");
+    let output = eval(r#""日本語テキスト" "sjis" encode "sjis" decode"#).unwrap();
+    assert_eq!(output.trim(), "日本語テキスト");
+}
+
+#[test]
+fn test_encode_utf16le() {
+    let output = eval(r#""hi" "utf-16le" encode to-hex"#).unwrap();
+    assert_eq!(output.trim(), "68006900");
+}
+
+#[test]
+fn test_decode_unknown_encoding() {
+    let err = eval(r#""e9" from-hex "klingon" decode"#).unwrap_err();
+    assert!(err.contains("unknown encoding"), "got: {}", err);
+}
+
+#[test]
+fn test_encoding_default_and_set() {
+    let output = eval("encoding").unwrap();
+    assert_eq!(output.trim(), "utf-8");
+    // WHATWG labels: Latin-1 is read as its windows-1252 superset
+    let output = eval(r#""Latin1" set-encoding encoding"#).unwrap();
+    assert_eq!(output.trim(), "windows-1252");
+    let output = eval(r#""Shift-JIS" set-encoding encoding"#).unwrap();
+    assert_eq!(output.trim(), "shift_jis");
+}
+
+#[test]
+fn test_set_encoding_decodes_command_output() {
+    let output = eval(r#""latin-1" set-encoding "printf '\351t\351'" "-c" sh"#).unwrap();
+    assert_eq!(output.trim(), "été");
+}
+
+// === Bytes operations ===
+
+#[test]
+fn test_bytes_slice() {
+    let run = |code: &str| eval(code).unwrap().trim().to_string();
+    assert_eq!(
+        run(r#""hello world" as-bytes 6 5 bytes-slice to-string"#),
+        "world"
+    );
+    assert_eq!(
+        run(r#""hello" as-bytes -3 10 bytes-slice to-string"#),
+        "llo"
+    );
+    assert_eq!(run(r#""hello" as-bytes 9 2 bytes-slice bytes-len"#), "0");
+}
+
+#[test]
+fn test_bytes_concat() {
+    let output = eval(r#""cafe" from-hex "babe" from-hex bytes-concat to-hex"#).unwrap();
+    assert_eq!(output.trim(), "cafebabe");
+    let output = eval(r#"["ab" "cd"] bytes-concat to-hex"#).unwrap();
+    assert_eq!(output.trim(), "61626364");
+}
+
+#[test]
+fn test_bytes_len_counts_utf8() {
+    assert_eq!(eval(r#""héllo" bytes-len"#).unwrap().trim(), "6");
+    assert_eq!(eval(r#""00ff" from-hex bytes-len"#).unwrap().trim(), "2");
+}
+
+#[test]
+fn test_bytes_find() {
+    assert_eq!(
+        eval(r#""7f454c4602" from-hex "ELF" bytes-find"#)
+            .unwrap()
+            .trim(),
+        "1"
+    );
+    assert_eq!(
+        eval(r#""7f454c46" from-hex "0203" from-hex bytes-find typeof"#)
+            .unwrap()
+            .trim(),
+        "nil"
+    );
+    assert_eq!(eval_exit_code(r#""abc" as-bytes "z" bytes-find"#), 1);
+}
+
+#[test]
+fn test_hexdump() {
+    let output = eval(r#""Hello, hexdump world!" as-bytes hexdump"#).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(
+        lines[0].starts_with("00000000  48 65 6c 6c 6f"),
+        "{}",
+        output
+    );
+    assert!(lines[0].ends_with("|Hello, h|") || lines[0].ends_with("|Hello, hexdump w|"));
+    assert_eq!(*lines.last().unwrap(), "00000015");
+    assert_eq!(eval(r#""" as-bytes hexdump"#).unwrap(), "");
+}
+
+#[test]
+fn test_bytes_compare() {
+    assert_eq!(
+        eval(r#""0102" from-hex "0103" from-hex compare"#)
+            .unwrap()
+            .trim(),
+        "-1"
+    );
+}
+
+#[test]
+fn test_from_hex_skips_separators() {
+    let output = eval(r#""de ad:be ef" from-hex to-hex"#).unwrap();
+    assert_eq!(output.trim(), "deadbeef");
+}
+
+// === Compression ===
+
+#[test]
+fn test_gzip_round_trip() {
+    let output = eval(r#""hello hello hello" gzip gunzip"#).unwrap();
+    assert_eq!(output.trim(), "hello hello hello");
+    // The gzip magic number
+    let output = eval(r#""hello" gzip 0 2 bytes-slice to-hex"#).unwrap();
+    assert_eq!(output.trim(), "1f8b");
+}
+
+#[test]
+fn test_zstd_round_trip() {
+    let output = eval(r#""hello hello hello" zstd unzstd"#).unwrap();
+    assert_eq!(output.trim(), "hello hello hello");
+    let output = eval(r#""00ff" from-hex zstd unzstd to-hex"#).unwrap();
+    assert_eq!(output.trim(), "00ff");
+}
+
+#[test]
+fn test_gunzip_reads_gz_files() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log.gz");
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(b"GET /\nPOST /login\n").unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+    let input = format!(r#""{}" read-file gunzip spread"#, path.display());
+    let output = eval(&input).unwrap();
+    assert!(output.contains("GET /"), "{}", output);
+    assert!(output.contains("POST /login"), "{}", output);
+}
+
+#[test]
+fn test_gunzip_rejects_other_data() {
+    assert!(eval(r#""not gzip" as-bytes gunzip"#).is_err());
+    assert!(eval(r#""not zstd" as-bytes unzstd"#).is_err());
+}
+
+#[test]
+fn test_gzip_leaves_files_to_the_command() {
+    if std::process::Command::new("gzip")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+    std::fs::write(&path, "notes").unwrap();
+
+    eval(&format!(r#""{}" gzip"#, path.display())).unwrap();
+    assert!(dir.path().join("notes.txt.gz").exists());
+    assert!(!path.exists());
+}
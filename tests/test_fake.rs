@@ -0,0 +1,77 @@
+//! Tests for the fake- test data words
+
+#[path = "common/mod.rs"]
+mod common;
+use common::eval;
+
+#[test]
+fn test_fake_seed_repeats_the_data() {
+    let program = r#"42 fake-seed fake-name fake-email 2 { n "name" } fake-rows to-json"#;
+    assert_eq!(eval(program).unwrap(), eval(program).unwrap());
+    assert_ne!(
+        eval(program).unwrap(),
+        eval(&program.replace("42", "43")).unwrap()
+    );
+}
+
+#[test]
+fn test_fake_email_uses_a_reserved_domain() {
+    let output = eval("fake-email").unwrap();
+    let domain = output.trim().rsplit_once('@').unwrap().1;
+    assert!(domain.starts_with("example."), "{}", output);
+}
+
+#[test]
+fn test_fake_rows_follows_the_schema() {
+    let output = eval(
+        r#"25 { id "id" name "name" email "email" age { type "int" min 18 max 20 }
+                plan ["free" "pro"] } fake-rows"#,
+    )
+    .unwrap();
+    assert!(
+        output.contains("id") && output.contains("plan"),
+        "{}",
+        output
+    );
+
+    let output = eval(r#"25 { id "id" } fake-rows "id" get to-json"#).unwrap();
+    let ids: Vec<String> = (1..=25).map(|i| i.to_string()).collect();
+    assert_eq!(output.trim(), format!("[{}]", ids.join(",")));
+
+    let output = eval(
+        r#"50 { age { type "int" min 18 max 20 } plan ["free" "pro"] } fake-rows
+           #["age" get 18 lt?] where count"#,
+    )
+    .unwrap();
+    assert_eq!(output.trim(), "0");
+    let output = eval(
+        r#"50 { age { type "int" min 18 max 20 } } fake-rows #["age" get 20 gt?] where count"#,
+    )
+    .unwrap();
+    assert_eq!(output.trim(), "0");
+    let output = eval(r#"50 { plan ["free" "pro"] } fake-rows "plan" get unique count"#).unwrap();
+    assert_eq!(output.trim(), "2");
+}
+
+#[test]
+fn test_fake_rows_email_belongs_to_the_name() {
+    let output = eval(r#"1 fake-seed 20 { name "name" email "email" } fake-rows to-json"#).unwrap();
+    let rows: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    for row in rows.as_array().unwrap() {
+        let first = row["name"].as_str().unwrap().split(' ').next().unwrap();
+        let email = row["email"].as_str().unwrap();
+        assert!(
+            email.starts_with(&first.to_lowercase()[..1]),
+            "{} / {}",
+            first,
+            email
+        );
+    }
+}
+
+#[test]
+fn test_fake_rows_rejects_an_unknown_generator() {
+    let err = eval(r#"2 { a "nope" } fake-rows"#).unwrap_err();
+    assert!(err.contains("unknown generator 'nope'"), "{}", err);
+    assert!(eval(r#"2 { a { type "name" min 1 } } fake-rows"#).is_err());
+}
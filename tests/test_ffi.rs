@@ -0,0 +1,84 @@
+//! Tests for the C API (`--features ffi`)
+
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+use hsab::ffi::*;
+use hsab::Evaluator;
+
+/// Take ownership of a string returned by the API
+unsafe fn take(s: *mut c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    let text = CStr::from_ptr(s).to_str().unwrap().to_string();
+    hsab_string_free(s);
+    Some(text)
+}
+
+unsafe fn eval_json(h: *mut Evaluator, source: &str) -> serde_json::Value {
+    let source = CString::new(source).unwrap();
+    serde_json::from_str(&take(hsab_eval(h, source.as_ptr())).unwrap()).unwrap()
+}
+
+#[test]
+fn test_ffi_eval_returns_stack_as_json() {
+    unsafe {
+        let h = hsab_new();
+        let result = eval_json(h, "1 2 plus");
+        assert_eq!(result["stack"], serde_json::json!([3]));
+        assert_eq!(result["output"], "3");
+        assert_eq!(result["exit_code"], 0);
+
+        let result = eval_json(h, "#[unclosed");
+        assert!(result["error"].as_str().unwrap().contains("Unmatched"));
+
+        assert!(hsab_eval(h, std::ptr::null()).is_null());
+        hsab_free(h);
+    }
+}
+
+#[test]
+fn test_ffi_push_and_pop() {
+    unsafe {
+        let h = hsab_new();
+        let json = CString::new(r#"{"a": [1, 2]}"#).unwrap();
+        assert_eq!(hsab_push(h, json.as_ptr()), 0);
+        let bad = CString::new("{").unwrap();
+        assert_eq!(hsab_push(h, bad.as_ptr()), -1);
+        assert_eq!(take(hsab_pop(h)).unwrap(), r#"{"a":[1,2]}"#);
+        assert!(hsab_pop(h).is_null());
+        hsab_free(h);
+    }
+}
+
+/// Adds the userdata's number to the top of the stack
+unsafe extern "C" fn add_n(h: *mut Evaluator, userdata: *mut c_void) -> c_int {
+    let n = *(userdata as *const i64);
+    let Some(top) = take(hsab_pop(h)) else {
+        return 1;
+    };
+    let sum = CString::new((top.parse::<i64>().unwrap() + n).to_string()).unwrap();
+    hsab_push(h, sum.as_ptr())
+}
+
+#[test]
+fn test_ffi_register_builtin() {
+    static TEN: i64 = 10;
+    unsafe {
+        let h = hsab_new();
+        let name = CString::new("add-ten").unwrap();
+        let userdata = &TEN as *const i64 as *mut c_void;
+        assert_eq!(hsab_register_builtin(h, name.as_ptr(), add_n, userdata), 0);
+        assert_eq!(eval_json(h, "5 add-ten")["stack"], serde_json::json!([15]));
+
+        eval_json(h, "drop");
+        let result = eval_json(h, "add-ten");
+        assert_eq!(
+            result["error"],
+            "Execution error: add-ten: builtin failed with status 1"
+        );
+        hsab_free(h);
+    }
+}
@@ -165,3 +165,79 @@ fn test_reap_jobs_reports_finished_jobs() {
         notices
     );
 }
+
+#[test]
+fn test_jobs_table_and_job_output() {
+    let mut evaluator = Evaluator::new();
+    run(&mut evaluator, r#"#[-c "echo made it" sh] &"#);
+    std::thread::sleep(Duration::from_millis(300));
+
+    let out = run(&mut evaluator, r#"jobs-table "status" get to-json"#);
+    assert_eq!(out, r#"["done"]"#);
+    let out = run(&mut evaluator, r#"jobs-table "command" get to-json"#);
+    assert_eq!(out, r#"["sh -c echo made it"]"#);
+    let out = run(&mut evaluator, "%1 job-output");
+    assert_eq!(out.trim(), "made it");
+}
+
+#[test]
+fn test_job_done_hook_gets_job_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let seen = dir.path().join("seen.json");
+    let mut evaluator = Evaluator::new();
+    run(
+        &mut evaluator,
+        &format!(r#"#["{}" save] :JOB_DONE"#, seen.display()),
+    );
+    run(&mut evaluator, "#[0.05 sleep] &");
+    std::thread::sleep(Duration::from_millis(300));
+
+    evaluator.reap_jobs();
+    evaluator.run_job_done_hook();
+    let record: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&seen).unwrap()).unwrap();
+    assert_eq!(record["id"], 1);
+    assert_eq!(record["status"], "done");
+    assert_eq!(record["command"], "sleep 0.05");
+
+    // Each job is reported once
+    std::fs::remove_file(&seen).unwrap();
+    evaluator.run_job_done_hook();
+    assert!(!seen.exists());
+}
+
+#[test]
+fn test_nohup_appends_output_to_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("deploy.log");
+    std::fs::write(&log, "earlier\n").unwrap();
+    let mut evaluator = Evaluator::new();
+    run(
+        &mut evaluator,
+        &format!(
+            r#"#[-c "echo out; echo err >&2" sh] "{}" nohup"#,
+            log.display()
+        ),
+    );
+    run(&mut evaluator, "wait");
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "earlier\nout\nerr\n"
+    );
+}
+
+#[test]
+fn test_disown_stops_tracking_job() {
+    let mut evaluator = Evaluator::new();
+    run(&mut evaluator, "#[0.2 sleep] &");
+    run(&mut evaluator, "#[0.2 sleep] &");
+    run(&mut evaluator, "%1 disown");
+    let out = run(&mut evaluator, r#"jobs-table "id" get to-json"#);
+    assert_eq!(out, "[2]");
+    run(&mut evaluator, "disown");
+    assert_eq!(evaluator.job_count(), 0);
+    let result = evaluator
+        .eval(&parse(lex("%1 disown").unwrap()).unwrap())
+        .unwrap_err();
+    assert!(result.to_string().contains("no such job"));
+}
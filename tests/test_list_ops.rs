@@ -0,0 +1,284 @@
+//! Integration tests for list-native operations (list-push, list-get, list-map, ...)
+
+#[path = "common/mod.rs"]
+mod common;
+#[allow(unused_imports)]
+use common::{eval, eval_exit_code, lex, parse, Evaluator};
+
+// === push / pop / get / len ===
+
+#[test]
+fn test_list_push() {
+    let output = eval("[1 2] 3 list-push to-json").unwrap();
+    assert_eq!(output.trim(), "[1,2,3]");
+}
+
+#[test]
+fn test_list_pop() {
+    let output = eval("[1 2 3] list-pop").unwrap();
+    assert_eq!(output.trim(), "1\n2\n3");
+    let output = eval("[1 2 3] list-pop drop to-json").unwrap();
+    assert_eq!(output.trim(), "[1,2]");
+}
+
+#[test]
+fn test_list_get_positive_and_negative() {
+    assert_eq!(eval("[a b c] 1 list-get").unwrap(), "b");
+    assert_eq!(eval("[a b c] -1 list-get").unwrap(), "c");
+}
+
+#[test]
+fn test_list_get_out_of_range_is_nil() {
+    let output = eval("[a b c] 10 list-get nil?").unwrap();
+    assert!(output.contains("true"));
+}
+
+#[test]
+fn test_list_len() {
+    assert_eq!(eval("'[1,2,3,4]' json list-len").unwrap(), "4");
+}
+
+#[test]
+fn test_list_ops_reject_non_list() {
+    assert!(eval("\"abc\" list-len").is_err());
+}
+
+// === slice ===
+
+#[test]
+fn test_list_slice() {
+    let output = eval("[a b c d e] 1 3 list-slice to-json").unwrap();
+    assert_eq!(output.trim(), r#"["b","c","d"]"#);
+}
+
+#[test]
+fn test_list_slice_negative_start() {
+    let output = eval("[a b c d e] -2 5 list-slice to-json").unwrap();
+    assert_eq!(output.trim(), r#"["d","e"]"#);
+}
+
+// === map / filter ===
+
+#[test]
+fn test_list_map() {
+    let output = eval("[1 2 3] #[10 mul] list-map to-json").unwrap();
+    assert_eq!(output.trim(), "[10,20,30]");
+}
+
+#[test]
+fn test_list_map_preserves_outer_stack() {
+    let output = eval("keep-me [1 2] #[1 plus] list-map to-json").unwrap();
+    assert_eq!(output.trim(), "keep-me\n[2,3]");
+}
+
+#[test]
+fn test_list_filter() {
+    let output = eval("[1 5 2 8] #[3 gt?] list-filter to-json").unwrap();
+    assert_eq!(output.trim(), "[5,8]");
+}
+
+#[test]
+fn test_list_map_over_json_records() {
+    let output = eval(r#"'[{"n":"a"},{"n":"b"}]' json #["n" get] list-map to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"["a","b"]"#);
+}
+
+// === enumerate ===
+
+#[test]
+fn test_enumerate() {
+    let output = eval("[x y] enumerate to-json").unwrap();
+    assert_eq!(output.trim(), r#"[[0,"x"],[1,"y"]]"#);
+}
+
+// === seq ===
+
+#[test]
+fn test_seq_like_coreutils() {
+    assert_eq!(eval("3 seq to-json").unwrap().trim(), "[1,2,3]");
+    assert_eq!(eval("5 2 seq to-json").unwrap().trim(), "[2,3,4,5]");
+    assert_eq!(eval("5 1 seq to-json").unwrap().trim(), "[1,2,3,4,5]");
+    assert_eq!(eval("10 3 1 seq to-json").unwrap().trim(), "[1,4,7,10]");
+    assert_eq!(eval("1 -3 10 seq to-json").unwrap().trim(), "[10,7,4,1]");
+    assert_eq!(eval("1 5 seq to-json").unwrap().trim(), "[]");
+    assert_eq!(
+        eval("1 0.25 0 seq to-json").unwrap().trim(),
+        "[0.0,0.25,0.5,0.75,1.0]"
+    );
+}
+
+#[test]
+fn test_seq_takes_only_numbers() {
+    assert_eq!(eval("foo 3 seq to-json").unwrap(), "foo\n[1,2,3]");
+    assert_eq!(eval("1 10 3 1 seq to-json").unwrap(), "1\n[1,4,7,10]");
+    assert!(eval("5 0 1 seq").unwrap_err().contains("step can't be 0"));
+    assert!(eval("seq").is_err());
+}
+
+#[test]
+fn test_seq_refuses_huge_ranges() {
+    assert!(eval("1e12 0.1 1 seq").unwrap_err().contains("more than"));
+    assert!(eval("9223372036854775807 -9223372036854775807 seq")
+        .unwrap_err()
+        .contains("more than"));
+    assert_eq!(eval("10000000 seq list-len").unwrap().trim(), "10000000");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_seq_flags_run_the_seq_command() {
+    assert_eq!(eval("10 8 -w seq").unwrap(), "08\n09\n10");
+    assert_eq!(eval("3 1 , -s seq").unwrap(), "1,2,3");
+}
+
+// === sort ===
+
+#[test]
+fn test_sort_lists_by_type() {
+    assert_eq!(
+        eval("[3 1 10 2] sort to-json").unwrap().trim(),
+        "[1,2,3,10]"
+    );
+    assert_eq!(
+        eval("[pear Apple fig] sort to-json").unwrap().trim(),
+        r#"["Apple","fig","pear"]"#
+    );
+    assert_eq!(
+        eval(r#"[pear Apple fig] "-fr" sort to-json"#)
+            .unwrap()
+            .trim(),
+        r#"["pear","fig","Apple"]"#
+    );
+    assert!(eval(r#"marker "a" 1 record collect sort"#)
+        .unwrap_err()
+        .contains("use sort-by for records"));
+}
+
+#[test]
+fn test_sort_numeric_and_natural() {
+    assert_eq!(
+        eval(r#"["10 apples" "9 pears" "x"] "-n" sort to-json"#)
+            .unwrap()
+            .trim(),
+        r#"["x","9 pears","10 apples"]"#
+    );
+    assert_eq!(
+        eval("[file10 file2 file1 File3] sort to-json")
+            .unwrap()
+            .trim(),
+        r#"["File3","file1","file10","file2"]"#
+    );
+    assert_eq!(
+        eval(r#"[file10 file2 file1 File3] "-fV" sort to-json"#)
+            .unwrap()
+            .trim(),
+        r#"["file1","file2","File3","file10"]"#
+    );
+}
+
+#[test]
+fn test_sort_takes_separate_flag_words() {
+    assert_eq!(
+        eval("[10 9 100] -n -r sort to-json").unwrap().trim(),
+        "[100,10,9]"
+    );
+    assert_eq!(
+        eval(r#"[file10 file2 File3] "-f" "-V" "-r" sort to-json"#)
+            .unwrap()
+            .trim(),
+        r#"["file10","File3","file2"]"#
+    );
+    assert_eq!(
+        eval(
+            r#"start "1
+3
+2" spread -n -r sort collect to-json"#
+        )
+        .unwrap()
+        .trim(),
+        "start\n[\"3\",\"2\",\"1\"]"
+    );
+}
+
+#[test]
+fn test_sort_spread_items_in_place() {
+    assert_eq!(
+        eval(r#""b\nc\na" spread sort collect to-json"#)
+            .unwrap()
+            .trim(),
+        r#"["a","b","c"]"#
+    );
+    assert_eq!(
+        eval(r#"start "v2\nv10\nv1" spread "-V" sort collect to-json"#)
+            .unwrap()
+            .trim(),
+        "start\n[\"v1\",\"v2\",\"v10\"]"
+    );
+}
+
+#[test]
+fn test_sort_leaves_files_to_the_sort_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("words.txt");
+    std::fs::write(&file, "b\na\n").unwrap();
+    let output = eval(&format!("{} sort", file.display())).unwrap();
+    assert_eq!(output.trim(), "a\nb");
+}
+
+// === head / tail / sample ===
+
+#[test]
+fn test_head_and_tail_keep_the_type() {
+    assert_eq!(
+        eval("[a b c d] 2 head to-json").unwrap().trim(),
+        r#"["a","b"]"#
+    );
+    assert_eq!(
+        eval("[a b c d] 3 tail to-json").unwrap().trim(),
+        r#"["b","c","d"]"#
+    );
+    assert_eq!(eval("[a b] 5 tail to-json").unwrap().trim(), r#"["a","b"]"#);
+    assert_eq!(eval(r#""x\ny\nz" 2 tail"#).unwrap().trim(), "y\nz");
+    let table = r#"marker "n" 1 record "n" 2 record "n" 3 record table"#;
+    let output = eval(&format!("{} 1 tail typeof", table)).unwrap();
+    assert_eq!(output.trim(), "table");
+    let output = eval(&format!("{} 1 tail to-json", table)).unwrap();
+    assert_eq!(output.trim(), r#"[{"n":3}]"#);
+}
+
+#[test]
+fn test_sample_is_seeded_and_keeps_order() {
+    let first = eval("7 fake-seed 10 seq 4 sample to-json").unwrap();
+    let again = eval("7 fake-seed 10 seq 4 sample to-json").unwrap();
+    assert_eq!(first, again);
+    let picked: Vec<i64> = serde_json::from_str(first.trim()).unwrap();
+    assert_eq!(picked.len(), 4);
+    assert!(picked.windows(2).all(|w| w[0] < w[1]), "{:?}", picked);
+    assert_eq!(eval("[a b] 5 sample list-len").unwrap().trim(), "2");
+}
+
+#[test]
+fn test_head_with_an_option_runs_the_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("n.txt");
+    std::fs::write(&file, "1\n2\n3\n").unwrap();
+    let output = eval(&format!("{} -2 head", file.display())).unwrap();
+    assert_eq!(output.trim(), "1\n2");
+}
+
+#[test]
+fn test_tail_file_reads_the_last_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("big.log");
+    let text: String = (1..=5000).map(|i| format!("line {}\n", i)).collect();
+    std::fs::write(&file, text).unwrap();
+    let output = eval(&format!(r#""{}" 3 tail-file"#, file.display())).unwrap();
+    assert_eq!(output, "line 4998\nline 4999\nline 5000");
+
+    std::fs::write(&file, "only\nno newline").unwrap();
+    let output = eval(&format!(r#""{}" 5 tail-file"#, file.display())).unwrap();
+    assert_eq!(output, "only\nno newline");
+    assert!(eval(r#""/nonexistent/x.log" 3 tail-file"#)
+        .unwrap_err()
+        .contains("tail-file"));
+}
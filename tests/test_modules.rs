@@ -102,3 +102,80 @@ fn test_import_skips_already_loaded() {
         output
     );
 }
+
+/// Run `input` on `evaluator`, returning its output
+fn run(evaluator: &mut Evaluator, input: &str) -> String {
+    let program = parse(lex(input).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap().output
+}
+
+/// Poll until a watched module has been reloaded, or give up after a while
+fn wait_for_reload(evaluator: &mut Evaluator) -> Vec<hsab::eval::ModuleReload> {
+    for _ in 0..50 {
+        let reloads = evaluator.poll_module_reloads();
+        if !reloads.is_empty() {
+            return reloads;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    Vec::new()
+}
+
+#[test]
+fn test_import_watch_reloads_changed_module() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let module_path = temp_dir.path().join("mylib.hsab");
+    std::fs::write(&module_path, "#[dup plus] :double\n#[2 div] :half\n").unwrap();
+
+    let mut evaluator = Evaluator::new();
+    run(
+        &mut evaluator,
+        &format!(r#""{}" .import-watch"#, module_path.display()),
+    );
+    assert_eq!(run(&mut evaluator, "21 mylib::double").trim(), "42");
+    assert!(evaluator.poll_module_reloads().is_empty());
+
+    std::fs::write(
+        &module_path,
+        "#[dup dup plus plus] :double\n#[3 times] :triple\n",
+    )
+    .unwrap();
+    let reloads = wait_for_reload(&mut evaluator);
+    assert_eq!(reloads.len(), 1, "the module was reloaded once");
+    let reload = &reloads[0];
+    assert_eq!(reload.added, vec!["mylib::triple"]);
+    assert_eq!(reload.changed, vec!["mylib::double"]);
+    assert_eq!(reload.removed, vec!["mylib::half"]);
+    assert_eq!(
+        reload.to_string(),
+        "mylib.hsab: +mylib::triple ~mylib::double -mylib::half"
+    );
+
+    // The reload ran on a stack of its own
+    assert_eq!(evaluator.stack().len(), 1);
+    assert_eq!(run(&mut evaluator, "drop 7 mylib::double").trim(), "21");
+    assert!(!evaluator.has_definition("mylib::half"));
+}
+
+#[test]
+fn test_import_watch_keeps_old_words_when_reload_fails() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let module_path = temp_dir.path().join("mylib.hsab");
+    std::fs::write(&module_path, "#[dup plus] :double\n").unwrap();
+
+    let mut evaluator = Evaluator::new();
+    run(
+        &mut evaluator,
+        &format!(r#""{}" .import-watch"#, module_path.display()),
+    );
+
+    std::fs::write(
+        &module_path,
+        "#[dup dup plus plus] :double\n#[unclosed :broken\n",
+    )
+    .unwrap();
+    let reloads = wait_for_reload(&mut evaluator);
+    assert_eq!(reloads.len(), 1);
+    assert!(reloads[0].error.is_some(), "{:?}", reloads[0]);
+    assert_eq!(run(&mut evaluator, "21 mylib::double").trim(), "42");
+}
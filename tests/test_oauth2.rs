@@ -0,0 +1,149 @@
+//! Tests for `oauth2-token`, against a token endpoint on localhost
+//!
+//! Tokens are kept under HOME, which every test in this file shares, so it
+//! is set once and the steps run in one test.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Receiver};
+
+mod common;
+use common::{lex, parse, Evaluator};
+
+fn run(evaluator: &mut Evaluator, source: &str) -> Result<String, String> {
+    evaluator.clear_stack();
+    let program = parse(lex(source).unwrap()).unwrap();
+    evaluator
+        .eval(&program)
+        .map(|result| result.output)
+        .map_err(|e| e.to_string())
+}
+
+/// A server that answers each request with the next of `replies` (status
+/// and JSON body), handing back the body of each request it got
+fn serve(replies: Vec<(u16, &'static str)>) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        for (status, reply) in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let body = loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|l| {
+                            let l = l.to_ascii_lowercase();
+                            l.strip_prefix("content-length: ")?.trim().parse().ok()
+                        })
+                        .unwrap_or(0usize);
+                    if request.len() >= end + 4 + length || n == 0 {
+                        break text[end + 4..].to_string();
+                    }
+                }
+            };
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                reply.len(),
+                reply
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            tx.send(body).unwrap();
+        }
+    });
+    (url, rx)
+}
+
+#[test]
+fn test_oauth2_token_keeps_refreshes_and_polls() {
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+    let mut evaluator = Evaluator::new();
+
+    // Client credentials: fetched once, then kept
+    let (url, requests) = serve(vec![
+        (
+            200,
+            r#"{"access_token":"one","expires_in":3600,"refresh_token":"r1"}"#,
+        ),
+        (200, r#"{"access_token":"two","expires_in":30}"#),
+        (200, r#"{"access_token":"three","expires_in":3600}"#),
+    ]);
+    let client = format!(
+        r#"{{ token_url "{}/token" client_id "app" client_secret "s e" scope ["read" "write"] }}"#,
+        url
+    );
+    let token = format!("{} oauth2-token", client);
+    assert_eq!(run(&mut evaluator, &token).unwrap(), "one");
+    assert_eq!(evaluator.origin(0), Some(hsab::Origin::Secret));
+    assert_eq!(
+        requests.recv().unwrap(),
+        "grant_type=client_credentials&client_id=app&client_secret=s%20e&scope=read%20write"
+    );
+    assert_eq!(run(&mut Evaluator::new(), &token).unwrap(), "one");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = home.path().join(".hsab").join("cache");
+        let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+        let mode = file.metadata().unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // refresh asks again, with the refresh token it has
+    assert_eq!(
+        run(&mut evaluator, &format!("refresh {}", token)).unwrap(),
+        "two"
+    );
+    assert!(requests
+        .recv()
+        .unwrap()
+        .starts_with("grant_type=refresh_token&refresh_token=r1&"));
+    // "two" expires within the margin, so it isn't used; it kept r1
+    assert_eq!(run(&mut evaluator, &token).unwrap(), "three");
+    assert!(requests.recv().unwrap().contains("refresh_token=r1"));
+
+    // Device code: polled until the user has signed in
+    let (url, requests) = serve(vec![
+        (
+            200,
+            r#"{"device_code":"dc","user_code":"ABCD","verification_uri":"https://example.com/device","interval":0}"#,
+        ),
+        (400, r#"{"error":"authorization_pending"}"#),
+        (200, r#"{"access_token":"device","expires_in":3600}"#),
+    ]);
+    let device = format!(
+        r#"{{ token_url "{u}/token" device_url "{u}/device" client_id "cli" }} oauth2-token"#,
+        u = url
+    );
+    assert_eq!(run(&mut evaluator, &device).unwrap(), "device");
+    assert_eq!(requests.recv().unwrap(), "client_id=cli");
+    for _ in 0..2 {
+        assert_eq!(
+            requests.recv().unwrap(),
+            "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code&device_code=dc&client_id=cli"
+        );
+    }
+
+    // Errors from the server are reported
+    let (url, _requests) = serve(vec![(
+        401,
+        r#"{"error":"invalid_client","error_description":"bad secret"}"#,
+    )]);
+    let err = run(
+        &mut evaluator,
+        &format!(
+            r#"{{ token_url "{}/token" client_id "x" client_secret "y" }} oauth2-token"#,
+            url
+        ),
+    )
+    .unwrap_err();
+    assert!(err.contains("invalid_client: bad secret"), "{}", err);
+    assert!(run(&mut evaluator, r#"{ client_id "x" } oauth2-token"#).is_err());
+}
@@ -109,3 +109,64 @@ fn test_ends_predicate_no_match() {
         "ends? should return 1 when string doesn't end with suffix"
     );
 }
+
+// eq-deep? / compare tests
+#[test]
+fn test_eq_deep_lists() {
+    let exit_code = eval_exit_code(r#"'[1,[2,3]]' from-json '[1,[2,3]]' from-json eq-deep?"#);
+    assert_eq!(exit_code, 0, "equal nested lists should be deep-equal");
+    let exit_code = eval_exit_code(r#"'[1,2]' from-json '[2,1]' from-json eq-deep?"#);
+    assert_eq!(exit_code, 1, "list order matters for deep equality");
+}
+
+#[test]
+fn test_eq_deep_records_ignore_key_order() {
+    let exit_code = eval_exit_code(
+        r#"'{"a":1,"b":{"c":2}}' from-json '{"b":{"c":2},"a":1}' from-json eq-deep?"#,
+    );
+    assert_eq!(exit_code, 0, "record key order should not matter");
+}
+
+#[test]
+fn test_eq_deep_records_differ() {
+    let exit_code = eval_exit_code(r#"'{"a":1}' from-json '{"a":2}' from-json eq-deep?"#);
+    assert_eq!(exit_code, 1);
+}
+
+#[test]
+fn test_compare_numbers_and_strings() {
+    assert_eq!(eval("1 2 compare").unwrap(), "-1");
+    assert_eq!(eval("10 9 compare").unwrap(), "1");
+    assert_eq!(eval("3 3.0 compare").unwrap(), "0");
+    assert_eq!(eval(r#""apple" "banana" compare"#).unwrap(), "-1");
+}
+
+#[test]
+fn test_compare_lists_lexicographic() {
+    assert_eq!(
+        eval("'[1,2]' from-json '[1,3]' from-json compare").unwrap(),
+        "-1"
+    );
+    assert_eq!(
+        eval("'[1,2,0]' from-json '[1,2]' from-json compare").unwrap(),
+        "1"
+    );
+}
+
+#[test]
+fn test_compare_records_is_type_error() {
+    assert!(eval(r#"'{"a":1}' from-json '{"a":1}' from-json compare"#).is_err());
+}
+
+#[test]
+fn test_cmp_is_still_the_file_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let f1 = dir.path().join("f1");
+    let f2 = dir.path().join("f2");
+    std::fs::write(&f1, "one\n").unwrap();
+    std::fs::write(&f2, "two\n").unwrap();
+    let exit_code = eval_exit_code(&format!("{} {} cmp", f2.display(), f1.display()));
+    assert_eq!(exit_code, 1, "cmp compares the two files");
+    let exit_code = eval_exit_code(&format!("{} {} cmp", f1.display(), f1.display()));
+    assert_eq!(exit_code, 0);
+}
@@ -0,0 +1,88 @@
+//! Tests for where stack values came from
+
+mod common;
+use common::{lex, parse, Evaluator};
+use hsab::Origin;
+
+fn run(source: &str) -> Evaluator {
+    let mut evaluator = Evaluator::new();
+    let program = parse(lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap();
+    evaluator
+}
+
+fn origins(evaluator: &Evaluator) -> Vec<Option<Origin>> {
+    (0..evaluator.stack().len())
+        .rev()
+        .map(|i| evaluator.origin(i))
+        .collect()
+}
+
+#[test]
+fn test_origin_of_typed_output_and_glob() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "").unwrap();
+    let evaluator = run(&format!(
+        r#"1 expr word "quoted" "{}/*.txt" glob"#,
+        dir.path().display()
+    ));
+    assert_eq!(
+        origins(&evaluator),
+        [
+            Some(Origin::Output),
+            Some(Origin::Typed),
+            Some(Origin::Typed),
+            Some(Origin::Glob)
+        ]
+    );
+    assert_eq!(Origin::Glob.to_string(), "glob");
+}
+
+#[test]
+fn test_computed_values_have_no_origin() {
+    let evaluator = run(r#""a" "b" suffix 1 2 plus"#);
+    assert_eq!(origins(&evaluator), [None, None]);
+}
+
+#[test]
+fn test_origin_moves_with_stack_operations() {
+    // Commands take every argument below them, so they go first
+    let evaluator = run("1 expr a dup");
+    assert_eq!(
+        origins(&evaluator),
+        [
+            Some(Origin::Output),
+            Some(Origin::Typed),
+            Some(Origin::Typed)
+        ]
+    );
+
+    let evaluator = run("1 expr a swap");
+    assert_eq!(
+        origins(&evaluator),
+        [Some(Origin::Typed), Some(Origin::Output)]
+    );
+
+    let evaluator = run(r#"1 expr a "b" "c" suffix rot"#);
+    assert_eq!(
+        origins(&evaluator),
+        [Some(Origin::Typed), None, Some(Origin::Output)]
+    );
+
+    let evaluator = run("1 expr a over");
+    assert_eq!(
+        origins(&evaluator),
+        [
+            Some(Origin::Output),
+            Some(Origin::Typed),
+            Some(Origin::Output)
+        ]
+    );
+}
+
+#[test]
+fn test_replaced_value_loses_origin() {
+    // The typed "a" is popped and a computed value takes its place
+    let evaluator = run(r#"a drop "x" "y" suffix"#);
+    assert_eq!(origins(&evaluator), [None]);
+}
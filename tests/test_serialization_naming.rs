@@ -301,3 +301,26 @@ fn test_kv_roundtrip_from_into() {
         output
     );
 }
+
+// === Spreadsheets ===
+
+#[cfg(not(feature = "xlsx"))]
+#[test]
+fn test_to_xlsx_needs_the_feature() {
+    let err = eval(r#"marker "a" 1 record table "out.xlsx" to-xlsx"#).unwrap_err();
+    assert!(err.contains("--features xlsx"), "{}", err);
+}
+
+#[cfg(feature = "xlsx")]
+#[test]
+fn test_to_xlsx_writes_a_workbook() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.xlsx");
+    eval(&format!(
+        r#"marker "name" "alice" "age" 30 record table "{}" to-xlsx"#,
+        path.display()
+    ))
+    .unwrap();
+    // An xlsx file is a zip archive
+    assert!(std::fs::read(&path).unwrap().starts_with(b"PK"));
+}
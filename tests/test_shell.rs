@@ -20,6 +20,99 @@ fn test_pipe_chained() {
     let _ = output;
 }
 
+#[test]
+fn test_pipestatus_records_every_stage() {
+    let output = eval(r#""exit 3" -c sh #[cat] | #[wc] | drop pipestatus"#).unwrap();
+    assert_eq!(output, "3\n0\n0");
+}
+
+#[test]
+fn test_pipefail_reports_first_failing_stage() {
+    // Without pipefail the successful consumer hides the failure
+    assert_eq!(eval_exit_code(r#""exit 3" -c sh #[cat] |"#), 0);
+    assert_eq!(
+        eval_exit_code(r#""on" pipefail "exit 3" -c sh #[cat] | #[false] |"#),
+        3
+    );
+    assert_eq!(
+        eval_exit_code(r#""on" pipefail "off" pipefail "exit 3" -c sh #[cat] |"#),
+        0
+    );
+    // A literal input has no producer to fail
+    assert_eq!(eval_exit_code(r#""on" pipefail "x" #[cat] |"#), 0);
+}
+
+#[test]
+fn test_pipe_threads_values_to_builtins() {
+    // A Table reaches select as a Table, not as its text
+    let output = eval(r#""name,size\na,1\nb,2" from-csv #[[name] select] | to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"[{"name":"a"},{"name":"b"}]"#);
+    // A structured value goes to a builtin even when a program shares its name
+    assert_eq!(eval("[3 1 2] #[sort] | to-json").unwrap().trim(), "[1,2,3]");
+    // Definitions get the value too, and chained pipes keep every status
+    assert_eq!(
+        eval("#[list-len] :count [3 1 2] #[sort] | #[count] | pipestatus to-json").unwrap(),
+        "3\n[0,0,0]"
+    );
+}
+
+#[test]
+fn test_pipe_gives_text_to_programs() {
+    assert_eq!(eval(r#""b\na" #[sort] |"#).unwrap(), "a\nb");
+    assert_eq!(eval(r#"[1 2] #[wc -l] |"#).unwrap().trim(), "1");
+}
+
+#[test]
+fn test_pipe_streams_between_programs() {
+    // yes never exits; head only sees its lines if they arrive as written
+    assert_eq!(eval("yes #[-n 2 head] |").unwrap(), "y\ny");
+    // Both processes' exit codes land in pipestatus
+    assert_eq!(
+        eval(r#""exit 3" -c sh #[cat] | pipestatus to-json"#).unwrap(),
+        "[3,0]"
+    );
+    // A streamed pipe can be extended by the next one
+    assert_eq!(
+        eval(r#""a\nb\n" printf #[-v a grep] | #[-c wc] | pipestatus to-json"#)
+            .unwrap()
+            .split_whitespace()
+            .collect::<Vec<_>>(),
+        ["1", "[0,0,0]"]
+    );
+}
+
+#[test]
+fn test_chained_pipes_stream_every_stage() {
+    // Each stage is a process of its own; yes ends when the chain does
+    let output = eval("yes #[-n 5 head] | #[-n 2 tail] | pipestatus to-json").unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[..2], ["y", "y"]);
+    assert!(lines[2].ends_with(",0,0]"), "{}", output);
+    assert_eq!(lines[2].matches(',').count(), 2, "{}", output);
+}
+
+#[test]
+fn test_pipeline_of_blocks() {
+    assert_eq!(
+        eval("#[yes] #[-n 3 head] #[-l wc] pipeline")
+            .unwrap()
+            .trim(),
+        "3"
+    );
+    assert_eq!(
+        eval(r#"#[-c "exit 4" sh] #[cat] pipeline pipestatus to-json"#).unwrap(),
+        "[4,0]"
+    );
+    // pipefail makes the first failing stage the pipeline's exit code
+    assert_eq!(eval_exit_code(r#"#[-c "exit 4" sh] #[cat] pipeline"#), 0);
+    assert_eq!(
+        eval_exit_code(r#""on" pipefail #[-c "exit 4" sh] #[cat] pipeline"#),
+        4
+    );
+    let err = eval("#[yes] #[select] pipeline").unwrap_err();
+    assert!(err.contains("select isn't a program"), "{}", err);
+}
+
 #[test]
 fn test_redirect_write() {
     use std::fs;
@@ -242,6 +335,25 @@ fn test_stdin_redirect() {
     // temp_file auto-cleans up on drop
 }
 
+#[test]
+fn test_here_string_feeds_stdin() {
+    // A final newline is added, as in bash
+    assert_eq!(eval(r#""some text" #[-l wc] <<<"#).unwrap().trim(), "1");
+    assert_eq!(eval(r#""b\na" #[sort] <<<"#).unwrap(), "a\nb");
+    assert_eq!(eval(r#""one\n" #[-c wc] <<<"#).unwrap().trim(), "4");
+    // Even a List goes in as text, not to hsab's sort
+    assert_eq!(eval(r#"[3 1 2] #[-n sort] <<<"#).unwrap(), "1\n2\n3");
+    assert_eq!(eval_exit_code(r#""x" #[-c "cat; exit 3" sh] <<<"#), 3);
+}
+
+#[test]
+fn test_heredoc_drops_shared_indentation() {
+    let script = "\"\"\"\n    SELECT *\n      FROM t\n\n    \"\"\" #[cat] heredoc";
+    assert_eq!(eval(script).unwrap(), "SELECT *\n  FROM t");
+    let script = "\"\"\"\n    a\n    b\n    \"\"\" #[-l wc] heredoc";
+    assert_eq!(eval(script).unwrap().trim(), "2");
+}
+
 #[test]
 fn test_stderr_to_stdout_redirect() {
     // 2>&1 should merge stderr into stdout
@@ -414,6 +526,76 @@ fn test_printf_percent_percent() {
     assert!(output.contains("%"));
 }
 
+#[test]
+fn test_printf_width_precision() {
+    let output = eval(r#"3.14159 "%08.2f|" printf"#).unwrap();
+    assert_eq!(output, "00003.14|");
+}
+
+#[test]
+fn test_printf_left_and_right_align() {
+    let output = eval(r#"ab "[%-5s]" printf"#).unwrap();
+    assert_eq!(output, "[ab   ]");
+    let output = eval(r#"ab "[%5s]" printf"#).unwrap();
+    assert_eq!(output, "[   ab]");
+}
+
+#[test]
+fn test_printf_zero_pad_negative() {
+    let output = eval(r#"-5 "%05d" printf"#).unwrap();
+    assert_eq!(output, "-0005");
+}
+
+#[test]
+fn test_printf_integers_are_exact() {
+    let output = eval(r#"99999999999999999999 "%d" printf"#).unwrap();
+    assert_eq!(output, "99999999999999999999");
+    let output = eval(r#"-99999999999999999999 "%d" printf"#).unwrap();
+    assert_eq!(output, "-99999999999999999999");
+    let output = eval(r#"9223372036854775807 1 plus "%d" printf"#).unwrap();
+    assert_eq!(output, "9223372036854775808");
+    let output = eval(r#"18446744073709551616 "%x" printf"#).unwrap();
+    assert_eq!(output, "10000000000000000");
+    let output = eval(r#"18446744073709551616 18446744073709551616 "%X %o" printf"#).unwrap();
+    assert_eq!(output, "10000000000000000 2000000000000000000000");
+    let output = eval(r#"-1 "%x" printf"#).unwrap();
+    assert_eq!(output, "ffffffffffffffff");
+}
+
+#[test]
+fn test_printf_integer_precision() {
+    let output = eval(r#"42 "%.5d" printf"#).unwrap();
+    assert_eq!(output, "00042");
+    let output = eval(r#"-42 "[%8.5d]" printf"#).unwrap();
+    assert_eq!(output, "[  -00042]");
+    let output = eval(r#"255 "%.4x" printf"#).unwrap();
+    assert_eq!(output, "00ff");
+    let output = eval(r#"99999999999999999999 "%.22d" printf"#).unwrap();
+    assert_eq!(output, "0099999999999999999999");
+}
+
+#[test]
+fn test_printf_string_precision() {
+    let output = eval(r#"abcdef "%.3s" printf"#).unwrap();
+    assert_eq!(output, "abc");
+}
+
+#[test]
+fn test_printf_json_structured() {
+    let output = eval(r#"[1 2] "%j" printf"#).unwrap();
+    assert_eq!(output, "[1,2]");
+    let output = eval(r#"{ a 1 } "%j" printf"#).unwrap();
+    assert_eq!(output, r#"{"a":1}"#);
+}
+
+#[test]
+fn test_printf_shell_quote() {
+    let output = eval(r#""it's here" "%q" printf"#).unwrap();
+    assert_eq!(output, r#"'it'\''s here'"#);
+    let output = eval(r#"plain "%q" printf"#).unwrap();
+    assert_eq!(output, "plain");
+}
+
 #[test]
 fn test_background_simple() {
     // Background should run without blocking
@@ -632,6 +814,25 @@ mod command_boundary {
         }
     }
 
+    #[test]
+    fn test_with_stderr_keeps_streams_apart() {
+        let stack = eval_stack(r#"#["echo out; echo err >&2" "-c" sh] with-stderr"#);
+        match &stack[0] {
+            Value::Map(record) => {
+                assert_eq!(record.get("stdout"), Some(&Value::Literal("out".into())));
+                assert_eq!(record.get("stderr"), Some(&Value::Literal("err".into())));
+                assert_eq!(record.get("exit"), Some(&Value::Int(0)));
+            }
+            other => panic!("expected Map, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_with_stderr_reports_failure() {
+        let stack = eval_stack(r#"#["echo oops >&2; exit 3" "-c" sh] with-stderr "exit" get"#);
+        assert_eq!(stack.last(), Some(&Value::Int(3)));
+    }
+
     #[test]
     fn test_empty_stdout_still_nil() {
         let stack = eval_stack(r#""exit 0" "-c" sh"#);
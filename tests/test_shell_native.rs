@@ -6,7 +6,7 @@ use std::fs;
 use std::path::Path;
 
 mod common;
-use common::eval;
+use common::{eval, lex, parse, Evaluator};
 
 // ============================================
 // File Creation
@@ -182,6 +182,56 @@ fn test_rm_nonexistent_returns_nil() {
     assert!(output.contains("nil") || output.trim() == "0" || output.is_empty());
 }
 
+/// Twelve files in a fresh directory, for glob confirmation
+fn glob_fixture() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..12 {
+        fs::write(dir.path().join(format!("f{}.log", i)), "x").unwrap();
+    }
+    dir
+}
+
+fn eval_with(evaluator: &mut Evaluator, source: &str) -> String {
+    let program = parse(lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap().output
+}
+
+#[test]
+fn test_rm_glob_only_asks_when_interactive() {
+    let dir = glob_fixture();
+    let output = eval(&format!("\"{}/*.log\" rm", dir.path().display())).unwrap();
+    assert_eq!(output, "12");
+}
+
+#[test]
+fn test_rm_glob_under_threshold_or_forced_skips_asking() {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_interactive(true);
+
+    let dir = glob_fixture();
+    eval_with(&mut evaluator, "20 confirm-glob");
+    let output = eval_with(
+        &mut evaluator,
+        &format!("\"{}/*.log\" rm", dir.path().display()),
+    );
+    assert_eq!(output, "12");
+
+    evaluator.clear_stack();
+    let dir = glob_fixture();
+    eval_with(&mut evaluator, "10 confirm-glob");
+    let output = eval_with(
+        &mut evaluator,
+        &format!("force \"{}/*.log\" rm", dir.path().display()),
+    );
+    assert_eq!(output, "12");
+}
+
+#[test]
+fn test_confirm_glob_rejects_negative() {
+    let err = eval("-1 confirm-glob").unwrap_err().to_string();
+    assert!(err.contains("can't be negative"), "{}", err);
+}
+
 #[test]
 fn test_realpath_returns_canonical() {
     // Use a known path
@@ -401,3 +451,279 @@ fn test_history_t_returns_table() {
     let output = eval("history-t typeof").unwrap();
     assert_eq!(output.trim(), "table");
 }
+
+// ============================================
+// Users and Permissions
+// ============================================
+
+#[cfg(unix)]
+fn id(flag: &str) -> String {
+    let out = std::process::Command::new("id").arg(flag).output().unwrap();
+    String::from_utf8(out.stdout).unwrap().trim().to_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_identity_matches_id() {
+    assert_eq!(eval(r#"id-record "uid" get"#).unwrap(), id("-u"));
+    assert_eq!(eval(r#"id-record "gid" get"#).unwrap(), id("-g"));
+    assert_eq!(eval("whoami").unwrap(), id("-un"));
+    assert_eq!(eval(r#"id-record "user" get"#).unwrap(), id("-un"));
+    let groups = eval(r#"id-record "groups" get"#).unwrap();
+    for gid in id("-G").split_whitespace() {
+        assert!(
+            groups.lines().any(|g| g == gid),
+            "{} not in {}",
+            gid,
+            groups
+        );
+    }
+
+    let root = id("-u") == "0";
+    assert_eq!(common::eval_exit_code("is-root?"), if root { 0 } else { 1 });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_chmod_sym_applies_symbolic_modes() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("script.sh");
+    fs::write(&file, "").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+    let mode = || fs::metadata(&file).unwrap().permissions().mode() & 0o7777;
+    let path = file.display();
+
+    let output = eval(&format!(r#""u+x,go-r" "{}" chmod-sym"#, path)).unwrap();
+    assert_eq!(output, file.to_string_lossy());
+    assert_eq!(mode(), 0o700);
+    eval(&format!(r#""a=r,u+w" "{}" chmod-sym"#, path)).unwrap();
+    assert_eq!(mode(), 0o644);
+    eval(&format!(r#""g=u" "{}" chmod-sym"#, path)).unwrap();
+    assert_eq!(mode(), 0o664);
+    eval(&format!(r#""a+X" "{}" chmod-sym"#, path)).unwrap();
+    assert_eq!(mode(), 0o664);
+    eval(&format!(r#""750" "{}" chmod-sym"#, path)).unwrap();
+    assert_eq!(mode(), 0o750);
+
+    let err = eval(&format!(r#""u+q" "{}" chmod-sym"#, path)).unwrap_err();
+    assert!(err.contains("invalid mode 'u+q'"), "{}", err);
+    let output = eval(r#""u+x" "/nonexistent/hsab" chmod-sym"#).unwrap();
+    assert!(output.is_empty() || output.contains("nil"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_chown_resolves_names_and_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("owned.txt");
+    fs::write(&file, "").unwrap();
+    let path = file.display();
+
+    // Giving a file to yourself always works
+    let spec = format!("{}:{}", id("-un"), id("-g"));
+    let output = eval(&format!(r#""{}" "{}" chown"#, spec, path)).unwrap();
+    assert_eq!(output, file.to_string_lossy());
+    let output = eval(&format!(r#"":{}" "{}" chown"#, id("-gn"), path)).unwrap();
+    assert_eq!(output, file.to_string_lossy());
+
+    let err = eval(&format!(r#""no-such-user-hsab" "{}" chown"#, path)).unwrap_err();
+    assert!(err.contains("unknown user 'no-such-user-hsab'"), "{}", err);
+}
+
+// ============================================
+// Disk Space
+// ============================================
+
+#[cfg(unix)]
+#[test]
+fn test_min_free_compares_against_sizes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().display();
+
+    let code = |size: &str| common::eval_exit_code(&format!(r#""{}" "{}" min-free?"#, path, size));
+    assert_eq!(code("1"), 0);
+    assert_eq!(code("1K"), 0);
+    assert_eq!(code("1000P"), 1);
+    assert_eq!(code("1000 PiB"), 1);
+    assert_eq!(
+        eval(&format!(r#""{}" "1000PB" min-free?"#, path)).unwrap(),
+        "false"
+    );
+    assert_eq!(
+        common::eval_exit_code(r#""/nonexistent/hsab" "1" min-free?"#),
+        1
+    );
+
+    let err = eval(&format!(r#""{}" "5X" min-free?"#, path)).unwrap_err();
+    assert!(err.contains("invalid size '5X'"), "{}", err);
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn test_mount_point_and_fs_type() {
+    let dir = tempfile::tempdir().unwrap();
+    let mount = eval(&format!(r#""{}" mount-point"#, dir.path().display())).unwrap();
+    let canonical = dir.path().canonicalize().unwrap();
+    assert!(
+        canonical.starts_with(&mount),
+        "{} not under {}",
+        canonical.display(),
+        mount
+    );
+    assert_eq!(eval(r#""/" mount-point"#).unwrap(), "/");
+
+    let fs_type = eval(&format!(r#""{}" fs-type"#, dir.path().display())).unwrap();
+    assert!(!fs_type.is_empty() && !fs_type.contains(char::is_whitespace));
+
+    let output = eval(r#""/nonexistent/hsab" mount-point"#).unwrap();
+    assert!(output.is_empty() || output.contains("nil"));
+}
+
+// ============================================
+// System Information
+// ============================================
+
+#[test]
+fn test_sysinfo_record() {
+    assert_eq!(eval("sysinfo typeof").unwrap(), "record");
+    assert_eq!(eval(r#"sysinfo "os" get"#).unwrap(), std::env::consts::OS);
+    assert_eq!(
+        eval(r#"sysinfo "arch" get"#).unwrap(),
+        std::env::consts::ARCH
+    );
+    let cpus: usize = eval(r#"sysinfo "cpu_count" get"#).unwrap().parse().unwrap();
+    assert!(cpus >= 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_sysinfo_linux_figures() {
+    let uname = std::process::Command::new("uname")
+        .arg("-r")
+        .output()
+        .unwrap();
+    let release = String::from_utf8(uname.stdout).unwrap();
+    assert_eq!(eval(r#"sysinfo "kernel" get"#).unwrap(), release.trim());
+
+    let total: u64 = eval(r#"sysinfo "mem_total" get"#).unwrap().parse().unwrap();
+    let free: u64 = eval(r#"sysinfo "mem_free" get"#).unwrap().parse().unwrap();
+    assert!(total > 0 && free <= total);
+    let uptime: u64 = eval(r#"sysinfo "uptime" get"#).unwrap().parse().unwrap();
+    assert!(uptime > 0);
+    assert_eq!(eval(r#"sysinfo "loadavg" get count"#).unwrap(), "3");
+}
+
+// ============================================
+// Copy, Move, and Remove
+// ============================================
+
+fn make_tree(root: &Path) {
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("a.txt"), "hello\n").unwrap();
+    fs::write(root.join("sub").join("b.txt"), "abc\n").unwrap();
+}
+
+#[test]
+fn test_copy_tree_returns_tally() {
+    let dir = tempfile::tempdir().unwrap();
+    let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+    make_tree(&src);
+
+    let cmd = format!(r#""{}" "{}" copy"#, src.display(), dst.display());
+    assert_eq!(eval(&format!("{} \"files\" get", cmd)).unwrap(), "2");
+    assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "abc\n");
+
+    // An existing directory receives the source inside it
+    let out = eval(&format!("{} to-json", cmd)).unwrap();
+    let canonical = dst.canonicalize().unwrap().join("src");
+    assert_eq!(
+        out,
+        format!(
+            r#"{{"path":"{}","files":2,"dirs":2,"bytes":10}}"#,
+            canonical.display()
+        )
+    );
+    assert!(eval(&format!(
+        r#""{}/missing" "{}" copy"#,
+        src.display(),
+        dst.display()
+    ))
+    .unwrap()
+    .is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_copy_keeps_permissions_and_symlinks() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+    make_tree(&src);
+    fs::set_permissions(src.join("a.txt"), fs::Permissions::from_mode(0o640)).unwrap();
+    fs::set_permissions(src.join("sub"), fs::Permissions::from_mode(0o750)).unwrap();
+    std::os::unix::fs::symlink("a.txt", src.join("link")).unwrap();
+
+    eval(&format!(r#""{}" "{}" copy"#, src.display(), dst.display())).unwrap();
+    let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&dst.join("a.txt")), 0o640);
+    assert_eq!(mode(&dst.join("sub")), 0o750);
+    assert_eq!(fs::read_link(dst.join("link")).unwrap(), Path::new("a.txt"));
+}
+
+#[test]
+fn test_move_and_remove_trees() {
+    let dir = tempfile::tempdir().unwrap();
+    let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+    make_tree(&src);
+
+    let out = eval(&format!(
+        r#""{}" "{}" move "bytes" get"#,
+        src.display(),
+        dst.display()
+    ))
+    .unwrap();
+    assert_eq!(out, "10");
+    assert!(!src.exists());
+    assert!(dst.join("sub/b.txt").exists());
+
+    let out = eval(&format!(r#""{}" remove "dirs" get"#, dst.display())).unwrap();
+    assert_eq!(out, "2");
+    assert!(!dst.exists());
+    assert!(eval(&format!(r#""{}" remove"#, dst.display()))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_copy_reports_progress() {
+    use std::sync::{Arc, Mutex};
+    let dir = tempfile::tempdir().unwrap();
+    let (src, dst) = (dir.path().join("src"), dir.path().join("dst"));
+    make_tree(&src);
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut evaluator = common::Evaluator::new();
+    let log = seen.clone();
+    evaluator.register_builtin("log-progress", move |eval| {
+        let record = eval.pop_value().unwrap();
+        log.lock().unwrap().push(hsab::ast::value_to_json(&record));
+        Ok(())
+    });
+    let source = format!(
+        r#""{}" "{}" #[log-progress] copy"#,
+        src.display(),
+        dst.display()
+    );
+    let program = common::parse(common::lex(&source).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0]["files"], 1);
+    assert_eq!(seen[0]["bytes"], 6);
+    assert_eq!(seen[1]["files"], 2);
+    assert_eq!(seen[1]["total_files"], 2);
+    assert_eq!(seen[1]["total_bytes"], 10);
+}
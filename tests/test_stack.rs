@@ -151,18 +151,14 @@ fn test_stack_underflow_drop() {
 }
 
 #[test]
-fn test_subst_creates_file() {
-    use std::path::Path;
-
+fn test_subst_pushes_dev_fd_path() {
     let output = eval("#[hello echo] subst").unwrap();
     let path = output.trim();
     assert!(
-        Path::new(path).exists() || path.contains("hsab_subst"),
-        "subst should create a temp file: {}",
+        path.starts_with("/dev/fd/"),
+        "subst should push a /dev/fd path: {}",
         path
     );
-    // Clean up
-    std::fs::remove_file(path).ok();
 }
 
 #[test]
@@ -175,6 +171,20 @@ fn test_subst_content() {
     );
 }
 
+#[test]
+fn test_subst_streams_endless_producer() {
+    // yes never finishes; the reader only takes what it needs
+    let output = eval("#[yes] subst 3 -n head").unwrap();
+    assert_eq!(output.trim(), "y\ny\ny");
+}
+
+#[test]
+fn test_subst_two_at_once() {
+    // The top of the stack is cat's first argument
+    let output = eval("#[a echo] subst #[b echo] subst cat").unwrap();
+    assert_eq!(output.trim(), "b\na");
+}
+
 #[test]
 fn test_read_pushes_to_stack() {
     // read without args should push input to stack
@@ -157,3 +157,40 @@ fn test_five_num_count() {
     let output = eval("'[1,2,3,4,5,6,7,8,9,10]' from-json five-num count").unwrap();
     assert_eq!(output.trim(), "5");
 }
+
+#[test]
+fn test_series_keeps_a_bounded_buffer() {
+    // On the stack, started from an empty list
+    let output = eval(r#"[] 1 series 2 2 series 3 2 series "value" get to-json"#).unwrap();
+    assert_eq!(output.trim(), "[2,3]");
+    let output = eval(r#"[] 4 series "time" get 0 nth len"#).unwrap();
+    assert_eq!(output.trim(), "23");
+
+    // Under a name, which outlives each pass of a loop
+    let output =
+        eval(r#"#["load" 1.5 series] 3 times "load" "2" 3 series "load" series-stats to-json"#)
+            .unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"{"count":3,"first":1.5,"last":2.0,"min":1.5,"max":2.0,"mean":1.6666666666666667,"stdev":0.23570226039551584,"change":0.5}"#
+    );
+
+    assert!(eval(r#"[] "high" series"#).is_err());
+    assert!(eval(r#""missing" series-stats"#).is_err());
+}
+
+#[test]
+fn test_sparkline() {
+    assert_eq!(eval("[1 2 3 4 5 6 7 8] sparkline").unwrap(), "▁▂▃▄▅▆▇█");
+    assert_eq!(
+        eval("[] 1 series 9 series 5 series sparkline").unwrap(),
+        "▁█▅"
+    );
+    // A flat line sits in the middle
+    assert_eq!(eval("[3 3 3] sparkline").unwrap(), "▄▄▄");
+    let output = eval("[] series-stats to-json").unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"{"count":0,"first":null,"last":null,"min":null,"max":null,"mean":null,"stdev":null,"change":null}"#
+    );
+}
@@ -189,6 +189,73 @@ fn test_interpolation_escaped() {
     );
 }
 
+#[test]
+fn test_variables_inside_unquoted_words() {
+    std::env::set_var("HSAB_WORD_DIR", "/opt/tool");
+    std::env::set_var("HSAB_WORD_VER", "2");
+    let output = eval(r#"$HSAB_WORD_DIR/bin echo"#).unwrap();
+    assert_eq!(output.trim(), "/opt/tool/bin");
+    let output = eval(r#"v$HSAB_WORD_VER.${HSAB_WORD_VER}-rc echo"#).unwrap();
+    assert_eq!(output.trim(), "v2.2-rc");
+    // Inside a block run as a command
+    let output = eval(r#""in" #[lib-$HSAB_WORD_VER echo] |"#).unwrap();
+    assert_eq!(output.trim(), "lib-2");
+    // Escaped, unset, and lone dollars
+    let output = eval(r#"\$HSAB_WORD_VER/x$HSAB_WORD_UNSET/end$ echo"#).unwrap();
+    assert_eq!(output.trim(), "$HSAB_WORD_VER/x/end$");
+    std::env::remove_var("HSAB_WORD_DIR");
+    std::env::remove_var("HSAB_WORD_VER");
+}
+
+#[test]
+fn test_interpolation_stack_positions() {
+    let output = eval(r#"src.txt dst.txt "${.1} copied to ${.0}""#).unwrap();
+    assert_eq!(output.lines().last(), Some("src.txt copied to dst.txt"));
+}
+
+#[test]
+fn test_interpolation_stack_not_consumed() {
+    let output = eval(r#"a "${.0}!" drop"#).unwrap();
+    assert_eq!(output.trim(), "a");
+}
+
+#[test]
+fn test_interpolation_stack_out_of_range() {
+    let output = eval(r#""[${.3}]""#).unwrap();
+    assert_eq!(output.trim(), "[]");
+}
+
+#[test]
+fn test_interpolation_nested_expression() {
+    let output = eval(r#""sum: ${= 1 2 plus}, echo: ${= hi echo}""#).unwrap();
+    assert_eq!(output.trim(), "sum: 3, echo: hi");
+}
+
+#[test]
+fn test_interpolation_nested_keeps_outer_stack() {
+    let output = eval(r#"x "${= 1 2 plus}" drop"#).unwrap();
+    assert_eq!(output.trim(), "x");
+}
+
+#[test]
+fn test_interpolation_nested_record_braces() {
+    let output = eval(r#""${= { a 1 } 'a' get}""#).unwrap();
+    assert_eq!(output.trim(), "1");
+}
+
+#[test]
+fn test_interpolation_unterminated_expression_is_error() {
+    assert!(eval(r#""total: ${= 1 2 plus""#).is_err());
+}
+
+#[test]
+fn test_interpolation_leaves_command_substitution_alone() {
+    let output = eval(r#""echo $(x touch)""#).unwrap();
+    assert_eq!(output.trim(), "echo $(x touch)");
+    let output = eval(r#""echo $(printf hi)" -c sh"#).unwrap();
+    assert_eq!(output.trim(), "hi");
+}
+
 #[test]
 fn test_reext_basic() {
     let output = eval(r#""file.txt" ".md" reext"#).unwrap();
@@ -763,3 +830,274 @@ fn test_str_replace_at_end() {
     let output = eval(r#""abc" "c" "X" str-replace"#).unwrap();
     assert_eq!(output.trim(), "abX");
 }
+
+#[test]
+fn test_strip_ansi() {
+    let output = eval(r#""\e[1;31mwarn\e[0m: x" strip-ansi"#).unwrap();
+    assert_eq!(output.trim(), "warn: x");
+}
+
+#[test]
+fn test_ansi_length() {
+    let output = eval(r#""\e[32m日本\e[0m ok" ansi-length"#).unwrap();
+    assert_eq!(output.trim(), "7");
+}
+
+#[test]
+fn test_colorize_plain_when_not_tty() {
+    // Test stdout is captured, so colorize must not emit escapes
+    let output = eval(r#"done "bold green" colorize"#).unwrap();
+    assert_eq!(output.trim(), "done");
+}
+
+#[test]
+fn test_colorize_unknown_color() {
+    assert!(eval(r#"x purple colorize"#).is_err());
+}
+
+#[test]
+fn test_make_patch_and_apply_patch_round_trip() {
+    let patch =
+        eval(r#""a\nb\nc\nd\ne\nf\ng\nh\n" "a\nb\nc\nD\ne\nf\ng\nh\ni\n" make-patch"#).unwrap();
+    assert_eq!(
+        patch,
+        "--- old\n+++ new\n@@ -1,8 +1,9 @@\n a\n b\n c\n-d\n+D\n e\n f\n g\n h\n+i\n"
+    );
+    let output = eval(
+        r#""a\nb\nc\nd\ne\nf\ng\nh\n" dup "a\nb\nc\nD\ne\nf\ng\nh\ni\n" make-patch apply-patch"#,
+    )
+    .unwrap();
+    assert_eq!(output, "a\nb\nc\nD\ne\nf\ng\nh\ni\n");
+
+    // "\ No newline at end of file" holds on either side
+    assert_eq!(
+        eval(r#""a\nb\nc\n" dup "a\nB\nc" make-patch apply-patch to-json"#).unwrap(),
+        r#""a\nB\nc""#
+    );
+    assert_eq!(
+        eval(r#""a\nb\nc" dup "a\nB\nc\n" make-patch apply-patch to-json"#).unwrap(),
+        r#""a\nB\nc\n""#
+    );
+    assert_eq!(
+        eval(r#""a\nb\nc" dup "a\nB\nc" make-patch apply-patch to-json"#).unwrap(),
+        r#""a\nB\nc""#
+    );
+    // Same texts make no patch, and an empty patch changes nothing
+    assert_eq!(eval(r#""x" "x" make-patch"#).unwrap(), "");
+    assert_eq!(eval(r#""x" "" apply-patch"#).unwrap(), "x");
+}
+
+#[test]
+fn test_apply_patch_keeps_missing_final_newline() {
+    let output = eval(r#""one\ntwo" dup "one\n2" make-patch"#).unwrap();
+    assert!(
+        output.contains("-two\n\\ No newline at end of file\n+2\n\\ No newline at end of file\n"),
+        "{}",
+        output
+    );
+    let output = eval(r#""one\ntwo" dup "one\n2" make-patch apply-patch"#).unwrap();
+    assert_eq!(output, "one\n2");
+}
+
+#[test]
+fn test_apply_patch_finds_moved_hunk_and_rejects_mismatch() {
+    // Two lines were added at the top since the patch was made
+    let output = eval(
+        r#""new1\nnew2\nport = 80\n" "--- old\n+++ new\n@@ -1 +1 @@\n-port = 80\n+port = 8080\n" apply-patch"#,
+    )
+    .unwrap();
+    assert_eq!(output, "new1\nnew2\nport = 8080\n");
+
+    // A patch of text that lost its final newline keeps the text's
+    let output =
+        eval(r#""a\nport = 80\n" "a\nport = 80" "a\nport = 8080" make-patch apply-patch"#).unwrap();
+    assert_eq!(output, "a\nport = 8080\n");
+
+    let err =
+        eval(r#""port = 81\n" "@@ -1 +1 @@\n-port = 80\n+port = 8080\n" apply-patch"#).unwrap_err();
+    assert!(err.contains("hunk 1 doesn't match"), "{}", err);
+}
+
+#[test]
+fn test_apply_patch_file_keeps_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("app.toml");
+    std::fs::write(&config, "name = \"app\"\nport = 80\n").unwrap();
+    let path = config.display();
+    eval(&format!(
+        r#""{path}" "name = \"app\"\nport = 80\n" "name = \"app\"\nport = 8080\n" make-patch apply-patch-file"#
+    ))
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "name = \"app\"\nport = 8080\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("app.toml.orig")).unwrap(),
+        "name = \"app\"\nport = 80\n"
+    );
+
+    // A patch that doesn't apply leaves the file alone
+    assert!(eval(&format!(
+        r#""{path}" "@@ -1 +1 @@\n-nope\n+x\n" apply-patch-file"#
+    ))
+    .is_err());
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "name = \"app\"\nport = 8080\n"
+    );
+}
+
+#[test]
+fn test_merge3_takes_each_sides_changes() {
+    use hsab::eval::merge3;
+
+    let base = "a\nb\nc\nd\ne\n";
+    let merge = merge3(
+        Some(base),
+        "a\nB\nc\nd\ne\n",
+        "a\nb\nc\nd\nE\nf\n",
+        ("ours", "theirs"),
+    );
+    assert_eq!(merge.text, "a\nB\nc\nd\nE\nf\n");
+    assert_eq!(merge.conflicts, 0);
+
+    let merge = merge3(
+        Some(base),
+        "a\nB\nc\nd\ne\n",
+        "a\nX\nc\nd\ne\n",
+        ("ours", "theirs"),
+    );
+    assert_eq!(
+        merge.text,
+        "a\n<<<<<<< ours\nB\n=======\nX\n>>>>>>> theirs\nc\nd\ne\n"
+    );
+    assert_eq!(merge.conflicts, 1);
+
+    // Without a base, a line only one side has is that side's addition
+    let merge = merge3(None, "a\nmine\nb\n", "a\nb\nnew\n", ("ours", "theirs"));
+    assert_eq!(merge.text, "a\nmine\nb\nnew\n");
+}
+
+#[test]
+fn test_edit_file_rewrites_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("app.conf");
+    std::fs::write(&config, "port = 80\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&config, std::fs::Permissions::from_mode(0o640)).unwrap();
+    }
+    let path = config.display();
+
+    let output = eval(&format!(
+        r#""{path}" #["80" "8080" str-replace] edit-file depth"#
+    ))
+    .unwrap();
+    assert_eq!(output.trim(), "0");
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "port = 8080\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&config).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640, "permissions are kept");
+    }
+    assert!(!dir.path().join("app.conf.bak").exists());
+
+    // With a suffix, the old contents are kept beside it
+    eval(&format!(
+        r#""{path}" #["8080" "9090" str-replace] ".bak" edit-file"#
+    ))
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "port = 9090\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("app.conf.bak")).unwrap(),
+        "port = 8080\n"
+    );
+}
+
+#[test]
+fn test_edit_file_failing_block_changes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("app.conf");
+    std::fs::write(&config, "port = 80\n").unwrap();
+    let path = config.display();
+
+    assert!(eval(&format!(r#""{path}" #[drop] edit-file"#)).is_err());
+    assert!(eval(&format!(r#""{path}" #["oops" throw] edit-file"#)).is_err());
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "port = 80\n");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_edit_lines_rewrites_matching_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("app.conf");
+    std::fs::write(
+        &config,
+        "# ports\r\nhttp = 80\r\nhttps = 443\r\nold = 1\r\nend",
+    )
+    .unwrap();
+    let path = config.display();
+
+    // The line is on top, its captures (whole match first) under it
+    eval(&format!(
+        r#""{path}" "^(\w+) = (\d+)$" #[drop dup 1 get " = 8" suffix swap 2 get suffix] edit-lines"#
+    ))
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "# ports\r\nhttp = 880\r\nhttps = 8443\r\nold = 81\r\nend"
+    );
+
+    // Leaving nothing deletes the line
+    eval(&format!(
+        r#""{path}" "^old" #[drop drop] ".bak" edit-lines"#
+    ))
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&config).unwrap(),
+        "# ports\r\nhttp = 880\r\nhttps = 8443\r\nend"
+    );
+    assert!(std::fs::read_to_string(dir.path().join("app.conf.bak"))
+        .unwrap()
+        .contains("old = 81"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_file_and_edit_lines_keep_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("real.conf");
+    let link = dir.path().join("app.conf");
+    std::fs::write(&target, "port = 80\n").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    let path = link.display();
+
+    eval(&format!(r#""{path}" #["80" "8080" str-replace] edit-file"#)).unwrap();
+    eval(&format!(
+        r#""{path}" "^port" #[swap drop "8080" "9090" str-replace] edit-lines"#
+    ))
+    .unwrap();
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "port = 9090\n");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_edit_lines_failing_block_changes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("app.conf");
+    std::fs::write(&config, "a\nb\n").unwrap();
+    let path = config.display();
+
+    let err = eval(&format!(r#""{path}" "b" #["oops" throw] edit-lines"#)).unwrap_err();
+    assert!(err.contains("line 2"), "{}", err);
+    assert!(eval(&format!(r#""{path}" "(" #[] edit-lines"#)).is_err());
+    assert_eq!(std::fs::read_to_string(&config).unwrap(), "a\nb\n");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -222,6 +222,16 @@ fn test_table_where_filter() {
     assert!(!output.contains("bob"), "bob should be filtered out");
 }
 
+#[test]
+fn test_where_on_record() {
+    // A record that matches is kept; one that doesn't becomes nil
+    let output =
+        eval(r#""name" "carol" "age" 35 record #["age" get 30 gt?] where "name" get"#).unwrap();
+    assert_eq!(output, "carol");
+    let output = eval(r#""name" "bob" "age" 25 record #["age" get 30 gt?] where typeof"#).unwrap();
+    assert_eq!(output, "nil");
+}
+
 #[test]
 fn test_table_sort_by() {
     let output = eval(
@@ -370,6 +380,58 @@ fn test_error_has_message() {
     );
 }
 
+#[test]
+fn test_validate_passes_the_record_through() {
+    let schema =
+        r#"{ name "string" port { type "int" min 1 max 65535 } debug "boolean?" tags ["string"] }"#;
+    let program = format!(
+        r#"{{ name "web" port 80 tags ["a" "b"] }} {} validate"#,
+        schema
+    );
+    assert_eq!(
+        eval(&format!("{} to-json", program)).unwrap().trim(),
+        r#"{"name":"web","port":80,"tags":["a","b"]}"#
+    );
+    assert_eq!(eval_exit_code(&program), 0);
+}
+
+#[test]
+fn test_validate_lists_every_violation() {
+    let program = r#"
+        { port "80" tags ["a" 3] level "loud" db { user "Bob" } }
+        { port "int" tags ["string"] level { type "string" one-of ["info" "warn"] }
+          db { host "string" user { type "string" pattern "^[a-z]+$" } } }
+        validate"#;
+    assert_eq!(eval_exit_code(program), 1);
+    let output = eval(&format!(r#"{} "message" get"#, program)).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"5 violations: port: expected int, got string; tags[1]: expected string, got int; level: "loud" is not one of "info", "warn"; db.host: missing; db.user: 'Bob' doesn't match /^[a-z]+$/"#
+    );
+    let output = eval(&format!(r#"{} "kind" get"#, program)).unwrap();
+    assert_eq!(output.trim(), "validation");
+}
+
+#[test]
+fn test_validate_checks_each_table_row() {
+    let output = eval(
+        r#"marker "a" 1 record marker "a" "x" record table { a "int" } validate "message" get"#,
+    )
+    .unwrap();
+    assert_eq!(
+        output.trim(),
+        "1 violation: [1].a: expected int, got string"
+    );
+}
+
+#[test]
+fn test_validate_rejects_a_bad_schema() {
+    let err = eval(r#"{ a 1 } { a "strin" } validate"#).unwrap_err();
+    assert!(err.contains("unknown type 'strin'"), "{}", err);
+    let err = eval(r#"{ a 1 } { a { type "int" minimum 1 } } validate"#).unwrap_err();
+    assert!(err.contains("unknown constraint 'minimum'"), "{}", err);
+}
+
 #[test]
 fn test_from_json_object() {
     let output = eval("'{\"name\":\"test\"}' from-json typeof").unwrap();
@@ -772,6 +834,92 @@ fn test_ls_table_with_path() {
     );
 }
 
+#[test]
+fn test_find_where_keeps_matching_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("src");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    for file in ["a.rs", "b.txt", "sub/c.rs"] {
+        std::fs::write(root.join(file), "x").unwrap();
+    }
+    let root = root.display();
+
+    let output = eval(&format!(
+        r#""{}" #["name" get ".rs" ends?] find-where "path" get"#,
+        root
+    ))
+    .unwrap();
+    assert_eq!(output, format!("{0}/a.rs\n{0}/sub/c.rs", root));
+
+    let output = eval(&format!(
+        r#""{}" #["type" get "dir" eq?] find-where "name" get"#,
+        root
+    ))
+    .unwrap();
+    assert_eq!(output, "sub");
+
+    let output = eval(&format!(r#""{}" #[false] find-where count"#, root)).unwrap();
+    assert_eq!(output, "0");
+}
+
+#[test]
+fn test_find_where_missing_dir_errors() {
+    let err = eval(r#""/nonexistent/hsab" #[true] find-where"#).unwrap_err();
+    assert!(err.to_string().contains("/nonexistent/hsab"), "{}", err);
+}
+
+#[test]
+fn test_grep_table_rows_per_match() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.rs"), "fn main() {}\nlet x = main;\n").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "nothing\n").unwrap();
+    let root = dir.path().display();
+
+    let output = eval(&format!(r#""m(ai)n" "{}" grep-table to-json"#, root)).unwrap();
+    let rows: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        rows,
+        serde_json::json!([
+            {"path": format!("{}/a.rs", root), "line": 1, "column": 4,
+             "match": "main", "context": "fn main() {}"},
+            {"path": format!("{}/a.rs", root), "line": 2, "column": 9,
+             "match": "main", "context": "let x = main;"},
+        ])
+    );
+
+    // A file can be searched on its own
+    let output = eval(&format!(
+        r#"nothing "{}/b.txt" grep-table "path" get"#,
+        root
+    ))
+    .unwrap();
+    assert_eq!(output, format!("{}/b.txt", root));
+}
+
+#[test]
+fn test_grep_table_skips_ignored_hidden_and_binary_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("target")).unwrap();
+    std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+    std::fs::write(dir.path().join("target/out.txt"), "needle\n").unwrap();
+    std::fs::write(dir.path().join(".hidden"), "needle\n").unwrap();
+    std::fs::write(dir.path().join("data.bin"), b"needle\0\x01").unwrap();
+    std::fs::write(dir.path().join("kept.txt"), "a needle\n").unwrap();
+
+    let output = eval(&format!(
+        r#"needle "{}" grep-table "path" get"#,
+        dir.path().display()
+    ))
+    .unwrap();
+    assert_eq!(output, format!("{}/kept.txt", dir.path().display()));
+}
+
+#[test]
+fn test_grep_table_bad_pattern_errors() {
+    let err = eval(r#""ma(" "/tmp" grep-table"#).unwrap_err();
+    assert!(err.to_string().contains("grep-table"), "{}", err);
+}
+
 #[test]
 fn test_open_json_file() {
     use std::fs::File;
@@ -1198,3 +1346,205 @@ fn test_value_type_names() {
     assert_eq!(Value::Nil.type_name(), "nil");
     assert_eq!(Value::Bytes(vec![]).type_name(), "bytes");
 }
+
+#[test]
+fn test_unique_records_structural() {
+    let output = eval(r#"'[{"a":1,"b":2},{"b":2,"a":1},{"a":3}]' from-json unique count"#).unwrap();
+    assert_eq!(output.trim(), "2");
+}
+
+#[test]
+fn test_duplicates_nested_lists() {
+    let output = eval("'[[1,2],[3],[1,2]]' from-json duplicates count").unwrap();
+    assert_eq!(output.trim(), "1");
+}
+
+#[test]
+fn test_freq_counts_most_frequent_first() {
+    let output = eval("[b a c a b a] freq to-json").unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"[{"value":"a","count":3},{"value":"b","count":2},{"value":"c","count":1}]"#
+    );
+    // Typed values stay typed, and the table works with where
+    let output = eval(r#"[1 2 2 "x"] freq #["count" get 1 gt?] where to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"[{"value":2,"count":2}]"#);
+}
+
+#[test]
+fn test_freq_takes_spread_items_and_lines() {
+    let output = eval(r#"keep-me "x\ny\ny" spread freq [value] select to-json"#).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"keep-me
+[{"value":"y"},{"value":"x"}]"#
+    );
+    let output = eval(r#""x\ny\ny" freq 1 first to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"[{"value":"y","count":2}]"#);
+    assert!(eval("42 freq").unwrap_err().contains("freq"));
+}
+
+#[test]
+fn test_record_literal_get() {
+    let output = eval(r#"{ name "x" age 3 } "age" get"#).unwrap();
+    assert_eq!(output.trim(), "3");
+}
+
+#[test]
+fn test_record_literal_nested() {
+    let output = eval(r#"{ a { b [1 2] } } to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"{"a":{"b":[1,2]}}"#);
+}
+
+#[test]
+fn test_record_literal_keys_stay_literal() {
+    let output = eval("{ count 5 } keys").unwrap();
+    assert!(output.contains("count"), "got: {}", output);
+}
+
+#[test]
+fn test_record_literal_empty() {
+    let output = eval("{ } typeof").unwrap();
+    assert_eq!(output.trim(), "record");
+}
+
+#[test]
+fn test_record_literal_odd_pairs_error() {
+    assert!(eval("{ a 1 b }").is_err());
+}
+
+#[test]
+fn test_get_or_falls_back_for_missing_key() {
+    let output = eval(r#"{ name "alice" } "nick" "anon" get-or"#).unwrap();
+    assert_eq!(output.trim(), "anon");
+    let output = eval(r#"{ name "alice" } "name" "anon" get-or"#).unwrap();
+    assert_eq!(output.trim(), "alice");
+    // A nil target (missing parent) falls back too
+    let output = eval(r#"{ name "alice" } "profile" get "nick" "anon" get-or"#).unwrap();
+    assert_eq!(output.trim(), "anon");
+}
+
+#[test]
+fn test_get_checked_sets_exit_code() {
+    assert_eq!(eval_exit_code(r#"{ name "alice" } "name" get?"#), 0);
+    assert_eq!(eval_exit_code(r#"{ name "alice" } "nick" get?"#), 1);
+    let output = eval(r#"{ name "alice" } "nick" get? typeof"#).unwrap();
+    assert_eq!(output.trim(), "nil");
+}
+
+#[test]
+fn test_nil_passes_through_string_ops() {
+    for op in [
+        "len",
+        r#"1 2 slice"#,
+        r#""l" indexof"#,
+        r#""l" "L" str-replace"#,
+        r#""!" suffix"#,
+        r#""profile" get"#,
+    ] {
+        let output = eval(&format!(r#"{{ a 1 }} "b" get {} typeof"#, op)).unwrap();
+        assert_eq!(output.trim(), "nil", "{}", op);
+    }
+    let output = eval(r#"{ a 1 } "b" get "." split1 depth"#).unwrap();
+    assert!(output.ends_with('2'), "{}", output);
+    assert_eq!(eval_exit_code(r#"{ a 1 } "b" get "x" contains?"#), 1);
+    // Real strings are unaffected
+    let output = eval(r#""hello" len"#).unwrap();
+    assert_eq!(output.trim(), "5");
+}
+
+#[test]
+fn test_columns_splits_aligned_output() {
+    // A header with a space, right-aligned numbers, columns packed with
+    // single spaces, and a last column with spaces in it
+    let ps = "CONTAINER ID     SIZE   VSZ   RSS TTY COMMAND\n\
+              abc123def456        4     0     0 ?   [kthreadd]\n\
+              0123456789ab      120 24864 10072 ?   /sbin/init splash";
+    let output = eval(&format!(r#""{}" columns to-json"#, ps)).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"[{"CONTAINER ID":"abc123def456","SIZE":4,"VSZ":0,"RSS":0,"TTY":"?","COMMAND":"[kthreadd]"},{"CONTAINER ID":"0123456789ab","SIZE":120,"VSZ":24864,"RSS":10072,"TTY":"?","COMMAND":"/sbin/init splash"}]"#
+    );
+
+    // A header word with nothing under it belongs to the one before it
+    let df = "Filesystem  Use% Mounted on\n/dev/vda     53% /\ntmpfs         0% /dev/shm";
+    let output = eval(&format!(r#""{}" columns "Mounted on" get to-json"#, df)).unwrap();
+    assert_eq!(output.trim(), r#"["/","/dev/shm"]"#);
+}
+
+#[test]
+fn test_columns_with_a_spec() {
+    // Names: no header, split on whitespace, the last taking the rest
+    let output = eval(r#""1 init\n  2   sshd -D\n3" ["pid" "cmd"] columns to-json"#).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"[{"pid":1,"cmd":"init"},{"pid":2,"cmd":"sshd -D"},{"pid":3,"cmd":null}]"#
+    );
+
+    // Widths: cut at fixed places
+    let output = eval(r#""NAME AGE\nweb1 10 days\ndb-2 3" [5 3] columns to-json"#).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"[{"NAME":"web1","AGE":"10 days"},{"NAME":"db-2","AGE":3}]"#
+    );
+
+    assert!(eval(r#""a b" [1 "x"] columns"#).is_err());
+}
+
+#[test]
+fn test_ini_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.ini");
+    std::fs::write(
+        &path,
+        "; app settings\nname = app\n\n[database]\nport: 5432\nhost = \" db \"\n# old\n[cache]\nenabled\n",
+    )
+    .unwrap();
+    let path = path.display();
+
+    let output = eval(&format!(r#""{path}" open to-json"#)).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"{"name":"app","database":{"port":"5432","host":" db "},"cache":{"enabled":""}}"#
+    );
+    let output = eval(&format!(r#""{path}" open to-ini"#)).unwrap();
+    assert_eq!(
+        output.trim_end(),
+        "name = app\n\n[database]\nport = 5432\nhost = \" db \"\n\n[cache]\nenabled ="
+    );
+    assert!(eval(r#""[open" from-ini"#).is_err());
+}
+
+#[test]
+fn test_env_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(".env");
+    std::fs::write(
+        &path,
+        "export API_URL=https://example.com\n# comment\nDEBUG=1 # on\nGREETING=\"hello\\n\\\"world\\\"\"\nRAW='a $b'\n",
+    )
+    .unwrap();
+    let path = path.display();
+
+    let output = eval(&format!(r#""{path}" open to-json"#)).unwrap();
+    assert_eq!(
+        output.trim(),
+        r#"{"API_URL":"https://example.com","DEBUG":"1","GREETING":"hello\n\"world\"","RAW":"a $b"}"#
+    );
+    eval(&format!(r#""{path}" open "DEBUG" "0" set "{path}" save"#)).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join(".env")).unwrap(),
+        "API_URL=https://example.com\nDEBUG=0\nGREETING=\"hello\\n\\\"world\\\"\"\nRAW=\"a \\$b\"\n"
+    );
+}
+
+#[test]
+fn test_from_passwd_and_group() {
+    let passwd = "root:x:0:0:root:/root:/bin/bash\\nnobody:x:65534:65534:nobody:/nonexistent:/usr/sbin/nologin";
+    let output = eval(&format!(r#""{}" from-passwd "uid" get to-json"#, passwd)).unwrap();
+    assert_eq!(output.trim(), "[0,65534]");
+    let output =
+        eval(r#""sudo:x:27:alice,bob\nusers:x:100:" from-passwd "members" get to-json"#).unwrap();
+    assert_eq!(output.trim(), r#"[["alice","bob"],[]]"#);
+    assert!(eval(r#""a:b:c" from-passwd"#).is_err());
+}
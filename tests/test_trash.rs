@@ -0,0 +1,92 @@
+//! Tests for the trash builtins
+//!
+//! The trash location comes from the environment, which every test in this
+//! file shares, so it is set once and the steps run in one test.
+
+use std::fs;
+
+mod common;
+use common::{eval, lex, parse, Evaluator};
+
+fn run(evaluator: &mut Evaluator, source: &str) -> String {
+    evaluator.clear_stack();
+    let program = parse(lex(source).unwrap()).unwrap();
+    evaluator.eval(&program).unwrap().output
+}
+
+#[test]
+fn test_trash_list_and_restore() {
+    let home = tempfile::tempdir().unwrap();
+    let work = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+    std::env::set_var("XDG_DATA_HOME", home.path().join("data"));
+    let trash = if cfg!(any(target_os = "macos", windows)) {
+        home.path().join(".hsab/trash")
+    } else {
+        home.path().join("data/Trash")
+    };
+
+    // Trash a file and a directory, and a second file of the same name
+    let file = work.path().join("notes 1.txt");
+    fs::write(&file, "first").unwrap();
+    let trashed = eval(&format!(r#""{}" trash"#, file.display())).unwrap();
+    assert_eq!(
+        trashed,
+        trash.join("files/notes 1.txt").display().to_string()
+    );
+    assert!(!file.exists());
+    let info = fs::read_to_string(trash.join("info/notes 1.txt.trashinfo")).unwrap();
+    assert!(info.starts_with("[Trash Info]\nPath="), "{}", info);
+    assert!(info.contains("notes%201.txt\n"), "{}", info);
+
+    fs::write(&file, "second").unwrap();
+    eval(&format!(r#""{}" trash"#, file.display())).unwrap();
+    assert!(trash.join("files/notes 1.txt.2").exists());
+
+    let dir = work.path().join("dir");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("inner"), "x").unwrap();
+    eval(&format!(r#""{}" trash"#, dir.display())).unwrap();
+    assert!(trash.join("files/dir/inner").exists());
+    assert!(eval(&format!(r#""{}" trash"#, dir.display()))
+        .unwrap()
+        .is_empty());
+
+    let listed = eval("trash-list").unwrap();
+    assert_eq!(eval("trash-list count").unwrap(), "3");
+    assert!(listed.contains("notes 1.txt.2\t"), "{}", listed);
+
+    // By original path: the latest deletion comes back, and nothing is
+    // overwritten
+    let restored = eval(&format!(r#""{}" trash-restore"#, file.display())).unwrap();
+    assert_eq!(restored, file.display().to_string());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+    assert!(eval(r#""notes 1.txt" trash-restore"#).unwrap().is_empty());
+
+    // By name
+    fs::remove_file(&file).unwrap();
+    eval(r#""notes 1.txt" trash-restore"#).unwrap();
+    assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+    eval(r#""dir" trash-restore"#).unwrap();
+    assert!(dir.join("inner").exists());
+    assert_eq!(eval("trash-list count").unwrap(), "0");
+
+    // rm-trash only applies to the interactive shell
+    let mut evaluator = Evaluator::new();
+    run(&mut evaluator, r#""on" rm-trash"#);
+    run(&mut evaluator, &format!(r#""{}" rm"#, file.display()));
+    assert_eq!(eval("trash-list count").unwrap(), "0");
+
+    fs::write(&file, "third").unwrap();
+    evaluator.set_interactive(true);
+    assert_eq!(
+        run(&mut evaluator, &format!(r#""{}" rm-r"#, dir.display())),
+        "1"
+    );
+    run(
+        &mut evaluator,
+        &format!(r#""{}/*.txt" rm"#, work.path().display()),
+    );
+    assert!(!file.exists() && !dir.exists());
+    assert_eq!(eval("trash-list count").unwrap(), "2");
+}
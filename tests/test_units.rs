@@ -0,0 +1,113 @@
+//! Tests for durations and sizes written in human units
+
+#[path = "common/mod.rs"]
+mod common;
+use common::{eval, eval_exit_code};
+use std::time::{Duration, Instant};
+
+// === duration ===
+
+#[test]
+fn test_duration_gives_seconds() {
+    assert_eq!(eval(r#""5m" duration"#).unwrap(), "300");
+    assert_eq!(eval(r#""1h30m" duration"#).unwrap(), "5400");
+    assert_eq!(eval(r#""250ms" duration"#).unwrap(), "0.25");
+    assert_eq!(eval(r#""2d" duration"#).unwrap(), "172800");
+    assert_eq!(eval(r#""1.5s" duration"#).unwrap(), "1.5");
+    assert_eq!(eval("90 duration").unwrap(), "90");
+}
+
+#[test]
+fn test_duration_rejects_unknown_units() {
+    let err = eval(r#""5 fortnights" duration"#).unwrap_err();
+    assert!(err.contains("duration"), "{}", err);
+    assert!(eval(r#""1h30" duration"#).is_err());
+    assert!(eval(r#""m" duration"#).is_err());
+}
+
+#[test]
+fn test_durations_are_finite_and_not_negative() {
+    for text in ["NaN", "inf", "-inf", "-5", "-1m"] {
+        assert!(
+            eval(&format!(r#""{}" duration"#, text)).is_err(),
+            "{}",
+            text
+        );
+    }
+    assert!(eval("-5 duration").is_err());
+    assert!(eval(r#""nan" delay"#).is_err());
+    assert!(eval(r#""-100" delay"#).is_err());
+    assert!(eval(r#"#[1] "units-test-negative" 2 -60 rate-limit"#).is_err());
+    assert_eq!(eval(r#""0" duration"#).unwrap(), "0");
+}
+
+// === size-bytes ===
+
+#[test]
+fn test_size_bytes_binary_and_decimal() {
+    assert_eq!(eval(r#""512" size-bytes"#).unwrap(), "512");
+    assert_eq!(eval(r#""10K" size-bytes"#).unwrap(), "10240");
+    assert_eq!(eval(r#""10KiB" size-bytes"#).unwrap(), "10240");
+    assert_eq!(eval(r#""10KB" size-bytes"#).unwrap(), "10000");
+    assert_eq!(eval(r#""1.5G" size-bytes"#).unwrap(), "1610612736");
+    assert_eq!(eval(r#""1.5GB" size-bytes"#).unwrap(), "1500000000");
+    assert_eq!(eval(r#""2 Mi" size-bytes"#).unwrap(), "2097152");
+    assert_eq!(eval(r#""3B" size-bytes"#).unwrap(), "3");
+}
+
+#[test]
+fn test_size_bytes_rejects_unknown_units() {
+    let err = eval(r#""5X" size-bytes"#).unwrap_err();
+    assert!(err.contains("size"), "{}", err);
+    assert!(eval(r#""5i" size-bytes"#).is_err());
+}
+
+// === Builtins taking human units ===
+
+#[test]
+fn test_sleep_and_delay_take_units() {
+    let start = Instant::now();
+    assert_eq!(eval(r#""150ms" sleep "done""#).unwrap(), "done");
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    // delay's bare numbers stay milliseconds
+    let start = Instant::now();
+    assert_eq!(eval_exit_code(r#""0.1s" delay"#), 0);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(eval("soon delay").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_takes_units() {
+    let start = Instant::now();
+    assert_eq!(eval_exit_code(r#""300ms" #[-c "sleep 5" sh] timeout"#), 124);
+    assert!(start.elapsed() < Duration::from_secs(4));
+    assert_eq!(eval_exit_code(r#""1m" #[true] timeout"#), 0);
+}
+
+#[test]
+fn test_retry_delay_takes_units() {
+    let start = Instant::now();
+    assert!(eval(r#"#[false] 2 "0.1s" retry-delay"#).is_err());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn test_rate_limit_takes_units() {
+    assert_eq!(
+        eval(r#"#["ran"] "units-test" 2 "1m" rate-limit"#).unwrap(),
+        "ran"
+    );
+    assert!(eval(r#"#[1] "units-test" 2 "soon" rate-limit"#).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_min_free_reads_decimal_sizes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().display();
+    let code = |size: &str| eval_exit_code(&format!(r#""{}" "{}" min-free?"#, path, size));
+    assert_eq!(code("1KB"), 0);
+    assert_eq!(code("1000PB"), 1);
+}